@@ -242,7 +242,7 @@ pub fn search_objects(
     let lim = limit.unwrap_or(10) as usize;
     match &mut *backend {
         DbBackend::Embedded { db, .. } => db
-            .search(bucket.as_deref(), &query, lim)
+            .search(bucket.as_deref(), &query, 0, lim)
             .map_err(|e| e.to_string()),
         DbBackend::Client { stream, host, port } => {
             let mut req = json!({"cmd": "search", "query": query, "limit": lim});
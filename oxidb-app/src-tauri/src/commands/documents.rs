@@ -42,6 +42,8 @@ pub fn find_documents(
                 }),
                 skip: params.skip,
                 limit: params.limit,
+                hint: None,
+                respect_ttl: false,
             };
             db.find_with_options(&params.collection, &query, &opts)
                 .map_err(|e| e.to_string())
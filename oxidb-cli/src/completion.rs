@@ -0,0 +1,203 @@
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+use serde_json::Value;
+
+/// Commands that stand alone.
+const COMMANDS: &[&str] = &["db.", "show collections", "show buckets", "show metrics", "describe ", "ping", "help", "exit", "quit"];
+
+/// `db.<method>(...)` commands.
+const DB_METHODS: &[&str] = &[
+    "createCollection(",
+    "createBucket(",
+    "deleteBucket(",
+    "beginTransaction()",
+    "commitTransaction()",
+    "rollbackTransaction()",
+    "search(",
+];
+
+/// `db.<collection>.<method>(...)` commands.
+const COLLECTION_METHODS: &[&str] = &[
+    "insert(",
+    "insertMany(",
+    "find(",
+    "findOne(",
+    "update(",
+    "updateOne(",
+    "findAndModify(",
+    "delete(",
+    "deleteOne(",
+    "bulkWrite(",
+    "count(",
+    "distinct(",
+    "aggregate(",
+    "createIndex(",
+    "createUniqueIndex(",
+    "createCompositeIndex(",
+    "createTextIndex(",
+    "createGeoIndex(",
+    "setSchema(",
+    "getSchema()",
+    "setDefaults(",
+    "getDefaults()",
+    "textSearch(",
+    "compact()",
+    "drop()",
+];
+
+/// Modifiers chained after `find(...)`.
+const CURSOR_METHODS: &[&str] = &["sort(", "limit(", "skip("];
+
+/// Most field names remembered from results.
+const MAX_FIELDS: usize = 200;
+
+/// REPL tab completion of commands, collection names, and field names seen
+/// in recent results.
+#[derive(Default)]
+pub struct ReplHelper {
+    collections: Vec<String>,
+    fields: Vec<String>,
+}
+
+impl ReplHelper {
+    /// Replace the known collection names with a `list_collections` result.
+    pub fn set_collections(&mut self, data: &Value) {
+        if let Some(names) = data.as_array() {
+            self.collections = names.iter().filter_map(|n| n.as_str().map(String::from)).collect();
+        }
+    }
+
+    /// Remember the field names of the documents in a command's result.
+    pub fn observe(&mut self, data: &Value) {
+        let docs: Vec<&Value> = match data {
+            Value::Array(items) => items.iter().collect(),
+            doc => vec![doc],
+        };
+        for doc in docs {
+            for key in doc.as_object().into_iter().flat_map(|obj| obj.keys()) {
+                if !self.fields.contains(key) && self.fields.len() < MAX_FIELDS {
+                    self.fields.push(key.clone());
+                }
+            }
+        }
+    }
+
+    /// Completions for the text before the cursor: the byte offset the
+    /// completed word starts at, and the candidates to replace it with.
+    pub fn candidates(&self, line: &str) -> (usize, Vec<String>) {
+        let word_start = line
+            .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+            .map_or(0, |i| i + 1);
+        let word = &line[word_start..];
+        let before = &line[..word_start];
+
+        // A field name being typed as a JSON key
+        if before.contains('(') {
+            let candidates = if before.ends_with('"') {
+                matching(self.fields.iter().map(String::as_str), word)
+            } else if before.ends_with(").") {
+                matching(CURSOR_METHODS.iter().copied(), word)
+            } else {
+                Vec::new()
+            };
+            return (word_start, candidates);
+        }
+
+        if let Some(col) = line.strip_prefix("describe ") {
+            return (line.len() - col.len(), matching(self.collections.iter().map(String::as_str), col));
+        }
+
+        let Some(path) = line.strip_prefix("db.") else {
+            return (0, matching(COMMANDS.iter().copied(), line));
+        };
+        match path.split_once('.') {
+            // db.<collection>.<method>
+            Some((_, method)) => (line.len() - method.len(), matching(COLLECTION_METHODS.iter().copied(), method)),
+            // db.<method> or db.<collection>
+            None => {
+                let collections = self.collections.iter().map(|c| format!("{c}."));
+                let mut candidates = matching(DB_METHODS.iter().copied(), path);
+                candidates.extend(collections.filter(|c| c.starts_with(path)));
+                (3, candidates)
+            }
+        }
+    }
+}
+
+fn matching<'a>(options: impl Iterator<Item = &'a str>, prefix: &str) -> Vec<String> {
+    options.filter(|o| o.starts_with(prefix)).map(String::from).collect()
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(&line[..pos]))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn helper() -> ReplHelper {
+        let mut h = ReplHelper::default();
+        h.set_collections(&json!(["users", "orders", "uploads"]));
+        h.observe(&json!([{"_id": 1, "name": "Alice", "nickname": "Al"}, {"_id": 2, "age": 30}]));
+        h
+    }
+
+    #[test]
+    fn test_complete_commands() {
+        let h = helper();
+        assert_eq!(h.candidates("sh"), (0, vec!["show collections".into(), "show buckets".into(), "show metrics".into()]));
+        assert_eq!(h.candidates("pi"), (0, vec!["ping".into()]));
+        assert_eq!(h.candidates("d").1, vec!["db.".to_string(), "describe ".to_string()]);
+        assert_eq!(h.candidates("describe o"), (9, vec!["orders".into()]));
+    }
+
+    #[test]
+    fn test_complete_collections_and_db_methods() {
+        let h = helper();
+        assert_eq!(h.candidates("db.u"), (3, vec!["users.".into(), "uploads.".into()]));
+        let (start, all) = h.candidates("db.");
+        assert_eq!(start, 3);
+        assert!(all.contains(&"createCollection(".to_string()));
+        assert!(all.contains(&"orders.".to_string()));
+        assert_eq!(h.candidates("db.beg").1, vec!["beginTransaction()".to_string()]);
+    }
+
+    #[test]
+    fn test_complete_collection_methods() {
+        let h = helper();
+        let (start, found) = h.candidates("db.users.fi");
+        assert_eq!(start, 9);
+        assert_eq!(found, vec!["find(".to_string(), "findOne(".to_string(), "findAndModify(".to_string()]);
+        assert!(h.candidates("db.users.").1.contains(&"aggregate(".to_string()));
+        assert_eq!(h.candidates("db.users.find({}).li"), (18, vec!["limit(".into()]));
+    }
+
+    #[test]
+    fn test_complete_fields() {
+        let h = helper();
+        let line = r#"db.users.find({"n"#;
+        assert_eq!(h.candidates(line), (line.len() - 1, vec!["name".into(), "nickname".into()]));
+        assert_eq!(h.candidates(r#"db.users.find({"name": "Alice", "a"#).1, vec!["age".to_string()]);
+        // Not inside a key
+        assert!(h.candidates("db.users.find({x").1.is_empty());
+    }
+}
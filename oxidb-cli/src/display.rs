@@ -1,10 +1,25 @@
 use colored::Colorize;
-use serde_json::Value;
+use serde_json::{Map, Value};
+
+/// How command results are printed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Pretty, colorized JSON
+    Json,
+    /// The raw response as one line of JSON, for piping
+    Raw,
+    /// Comma-separated values, one row per document
+    Csv,
+    /// An aligned ASCII table, one row per document
+    Table,
+}
 
 /// Format a response Value for display to the user.
-/// If `raw_json` is true, output unformatted JSON suitable for piping.
-pub fn format_response(response: &Value, raw_json: bool) -> String {
-    if raw_json {
+///
+/// `Csv` and `Table` render documents (e.g. find and aggregate results) as
+/// rows; other results are shown as with `Json`.
+pub fn format_response(response: &Value, format: OutputFormat) -> String {
+    if format == OutputFormat::Raw {
         return response.to_string();
     }
 
@@ -23,7 +38,115 @@ pub fn format_response(response: &Value, raw_json: bool) -> String {
         None => return "ok".green().to_string(),
     };
 
-    format_value(data)
+    match (format, rows(data)) {
+        (OutputFormat::Csv, Some(rows)) => format_csv(&rows),
+        (OutputFormat::Table, Some(rows)) => format_table(&rows),
+        _ => format_value(data),
+    }
+}
+
+/// The documents in a result, if it is a document or a list of them.
+fn rows(data: &Value) -> Option<Vec<&Map<String, Value>>> {
+    match data {
+        Value::Object(doc) => Some(vec![doc]),
+        Value::Array(items) => items.iter().map(|item| item.as_object()).collect(),
+        _ => None,
+    }
+}
+
+/// Column names: every top-level key, in the order first seen.
+fn columns<'a>(rows: &[&'a Map<String, Value>]) -> Vec<&'a str> {
+    let mut columns: Vec<&str> = Vec::new();
+    for row in rows {
+        for key in row.keys() {
+            if !columns.contains(&key.as_str()) {
+                columns.push(key);
+            }
+        }
+    }
+    columns
+}
+
+/// Cell text for a field: scalars as themselves, missing fields empty, and
+/// nested objects and arrays as JSON.
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Render documents as CSV with a header row.
+pub fn format_csv(rows: &[&Map<String, Value>]) -> String {
+    let columns = columns(rows);
+    let mut lines = Vec::with_capacity(rows.len() + 1);
+    lines.push(columns.iter().map(|c| csv_field(c)).collect::<Vec<_>>().join(","));
+    for row in rows {
+        let fields: Vec<String> = columns.iter().map(|c| csv_field(&cell(row.get(*c)))).collect();
+        lines.push(fields.join(","));
+    }
+    lines.join("\n")
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or line break.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Render documents as an ASCII table. Numbers are right-aligned, everything
+/// else left-aligned.
+pub fn format_table(rows: &[&Map<String, Value>]) -> String {
+    if rows.is_empty() {
+        return "(empty)".dimmed().to_string();
+    }
+    let columns = columns(rows);
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| columns.iter().map(|c| cell(row.get(*c))).collect())
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            cells
+                .iter()
+                .map(|r| r[i].chars().count())
+                .chain([c.chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let border = format!(
+        "+{}+",
+        widths.iter().map(|w| "-".repeat(w + 2)).collect::<Vec<_>>().join("+")
+    );
+    let line = |values: &[String], right: &[bool]| {
+        let padded: Vec<String> = values
+            .iter()
+            .zip(&widths)
+            .zip(right)
+            .map(|((v, w), right)| {
+                let pad = " ".repeat(w - v.chars().count());
+                if *right { format!(" {pad}{v} ") } else { format!(" {v}{pad} ") }
+            })
+            .collect();
+        format!("|{}|", padded.join("|"))
+    };
+
+    let header: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+    let mut lines = vec![border.clone(), line(&header, &vec![false; columns.len()]), border.clone()];
+    for (row, values) in rows.iter().zip(&cells) {
+        let right: Vec<bool> = columns.iter().map(|c| row.get(*c).is_some_and(Value::is_number)).collect();
+        lines.push(line(values, &right));
+    }
+    lines.push(border);
+    lines.join("\n")
 }
 
 fn format_value(value: &Value) -> String {
@@ -205,15 +328,23 @@ Collection commands:
   db.<col>.findOne({...})               Find one document
   db.<col>.update({q}, {u})             Update documents
   db.<col>.updateOne({q}, {u})          Update one document
+  db.<col>.findAndModify({...})         Update or remove one document, return it
   db.<col>.delete({...})                Delete documents
   db.<col>.deleteOne({...})             Delete one document
+  db.<col>.bulkWrite([{op, ...}, ...])  Apply inserts/updates/deletes atomically
   db.<col>.count()                      Count documents
   db.<col>.count({...})                 Count matching documents
+  db.<col>.distinct("field", {...})     Unique values of a field
   db.<col>.aggregate([...])             Aggregation pipeline
   db.<col>.createIndex("field")         Create index
   db.<col>.createUniqueIndex("field")   Create unique index
   db.<col>.createCompositeIndex([...])  Create composite index
   db.<col>.createTextIndex([...])       Create text index
+  db.<col>.createGeoIndex("field")      Create geo index
+  db.<col>.setSchema({...}, {opts})     Validate writes against a JSON Schema
+  db.<col>.getSchema()                  Show the collection's schema
+  db.<col>.setDefaults({...})           Defaults for omitted fields on insert
+  db.<col>.getDefaults()                Show the collection's defaults
   db.<col>.textSearch("query", N)       Full-text search
   db.<col>.compact()                    Compact collection
   db.<col>.drop()                       Drop collection
@@ -221,6 +352,8 @@ Collection commands:
 Database commands:
   show collections                      List all collections
   show buckets                          List all buckets
+  show metrics                          Command counters (Prometheus format)
+  describe <col>                        Count, field types and indexes
   db.createCollection("name")           Create a collection
   db.createBucket("name")               Create a bucket
   db.deleteBucket("name")               Delete a bucket
@@ -238,3 +371,56 @@ Other:
 "#;
     println!("{}", help.trim());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn docs(value: &Value) -> Vec<&Map<String, Value>> {
+        rows(value).unwrap()
+    }
+
+    #[test]
+    fn test_csv_union_of_keys() {
+        let data = json!([
+            {"_id": 1, "name": "Alice", "tags": ["a", "b"]},
+            {"_id": 2, "name": "Smith, Bob", "city": "NYC"},
+            {"_id": 3, "address": {"zip": "10001"}, "note": "say \"hi\""}
+        ]);
+        let csv = format_csv(&docs(&data));
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "_id,name,tags,city,address,note");
+        assert_eq!(lines[1], r#"1,Alice,"[""a"",""b""]",,,"#);
+        assert_eq!(lines[2], r#"2,"Smith, Bob",,NYC,,"#);
+        assert_eq!(lines[3], r#"3,,,,"{""zip"":""10001""}","say ""hi""""#);
+    }
+
+    #[test]
+    fn test_table_alignment() {
+        let data = json!([
+            {"age": 7, "name": "Al"},
+            {"age": 1234, "name": "Beatrice", "ok": true}
+        ]);
+        let expected = [
+            "+------+----------+------+",
+            "| age  | name     | ok   |",
+            "+------+----------+------+",
+            "|    7 | Al       |      |",
+            "| 1234 | Beatrice | true |",
+            "+------+----------+------+",
+        ];
+        assert_eq!(format_table(&docs(&data)), expected.join("\n"));
+    }
+
+    #[test]
+    fn test_only_documents_become_rows() {
+        colored::control::set_override(false);
+        let response = json!({"ok": true, "data": {"count": 3}});
+        assert_eq!(format_response(&response, OutputFormat::Csv), "count\n3");
+        let response = json!({"ok": true, "data": 3});
+        assert_eq!(format_response(&response, OutputFormat::Table), "3");
+        let response = json!({"ok": true, "data": [1, 2]});
+        assert_eq!(format_response(&response, OutputFormat::Csv), "1\n2");
+    }
+}
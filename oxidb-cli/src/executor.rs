@@ -6,7 +6,7 @@ use std::sync::Arc;
 
 use base64::Engine;
 use oxidb::OxiDb;
-use oxidb::query::parse_find_options;
+use oxidb::query::{parse_find_and_modify_options, parse_find_options};
 use serde_json::{Value, json};
 
 pub trait CommandExecutor {
@@ -54,6 +54,8 @@ impl CommandExecutor for EmbeddedExecutor {
         match cmd.as_str() {
             "ping" => ok_val(json!("pong")),
 
+            "metrics" => ok_val(json!(self.db.metrics_text())),
+
             // --- Transactions ---
             "begin_tx" => {
                 if self.active_tx.is_some() {
@@ -201,6 +203,22 @@ impl CommandExecutor for EmbeddedExecutor {
                     Err(e) => err_val(&e.to_string()),
                 }
             }
+            "find_and_modify" => {
+                let col = match collection.as_deref() {
+                    Some(c) => c,
+                    None => return err_val("missing 'collection'"),
+                };
+                let empty = json!({});
+                let query = request.get("query").unwrap_or(&empty);
+                let opts = match parse_find_and_modify_options(&request) {
+                    Ok(o) => o,
+                    Err(e) => return err_val(&e.to_string()),
+                };
+                match self.db.find_and_modify(col, query, request.get("update"), &opts) {
+                    Ok(doc) => ok_val(json!(doc)),
+                    Err(e) => err_val(&e.to_string()),
+                }
+            }
             "delete" => {
                 let col = match collection.as_deref() {
                     Some(c) => c,
@@ -236,6 +254,21 @@ impl CommandExecutor for EmbeddedExecutor {
                     Err(e) => err_val(&e.to_string()),
                 }
             }
+            "bulk_write" => {
+                let col = match collection.as_deref() {
+                    Some(c) => c,
+                    None => return err_val("missing 'collection'"),
+                };
+                let ops = match request.get("ops").map(oxidb::bulk::parse_bulk_ops) {
+                    Some(Ok(ops)) => ops,
+                    Some(Err(e)) => return err_val(&e.to_string()),
+                    None => return err_val("missing 'ops'"),
+                };
+                match self.db.bulk_write(col, ops) {
+                    Ok(results) => ok_val(json!(results.iter().map(|r| r.to_json()).collect::<Vec<_>>())),
+                    Err(e) => err_val(&e.to_string()),
+                }
+            }
             "count" => {
                 let col = match collection.as_deref() {
                     Some(c) => c,
@@ -248,6 +281,22 @@ impl CommandExecutor for EmbeddedExecutor {
                     Err(e) => err_val(&e.to_string()),
                 }
             }
+            "distinct" => {
+                let col = match collection.as_deref() {
+                    Some(c) => c,
+                    None => return err_val("missing 'collection'"),
+                };
+                let field = match request.get("field").and_then(|v| v.as_str()) {
+                    Some(f) => f,
+                    None => return err_val("missing 'field'"),
+                };
+                let empty = json!({});
+                let query = request.get("query").unwrap_or(&empty);
+                match self.db.distinct(col, field, query) {
+                    Ok(values) => ok_val(json!(values)),
+                    Err(e) => err_val(&e.to_string()),
+                }
+            }
 
             // --- Indexes ---
             "create_index" => {
@@ -320,6 +369,20 @@ impl CommandExecutor for EmbeddedExecutor {
                     Err(e) => err_val(&e.to_string()),
                 }
             }
+            "create_geo_index" => {
+                let col = match collection.as_deref() {
+                    Some(c) => c,
+                    None => return err_val("missing 'collection'"),
+                };
+                let field = match request.get("field").and_then(|v| v.as_str()) {
+                    Some(f) => f,
+                    None => return err_val("missing 'field'"),
+                };
+                match self.db.create_geo_index(col, field) {
+                    Ok(()) => ok_val(json!("geo index created")),
+                    Err(e) => err_val(&e.to_string()),
+                }
+            }
             "list_indexes" => {
                 let col = match collection.as_deref() {
                     Some(c) => c,
@@ -330,6 +393,84 @@ impl CommandExecutor for EmbeddedExecutor {
                     Err(e) => err_val(&e.to_string()),
                 }
             }
+            "set_schema" => {
+                let col = match collection.as_deref() {
+                    Some(c) => c,
+                    None => return err_val("missing 'collection'"),
+                };
+                let schema = match request.get("schema").cloned() {
+                    Some(Value::Null) => None,
+                    Some(schema) => {
+                        let level = match request.get("level").and_then(|v| v.as_str()) {
+                            Some(level) => match oxidb::ValidationLevel::parse(level) {
+                                Ok(level) => level,
+                                Err(e) => return err_val(&e.to_string()),
+                            },
+                            None => oxidb::ValidationLevel::Strict,
+                        };
+                        let coerce = request.get("coerce").and_then(|v| v.as_bool()).unwrap_or(false);
+                        match oxidb::CollectionSchema::new(schema, level, coerce) {
+                            Ok(schema) => Some(schema),
+                            Err(e) => return err_val(&e.to_string()),
+                        }
+                    }
+                    None => return err_val("missing 'schema'"),
+                };
+                let msg = if schema.is_some() { "schema set" } else { "schema removed" };
+                match self.db.set_collection_schema(col, schema) {
+                    Ok(()) => ok_val(json!(msg)),
+                    Err(e) => err_val(&e.to_string()),
+                }
+            }
+            "set_defaults" => {
+                let col = match collection.as_deref() {
+                    Some(c) => c,
+                    None => return err_val("missing 'collection'"),
+                };
+                let defaults = match request.get("defaults").cloned() {
+                    Some(Value::Null) => Ok(oxidb::FieldDefaults::default()),
+                    Some(spec) => oxidb::FieldDefaults::new(spec),
+                    None => return err_val("missing 'defaults'"),
+                };
+                match defaults.and_then(|defaults| self.db.set_collection_defaults(col, defaults)) {
+                    Ok(()) => ok_val(json!("defaults set")),
+                    Err(e) => err_val(&e.to_string()),
+                }
+            }
+            "get_defaults" => {
+                let col = match collection.as_deref() {
+                    Some(c) => c,
+                    None => return err_val("missing 'collection'"),
+                };
+                match self.db.get_collection_defaults(col) {
+                    Ok(defaults) => ok_val(json!(defaults)),
+                    Err(e) => err_val(&e.to_string()),
+                }
+            }
+            "get_schema" => {
+                let col = match collection.as_deref() {
+                    Some(c) => c,
+                    None => return err_val("missing 'collection'"),
+                };
+                match self.db.get_collection_schema(col) {
+                    Ok(schema) => ok_val(json!(schema)),
+                    Err(e) => err_val(&e.to_string()),
+                }
+            }
+            "describe" => {
+                let col = match collection.as_deref() {
+                    Some(c) => c,
+                    None => return err_val("missing 'collection'"),
+                };
+                let sample = request
+                    .get("sample")
+                    .and_then(|v| v.as_u64())
+                    .map_or(oxidb::DEFAULT_DESCRIBE_SAMPLE, |n| n as usize);
+                match self.db.describe(col, sample) {
+                    Ok(desc) => ok_val(json!(desc)),
+                    Err(e) => err_val(&e.to_string()),
+                }
+            }
             "drop_index" => {
                 let col = match collection.as_deref() {
                     Some(c) => c,
@@ -547,7 +688,11 @@ impl CommandExecutor for EmbeddedExecutor {
                     .get("limit")
                     .and_then(|v| v.as_u64())
                     .unwrap_or(10) as usize;
-                match self.db.search(bucket, query, limit) {
+                let offset = request
+                    .get("offset")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(0) as usize;
+                match self.db.search(bucket, query, offset, limit) {
                     Ok(results) => ok_val(json!(results)),
                     Err(e) => err_val(&e.to_string()),
                 }
@@ -1,12 +1,20 @@
+mod completion;
 mod display;
 mod executor;
 mod parser;
+mod transfer;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use rustyline::history::FileHistory;
+use rustyline::{Editor, Helper};
+use serde_json::json;
 
+use completion::ReplHelper;
+use display::OutputFormat;
 use executor::{ClientExecutor, CommandExecutor, EmbeddedExecutor};
+use transfer::FileFormat;
 
 #[derive(Parser)]
 #[command(name = "oxidb", about = "OxiDB interactive shell and CLI")]
@@ -27,13 +35,65 @@ struct Cli {
     #[arg(long)]
     eval: Option<String>,
 
-    /// Output raw JSON (no pretty-printing)
+    /// Output format for results
+    #[arg(long, value_enum, default_value_t = OutputFormat::Json)]
+    format: OutputFormat,
+
+    /// Output raw JSON (no pretty-printing); same as --format raw
     #[arg(long)]
     json: bool,
 
     /// Encryption key file path (embedded mode)
     #[arg(long)]
     encryption_key: Option<PathBuf>,
+
+    /// Don't load or save the shell history file (~/.oxidb_history, or
+    /// $OXIDB_HISTORY)
+    #[arg(long)]
+    no_history: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Insert documents from a JSON Lines (or JSON array) file
+    Import {
+        /// Collection to insert into
+        #[arg(long)]
+        collection: String,
+
+        /// File to read
+        #[arg(long)]
+        file: PathBuf,
+
+        /// File layout
+        #[arg(long, value_enum, default_value_t = FileFormat::Jsonl)]
+        format: FileFormat,
+
+        /// Documents per insert_many call
+        #[arg(long, default_value_t = 1000)]
+        batch_size: usize,
+    },
+    /// Write every document of a collection to a JSON Lines (or JSON array) file
+    Export {
+        /// Collection to export
+        #[arg(long)]
+        collection: String,
+
+        /// File to write
+        #[arg(long)]
+        file: PathBuf,
+
+        /// File layout
+        #[arg(long, value_enum, default_value_t = FileFormat::Jsonl)]
+        format: FileFormat,
+
+        /// Documents per find call
+        #[arg(long, default_value_t = 1000)]
+        batch_size: usize,
+    },
 }
 
 fn main() {
@@ -69,16 +129,66 @@ fn main() {
         std::process::exit(1);
     };
 
-    if let Some(expr) = &cli.eval {
+    let format = if cli.json { OutputFormat::Raw } else { cli.format };
+    if let Some(command) = cli.command {
+        run_command(&mut *executor, command);
+    } else if let Some(expr) = &cli.eval {
         // One-shot mode
-        run_one(&mut *executor, expr, cli.json);
+        run_one(&mut *executor, expr, format);
     } else {
         // REPL mode
-        run_repl(&mut *executor, cli.json);
+        let history = if cli.no_history { None } else { history_path() };
+        run_repl(&mut *executor, format, history.as_deref());
+    }
+}
+
+fn run_command(executor: &mut dyn CommandExecutor, command: Command) {
+    match command {
+        Command::Import { collection, file, format, batch_size } => {
+            let reader = match std::fs::File::open(&file) {
+                Ok(f) => std::io::BufReader::new(f),
+                Err(e) => {
+                    eprintln!("Error: cannot open {}: {e}", file.display());
+                    std::process::exit(1);
+                }
+            };
+            match transfer::import(executor, &collection, reader, format, batch_size.max(1)) {
+                Ok(summary) => {
+                    for msg in &summary.skipped {
+                        eprintln!("Skipped {msg}");
+                    }
+                    println!(
+                        "Imported {} documents into '{collection}' ({} skipped)",
+                        summary.imported,
+                        summary.skipped.len()
+                    );
+                }
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Export { collection, file, format, batch_size } => {
+            let writer = match std::fs::File::create(&file) {
+                Ok(f) => std::io::BufWriter::new(f),
+                Err(e) => {
+                    eprintln!("Error: cannot create {}: {e}", file.display());
+                    std::process::exit(1);
+                }
+            };
+            match transfer::export(executor, &collection, writer, format, batch_size.max(1)) {
+                Ok(count) => println!("Exported {count} documents from '{collection}'"),
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
     }
 }
 
-fn run_one(executor: &mut dyn CommandExecutor, expr: &str, raw_json: bool) {
+fn run_one(executor: &mut dyn CommandExecutor, expr: &str, format: OutputFormat) {
     let cmd = match parser::parse(expr) {
         Ok(c) => c,
         Err(e) => {
@@ -88,7 +198,7 @@ fn run_one(executor: &mut dyn CommandExecutor, expr: &str, raw_json: bool) {
     };
     match executor.execute(cmd) {
         Ok(response) => {
-            println!("{}", display::format_response(&response, raw_json));
+            println!("{}", display::format_response(&response, format));
         }
         Err(e) => {
             eprintln!("Error: {e}");
@@ -97,14 +207,52 @@ fn run_one(executor: &mut dyn CommandExecutor, expr: &str, raw_json: bool) {
     }
 }
 
-fn run_repl(executor: &mut dyn CommandExecutor, raw_json: bool) {
-    let mut rl = match rustyline::DefaultEditor::new() {
+/// Where the shell history is kept: `$OXIDB_HISTORY` if set, otherwise
+/// `.oxidb_history` in the home directory.
+fn history_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("OXIDB_HISTORY") {
+        return Some(PathBuf::from(path));
+    }
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".oxidb_history"))
+}
+
+/// Load saved history. A missing file (the first run) is not an error.
+fn load_history<H: Helper>(rl: &mut Editor<H, FileHistory>, path: &Path) {
+    match rl.load_history(path) {
+        Ok(()) => {}
+        Err(rustyline::error::ReadlineError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => eprintln!("Warning: could not load history from {}: {e}", path.display()),
+    }
+}
+
+fn save_history<H: Helper>(rl: &mut Editor<H, FileHistory>, path: &Path) {
+    if let Err(e) = rl.save_history(path) {
+        eprintln!("Warning: could not save history to {}: {e}", path.display());
+    }
+}
+
+/// Update the collection names offered by tab completion.
+fn refresh_collections(executor: &mut dyn CommandExecutor, helper: &mut ReplHelper) {
+    let resp = executor.execute(json!({"cmd": "list_collections"}));
+    if let Some(data) = resp.ok().as_ref().and_then(|r| r.get("data")) {
+        helper.set_collections(data);
+    }
+}
+
+fn run_repl(executor: &mut dyn CommandExecutor, format: OutputFormat, history: Option<&Path>) {
+    let mut rl = match Editor::<ReplHelper, FileHistory>::new() {
         Ok(r) => r,
         Err(e) => {
             eprintln!("Failed to initialize readline: {e}");
             std::process::exit(1);
         }
     };
+    if let Some(path) = history {
+        load_history(&mut rl, path);
+    }
+    let mut helper = ReplHelper::default();
+    refresh_collections(executor, &mut helper);
+    rl.set_helper(Some(helper));
 
     display::print_banner(env!("CARGO_PKG_VERSION"));
 
@@ -147,11 +295,45 @@ fn run_repl(executor: &mut dyn CommandExecutor, raw_json: bool) {
 
         match executor.execute(cmd) {
             Ok(response) => {
-                println!("{}", display::format_response(&response, raw_json));
+                println!("{}", display::format_response(&response, format));
+                if let Some(helper) = rl.helper_mut() {
+                    if let Some(data) = response.get("data") {
+                        helper.observe(data);
+                    }
+                    refresh_collections(executor, helper);
+                }
             }
             Err(e) => {
                 eprintln!("Error: {e}");
             }
         }
     }
+
+    if let Some(path) = history {
+        save_history(&mut rl, path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+
+        // First run: no file yet
+        let mut rl = rustyline::DefaultEditor::new().unwrap();
+        load_history(&mut rl, &path);
+        assert_eq!(rl.history().iter().count(), 0);
+        rl.add_history_entry("db.users.find({})").unwrap();
+        rl.add_history_entry("show collections").unwrap();
+        save_history(&mut rl, &path);
+
+        let mut rl = rustyline::DefaultEditor::new().unwrap();
+        load_history(&mut rl, &path);
+        let entries: Vec<&String> = rl.history().iter().collect();
+        assert_eq!(entries, ["db.users.find({})", "show collections"]);
+    }
 }
@@ -7,6 +7,7 @@ use serde_json::{Value, json};
 ///   db.<collection>.<method>(args...).sort({...}).limit(N).skip(N)
 ///   show collections
 ///   show buckets
+///   describe <collection>
 ///   db.createCollection("name")
 ///   db.createBucket("name")
 ///   db.deleteBucket("name")
@@ -37,10 +38,20 @@ pub fn parse(input: &str) -> Result<Value, String> {
         return match what {
             "collections" => Ok(json!({"cmd": "list_collections"})),
             "buckets" => Ok(json!({"cmd": "list_buckets"})),
+            "metrics" => Ok(json!({"cmd": "metrics"})),
             _ => Err(format!("unknown: show {what}")),
         };
     }
 
+    // describe <collection>
+    if let Some(rest) = input.strip_prefix("describe ") {
+        let col = rest.trim();
+        if col.is_empty() || col.contains(char::is_whitespace) {
+            return Err("usage: describe <collection>".into());
+        }
+        return Ok(json!({"cmd": "describe", "collection": col}));
+    }
+
     // Everything else must start with "db."
     if !input.starts_with("db.") {
         return Err(format!("unknown command: {input}"));
@@ -189,6 +200,16 @@ fn build_method_command(
             let update: Value = parse_json_arg(args[1])?;
             Ok(json!({"cmd": "update_one", "collection": collection, "query": query, "update": update}))
         }
+        "findAndModify" => {
+            let spec: Value = parse_json_arg(args_str)?;
+            let Value::Object(mut cmd) = spec else {
+                return Err("findAndModify requires {query, update | remove, sort?, new?}".into());
+            };
+            cmd.retain(|k, _| matches!(k.as_str(), "query" | "update" | "sort" | "new" | "remove"));
+            cmd.insert("cmd".into(), json!("find_and_modify"));
+            cmd.insert("collection".into(), json!(collection));
+            Ok(Value::Object(cmd))
+        }
         "delete" => {
             let args = split_args(args_str)?;
             let query = if args.is_empty() || args[0].trim().is_empty() {
@@ -207,6 +228,13 @@ fn build_method_command(
             };
             Ok(json!({"cmd": "delete_one", "collection": collection, "query": query}))
         }
+        "bulkWrite" => {
+            let ops: Value = parse_json_arg(args_str)?;
+            if !ops.is_array() {
+                return Err("bulkWrite requires [{op, ...}, ...]".into());
+            }
+            Ok(json!({"cmd": "bulk_write", "collection": collection, "ops": ops}))
+        }
         "count" => {
             let args = split_args(args_str)?;
             let query = if args.is_empty() || args[0].trim().is_empty() {
@@ -216,6 +244,18 @@ fn build_method_command(
             };
             Ok(json!({"cmd": "count", "collection": collection, "query": query}))
         }
+        "distinct" => {
+            let args = split_args(args_str)?;
+            if args.is_empty() || args[0].trim().is_empty() {
+                return Err("distinct requires a field name".into());
+            }
+            let field = parse_string_arg(args[0])?;
+            let query = match args.get(1) {
+                Some(q) if !q.trim().is_empty() => parse_json_arg(q)?,
+                _ => json!({}),
+            };
+            Ok(json!({"cmd": "distinct", "collection": collection, "field": field, "query": query}))
+        }
         "createIndex" => {
             let field = parse_string_arg(args_str)?;
             Ok(json!({"cmd": "create_index", "collection": collection, "field": field}))
@@ -232,6 +272,32 @@ fn build_method_command(
             let fields: Value = parse_json_arg(args_str)?;
             Ok(json!({"cmd": "create_text_index", "collection": collection, "fields": fields}))
         }
+        "createGeoIndex" => {
+            let field = parse_string_arg(args_str)?;
+            Ok(json!({"cmd": "create_geo_index", "collection": collection, "field": field}))
+        }
+        "setSchema" => {
+            let args = split_args(args_str)?;
+            if args.is_empty() || args[0].trim().is_empty() {
+                return Err("setSchema requires a schema (or null to remove it)".into());
+            }
+            let mut cmd = json!({"cmd": "set_schema", "collection": collection, "schema": parse_json_arg(args[0])?});
+            if let Some(opts) = args.get(1) {
+                let opts: Value = parse_json_arg(opts)?;
+                for key in ["level", "coerce"] {
+                    if let Some(v) = opts.get(key) {
+                        cmd[key] = v.clone();
+                    }
+                }
+            }
+            Ok(cmd)
+        }
+        "getSchema" => Ok(json!({"cmd": "get_schema", "collection": collection})),
+        "setDefaults" => {
+            let defaults: Value = parse_json_arg(args_str)?;
+            Ok(json!({"cmd": "set_defaults", "collection": collection, "defaults": defaults}))
+        }
+        "getDefaults" => Ok(json!({"cmd": "get_defaults", "collection": collection})),
         "textSearch" => {
             let args = split_args(args_str)?;
             if args.is_empty() {
@@ -434,6 +500,19 @@ mod tests {
         assert_eq!(cmd["cmd"], "list_buckets");
     }
 
+    #[test]
+    fn test_show_metrics() {
+        let cmd = parse("show metrics").unwrap();
+        assert_eq!(cmd["cmd"], "metrics");
+    }
+
+    #[test]
+    fn test_describe() {
+        let cmd = parse("describe users").unwrap();
+        assert_eq!(cmd, json!({"cmd": "describe", "collection": "users"}));
+        assert!(parse("describe users orders").is_err());
+    }
+
     #[test]
     fn test_insert() {
         let cmd = parse(r#"db.users.insert({"name": "Alice", "age": 30})"#).unwrap();
@@ -521,6 +600,18 @@ mod tests {
         assert_eq!(cmd["query"]["city"], "NY");
     }
 
+    #[test]
+    fn test_distinct() {
+        let cmd = parse(r#"db.users.distinct("city")"#).unwrap();
+        assert_eq!(cmd["cmd"], "distinct");
+        assert_eq!(cmd["field"], "city");
+        assert_eq!(cmd["query"], json!({}));
+
+        let cmd = parse(r#"db.users.distinct("city", {"active": true})"#).unwrap();
+        assert_eq!(cmd["query"]["active"], true);
+        assert!(parse("db.users.distinct()").is_err());
+    }
+
     #[test]
     fn test_create_index() {
         let cmd = parse(r#"db.users.createIndex("email")"#).unwrap();
@@ -548,6 +639,60 @@ mod tests {
         assert_eq!(cmd["cmd"], "create_text_index");
     }
 
+    #[test]
+    fn test_create_geo_index() {
+        let cmd = parse(r#"db.places.createGeoIndex("location")"#).unwrap();
+        assert_eq!(cmd["cmd"], "create_geo_index");
+        assert_eq!(cmd["field"], "location");
+    }
+
+    #[test]
+    fn test_set_and_get_schema() {
+        let cmd = parse(r#"db.users.setSchema({"required": ["name"]}, {"level": "warn", "coerce": true})"#).unwrap();
+        assert_eq!(
+            cmd,
+            json!({"cmd": "set_schema", "collection": "users", "schema": {"required": ["name"]}, "level": "warn", "coerce": true})
+        );
+        assert_eq!(parse("db.users.setSchema(null)").unwrap()["schema"], Value::Null);
+        assert!(parse("db.users.setSchema()").is_err());
+        assert_eq!(parse("db.users.getSchema()").unwrap(), json!({"cmd": "get_schema", "collection": "users"}));
+    }
+
+    #[test]
+    fn test_set_and_get_defaults() {
+        let cmd = parse(r#"db.orders.setDefaults({"created_at": "$now"})"#).unwrap();
+        assert_eq!(cmd, json!({"cmd": "set_defaults", "collection": "orders", "defaults": {"created_at": "$now"}}));
+        assert!(parse("db.orders.setDefaults()").is_err());
+        assert_eq!(parse("db.orders.getDefaults()").unwrap()["cmd"], "get_defaults");
+    }
+
+    #[test]
+    fn test_find_and_modify() {
+        let cmd = parse(r#"db.jobs.findAndModify({"query": {"state": "pending"}, "update": {"$set": {"state": "claimed"}}, "sort": {"priority": -1}, "new": true})"#).unwrap();
+        assert_eq!(
+            cmd,
+            json!({
+                "cmd": "find_and_modify", "collection": "jobs", "query": {"state": "pending"},
+                "update": {"$set": {"state": "claimed"}}, "sort": {"priority": -1}, "new": true
+            })
+        );
+        assert_eq!(parse(r#"db.jobs.findAndModify({"remove": true})"#).unwrap()["remove"], true);
+        assert!(parse(r#"db.jobs.findAndModify([])"#).is_err());
+    }
+
+    #[test]
+    fn test_bulk_write() {
+        let cmd = parse(r#"db.notes.bulkWrite([{"op": "insert", "doc": {"a": 1}}, {"op": "delete", "query": {"a": 2}}])"#).unwrap();
+        assert_eq!(
+            cmd,
+            json!({
+                "cmd": "bulk_write", "collection": "notes",
+                "ops": [{"op": "insert", "doc": {"a": 1}}, {"op": "delete", "query": {"a": 2}}]
+            })
+        );
+        assert!(parse(r#"db.notes.bulkWrite({"op": "insert"})"#).is_err());
+    }
+
     #[test]
     fn test_text_search() {
         let cmd = parse(r#"db.users.textSearch("rust programming", 10)"#).unwrap();
@@ -0,0 +1,233 @@
+use std::io::{BufRead, Write};
+
+use serde_json::{Value, json};
+
+use crate::executor::CommandExecutor;
+
+/// Layout of an import or export file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum FileFormat {
+    /// One JSON document per line
+    Jsonl,
+    /// A single JSON array of documents
+    Json,
+}
+
+/// Outcome of an import.
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub imported: usize,
+    /// One message per skipped line or array element.
+    pub skipped: Vec<String>,
+}
+
+/// Insert the documents read from `reader` into `collection`, `batch_size`
+/// at a time. Lines (or array elements) that aren't JSON objects are
+/// skipped and reported in the summary; a failed insert stops the import.
+pub fn import<R: BufRead>(
+    executor: &mut dyn CommandExecutor,
+    collection: &str,
+    reader: R,
+    format: FileFormat,
+    batch_size: usize,
+) -> Result<ImportSummary, String> {
+    let mut summary = ImportSummary::default();
+    let mut batch = Vec::with_capacity(batch_size);
+    match format {
+        FileFormat::Jsonl => {
+            for (i, line) in reader.lines().enumerate() {
+                let line = line.map_err(|e| format!("read error: {e}"))?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<Value>(&line) {
+                    Ok(doc) if doc.is_object() => batch.push(doc),
+                    Ok(_) => summary.skipped.push(format!("line {}: not a JSON object", i + 1)),
+                    Err(e) => summary.skipped.push(format!("line {}: {e}", i + 1)),
+                }
+                if batch.len() >= batch_size {
+                    summary.imported += insert_batch(executor, collection, &mut batch)?;
+                }
+            }
+        }
+        FileFormat::Json => {
+            let docs = match serde_json::from_reader::<_, Value>(reader) {
+                Ok(Value::Array(docs)) => docs,
+                Ok(_) => return Err("expected a JSON array of documents".into()),
+                Err(e) => return Err(format!("invalid JSON: {e}")),
+            };
+            for (i, doc) in docs.into_iter().enumerate() {
+                if !doc.is_object() {
+                    summary.skipped.push(format!("element {i}: not a JSON object"));
+                    continue;
+                }
+                batch.push(doc);
+                if batch.len() >= batch_size {
+                    summary.imported += insert_batch(executor, collection, &mut batch)?;
+                }
+            }
+        }
+    }
+    if !batch.is_empty() {
+        summary.imported += insert_batch(executor, collection, &mut batch)?;
+    }
+    Ok(summary)
+}
+
+fn insert_batch(
+    executor: &mut dyn CommandExecutor,
+    collection: &str,
+    batch: &mut Vec<Value>,
+) -> Result<usize, String> {
+    let count = batch.len();
+    let docs = std::mem::take(batch);
+    let resp = executor.execute(json!({"cmd": "insert_many", "collection": collection, "docs": docs}))?;
+    response_data(resp)?;
+    Ok(count)
+}
+
+/// Write every document in `collection` to `writer`, reading it
+/// `batch_size` documents at a time in `_id` order. Returns the number of
+/// documents written.
+pub fn export<W: Write>(
+    executor: &mut dyn CommandExecutor,
+    collection: &str,
+    mut writer: W,
+    format: FileFormat,
+    batch_size: usize,
+) -> Result<usize, String> {
+    let write_err = |e: std::io::Error| format!("write error: {e}");
+    let mut written = 0;
+    if format == FileFormat::Json {
+        writer.write_all(b"[").map_err(write_err)?;
+    }
+    loop {
+        let resp = executor.execute(json!({
+            "cmd": "find",
+            "collection": collection,
+            "query": {},
+            "sort": {"_id": 1},
+            "skip": written,
+            "limit": batch_size,
+        }))?;
+        let docs = match response_data(resp)? {
+            Value::Array(docs) => docs,
+            _ => return Err("unexpected find response".into()),
+        };
+        let count = docs.len();
+        for doc in docs {
+            let sep: &[u8] = match format {
+                FileFormat::Jsonl => b"",
+                FileFormat::Json if written == 0 => b"\n",
+                FileFormat::Json => b",\n",
+            };
+            writer.write_all(sep).map_err(write_err)?;
+            serde_json::to_writer(&mut writer, &doc).map_err(|e| format!("write error: {e}"))?;
+            if format == FileFormat::Jsonl {
+                writer.write_all(b"\n").map_err(write_err)?;
+            }
+            written += 1;
+        }
+        if count < batch_size {
+            break;
+        }
+    }
+    if format == FileFormat::Json {
+        writer.write_all(b"\n]\n").map_err(write_err)?;
+    }
+    writer.flush().map_err(write_err)?;
+    Ok(written)
+}
+
+/// The `data` of a successful response, or its error message.
+fn response_data(resp: Value) -> Result<Value, String> {
+    if resp.get("ok").and_then(|v| v.as_bool()) == Some(true) {
+        Ok(resp.get("data").cloned().unwrap_or(Value::Null))
+    } else {
+        Err(resp
+            .get("error")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown error")
+            .to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::EmbeddedExecutor;
+
+    fn open(dir: &tempfile::TempDir) -> EmbeddedExecutor {
+        EmbeddedExecutor::open(dir.path(), None).unwrap()
+    }
+
+    /// A document without the fields the database assigns.
+    fn contents(mut doc: Value) -> Value {
+        let obj = doc.as_object_mut().unwrap();
+        obj.remove("_id");
+        obj.remove("_version");
+        doc
+    }
+
+    #[test]
+    fn test_jsonl_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut exec = open(&dir);
+        let input = concat!(
+            "{\"name\": \"Alice\", \"tags\": [\"a\"], \"address\": {\"city\": \"NYC\"}}\n",
+            "not json\n",
+            "\n",
+            "{\"name\": \"Bob\", \"active\": false}\n",
+            "[1, 2]\n",
+            "{\"name\": \"Carol\", \"score\": 9.5}\n",
+        );
+        let summary = import(&mut exec, "people", input.as_bytes(), FileFormat::Jsonl, 2).unwrap();
+        assert_eq!(summary.imported, 3);
+        assert_eq!(summary.skipped.len(), 2);
+        assert!(summary.skipped[0].starts_with("line 2:"));
+        assert_eq!(summary.skipped[1], "line 5: not a JSON object");
+
+        let mut out = Vec::new();
+        assert_eq!(export(&mut exec, "people", &mut out, FileFormat::Jsonl, 2).unwrap(), 3);
+        let exported: Vec<Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|l| contents(serde_json::from_str(l).unwrap()))
+            .collect();
+        let expected: Vec<Value> = input
+            .lines()
+            .filter_map(|l| serde_json::from_str::<Value>(l).ok())
+            .filter(Value::is_object)
+            .collect();
+        assert_eq!(exported, expected);
+
+        // Exported JSONL imports into another collection unchanged
+        let mut out = Vec::new();
+        export(&mut exec, "people", &mut out, FileFormat::Jsonl, 100).unwrap();
+        let summary = import(&mut exec, "copy", out.as_slice(), FileFormat::Jsonl, 100).unwrap();
+        assert_eq!(summary.imported, 3);
+        assert!(summary.skipped.is_empty());
+    }
+
+    #[test]
+    fn test_json_array_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut exec = open(&dir);
+        let docs = json!([{"n": "1"}, 7, {"n": "2"}, {"n": "3"}]);
+        let input = docs.to_string();
+        let summary = import(&mut exec, "nums", input.as_bytes(), FileFormat::Json, 2).unwrap();
+        assert_eq!(summary.imported, 3);
+        assert_eq!(summary.skipped, vec!["element 1: not a JSON object".to_string()]);
+
+        let mut out = Vec::new();
+        assert_eq!(export(&mut exec, "nums", &mut out, FileFormat::Json, 2).unwrap(), 3);
+        let exported: Value = serde_json::from_slice(&out).unwrap();
+        let exported: Vec<Value> = exported.as_array().unwrap().iter().cloned().map(contents).collect();
+        assert_eq!(exported, vec![json!({"n": "1"}), json!({"n": "2"}), json!({"n": "3"})]);
+
+        // An empty collection exports as an empty array
+        let mut out = Vec::new();
+        assert_eq!(export(&mut exec, "none", &mut out, FileFormat::Json, 2).unwrap(), 0);
+        assert_eq!(serde_json::from_slice::<Value>(&out).unwrap(), json!([]));
+    }
+}
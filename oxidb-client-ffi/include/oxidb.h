@@ -7,19 +7,38 @@
 extern "C" {
 #endif
 
-/* Opaque connection handle */
+/* Opaque connection handle.
+   A connection is not thread-safe: use each one from a single thread at a
+   time (or guard it with a lock), and open one connection per thread. */
 typedef void OxiDbConn;
 
 /* Connect to an OxiDB server. Returns NULL on failure. */
 OxiDbConn* oxidb_connect(const char* host, uint16_t port);
 
+/* Connect with options given as a JSON object (options_json may be NULL):
+     "auto_reconnect": true  reconnect once and retry a request when the
+                             server closed the connection (e.g. it restarted).
+                             A write sent just before the drop may be applied
+                             twice; requests inside a transaction fail instead
+                             of being retried, since the transaction was lost.
+     "username", "password"  log in on connect and after every reconnect.
+   Returns NULL on failure (including failed authentication). */
+OxiDbConn* oxidb_connect_with_options(const char* host, uint16_t port,
+                                      const char* options_json);
+
 /* Disconnect and free the connection handle. */
 void oxidb_disconnect(OxiDbConn* conn);
 
+/* Why the last call on conn returned NULL (invalid argument, malformed JSON,
+   or a connection error), or "" if it succeeded. The string is owned by the
+   connection: do not free it, and do not use it after the next call on conn. */
+const char* oxidb_last_error(OxiDbConn* conn);
+
 /* All functions below return a JSON string (caller must free with oxidb_free_string).
-   Returns NULL on connection error. */
+   Returns NULL on invalid arguments or a connection error; see oxidb_last_error. */
 
 char* oxidb_ping(OxiDbConn* conn);
+char* oxidb_metrics(OxiDbConn* conn);
 
 char* oxidb_insert(OxiDbConn* conn, const char* collection, const char* doc_json);
 char* oxidb_insert_many(OxiDbConn* conn, const char* collection, const char* docs_json);
@@ -29,10 +48,23 @@ char* oxidb_update(OxiDbConn* conn, const char* collection, const char* query_js
                    const char* update_json);
 char* oxidb_update_one(OxiDbConn* conn, const char* collection, const char* query_json,
                        const char* update_json);
+/* Atomically update (or remove) the first matching document and return it.
+   update_json is NULL when options_json has "remove": true; options_json (may be
+   NULL) holds "sort", "new" (return the updated document), and "remove". */
+char* oxidb_find_and_modify(OxiDbConn* conn, const char* collection, const char* query_json,
+                            const char* update_json, const char* options_json);
 char* oxidb_delete(OxiDbConn* conn, const char* collection, const char* query_json);
 char* oxidb_delete_one(OxiDbConn* conn, const char* collection, const char* query_json);
+/* Apply a JSON array of {"op": "insert"|"update"|"delete", ...} atomically;
+   returns one result per op. */
+char* oxidb_bulk_write(OxiDbConn* conn, const char* collection, const char* ops_json);
 char* oxidb_count(OxiDbConn* conn, const char* collection);
+/* Sorted unique values of `field` among documents matching query_json ("{}" for all). */
+char* oxidb_distinct(OxiDbConn* conn, const char* collection, const char* field,
+                     const char* query_json);
 char* oxidb_compact(OxiDbConn* conn, const char* collection);
+/* Compacts every collection; reports totals and per-collection results. Admin only. */
+char* oxidb_compact_all(OxiDbConn* conn);
 
 char* oxidb_create_index(OxiDbConn* conn, const char* collection, const char* field);
 char* oxidb_create_unique_index(OxiDbConn* conn, const char* collection, const char* field);
@@ -40,7 +72,19 @@ char* oxidb_create_composite_index(OxiDbConn* conn, const char* collection,
                                    const char* fields_json);
 char* oxidb_create_text_index(OxiDbConn* conn, const char* collection,
                               const char* fields_json);
+char* oxidb_create_geo_index(OxiDbConn* conn, const char* collection, const char* field);
 char* oxidb_list_indexes(OxiDbConn* conn, const char* collection);
+char* oxidb_describe(OxiDbConn* conn, const char* collection);
+/* Validate writes against a JSON Schema ("null" removes it). level is "strict"
+   or "warn" (NULL for strict); coerce != 0 converts values to the schema's
+   types first. get_schema returns null when no schema is set. */
+char* oxidb_set_schema(OxiDbConn* conn, const char* collection, const char* schema_json,
+                       const char* level, int32_t coerce);
+char* oxidb_get_schema(OxiDbConn* conn, const char* collection);
+/* Defaults for fields inserted documents omit: field paths to literals, "$now"
+   or "$uuid" ("{}" or "null" removes them). */
+char* oxidb_set_defaults(OxiDbConn* conn, const char* collection, const char* defaults_json);
+char* oxidb_get_defaults(OxiDbConn* conn, const char* collection);
 char* oxidb_drop_index(OxiDbConn* conn, const char* collection, const char* index);
 
 /* Collection-level text search */
@@ -60,7 +104,23 @@ char* oxidb_delete_bucket(OxiDbConn* conn, const char* bucket);
 char* oxidb_put_object(OxiDbConn* conn, const char* bucket, const char* key,
                        const char* data_b64, const char* content_type,
                        const char* metadata_json);
+char* oxidb_copy_object(OxiDbConn* conn, const char* src_bucket, const char* src_key,
+                        const char* dst_bucket, const char* dst_key,
+                        const char* content_type, const char* metadata_json);
+char* oxidb_create_multipart_upload(OxiDbConn* conn, const char* bucket,
+                                    const char* key, const char* content_type,
+                                    const char* metadata_json);
+char* oxidb_upload_part(OxiDbConn* conn, const char* upload_id,
+                        uint32_t part_number, const char* data_b64);
+char* oxidb_complete_multipart_upload(OxiDbConn* conn, const char* upload_id);
+char* oxidb_abort_multipart_upload(OxiDbConn* conn, const char* upload_id);
 char* oxidb_get_object(OxiDbConn* conn, const char* bucket, const char* key);
+char* oxidb_get_object_range(OxiDbConn* conn, const char* bucket, const char* key,
+                             int64_t start, int64_t end);
+char* oxidb_get_object_version(OxiDbConn* conn, const char* bucket, const char* key,
+                               const char* version_id);
+char* oxidb_list_object_versions(OxiDbConn* conn, const char* bucket, const char* key);
+char* oxidb_enable_versioning(OxiDbConn* conn, const char* bucket, int32_t enabled);
 char* oxidb_head_object(OxiDbConn* conn, const char* bucket, const char* key);
 char* oxidb_delete_object(OxiDbConn* conn, const char* bucket, const char* key);
 char* oxidb_list_objects(OxiDbConn* conn, const char* bucket, const char* prefix,
@@ -74,6 +134,8 @@ char* oxidb_search(OxiDbConn* conn, const char* query, const char* bucket,
 char* oxidb_begin_tx(OxiDbConn* conn);
 char* oxidb_commit_tx(OxiDbConn* conn);
 char* oxidb_rollback_tx(OxiDbConn* conn);
+char* oxidb_savepoint(OxiDbConn* conn, const char* name);
+char* oxidb_rollback_to(OxiDbConn* conn, const char* name);
 
 /* Free a string returned by any oxidb_* function. */
 void oxidb_free_string(char* ptr);
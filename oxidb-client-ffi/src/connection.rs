@@ -1,24 +1,73 @@
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::io::{self, Read, Write};
 use std::net::TcpStream;
 
+/// Options for `OxiDbConnection::connect_with_options`.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectOptions {
+    /// Reconnect once and retry when a request finds the connection closed.
+    pub auto_reconnect: bool,
+    /// Username and password sent with `auth_simple` after every (re)connect.
+    pub credentials: Option<(String, String)>,
+}
+
 /// A connection to an OxiDB server.
+///
+/// Not thread-safe: requests and `last_error` share the connection's state,
+/// so each connection must be used by one thread at a time.
 pub struct OxiDbConnection {
     stream: TcpStream,
+    host: String,
+    port: u16,
+    options: ConnectOptions,
+    in_transaction: bool,
     last_error: Option<CString>,
 }
 
 impl OxiDbConnection {
     pub fn connect(host: &str, port: u16) -> io::Result<Self> {
+        Self::connect_with_options(host, port, ConnectOptions::default())
+    }
+
+    pub fn connect_with_options(host: &str, port: u16, options: ConnectOptions) -> io::Result<Self> {
         let stream = TcpStream::connect((host, port))?;
-        Ok(Self {
+        let mut conn = Self {
             stream,
+            host: host.to_string(),
+            port,
+            options,
+            in_transaction: false,
             last_error: None,
-        })
+        };
+        conn.authenticate()?;
+        Ok(conn)
     }
 
     /// Send a length-prefixed JSON request and read the length-prefixed response.
+    ///
+    /// With `auto_reconnect`, a request that finds the connection closed (the
+    /// server restarted, say) reconnects and is sent once more. A request that
+    /// was executed just before the connection dropped may therefore run
+    /// twice. Requests inside a transaction are not retried, since the
+    /// server rolled the transaction back when the connection closed.
     pub fn request(&mut self, json_bytes: &[u8]) -> io::Result<Vec<u8>> {
+        match self.send(json_bytes) {
+            Err(e) if self.options.auto_reconnect && is_disconnect(&e) => {
+                let in_transaction = std::mem::take(&mut self.in_transaction);
+                self.reconnect()?;
+                if in_transaction {
+                    return Err(io::Error::new(
+                        e.kind(),
+                        format!("connection lost during a transaction, which was rolled back: {e}"),
+                    ));
+                }
+                self.send(json_bytes)
+            }
+            result => result,
+        }
+    }
+
+    fn send(&mut self, json_bytes: &[u8]) -> io::Result<Vec<u8>> {
         // Write: [u32 LE length][json]
         let len = (json_bytes.len() as u32).to_le_bytes();
         self.stream.write_all(&len)?;
@@ -35,7 +84,53 @@ impl OxiDbConnection {
         Ok(buf)
     }
 
+    fn reconnect(&mut self) -> io::Result<()> {
+        self.stream = TcpStream::connect((self.host.as_str(), self.port))?;
+        self.authenticate()
+    }
+
+    /// Log in with the stored credentials, if any.
+    fn authenticate(&mut self) -> io::Result<()> {
+        let Some((username, password)) = &self.options.credentials else {
+            return Ok(());
+        };
+        let req = serde_json::json!({"cmd": "auth_simple", "username": username, "password": password});
+        let resp: serde_json::Value = serde_json::from_slice(&self.send(req.to_string().as_bytes())?)?;
+        if resp["ok"] == true {
+            return Ok(());
+        }
+        let msg = resp["error"].as_str().unwrap_or("unknown error");
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("authentication failed: {msg}")))
+    }
+
+    /// Record whether a transaction is open, so a dropped connection isn't
+    /// silently retried outside it.
+    pub fn set_in_transaction(&mut self, in_transaction: bool) {
+        self.in_transaction = in_transaction;
+    }
+
     pub fn set_last_error(&mut self, err: String) {
         self.last_error = CString::new(err).ok();
     }
+
+    pub fn clear_last_error(&mut self) {
+        self.last_error = None;
+    }
+
+    /// The last recorded error, or an empty string.
+    pub fn last_error(&self) -> &CStr {
+        self.last_error.as_deref().unwrap_or(c"")
+    }
+}
+
+/// Whether `e` means the server closed the connection.
+fn is_disconnect(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::UnexpectedEof
+    )
 }
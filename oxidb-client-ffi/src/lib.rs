@@ -1,6 +1,6 @@
 mod connection;
 
-use connection::OxiDbConnection;
+use connection::{ConnectOptions, OxiDbConnection};
 use std::ffi::{CStr, CString, c_char, c_void};
 use std::ptr;
 
@@ -16,7 +16,18 @@ unsafe fn send_request(conn: *mut OxiDbConn, json: &serde_json::Value) -> *mut c
 
     match conn.request(payload.as_bytes()) {
         Ok(resp) => match CString::new(resp) {
-            Ok(cs) => cs.into_raw(),
+            Ok(cs) => {
+                match json["cmd"].as_str() {
+                    Some("begin_tx") => {
+                        let resp = serde_json::from_slice::<serde_json::Value>(cs.to_bytes());
+                        conn.set_in_transaction(resp.is_ok_and(|r| r["ok"] == true));
+                    }
+                    Some("commit_tx" | "rollback_tx") => conn.set_in_transaction(false),
+                    _ => {}
+                }
+                conn.clear_last_error();
+                cs.into_raw()
+            }
             Err(e) => {
                 conn.set_last_error(format!("response contains null byte: {e}"));
                 ptr::null_mut()
@@ -29,6 +40,15 @@ unsafe fn send_request(conn: *mut OxiDbConn, json: &serde_json::Value) -> *mut c
     }
 }
 
+/// Helper: record `msg` as the connection's last error and return NULL.
+unsafe fn fail(conn: *mut OxiDbConn, msg: &str) -> *mut c_char {
+    if !conn.is_null() {
+        let conn = unsafe { &mut *(conn as *mut OxiDbConnection) };
+        conn.set_last_error(msg.to_string());
+    }
+    ptr::null_mut()
+}
+
 /// Helper: convert a C string pointer to a &str, returning None if null or invalid UTF-8.
 unsafe fn cstr_to_str<'a>(s: *const c_char) -> Option<&'a str> {
     if s.is_null() {
@@ -50,6 +70,48 @@ pub unsafe extern "C" fn oxidb_connect(host: *const c_char, port: u16) -> *mut O
     }
 }
 
+/// Connect with options given as a JSON object (NULL or `"{}"` for none):
+/// `"auto_reconnect"` (bool) reconnects once and retries a request when the
+/// connection was closed, e.g. by a server restart; `"username"` and
+/// `"password"` log in on connect and again after every reconnect.
+///
+/// # Safety
+/// `host` must be a valid null-terminated C string; `options_json` must be one
+/// or NULL.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_connect_with_options(
+    host: *const c_char,
+    port: u16,
+    options_json: *const c_char,
+) -> *mut OxiDbConn {
+    let host_str = match unsafe { cstr_to_str(host) } {
+        Some(s) => s,
+        None => return ptr::null_mut(),
+    };
+    let options: serde_json::Value = if options_json.is_null() {
+        serde_json::json!({})
+    } else {
+        match unsafe { cstr_to_str(options_json) }.map(serde_json::from_str) {
+            Some(Ok(v)) => v,
+            _ => return ptr::null_mut(),
+        }
+    };
+    let credentials = match (options["username"].as_str(), options["password"].as_str()) {
+        (Some(user), Some(pass)) => Some((user.to_string(), pass.to_string())),
+        (None, None) => None,
+        _ => return ptr::null_mut(),
+    };
+    let options = ConnectOptions {
+        auto_reconnect: options["auto_reconnect"].as_bool().unwrap_or(false),
+        credentials,
+    };
+
+    match OxiDbConnection::connect_with_options(host_str, port, options) {
+        Ok(conn) => Box::into_raw(Box::new(conn)) as *mut OxiDbConn,
+        Err(_) => ptr::null_mut(),
+    }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn oxidb_disconnect(conn: *mut OxiDbConn) {
     if !conn.is_null() {
@@ -57,12 +119,33 @@ pub unsafe extern "C" fn oxidb_disconnect(conn: *mut OxiDbConn) {
     }
 }
 
+/// Why the last call on this connection returned NULL, or an empty string if
+/// it succeeded. The string is owned by the connection: don't free it, and
+/// don't use it after the next call on the same connection.
+///
+/// # Safety
+/// `conn` must be a handle from `oxidb_connect`, or NULL.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_last_error(conn: *mut OxiDbConn) -> *const c_char {
+    if conn.is_null() {
+        return c"".as_ptr();
+    }
+    let conn = unsafe { &*(conn as *mut OxiDbConnection) };
+    conn.last_error().as_ptr()
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn oxidb_ping(conn: *mut OxiDbConn) -> *mut c_char {
     let req = serde_json::json!({"cmd": "ping"});
     unsafe { send_request(conn, &req) }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_metrics(conn: *mut OxiDbConn) -> *mut c_char {
+    let req = serde_json::json!({"cmd": "metrics"});
+    unsafe { send_request(conn, &req) }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn oxidb_insert(
     conn: *mut OxiDbConn,
@@ -71,15 +154,15 @@ pub unsafe extern "C" fn oxidb_insert(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let doc_str = match unsafe { cstr_to_str(doc_json) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let doc: serde_json::Value = match serde_json::from_str(doc_str) {
         Ok(v) => v,
-        Err(_) => return ptr::null_mut(),
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
     };
     let req = serde_json::json!({"cmd": "insert", "collection": col, "doc": doc});
     unsafe { send_request(conn, &req) }
@@ -93,15 +176,15 @@ pub unsafe extern "C" fn oxidb_insert_many(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let docs_str = match unsafe { cstr_to_str(docs_json) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let docs: serde_json::Value = match serde_json::from_str(docs_str) {
         Ok(v) => v,
-        Err(_) => return ptr::null_mut(),
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
     };
     let req = serde_json::json!({"cmd": "insert_many", "collection": col, "docs": docs});
     unsafe { send_request(conn, &req) }
@@ -115,15 +198,15 @@ pub unsafe extern "C" fn oxidb_find(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let query_str = match unsafe { cstr_to_str(query_json) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let query: serde_json::Value = match serde_json::from_str(query_str) {
         Ok(v) => v,
-        Err(_) => return ptr::null_mut(),
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
     };
     let req = serde_json::json!({"cmd": "find", "collection": col, "query": query});
     unsafe { send_request(conn, &req) }
@@ -137,15 +220,15 @@ pub unsafe extern "C" fn oxidb_find_one(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let query_str = match unsafe { cstr_to_str(query_json) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let query: serde_json::Value = match serde_json::from_str(query_str) {
         Ok(v) => v,
-        Err(_) => return ptr::null_mut(),
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
     };
     let req = serde_json::json!({"cmd": "find_one", "collection": col, "query": query});
     unsafe { send_request(conn, &req) }
@@ -160,23 +243,23 @@ pub unsafe extern "C" fn oxidb_update(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let query_str = match unsafe { cstr_to_str(query_json) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let update_str = match unsafe { cstr_to_str(update_json) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let query: serde_json::Value = match serde_json::from_str(query_str) {
         Ok(v) => v,
-        Err(_) => return ptr::null_mut(),
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
     };
     let update: serde_json::Value = match serde_json::from_str(update_str) {
         Ok(v) => v,
-        Err(_) => return ptr::null_mut(),
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
     };
     let req =
         serde_json::json!({"cmd": "update", "collection": col, "query": query, "update": update});
@@ -192,29 +275,76 @@ pub unsafe extern "C" fn oxidb_update_one(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let query_str = match unsafe { cstr_to_str(query_json) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let update_str = match unsafe { cstr_to_str(update_json) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let query: serde_json::Value = match serde_json::from_str(query_str) {
         Ok(v) => v,
-        Err(_) => return ptr::null_mut(),
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
     };
     let update: serde_json::Value = match serde_json::from_str(update_str) {
         Ok(v) => v,
-        Err(_) => return ptr::null_mut(),
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
     };
     let req =
         serde_json::json!({"cmd": "update_one", "collection": col, "query": query, "update": update});
     unsafe { send_request(conn, &req) }
 }
 
+/// Atomically update (or remove) the first matching document and return it.
+/// `update_json` is NULL with `"remove": true`; `options_json` (may be NULL)
+/// holds `sort`, `new`, and `remove`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_find_and_modify(
+    conn: *mut OxiDbConn,
+    collection: *const c_char,
+    query_json: *const c_char,
+    update_json: *const c_char,
+    options_json: *const c_char,
+) -> *mut c_char {
+    let col = match unsafe { cstr_to_str(collection) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let query_str = match unsafe { cstr_to_str(query_json) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let query: serde_json::Value = match serde_json::from_str(query_str) {
+        Ok(v) => v,
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
+    };
+    let mut req = if options_json.is_null() {
+        serde_json::json!({})
+    } else {
+        match unsafe { cstr_to_str(options_json) }.map(serde_json::from_str::<serde_json::Value>) {
+            Some(Ok(opts @ serde_json::Value::Object(_))) => opts,
+            Some(Ok(_)) => return unsafe { fail(conn, "options must be a JSON object") },
+            Some(Err(e)) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
+            None => return unsafe { fail(conn, "invalid string argument") },
+        }
+    };
+    if !update_json.is_null() {
+        let update = match unsafe { cstr_to_str(update_json) }.map(serde_json::from_str::<serde_json::Value>) {
+            Some(Ok(v)) => v,
+            Some(Err(e)) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
+            None => return unsafe { fail(conn, "invalid string argument") },
+        };
+        req["update"] = update;
+    }
+    req["cmd"] = serde_json::json!("find_and_modify");
+    req["collection"] = serde_json::json!(col);
+    req["query"] = query;
+    unsafe { send_request(conn, &req) }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn oxidb_delete(
     conn: *mut OxiDbConn,
@@ -223,15 +353,15 @@ pub unsafe extern "C" fn oxidb_delete(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let query_str = match unsafe { cstr_to_str(query_json) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let query: serde_json::Value = match serde_json::from_str(query_str) {
         Ok(v) => v,
-        Err(_) => return ptr::null_mut(),
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
     };
     let req = serde_json::json!({"cmd": "delete", "collection": col, "query": query});
     unsafe { send_request(conn, &req) }
@@ -245,20 +375,42 @@ pub unsafe extern "C" fn oxidb_delete_one(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let query_str = match unsafe { cstr_to_str(query_json) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let query: serde_json::Value = match serde_json::from_str(query_str) {
         Ok(v) => v,
-        Err(_) => return ptr::null_mut(),
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
     };
     let req = serde_json::json!({"cmd": "delete_one", "collection": col, "query": query});
     unsafe { send_request(conn, &req) }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_bulk_write(
+    conn: *mut OxiDbConn,
+    collection: *const c_char,
+    ops_json: *const c_char,
+) -> *mut c_char {
+    let col = match unsafe { cstr_to_str(collection) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let ops_str = match unsafe { cstr_to_str(ops_json) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let ops: serde_json::Value = match serde_json::from_str(ops_str) {
+        Ok(v) => v,
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
+    };
+    let req = serde_json::json!({"cmd": "bulk_write", "collection": col, "ops": ops});
+    unsafe { send_request(conn, &req) }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn oxidb_count(
     conn: *mut OxiDbConn,
@@ -266,12 +418,39 @@ pub unsafe extern "C" fn oxidb_count(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "count", "collection": col});
     unsafe { send_request(conn, &req) }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_distinct(
+    conn: *mut OxiDbConn,
+    collection: *const c_char,
+    field: *const c_char,
+    query_json: *const c_char,
+) -> *mut c_char {
+    let col = match unsafe { cstr_to_str(collection) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let fld = match unsafe { cstr_to_str(field) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let query_str = match unsafe { cstr_to_str(query_json) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let query: serde_json::Value = match serde_json::from_str(query_str) {
+        Ok(v) => v,
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
+    };
+    let req = serde_json::json!({"cmd": "distinct", "collection": col, "field": fld, "query": query});
+    unsafe { send_request(conn, &req) }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn oxidb_compact(
     conn: *mut OxiDbConn,
@@ -279,12 +458,18 @@ pub unsafe extern "C" fn oxidb_compact(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "compact", "collection": col});
     unsafe { send_request(conn, &req) }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_compact_all(conn: *mut OxiDbConn) -> *mut c_char {
+    let req = serde_json::json!({"cmd": "compact_all"});
+    unsafe { send_request(conn, &req) }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn oxidb_create_index(
     conn: *mut OxiDbConn,
@@ -293,11 +478,11 @@ pub unsafe extern "C" fn oxidb_create_index(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let fld = match unsafe { cstr_to_str(field) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "create_index", "collection": col, "field": fld});
     unsafe { send_request(conn, &req) }
@@ -311,11 +496,11 @@ pub unsafe extern "C" fn oxidb_create_unique_index(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let fld = match unsafe { cstr_to_str(field) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "create_unique_index", "collection": col, "field": fld});
     unsafe { send_request(conn, &req) }
@@ -329,15 +514,15 @@ pub unsafe extern "C" fn oxidb_create_composite_index(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let fields_str = match unsafe { cstr_to_str(fields_json) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let fields: serde_json::Value = match serde_json::from_str(fields_str) {
         Ok(v) => v,
-        Err(_) => return ptr::null_mut(),
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
     };
     let req =
         serde_json::json!({"cmd": "create_composite_index", "collection": col, "fields": fields});
@@ -352,21 +537,39 @@ pub unsafe extern "C" fn oxidb_create_text_index(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let fields_str = match unsafe { cstr_to_str(fields_json) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let fields: serde_json::Value = match serde_json::from_str(fields_str) {
         Ok(v) => v,
-        Err(_) => return ptr::null_mut(),
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
     };
     let req =
         serde_json::json!({"cmd": "create_text_index", "collection": col, "fields": fields});
     unsafe { send_request(conn, &req) }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_create_geo_index(
+    conn: *mut OxiDbConn,
+    collection: *const c_char,
+    field: *const c_char,
+) -> *mut c_char {
+    let col = match unsafe { cstr_to_str(collection) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let fld = match unsafe { cstr_to_str(field) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let req = serde_json::json!({"cmd": "create_geo_index", "collection": col, "field": fld});
+    unsafe { send_request(conn, &req) }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn oxidb_list_indexes(
     conn: *mut OxiDbConn,
@@ -374,12 +577,112 @@ pub unsafe extern "C" fn oxidb_list_indexes(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "list_indexes", "collection": col});
     unsafe { send_request(conn, &req) }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_describe(
+    conn: *mut OxiDbConn,
+    collection: *const c_char,
+) -> *mut c_char {
+    let col = match unsafe { cstr_to_str(collection) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let req = serde_json::json!({"cmd": "describe", "collection": col});
+    unsafe { send_request(conn, &req) }
+}
+
+/// Validate a collection's writes against a JSON Schema ("null" removes it).
+/// `level` is "strict" or "warn", or NULL for strict; `coerce` != 0 converts
+/// values to the schema's types first.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_set_schema(
+    conn: *mut OxiDbConn,
+    collection: *const c_char,
+    schema_json: *const c_char,
+    level: *const c_char,
+    coerce: i32,
+) -> *mut c_char {
+    let col = match unsafe { cstr_to_str(collection) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let schema_str = match unsafe { cstr_to_str(schema_json) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let schema: serde_json::Value = match serde_json::from_str(schema_str) {
+        Ok(v) => v,
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
+    };
+    let level = if level.is_null() {
+        "strict"
+    } else {
+        match unsafe { cstr_to_str(level) } {
+            Some(s) => s,
+            None => return unsafe { fail(conn, "invalid string argument") },
+        }
+    };
+    let req = serde_json::json!({
+        "cmd": "set_schema", "collection": col, "schema": schema, "level": level, "coerce": coerce != 0
+    });
+    unsafe { send_request(conn, &req) }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_get_schema(
+    conn: *mut OxiDbConn,
+    collection: *const c_char,
+) -> *mut c_char {
+    let col = match unsafe { cstr_to_str(collection) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let req = serde_json::json!({"cmd": "get_schema", "collection": col});
+    unsafe { send_request(conn, &req) }
+}
+
+/// Fill in defaults for fields inserted documents omit: a JSON object of
+/// field paths to literals, "$now", or "$uuid" ("{}" or "null" removes them).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_set_defaults(
+    conn: *mut OxiDbConn,
+    collection: *const c_char,
+    defaults_json: *const c_char,
+) -> *mut c_char {
+    let col = match unsafe { cstr_to_str(collection) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let defaults_str = match unsafe { cstr_to_str(defaults_json) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let defaults: serde_json::Value = match serde_json::from_str(defaults_str) {
+        Ok(v) => v,
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
+    };
+    let req = serde_json::json!({"cmd": "set_defaults", "collection": col, "defaults": defaults});
+    unsafe { send_request(conn, &req) }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_get_defaults(
+    conn: *mut OxiDbConn,
+    collection: *const c_char,
+) -> *mut c_char {
+    let col = match unsafe { cstr_to_str(collection) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let req = serde_json::json!({"cmd": "get_defaults", "collection": col});
+    unsafe { send_request(conn, &req) }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn oxidb_drop_index(
     conn: *mut OxiDbConn,
@@ -388,11 +691,11 @@ pub unsafe extern "C" fn oxidb_drop_index(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let idx = match unsafe { cstr_to_str(index) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "drop_index", "collection": col, "index": idx});
     unsafe { send_request(conn, &req) }
@@ -407,11 +710,11 @@ pub unsafe extern "C" fn oxidb_text_search(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let q = match unsafe { cstr_to_str(query) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let mut req = serde_json::json!({"cmd": "text_search", "collection": col, "query": q});
     if limit > 0 {
@@ -433,7 +736,7 @@ pub unsafe extern "C" fn oxidb_create_collection(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "create_collection", "collection": col});
     unsafe { send_request(conn, &req) }
@@ -446,7 +749,7 @@ pub unsafe extern "C" fn oxidb_drop_collection(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "drop_collection", "collection": col});
     unsafe { send_request(conn, &req) }
@@ -460,15 +763,15 @@ pub unsafe extern "C" fn oxidb_aggregate(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let pipeline_str = match unsafe { cstr_to_str(pipeline_json) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let pipeline: serde_json::Value = match serde_json::from_str(pipeline_str) {
         Ok(v) => v,
-        Err(_) => return ptr::null_mut(),
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
     };
     let req =
         serde_json::json!({"cmd": "aggregate", "collection": col, "pipeline": pipeline});
@@ -486,7 +789,7 @@ pub unsafe extern "C" fn oxidb_create_bucket(
 ) -> *mut c_char {
     let b = match unsafe { cstr_to_str(bucket) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "create_bucket", "bucket": b});
     unsafe { send_request(conn, &req) }
@@ -505,7 +808,7 @@ pub unsafe extern "C" fn oxidb_delete_bucket(
 ) -> *mut c_char {
     let b = match unsafe { cstr_to_str(bucket) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "delete_bucket", "bucket": b});
     unsafe { send_request(conn, &req) }
@@ -522,15 +825,15 @@ pub unsafe extern "C" fn oxidb_put_object(
 ) -> *mut c_char {
     let b = match unsafe { cstr_to_str(bucket) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let k = match unsafe { cstr_to_str(key) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let d = match unsafe { cstr_to_str(data_b64) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let ct = unsafe { cstr_to_str(content_type) }.unwrap_or("application/octet-stream");
     let meta: serde_json::Value = unsafe { cstr_to_str(metadata_json) }
@@ -548,6 +851,133 @@ pub unsafe extern "C" fn oxidb_put_object(
     unsafe { send_request(conn, &req) }
 }
 
+/// Copy an object server-side. Pass NULL `content_type` / `metadata_json` to
+/// keep the source object's values.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_copy_object(
+    conn: *mut OxiDbConn,
+    src_bucket: *const c_char,
+    src_key: *const c_char,
+    dst_bucket: *const c_char,
+    dst_key: *const c_char,
+    content_type: *const c_char,
+    metadata_json: *const c_char,
+) -> *mut c_char {
+    let src_b = match unsafe { cstr_to_str(src_bucket) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let src_k = match unsafe { cstr_to_str(src_key) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let dst_b = match unsafe { cstr_to_str(dst_bucket) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let dst_k = match unsafe { cstr_to_str(dst_key) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let mut req = serde_json::json!({
+        "cmd": "copy_object",
+        "src_bucket": src_b,
+        "src_key": src_k,
+        "dst_bucket": dst_b,
+        "dst_key": dst_k,
+    });
+    if let Some(ct) = unsafe { cstr_to_str(content_type) } {
+        req["content_type"] = serde_json::json!(ct);
+    }
+    if let Some(meta) = unsafe { cstr_to_str(metadata_json) }
+        .and_then(|s| serde_json::from_str::<serde_json::Value>(s).ok())
+    {
+        req["metadata"] = meta;
+    }
+    unsafe { send_request(conn, &req) }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_create_multipart_upload(
+    conn: *mut OxiDbConn,
+    bucket: *const c_char,
+    key: *const c_char,
+    content_type: *const c_char,
+    metadata_json: *const c_char,
+) -> *mut c_char {
+    let b = match unsafe { cstr_to_str(bucket) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let k = match unsafe { cstr_to_str(key) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let ct = unsafe { cstr_to_str(content_type) }.unwrap_or("application/octet-stream");
+    let meta: serde_json::Value = unsafe { cstr_to_str(metadata_json) }
+        .and_then(|s| serde_json::from_str(s).ok())
+        .unwrap_or(serde_json::json!({}));
+
+    let req = serde_json::json!({
+        "cmd": "create_multipart_upload",
+        "bucket": b,
+        "key": k,
+        "content_type": ct,
+        "metadata": meta,
+    });
+    unsafe { send_request(conn, &req) }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_upload_part(
+    conn: *mut OxiDbConn,
+    upload_id: *const c_char,
+    part_number: u32,
+    data_b64: *const c_char,
+) -> *mut c_char {
+    let u = match unsafe { cstr_to_str(upload_id) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let d = match unsafe { cstr_to_str(data_b64) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let req = serde_json::json!({
+        "cmd": "upload_part",
+        "upload_id": u,
+        "part_number": part_number,
+        "data": d,
+    });
+    unsafe { send_request(conn, &req) }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_complete_multipart_upload(
+    conn: *mut OxiDbConn,
+    upload_id: *const c_char,
+) -> *mut c_char {
+    let u = match unsafe { cstr_to_str(upload_id) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let req = serde_json::json!({"cmd": "complete_multipart_upload", "upload_id": u});
+    unsafe { send_request(conn, &req) }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_abort_multipart_upload(
+    conn: *mut OxiDbConn,
+    upload_id: *const c_char,
+) -> *mut c_char {
+    let u = match unsafe { cstr_to_str(upload_id) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let req = serde_json::json!({"cmd": "abort_multipart_upload", "upload_id": u});
+    unsafe { send_request(conn, &req) }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn oxidb_get_object(
     conn: *mut OxiDbConn,
@@ -556,16 +986,103 @@ pub unsafe extern "C" fn oxidb_get_object(
 ) -> *mut c_char {
     let b = match unsafe { cstr_to_str(bucket) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let k = match unsafe { cstr_to_str(key) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "get_object", "bucket": b, "key": k});
     unsafe { send_request(conn, &req) }
 }
 
+/// Read a byte range of an object. `start`/`end` are inclusive offsets; pass
+/// a negative `start` to read the last `end` bytes, and a negative `end` to
+/// read from `start` to the end of the object.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_get_object_range(
+    conn: *mut OxiDbConn,
+    bucket: *const c_char,
+    key: *const c_char,
+    start: i64,
+    end: i64,
+) -> *mut c_char {
+    let b = match unsafe { cstr_to_str(bucket) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let k = match unsafe { cstr_to_str(key) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let mut req = serde_json::json!({"cmd": "get_object_range", "bucket": b, "key": k});
+    if start < 0 {
+        req["suffix"] = serde_json::json!(end);
+    } else {
+        req["start"] = serde_json::json!(start);
+        if end >= 0 {
+            req["end"] = serde_json::json!(end);
+        }
+    }
+    unsafe { send_request(conn, &req) }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_get_object_version(
+    conn: *mut OxiDbConn,
+    bucket: *const c_char,
+    key: *const c_char,
+    version_id: *const c_char,
+) -> *mut c_char {
+    let b = match unsafe { cstr_to_str(bucket) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let k = match unsafe { cstr_to_str(key) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let v = match unsafe { cstr_to_str(version_id) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let req = serde_json::json!({"cmd": "get_object", "bucket": b, "key": k, "version_id": v});
+    unsafe { send_request(conn, &req) }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_list_object_versions(
+    conn: *mut OxiDbConn,
+    bucket: *const c_char,
+    key: *const c_char,
+) -> *mut c_char {
+    let b = match unsafe { cstr_to_str(bucket) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let k = match unsafe { cstr_to_str(key) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let req = serde_json::json!({"cmd": "list_object_versions", "bucket": b, "key": k});
+    unsafe { send_request(conn, &req) }
+}
+
+/// Turn object versioning on (`enabled` != 0) or off for a bucket.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_enable_versioning(
+    conn: *mut OxiDbConn,
+    bucket: *const c_char,
+    enabled: i32,
+) -> *mut c_char {
+    let b = match unsafe { cstr_to_str(bucket) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let req = serde_json::json!({"cmd": "enable_versioning", "bucket": b, "enabled": enabled != 0});
+    unsafe { send_request(conn, &req) }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn oxidb_head_object(
     conn: *mut OxiDbConn,
@@ -574,11 +1091,11 @@ pub unsafe extern "C" fn oxidb_head_object(
 ) -> *mut c_char {
     let b = match unsafe { cstr_to_str(bucket) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let k = match unsafe { cstr_to_str(key) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "head_object", "bucket": b, "key": k});
     unsafe { send_request(conn, &req) }
@@ -592,11 +1109,11 @@ pub unsafe extern "C" fn oxidb_delete_object(
 ) -> *mut c_char {
     let b = match unsafe { cstr_to_str(bucket) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let k = match unsafe { cstr_to_str(key) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "delete_object", "bucket": b, "key": k});
     unsafe { send_request(conn, &req) }
@@ -611,7 +1128,7 @@ pub unsafe extern "C" fn oxidb_list_objects(
 ) -> *mut c_char {
     let b = match unsafe { cstr_to_str(bucket) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let mut req = serde_json::json!({"cmd": "list_objects", "bucket": b});
     if let Some(p) = unsafe { cstr_to_str(prefix) } {
@@ -632,7 +1149,7 @@ pub unsafe extern "C" fn oxidb_search(
 ) -> *mut c_char {
     let q = match unsafe { cstr_to_str(query) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let mut req = serde_json::json!({"cmd": "search", "query": q});
     if let Some(b) = unsafe { cstr_to_str(bucket) } {
@@ -666,6 +1183,32 @@ pub unsafe extern "C" fn oxidb_rollback_tx(conn: *mut OxiDbConn) -> *mut c_char
     unsafe { send_request(conn, &req) }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_savepoint(
+    conn: *mut OxiDbConn,
+    name: *const c_char,
+) -> *mut c_char {
+    let n = match unsafe { cstr_to_str(name) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let req = serde_json::json!({"cmd": "savepoint", "name": n});
+    unsafe { send_request(conn, &req) }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_rollback_to(
+    conn: *mut OxiDbConn,
+    name: *const c_char,
+) -> *mut c_char {
+    let n = match unsafe { cstr_to_str(name) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let req = serde_json::json!({"cmd": "rollback_to", "name": n});
+    unsafe { send_request(conn, &req) }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn oxidb_sql(
     conn: *mut OxiDbConn,
@@ -673,12 +1216,36 @@ pub unsafe extern "C" fn oxidb_sql(
 ) -> *mut c_char {
     let q = match unsafe { cstr_to_str(query) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "sql", "query": q});
     unsafe { send_request(conn, &req) }
 }
 
+/// Run a SQL statement with `params_json` bound to its placeholders: a JSON
+/// array for `?` placeholders, or an object for `:name` placeholders.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_sql_params(
+    conn: *mut OxiDbConn,
+    query: *const c_char,
+    params_json: *const c_char,
+) -> *mut c_char {
+    let q = match unsafe { cstr_to_str(query) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let params_str = match unsafe { cstr_to_str(params_json) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let params: serde_json::Value = match serde_json::from_str(params_str) {
+        Ok(v) => v,
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
+    };
+    let req = serde_json::json!({"cmd": "sql", "query": q, "params": params});
+    unsafe { send_request(conn, &req) }
+}
+
 // ---------------------------------------------------------------------------
 // Cron scheduler
 // ---------------------------------------------------------------------------
@@ -690,11 +1257,11 @@ pub unsafe extern "C" fn oxidb_create_schedule(
 ) -> *mut c_char {
     let json_str = match unsafe { cstr_to_str(schedule_json) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let mut def: serde_json::Value = match serde_json::from_str(json_str) {
         Ok(v) => v,
-        Err(_) => return ptr::null_mut(),
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
     };
     def["cmd"] = serde_json::json!("create_schedule");
     unsafe { send_request(conn, &def) }
@@ -713,12 +1280,31 @@ pub unsafe extern "C" fn oxidb_get_schedule(
 ) -> *mut c_char {
     let n = match unsafe { cstr_to_str(name) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "get_schedule", "name": n});
     unsafe { send_request(conn, &req) }
 }
 
+/// Recent runs of a schedule, most recent first. Pass `limit` <= 0 for all
+/// retained runs.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_schedule_history(
+    conn: *mut OxiDbConn,
+    name: *const c_char,
+    limit: i32,
+) -> *mut c_char {
+    let n = match unsafe { cstr_to_str(name) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let mut req = serde_json::json!({"cmd": "schedule_history", "name": n});
+    if limit > 0 {
+        req["limit"] = serde_json::json!(limit);
+    }
+    unsafe { send_request(conn, &req) }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn oxidb_delete_schedule(
     conn: *mut OxiDbConn,
@@ -726,7 +1312,7 @@ pub unsafe extern "C" fn oxidb_delete_schedule(
 ) -> *mut c_char {
     let n = match unsafe { cstr_to_str(name) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "delete_schedule", "name": n});
     unsafe { send_request(conn, &req) }
@@ -739,12 +1325,26 @@ pub unsafe extern "C" fn oxidb_enable_schedule(
 ) -> *mut c_char {
     let n = match unsafe { cstr_to_str(name) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "enable_schedule", "name": n});
     unsafe { send_request(conn, &req) }
 }
 
+/// Run a schedule's procedure now, returning its result.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_run_schedule(
+    conn: *mut OxiDbConn,
+    name: *const c_char,
+) -> *mut c_char {
+    let n = match unsafe { cstr_to_str(name) } {
+        Some(s) => s,
+        None => return unsafe { fail(conn, "invalid string argument") },
+    };
+    let req = serde_json::json!({"cmd": "run_schedule", "name": n});
+    unsafe { send_request(conn, &req) }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn oxidb_disable_schedule(
     conn: *mut OxiDbConn,
@@ -752,7 +1352,7 @@ pub unsafe extern "C" fn oxidb_disable_schedule(
 ) -> *mut c_char {
     let n = match unsafe { cstr_to_str(name) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let req = serde_json::json!({"cmd": "disable_schedule", "name": n});
     unsafe { send_request(conn, &req) }
@@ -772,11 +1372,11 @@ pub unsafe extern "C" fn oxidb_create_vector_index(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let fld = match unsafe { cstr_to_str(field) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let m = unsafe { cstr_to_str(metric) }.unwrap_or("cosine");
     let req = serde_json::json!({
@@ -799,19 +1399,19 @@ pub unsafe extern "C" fn oxidb_vector_search(
 ) -> *mut c_char {
     let col = match unsafe { cstr_to_str(collection) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let fld = match unsafe { cstr_to_str(field) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let vec_str = match unsafe { cstr_to_str(vector_json) } {
         Some(s) => s,
-        None => return ptr::null_mut(),
+        None => return unsafe { fail(conn, "invalid string argument") },
     };
     let vector: serde_json::Value = match serde_json::from_str(vec_str) {
         Ok(v) => v,
-        Err(_) => return ptr::null_mut(),
+        Err(e) => return unsafe { fail(conn, &format!("invalid JSON: {e}")) },
     };
     let mut req = serde_json::json!({
         "cmd": "vector_search",
@@ -832,3 +1432,138 @@ pub unsafe extern "C" fn oxidb_free_string(ptr: *mut c_char) {
         let _ = unsafe { CString::from_raw(ptr) };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    /// Accept one connection, answer `replies` requests with `{"ok": true}`
+    /// (or an error for an `auth_simple` without password "secret"), then
+    /// close the connection and the listener. Returns the requests received.
+    fn serve(listener: TcpListener, replies: usize) -> JoinHandle<Vec<Value>> {
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut requests = Vec::new();
+            for _ in 0..replies {
+                let mut len = [0u8; 4];
+                stream.read_exact(&mut len).unwrap();
+                let mut buf = vec![0u8; u32::from_le_bytes(len) as usize];
+                stream.read_exact(&mut buf).unwrap();
+                let req: Value = serde_json::from_slice(&buf).unwrap();
+                let resp: &[u8] = if req["cmd"] == "auth_simple" && req["password"] != "secret" {
+                    br#"{"ok":false,"error":"authentication failed"}"#
+                } else {
+                    br#"{"ok":true}"#
+                };
+                stream.write_all(&(resp.len() as u32).to_le_bytes()).unwrap();
+                stream.write_all(resp).unwrap();
+                requests.push(req);
+            }
+            requests
+        })
+    }
+
+    /// A server on a free port that answers `replies` requests.
+    fn mock_server(replies: usize) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        serve(listener, replies);
+        port
+    }
+
+    fn restart(port: u16, replies: usize) -> JoinHandle<Vec<Value>> {
+        serve(TcpListener::bind(("127.0.0.1", port)).unwrap(), replies)
+    }
+
+    fn last_error(conn: *mut OxiDbConn) -> String {
+        unsafe { CStr::from_ptr(oxidb_last_error(conn)) }.to_str().unwrap().to_string()
+    }
+
+    fn take_string(ptr: *mut c_char) -> String {
+        assert!(!ptr.is_null());
+        let s = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { oxidb_free_string(ptr) };
+        s
+    }
+
+    #[test]
+    fn test_last_error() {
+        let port = mock_server(2);
+        let conn = unsafe { oxidb_connect(c"127.0.0.1".as_ptr(), port) };
+        assert!(!conn.is_null());
+        assert_eq!(last_error(conn), "");
+
+        // Malformed document
+        let resp = unsafe { oxidb_insert(conn, c"users".as_ptr(), c"{not json".as_ptr()) };
+        assert!(resp.is_null());
+        assert!(last_error(conn).starts_with("invalid JSON"), "{}", last_error(conn));
+
+        // A successful request clears it
+        assert_eq!(take_string(unsafe { oxidb_ping(conn) }), r#"{"ok":true}"#);
+        assert_eq!(last_error(conn), "");
+
+        let resp = unsafe { oxidb_find(conn, ptr::null(), c"{}".as_ptr()) };
+        assert!(resp.is_null());
+        assert_eq!(last_error(conn), "invalid string argument");
+        assert_eq!(take_string(unsafe { oxidb_ping(conn) }), r#"{"ok":true}"#);
+
+        // The server has gone away
+        assert!(unsafe { oxidb_ping(conn) }.is_null());
+        assert!(!last_error(conn).is_empty());
+
+        unsafe { oxidb_disconnect(conn) };
+        assert_eq!(unsafe { CStr::from_ptr(oxidb_last_error(ptr::null_mut())) }, c"");
+    }
+
+    #[test]
+    fn test_auto_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = serve(listener, 2);
+        let options = cr#"{"auto_reconnect": true, "username": "app", "password": "secret"}"#;
+        let conn = unsafe { oxidb_connect_with_options(c"127.0.0.1".as_ptr(), port, options.as_ptr()) };
+        assert!(!conn.is_null());
+        take_string(unsafe { oxidb_ping(conn) });
+        let auth = json!({"cmd": "auth_simple", "username": "app", "password": "secret"});
+        assert_eq!(server.join().unwrap(), [auth.clone(), json!({"cmd": "ping"})]);
+
+        // The server restarts: the next request reconnects, logs in again and
+        // is retried
+        let server = restart(port, 3);
+        assert_eq!(take_string(unsafe { oxidb_ping(conn) }), r#"{"ok":true}"#);
+        assert_eq!(last_error(conn), "");
+        take_string(unsafe { oxidb_begin_tx(conn) });
+        assert_eq!(server.join().unwrap(), [auth.clone(), json!({"cmd": "ping"}), json!({"cmd": "begin_tx"})]);
+
+        // A request inside the lost transaction isn't retried
+        let server = restart(port, 2);
+        assert!(unsafe { oxidb_ping(conn) }.is_null());
+        assert!(last_error(conn).contains("transaction"), "{}", last_error(conn));
+        take_string(unsafe { oxidb_ping(conn) });
+        assert_eq!(server.join().unwrap(), [auth, json!({"cmd": "ping"})]);
+        unsafe { oxidb_disconnect(conn) };
+    }
+
+    #[test]
+    fn test_no_reconnect_by_default() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = serve(listener, 1);
+        let conn = unsafe { oxidb_connect_with_options(c"127.0.0.1".as_ptr(), port, ptr::null()) };
+        take_string(unsafe { oxidb_ping(conn) });
+        server.join().unwrap();
+
+        let _server = restart(port, 1);
+        assert!(unsafe { oxidb_ping(conn) }.is_null());
+        unsafe { oxidb_disconnect(conn) };
+
+        // Wrong credentials fail the connect
+        let port = mock_server(1);
+        let options = cr#"{"username": "app", "password": "wrong"}"#;
+        assert!(unsafe { oxidb_connect_with_options(c"127.0.0.1".as_ptr(), port, options.as_ptr()) }.is_null());
+    }
+}
@@ -27,7 +27,14 @@ void oxidb_close(OxiDbHandle* handle);
    Returns NULL only on internal error. */
 char* oxidb_execute(OxiDbHandle* handle, const char* cmd_json);
 
-/* Free a string returned by oxidb_execute. Safe to call with NULL. */
+/* Execute a JSON array of commands in order under a single lock.
+   Returns a JSON array with one response per command; a failed command
+   doesn't stop the rest (caller must free with oxidb_free_string).
+   Returns NULL only on internal error. */
+char* oxidb_execute_batch(OxiDbHandle* handle, const char* cmds_json);
+
+/* Free a string returned by oxidb_execute or oxidb_execute_batch.
+   Safe to call with NULL. */
 void oxidb_free_string(char* ptr);
 
 #ifdef __cplusplus
@@ -6,7 +6,7 @@ use std::sync::{Arc, Mutex};
 
 use base64::Engine;
 use oxidb::OxiDb;
-use oxidb::query::parse_find_options;
+use oxidb::query::{parse_find_and_modify_options, parse_find_options};
 use serde_json::{Value, json};
 
 // ---------------------------------------------------------------------------
@@ -32,8 +32,17 @@ fn err_bytes(msg: &str) -> Vec<u8> {
     serde_json::to_vec(&json!({ "ok": false, "error": msg })).unwrap()
 }
 
+fn error_bytes(e: &oxidb::Error) -> Vec<u8> {
+    serde_json::to_vec(&json!({ "ok": false, "error": e.to_string(), "code": e.code() })).unwrap()
+}
+
 /// Serialize find results directly from Arc references — zero Value::clone.
 fn ok_docs_bytes(docs: &[Arc<Value>]) -> Vec<u8> {
+    ok_page_bytes(docs, None)
+}
+
+/// Like `ok_docs_bytes`, with the total match count alongside the page when given.
+fn ok_page_bytes(docs: &[Arc<Value>], total: Option<usize>) -> Vec<u8> {
     let mut buf = Vec::with_capacity(docs.len() * 200 + 64);
     buf.extend_from_slice(b"{\"ok\":true,\"data\":[");
     for (i, doc) in docs.iter().enumerate() {
@@ -42,7 +51,11 @@ fn ok_docs_bytes(docs: &[Arc<Value>]) -> Vec<u8> {
         }
         serde_json::to_writer(&mut buf, doc.as_ref()).unwrap();
     }
-    buf.extend_from_slice(b"]}");
+    buf.push(b']');
+    if let Some(total) = total {
+        buf.extend_from_slice(format!(",\"total\":{total}").as_bytes());
+    }
+    buf.push(b'}');
     buf
 }
 
@@ -56,21 +69,37 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
         None => return err_bytes("missing or invalid 'cmd' field"),
     };
 
+    let resp = execute_command(db, &cmd, request, active_tx);
+    db.metrics().record_command(&cmd, resp.starts_with(b"{\"ok\":false"));
+    resp
+}
+
+fn execute_command(db: &Arc<OxiDb>, cmd: &str, request: Value, active_tx: &mut Option<u64>) -> Vec<u8> {
     let collection: Option<String> =
         request.get("collection").and_then(|v| v.as_str().map(|s| s.to_string()));
 
     let mut request = request;
 
-    match cmd.as_str() {
+    match cmd {
         "ping" => ok_bytes(json!("pong")),
 
+        "metrics" => ok_bytes(json!(db.metrics_text())),
+
         // --- Transactions ---
 
         "begin_tx" => {
             if active_tx.is_some() {
                 return err_bytes("transaction already active");
             }
-            let tx_id = db.begin_transaction();
+            let read_only = request
+                .get("read_only")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let tx_id = if read_only {
+                db.begin_read_transaction()
+            } else {
+                db.begin_transaction()
+            };
             *active_tx = Some(tx_id);
             ok_bytes(json!({ "tx_id": tx_id }))
         }
@@ -78,9 +107,9 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
         "commit_tx" => match active_tx.take() {
             Some(tx_id) => match db.commit_transaction(tx_id) {
                 Ok(()) => ok_bytes(json!("committed")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             },
-            None => err_bytes("no active transaction"),
+            None => error_bytes(&oxidb::Error::NoActiveTransaction),
         },
 
         "rollback_tx" => match active_tx.take() {
@@ -88,9 +117,39 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
                 let _ = db.rollback_transaction(tx_id);
                 ok_bytes(json!("rolled back"))
             }
-            None => err_bytes("no active transaction"),
+            None => error_bytes(&oxidb::Error::NoActiveTransaction),
         },
 
+        "savepoint" => {
+            let tx_id = match *active_tx {
+                Some(id) => id,
+                None => return error_bytes(&oxidb::Error::NoActiveTransaction),
+            };
+            let name = match request.get("name").and_then(|v| v.as_str()) {
+                Some(n) => n,
+                None => return err_bytes("missing 'name'"),
+            };
+            match db.tx_savepoint(tx_id, name) {
+                Ok(()) => ok_bytes(json!("ok")),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "rollback_to" => {
+            let tx_id = match *active_tx {
+                Some(id) => id,
+                None => return error_bytes(&oxidb::Error::NoActiveTransaction),
+            };
+            let name = match request.get("name").and_then(|v| v.as_str()) {
+                Some(n) => n,
+                None => return err_bytes("missing 'name'"),
+            };
+            match db.tx_rollback_to(tx_id, name) {
+                Ok(()) => ok_bytes(json!("rolled back")),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
         // --- CRUD ---
 
         "insert" => {
@@ -105,12 +164,12 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             if let Some(tx_id) = *active_tx {
                 match db.tx_insert(tx_id, col, doc) {
                     Ok(()) => ok_bytes(json!("buffered")),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             } else {
                 match db.insert(col, doc) {
                     Ok(id) => ok_bytes(json!({ "id": id })),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             }
         }
@@ -127,14 +186,14 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             if let Some(tx_id) = *active_tx {
                 for doc in docs {
                     if let Err(e) = db.tx_insert(tx_id, col, doc) {
-                        return err_bytes(&e.to_string());
+                        return error_bytes(&e);
                     }
                 }
                 ok_bytes(json!("buffered"))
             } else {
                 match db.insert_many(col, docs) {
                     Ok(ids) => ok_bytes(json!(ids)),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             }
         }
@@ -146,23 +205,52 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             };
             let empty = json!({});
             let query = request.get("query").unwrap_or(&empty);
+            let with_count = request.get("with_count").and_then(|v| v.as_bool()).unwrap_or(false);
             if let Some(tx_id) = *active_tx {
+                // Transactional reads ignore skip/limit, so the page is every match
                 match db.tx_find(tx_id, col, query) {
+                    Ok(docs) if with_count => {
+                        let total = docs.len();
+                        serde_json::to_vec(&json!({"ok": true, "data": docs, "total": total})).unwrap()
+                    }
                     Ok(docs) => ok_bytes(json!(docs)),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             } else {
                 let opts = match parse_find_options(&request) {
                     Ok(o) => o,
-                    Err(e) => return err_bytes(&e.to_string()),
+                    Err(e) => return error_bytes(&e),
                 };
+                if with_count {
+                    return match db.find_with_count_arcs(col, query, &opts) {
+                        Ok((arcs, total)) => ok_page_bytes(&arcs, Some(total)),
+                        Err(e) => error_bytes(&e),
+                    };
+                }
                 match db.find_with_options_arcs(col, query, &opts) {
                     Ok(arcs) => ok_docs_bytes(&arcs),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             }
         }
 
+        "explain" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            let empty = json!({});
+            let query = request.get("query").unwrap_or(&empty);
+            let opts = match parse_find_options(&request) {
+                Ok(o) => o,
+                Err(e) => return error_bytes(&e),
+            };
+            match db.explain(col, query, &opts) {
+                Ok(plan) => ok_bytes(plan),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
         "find_one" => {
             let col = match collection.as_deref() {
                 Some(c) => c,
@@ -172,7 +260,7 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             let query = request.get("query").unwrap_or(&empty);
             match db.find_one(col, query) {
                 Ok(doc) => ok_bytes(json!(doc)),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -189,15 +277,23 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
                 Some(u) => u,
                 None => return err_bytes("missing 'update'"),
             };
+            let expected_version = match request.get("expected_version").map(|v| v.as_u64()) {
+                None => None,
+                Some(Some(v)) => Some(v),
+                Some(None) => return err_bytes("'expected_version' must be a non-negative integer"),
+            };
             if let Some(tx_id) = *active_tx {
+                if expected_version.is_some() {
+                    return err_bytes("'expected_version' can't be used in a transaction");
+                }
                 match db.tx_update(tx_id, col, query, update) {
                     Ok(()) => ok_bytes(json!("buffered")),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             } else {
-                match db.update(col, query, update) {
+                match db.update_with_version(col, query, update, expected_version) {
                     Ok(count) => ok_bytes(json!({ "modified": count })),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             }
         }
@@ -215,9 +311,31 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
                 Some(u) => u,
                 None => return err_bytes("missing 'update'"),
             };
-            match db.update_one(col, query, update) {
+            let expected_version = match request.get("expected_version").map(|v| v.as_u64()) {
+                None => None,
+                Some(Some(v)) => Some(v),
+                Some(None) => return err_bytes("'expected_version' must be a non-negative integer"),
+            };
+            match db.update_one_with_version(col, query, update, expected_version) {
                 Ok(count) => ok_bytes(json!({ "modified": count })),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "find_and_modify" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            let empty = json!({});
+            let query = request.get("query").unwrap_or(&empty);
+            let opts = match parse_find_and_modify_options(&request) {
+                Ok(o) => o,
+                Err(e) => return error_bytes(&e),
+            };
+            match db.find_and_modify(col, query, request.get("update"), &opts) {
+                Ok(doc) => ok_bytes(json!(doc)),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -233,12 +351,12 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             if let Some(tx_id) = *active_tx {
                 match db.tx_delete(tx_id, col, query) {
                     Ok(()) => ok_bytes(json!("buffered")),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             } else {
                 match db.delete(col, query) {
                     Ok(count) => ok_bytes(json!({ "deleted": count })),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             }
         }
@@ -254,7 +372,26 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             };
             match db.delete_one(col, query) {
                 Ok(count) => ok_bytes(json!({ "deleted": count })),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "bulk_write" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            let ops = match request.get("ops").map(oxidb::bulk::parse_bulk_ops) {
+                Some(Ok(ops)) => ops,
+                Some(Err(e)) => return error_bytes(&e),
+                None => return err_bytes("missing 'ops'"),
+            };
+            if active_tx.is_some() {
+                return err_bytes("bulk_write can't be used in a transaction");
+            }
+            match db.bulk_write(col, ops) {
+                Ok(results) => ok_bytes(json!(results.iter().map(|r| r.to_json()).collect::<Vec<_>>())),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -267,12 +404,29 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             let query = request.get("query").unwrap_or(&empty);
             match db.count(col, query) {
                 Ok(n) => ok_bytes(json!({ "count": n })),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
         // --- Indexes ---
 
+        "distinct" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            let field = match request.get("field").and_then(|v| v.as_str()) {
+                Some(f) => f,
+                None => return err_bytes("missing 'field'"),
+            };
+            let empty = json!({});
+            let query = request.get("query").unwrap_or(&empty);
+            match db.distinct(col, field, query) {
+                Ok(values) => ok_bytes(json!(values)),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
         "create_index" => {
             let col = match collection.as_deref() {
                 Some(c) => c,
@@ -282,9 +436,19 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
                 Some(f) => f,
                 None => return err_bytes("missing 'field'"),
             };
-            match db.create_index(col, field) {
+            let flag = |key: &str| request.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+            let created = if flag("sparse") {
+                db.create_sparse_index(col, field)
+            } else {
+                db.create_index(col, field)
+            };
+            let result = match created {
+                Ok(()) if flag("descending") => db.create_descending_index(col, field),
+                other => other,
+            };
+            match result {
                 Ok(()) => ok_bytes(json!("index created")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -299,7 +463,7 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             };
             match db.create_unique_index(col, field) {
                 Ok(()) => ok_bytes(json!("unique index created")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -321,7 +485,7 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             };
             match db.create_composite_index(col, fields) {
                 Ok(name) => ok_bytes(json!({ "index": name })),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -343,7 +507,22 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             };
             match db.create_text_index(col, fields) {
                 Ok(()) => ok_bytes(json!("text index created")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "create_geo_index" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            let field = match request.get("field").and_then(|v| v.as_str()) {
+                Some(f) => f,
+                None => return err_bytes("missing 'field'"),
+            };
+            match db.create_geo_index(col, field) {
+                Ok(()) => ok_bytes(json!("geo index created")),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -354,7 +533,75 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             };
             match db.list_indexes(col) {
                 Ok(indexes) => ok_bytes(json!(indexes)),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "set_schema" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            let schema = match request.get_mut("schema").map(Value::take) {
+                Some(Value::Null) => None,
+                Some(schema) => {
+                    let level = match request.get("level").and_then(|v| v.as_str()) {
+                        Some(level) => match oxidb::ValidationLevel::parse(level) {
+                            Ok(level) => level,
+                            Err(e) => return error_bytes(&e),
+                        },
+                        None => oxidb::ValidationLevel::Strict,
+                    };
+                    let coerce = request.get("coerce").and_then(|v| v.as_bool()).unwrap_or(false);
+                    match oxidb::CollectionSchema::new(schema, level, coerce) {
+                        Ok(schema) => Some(schema),
+                        Err(e) => return error_bytes(&e),
+                    }
+                }
+                None => return err_bytes("missing 'schema'"),
+            };
+            let msg = if schema.is_some() { "schema set" } else { "schema removed" };
+            match db.set_collection_schema(col, schema) {
+                Ok(()) => ok_bytes(json!(msg)),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "get_schema" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            match db.get_collection_schema(col) {
+                Ok(schema) => ok_bytes(json!(schema)),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "set_defaults" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            let defaults = match request.get_mut("defaults").map(Value::take) {
+                Some(Value::Null) => Ok(oxidb::FieldDefaults::default()),
+                Some(spec) => oxidb::FieldDefaults::new(spec),
+                None => return err_bytes("missing 'defaults'"),
+            };
+            match defaults.and_then(|defaults| db.set_collection_defaults(col, defaults)) {
+                Ok(()) => ok_bytes(json!("defaults set")),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "get_defaults" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            match db.get_collection_defaults(col) {
+                Ok(defaults) => ok_bytes(json!(defaults)),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -369,7 +616,7 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             };
             match db.drop_index(col, index) {
                 Ok(()) => ok_bytes(json!("index dropped")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -388,7 +635,7 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
                 .unwrap_or(10) as usize;
             match db.text_search(col, query, limit) {
                 Ok(results) => ok_bytes(json!(results)),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -401,7 +648,7 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             };
             match db.create_collection(col) {
                 Ok(()) => ok_bytes(json!("collection created")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -417,7 +664,7 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             };
             match db.drop_collection(col) {
                 Ok(()) => ok_bytes(json!("collection dropped")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -432,10 +679,35 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
                     "new_size": stats.new_size,
                     "docs_kept": stats.docs_kept
                 })),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
+        "compact_all" => match db.compact_all() {
+            Ok(stats) => {
+                let collections: Vec<Value> = stats
+                    .collections
+                    .iter()
+                    .map(|(name, result)| match result {
+                        Ok(s) => json!({
+                            "collection": name,
+                            "old_size": s.old_size,
+                            "new_size": s.new_size,
+                            "docs_kept": s.docs_kept
+                        }),
+                        Err(e) => json!({"collection": name, "error": e.to_string(), "code": e.code()}),
+                    })
+                    .collect();
+                ok_bytes(json!({
+                    "old_size": stats.total.old_size,
+                    "new_size": stats.total.new_size,
+                    "docs_kept": stats.total.docs_kept,
+                    "collections": collections
+                }))
+            }
+            Err(e) => error_bytes(&e),
+        },
+
         "aggregate" => {
             let col = match collection.as_deref() {
                 Some(c) => c,
@@ -447,7 +719,7 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             };
             match db.aggregate(col, pipeline) {
                 Ok(docs) => ok_bytes(json!(docs)),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -460,7 +732,7 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             };
             match db.create_bucket(bucket) {
                 Ok(()) => ok_bytes(json!("bucket created")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -476,7 +748,7 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             };
             match db.delete_bucket(bucket) {
                 Ok(()) => ok_bytes(json!("bucket deleted")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -512,7 +784,112 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
                 .unwrap_or_default();
             match db.put_object(bucket, key, &data, content_type, metadata) {
                 Ok(meta) => ok_bytes(meta),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "copy_object" => {
+            let src_bucket = match request.get("src_bucket").and_then(|v| v.as_str()) {
+                Some(b) => b,
+                None => return err_bytes("missing 'src_bucket'"),
+            };
+            let src_key = match request.get("src_key").and_then(|v| v.as_str()) {
+                Some(k) => k,
+                None => return err_bytes("missing 'src_key'"),
+            };
+            let dst_bucket = request
+                .get("dst_bucket")
+                .and_then(|v| v.as_str())
+                .unwrap_or(src_bucket);
+            let dst_key = match request.get("dst_key").and_then(|v| v.as_str()) {
+                Some(k) => k,
+                None => return err_bytes("missing 'dst_key'"),
+            };
+            let content_type = request.get("content_type").and_then(|v| v.as_str());
+            let metadata: Option<HashMap<String, String>> = request
+                .get("metadata")
+                .and_then(|v| v.as_object())
+                .map(|obj| {
+                    obj.iter()
+                        .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
+                        .collect()
+                });
+            match db.copy_object(src_bucket, src_key, dst_bucket, dst_key, content_type, metadata) {
+                Ok(meta) => ok_bytes(meta),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "create_multipart_upload" => {
+            let bucket = match request.get("bucket").and_then(|v| v.as_str()) {
+                Some(b) => b,
+                None => return err_bytes("missing 'bucket'"),
+            };
+            let key = match request.get("key").and_then(|v| v.as_str()) {
+                Some(k) => k,
+                None => return err_bytes("missing 'key'"),
+            };
+            let content_type = request
+                .get("content_type")
+                .and_then(|v| v.as_str())
+                .unwrap_or("application/octet-stream");
+            let metadata: HashMap<String, String> = request
+                .get("metadata")
+                .and_then(|v| v.as_object())
+                .map(|obj| {
+                    obj.iter()
+                        .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
+                        .collect()
+                })
+                .unwrap_or_default();
+            match db.create_multipart_upload(bucket, key, content_type, metadata) {
+                Ok(upload_id) => ok_bytes(json!({"upload_id": upload_id})),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "upload_part" => {
+            let upload_id = match request.get("upload_id").and_then(|v| v.as_str()) {
+                Some(u) => u,
+                None => return err_bytes("missing 'upload_id'"),
+            };
+            let part_number = match request.get("part_number").and_then(|v| v.as_u64()) {
+                Some(n) if n <= u32::MAX as u64 => n as u32,
+                _ => return err_bytes("missing or invalid 'part_number'"),
+            };
+            let data_b64 = match request.get("data").and_then(|v| v.as_str()) {
+                Some(d) => d,
+                None => return err_bytes("missing 'data' (base64)"),
+            };
+            let data = match base64::engine::general_purpose::STANDARD.decode(data_b64) {
+                Ok(d) => d,
+                Err(e) => return err_bytes(&format!("invalid base64: {e}")),
+            };
+            match db.upload_part(upload_id, part_number, &data) {
+                Ok(etag) => ok_bytes(json!({"part_number": part_number, "etag": etag})),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "complete_multipart_upload" => {
+            let upload_id = match request.get("upload_id").and_then(|v| v.as_str()) {
+                Some(u) => u,
+                None => return err_bytes("missing 'upload_id'"),
+            };
+            match db.complete_multipart_upload(upload_id) {
+                Ok(meta) => ok_bytes(meta),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "abort_multipart_upload" => {
+            let upload_id = match request.get("upload_id").and_then(|v| v.as_str()) {
+                Some(u) => u,
+                None => return err_bytes("missing 'upload_id'"),
+            };
+            match db.abort_multipart_upload(upload_id) {
+                Ok(()) => ok_bytes(json!("upload aborted")),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -525,7 +902,11 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
                 Some(k) => k,
                 None => return err_bytes("missing 'key'"),
             };
-            match db.get_object(bucket, key) {
+            let result = match request.get("version_id").and_then(|v| v.as_str()) {
+                Some(version_id) => db.get_object_version(bucket, key, version_id),
+                None => db.get_object(bucket, key),
+            };
+            match result {
                 Ok((data, meta)) => {
                     let encoded = base64::engine::general_purpose::STANDARD.encode(&data);
                     ok_bytes(json!({
@@ -533,7 +914,68 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
                         "metadata": meta,
                     }))
                 }
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "get_object_range" => {
+            let bucket = match request.get("bucket").and_then(|v| v.as_str()) {
+                Some(b) => b,
+                None => return err_bytes("missing 'bucket'"),
+            };
+            let key = match request.get("key").and_then(|v| v.as_str()) {
+                Some(k) => k,
+                None => return err_bytes("missing 'key'"),
+            };
+            let start = request.get("start").and_then(|v| v.as_u64());
+            let end = request.get("end").and_then(|v| v.as_u64());
+            let suffix = request.get("suffix").and_then(|v| v.as_u64());
+            let (start, end) = match (start, suffix) {
+                (Some(_), Some(_)) => return err_bytes("'start' and 'suffix' are mutually exclusive"),
+                (Some(s), None) => (Some(s), end),
+                (None, Some(n)) => (None, Some(n)),
+                (None, None) => return err_bytes("missing 'start' or 'suffix'"),
+            };
+            match db.get_object_range(bucket, key, start, end) {
+                Ok((range, meta)) => {
+                    let encoded = base64::engine::general_purpose::STANDARD.encode(&range.data);
+                    ok_bytes(json!({
+                        "content": encoded,
+                        "content_range": format!("bytes {}-{}/{}", range.start, range.end, range.total_size),
+                        "metadata": meta,
+                    }))
+                }
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "list_object_versions" => {
+            let bucket = match request.get("bucket").and_then(|v| v.as_str()) {
+                Some(b) => b,
+                None => return err_bytes("missing 'bucket'"),
+            };
+            let key = match request.get("key").and_then(|v| v.as_str()) {
+                Some(k) => k,
+                None => return err_bytes("missing 'key'"),
+            };
+            match db.list_object_versions(bucket, key) {
+                Ok(list) => ok_bytes(json!(list)),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "enable_versioning" => {
+            let bucket = match request.get("bucket").and_then(|v| v.as_str()) {
+                Some(b) => b,
+                None => return err_bytes("missing 'bucket'"),
+            };
+            let enabled = request
+                .get("enabled")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(true);
+            match db.set_bucket_versioning(bucket, enabled) {
+                Ok(()) => ok_bytes(json!({"bucket": bucket, "versioning": enabled})),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -548,7 +990,7 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             };
             match db.head_object(bucket, key) {
                 Ok(meta) => ok_bytes(meta),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -563,7 +1005,7 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
             };
             match db.delete_object(bucket, key) {
                 Ok(()) => ok_bytes(json!("object deleted")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -579,7 +1021,7 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
                 .map(|n| n as usize);
             match db.list_objects(bucket, prefix, limit) {
                 Ok(list) => ok_bytes(json!(list)),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -593,9 +1035,13 @@ fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>)
                 .get("limit")
                 .and_then(|v| v.as_u64())
                 .unwrap_or(10) as usize;
-            match db.search(bucket, query, limit) {
+            let offset = request
+                .get("offset")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0) as usize;
+            match db.search(bucket, query, offset, limit) {
                 Ok(results) => ok_bytes(json!(results)),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -724,13 +1170,171 @@ pub unsafe extern "C" fn oxidb_execute(
     result_to_cstring(response)
 }
 
-/// Free a string returned by `oxidb_execute`. Safe to call with NULL.
+/// Execute a JSON array of commands in order, holding the handle's lock for the
+/// whole batch. Returns a JSON array with one response per command (caller must
+/// free with `oxidb_free_string`). A failing command doesn't stop the rest; its
+/// entry is an error response. Returns NULL only on invalid input.
 ///
 /// # Safety
-/// `ptr` must be a pointer returned by `oxidb_execute`, or NULL.
+/// `handle` must be a valid handle from `oxidb_open`. `cmds_json` must be a valid
+/// null-terminated C string containing a JSON array.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn oxidb_execute_batch(
+    handle: *mut Handle,
+    cmds_json: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() {
+        return result_to_cstring(err_bytes("null handle"));
+    }
+    let h = unsafe { &*(handle as *mut OxiDbHandle) };
+
+    let cmds_str = match unsafe { cstr_to_str(cmds_json) } {
+        Some(s) => s,
+        None => return result_to_cstring(err_bytes("invalid command string")),
+    };
+
+    let requests = match serde_json::from_str(cmds_str) {
+        Ok(Value::Array(requests)) => requests,
+        Ok(_) => return result_to_cstring(err_bytes("expected a JSON array of commands")),
+        Err(e) => return result_to_cstring(err_bytes(&format!("invalid JSON: {e}"))),
+    };
+
+    let mut active_tx = h.active_tx.lock().unwrap();
+    let mut buf = Vec::with_capacity(requests.len() * 64 + 2);
+    buf.push(b'[');
+    for (i, request) in requests.into_iter().enumerate() {
+        if i > 0 {
+            buf.push(b',');
+        }
+        buf.extend_from_slice(&handle_request(&h.db, request, &mut active_tx));
+    }
+    buf.push(b']');
+    result_to_cstring(buf)
+}
+
+/// Free a string returned by `oxidb_execute` or `oxidb_execute_batch`. Safe to
+/// call with NULL.
+///
+/// # Safety
+/// `ptr` must be a pointer returned by `oxidb_execute` or `oxidb_execute_batch`,
+/// or NULL.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn oxidb_free_string(ptr: *mut c_char) {
     if !ptr.is_null() {
         let _ = unsafe { CString::from_raw(ptr) };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(dir: &tempfile::TempDir) -> *mut Handle {
+        let path = CString::new(dir.path().to_str().unwrap()).unwrap();
+        let handle = unsafe { oxidb_open(path.as_ptr()) };
+        assert!(!handle.is_null());
+        handle
+    }
+
+    fn execute_batch(handle: *mut Handle, cmds: &str) -> Value {
+        let cmds = CString::new(cmds).unwrap();
+        let ptr = unsafe { oxidb_execute_batch(handle, cmds.as_ptr()) };
+        assert!(!ptr.is_null());
+        let resp = serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { oxidb_free_string(ptr) };
+        resp
+    }
+
+    #[test]
+    fn test_execute_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let handle = open(&dir);
+        let cmds = json!([
+            {"cmd": "insert", "collection": "users", "doc": {"name": "Alice", "age": 30}},
+            {"cmd": "insert", "collection": "users", "doc": {"name": "Bob", "age": 25}},
+            {"cmd": "nope"},
+            {"cmd": "find", "collection": "users", "query": {"age": {"$gt": 26}}},
+            {"cmd": "insert", "collection": "users"},
+            {"cmd": "count", "collection": "users"},
+        ]);
+        let resp = execute_batch(handle, &cmds.to_string());
+        let resp = resp.as_array().unwrap();
+        assert_eq!(resp.len(), 6);
+        assert_eq!(resp[0]["ok"], true);
+        assert_eq!(resp[1]["ok"], true);
+        assert_eq!(resp[2], json!({"ok": false, "error": "unknown command: nope"}));
+        let found = resp[3]["data"].as_array().unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0]["name"], "Alice");
+        assert_eq!(resp[4]["ok"], false);
+        assert_eq!(resp[5]["data"]["count"], 2);
+        unsafe { oxidb_close(handle) };
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let dir = tempfile::tempdir().unwrap();
+        let handle = open(&dir);
+        let cmds = json!([
+            {"cmd": "create_unique_index", "collection": "users", "field": "email"},
+            {"cmd": "insert", "collection": "users", "doc": {"email": "a@b.c"}},
+            {"cmd": "insert", "collection": "users", "doc": {"email": "a@b.c"}},
+            {"cmd": "rollback_tx"},
+            {"cmd": "drop_index", "collection": "users", "index": "nope"},
+            {"cmd": "find", "collection": "users"},
+        ]);
+        let resp = execute_batch(handle, &cmds.to_string());
+        let codes: Vec<&Value> = resp.as_array().unwrap().iter().map(|r| &r["code"]).collect();
+        assert_eq!(
+            codes,
+            [
+                &Value::Null,
+                &Value::Null,
+                &json!("unique_violation"),
+                &json!("no_active_tx"),
+                &json!("index_not_found"),
+                &Value::Null,
+            ]
+        );
+        assert_eq!(resp[2]["ok"], false);
+        assert!(resp[2]["error"].as_str().unwrap().contains("unique constraint"));
+        unsafe { oxidb_close(handle) };
+    }
+
+    #[test]
+    fn test_execute_batch_transaction_and_bad_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let handle = open(&dir);
+        let cmds = json!([
+            {"cmd": "begin_tx"},
+            {"cmd": "insert", "collection": "items", "doc": {"n": 1}},
+            {"cmd": "commit_tx"},
+            {"cmd": "find", "collection": "items", "query": {}},
+        ]);
+        let resp = execute_batch(handle, &cmds.to_string());
+        assert!(resp.as_array().unwrap().iter().all(|r| r["ok"] == true));
+        assert_eq!(resp[3]["data"].as_array().unwrap().len(), 1);
+
+        assert_eq!(execute_batch(handle, "[]"), json!([]));
+        assert_eq!(execute_batch(handle, "{\"cmd\": \"ping\"}")["ok"], false);
+        assert_eq!(execute_batch(handle, "not json")["ok"], false);
+        unsafe { oxidb_close(handle) };
+    }
+
+    #[test]
+    fn test_metrics() {
+        let dir = tempfile::tempdir().unwrap();
+        let handle = open(&dir);
+        let cmds = json!([
+            {"cmd": "insert", "collection": "items", "doc": {"n": 1}},
+            {"cmd": "insert", "collection": "items"},
+            {"cmd": "metrics"},
+        ]);
+        let resp = execute_batch(handle, &cmds.to_string());
+        let text = resp[2]["data"].as_str().unwrap();
+        assert!(text.contains("oxidb_commands_total{command=\"insert\"} 2\n"));
+        assert!(text.contains("oxidb_command_errors_total{command=\"insert\"} 1\n"));
+        assert!(text.contains("oxidb_collections 1\n"));
+        unsafe { oxidb_close(handle) };
+    }
+}
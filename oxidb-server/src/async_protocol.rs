@@ -2,28 +2,34 @@ use std::io;
 
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-/// Read a length-prefixed message: [u32 LE length][payload bytes].
+use crate::protocol::{self, Compression};
+
+/// Read a length-prefixed message: [u32 LE length][payload bytes]. A gzipped
+/// payload is decompressed before it's returned.
 pub async fn read_message<R: AsyncReadExt + Unpin>(reader: &mut R) -> io::Result<Vec<u8>> {
     let mut len_buf = [0u8; 4];
     reader.read_exact(&mut len_buf).await?;
-    let len = u32::from_le_bytes(len_buf) as usize;
-
-    if len > 16 * 1024 * 1024 {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "message too large (>16 MiB)",
-        ));
-    }
+    let (len, gzipped) = protocol::parse_prefix(len_buf)?;
 
     let mut buf = vec![0u8; len];
     reader.read_exact(&mut buf).await?;
-    Ok(buf)
+    if gzipped { protocol::gunzip(&buf) } else { Ok(buf) }
 }
 
 /// Write a length-prefixed message: [u32 LE length][payload bytes].
 pub async fn write_message<W: AsyncWriteExt + Unpin>(writer: &mut W, data: &[u8]) -> io::Result<()> {
-    let len = (data.len() as u32).to_le_bytes();
-    writer.write_all(&len).await?;
-    writer.write_all(data).await?;
+    write_message_with(writer, data, Compression::None).await
+}
+
+/// Write a length-prefixed message, gzipping the payload if `compression`
+/// is on and the payload is large enough to be worth it.
+pub async fn write_message_with<W: AsyncWriteExt + Unpin>(
+    writer: &mut W,
+    data: &[u8],
+    compression: Compression,
+) -> io::Result<()> {
+    let (prefix, body) = protocol::frame(data, compression);
+    writer.write_all(&prefix.to_le_bytes()).await?;
+    writer.write_all(&body).await?;
     writer.flush().await
 }
@@ -1,21 +1,26 @@
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use serde_json::{Value, json};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
 
+use openraft::error::{ClientWriteError, RaftError};
 use oxidb::OxiDb;
 
-use crate::async_protocol::{read_message, write_message};
+use crate::async_protocol::{read_message, write_message, write_message_with};
 use crate::audit::{self, AuditEvent, AuditLog};
 use crate::auth::{Role, UserStore};
 use crate::handler;
+use crate::protocol::{self, Codec, Compression};
 use crate::raft::management;
 use crate::raft::types::{OxiDbRequest, OxiDbResponse, OxiRaft};
+use crate::ratelimit::{self, RateLimiter};
 use crate::rbac;
 use crate::scram::ScramState;
-use crate::session::Session;
+use crate::session::{Session, SessionLimits};
+use crate::slowlog::SlowQueryLog;
+use crate::tls::{CertIdentity, ClientCertRoles};
 
 /// Shared server state passed to each async connection handler.
 pub struct ServerState {
@@ -23,8 +28,17 @@ pub struct ServerState {
     pub user_store: Option<Arc<Mutex<UserStore>>>,
     pub audit_log: Option<Arc<AuditLog>>,
     pub auth_enabled: bool,
+    pub session_limits: SessionLimits,
+    pub rate_limiter: Option<RateLimiter>,
+    pub client_cert_roles: ClientCertRoles,
+    /// SCRAM channel binding data when connections use TLS.
+    pub tls_channel_binding: Option<Vec<u8>>,
     /// Raft node — `None` in standalone mode.
     pub raft: Option<Arc<OxiRaft>>,
+    /// Address peers reach this node's Raft port at, registered for it by
+    /// `raft_init`.
+    pub raft_addr: String,
+    pub slow_query_log: Option<Arc<SlowQueryLog>>,
 }
 
 /// Handle a plain TCP connection.
@@ -38,7 +52,7 @@ pub async fn handle_connection(stream: TcpStream, state: Arc<ServerState>, idle_
     stream.set_nodelay(true).ok();
 
     let (reader, writer) = tokio::io::split(stream);
-    handle_stream(reader, writer, state, &peer, idle_timeout).await;
+    handle_stream(reader, writer, state, &peer, idle_timeout, None).await;
 
     eprintln!("client disconnected: {peer}");
 }
@@ -66,8 +80,9 @@ pub async fn handle_tls_connection(
         }
     };
 
+    let identity = state.client_cert_roles.identify(tls_stream.get_ref().1.peer_certificates());
     let (reader, writer) = tokio::io::split(tls_stream);
-    handle_stream(reader, writer, state, &peer, idle_timeout).await;
+    handle_stream(reader, writer, state, &peer, idle_timeout, identity).await;
 
     eprintln!("client disconnected: {peer}");
 }
@@ -79,15 +94,21 @@ async fn handle_stream<R, W>(
     state: Arc<ServerState>,
     peer: &str,
     idle_timeout: Duration,
+    cert_identity: Option<CertIdentity>,
 ) where
     R: AsyncRead + Unpin + Send + 'static,
     W: AsyncWrite + Unpin + Send + 'static,
 {
     let mut active_tx: Option<u64> = None;
     let mut session = Session::new();
+    let mut codec = Codec::Json;
+    let mut compression = Compression::None;
 
     if !state.auth_enabled {
         session.set_authenticated("anonymous".to_string(), Role::Admin);
+    } else if let Some(identity) = cert_identity {
+        eprintln!("{peer} authenticated by client certificate as {} ({})", identity.name, identity.role);
+        session.set_authenticated_as(identity.name, &identity.role);
     }
 
     loop {
@@ -114,19 +135,53 @@ async fn handle_stream<R, W>(
             }
         };
 
-        let request: Value = match serde_json::from_slice(&msg) {
+        let request = match codec.decode(&msg) {
             Ok(v) => v,
             Err(e) => {
-                let resp = json!({"ok": false, "error": format!("invalid JSON: {e}")});
-                let _ = write_message(&mut writer, resp.to_string().as_bytes()).await;
+                let _ = write_message(&mut writer, &codec.encode(&handler::err_bytes(&e))).await;
                 continue;
             }
         };
 
+        // Switch body codec; the reply goes out in the codec the request came in.
+        if let Some(requested) = protocol::codec_request(&request) {
+            let resp = match requested {
+                Ok(c) => handler::ok_bytes(json!({"codec": c.name()})),
+                Err(msg) => handler::err_bytes(msg),
+            };
+            let resp = handler::with_request_id(resp, request.get("request_id"));
+            if let Err(e) = write_message(&mut writer, &codec.encode(&resp)).await {
+                eprintln!("write error to {peer}: {e}");
+                break;
+            }
+            if let Ok(c) = requested {
+                codec = c;
+            }
+            continue;
+        }
+
+        if let Some(requested) = protocol::compression_request(&request) {
+            let resp = match requested {
+                Ok(c) => {
+                    compression = c;
+                    handler::ok_bytes(json!({"compression": c.name()}))
+                }
+                Err(msg) => handler::err_bytes(msg),
+            };
+            let resp = handler::with_request_id(resp, request.get("request_id"));
+            if let Err(e) = write_message(&mut writer, &codec.encode(&resp)).await {
+                eprintln!("write error to {peer}: {e}");
+                break;
+            }
+            continue;
+        }
+
+        let id = request.get("request_id").cloned();
         let resp_bytes =
             dispatch_request(request, &state, &mut session, &mut active_tx, peer).await;
+        let resp_bytes = handler::with_request_id(resp_bytes, id.as_ref());
 
-        if let Err(e) = write_message(&mut writer, &resp_bytes).await {
+        if let Err(e) = write_message_with(&mut writer, &codec.encode(&resp_bytes), compression).await {
             eprintln!("write error to {peer}: {e}");
             break;
         }
@@ -144,7 +199,7 @@ async fn dispatch_request(
     state: &ServerState,
     session: &mut Session,
     active_tx: &mut Option<u64>,
-    _peer: &str,
+    peer: &str,
 ) -> Vec<u8> {
     let cmd = request
         .get("cmd")
@@ -157,6 +212,50 @@ async fn dispatch_request(
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
 
+    // Health probes skip session, rate-limit and auth checks. A clustered
+    // node is only ready while it belongs to a quorum with a known leader.
+    if matches!(cmd.as_str(), "healthz" | "readyz") {
+        let not_ready = match (&state.raft, cmd.as_str()) {
+            (Some(raft), "readyz") => management::not_ready_reason(raft),
+            _ => None,
+        };
+        if let Some(reason) = not_ready {
+            return handler::err_code_bytes(&reason, "not_ready");
+        }
+        return handler::handle_request(&state.db, request, active_tx);
+    }
+
+    // ---------------------------------------------------------------
+    // Session expiry and logout
+    // ---------------------------------------------------------------
+    if state.auth_enabled && session.check_expiry(&state.session_limits, Instant::now()) {
+        if let Some(tx_id) = active_tx.take() {
+            let _ = state.db.rollback_transaction(tx_id);
+        }
+        if !matches!(cmd.as_str(), "ping" | "authenticate" | "auth_simple" | "auth_apikey") {
+            return handler::err_bytes("session expired: authenticate again");
+        }
+    }
+    if cmd == "logout" {
+        if let Some(tx_id) = active_tx.take() {
+            let _ = state.db.rollback_transaction(tx_id);
+        }
+        log_audit(state, session, &cmd, None, "ok", "");
+        session.logout();
+        return handler::ok_bytes(json!("logged out"));
+    }
+
+    // ---------------------------------------------------------------
+    // Rate limiting
+    // ---------------------------------------------------------------
+    if let Some(limiter) = &state.rate_limiter {
+        let user = if state.auth_enabled { session.username.as_deref() } else { None };
+        if !limiter.check(&ratelimit::client_key(user, peer), Instant::now()) {
+            log_audit(state, session, &cmd, collection.as_deref(), "denied", "rate limit exceeded");
+            return handler::err_code_bytes("rate limit exceeded", "rate_limited");
+        }
+    }
+
     // ---------------------------------------------------------------
     // Authentication flow (SCRAM-SHA-256)
     // ---------------------------------------------------------------
@@ -164,39 +263,46 @@ async fn dispatch_request(
         return handle_auth(&cmd, &request, state, session);
     }
 
+    // ---------------------------------------------------------------
+    // Forced password change
+    // ---------------------------------------------------------------
+    if state.auth_enabled && rbac::password_change_required(session, &cmd) {
+        log_audit(state, session, &cmd, collection.as_deref(), "denied", "");
+        return handler::err_bytes("password change required: run 'change_password' first");
+    }
+
     // ---------------------------------------------------------------
     // RBAC check
     // ---------------------------------------------------------------
-    if state.auth_enabled {
-        if let Some(role) = session.role() {
-            let is_user_cmd = matches!(
-                cmd.as_str(),
-                "create_user" | "drop_user" | "update_user" | "list_users"
-            );
-            let permitted = if is_user_cmd {
-                role == Role::Admin
-            } else {
-                rbac::is_permitted(role, &cmd)
-            };
+    if state.auth_enabled && !rbac::session_permitted(session, state.user_store.as_deref(), &cmd) {
+        log_audit(state, session, &cmd, collection.as_deref(), "denied", "");
+        return handler::err_bytes(&format!(
+            "permission denied: role '{}' cannot execute '{}'",
+            session.role_name(),
+            cmd
+        ));
+    }
 
-            if !permitted {
-                log_audit(state, session, &cmd, collection.as_deref(), "denied", "");
-                return handler::err_bytes(&format!(
-                    "permission denied: role '{}' cannot execute '{}'",
-                    role.as_str(),
-                    cmd
-                ));
-            }
-        }
+    // ---------------------------------------------------------------
+    // Bucket ACLs
+    // ---------------------------------------------------------------
+    let bucket_check = match state.auth_enabled {
+        true => rbac::bucket_permitted(&state.db, session, &cmd, &request),
+        false => Ok(()),
+    };
+    if let Err(msg) = bucket_check {
+        log_audit(state, session, &cmd, None, "denied", &msg);
+        return handler::err_bytes(&msg);
     }
+    let request = rbac::with_bucket_owner(session, &cmd, request);
 
     // ---------------------------------------------------------------
     // Raft management commands (cluster mode only)
     // ---------------------------------------------------------------
     if let Some(raft) = &state.raft {
         match cmd.as_str() {
-            "raft_init" | "raft_add_learner" | "raft_change_membership" | "raft_metrics" => {
-                let resp = management::handle_raft_command(&cmd, &request, raft).await;
+            "raft_init" | "raft_add_learner" | "raft_change_membership" | "raft_remove_node" | "raft_metrics" => {
+                let resp = management::handle_raft_command(&cmd, &request, raft, &state.raft_addr).await;
                 log_audit(state, session, &cmd, None, "ok", "");
                 return resp;
             }
@@ -208,39 +314,91 @@ async fn dispatch_request(
     // Handle user management commands
     // ---------------------------------------------------------------
     if let Some(user_store) = &state.user_store {
+        if cmd == "change_password" {
+            let resp_bytes = handler::handle_change_password(session, &request, user_store);
+            log_audit(state, session, &cmd, None, "ok", "");
+            return resp_bytes;
+        }
         if let Some(resp_bytes) = handler::handle_user_command(&cmd, &request, user_store) {
             log_audit(state, session, &cmd, None, "ok", "");
             return resp_bytes;
         }
     }
 
+    // Audit entries are per node: this queries the local log only.
+    if cmd == "query_audit" {
+        let resp_bytes = handler::handle_query_audit(&request, state.audit_log.as_deref());
+        log_audit(state, session, &cmd, None, "ok", "");
+        return resp_bytes;
+    }
+
+    // So are slow queries
+    if cmd == "query_slow_log" {
+        let resp_bytes = handler::handle_query_slow_log(&request, state.slow_query_log.as_deref());
+        log_audit(state, session, &cmd, None, "ok", "");
+        return resp_bytes;
+    }
+
     // ---------------------------------------------------------------
     // Write routing through Raft (cluster mode)
     // ---------------------------------------------------------------
     if let Some(raft) = &state.raft {
-        if is_write_command(&cmd) && active_tx.is_none() {
-            let raft_req = match build_raft_request(&cmd, &request) {
+        // Replicas apply every write with full durability, so a relaxed
+        // write can't be proposed, and a flush would only sync this node
+        if cmd == "flush" {
+            return handler::err_bytes("'flush' is not supported in cluster mode");
+        }
+        if request.get("durability").is_some() {
+            return handler::err_bytes("'durability' is not supported in cluster mode");
+        }
+        if is_write_command(&cmd) && active_tx.is_none() && !handler::is_dry_run(&request) {
+            // Only the leader can commit: point the client at it
+            if !management::is_leader(raft) {
+                return management::not_leader_bytes(raft).await;
+            }
+            let mut raft_req = match build_raft_request(&cmd, &request) {
                 Some(req) => req,
                 None => {
                     // Fall through to local handler if we can't build a raft request
                     return dispatch_local(state, request, active_tx, session, &cmd, collection.as_deref()).await;
                 }
             };
+            resolve_defaults(&state.db, &mut raft_req);
             let result = raft.client_write(raft_req).await;
             log_audit(state, session, &cmd, collection.as_deref(), "ok", "");
-            return match result {
+            let (resp, failed) = match result {
                 Ok(resp) => {
                     let raft_resp: OxiDbResponse = resp.data;
                     match raft_resp {
-                        OxiDbResponse::Ok { data } => handler::ok_bytes(data),
-                        OxiDbResponse::Error { message } => handler::err_bytes(&message),
+                        OxiDbResponse::Ok { data } => (handler::ok_bytes(data), false),
+                        OxiDbResponse::Error { message } => (handler::err_bytes(&message), true),
                     }
                 }
-                Err(e) => handler::err_bytes(&format!("raft error: {e}")),
+                // Leadership moved since the check above
+                Err(RaftError::APIError(ClientWriteError::ForwardToLeader(_))) => {
+                    (management::not_leader_bytes(raft).await, true)
+                }
+                Err(e) => (handler::err_bytes(&format!("raft error: {e}")), true),
             };
+            // Writes applied through raft bypass the handler's counting
+            state.db.metrics().record_command(&cmd, failed);
+            return resp;
         }
     }
 
+    // ---------------------------------------------------------------
+    // Linearizable reads (cluster mode): only the leader serves them, once
+    // it has confirmed leadership and applied everything committed
+    // ---------------------------------------------------------------
+    if let Some(raft) = &state.raft
+        && matches!(cmd.as_str(), "find" | "find_one" | "count")
+        && active_tx.is_none()
+        && handler::ReadConcern::from_request(&request) == Ok(handler::ReadConcern::Linearizable)
+        && let Err(resp) = management::ensure_linearizable(raft).await
+    {
+        return resp;
+    }
+
     // ---------------------------------------------------------------
     // Local execution (standalone mode, reads, or transactions)
     // ---------------------------------------------------------------
@@ -269,9 +427,16 @@ async fn dispatch_local(
     }
 
     // All other commands: run handler in a blocking thread.
+    let bucket = request.get("bucket").and_then(|v| v.as_str()).map(str::to_string);
     let mut tx = active_tx.take();
+    let slow_query_log = state.slow_query_log.clone();
     let resp_bytes = tokio::task::spawn_blocking(move || {
+        let started = std::time::Instant::now();
+        let timed = slow_query_log.as_ref().filter(|_| SlowQueryLog::is_timed(&request)).map(|_| request.clone());
         let resp = handler::handle_request(&db, request, &mut tx);
+        if let (Some(slow_query_log), Some(request)) = (&slow_query_log, &timed) {
+            slow_query_log.observe(&db, request, started.elapsed());
+        }
         (resp, tx)
     })
     .await
@@ -282,7 +447,10 @@ async fn dispatch_local(
         )
     });
     *active_tx = resp_bytes.1;
-    let bytes = resp_bytes.0;
+    let mut bytes = resp_bytes.0;
+    if state.auth_enabled {
+        bytes = rbac::filter_readable_buckets(&state.db, session, cmd, bucket.as_deref(), bytes);
+    }
 
     log_audit(state, session, cmd, collection, "ok", "");
     bytes
@@ -296,8 +464,10 @@ fn is_write_command(cmd: &str) -> bool {
             | "insert_many"
             | "update"
             | "update_one"
+            | "find_and_modify"
             | "delete"
             | "delete_one"
+            | "bulk_write"
             | "create_collection"
             | "drop_collection"
             | "compact"
@@ -305,14 +475,43 @@ fn is_write_command(cmd: &str) -> bool {
             | "create_unique_index"
             | "create_composite_index"
             | "create_text_index"
+            | "create_geo_index"
             | "drop_index"
+            | "set_schema"
+            | "set_defaults"
             | "create_bucket"
             | "delete_bucket"
+            | "set_bucket_acl"
+            | "set_bucket_tokenizer"
             | "put_object"
             | "delete_object"
     )
 }
 
+/// Fill in the collection's defaults for the documents an insert carries
+/// before it is proposed, so that every replica stores the same `$now` and
+/// `$uuid` values rather than generating its own while applying the entry.
+fn resolve_defaults(db: &OxiDb, req: &mut OxiDbRequest) {
+    let (collection, docs): (&str, Vec<&mut Value>) = match req {
+        OxiDbRequest::Insert { collection, document } => (collection, vec![document]),
+        OxiDbRequest::InsertMany { collection, documents } => (collection, documents.iter_mut().collect()),
+        OxiDbRequest::BulkWrite { collection, ops } => {
+            let inserts = ops.as_array_mut().into_iter().flatten().filter(|op| op["op"] == "insert");
+            (collection, inserts.filter_map(|op| op.get_mut("doc")).collect())
+        }
+        _ => return,
+    };
+    let Ok(defaults) = db.get_collection_defaults(collection) else {
+        return;
+    };
+    if defaults.is_empty() {
+        return;
+    }
+    for doc in docs.into_iter().filter(|doc| doc.is_object()) {
+        defaults.apply(doc);
+    }
+}
+
 /// Build an `OxiDbRequest` from the JSON request for Raft replication.
 fn build_raft_request(cmd: &str, request: &Value) -> Option<OxiDbRequest> {
     let collection = request
@@ -332,15 +531,28 @@ fn build_raft_request(cmd: &str, request: &Value) -> Option<OxiDbRequest> {
                 .and_then(|v| v.as_array())
                 .cloned()?,
         }),
+        // A malformed expected_version is left to the local handler to report
         "update" => Some(OxiDbRequest::Update {
             collection: collection?,
             query: request.get("query")?.clone(),
             update: request.get("update")?.clone(),
+            expected_version: request.get("expected_version").map(|v| v.as_u64().ok_or(())).transpose().ok()?,
         }),
         "update_one" => Some(OxiDbRequest::UpdateOne {
             collection: collection?,
             query: request.get("query")?.clone(),
             update: request.get("update")?.clone(),
+            expected_version: request.get("expected_version").map(|v| v.as_u64().ok_or(())).transpose().ok()?,
+        }),
+        "find_and_modify" => Some(OxiDbRequest::FindAndModify {
+            collection: collection?,
+            query: request.get("query").cloned().unwrap_or_else(|| json!({})),
+            update: request.get("update").cloned(),
+            options: json!({
+                "sort": request.get("sort"),
+                "new": request.get("new"),
+                "remove": request.get("remove"),
+            }),
         }),
         "delete" => Some(OxiDbRequest::Delete {
             collection: collection?,
@@ -350,6 +562,10 @@ fn build_raft_request(cmd: &str, request: &Value) -> Option<OxiDbRequest> {
             collection: collection?,
             query: request.get("query")?.clone(),
         }),
+        "bulk_write" => Some(OxiDbRequest::BulkWrite {
+            collection: collection?,
+            ops: request.get("ops")?.clone(),
+        }),
         "create_collection" => Some(OxiDbRequest::CreateCollection {
             name: collection?,
         }),
@@ -362,6 +578,8 @@ fn build_raft_request(cmd: &str, request: &Value) -> Option<OxiDbRequest> {
         "create_index" => Some(OxiDbRequest::CreateIndex {
             collection: collection?,
             field: request.get("field")?.as_str()?.to_string(),
+            descending: request.get("descending").and_then(|v| v.as_bool()).unwrap_or(false),
+            sparse: request.get("sparse").and_then(|v| v.as_bool()).unwrap_or(false),
         }),
         "create_unique_index" => Some(OxiDbRequest::CreateUniqueIndex {
             collection: collection?,
@@ -387,16 +605,39 @@ fn build_raft_request(cmd: &str, request: &Value) -> Option<OxiDbRequest> {
                 fields: fields?,
             })
         }
+        "create_geo_index" => Some(OxiDbRequest::CreateGeoIndex {
+            collection: collection?,
+            field: request.get("field")?.as_str()?.to_string(),
+        }),
         "drop_index" => Some(OxiDbRequest::DropIndex {
             collection: collection?,
             index: request.get("index")?.as_str()?.to_string(),
         }),
+        "set_schema" => Some(OxiDbRequest::SetSchema {
+            collection: collection?,
+            schema: request.get("schema")?.clone(),
+            level: request.get("level").and_then(|v| v.as_str()).unwrap_or("strict").to_string(),
+            coerce: request.get("coerce").and_then(|v| v.as_bool()).unwrap_or(false),
+        }),
+        "set_defaults" => Some(OxiDbRequest::SetDefaults {
+            collection: collection?,
+            defaults: request.get("defaults")?.clone(),
+        }),
         "create_bucket" => Some(OxiDbRequest::CreateBucket {
             bucket: request.get("bucket")?.as_str()?.to_string(),
+            owner: request.get("owner").and_then(|v| v.as_str()).map(str::to_string),
         }),
         "delete_bucket" => Some(OxiDbRequest::DeleteBucket {
             bucket: request.get("bucket")?.as_str()?.to_string(),
         }),
+        "set_bucket_acl" => Some(OxiDbRequest::SetBucketAcl {
+            bucket: request.get("bucket")?.as_str()?.to_string(),
+            acl: request.get("acl")?.clone(),
+        }),
+        "set_bucket_tokenizer" => Some(OxiDbRequest::SetBucketTokenizer {
+            bucket: request.get("bucket")?.as_str()?.to_string(),
+            tokenizer: request.get("tokenizer")?.as_str()?.to_string(),
+        }),
         "put_object" => Some(OxiDbRequest::PutObject {
             bucket: request.get("bucket")?.as_str()?.to_string(),
             key: request.get("key")?.as_str()?.to_string(),
@@ -433,7 +674,7 @@ fn handle_auth(
                 .unwrap_or("");
 
             let user_store_guard = state.user_store.as_ref().unwrap().lock().unwrap();
-            match ScramState::process_client_first(client_first, &user_store_guard) {
+            match ScramState::process_client_first(client_first, &user_store_guard, state.tls_channel_binding.as_deref()) {
                 Ok((server_first, scram_state)) => {
                     drop(user_store_guard);
                     session.scram_state = Some(scram_state);
@@ -455,10 +696,11 @@ fn handle_auth(
             if let Some(scram_state) = session.scram_state.take() {
                 let user_store_guard = state.user_store.as_ref().unwrap().lock().unwrap();
                 match scram_state.process_client_final(client_final, &user_store_guard) {
-                    Ok((server_final, role)) => {
-                        let username = scram_state.username().to_string();
+                    Ok((server_final, _)) => {
+                        if let Some(user) = user_store_guard.get_user(scram_state.username()) {
+                            session.set_authenticated_user(user);
+                        }
                         drop(user_store_guard);
-                        session.set_authenticated(username, role);
                         handler::ok_bytes(json!({
                             "payload": server_final,
                             "done": true,
@@ -482,12 +724,32 @@ fn handle_auth(
                 .unwrap_or("");
 
             let user_store_guard = state.user_store.as_ref().unwrap().lock().unwrap();
-            match user_store_guard.authenticate(username, password) {
-                Some(role) => {
+            let user = user_store_guard
+                .authenticate(username, password)
+                .and_then(|_| user_store_guard.get_user(username));
+            match user {
+                Some(user) => {
+                    session.set_authenticated_user(user);
+                    drop(user_store_guard);
+                    handler::ok_bytes(json!({
+                        "role": session.role_name(),
+                        "done": true,
+                    }))
+                }
+                None => handler::err_bytes("authentication failed"),
+            }
+        }
+
+        "auth_apikey" => {
+            let key = request.get("key").and_then(|v| v.as_str()).unwrap_or("");
+
+            let user_store_guard = state.user_store.as_ref().unwrap().lock().unwrap();
+            match user_store_guard.authenticate_api_key(key) {
+                Some(user) => {
+                    session.set_authenticated_user(user);
                     drop(user_store_guard);
-                    session.set_authenticated(username.to_string(), role);
                     handler::ok_bytes(json!({
-                        "role": role.as_str(),
+                        "role": session.role_name(),
                         "done": true,
                     }))
                 }
@@ -1,9 +1,23 @@
+use std::collections::VecDeque;
 use std::fs::{self, File, OpenOptions};
-use std::io::Write;
-use std::path::Path;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
 
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
 use serde::Serialize;
+use serde_json::Value;
+
+/// Most events a `query_audit` returns when it doesn't set a `limit`.
+pub const DEFAULT_QUERY_LIMIT: usize = 1000;
+
+/// Rolled segments kept by default.
+pub const DEFAULT_MAX_SEGMENTS: usize = 10;
+
+const ACTIVE_FILE: &str = "audit.log";
 
 #[derive(Serialize)]
 pub struct AuditEvent<'a> {
@@ -15,36 +29,315 @@ pub struct AuditEvent<'a> {
     pub detail: &'a str,
 }
 
+/// When the active audit log is rolled over to a timestamped segment
+/// (`audit-<UTC time>.log`, or `.log.gz` when compressed) and how many
+/// segments are kept. With neither limit set the log is never rolled.
+#[derive(Debug, Clone)]
+pub struct AuditRotation {
+    /// Roll once the active file has grown past this many bytes.
+    pub max_bytes: Option<u64>,
+    /// Roll once the active file has been in use this long.
+    pub max_age: Option<Duration>,
+    /// Rolled segments to keep; the oldest beyond this are deleted.
+    pub max_segments: usize,
+    /// Gzip segments once they're rolled.
+    pub compress: bool,
+}
+
+impl Default for AuditRotation {
+    fn default() -> Self {
+        Self { max_bytes: None, max_age: None, max_segments: DEFAULT_MAX_SEGMENTS, compress: false }
+    }
+}
+
+impl AuditRotation {
+    /// Read `OXIDB_AUDIT_MAX_SIZE` (bytes), `OXIDB_AUDIT_MAX_AGE` (seconds),
+    /// `OXIDB_AUDIT_MAX_SEGMENTS` and `OXIDB_AUDIT_COMPRESS`. A size or age
+    /// of 0 means no limit.
+    pub fn from_env() -> Self {
+        let number = |var: &str| {
+            std::env::var(var)
+                .ok()
+                .map(|v| v.parse::<u64>().unwrap_or_else(|_| panic!("{var} must be a valid u64")))
+        };
+        Self {
+            max_bytes: number("OXIDB_AUDIT_MAX_SIZE").filter(|&n| n > 0),
+            max_age: number("OXIDB_AUDIT_MAX_AGE").filter(|&n| n > 0).map(Duration::from_secs),
+            max_segments: number("OXIDB_AUDIT_MAX_SEGMENTS").map_or(DEFAULT_MAX_SEGMENTS, |n| n as usize),
+            compress: std::env::var("OXIDB_AUDIT_COMPRESS").is_ok_and(|v| v == "true" || v == "1"),
+        }
+    }
+}
+
 pub struct AuditLog {
-    file: Mutex<File>,
+    dir: PathBuf,
+    rotation: AuditRotation,
+    active: Mutex<ActiveFile>,
+    /// Held while rolled segments are compressed or pruned, and while a
+    /// query reads them, so a query never sees a segment half-processed.
+    segments: Mutex<()>,
+}
+
+struct ActiveFile {
+    file: File,
+    size: u64,
+    started: SystemTime,
+}
+
+impl ActiveFile {
+    fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { file, size, started: first_event_time(path).unwrap_or_else(SystemTime::now) })
+    }
+}
+
+/// Timestamp of the first event in the file at `path`: when a log reopened
+/// after a restart started.
+fn first_event_time(path: &Path) -> Option<SystemTime> {
+    let mut line = String::new();
+    BufReader::new(File::open(path).ok()?).read_line(&mut line).ok()?;
+    let event: Value = serde_json::from_str(&line).ok()?;
+    let ts = DateTime::parse_from_rfc3339(event.get("ts")?.as_str()?).ok()?;
+    Some(ts.with_timezone(&Utc).into())
+}
+
+/// Filters for [`AuditLog::query`]. Unset filters match every event; the
+/// time bounds are inclusive.
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    pub user: Option<String>,
+    pub cmd: Option<String>,
+    pub collection: Option<String>,
+    pub result: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Return at most this many events, the most recent ones.
+    pub limit: usize,
+}
+
+impl AuditQuery {
+    /// Parse the filters of a `query_audit` request.
+    pub fn from_request(request: &Value) -> Result<Self, String> {
+        let text = |field: &str| -> Result<Option<String>, String> {
+            match request.get(field) {
+                None | Some(Value::Null) => Ok(None),
+                Some(Value::String(s)) => Ok(Some(s.clone())),
+                Some(_) => Err(format!("'{field}' must be a string")),
+            }
+        };
+        let time = |field: &str| -> Result<Option<DateTime<Utc>>, String> {
+            text(field)?
+                .map(|s| {
+                    DateTime::parse_from_rfc3339(&s)
+                        .map(|t| t.with_timezone(&Utc))
+                        .map_err(|_| format!("'{field}' must be an RFC 3339 timestamp"))
+                })
+                .transpose()
+        };
+        let limit = match request.get("limit") {
+            None | Some(Value::Null) => DEFAULT_QUERY_LIMIT,
+            Some(v) => v.as_u64().filter(|&n| n > 0).ok_or("'limit' must be a positive integer")? as usize,
+        };
+        Ok(Self {
+            user: text("user")?,
+            cmd: text("command")?,
+            collection: text("collection")?,
+            result: text("result")?,
+            since: time("since")?,
+            until: time("until")?,
+            limit,
+        })
+    }
+
+    fn matches(&self, event: &Value) -> bool {
+        let field_is = |name: &str, want: &Option<String>| {
+            want.as_deref().is_none_or(|w| event.get(name).and_then(|v| v.as_str()) == Some(w))
+        };
+        if !(field_is("user", &self.user)
+            && field_is("cmd", &self.cmd)
+            && field_is("collection", &self.collection)
+            && field_is("result", &self.result))
+        {
+            return false;
+        }
+        if self.since.is_none() && self.until.is_none() {
+            return true;
+        }
+        let Some(ts) = event
+            .get("ts")
+            .and_then(|v| v.as_str())
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        else {
+            return false;
+        };
+        self.since.is_none_or(|since| ts >= since) && self.until.is_none_or(|until| ts <= until)
+    }
 }
 
 impl AuditLog {
     /// Open or create an audit log file at `{data_dir}/_audit/audit.log`.
     pub fn open(data_dir: &Path) -> Result<Self, String> {
-        let audit_dir = data_dir.join("_audit");
-        fs::create_dir_all(&audit_dir)
+        Self::open_with_rotation(data_dir, AuditRotation::default())
+    }
+
+    /// Like [`AuditLog::open`], rolling the file over as `rotation` says.
+    pub fn open_with_rotation(data_dir: &Path, rotation: AuditRotation) -> Result<Self, String> {
+        let dir = data_dir.join("_audit");
+        fs::create_dir_all(&dir)
             .map_err(|e| format!("failed to create audit dir: {e}"))?;
 
-        let path = audit_dir.join("audit.log");
-        let file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&path)
+        let active = ActiveFile::open(&dir.join(ACTIVE_FILE))
             .map_err(|e| format!("failed to open audit log: {e}"))?;
 
         Ok(Self {
-            file: Mutex::new(file),
+            dir,
+            rotation,
+            active: Mutex::new(active),
+            segments: Mutex::new(()),
         })
     }
 
     /// Log an audit event. Fire-and-forget (no fsync).
+    ///
+    /// If the active file is due for rotation it's rolled first, under the
+    /// same lock as the append, so concurrent events all land in exactly one
+    /// file. Compressing and pruning the rolled segment happen after the
+    /// lock is released.
     pub fn log(&self, event: &AuditEvent) {
-        let mut file = self.file.lock().unwrap();
-        if let Ok(json) = serde_json::to_string(event) {
-            let _ = writeln!(file, "{}", json);
+        let Ok(json) = serde_json::to_string(event) else {
+            return;
+        };
+        let rolled = {
+            let mut active = self.active.lock().unwrap();
+            let rolled = if self.due_for_rotation(&active) { self.roll(&mut active).ok() } else { None };
+            if writeln!(active.file, "{}", json).is_ok() {
+                active.size += json.len() as u64 + 1;
+            }
+            rolled
+        };
+        if let Some(segment) = rolled {
+            self.finish_segment(&segment);
+        }
+    }
+
+    fn due_for_rotation(&self, active: &ActiveFile) -> bool {
+        if active.size == 0 {
+            return false;
         }
+        self.rotation.max_bytes.is_some_and(|max| active.size >= max)
+            || self.rotation.max_age.is_some_and(|max| active.started.elapsed().is_ok_and(|age| age >= max))
     }
+
+    /// Rename the active file to a new segment and start a fresh one.
+    fn roll(&self, active: &mut ActiveFile) -> io::Result<PathBuf> {
+        let path = self.dir.join(ACTIVE_FILE);
+        let segment = self.new_segment_path();
+        fs::rename(&path, &segment)?;
+        *active = ActiveFile::open(&path)?;
+        Ok(segment)
+    }
+
+    fn new_segment_path(&self) -> PathBuf {
+        loop {
+            let name = format!("audit-{}.log", Utc::now().format("%Y%m%dT%H%M%S%.6fZ"));
+            let path = self.dir.join(&name);
+            if !path.exists() && !self.dir.join(format!("{name}.gz")).exists() {
+                return path;
+            }
+        }
+    }
+
+    /// Compress a freshly rolled segment if configured, then delete the
+    /// oldest segments beyond the limit.
+    fn finish_segment(&self, segment: &Path) {
+        let _guard = self.segments.lock().unwrap();
+        if self.rotation.compress {
+            let _ = gzip_segment(segment);
+        }
+        let segments = self.segment_paths();
+        let excess = segments.len().saturating_sub(self.rotation.max_segments);
+        for old in &segments[..excess] {
+            let _ = fs::remove_file(old);
+        }
+    }
+
+    /// Rolled segments, oldest first. Their names sort by roll time.
+    fn segment_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name().and_then(|n| n.to_str()).is_some_and(|name| {
+                    name.starts_with("audit-") && (name.ends_with(".log") || name.ends_with(".log.gz"))
+                })
+            })
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    /// Scan the log, rolled segments included, for events matching `query`,
+    /// returning the most recent `query.limit` of them oldest first. Appends
+    /// carry on during the scan; a line still being written is skipped.
+    pub fn query(&self, query: &AuditQuery) -> Result<Vec<Value>, String> {
+        let _guard = self.segments.lock().unwrap();
+        // List the segments and open the active file together, so a roll
+        // can't slip in between and hide or duplicate events.
+        let (segments, active) = {
+            let _active = self.active.lock().unwrap();
+            let active = File::open(self.dir.join(ACTIVE_FILE))
+                .map_err(|e| format!("failed to open audit log: {e}"))?;
+            (self.segment_paths(), active)
+        };
+
+        let mut matched = VecDeque::new();
+        for segment in &segments {
+            let file = File::open(segment).map_err(|e| format!("failed to open audit segment: {e}"))?;
+            if segment.extension().is_some_and(|ext| ext == "gz") {
+                scan(GzDecoder::new(file), query, &mut matched)?;
+            } else {
+                scan(file, query, &mut matched)?;
+            }
+        }
+        scan(active, query, &mut matched)?;
+        Ok(matched.into())
+    }
+}
+
+/// Add the events in `reader` matching `query` to `matched`, keeping only
+/// the last `query.limit`.
+fn scan(reader: impl Read, query: &AuditQuery, matched: &mut VecDeque<Value>) -> Result<(), String> {
+    for line in BufReader::new(reader).lines() {
+        let line = line.map_err(|e| format!("failed to read audit log: {e}"))?;
+        let Ok(event) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        if query.matches(&event) {
+            if matched.len() == query.limit {
+                matched.pop_front();
+            }
+            matched.push_back(event);
+        }
+    }
+    Ok(())
+}
+
+/// Replace `segment` with `segment.gz`. The compressed file is written
+/// under a temporary name first, so a crash leaves the original intact.
+fn gzip_segment(segment: &Path) -> io::Result<()> {
+    let mut gz_name = segment.as_os_str().to_owned();
+    gz_name.push(".gz");
+    let gz_path = PathBuf::from(gz_name);
+    let tmp_path = gz_path.with_extension("gz.tmp");
+
+    let mut encoder = GzEncoder::new(File::create(&tmp_path)?, flate2::Compression::default());
+    io::copy(&mut File::open(segment)?, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    fs::rename(&tmp_path, &gz_path)?;
+    fs::remove_file(segment)
 }
 
 /// Get current timestamp as RFC 3339 string.
@@ -1,11 +1,13 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use argon2::password_hash::rand_core::OsRng;
 use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
 use argon2::Argon2;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -38,12 +40,63 @@ impl Role {
 pub struct UserRecord {
     pub username: String,
     pub password_hash: String,
+    /// Built-in role. `Read` for users with a custom role, whose permissions
+    /// come from that role instead.
     pub role: Role,
+    /// Name of a user-defined role, if the user has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_role: Option<String>,
+    /// Unix seconds when the password was last set; 0 if unknown.
+    #[serde(default)]
+    pub password_changed_at: u64,
+    /// The user must change their password before running anything else.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub must_change: bool,
+}
+
+impl UserRecord {
+    /// The custom role name, or the built-in role's.
+    pub fn role_name(&self) -> &str {
+        self.custom_role.as_deref().unwrap_or(self.role.as_str())
+    }
+}
+
+/// A user-defined role: the exact set of commands its users may run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomRole {
+    pub name: String,
+    pub commands: BTreeSet<String>,
+}
+
+/// An API key that authenticates as a user. Only a hash of the key is kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    pub id: String,
+    pub username: String,
+    /// SHA-256 of the key, hex-encoded.
+    key_hash: String,
+    /// Unix seconds.
+    pub created_at: u64,
+    /// Unix seconds after which the key is rejected; `None` never expires.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    #[serde(default)]
+    pub revoked: bool,
+}
+
+impl ApiKeyRecord {
+    fn is_valid(&self, now: u64) -> bool {
+        !self.revoked && self.expires_at.is_none_or(|at| now < at)
+    }
 }
 
 pub struct UserStore {
     users: HashMap<String, UserRecord>,
+    roles: BTreeMap<String, CustomRole>,
+    api_keys: Vec<ApiKeyRecord>,
     store_path: PathBuf,
+    roles_path: PathBuf,
+    api_keys_path: PathBuf,
 }
 
 impl UserStore {
@@ -67,7 +120,29 @@ impl UserStore {
             }
         }
 
-        let mut store = Self { users, store_path };
+        let roles_path = auth_dir.join("roles.json");
+        let mut roles = BTreeMap::new();
+        if roles_path.exists() {
+            let data = fs::read_to_string(&roles_path)
+                .map_err(|e| format!("failed to read roles.json: {e}"))?;
+            let records: Vec<CustomRole> = serde_json::from_str(&data)
+                .map_err(|e| format!("failed to parse roles.json: {e}"))?;
+            for role in records {
+                roles.insert(role.name.clone(), role);
+            }
+        }
+
+        let api_keys_path = auth_dir.join("api_keys.json");
+        let api_keys = if api_keys_path.exists() {
+            let data = fs::read_to_string(&api_keys_path)
+                .map_err(|e| format!("failed to read api_keys.json: {e}"))?;
+            serde_json::from_str(&data)
+                .map_err(|e| format!("failed to parse api_keys.json: {e}"))?
+        } else {
+            Vec::new()
+        };
+
+        let mut store = Self { users, roles, api_keys, store_path, roles_path, api_keys_path };
 
         // Create default admin if no users exist
         if store.users.is_empty() {
@@ -110,7 +185,13 @@ impl UserStore {
             return Err(format!("user '{}' not found", username));
         }
         self.users.remove(username);
-        self.save()
+        self.save()?;
+        let keys = self.api_keys.len();
+        self.api_keys.retain(|k| k.username != username);
+        if self.api_keys.len() != keys {
+            self.save_api_keys()?;
+        }
+        Ok(())
     }
 
     pub fn update_user(
@@ -124,10 +205,48 @@ impl UserStore {
 
         if let Some(pw) = password {
             record.password_hash = hash_password(pw)?;
+            record.password_changed_at = unix_now();
         }
         if let Some(r) = role {
             record.role = r;
+            record.custom_role = None;
+        }
+        self.save()
+    }
+
+    /// Require (or stop requiring) a user to change their password.
+    pub fn set_must_change(&mut self, username: &str, must_change: bool) -> Result<(), String> {
+        let record = self.users.get_mut(username)
+            .ok_or_else(|| format!("user '{}' not found", username))?;
+        record.must_change = must_change;
+        self.save()
+    }
+
+    /// Replace a user's password, given their current one. Clears `must_change`.
+    pub fn change_password(&mut self, username: &str, old_password: &str, new_password: &str) -> Result<(), String> {
+        if self.authenticate(username, old_password).is_none() {
+            return Err("current password is incorrect".to_string());
         }
+        if new_password == old_password {
+            return Err("new password must differ from the current one".to_string());
+        }
+        let record = self.users.get_mut(username)
+            .ok_or_else(|| format!("user '{}' not found", username))?;
+        record.password_hash = hash_password(new_password)?;
+        record.password_changed_at = unix_now();
+        record.must_change = false;
+        self.save()
+    }
+
+    /// Give a user the user-defined role `role`, replacing their built-in one.
+    pub fn assign_custom_role(&mut self, username: &str, role: &str) -> Result<(), String> {
+        if !self.roles.contains_key(role) {
+            return Err(format!("role '{}' not found", role));
+        }
+        let record = self.users.get_mut(username)
+            .ok_or_else(|| format!("user '{}' not found", username))?;
+        record.role = Role::Read;
+        record.custom_role = Some(role.to_string());
         self.save()
     }
 
@@ -135,17 +254,104 @@ impl UserStore {
         self.users.values().map(|r| {
             serde_json::json!({
                 "username": r.username,
-                "role": r.role.as_str(),
+                "role": r.role_name(),
             })
         }).collect()
     }
 
+    /// Define a role whose users may run exactly `commands`. Names of built-in
+    /// roles are reserved.
+    pub fn create_role(&mut self, name: &str, commands: Vec<String>) -> Result<(), String> {
+        if Role::from_str(name).is_some() {
+            return Err(format!("'{}' is a built-in role", name));
+        }
+        if self.roles.contains_key(name) {
+            return Err(format!("role '{}' already exists", name));
+        }
+        let role = CustomRole { name: name.to_string(), commands: commands.into_iter().collect() };
+        self.roles.insert(name.to_string(), role);
+        self.save_roles()
+    }
+
+    /// Delete a user-defined role. Fails while any user has it.
+    pub fn drop_role(&mut self, name: &str) -> Result<(), String> {
+        if !self.roles.contains_key(name) {
+            return Err(format!("role '{}' not found", name));
+        }
+        let mut holders: Vec<&str> = self.users.values()
+            .filter(|u| u.custom_role.as_deref() == Some(name))
+            .map(|u| u.username.as_str())
+            .collect();
+        if !holders.is_empty() {
+            holders.sort_unstable();
+            return Err(format!("role '{}' is assigned to: {}", name, holders.join(", ")));
+        }
+        self.roles.remove(name);
+        self.save_roles()
+    }
+
+    /// Mint an API key for `username`, valid for `expires_in` seconds (or
+    /// until revoked). Returns the key's id and the key itself, which is not
+    /// stored and can't be shown again.
+    pub fn create_api_key(&mut self, username: &str, expires_in: Option<u64>) -> Result<(String, String), String> {
+        if !self.users.contains_key(username) {
+            return Err(format!("user '{}' not found", username));
+        }
+        let id = random_hex(8);
+        let key = format!("oxk_{}", random_hex(32));
+        let created_at = unix_now();
+        self.api_keys.push(ApiKeyRecord {
+            id: id.clone(),
+            username: username.to_string(),
+            key_hash: hash_api_key(&key),
+            created_at,
+            expires_at: expires_in.map(|secs| created_at.saturating_add(secs)),
+            revoked: false,
+        });
+        self.save_api_keys()?;
+        Ok((id, key))
+    }
+
+    /// Revoke an API key by id. Revoked keys stay listed.
+    pub fn revoke_api_key(&mut self, id: &str) -> Result<(), String> {
+        let record = self.api_keys.iter_mut().find(|k| k.id == id)
+            .ok_or_else(|| format!("api key '{}' not found", id))?;
+        record.revoked = true;
+        self.save_api_keys()
+    }
+
+    pub fn list_api_keys(&self) -> &[ApiKeyRecord] {
+        &self.api_keys
+    }
+
+    /// The user an API key authenticates as, unless it is unknown, revoked or expired.
+    pub fn authenticate_api_key(&self, key: &str) -> Option<&UserRecord> {
+        let hash = hash_api_key(key);
+        let now = unix_now();
+        let record = self.api_keys.iter().find(|k| k.key_hash == hash)?;
+        if !record.is_valid(now) {
+            return None;
+        }
+        self.users.get(&record.username)
+    }
+
+    pub fn get_role(&self, name: &str) -> Option<&CustomRole> {
+        self.roles.get(name)
+    }
+
+    pub fn list_roles(&self) -> Vec<&CustomRole> {
+        self.roles.values().collect()
+    }
+
     fn create_user_internal(&mut self, username: &str, password: &str, role: Role) -> Result<(), String> {
         let password_hash = hash_password(password)?;
         let record = UserRecord {
             username: username.to_string(),
             password_hash,
             role,
+            custom_role: None,
+            password_changed_at: unix_now(),
+            must_change: false,
         };
         self.users.insert(username.to_string(), record);
         self.save()
@@ -159,6 +365,23 @@ impl UserStore {
             .map_err(|e| format!("failed to write users.json: {e}"))?;
         Ok(())
     }
+
+    fn save_api_keys(&self) -> Result<(), String> {
+        let data = serde_json::to_string_pretty(&self.api_keys)
+            .map_err(|e| format!("failed to serialize api keys: {e}"))?;
+        fs::write(&self.api_keys_path, data)
+            .map_err(|e| format!("failed to write api_keys.json: {e}"))?;
+        Ok(())
+    }
+
+    fn save_roles(&self) -> Result<(), String> {
+        let records: Vec<&CustomRole> = self.roles.values().collect();
+        let data = serde_json::to_string_pretty(&records)
+            .map_err(|e| format!("failed to serialize roles: {e}"))?;
+        fs::write(&self.roles_path, data)
+            .map_err(|e| format!("failed to write roles.json: {e}"))?;
+        Ok(())
+    }
 }
 
 fn hash_password(password: &str) -> Result<String, String> {
@@ -170,6 +393,21 @@ fn hash_password(password: &str) -> Result<String, String> {
     Ok(hash.to_string())
 }
 
+fn hash_api_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes()).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn random_hex(len: usize) -> String {
+    use rand::RngCore;
+    let mut bytes = vec![0u8; len];
+    rand::rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
 fn generate_random_password() -> String {
     use rand::RngCore;
     let mut bytes = [0u8; 24];
@@ -0,0 +1,79 @@
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::handler;
+use crate::protocol;
+
+/// Default for `OXIDB_MAX_CONNECTIONS`.
+pub const DEFAULT_MAX_CONNECTIONS: usize = 1024;
+
+/// How long the accept loop spends telling a rejected client why.
+const REJECT_WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Caps the connections the server holds at once, counting both those
+/// waiting for a worker and those being served. The accept loop takes a
+/// permit per connection and turns the connection away when none is left,
+/// so a flood can't queue streams without bound.
+#[derive(Debug)]
+pub struct ConnectionLimiter {
+    max: usize,
+    active: AtomicUsize,
+}
+
+/// One admitted connection; releases its slot when dropped.
+#[derive(Debug)]
+pub struct ConnectionPermit {
+    limiter: Arc<ConnectionLimiter>,
+}
+
+impl ConnectionLimiter {
+    /// Limiter admitting at most `max` connections (at least one).
+    pub fn new(max: usize) -> Arc<Self> {
+        Arc::new(Self { max: max.max(1), active: AtomicUsize::new(0) })
+    }
+
+    /// Read the limit from `OXIDB_MAX_CONNECTIONS`.
+    pub fn from_env() -> Arc<Self> {
+        let max = match std::env::var("OXIDB_MAX_CONNECTIONS") {
+            Ok(v) => v
+                .parse::<usize>()
+                .ok()
+                .filter(|&n| n > 0)
+                .expect("OXIDB_MAX_CONNECTIONS must be a positive integer"),
+            Err(_) => DEFAULT_MAX_CONNECTIONS,
+        };
+        Self::new(max)
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Connections currently holding a permit.
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// Take a slot for a new connection, or `None` at the limit.
+    pub fn try_acquire(self: &Arc<Self>) -> Option<ConnectionPermit> {
+        self.active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| (n < self.max).then_some(n + 1))
+            .ok()
+            .map(|_| ConnectionPermit { limiter: Arc::clone(self) })
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        self.limiter.active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Tell a plain-TCP client it was turned away, then close the connection.
+/// Best effort: a client that isn't reading just gets the close.
+pub fn reject(mut stream: TcpStream) {
+    let _ = stream.set_write_timeout(Some(REJECT_WRITE_TIMEOUT));
+    let _ = protocol::write_message(&mut stream, &handler::err_bytes("too many connections"));
+}
@@ -1,5 +1,18 @@
-use std::net::UdpSocket;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Messages waiting for the TCP sender thread; further ones are dropped so
+/// that logging never waits on the collector.
+const TCP_QUEUE_LEN: usize = 1024;
+
+/// Wait after a failed TCP connect before trying again. Messages logged in
+/// the meantime are dropped.
+const TCP_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Connect and write timeout for the TCP collector.
+const TCP_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// GELF severity levels (syslog-compatible).
 #[repr(u8)]
@@ -15,30 +28,36 @@ pub enum GelfLevel {
     Debug = 7,
 }
 
-/// Fire-and-forget GELF UDP logger.
+/// Fire-and-forget GELF logger.
 ///
-/// Wraps a non-blocking `UdpSocket` pre-connected to the GELF target.
+/// Over UDP (the default), wraps a non-blocking `UdpSocket` pre-connected to
+/// the GELF target. Over TCP, messages go through a bounded queue to a
+/// background thread that owns the connection and reconnects when it drops.
 /// Send failures are silently ignored — logging must never block or crash
 /// the server.
 pub struct GelfLogger {
-    socket: UdpSocket,
+    transport: Transport,
     hostname: String,
 }
 
+enum Transport {
+    Udp(UdpSocket),
+    Tcp(SyncSender<Vec<u8>>),
+}
+
 impl GelfLogger {
-    /// Create a new GELF logger targeting `addr` (e.g. `"172.17.0.1:12201"`).
+    /// Create a new GELF logger targeting `addr`: `host:port` or
+    /// `udp://host:port` for UDP (e.g. `"172.17.0.1:12201"`), or
+    /// `tcp://host:port` for TCP.
     pub fn new(addr: &str) -> Result<Self, String> {
-        let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| format!("GELF bind: {e}"))?;
-        socket
-            .set_nonblocking(true)
-            .map_err(|e| format!("GELF nonblocking: {e}"))?;
-        socket
-            .connect(addr)
-            .map_err(|e| format!("GELF connect to {addr}: {e}"))?;
+        let transport = match addr.strip_prefix("tcp://") {
+            Some(target) => Transport::Tcp(spawn_tcp_sender(target)?),
+            None => Transport::Udp(udp_socket(addr.strip_prefix("udp://").unwrap_or(addr))?),
+        };
 
         let hostname = std::env::var("HOSTNAME").unwrap_or_else(|_| "oxidb-server".to_string());
 
-        Ok(Self { socket, hostname })
+        Ok(Self { transport, hostname })
     }
 
     /// Send a GELF message. Extra fields are added as `_key` entries.
@@ -68,7 +87,80 @@ impl GelfLogger {
             }
         }
 
-        let payload = msg.to_string();
-        let _ = self.socket.send(payload.as_bytes());
+        let payload = msg.to_string().into_bytes();
+        match &self.transport {
+            Transport::Udp(socket) => {
+                let _ = socket.send(&payload);
+            }
+            Transport::Tcp(queue) => {
+                let _ = queue.try_send(payload);
+            }
+        }
+    }
+}
+
+fn udp_socket(addr: &str) -> Result<UdpSocket, String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| format!("GELF bind: {e}"))?;
+    socket
+        .set_nonblocking(true)
+        .map_err(|e| format!("GELF nonblocking: {e}"))?;
+    socket
+        .connect(addr)
+        .map_err(|e| format!("GELF connect to {addr}: {e}"))?;
+    Ok(socket)
+}
+
+/// Start the thread that writes to a TCP collector. The collector needn't be
+/// up yet: the thread connects when there's something to send.
+fn spawn_tcp_sender(target: &str) -> Result<SyncSender<Vec<u8>>, String> {
+    if target.rsplit_once(':').and_then(|(_, port)| port.parse::<u16>().ok()).is_none() {
+        return Err(format!("GELF address tcp://{target}: expected host:port"));
+    }
+    let (tx, rx) = mpsc::sync_channel(TCP_QUEUE_LEN);
+    let target = target.to_string();
+    std::thread::Builder::new()
+        .name("gelf-tcp".to_string())
+        .spawn(move || tcp_sender(&target, rx))
+        .map_err(|e| format!("GELF sender thread: {e}"))?;
+    Ok(tx)
+}
+
+/// Write queued messages to the collector, each terminated by a null byte
+/// as GELF TCP framing requires. Runs until the logger is dropped.
+fn tcp_sender(target: &str, rx: Receiver<Vec<u8>>) {
+    let mut conn: Option<TcpStream> = None;
+    let mut retry_at = Instant::now();
+    for mut payload in rx {
+        payload.push(0);
+        // A write fails once the collector has closed the connection; retry
+        // that message once on a fresh one.
+        for _ in 0..2 {
+            let Some(stream) = connection(&mut conn, &mut retry_at, target) else {
+                break;
+            };
+            if stream.write_all(&payload).is_ok() {
+                break;
+            }
+            conn = None;
+        }
+    }
+}
+
+/// The open connection, reconnecting if there is none and the last failed
+/// attempt was at least [`TCP_RECONNECT_DELAY`] ago.
+fn connection<'a>(conn: &'a mut Option<TcpStream>, retry_at: &mut Instant, target: &str) -> Option<&'a mut TcpStream> {
+    if conn.is_none() && Instant::now() >= *retry_at {
+        *conn = tcp_connect(target);
+        if conn.is_none() {
+            *retry_at = Instant::now() + TCP_RECONNECT_DELAY;
+        }
     }
+    conn.as_mut()
+}
+
+fn tcp_connect(target: &str) -> Option<TcpStream> {
+    let addr = target.to_socket_addrs().ok()?.next()?;
+    let stream = TcpStream::connect_timeout(&addr, TCP_TIMEOUT).ok()?;
+    stream.set_write_timeout(Some(TCP_TIMEOUT)).ok()?;
+    Some(stream)
 }
@@ -1,11 +1,14 @@
 use base64::Engine;
-use oxidb::OxiDb;
-use oxidb::query::parse_find_options;
+use oxidb::{Durability, OxiDb};
+use oxidb::query::{FindOptions, parse_find_and_modify_options, parse_find_options};
 use serde_json::{Value, json};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
+use crate::audit::{AuditLog, AuditQuery};
 use crate::auth::{Role, UserStore};
+use crate::session::Session;
+use crate::slowlog::SlowQueryLog;
 
 pub fn ok_bytes(data: Value) -> Vec<u8> {
     serde_json::to_vec(&json!({ "ok": true, "data": data })).unwrap()
@@ -15,33 +18,176 @@ pub fn err_bytes(msg: &str) -> Vec<u8> {
     serde_json::to_vec(&json!({ "ok": false, "error": msg })).unwrap()
 }
 
+/// Error response with a `code` alongside the message.
+pub fn err_code_bytes(msg: &str, code: &str) -> Vec<u8> {
+    serde_json::to_vec(&json!({ "ok": false, "error": msg, "code": code })).unwrap()
+}
+
+/// Error response for a database error, with its `code` alongside the message.
+pub fn error_bytes(e: &oxidb::Error) -> Vec<u8> {
+    err_code_bytes(&e.to_string(), e.code())
+}
+
+/// Echo a request's `request_id` at the front of its response so a client
+/// that pipelines requests can match the responses up. Responses are non-empty
+/// JSON objects, so the id is spliced in after the opening brace.
+pub fn with_request_id(mut resp: Vec<u8>, id: Option<&Value>) -> Vec<u8> {
+    let Some(id) = id else { return resp };
+    if resp.len() < 3 || resp[0] != b'{' {
+        return resp;
+    }
+    let mut tag = b"\"request_id\":".to_vec();
+    serde_json::to_writer(&mut tag, id).unwrap();
+    tag.push(b',');
+    resp.splice(1..1, tag);
+    resp
+}
+
 /// Serialize find results directly from Arc references — zero Value::clone.
 fn ok_docs_bytes(docs: &[Arc<Value>]) -> Vec<u8> {
+    ok_page_bytes(docs, None)
+}
+
+/// Like `ok_docs_bytes`, with the total match count alongside the page when given.
+fn ok_page_bytes(docs: &[Arc<Value>], total: Option<usize>) -> Vec<u8> {
     let mut buf = Vec::with_capacity(docs.len() * 200 + 64);
     buf.extend_from_slice(b"{\"ok\":true,\"data\":[");
     for (i, doc) in docs.iter().enumerate() {
         if i > 0 { buf.push(b','); }
         serde_json::to_writer(&mut buf, doc.as_ref()).unwrap();
     }
-    buf.extend_from_slice(b"]}");
+    buf.push(b']');
+    if let Some(total) = total {
+        buf.extend_from_slice(format!(",\"total\":{total}").as_bytes());
+    }
+    buf.push(b'}');
     buf
 }
 
+/// Whether an `update`/`delete` (or `_one`) request only asks what it would
+/// change. Dry runs write nothing, so cluster mode answers them locally.
+pub fn is_dry_run(request: &Value) -> bool {
+    request.get("dry_run").and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+/// Dry-run `update`: the would-be-updated documents, unchanged on disk.
+fn update_dry_run_bytes(
+    db: &OxiDb,
+    col: &str,
+    query: &Value,
+    update: &Value,
+    limit: Option<usize>,
+    expected_version: Option<u64>,
+) -> Vec<u8> {
+    match db.update_dry_run(col, query, update, limit, expected_version) {
+        Ok(docs) => {
+            let ids: Vec<&Value> = docs.iter().filter_map(|d| d.get("_id")).collect();
+            ok_bytes(json!({ "dry_run": true, "modified": docs.len(), "ids": ids, "documents": docs }))
+        }
+        Err(e) => error_bytes(&e),
+    }
+}
+
+/// Dry-run `delete`: the IDs that would be deleted.
+fn delete_dry_run_bytes(db: &OxiDb, col: &str, query: &Value, limit: Option<usize>) -> Vec<u8> {
+    match db.delete_dry_run(col, query, limit) {
+        Ok(ids) => ok_bytes(json!({ "dry_run": true, "deleted": ids.len(), "ids": ids })),
+        Err(e) => error_bytes(&e),
+    }
+}
+
+/// Consistency a `find`, `find_one` or `count` asks for with `read_concern`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadConcern {
+    /// Read this node's data as it is (the default).
+    Local,
+    /// In cluster mode, read on the leader once it has confirmed its
+    /// leadership with a quorum and applied every committed write. A single
+    /// server is always linearizable.
+    Linearizable,
+}
+
+impl ReadConcern {
+    /// The request's `read_concern`, `Local` when absent.
+    pub fn from_request(request: &Value) -> Result<Self, String> {
+        match request.get("read_concern") {
+            None => Ok(Self::Local),
+            Some(v) => match v.as_str() {
+                Some("local") => Ok(Self::Local),
+                Some("linearizable") => Ok(Self::Linearizable),
+                _ => Err("'read_concern' must be \"local\" or \"linearizable\"".into()),
+            },
+        }
+    }
+}
+
+/// The `durability` an `insert`, `insert_many` or `bulk_write` asks for:
+/// `"full"` (the default) or `"relaxed"`, which skips the fsync.
+pub fn durability_from_request(request: &Value) -> Result<Durability, String> {
+    match request.get("durability") {
+        None => Ok(Durability::Full),
+        Some(v) => match v.as_str() {
+            Some("full") => Ok(Durability::Full),
+            Some("relaxed") => Ok(Durability::Relaxed),
+            _ => Err("'durability' must be \"full\" or \"relaxed\"".into()),
+        },
+    }
+}
+
 /// Handle a single JSON request and return pre-serialized JSON response bytes.
+/// Each command is counted in the database's metrics, along with whether it failed.
 pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u64>) -> Vec<u8> {
     let cmd = match request.get("cmd").and_then(|v| v.as_str().map(|s| s.to_string())) {
         Some(c) => c,
         None => return err_bytes("missing or invalid 'cmd' field"),
     };
 
+    let resp = execute_command(db, &cmd, request, active_tx);
+    // Every error response is built by `err_bytes`/`err_code_bytes`
+    db.metrics().record_command(&cmd, resp.starts_with(b"{\"ok\":false"));
+    resp
+}
+
+fn execute_command(db: &Arc<OxiDb>, cmd: &str, request: Value, active_tx: &mut Option<u64>) -> Vec<u8> {
     let collection: Option<String> = request.get("collection").and_then(|v| v.as_str().map(|s| s.to_string()));
 
     // Take ownership of mutable request for extracting fields without cloning
     let mut request = request;
 
-    match cmd.as_str() {
+    if matches!(cmd, "find" | "find_one" | "count") {
+        match ReadConcern::from_request(&request) {
+            Err(e) => return err_bytes(&e),
+            Ok(ReadConcern::Linearizable) if active_tx.is_some() => {
+                return err_bytes("read_concern \"linearizable\" can't be used in a transaction");
+            }
+            Ok(_) => {}
+        }
+    }
+    let durability = match cmd {
+        "insert" | "insert_many" | "bulk_write" => match durability_from_request(&request) {
+            Err(e) => return err_bytes(&e),
+            Ok(Durability::Relaxed) if active_tx.is_some() => {
+                return err_bytes("durability \"relaxed\" can't be used in a transaction");
+            }
+            Ok(d) => d,
+        },
+        _ => Durability::Full,
+    };
+
+    match cmd {
         "ping" => ok_bytes(json!("pong")),
 
+        "metrics" => ok_bytes(json!(db.metrics_text())),
+
+        // Liveness: answers without touching storage
+        "healthz" => ok_bytes(json!({ "status": "ok" })),
+
+        // Readiness: the data directory must accept writes
+        "readyz" => match db.check_writable() {
+            Ok(()) => ok_bytes(json!({ "status": "ready" })),
+            Err(e) => err_code_bytes(&format!("data directory not writable: {e}"), "not_ready"),
+        },
+
         // -------------------------------------------------------------------
         // Transaction commands
         // -------------------------------------------------------------------
@@ -50,7 +196,15 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             if active_tx.is_some() {
                 return err_bytes("transaction already active");
             }
-            let tx_id = db.begin_transaction();
+            let read_only = request
+                .get("read_only")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let tx_id = if read_only {
+                db.begin_read_transaction()
+            } else {
+                db.begin_transaction()
+            };
             *active_tx = Some(tx_id);
             ok_bytes(json!({ "tx_id": tx_id }))
         }
@@ -59,9 +213,9 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             match active_tx.take() {
                 Some(tx_id) => match db.commit_transaction(tx_id) {
                     Ok(()) => ok_bytes(json!("committed")),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 },
-                None => err_bytes("no active transaction"),
+                None => error_bytes(&oxidb::Error::NoActiveTransaction),
             }
         }
 
@@ -71,7 +225,37 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                     let _ = db.rollback_transaction(tx_id);
                     ok_bytes(json!("rolled back"))
                 }
-                None => err_bytes("no active transaction"),
+                None => error_bytes(&oxidb::Error::NoActiveTransaction),
+            }
+        }
+
+        "savepoint" => {
+            let tx_id = match *active_tx {
+                Some(id) => id,
+                None => return error_bytes(&oxidb::Error::NoActiveTransaction),
+            };
+            let name = match request.get("name").and_then(|v| v.as_str()) {
+                Some(n) => n,
+                None => return err_bytes("missing 'name'"),
+            };
+            match db.tx_savepoint(tx_id, name) {
+                Ok(()) => ok_bytes(json!("ok")),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "rollback_to" => {
+            let tx_id = match *active_tx {
+                Some(id) => id,
+                None => return error_bytes(&oxidb::Error::NoActiveTransaction),
+            };
+            let name = match request.get("name").and_then(|v| v.as_str()) {
+                Some(n) => n,
+                None => return err_bytes("missing 'name'"),
+            };
+            match db.tx_rollback_to(tx_id, name) {
+                Ok(()) => ok_bytes(json!("rolled back")),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -91,12 +275,12 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             if let Some(tx_id) = *active_tx {
                 match db.tx_insert(tx_id, col, doc) {
                     Ok(()) => ok_bytes(json!("buffered")),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             } else {
-                match db.insert(col, doc) {
+                match db.insert_with_durability(col, doc, durability) {
                     Ok(id) => ok_bytes(json!({ "id": id })),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             }
         }
@@ -113,14 +297,14 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             if let Some(tx_id) = *active_tx {
                 for doc in docs {
                     if let Err(e) = db.tx_insert(tx_id, col, doc) {
-                        return err_bytes(&e.to_string());
+                        return error_bytes(&e);
                     }
                 }
                 ok_bytes(json!("buffered"))
             } else {
-                match db.insert_many(col, docs) {
+                match db.insert_many_with_durability(col, docs, durability) {
                     Ok(ids) => ok_bytes(json!(ids)),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             }
         }
@@ -132,24 +316,53 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             let empty = json!({});
             let query = request.get("query").unwrap_or(&empty);
+            let with_count = request.get("with_count").and_then(|v| v.as_bool()).unwrap_or(false);
             if let Some(tx_id) = *active_tx {
+                // Transactional reads ignore skip/limit and respect_ttl, so the page is every match
                 match db.tx_find(tx_id, col, query) {
+                    Ok(docs) if with_count => {
+                        let total = docs.len();
+                        serde_json::to_vec(&json!({"ok": true, "data": docs, "total": total})).unwrap()
+                    }
                     Ok(docs) => ok_bytes(json!(docs)),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             } else {
                 let opts = match parse_find_options(&request) {
                     Ok(o) => o,
-                    Err(e) => return err_bytes(&e.to_string()),
+                    Err(e) => return error_bytes(&e),
                 };
+                if with_count {
+                    return match db.find_with_count_arcs(col, query, &opts) {
+                        Ok((arcs, total)) => ok_page_bytes(&arcs, Some(total)),
+                        Err(e) => error_bytes(&e),
+                    };
+                }
                 // Zero-copy path: serialize directly from Arc references
                 match db.find_with_options_arcs(col, query, &opts) {
                     Ok(arcs) => ok_docs_bytes(&arcs),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             }
         }
 
+        "explain" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            let empty = json!({});
+            let query = request.get("query").unwrap_or(&empty);
+            let opts = match parse_find_options(&request) {
+                Ok(o) => o,
+                Err(e) => return error_bytes(&e),
+            };
+            match db.explain(col, query, &opts) {
+                Ok(plan) => ok_bytes(plan),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
         "find_one" => {
             let col = match collection.as_deref() {
                 Some(c) => c,
@@ -157,9 +370,16 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             let empty = json!({});
             let query = request.get("query").unwrap_or(&empty);
-            match db.find_one(col, query) {
+            let found = match request.get("respect_ttl").and_then(|v| v.as_bool()).unwrap_or(false) {
+                true => {
+                    let opts = FindOptions { limit: Some(1), respect_ttl: true, ..FindOptions::default() };
+                    db.find_with_options(col, query, &opts).map(|mut docs| docs.pop())
+                }
+                false => db.find_one(col, query),
+            };
+            match found {
                 Ok(doc) => ok_bytes(json!(doc)),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -176,15 +396,29 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                 Some(u) => u,
                 None => return err_bytes("missing 'update'"),
             };
+            let expected_version = match request.get("expected_version").map(|v| v.as_u64()) {
+                None => None,
+                Some(Some(v)) => Some(v),
+                Some(None) => return err_bytes("'expected_version' must be a non-negative integer"),
+            };
+            if is_dry_run(&request) {
+                if active_tx.is_some() {
+                    return err_bytes("'dry_run' can't be used in a transaction");
+                }
+                return update_dry_run_bytes(db, col, query, update, None, expected_version);
+            }
             if let Some(tx_id) = *active_tx {
+                if expected_version.is_some() {
+                    return err_bytes("'expected_version' can't be used in a transaction");
+                }
                 match db.tx_update(tx_id, col, query, update) {
                     Ok(()) => ok_bytes(json!("buffered")),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             } else {
-                match db.update(col, query, update) {
+                match db.update_with_version(col, query, update, expected_version) {
                     Ok(count) => ok_bytes(json!({ "modified": count })),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             }
         }
@@ -202,9 +436,34 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                 Some(u) => u,
                 None => return err_bytes("missing 'update'"),
             };
-            match db.update_one(col, query, update) {
+            let expected_version = match request.get("expected_version").map(|v| v.as_u64()) {
+                None => None,
+                Some(Some(v)) => Some(v),
+                Some(None) => return err_bytes("'expected_version' must be a non-negative integer"),
+            };
+            if is_dry_run(&request) {
+                return update_dry_run_bytes(db, col, query, update, Some(1), expected_version);
+            }
+            match db.update_one_with_version(col, query, update, expected_version) {
                 Ok(count) => ok_bytes(json!({ "modified": count })),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "find_and_modify" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            let empty = json!({});
+            let query = request.get("query").unwrap_or(&empty);
+            let opts = match parse_find_and_modify_options(&request) {
+                Ok(o) => o,
+                Err(e) => return error_bytes(&e),
+            };
+            match db.find_and_modify(col, query, request.get("update"), &opts) {
+                Ok(doc) => ok_bytes(json!(doc)),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -217,15 +476,21 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                 Some(q) => q,
                 None => return err_bytes("missing 'query'"),
             };
+            if is_dry_run(&request) {
+                if active_tx.is_some() {
+                    return err_bytes("'dry_run' can't be used in a transaction");
+                }
+                return delete_dry_run_bytes(db, col, query, None);
+            }
             if let Some(tx_id) = *active_tx {
                 match db.tx_delete(tx_id, col, query) {
                     Ok(()) => ok_bytes(json!("buffered")),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             } else {
                 match db.delete(col, query) {
                     Ok(count) => ok_bytes(json!({ "deleted": count })),
-                    Err(e) => err_bytes(&e.to_string()),
+                    Err(e) => error_bytes(&e),
                 }
             }
         }
@@ -239,9 +504,31 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                 Some(q) => q,
                 None => return err_bytes("missing 'query'"),
             };
+            if is_dry_run(&request) {
+                return delete_dry_run_bytes(db, col, query, Some(1));
+            }
             match db.delete_one(col, query) {
                 Ok(count) => ok_bytes(json!({ "deleted": count })),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "bulk_write" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            let ops = match request.get("ops").map(oxidb::bulk::parse_bulk_ops) {
+                Some(Ok(ops)) => ops,
+                Some(Err(e)) => return error_bytes(&e),
+                None => return err_bytes("missing 'ops'"),
+            };
+            if active_tx.is_some() {
+                return err_bytes("bulk_write can't be used in a transaction");
+            }
+            match db.bulk_write_with_durability(col, ops, durability) {
+                Ok(results) => ok_bytes(json!(results.iter().map(|r| r.to_json()).collect::<Vec<_>>())),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -254,7 +541,24 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             let query = request.get("query").unwrap_or(&empty);
             match db.count(col, query) {
                 Ok(n) => ok_bytes(json!({ "count": n })),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "distinct" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            let field = match request.get("field").and_then(|v| v.as_str()) {
+                Some(f) => f,
+                None => return err_bytes("missing 'field'"),
+            };
+            let empty = json!({});
+            let query = request.get("query").unwrap_or(&empty);
+            match db.distinct(col, field, query) {
+                Ok(values) => ok_bytes(json!(values)),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -267,9 +571,19 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                 Some(f) => f,
                 None => return err_bytes("missing 'field'"),
             };
-            match db.create_index(col, field) {
+            let flag = |key: &str| request.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+            let created = if flag("sparse") {
+                db.create_sparse_index(col, field)
+            } else {
+                db.create_index(col, field)
+            };
+            let result = match created {
+                Ok(()) if flag("descending") => db.create_descending_index(col, field),
+                other => other,
+            };
+            match result {
                 Ok(()) => ok_bytes(json!("index created")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -284,7 +598,7 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.create_unique_index(col, field) {
                 Ok(()) => ok_bytes(json!("unique index created")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -306,7 +620,7 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.create_composite_index(col, fields) {
                 Ok(name) => ok_bytes(json!({ "index": name })),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -328,7 +642,22 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.create_text_index(col, fields) {
                 Ok(()) => ok_bytes(json!("text index created")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "create_geo_index" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            let field = match request.get("field").and_then(|v| v.as_str()) {
+                Some(f) => f,
+                None => return err_bytes("missing 'field'"),
+            };
+            match db.create_geo_index(col, field) {
+                Ok(()) => ok_bytes(json!("geo index created")),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -339,7 +668,90 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.list_indexes(col) {
                 Ok(indexes) => ok_bytes(json!(indexes)),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "describe" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            let sample = request
+                .get("sample")
+                .and_then(|v| v.as_u64())
+                .map_or(oxidb::DEFAULT_DESCRIBE_SAMPLE, |n| n as usize);
+            match db.describe(col, sample) {
+                Ok(desc) => ok_bytes(json!(desc)),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "set_schema" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            let schema = match request.get_mut("schema").map(Value::take) {
+                Some(Value::Null) => None,
+                Some(schema) => {
+                    let level = match request.get("level").and_then(|v| v.as_str()) {
+                        Some(level) => match oxidb::ValidationLevel::parse(level) {
+                            Ok(level) => level,
+                            Err(e) => return error_bytes(&e),
+                        },
+                        None => oxidb::ValidationLevel::Strict,
+                    };
+                    let coerce = request.get("coerce").and_then(|v| v.as_bool()).unwrap_or(false);
+                    match oxidb::CollectionSchema::new(schema, level, coerce) {
+                        Ok(schema) => Some(schema),
+                        Err(e) => return error_bytes(&e),
+                    }
+                }
+                None => return err_bytes("missing 'schema'"),
+            };
+            let msg = if schema.is_some() { "schema set" } else { "schema removed" };
+            match db.set_collection_schema(col, schema) {
+                Ok(()) => ok_bytes(json!(msg)),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "get_schema" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            match db.get_collection_schema(col) {
+                Ok(schema) => ok_bytes(json!(schema)),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "set_defaults" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            let defaults = match request.get_mut("defaults").map(Value::take) {
+                Some(Value::Null) => Ok(oxidb::FieldDefaults::default()),
+                Some(spec) => oxidb::FieldDefaults::new(spec),
+                None => return err_bytes("missing 'defaults'"),
+            };
+            match defaults.and_then(|defaults| db.set_collection_defaults(col, defaults)) {
+                Ok(()) => ok_bytes(json!("defaults set")),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "get_defaults" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            match db.get_collection_defaults(col) {
+                Ok(defaults) => ok_bytes(json!(defaults)),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -354,7 +766,7 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.drop_index(col, index) {
                 Ok(()) => ok_bytes(json!("index dropped")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -371,9 +783,23 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                 .get("limit")
                 .and_then(|v| v.as_u64())
                 .unwrap_or(10) as usize;
+            let count_only = request.get("count_only").and_then(|v| v.as_bool()).unwrap_or(false);
+            if let Some(filter) = request.get("filter") {
+                return match db.text_search_filtered(col, query, filter, limit) {
+                    Ok(results) if count_only => ok_bytes(json!({ "count": results.len() })),
+                    Ok(results) => ok_bytes(json!(results)),
+                    Err(e) => error_bytes(&e),
+                };
+            }
+            if count_only {
+                return match db.text_search_count(col, query, limit) {
+                    Ok(n) => ok_bytes(json!({ "count": n })),
+                    Err(e) => error_bytes(&e),
+                };
+            }
             match db.text_search(col, query, limit) {
                 Ok(results) => ok_bytes(json!(results)),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -384,7 +810,7 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.create_collection(col) {
                 Ok(()) => ok_bytes(json!("collection created")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -400,7 +826,7 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.drop_collection(col) {
                 Ok(()) => ok_bytes(json!("collection dropped")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -415,10 +841,61 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                     "new_size": stats.new_size,
                     "docs_kept": stats.docs_kept
                 })),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "flush" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            match db.flush(col) {
+                Ok(()) => ok_bytes(json!("flushed")),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "repair" => {
+            let col = match collection.as_deref() {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            match db.repair(col) {
+                Ok(report) => ok_bytes(json!({
+                    "recovered": report.recovered,
+                    "lost": report.lost,
+                    "quarantine": report.quarantine.map(|p| p.to_string_lossy().into_owned())
+                })),
+                Err(e) => error_bytes(&e),
             }
         }
 
+        "compact_all" => match db.compact_all() {
+            Ok(stats) => {
+                let collections: Vec<Value> = stats
+                    .collections
+                    .iter()
+                    .map(|(name, result)| match result {
+                        Ok(s) => json!({
+                            "collection": name,
+                            "old_size": s.old_size,
+                            "new_size": s.new_size,
+                            "docs_kept": s.docs_kept
+                        }),
+                        Err(e) => json!({"collection": name, "error": e.to_string(), "code": e.code()}),
+                    })
+                    .collect();
+                ok_bytes(json!({
+                    "old_size": stats.total.old_size,
+                    "new_size": stats.total.new_size,
+                    "docs_kept": stats.total.docs_kept,
+                    "collections": collections
+                }))
+            }
+            Err(e) => error_bytes(&e),
+        },
+
         "aggregate" => {
             let col = match collection.as_deref() {
                 Some(c) => c,
@@ -430,7 +907,7 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.aggregate(col, pipeline) {
                 Ok(docs) => ok_bytes(json!(docs)),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -443,9 +920,13 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                 Some(b) => b,
                 None => return err_bytes("missing 'bucket'"),
             };
-            match db.create_bucket(bucket) {
+            let created = match request.get("owner").and_then(|v| v.as_str()) {
+                Some(owner) => db.create_owned_bucket(bucket, owner),
+                None => db.create_bucket(bucket),
+            };
+            match created {
                 Ok(()) => ok_bytes(json!("bucket created")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -461,7 +942,7 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.delete_bucket(bucket) {
                 Ok(()) => ok_bytes(json!("bucket deleted")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -497,7 +978,112 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                 .unwrap_or_default();
             match db.put_object(bucket, key, &data, content_type, metadata) {
                 Ok(meta) => ok_bytes(meta),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "copy_object" => {
+            let src_bucket = match request.get("src_bucket").and_then(|v| v.as_str()) {
+                Some(b) => b,
+                None => return err_bytes("missing 'src_bucket'"),
+            };
+            let src_key = match request.get("src_key").and_then(|v| v.as_str()) {
+                Some(k) => k,
+                None => return err_bytes("missing 'src_key'"),
+            };
+            let dst_bucket = request
+                .get("dst_bucket")
+                .and_then(|v| v.as_str())
+                .unwrap_or(src_bucket);
+            let dst_key = match request.get("dst_key").and_then(|v| v.as_str()) {
+                Some(k) => k,
+                None => return err_bytes("missing 'dst_key'"),
+            };
+            let content_type = request.get("content_type").and_then(|v| v.as_str());
+            let metadata: Option<HashMap<String, String>> = request
+                .get("metadata")
+                .and_then(|v| v.as_object())
+                .map(|obj| {
+                    obj.iter()
+                        .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
+                        .collect()
+                });
+            match db.copy_object(src_bucket, src_key, dst_bucket, dst_key, content_type, metadata) {
+                Ok(meta) => ok_bytes(meta),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "create_multipart_upload" => {
+            let bucket = match request.get("bucket").and_then(|v| v.as_str()) {
+                Some(b) => b,
+                None => return err_bytes("missing 'bucket'"),
+            };
+            let key = match request.get("key").and_then(|v| v.as_str()) {
+                Some(k) => k,
+                None => return err_bytes("missing 'key'"),
+            };
+            let content_type = request
+                .get("content_type")
+                .and_then(|v| v.as_str())
+                .unwrap_or("application/octet-stream");
+            let metadata: HashMap<String, String> = request
+                .get("metadata")
+                .and_then(|v| v.as_object())
+                .map(|obj| {
+                    obj.iter()
+                        .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
+                        .collect()
+                })
+                .unwrap_or_default();
+            match db.create_multipart_upload(bucket, key, content_type, metadata) {
+                Ok(upload_id) => ok_bytes(json!({"upload_id": upload_id})),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "upload_part" => {
+            let upload_id = match request.get("upload_id").and_then(|v| v.as_str()) {
+                Some(u) => u,
+                None => return err_bytes("missing 'upload_id'"),
+            };
+            let part_number = match request.get("part_number").and_then(|v| v.as_u64()) {
+                Some(n) if n <= u32::MAX as u64 => n as u32,
+                _ => return err_bytes("missing or invalid 'part_number'"),
+            };
+            let data_b64 = match request.get("data").and_then(|v| v.as_str()) {
+                Some(d) => d,
+                None => return err_bytes("missing 'data' (base64)"),
+            };
+            let data = match base64::engine::general_purpose::STANDARD.decode(data_b64) {
+                Ok(d) => d,
+                Err(e) => return err_bytes(&format!("invalid base64: {e}")),
+            };
+            match db.upload_part(upload_id, part_number, &data) {
+                Ok(etag) => ok_bytes(json!({"part_number": part_number, "etag": etag})),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "complete_multipart_upload" => {
+            let upload_id = match request.get("upload_id").and_then(|v| v.as_str()) {
+                Some(u) => u,
+                None => return err_bytes("missing 'upload_id'"),
+            };
+            match db.complete_multipart_upload(upload_id) {
+                Ok(meta) => ok_bytes(meta),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "abort_multipart_upload" => {
+            let upload_id = match request.get("upload_id").and_then(|v| v.as_str()) {
+                Some(u) => u,
+                None => return err_bytes("missing 'upload_id'"),
+            };
+            match db.abort_multipart_upload(upload_id) {
+                Ok(()) => ok_bytes(json!("upload aborted")),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -510,7 +1096,14 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                 Some(k) => k,
                 None => return err_bytes("missing 'key'"),
             };
-            match db.get_object(bucket, key) {
+            let verify = request.get("verify").and_then(|v| v.as_bool()).unwrap_or(false);
+            let result = match (request.get("version_id").and_then(|v| v.as_str()), verify) {
+                (Some(_), true) => return err_bytes("'verify' can't be combined with 'version_id'"),
+                (Some(version_id), false) => db.get_object_version(bucket, key, version_id),
+                (None, true) => db.get_object_verified(bucket, key),
+                (None, false) => db.get_object(bucket, key),
+            };
+            match result {
                 Ok((data, meta)) => {
                     let encoded = base64::engine::general_purpose::STANDARD.encode(&data);
                     ok_bytes(json!({
@@ -518,10 +1111,148 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                         "metadata": meta,
                     }))
                 }
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "verify_object" => {
+            let bucket = match request.get("bucket").and_then(|v| v.as_str()) {
+                Some(b) => b,
+                None => return err_bytes("missing 'bucket'"),
+            };
+            let key = match request.get("key").and_then(|v| v.as_str()) {
+                Some(k) => k,
+                None => return err_bytes("missing 'key'"),
+            };
+            match db.verify_object(bucket, key) {
+                Ok(sha256) => ok_bytes(json!({
+                    "bucket": bucket,
+                    "key": key,
+                    "verified": sha256.is_some(),
+                    "sha256": sha256,
+                })),
+                Err(e) => error_bytes(&e),
             }
         }
 
+        "get_object_range" => {
+            let bucket = match request.get("bucket").and_then(|v| v.as_str()) {
+                Some(b) => b,
+                None => return err_bytes("missing 'bucket'"),
+            };
+            let key = match request.get("key").and_then(|v| v.as_str()) {
+                Some(k) => k,
+                None => return err_bytes("missing 'key'"),
+            };
+            let start = request.get("start").and_then(|v| v.as_u64());
+            let end = request.get("end").and_then(|v| v.as_u64());
+            let suffix = request.get("suffix").and_then(|v| v.as_u64());
+            let (start, end) = match (start, suffix) {
+                (Some(_), Some(_)) => return err_bytes("'start' and 'suffix' are mutually exclusive"),
+                (Some(s), None) => (Some(s), end),
+                (None, Some(n)) => (None, Some(n)),
+                (None, None) => return err_bytes("missing 'start' or 'suffix'"),
+            };
+            match db.get_object_range(bucket, key, start, end) {
+                Ok((range, meta)) => {
+                    let encoded = base64::engine::general_purpose::STANDARD.encode(&range.data);
+                    ok_bytes(json!({
+                        "content": encoded,
+                        "content_range": format!("bytes {}-{}/{}", range.start, range.end, range.total_size),
+                        "metadata": meta,
+                    }))
+                }
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "list_object_versions" => {
+            let bucket = match request.get("bucket").and_then(|v| v.as_str()) {
+                Some(b) => b,
+                None => return err_bytes("missing 'bucket'"),
+            };
+            let key = match request.get("key").and_then(|v| v.as_str()) {
+                Some(k) => k,
+                None => return err_bytes("missing 'key'"),
+            };
+            match db.list_object_versions(bucket, key) {
+                Ok(list) => ok_bytes(json!(list)),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "enable_versioning" => {
+            let bucket = match request.get("bucket").and_then(|v| v.as_str()) {
+                Some(b) => b,
+                None => return err_bytes("missing 'bucket'"),
+            };
+            let enabled = request
+                .get("enabled")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(true);
+            match db.set_bucket_versioning(bucket, enabled) {
+                Ok(()) => ok_bytes(json!({"bucket": bucket, "versioning": enabled})),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "set_bucket_tokenizer" => {
+            let bucket = match request.get("bucket").and_then(|v| v.as_str()) {
+                Some(b) => b,
+                None => return err_bytes("missing 'bucket'"),
+            };
+            let tokenizer = match request.get("tokenizer").and_then(|v| v.as_str()) {
+                Some(t) => match oxidb::fts::Tokenizer::parse(t) {
+                    Ok(t) => t,
+                    Err(e) => return err_bytes(&e),
+                },
+                None => return err_bytes("missing 'tokenizer'"),
+            };
+            match db.set_bucket_tokenizer(bucket, tokenizer) {
+                Ok(skipped) => ok_bytes(json!({
+                    "bucket": bucket,
+                    "tokenizer": tokenizer.as_str(),
+                    "not_reindexed": skipped,
+                })),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "get_bucket_tokenizer" => {
+            let bucket = match request.get("bucket").and_then(|v| v.as_str()) {
+                Some(b) => b,
+                None => return err_bytes("missing 'bucket'"),
+            };
+            ok_bytes(json!({"bucket": bucket, "tokenizer": db.bucket_tokenizer(bucket).as_str()}))
+        }
+
+        "set_bucket_acl" => {
+            let bucket = match request.get("bucket").and_then(|v| v.as_str()) {
+                Some(b) => b,
+                None => return err_bytes("missing 'bucket'"),
+            };
+            let acl = match request.get("acl") {
+                Some(Value::Null) => None,
+                Some(v) => match serde_json::from_value::<oxidb::blob::BucketAcl>(v.clone()) {
+                    Ok(acl) if !acl.owner.is_empty() => Some(acl),
+                    _ => return err_bytes("'acl' must be null or an object with 'owner', 'readers' and 'writers'"),
+                },
+                None => return err_bytes("missing 'acl'"),
+            };
+            match db.set_bucket_acl(bucket, acl.clone()) {
+                Ok(()) => ok_bytes(json!({"bucket": bucket, "acl": acl})),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "get_bucket_acl" => {
+            let bucket = match request.get("bucket").and_then(|v| v.as_str()) {
+                Some(b) => b,
+                None => return err_bytes("missing 'bucket'"),
+            };
+            ok_bytes(json!({"bucket": bucket, "acl": db.bucket_acl(bucket)}))
+        }
+
         "head_object" => {
             let bucket = match request.get("bucket").and_then(|v| v.as_str()) {
                 Some(b) => b,
@@ -533,7 +1264,7 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.head_object(bucket, key) {
                 Ok(meta) => ok_bytes(meta),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -548,7 +1279,7 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.delete_object(bucket, key) {
                 Ok(()) => ok_bytes(json!("object deleted")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -564,7 +1295,7 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                 .map(|n| n as usize);
             match db.list_objects(bucket, prefix, limit) {
                 Ok(list) => ok_bytes(json!(list)),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -578,9 +1309,13 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                 .get("limit")
                 .and_then(|v| v.as_u64())
                 .unwrap_or(10) as usize;
-            match db.search(bucket, query, limit) {
+            let offset = request
+                .get("offset")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0) as usize;
+            match db.search(bucket, query, offset, limit) {
                 Ok(results) => ok_bytes(json!(results)),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -593,13 +1328,22 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                 Some(p) => p,
                 None => return err_bytes("missing 'path'"),
             };
-            match db.backup(std::path::Path::new(path)) {
+            let result = match request.get("incremental_from").and_then(|v| v.as_str()) {
+                Some(manifest) => db.backup_incremental(
+                    std::path::Path::new(path),
+                    std::path::Path::new(manifest),
+                ),
+                None => db.backup(std::path::Path::new(path)),
+            };
+            match result {
                 Ok(info) => ok_bytes(json!({
                     "path": info.path,
                     "size_bytes": info.size_bytes,
                     "collections": info.collections,
+                    "archived_collections": info.archived_collections,
+                    "manifest": info.manifest_path,
                 })),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -612,16 +1356,56 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                 Some(t) => t,
                 None => return err_bytes("missing 'target'"),
             };
-            match oxidb::OxiDb::restore(
-                std::path::Path::new(archive),
-                std::path::Path::new(target),
-            ) {
+            let mut archives = vec![archive];
+            if let Some(incrementals) = request.get("incrementals").and_then(|v| v.as_array()) {
+                for inc in incrementals {
+                    match inc.as_str() {
+                        Some(a) => archives.push(a),
+                        None => return err_bytes("'incrementals' must be an array of paths"),
+                    }
+                }
+            }
+            match oxidb::OxiDb::restore_chain(&archives, std::path::Path::new(target)) {
                 Ok(info) => ok_bytes(json!({
                     "path": info.path,
                     "collections": info.collections,
                     "message": "restore complete; restart server with this data directory to use",
                 })),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "backup_collection" => {
+            let collection = match request.get("collection").and_then(|v| v.as_str()) {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            let path = match request.get("path").and_then(|v| v.as_str()) {
+                Some(p) => p,
+                None => return err_bytes("missing 'path'"),
+            };
+            match db.backup_collection(collection, std::path::Path::new(path)) {
+                Ok(info) => ok_bytes(json!({
+                    "path": info.path,
+                    "size_bytes": info.size_bytes,
+                    "collection": collection,
+                })),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "restore_collection" => {
+            let collection = match request.get("collection").and_then(|v| v.as_str()) {
+                Some(c) => c,
+                None => return err_bytes("missing 'collection'"),
+            };
+            let archive = match request.get("archive").and_then(|v| v.as_str()) {
+                Some(a) => a,
+                None => return err_bytes("missing 'archive'"),
+            };
+            match db.restore_collection(std::path::Path::new(archive), collection) {
+                Ok(_) => ok_bytes(json!({"collection": collection, "restored": true})),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -630,7 +1414,8 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                 Some(q) => q,
                 None => return err_bytes("missing 'query' string"),
             };
-            match oxidb::sql::execute_sql(db, query_str) {
+            let params = request.get("params").cloned().unwrap_or(Value::Null);
+            match oxidb::sql::execute_sql_with_params(db, query_str, &params) {
                 Ok(result) => match result {
                     oxidb::SqlResult::Select(docs) => ok_bytes(json!(docs)),
                     oxidb::SqlResult::Insert(ids) => ok_bytes(json!({ "ids": ids })),
@@ -638,7 +1423,7 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                     oxidb::SqlResult::Delete(count) => ok_bytes(json!({ "deleted": count })),
                     oxidb::SqlResult::Ddl(msg) => ok_bytes(json!(msg)),
                 },
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -653,7 +1438,7 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.create_procedure(&name, request) {
                 Ok(()) => ok_bytes(json!("procedure created")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -665,13 +1450,13 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             let params = request.get("params").cloned().unwrap_or(json!({}));
             match db.call_procedure(name, params) {
                 Ok(val) => ok_bytes(val),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
         "list_procedures" => match db.list_procedures() {
             Ok(names) => ok_bytes(json!(names)),
-            Err(e) => err_bytes(&e.to_string()),
+            Err(e) => error_bytes(&e),
         },
 
         "get_procedure" => {
@@ -681,7 +1466,7 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.get_procedure(name) {
                 Ok(def) => ok_bytes(def),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -692,7 +1477,7 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.delete_procedure(name) {
                 Ok(()) => ok_bytes(json!("procedure deleted")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -707,13 +1492,13 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.create_schedule(&name, request) {
                 Ok(()) => ok_bytes(json!("schedule created")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
         "list_schedules" => match db.list_schedules() {
             Ok(schedules) => ok_bytes(json!(schedules)),
-            Err(e) => err_bytes(&e.to_string()),
+            Err(e) => error_bytes(&e),
         },
 
         "get_schedule" => {
@@ -723,7 +1508,19 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.get_schedule(name) {
                 Ok(sched) => ok_bytes(sched),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "schedule_history" => {
+            let name = match request.get("name").and_then(|v| v.as_str()) {
+                Some(n) => n,
+                None => return err_bytes("missing 'name'"),
+            };
+            let limit = request.get("limit").and_then(|v| v.as_u64()).map(|n| n as usize);
+            match db.schedule_history(name, limit) {
+                Ok(runs) => ok_bytes(json!(runs)),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -734,7 +1531,7 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.delete_schedule(name) {
                 Ok(()) => ok_bytes(json!("schedule deleted")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -745,7 +1542,18 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.enable_schedule(name) {
                 Ok(()) => ok_bytes(json!("schedule enabled")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
+            }
+        }
+
+        "run_schedule" => {
+            let name = match request.get("name").and_then(|v| v.as_str()) {
+                Some(n) => n,
+                None => return err_bytes("missing 'name'"),
+            };
+            match db.run_schedule_now(name) {
+                Ok(result) => ok_bytes(result),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -756,7 +1564,7 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
             };
             match db.disable_schedule(name) {
                 Ok(()) => ok_bytes(json!("schedule disabled")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -781,10 +1589,30 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                 .get("metric")
                 .and_then(|v| v.as_str())
                 .unwrap_or("cosine");
-            let metric = oxidb::vector::VectorIndex::parse_metric(metric_str);
-            match db.create_vector_index(col, field, dimension, metric) {
+            let metric = match oxidb::vector::DistanceMetric::parse(metric_str) {
+                Ok(m) => m,
+                Err(e) => return err_bytes(&e),
+            };
+            let normalize = match request.get("normalize").map(|v| v.as_bool()) {
+                None => false,
+                Some(Some(b)) => b,
+                Some(None) => return err_bytes("'normalize' must be a boolean"),
+            };
+            let mut hnsw = [None, None];
+            for (slot, name) in hnsw.iter_mut().zip(["m", "ef_construction"]) {
+                match request.get(name).map(|v| v.as_u64()) {
+                    None => {}
+                    Some(Some(n)) => *slot = Some(n as usize),
+                    Some(None) => return err_bytes(&format!("'{name}' must be a non-negative integer")),
+                }
+            }
+            let params = match oxidb::vector::HnswParams::new(hnsw[0], hnsw[1]) {
+                Ok(p) => p,
+                Err(e) => return err_bytes(&e),
+            };
+            match db.create_vector_index(col, field, dimension, metric, params, normalize) {
                 Ok(()) => ok_bytes(json!("vector index created")),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -815,9 +1643,22 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
                 .get("ef_search")
                 .and_then(|v| v.as_u64())
                 .map(|v| v as usize);
-            match db.vector_search(col, field, &vector, limit, ef_search) {
+            let exact = request.get("exact").and_then(|v| v.as_bool()).unwrap_or(false);
+            let min_similarity = match request.get("min_similarity").map(|v| v.as_f64()) {
+                None => None,
+                Some(Some(s)) => Some(s as f32),
+                Some(None) => return err_bytes("'min_similarity' must be a number"),
+            };
+            let opts = oxidb::vector::VectorSearchOptions { ef_search, exact, min_similarity };
+            if request.get("count_only").and_then(|v| v.as_bool()).unwrap_or(false) {
+                return match db.vector_search_count(col, field, &vector, limit, &opts) {
+                    Ok(n) => ok_bytes(json!({ "count": n })),
+                    Err(e) => error_bytes(&e),
+                };
+            }
+            match db.vector_search(col, field, &vector, limit, &opts) {
                 Ok(results) => ok_bytes(json!(results)),
-                Err(e) => err_bytes(&e.to_string()),
+                Err(e) => error_bytes(&e),
             }
         }
 
@@ -825,7 +1666,7 @@ pub fn handle_request(db: &Arc<OxiDb>, request: Value, active_tx: &mut Option<u6
     }
 }
 
-/// Handle user management commands (requires admin role).
+/// Handle user, role and API key management commands (requires admin role).
 pub fn handle_user_command(
     cmd: &str,
     request: &Value,
@@ -842,12 +1683,20 @@ pub fn handle_user_command(
                 None => return Some(err_bytes("missing 'password'")),
             };
             let role_str = request.get("role").and_then(|v| v.as_str()).unwrap_or("read");
-            let role = match Role::from_str(role_str) {
-                Some(r) => r,
+            let must_change = request.get("must_change").and_then(|v| v.as_bool()).unwrap_or(false);
+            let mut store = user_store.lock().unwrap();
+            let result = match Role::from_str(role_str) {
+                Some(role) => store.create_user(username, password, role),
+                None if store.get_role(role_str).is_some() => store
+                    .create_user(username, password, Role::Read)
+                    .and_then(|()| store.assign_custom_role(username, role_str)),
                 None => return Some(err_bytes(&format!("invalid role: {role_str}"))),
             };
-            let mut store = user_store.lock().unwrap();
-            match store.create_user(username, password, role) {
+            let result = match result {
+                Ok(()) if must_change => store.set_must_change(username, true),
+                other => other,
+            };
+            match result {
                 Ok(()) => Some(ok_bytes(json!("user created"))),
                 Err(e) => Some(err_bytes(&e)),
             }
@@ -869,12 +1718,31 @@ pub fn handle_user_command(
                 None => return Some(err_bytes("missing 'username'")),
             };
             let password = request.get("password").and_then(|v| v.as_str());
-            let role = request.get("role").and_then(|v| v.as_str()).and_then(Role::from_str);
-            if password.is_none() && role.is_none() {
-                return Some(err_bytes("must specify 'password' or 'role' to update"));
+            let role_str = request.get("role").and_then(|v| v.as_str());
+            let must_change = request.get("must_change").and_then(|v| v.as_bool());
+            if password.is_none() && role_str.is_none() && must_change.is_none() {
+                return Some(err_bytes("must specify 'password', 'role' or 'must_change' to update"));
             }
             let mut store = user_store.lock().unwrap();
-            match store.update_user(username, password, role) {
+            let role = role_str.and_then(Role::from_str);
+            let custom_role = match role_str {
+                Some(name) if role.is_none() => match store.get_role(name) {
+                    Some(_) => Some(name),
+                    None => return Some(err_bytes(&format!("invalid role: {name}"))),
+                },
+                _ => None,
+            };
+            let result = store
+                .update_user(username, password, role)
+                .and_then(|()| match custom_role {
+                    Some(name) => store.assign_custom_role(username, name),
+                    None => Ok(()),
+                })
+                .and_then(|()| match must_change {
+                    Some(flag) => store.set_must_change(username, flag),
+                    None => Ok(()),
+                });
+            match result {
                 Ok(()) => Some(ok_bytes(json!("user updated"))),
                 Err(e) => Some(err_bytes(&e)),
             }
@@ -884,6 +1752,147 @@ pub fn handle_user_command(
             let users = store.list_users();
             Some(ok_bytes(json!(users)))
         }
+        "create_role" => {
+            let name = match request.get("name").and_then(|v| v.as_str()) {
+                Some(n) => n,
+                None => return Some(err_bytes("missing 'name'")),
+            };
+            let commands = match request.get("commands").and_then(|v| v.as_array()) {
+                Some(arr) => {
+                    let mut commands = Vec::with_capacity(arr.len());
+                    for v in arr {
+                        match v.as_str() {
+                            Some(c) => commands.push(c.to_string()),
+                            None => return Some(err_bytes("'commands' must be an array of strings")),
+                        }
+                    }
+                    commands
+                }
+                None => return Some(err_bytes("missing 'commands' array")),
+            };
+            let mut store = user_store.lock().unwrap();
+            match store.create_role(name, commands) {
+                Ok(()) => Some(ok_bytes(json!("role created"))),
+                Err(e) => Some(err_bytes(&e)),
+            }
+        }
+        "drop_role" => {
+            let name = match request.get("name").and_then(|v| v.as_str()) {
+                Some(n) => n,
+                None => return Some(err_bytes("missing 'name'")),
+            };
+            let mut store = user_store.lock().unwrap();
+            match store.drop_role(name) {
+                Ok(()) => Some(ok_bytes(json!("role dropped"))),
+                Err(e) => Some(err_bytes(&e)),
+            }
+        }
+        "list_roles" => {
+            let store = user_store.lock().unwrap();
+            Some(ok_bytes(json!(store.list_roles())))
+        }
+        "create_api_key" => {
+            let username = match request.get("username").and_then(|v| v.as_str()) {
+                Some(u) => u,
+                None => return Some(err_bytes("missing 'username'")),
+            };
+            let expires_in = request.get("expires_in_secs").and_then(|v| v.as_u64());
+            let mut store = user_store.lock().unwrap();
+            match store.create_api_key(username, expires_in) {
+                Ok((id, key)) => Some(ok_bytes(json!({"id": id, "key": key}))),
+                Err(e) => Some(err_bytes(&e)),
+            }
+        }
+        "revoke_api_key" => {
+            let id = match request.get("id").and_then(|v| v.as_str()) {
+                Some(i) => i,
+                None => return Some(err_bytes("missing 'id'")),
+            };
+            let mut store = user_store.lock().unwrap();
+            match store.revoke_api_key(id) {
+                Ok(()) => Some(ok_bytes(json!("api key revoked"))),
+                Err(e) => Some(err_bytes(&e)),
+            }
+        }
+        "list_api_keys" => {
+            let store = user_store.lock().unwrap();
+            let keys: Vec<Value> = store
+                .list_api_keys()
+                .iter()
+                .map(|k| {
+                    json!({
+                        "id": k.id,
+                        "username": k.username,
+                        "created_at": k.created_at,
+                        "expires_at": k.expires_at,
+                        "revoked": k.revoked,
+                    })
+                })
+                .collect();
+            Some(ok_bytes(json!(keys)))
+        }
         _ => None,
     }
 }
+
+/// Handle `query_audit`: filter the audit log by user, command, collection,
+/// result and time range.
+pub fn handle_query_audit(request: &Value, audit_log: Option<&AuditLog>) -> Vec<u8> {
+    let Some(audit_log) = audit_log else {
+        return err_bytes("audit logging is disabled (set OXIDB_AUDIT=true)");
+    };
+    match AuditQuery::from_request(request).and_then(|q| audit_log.query(&q)) {
+        Ok(events) => ok_bytes(Value::Array(events)),
+        Err(e) => err_bytes(&e),
+    }
+}
+
+/// Handle `query_slow_log`: this node's most recent slow queries, oldest
+/// first, optionally only those on `collection`, at most `limit` of them.
+pub fn handle_query_slow_log(request: &Value, slow_query_log: Option<&SlowQueryLog>) -> Vec<u8> {
+    let Some(slow_query_log) = slow_query_log else {
+        return err_bytes("the slow query log is disabled (set OXIDB_SLOW_QUERY_MS)");
+    };
+    let collection = match request.get("collection") {
+        None | Some(Value::Null) => None,
+        Some(Value::String(c)) => Some(c.as_str()),
+        Some(_) => return err_bytes("'collection' must be a string"),
+    };
+    let limit = match request.get("limit") {
+        None | Some(Value::Null) => usize::MAX,
+        Some(v) => match v.as_u64().filter(|&n| n > 0) {
+            Some(n) => n as usize,
+            None => return err_bytes("'limit' must be a positive integer"),
+        },
+    };
+    ok_bytes(Value::Array(slow_query_log.entries(collection, limit)))
+}
+
+/// Handle `change_password` for the session's own user, lifting a forced
+/// password change on success.
+pub fn handle_change_password(
+    session: &mut Session,
+    request: &Value,
+    user_store: &Arc<Mutex<UserStore>>,
+) -> Vec<u8> {
+    let old_password = match request.get("old_password").and_then(|v| v.as_str()) {
+        Some(p) => p,
+        None => return err_bytes("missing 'old_password'"),
+    };
+    let new_password = match request.get("new_password").and_then(|v| v.as_str()) {
+        Some(p) => p,
+        None => return err_bytes("missing 'new_password'"),
+    };
+    let username = match &session.username {
+        Some(u) => u.clone(),
+        None => return err_bytes("authentication required"),
+    };
+    let mut store = user_store.lock().unwrap();
+    match store.change_password(&username, old_password, new_password) {
+        Ok(()) => {
+            session.must_change_password = false;
+            ok_bytes(json!("password changed"))
+        }
+        Err(e) => err_bytes(&e),
+    }
+}
@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+/// Default for `OXIDB_KEEPALIVE_MISSED`.
+pub const DEFAULT_MAX_MISSED: u32 = 3;
+
+/// Application-level keepalive: clients send a request, or `{"cmd":"ping"}`
+/// when they have nothing else to say, at least once per `interval`, and a
+/// connection that misses `max_missed` intervals in a row is closed. This
+/// finds peers that vanished without a FIN (NAT timeouts, pulled cables)
+/// sooner than the idle timeout, and also covers watch connections, which
+/// the idle timeout doesn't.
+#[derive(Debug, Clone, Copy)]
+pub struct Keepalive {
+    /// Expected heartbeat interval; `None` disables the keepalive.
+    pub interval: Option<Duration>,
+    /// Intervals a client may miss before it's disconnected.
+    pub max_missed: u32,
+}
+
+impl Default for Keepalive {
+    fn default() -> Self {
+        Self { interval: None, max_missed: DEFAULT_MAX_MISSED }
+    }
+}
+
+impl Keepalive {
+    /// Read `OXIDB_KEEPALIVE_INTERVAL` (seconds, 0 = off) and
+    /// `OXIDB_KEEPALIVE_MISSED`.
+    pub fn from_env() -> Self {
+        let interval = std::env::var("OXIDB_KEEPALIVE_INTERVAL")
+            .ok()
+            .map(|v| v.parse::<u64>().expect("OXIDB_KEEPALIVE_INTERVAL must be a valid u64 (seconds)"))
+            .filter(|&s| s > 0)
+            .map(Duration::from_secs);
+        let max_missed = std::env::var("OXIDB_KEEPALIVE_MISSED")
+            .ok()
+            .map(|v| {
+                v.parse::<u32>()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .expect("OXIDB_KEEPALIVE_MISSED must be a positive integer")
+            })
+            .unwrap_or(DEFAULT_MAX_MISSED);
+        Self { interval, max_missed }
+    }
+
+    /// Longest a client may stay silent, if the keepalive is on.
+    pub fn limit(&self) -> Option<Duration> {
+        self.interval.map(|interval| interval * self.max_missed.max(1))
+    }
+
+    /// Socket read timeout for a connection in request mode: the shorter of
+    /// the idle timeout (zero = none) and the keepalive limit.
+    pub fn read_timeout(&self, idle_timeout: Duration) -> Option<Duration> {
+        let idle = Some(idle_timeout).filter(|t| !t.is_zero());
+        match (idle, self.limit()) {
+            (Some(idle), Some(limit)) => Some(idle.min(limit)),
+            (idle, limit) => idle.or(limit),
+        }
+    }
+
+    /// Whether a read timing out after `read_timeout` means missed
+    /// heartbeats rather than the idle timeout.
+    pub fn expired_by(&self, read_timeout: Option<Duration>) -> bool {
+        self.limit().is_some_and(|limit| read_timeout == Some(limit))
+    }
+}
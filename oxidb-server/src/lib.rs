@@ -1,12 +1,19 @@
 pub mod audit;
 pub mod auth;
+pub mod connlimit;
 pub mod gelf;
 pub mod handler;
+pub mod keepalive;
+pub mod metrics_http;
 pub mod protocol;
+pub mod ratelimit;
 pub mod rbac;
 pub mod scram;
 pub mod session;
+pub mod shutdown;
+pub mod slowlog;
 pub mod tls;
+pub mod watch;
 
 #[cfg(feature = "cluster")]
 pub mod async_protocol;
@@ -1,15 +1,22 @@
 #[global_allocator]
 static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
-use oxidb_server::audit::{self, AuditEvent, AuditLog};
+use oxidb_server::audit::{self, AuditEvent, AuditLog, AuditRotation};
 use oxidb_server::auth::UserStore;
+use oxidb_server::connlimit::{self, ConnectionLimiter, ConnectionPermit};
 use oxidb_server::gelf::{GelfLevel, GelfLogger};
 use oxidb_server::handler;
-use oxidb_server::protocol;
+use oxidb_server::keepalive::Keepalive;
+use oxidb_server::metrics_http;
+use oxidb_server::protocol::{self, Codec, Compression};
+use oxidb_server::ratelimit::{self, RateLimiter};
 use oxidb_server::rbac;
 use oxidb_server::scram::ScramState;
-use oxidb_server::session::Session;
-use oxidb_server::tls;
+use oxidb_server::session::{Session, SessionLimits};
+use oxidb_server::shutdown::Shutdown;
+use oxidb_server::slowlog::SlowQueryLog;
+use oxidb_server::tls::{self, CertIdentity, ClientCertRoles};
+use oxidb_server::watch::{self, WatchExit};
 
 #[cfg(feature = "cluster")]
 use openraft::storage::Adaptor;
@@ -20,6 +27,8 @@ use oxidb_server::raft::config::RaftConfig;
 #[cfg(feature = "cluster")]
 use oxidb_server::raft::log_store::OxiDbStore;
 #[cfg(feature = "cluster")]
+use oxidb_server::raft::management;
+#[cfg(feature = "cluster")]
 use oxidb_server::raft::network::{self, OxiDbNetworkFactory};
 
 use std::env;
@@ -28,9 +37,9 @@ use std::net::{TcpListener, TcpStream};
 use std::path::Path;
 use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use oxidb::OxiDb;
+use oxidb::{OxiDb, SyncMode};
 
 /// Log to stderr and optionally to a GELF endpoint.
 ///
@@ -56,8 +65,8 @@ macro_rules! server_log {
     }};
 }
 
-fn configure_stream(stream: &TcpStream, idle_timeout: Duration) {
-    let _ = stream.set_read_timeout(Some(idle_timeout));
+fn configure_stream(stream: &TcpStream, read_timeout: Option<Duration>) {
+    let _ = stream.set_read_timeout(read_timeout);
     let _ = stream.set_nodelay(true);
 }
 
@@ -68,6 +77,14 @@ struct ServerState {
     audit_log: Option<Arc<AuditLog>>,
     gelf: Option<Arc<GelfLogger>>,
     auth_enabled: bool,
+    session_limits: SessionLimits,
+    rate_limiter: Option<RateLimiter>,
+    client_cert_roles: ClientCertRoles,
+    /// SCRAM channel binding data when connections use TLS.
+    tls_channel_binding: Option<Vec<u8>>,
+    keepalive: Keepalive,
+    shutdown: Shutdown,
+    slow_query_log: Option<SlowQueryLog>,
 }
 
 /// Dispatch a single request through auth -> RBAC -> handler pipeline.
@@ -76,7 +93,7 @@ fn dispatch_request(
     state: &ServerState,
     session: &mut Session,
     active_tx: &mut Option<u64>,
-    _peer: &str,
+    peer: &str,
 ) -> Vec<u8> {
     let cmd = request
         .get("cmd")
@@ -89,6 +106,42 @@ fn dispatch_request(
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
 
+    // Health probes skip session, rate-limit and auth checks
+    if matches!(cmd.as_str(), "healthz" | "readyz") {
+        return handler::handle_request(&state.db, request.clone(), active_tx);
+    }
+
+    // ---------------------------------------------------------------
+    // Session expiry and logout
+    // ---------------------------------------------------------------
+    if state.auth_enabled && session.check_expiry(&state.session_limits, Instant::now()) {
+        if let Some(tx_id) = active_tx.take() {
+            let _ = state.db.rollback_transaction(tx_id);
+        }
+        if !matches!(cmd.as_str(), "ping" | "authenticate" | "auth_simple" | "auth_apikey") {
+            return handler::err_bytes("session expired: authenticate again");
+        }
+    }
+    if cmd == "logout" {
+        if let Some(tx_id) = active_tx.take() {
+            let _ = state.db.rollback_transaction(tx_id);
+        }
+        log_audit(state, session, &cmd, None, "ok", "");
+        session.logout();
+        return handler::ok_bytes(serde_json::json!("logged out"));
+    }
+
+    // ---------------------------------------------------------------
+    // Rate limiting
+    // ---------------------------------------------------------------
+    if let Some(limiter) = &state.rate_limiter {
+        let user = if state.auth_enabled { session.username.as_deref() } else { None };
+        if !limiter.check(&ratelimit::client_key(user, peer), Instant::now()) {
+            log_audit(state, session, &cmd, collection.as_deref(), "denied", "rate limit exceeded");
+            return handler::err_code_bytes("rate limit exceeded", "rate_limited");
+        }
+    }
+
     // ---------------------------------------------------------------
     // Authentication flow (SCRAM-SHA-256)
     // ---------------------------------------------------------------
@@ -103,7 +156,7 @@ fn dispatch_request(
                     .unwrap_or("");
 
                 let user_store_guard = state.user_store.as_ref().unwrap().lock().unwrap();
-                match ScramState::process_client_first(client_first, &user_store_guard) {
+                match ScramState::process_client_first(client_first, &user_store_guard, state.tls_channel_binding.as_deref()) {
                     Ok((server_first, scram_state)) => {
                         drop(user_store_guard);
                         session.scram_state = Some(scram_state);
@@ -126,10 +179,11 @@ fn dispatch_request(
                     let user_store_guard =
                         state.user_store.as_ref().unwrap().lock().unwrap();
                     match scram_state.process_client_final(client_final, &user_store_guard) {
-                        Ok((server_final, role)) => {
-                            let username = scram_state.username().to_string();
+                        Ok((server_final, _)) => {
+                            if let Some(user) = user_store_guard.get_user(scram_state.username()) {
+                                session.set_authenticated_user(user);
+                            }
                             drop(user_store_guard);
-                            session.set_authenticated(username, role);
                             handler::ok_bytes(serde_json::json!({
                                 "payload": server_final,
                                 "done": true,
@@ -153,12 +207,32 @@ fn dispatch_request(
                     .unwrap_or("");
 
                 let user_store_guard = state.user_store.as_ref().unwrap().lock().unwrap();
-                match user_store_guard.authenticate(username, password) {
-                    Some(role) => {
+                let user = user_store_guard
+                    .authenticate(username, password)
+                    .and_then(|_| user_store_guard.get_user(username));
+                match user {
+                    Some(user) => {
+                        session.set_authenticated_user(user);
+                        drop(user_store_guard);
+                        handler::ok_bytes(serde_json::json!({
+                            "role": session.role_name(),
+                            "done": true,
+                        }))
+                    }
+                    None => handler::err_bytes("authentication failed"),
+                }
+            }
+
+            "auth_apikey" => {
+                let key = request.get("key").and_then(|v| v.as_str()).unwrap_or("");
+
+                let user_store_guard = state.user_store.as_ref().unwrap().lock().unwrap();
+                match user_store_guard.authenticate_api_key(key) {
+                    Some(user) => {
+                        session.set_authenticated_user(user);
                         drop(user_store_guard);
-                        session.set_authenticated(username.to_string(), role);
                         handler::ok_bytes(serde_json::json!({
-                            "role": role.as_str(),
+                            "role": session.role_name(),
                             "done": true,
                         }))
                     }
@@ -170,36 +244,47 @@ fn dispatch_request(
         };
     }
 
+    // ---------------------------------------------------------------
+    // Forced password change
+    // ---------------------------------------------------------------
+    if state.auth_enabled && rbac::password_change_required(session, &cmd) {
+        log_audit(state, session, &cmd, collection.as_deref(), "denied", "");
+        return handler::err_bytes("password change required: run 'change_password' first");
+    }
+
     // ---------------------------------------------------------------
     // RBAC check
     // ---------------------------------------------------------------
-    if state.auth_enabled {
-        if let Some(role) = session.role() {
-            let is_user_cmd = matches!(
-                cmd.as_str(),
-                "create_user" | "drop_user" | "update_user" | "list_users"
-            );
-            let permitted = if is_user_cmd {
-                role == oxidb_server::auth::Role::Admin
-            } else {
-                rbac::is_permitted(role, &cmd)
-            };
+    if state.auth_enabled && !rbac::session_permitted(session, state.user_store.as_deref(), &cmd) {
+        log_audit(state, session, &cmd, collection.as_deref(), "denied", "");
+        return handler::err_bytes(&format!(
+            "permission denied: role '{}' cannot execute '{}'",
+            session.role_name(),
+            cmd
+        ));
+    }
 
-            if !permitted {
-                log_audit(state, session, &cmd, collection.as_deref(), "denied", "");
-                return handler::err_bytes(&format!(
-                    "permission denied: role '{}' cannot execute '{}'",
-                    role.as_str(),
-                    cmd
-                ));
-            }
-        }
+    // ---------------------------------------------------------------
+    // Bucket ACLs
+    // ---------------------------------------------------------------
+    let bucket_check = match state.auth_enabled {
+        true => rbac::bucket_permitted(&state.db, session, &cmd, request),
+        false => Ok(()),
+    };
+    if let Err(msg) = bucket_check {
+        log_audit(state, session, &cmd, None, "denied", &msg);
+        return handler::err_bytes(&msg);
     }
 
     // ---------------------------------------------------------------
     // Handle user management commands
     // ---------------------------------------------------------------
     if let Some(user_store) = &state.user_store {
+        if cmd == "change_password" {
+            let resp_bytes = handler::handle_change_password(session, request, user_store);
+            log_audit(state, session, &cmd, None, "ok", "");
+            return resp_bytes;
+        }
         if let Some(resp_bytes) =
             handler::handle_user_command(&cmd, request, user_store)
         {
@@ -208,10 +293,31 @@ fn dispatch_request(
         }
     }
 
+    if cmd == "query_audit" {
+        let resp_bytes = handler::handle_query_audit(request, state.audit_log.as_deref());
+        log_audit(state, session, &cmd, None, "ok", "");
+        return resp_bytes;
+    }
+
+    if cmd == "query_slow_log" {
+        let resp_bytes = handler::handle_query_slow_log(request, state.slow_query_log.as_ref());
+        log_audit(state, session, &cmd, None, "ok", "");
+        return resp_bytes;
+    }
+
     // ---------------------------------------------------------------
     // Standard command dispatch
     // ---------------------------------------------------------------
-    let resp_bytes = handler::handle_request(&state.db, request.clone(), active_tx);
+    let started = Instant::now();
+    let owned_request = rbac::with_bucket_owner(session, &cmd, request.clone());
+    let mut resp_bytes = handler::handle_request(&state.db, owned_request, active_tx);
+    if let Some(slow_query_log) = &state.slow_query_log {
+        slow_query_log.observe(&state.db, request, started.elapsed());
+    }
+    if state.auth_enabled {
+        let bucket = request.get("bucket").and_then(|v| v.as_str());
+        resp_bytes = rbac::filter_readable_buckets(&state.db, session, &cmd, bucket, resp_bytes);
+    }
 
     log_audit(state, session, &cmd, collection.as_deref(), "ok", "");
 
@@ -221,12 +327,12 @@ fn dispatch_request(
 /// Parsed watch request parameters.
 struct WatchRequest {
     filter: oxidb::WatchFilter,
-    resume_after: Option<u64>,
+    opts: oxidb::WatchOptions,
 }
 
 /// Check if an incoming request is a `watch` command.
-/// Returns `Ok(Some(params))` if authorized, `Err(msg)` if watch but unauthorized,
-/// `Ok(None)` if not a watch command.
+/// Returns `Ok(Some(params))` if authorized, `Err(msg)` if watch but unauthorized
+/// or malformed, `Ok(None)` if not a watch command.
 fn try_watch_request(
     request: &serde_json::Value,
     state: &ServerState,
@@ -239,6 +345,10 @@ fn try_watch_request(
     if cmd != "watch" {
         return Ok(None);
     }
+    // Watch takes over the connection, so there's nothing to pipeline behind it
+    if request.get("request_id").is_some() {
+        return Err("'request_id' is not supported with watch");
+    }
     // Require authentication with Admin role
     if state.auth_enabled {
         if !session.is_authenticated() {
@@ -248,12 +358,75 @@ fn try_watch_request(
             return Err("permission denied: watch requires Admin role");
         }
     }
-    let filter = match request.get("collection").and_then(|v| v.as_str()) {
-        Some(col) => oxidb::WatchFilter::Collection(col.to_string()),
-        None => oxidb::WatchFilter::All,
+    let collection = request.get("collection").and_then(|v| v.as_str()).map(String::from);
+    let filter = match request.get("operations") {
+        Some(ops) => {
+            let ops = ops.as_array().ok_or("'operations' must be an array")?;
+            let mut operations = Vec::with_capacity(ops.len());
+            for op in ops {
+                match op.as_str().and_then(oxidb::OperationType::from_name) {
+                    Some(op) => operations.push(op),
+                    None => return Err("'operations' entries must be insert, update, or delete"),
+                }
+            }
+            oxidb::WatchFilter::Operations { collection, operations }
+        }
+        None => match collection {
+            Some(col) => oxidb::WatchFilter::Collection(col),
+            None => oxidb::WatchFilter::All,
+        },
+    };
+    let resume_after_time = match request.get("resume_after_time") {
+        Some(v) => {
+            let ts = v
+                .as_str()
+                .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+                .ok_or("'resume_after_time' must be an RFC 3339 timestamp")?;
+            Some(ts.with_timezone(&chrono::Utc))
+        }
+        None => None,
+    };
+    let opts = oxidb::WatchOptions {
+        resume_after: request.get("resume_after").and_then(|v| v.as_u64()),
+        resume_after_time,
+        full_document: request.get("full_document").and_then(|v| v.as_bool()).unwrap_or(false),
+    };
+    Ok(Some(WatchRequest { filter, opts }))
+}
+
+/// Handle the per-connection `set_codec` and `set_compression` handshakes,
+/// updating the connection's settings if the request is valid. The reply is
+/// encoded in the codec the request arrived in and is never compressed.
+fn negotiate(request: &serde_json::Value, codec: &mut Codec, compression: &mut Compression) -> Option<Vec<u8>> {
+    let reply_codec = *codec;
+    let resp = if let Some(requested) = protocol::codec_request(request) {
+        match requested {
+            Ok(c) => {
+                *codec = c;
+                handler::ok_bytes(serde_json::json!({"codec": c.name()}))
+            }
+            Err(msg) => handler::err_bytes(msg),
+        }
+    } else {
+        match protocol::compression_request(request)? {
+            Ok(c) => {
+                *compression = c;
+                handler::ok_bytes(serde_json::json!({"compression": c.name()}))
+            }
+            Err(msg) => handler::err_bytes(msg),
+        }
     };
-    let resume_after = request.get("resume_after").and_then(|v| v.as_u64());
-    Ok(Some(WatchRequest { filter, resume_after }))
+    let resp = handler::with_request_id(resp, request.get("request_id"));
+    Some(reply_codec.encode(&resp).into_owned())
+}
+
+/// Log why a connection whose read timed out after `read_timeout` is closed.
+fn log_read_timeout(state: &ServerState, read_timeout: Option<Duration>, peer: &str) {
+    if state.keepalive.expired_by(read_timeout) {
+        server_log!(state, GelfLevel::Warning, format!("{peer} missed {} keepalive intervals, disconnecting", state.keepalive.max_missed), extra: "peer" => peer);
+    } else {
+        server_log!(state, GelfLevel::Warning, format!("idle timeout for {peer}, disconnecting"), extra: "peer" => peer);
+    }
 }
 
 /// Watch mode loop: push change events to the client and listen for `unwatch`.
@@ -300,30 +473,13 @@ fn handle_watch_mode<R: Read + Send + 'static, W: Write>(
         }
     });
 
+    let mut last_heard = Instant::now();
     loop {
         // Check for change events (non-blocking with short timeout)
         match handle.rx.recv_timeout(Duration::from_millis(50)) {
             Ok(event) => {
-                // Check for backpressure drops before sending
-                let dropped = handle.take_dropped();
-                if dropped > 0 {
-                    let overflow_msg = serde_json::json!({
-                        "event": "overflow",
-                        "data": { "dropped": dropped },
-                    });
-                    let overflow_bytes = overflow_msg.to_string().into_bytes();
-                    if let Err(e) = protocol::write_message(writer, &overflow_bytes) {
-                        server_log!(state, GelfLevel::Error, format!("watch write error to {peer}: {e}"), extra: "peer" => peer);
-                        break;
-                    }
-                }
-
-                let msg = serde_json::json!({
-                    "event": "change",
-                    "data": event,
-                });
-                let msg_bytes = msg.to_string().into_bytes();
-                if let Err(e) = protocol::write_message(writer, &msg_bytes) {
+                // Sends an overflow message first if events were dropped
+                if let Err(e) = watch::send_event(writer, &handle, &event) {
                     server_log!(state, GelfLevel::Error, format!("watch write error to {peer}: {e}"), extra: "peer" => peer);
                     break;
                 }
@@ -335,14 +491,17 @@ fn handle_watch_mode<R: Read + Send + 'static, W: Write>(
         // Check for client commands (non-blocking)
         match msg_rx.try_recv() {
             Ok(Some(data)) => {
-                if let Ok(request) = serde_json::from_slice::<serde_json::Value>(&data) {
-                    let cmd = request.get("cmd").and_then(|v| v.as_str()).unwrap_or("");
-                    if cmd == "unwatch" {
-                        state.db.unwatch(sub_id);
-                        let ack = handler::ok_bytes(serde_json::json!("unwatched"));
-                        let _ = protocol::write_message(writer, &ack);
-                        return; // Return to normal connection loop
-                    }
+                last_heard = Instant::now();
+                if watch::is_unwatch(&data) {
+                    state.db.unwatch(sub_id);
+                    let ack = handler::ok_bytes(serde_json::json!("unwatched"));
+                    let _ = protocol::write_message(writer, &ack);
+                    return; // Return to normal connection loop
+                }
+                let pong = watch::is_ping(&data).then(watch::pong_message);
+                if let Some(Err(e)) = pong.map(|pong| protocol::write_message(writer, &pong)) {
+                    server_log!(state, GelfLevel::Error, format!("watch write error to {peer}: {e}"), extra: "peer" => peer);
+                    break;
                 }
             }
             Ok(None) => {
@@ -352,6 +511,11 @@ fn handle_watch_mode<R: Read + Send + 'static, W: Write>(
             Err(std::sync::mpsc::TryRecvError::Empty) => {}
             Err(std::sync::mpsc::TryRecvError::Disconnected) => break,
         }
+
+        if state.keepalive.limit().is_some_and(|limit| last_heard.elapsed() >= limit) {
+            log_read_timeout(state, state.keepalive.limit(), peer);
+            break;
+        }
     }
 
     state.db.unwatch(sub_id);
@@ -365,6 +529,8 @@ fn handle_connection(
 ) {
     let mut active_tx: Option<u64> = None;
     let mut session = Session::new();
+    let mut codec = Codec::Json;
+    let mut compression = Compression::None;
 
     if !state.auth_enabled {
         session.set_authenticated("anonymous".to_string(), oxidb_server::auth::Role::Admin);
@@ -380,7 +546,7 @@ fn handle_connection(
                 if e.kind() == std::io::ErrorKind::WouldBlock
                     || e.kind() == std::io::ErrorKind::TimedOut
                 {
-                    server_log!(state, GelfLevel::Warning, format!("idle timeout for {peer}, disconnecting"), extra: "peer" => peer);
+                    log_read_timeout(state, stream.read_timeout().ok().flatten(), peer);
                 } else if e.kind() != std::io::ErrorKind::UnexpectedEof {
                     server_log!(state, GelfLevel::Error, format!("read error from {peer}: {e}"), extra: "peer" => peer);
                 }
@@ -388,25 +554,36 @@ fn handle_connection(
             }
         };
 
-        let request: serde_json::Value = match serde_json::from_slice(&msg) {
+        let request = match codec.decode(&msg) {
             Ok(v) => v,
             Err(e) => {
-                let resp =
-                    serde_json::json!({"ok": false, "error": format!("invalid JSON: {e}")});
-                let _ = protocol::write_message(&mut writer, resp.to_string().as_bytes());
+                let _ = protocol::write_message(&mut writer, &codec.encode(&handler::err_bytes(&e)));
                 continue;
             }
         };
 
+        if let Some(reply) = negotiate(&request, &mut codec, &mut compression) {
+            if let Err(e) = protocol::write_message(&mut writer, &reply) {
+                server_log!(state, GelfLevel::Error, format!("write error to {peer}: {e}"), extra: "peer" => peer);
+                break;
+            }
+            continue;
+        }
+
         // Check for watch command
         match try_watch_request(&request, state, &session) {
             Err(msg) => {
-                let resp = handler::err_bytes(msg);
-                let _ = protocol::write_message(&mut writer, &resp);
+                let resp = handler::with_request_id(handler::err_bytes(msg), request.get("request_id"));
+                let _ = protocol::write_message(&mut writer, &codec.encode(&resp));
+                continue;
+            }
+            Ok(Some(_)) if codec != Codec::Json => {
+                let resp = handler::err_bytes("watch requires the json codec");
+                let _ = protocol::write_message(&mut writer, &codec.encode(&resp));
                 continue;
             }
             Ok(Some(watch_req)) => {
-                let handle = match state.db.watch(watch_req.filter, watch_req.resume_after) {
+                let handle = match state.db.watch_with_options(watch_req.filter, &watch_req.opts) {
                     Ok(h) => h,
                     Err(oxidb::ResumeError::TokenTooOld) => {
                         let resp = handler::err_bytes("resume token too old");
@@ -439,11 +616,15 @@ fn handle_connection(
         }
 
         let resp_bytes = dispatch_request(&request, state, &mut session, &mut active_tx, peer);
+        let resp_bytes = handler::with_request_id(resp_bytes, request.get("request_id"));
 
-        if let Err(e) = protocol::write_message(&mut writer, &resp_bytes) {
+        if let Err(e) = protocol::write_message_with(&mut writer, &codec.encode(&resp_bytes), compression) {
             server_log!(state, GelfLevel::Error, format!("write error to {peer}: {e}"), extra: "peer" => peer);
             break;
         }
+        if state.shutdown.is_requested() {
+            break;
+        }
     }
 
     if let Some(tx_id) = active_tx {
@@ -451,17 +632,28 @@ fn handle_connection(
     }
 }
 
-/// Variant for streams that are a single Read+Write object (e.g. TLS).
-fn handle_connection_single<S: Read + Write>(
-    stream: &mut S,
+type TlsStream = rustls::StreamOwned<rustls::ServerConnection, TcpStream>;
+
+/// How often the TLS watch loop polls the client between event pushes.
+const TLS_WATCH_POLL: Duration = Duration::from_millis(50);
+
+/// Variant for streams that are a single Read+Write object (TLS).
+fn handle_connection_single(
+    stream: &mut TlsStream,
     state: &ServerState,
     peer: &str,
+    cert_identity: Option<CertIdentity>,
 ) {
     let mut active_tx: Option<u64> = None;
     let mut session = Session::new();
+    let mut codec = Codec::Json;
+    let mut compression = Compression::None;
 
     if !state.auth_enabled {
         session.set_authenticated("anonymous".to_string(), oxidb_server::auth::Role::Admin);
+    } else if let Some(identity) = cert_identity {
+        server_log!(state, GelfLevel::Informational, format!("{peer} authenticated by client certificate as {} ({})", identity.name, identity.role), extra: "peer" => peer);
+        session.set_authenticated_as(identity.name, &identity.role);
     }
 
     loop {
@@ -471,7 +663,7 @@ fn handle_connection_single<S: Read + Write>(
                 if e.kind() == std::io::ErrorKind::WouldBlock
                     || e.kind() == std::io::ErrorKind::TimedOut
                 {
-                    server_log!(state, GelfLevel::Warning, format!("idle timeout for {peer}, disconnecting"), extra: "peer" => peer);
+                    log_read_timeout(state, stream.sock.read_timeout().ok().flatten(), peer);
                 } else if e.kind() != std::io::ErrorKind::UnexpectedEof {
                     server_log!(state, GelfLevel::Error, format!("read error from {peer}: {e}"), extra: "peer" => peer);
                 }
@@ -479,23 +671,43 @@ fn handle_connection_single<S: Read + Write>(
             }
         };
 
-        let request: serde_json::Value = match serde_json::from_slice(&msg) {
+        let request = match codec.decode(&msg) {
             Ok(v) => v,
             Err(e) => {
-                let resp =
-                    serde_json::json!({"ok": false, "error": format!("invalid JSON: {e}")});
-                let _ = protocol::write_message(stream, resp.to_string().as_bytes());
+                let _ = protocol::write_message(stream, &codec.encode(&handler::err_bytes(&e)));
                 continue;
             }
         };
 
-        // Intercept watch/unwatch — not supported over TLS (can't split the stream)
-        if request.get("cmd").and_then(|v| v.as_str()) == Some("watch") {
-            let resp = handler::err_bytes("watch is not supported over TLS connections");
-            let _ = protocol::write_message(stream, &resp);
+        if let Some(reply) = negotiate(&request, &mut codec, &mut compression) {
+            if let Err(e) = protocol::write_message(stream, &reply) {
+                server_log!(state, GelfLevel::Error, format!("write error to {peer}: {e}"), extra: "peer" => peer);
+                break;
+            }
             continue;
         }
 
+        // Check for watch command
+        match try_watch_request(&request, state, &session) {
+            Err(msg) => {
+                let resp = handler::with_request_id(handler::err_bytes(msg), request.get("request_id"));
+                let _ = protocol::write_message(stream, &codec.encode(&resp));
+                continue;
+            }
+            Ok(Some(_)) if codec != Codec::Json => {
+                let resp = handler::err_bytes("watch requires the json codec");
+                let _ = protocol::write_message(stream, &codec.encode(&resp));
+                continue;
+            }
+            Ok(Some(watch_req)) => {
+                if watch_tls(stream, state, watch_req, peer) {
+                    continue;
+                }
+                break;
+            }
+            Ok(None) => {}
+        }
+
         let resp_bytes = dispatch_request(
             &request,
             state,
@@ -503,11 +715,15 @@ fn handle_connection_single<S: Read + Write>(
             &mut active_tx,
             peer,
         );
+        let resp_bytes = handler::with_request_id(resp_bytes, request.get("request_id"));
 
-        if let Err(e) = protocol::write_message(stream, &resp_bytes) {
+        if let Err(e) = protocol::write_message_with(stream, &codec.encode(&resp_bytes), compression) {
             server_log!(state, GelfLevel::Error, format!("write error to {peer}: {e}"), extra: "peer" => peer);
             break;
         }
+        if state.shutdown.is_requested() {
+            break;
+        }
     }
 
     if let Some(tx_id) = active_tx {
@@ -515,6 +731,48 @@ fn handle_connection_single<S: Read + Write>(
     }
 }
 
+/// Run watch mode on a TLS stream. The stream can't be split, so the socket
+/// read timeout is shortened to poll for `unwatch` between event pushes and
+/// restored afterwards. Returns `true` if the connection should keep serving
+/// requests.
+fn watch_tls(stream: &mut TlsStream, state: &ServerState, watch_req: WatchRequest, peer: &str) -> bool {
+    let handle = match state.db.watch_with_options(watch_req.filter, &watch_req.opts) {
+        Ok(h) => h,
+        Err(oxidb::ResumeError::TokenTooOld) => {
+            let resp = handler::err_bytes("resume token too old");
+            return protocol::write_message(stream, &resp).is_ok();
+        }
+    };
+    let ack = handler::ok_bytes(serde_json::json!("watching"));
+    if let Err(e) = protocol::write_message(stream, &ack) {
+        server_log!(state, GelfLevel::Error, format!("write error to {peer}: {e}"), extra: "peer" => peer);
+        state.db.unwatch(handle.id);
+        return false;
+    }
+
+    let idle_timeout = stream.sock.read_timeout().ok().flatten();
+    if let Err(e) = stream.sock.set_read_timeout(Some(TLS_WATCH_POLL)) {
+        server_log!(state, GelfLevel::Error, format!("socket config error for {peer}: {e}"), extra: "peer" => peer);
+        state.db.unwatch(handle.id);
+        return false;
+    }
+    let result = watch::watch_single_with_keepalive(stream, &state.db, handle, state.keepalive.limit());
+    let _ = stream.sock.set_read_timeout(idle_timeout);
+
+    match result {
+        Ok(WatchExit::Unwatched) => true,
+        Ok(WatchExit::Disconnected) => false,
+        Ok(WatchExit::KeepaliveExpired) => {
+            log_read_timeout(state, state.keepalive.limit(), peer);
+            false
+        }
+        Err(e) => {
+            server_log!(state, GelfLevel::Error, format!("watch error for {peer}: {e}"), extra: "peer" => peer);
+            false
+        }
+    }
+}
+
 fn log_audit(
     state: &ServerState,
     session: &Session,
@@ -536,7 +794,11 @@ fn log_audit(
 }
 
 fn handle_client(stream: TcpStream, state: &Arc<ServerState>, idle_timeout: Duration, tls_config: Option<&Arc<rustls::ServerConfig>>) {
-    configure_stream(&stream, idle_timeout);
+    // Accepted just before shutdown: close without serving
+    let Some(_guard) = state.shutdown.track(&stream) else {
+        return;
+    };
+    configure_stream(&stream, state.keepalive.read_timeout(idle_timeout));
 
     let peer = stream
         .peer_addr()
@@ -554,9 +816,16 @@ fn handle_client(stream: TcpStream, state: &Arc<ServerState>, idle_timeout: Dura
             }
         };
         let mut tls_stream = rustls::StreamOwned::new(conn, stream);
-        // StreamOwned<ServerConnection, TcpStream> implements both Read + Write.
-        // handle_connection_single takes a single &mut impl Read + Write.
-        handle_connection_single(&mut tls_stream, state, &peer);
+        // Finish the handshake up front so a rejected client certificate is
+        // reported here and the verified one can authenticate the session
+        while tls_stream.conn.is_handshaking() {
+            if let Err(e) = tls_stream.conn.complete_io(&mut tls_stream.sock) {
+                server_log!(state, GelfLevel::Warning, format!("TLS handshake error for {peer}: {e}"), extra: "peer" => &peer);
+                return;
+            }
+        }
+        let identity = state.client_cert_roles.identify(tls_stream.conn.peer_certificates());
+        handle_connection_single(&mut tls_stream, state, &peer, identity);
     } else {
         // Plain TCP connection
         handle_connection(&stream, state, &peer);
@@ -592,7 +861,7 @@ fn main() {
             .map(|v| v == "true" || v == "1")
             .unwrap_or(false);
 
-    // GELF UDP logging (e.g. OXIDB_GELF_ADDR=172.17.0.1:12201)
+    // GELF logging over UDP (e.g. OXIDB_GELF_ADDR=172.17.0.1:12201) or TCP (tcp://host:12201)
     let gelf = match env::var("OXIDB_GELF_ADDR") {
         Ok(gelf_addr) => {
             let logger = GelfLogger::new(&gelf_addr).expect("failed to create GELF logger");
@@ -646,18 +915,117 @@ fn main() {
     let db = Arc::new(db);
     db.start_scheduler();
 
+    // Transaction timeout: stalled transactions are rejected and reaped
+    if let Ok(v) = env::var("OXIDB_TX_TIMEOUT") {
+        let secs: u64 = v
+            .parse()
+            .expect("OXIDB_TX_TIMEOUT must be a valid u64 (seconds)");
+        if secs > 0 {
+            db.set_default_tx_timeout(Some(Duration::from_secs(secs)));
+            db.start_tx_reaper(Duration::from_secs(1));
+            eprintln!("transaction timeout: {secs}s");
+        }
+    }
+
+    // Unindexed scans of large collections run on several threads (0 = one per CPU)
+    let scan_threads: usize = env::var("OXIDB_SCAN_THREADS")
+        .unwrap_or_else(|_| "0".to_string())
+        .parse()
+        .expect("OXIDB_SCAN_THREADS must be a valid usize");
+    if scan_threads > 0 {
+        db.set_scan_workers(scan_threads);
+    }
+
+    // When writes are fsynced: each on its own, shared by concurrent inserts, or never
+    if let Ok(v) = env::var("OXIDB_SYNC") {
+        let mode = SyncMode::parse(&v).expect("OXIDB_SYNC must be one of: full, group, none");
+        db.set_sync_mode(mode);
+    }
+
+    // Concurrent inserts into a collection share fsyncs; the leader waits this long for others
+    if let Ok(v) = env::var("OXIDB_GROUP_COMMIT_WINDOW_US") {
+        let micros: u64 = v
+            .parse()
+            .expect("OXIDB_GROUP_COMMIT_WINDOW_US must be a valid u64 (microseconds)");
+        db.set_group_commit_window(Duration::from_micros(micros));
+    }
+
+    // Size limits: oversized inserts and find results are rejected (0 = no limit)
+    let max_doc_size: usize = env::var("OXIDB_MAX_DOC_SIZE")
+        .unwrap_or_else(|_| "0".to_string())
+        .parse()
+        .expect("OXIDB_MAX_DOC_SIZE must be a valid usize (bytes)");
+    db.set_max_document_size(Some(max_doc_size).filter(|&n| n > 0));
+    let max_find_results: usize = env::var("OXIDB_MAX_FIND_RESULTS")
+        .unwrap_or_else(|_| "0".to_string())
+        .parse()
+        .expect("OXIDB_MAX_FIND_RESULTS must be a valid usize");
+    db.set_max_find_results(Some(max_find_results).filter(|&n| n > 0));
+
+    // Load collections past records that fail their checksum instead of refusing them
+    let skip_corrupt = env::var("OXIDB_SKIP_CORRUPT_RECORDS")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    db.set_skip_corrupt_records(skip_corrupt);
+
+    // Expired blobs are hidden immediately and purged by a background sweep
+    let sweep_secs: u64 = env::var("OXIDB_BLOB_SWEEP_INTERVAL")
+        .unwrap_or_else(|_| "60".to_string())
+        .parse()
+        .expect("OXIDB_BLOB_SWEEP_INTERVAL must be a valid u64 (seconds)");
+    if sweep_secs > 0 {
+        db.start_blob_sweeper(Duration::from_secs(sweep_secs));
+    }
+
+    // Documents past their _expireAt are removed by a background sweep
+    let ttl_sweep_secs: u64 = env::var("OXIDB_TTL_SWEEP_INTERVAL")
+        .unwrap_or_else(|_| "60".to_string())
+        .parse()
+        .expect("OXIDB_TTL_SWEEP_INTERVAL must be a valid u64 (seconds)");
+    if ttl_sweep_secs > 0 {
+        db.start_ttl_sweeper(Duration::from_secs(ttl_sweep_secs));
+    }
+
+    // Auto-compaction: collections whose dead space exceeds the ratio are compacted in the background
+    if let Ok(v) = env::var("OXIDB_AUTO_COMPACT_RATIO") {
+        let ratio: f64 = v
+            .parse()
+            .expect("OXIDB_AUTO_COMPACT_RATIO must be a number between 0 and 1");
+        let interval_secs: u64 = env::var("OXIDB_AUTO_COMPACT_INTERVAL")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse()
+            .expect("OXIDB_AUTO_COMPACT_INTERVAL must be a valid u64 (seconds)");
+        if ratio > 0.0 && interval_secs > 0 {
+            db.start_auto_compactor(Duration::from_secs(interval_secs), ratio);
+            eprintln!("auto-compaction: dead space over {ratio}, checked every {interval_secs}s");
+        }
+    }
+
+    // Prometheus scrape endpoint
+    if let Ok(metrics_addr) = env::var("OXIDB_METRICS_ADDR") {
+        let listener = TcpListener::bind(&metrics_addr).expect("failed to bind metrics listener");
+        eprintln!("metrics: serving http://{metrics_addr}/metrics");
+        let db = Arc::clone(&db);
+        std::thread::spawn(move || metrics_http::serve(listener, db));
+    }
+
     // TLS
-    let tls_config = match (env::var("OXIDB_TLS_CERT"), env::var("OXIDB_TLS_KEY")) {
+    let (tls_config, tls_channel_binding) = match (env::var("OXIDB_TLS_CERT"), env::var("OXIDB_TLS_KEY")) {
         (Ok(cert), Ok(key)) => {
-            let config = tls::load_tls_config(Path::new(&cert), Path::new(&key))
+            let client_ca = env::var("OXIDB_TLS_CLIENT_CA").ok();
+            let config = tls::load_tls_config_with_client_ca(Path::new(&cert), Path::new(&key), client_ca.as_deref().map(Path::new))
                 .expect("failed to load TLS config");
             eprintln!("TLS: enabled");
+            if client_ca.is_some() {
+                eprintln!("TLS: client certificates required");
+            }
             if let Some(g) = &gelf {
                 g.send(GelfLevel::Informational, "TLS: enabled", &[]);
             }
-            Some(config)
+            let binding = tls::server_end_point(Path::new(&cert)).expect("failed to load TLS config");
+            (Some(config), Some(binding))
         }
-        _ => None,
+        _ => (None, None),
     };
 
     // Authentication
@@ -681,8 +1049,8 @@ fn main() {
         .map(|v| v == "true" || v == "1")
         .unwrap_or(false);
     let audit_log = if audit_enabled {
-        let log =
-            AuditLog::open(Path::new(&data_dir)).expect("failed to open audit log");
+        let log = AuditLog::open_with_rotation(Path::new(&data_dir), AuditRotation::from_env())
+            .expect("failed to open audit log");
         eprintln!("audit logging: enabled");
         if let Some(g) = &gelf {
             g.send(GelfLevel::Informational, "audit logging: enabled", &[]);
@@ -692,18 +1060,38 @@ fn main() {
         None
     };
 
+    // Slow query log
+    let slow_query_log = SlowQueryLog::from_env(gelf.clone());
+    if let Some(log) = &slow_query_log {
+        eprintln!("slow query log: queries over {}ms", log.threshold().as_millis());
+    }
+
     let state = Arc::new(ServerState {
         db,
         user_store,
         audit_log,
         gelf,
         auth_enabled,
+        session_limits: SessionLimits::from_env(),
+        rate_limiter: RateLimiter::from_env(),
+        client_cert_roles: ClientCertRoles::from_env(),
+        tls_channel_binding,
+        keepalive: Keepalive::from_env(),
+        shutdown: Shutdown::new(),
+        slow_query_log,
     });
 
+    let shutdown_timeout_secs: u64 = env::var("OXIDB_SHUTDOWN_TIMEOUT")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()
+        .expect("OXIDB_SHUTDOWN_TIMEOUT must be a valid u64 (seconds)");
+
+    let limiter = ConnectionLimiter::from_env();
     let listener = TcpListener::bind(&addr).expect("failed to bind TCP listener");
-    server_log!(state, GelfLevel::Notice, format!("oxidb-server listening on {addr} (pool_size={pool_size}, data_dir={data_dir}, idle_timeout={idle_timeout_secs}s)"));
+    server_log!(state, GelfLevel::Notice, format!("oxidb-server listening on {addr} (pool_size={pool_size}, max_connections={}, data_dir={data_dir}, idle_timeout={idle_timeout_secs}s)", limiter.max()));
 
-    let (tx, rx) = mpsc::channel::<TcpStream>();
+    // Every queued connection holds a permit, so the queue never fills
+    let (tx, rx) = mpsc::sync_channel::<(TcpStream, ConnectionPermit)>(limiter.max());
     let rx = Arc::new(Mutex::new(rx));
 
     for _ in 0..pool_size {
@@ -713,24 +1101,69 @@ fn main() {
         std::thread::spawn(move || loop {
             let stream = rx.lock().unwrap().recv();
             match stream {
-                Ok(stream) => handle_client(stream, &state, idle_timeout, tls_config.as_ref()),
+                Ok((stream, _permit)) => handle_client(stream, &state, idle_timeout, tls_config.as_ref()),
                 Err(_) => break,
             }
         });
     }
 
+    // SIGTERM / SIGINT: stop accepting, then wake the blocked accept below
+    let local_addr = listener.local_addr().expect("failed to read listener address");
+    let mut signals = signal_hook::iterator::Signals::new([signal_hook::consts::SIGTERM, signal_hook::consts::SIGINT])
+        .expect("failed to install signal handlers");
+    {
+        let state = Arc::clone(&state);
+        std::thread::spawn(move || {
+            if let Some(sig) = signals.forever().next() {
+                server_log!(state, GelfLevel::Notice, format!("received signal {sig}, shutting down"));
+                state.shutdown.request();
+                let _ = TcpStream::connect(local_addr);
+            }
+        });
+    }
+
     for stream in listener.incoming() {
+        if state.shutdown.is_requested() {
+            break;
+        }
         match stream {
-            Ok(s) => {
-                if let Err(e) = tx.send(s) {
-                    server_log!(state, GelfLevel::Error, format!("failed to dispatch connection: {e}"));
+            Ok(s) => match limiter.try_acquire() {
+                Some(permit) => {
+                    if let Err(e) = tx.send((s, permit)) {
+                        server_log!(state, GelfLevel::Error, format!("failed to dispatch connection: {e}"));
+                    }
                 }
-            }
+                None => {
+                    let peer = s.peer_addr().map(|a| a.to_string()).unwrap_or_else(|_| "unknown".into());
+                    server_log!(state, GelfLevel::Warning, format!("connection limit ({}) reached, rejecting {peer}", limiter.max()), extra: "peer" => &peer);
+                    // A TLS client can't read a plain frame; it just sees the close
+                    if tls_config.is_none() {
+                        connlimit::reject(s);
+                    }
+                }
+            },
             Err(e) => {
                 server_log!(state, GelfLevel::Error, format!("accept error: {e}"));
             }
         }
     }
+
+    // Workers exit once the connections already handed to them are done
+    drop(tx);
+    let open = state.shutdown.open_connections();
+    if open > 0 {
+        server_log!(state, GelfLevel::Notice, format!("waiting up to {shutdown_timeout_secs}s for {open} connection(s) to finish"));
+    }
+    if !state.shutdown.wait_for_connections(Duration::from_secs(shutdown_timeout_secs)) {
+        let open = state.shutdown.open_connections();
+        server_log!(state, GelfLevel::Warning, format!("shutdown timeout: {open} connection(s) still open"));
+    }
+    let rolled_back = state.db.rollback_all_transactions();
+    if rolled_back > 0 {
+        server_log!(state, GelfLevel::Notice, format!("rolled back {rolled_back} open transaction(s)"));
+    }
+    state.db.flush_indexes();
+    server_log!(state, GelfLevel::Notice, "oxidb-server stopped");
 }
 
 /// Run the server in cluster mode with Raft consensus.
@@ -752,7 +1185,10 @@ fn run_cluster_mode() {
         .unwrap_or_else(|_| "30".to_string())
         .parse()
         .expect("OXIDB_IDLE_TIMEOUT must be a valid u64 (seconds)");
-    let idle_timeout = Duration::from_secs(idle_timeout_secs);
+    // The async handlers only have a read timeout, so the keepalive shortens it
+    let idle_timeout = Keepalive::from_env()
+        .read_timeout(Duration::from_secs(idle_timeout_secs))
+        .unwrap_or(Duration::ZERO);
 
     let verbose = env::args().any(|a| a == "--verbose")
         || env::var("OXIDB_VERBOSE")
@@ -819,6 +1255,97 @@ fn run_cluster_mode() {
     }
     let db = Arc::new(db);
 
+    if let Ok(v) = env::var("OXIDB_TX_TIMEOUT") {
+        let secs: u64 = v
+            .parse()
+            .expect("OXIDB_TX_TIMEOUT must be a valid u64 (seconds)");
+        if secs > 0 {
+            db.set_default_tx_timeout(Some(Duration::from_secs(secs)));
+            db.start_tx_reaper(Duration::from_secs(1));
+            eprintln!("transaction timeout: {secs}s");
+        }
+    }
+
+    // Unindexed scans of large collections run on several threads (0 = one per CPU)
+    let scan_threads: usize = env::var("OXIDB_SCAN_THREADS")
+        .unwrap_or_else(|_| "0".to_string())
+        .parse()
+        .expect("OXIDB_SCAN_THREADS must be a valid usize");
+    if scan_threads > 0 {
+        db.set_scan_workers(scan_threads);
+    }
+
+    // When writes are fsynced: each on its own, shared by concurrent inserts, or never
+    if let Ok(v) = env::var("OXIDB_SYNC") {
+        let mode = SyncMode::parse(&v).expect("OXIDB_SYNC must be one of: full, group, none");
+        db.set_sync_mode(mode);
+    }
+
+    // Concurrent inserts into a collection share fsyncs; the leader waits this long for others
+    if let Ok(v) = env::var("OXIDB_GROUP_COMMIT_WINDOW_US") {
+        let micros: u64 = v
+            .parse()
+            .expect("OXIDB_GROUP_COMMIT_WINDOW_US must be a valid u64 (microseconds)");
+        db.set_group_commit_window(Duration::from_micros(micros));
+    }
+
+    // Size limits: oversized inserts and find results are rejected (0 = no limit)
+    let max_doc_size: usize = env::var("OXIDB_MAX_DOC_SIZE")
+        .unwrap_or_else(|_| "0".to_string())
+        .parse()
+        .expect("OXIDB_MAX_DOC_SIZE must be a valid usize (bytes)");
+    db.set_max_document_size(Some(max_doc_size).filter(|&n| n > 0));
+    let max_find_results: usize = env::var("OXIDB_MAX_FIND_RESULTS")
+        .unwrap_or_else(|_| "0".to_string())
+        .parse()
+        .expect("OXIDB_MAX_FIND_RESULTS must be a valid usize");
+    db.set_max_find_results(Some(max_find_results).filter(|&n| n > 0));
+
+    // Load collections past records that fail their checksum instead of refusing them
+    let skip_corrupt = env::var("OXIDB_SKIP_CORRUPT_RECORDS")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    db.set_skip_corrupt_records(skip_corrupt);
+
+    // Expired blobs are hidden immediately and purged by a background sweep
+    let sweep_secs: u64 = env::var("OXIDB_BLOB_SWEEP_INTERVAL")
+        .unwrap_or_else(|_| "60".to_string())
+        .parse()
+        .expect("OXIDB_BLOB_SWEEP_INTERVAL must be a valid u64 (seconds)");
+    if sweep_secs > 0 {
+        db.start_blob_sweeper(Duration::from_secs(sweep_secs));
+    }
+
+    // Documents past their _expireAt are removed by a sweep on the leader,
+    // started once the Raft node is up
+    let ttl_sweep_secs: u64 = env::var("OXIDB_TTL_SWEEP_INTERVAL")
+        .unwrap_or_else(|_| "60".to_string())
+        .parse()
+        .expect("OXIDB_TTL_SWEEP_INTERVAL must be a valid u64 (seconds)");
+
+    // Auto-compaction: collections whose dead space exceeds the ratio are compacted in the background
+    if let Ok(v) = env::var("OXIDB_AUTO_COMPACT_RATIO") {
+        let ratio: f64 = v
+            .parse()
+            .expect("OXIDB_AUTO_COMPACT_RATIO must be a number between 0 and 1");
+        let interval_secs: u64 = env::var("OXIDB_AUTO_COMPACT_INTERVAL")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse()
+            .expect("OXIDB_AUTO_COMPACT_INTERVAL must be a valid u64 (seconds)");
+        if ratio > 0.0 && interval_secs > 0 {
+            db.start_auto_compactor(Duration::from_secs(interval_secs), ratio);
+            eprintln!("auto-compaction: dead space over {ratio}, checked every {interval_secs}s");
+        }
+    }
+
+    // Prometheus scrape endpoint
+    if let Ok(metrics_addr) = env::var("OXIDB_METRICS_ADDR") {
+        let listener = TcpListener::bind(&metrics_addr).expect("failed to bind metrics listener");
+        eprintln!("metrics: serving http://{metrics_addr}/metrics");
+        let db = Arc::clone(&db);
+        std::thread::spawn(move || metrics_http::serve(listener, db));
+    }
+
     // Authentication
     let auth_enabled = env::var("OXIDB_AUTH")
         .map(|v| v == "true" || v == "1")
@@ -839,7 +1366,8 @@ fn run_cluster_mode() {
         .map(|v| v == "true" || v == "1")
         .unwrap_or(false);
     let audit_log = if audit_enabled {
-        let log = AuditLog::open(Path::new(&data_dir)).expect("failed to open audit log");
+        let log = AuditLog::open_with_rotation(Path::new(&data_dir), AuditRotation::from_env())
+            .expect("failed to open audit log");
         eprintln!("audit logging: enabled");
         if let Some(g) = &gelf {
             g.send(GelfLevel::Informational, "audit logging: enabled", &[]);
@@ -849,19 +1377,30 @@ fn run_cluster_mode() {
         None
     };
 
+    // Slow query log
+    let slow_query_log = SlowQueryLog::from_env(gelf.clone());
+    if let Some(log) = &slow_query_log {
+        eprintln!("slow query log: queries over {}ms", log.threshold().as_millis());
+    }
+
     // TLS
-    let tls_acceptor: Option<tokio_rustls::TlsAcceptor> =
+    let (tls_acceptor, tls_channel_binding): (Option<tokio_rustls::TlsAcceptor>, Option<Vec<u8>>) =
         match (env::var("OXIDB_TLS_CERT"), env::var("OXIDB_TLS_KEY")) {
             (Ok(cert), Ok(key)) => {
-                let config = tls::load_tls_config(Path::new(&cert), Path::new(&key))
+                let client_ca = env::var("OXIDB_TLS_CLIENT_CA").ok();
+                let config = tls::load_tls_config_with_client_ca(Path::new(&cert), Path::new(&key), client_ca.as_deref().map(Path::new))
                     .expect("failed to load TLS config");
                 eprintln!("TLS: enabled");
+                if client_ca.is_some() {
+                    eprintln!("TLS: client certificates required");
+                }
                 if let Some(g) = &gelf {
                     g.send(GelfLevel::Informational, "TLS: enabled", &[]);
                 }
-                Some(tokio_rustls::TlsAcceptor::from(config))
+                let binding = tls::server_end_point(Path::new(&cert)).expect("failed to load TLS config");
+                (Some(tokio_rustls::TlsAcceptor::from(config)), Some(binding))
             }
-            _ => None,
+            _ => (None, None),
         };
 
     // Build tokio runtime
@@ -875,6 +1414,12 @@ fn run_cluster_mode() {
         // Create Raft node
         let node_id = raft_config.node_id;
         let raft_addr = raft_config.raft_addr.clone();
+        // Peers reach this node at its OXIDB_RAFT_PEERS entry; the listen
+        // address may be a wildcard
+        let advertised_raft_addr = raft_config
+            .peers
+            .get(&node_id)
+            .map_or_else(|| raft_addr.clone(), |node| node.addr.clone());
         let openraft_config = RaftConfig::openraft_config();
 
         let store = OxiDbStore::new(Arc::clone(&db));
@@ -890,6 +1435,10 @@ fn run_cluster_mode() {
         .await
         .expect("failed to create Raft node");
         let raft = Arc::new(raft);
+        if ttl_sweep_secs > 0 {
+            let interval = Duration::from_secs(ttl_sweep_secs);
+            tokio::spawn(management::run_ttl_sweeper(Arc::clone(&raft), Arc::clone(&db), interval));
+        }
 
         // Build async server state
         let state = Arc::new(AsyncServerState {
@@ -897,7 +1446,13 @@ fn run_cluster_mode() {
             user_store,
             audit_log,
             auth_enabled,
+            session_limits: SessionLimits::from_env(),
+            rate_limiter: RateLimiter::from_env(),
+            client_cert_roles: ClientCertRoles::from_env(),
+            tls_channel_binding,
             raft: Some(Arc::clone(&raft)),
+            raft_addr: advertised_raft_addr,
+            slow_query_log: slow_query_log.map(Arc::new),
         });
 
         // Spawn Raft RPC listener
@@ -914,13 +1469,15 @@ fn run_cluster_mode() {
         }
 
         let raft_for_rpc = Arc::clone(&raft);
+        let advertise_addr = Arc::new(raft_config.advertise_addr.clone());
         tokio::spawn(async move {
             loop {
                 match raft_listener.accept().await {
                     Ok((stream, _)) => {
                         let raft_ref = Arc::clone(&raft_for_rpc);
+                        let advertise_addr = Arc::clone(&advertise_addr);
                         tokio::spawn(async move {
-                            network::handle_raft_rpc(stream, &raft_ref).await;
+                            network::handle_raft_rpc(stream, &raft_ref, &advertise_addr).await;
                         });
                     }
                     Err(e) => {
@@ -948,12 +1505,22 @@ fn run_cluster_mode() {
             );
         }
 
+        let limiter = ConnectionLimiter::from_env();
         loop {
             match client_listener.accept().await {
-                Ok((stream, _)) => {
+                Ok((stream, peer)) => {
+                    let Some(permit) = limiter.try_acquire() else {
+                        eprintln!("connection limit ({}) reached, rejecting {peer}", limiter.max());
+                        if let (None, Ok(stream)) = (&tls_acceptor, stream.into_std()) {
+                            let _ = stream.set_nonblocking(false);
+                            connlimit::reject(stream);
+                        }
+                        continue;
+                    };
                     let state = Arc::clone(&state);
                     let tls = tls_acceptor.clone();
                     tokio::spawn(async move {
+                        let _permit = permit;
                         if let Some(acceptor) = tls {
                             async_server::handle_tls_connection(
                                 stream, state, acceptor, idle_timeout,
@@ -0,0 +1,43 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+
+use oxidb::OxiDb;
+
+/// Largest request head read from a scraper.
+const MAX_REQUEST_BYTES: u64 = 8192;
+
+/// Serve `GET /metrics` over plain HTTP for Prometheus scrapers, one request
+/// per connection. Connections are handled one at a time; a read timeout
+/// keeps a stalled client from holding up the next scrape.
+pub fn serve(listener: TcpListener, db: Arc<OxiDb>) {
+    for stream in listener.incoming().flatten() {
+        let _ = respond(stream, &db);
+    }
+}
+
+fn respond(mut stream: TcpStream, db: &OxiDb) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream.try_clone()?.take(MAX_REQUEST_BYTES));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Skip the headers; nothing in them changes the response
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && !line.trim_end().is_empty() {
+        line.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", db.metrics_text()),
+        (Some("GET"), _) => ("404 Not Found", "not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "method not allowed\n".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
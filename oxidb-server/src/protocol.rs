@@ -1,27 +1,309 @@
+use std::borrow::Cow;
 use std::io::{self, Read, Write};
 
-/// Read a length-prefixed message: [u32 LE length][json bytes].
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde_json::Value;
+
+const MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+
+/// Set in the length prefix when the body is gzip-compressed. Lengths never
+/// reach it: messages are capped at 16 MiB.
+const GZIP_FLAG: u32 = 1 << 31;
+
+/// Bodies smaller than this are sent uncompressed even on a connection that
+/// asked for compression; gzip's overhead would outweigh the savings.
+pub const COMPRESSION_THRESHOLD: usize = 4 * 1024;
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "message too large (>16 MiB)")
+}
+
+/// Body encoding used on a connection. Framing is the same for every codec;
+/// connections start out in `Json` and switch with a `set_codec` request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Codec {
+    #[default]
+    Json,
+    MessagePack,
+}
+
+impl Codec {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(Codec::Json),
+            "msgpack" => Some(Codec::MessagePack),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Codec::Json => "json",
+            Codec::MessagePack => "msgpack",
+        }
+    }
+
+    /// Decode a request body into the JSON value the handler works on.
+    pub fn decode(self, body: &[u8]) -> Result<Value, String> {
+        match self {
+            Codec::Json => serde_json::from_slice(body).map_err(|e| format!("invalid JSON: {e}")),
+            Codec::MessagePack => {
+                let mut reader = body;
+                let value = rmpv::decode::read_value(&mut reader)
+                    .map_err(|e| format!("invalid MessagePack: {e}"))?;
+                if !reader.is_empty() {
+                    return Err("invalid MessagePack: trailing bytes after value".into());
+                }
+                msgpack_to_json(value).map_err(|e| format!("invalid MessagePack: {e}"))
+            }
+        }
+    }
+
+    /// Encode a value as a message body.
+    pub fn encode_value(self, value: &Value) -> Vec<u8> {
+        match self {
+            Codec::Json => serde_json::to_vec(value).unwrap(),
+            Codec::MessagePack => {
+                let mut out = Vec::new();
+                rmpv::encode::write_value(&mut out, &json_to_msgpack(value)).unwrap();
+                out
+            }
+        }
+    }
+
+    /// Encode a response for the wire. Handlers build responses as JSON
+    /// bytes, which pass through untouched in `Json` mode. Bytes that aren't
+    /// valid JSON become an error response rather than a nil.
+    pub fn encode(self, json: &[u8]) -> Cow<'_, [u8]> {
+        match self {
+            Codec::Json => Cow::Borrowed(json),
+            Codec::MessagePack => Cow::Owned(match serde_json::from_slice::<Value>(json) {
+                Ok(value) => self.encode_value(&value),
+                Err(e) => self.encode_value(&serde_json::json!({
+                    "ok": false,
+                    "error": format!("failed to encode response: {e}"),
+                    "code": "json_error",
+                })),
+            }),
+        }
+    }
+}
+
+// Values are converted by hand rather than through serde: with serde_json's
+// `arbitrary_precision` feature, numbers serialize as a private wrapper
+// struct instead of as MessagePack integers and floats.
+
+fn json_to_msgpack(value: &Value) -> rmpv::Value {
+    match value {
+        Value::Null => rmpv::Value::Nil,
+        Value::Bool(b) => rmpv::Value::Boolean(*b),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                rmpv::Value::from(i)
+            } else if let Some(u) = n.as_u64() {
+                rmpv::Value::from(u)
+            } else {
+                rmpv::Value::F64(n.as_f64().unwrap_or(f64::NAN))
+            }
+        }
+        Value::String(s) => rmpv::Value::from(s.as_str()),
+        Value::Array(items) => rmpv::Value::Array(items.iter().map(json_to_msgpack).collect()),
+        Value::Object(map) => rmpv::Value::Map(
+            map.iter().map(|(k, v)| (rmpv::Value::from(k.as_str()), json_to_msgpack(v))).collect(),
+        ),
+    }
+}
+
+fn msgpack_to_json(value: rmpv::Value) -> Result<Value, &'static str> {
+    Ok(match value {
+        rmpv::Value::Nil => Value::Null,
+        rmpv::Value::Boolean(b) => Value::Bool(b),
+        rmpv::Value::Integer(i) => match (i.as_i64(), i.as_u64()) {
+            (Some(n), _) => Value::from(n),
+            (None, Some(n)) => Value::from(n),
+            (None, None) => return Err("integer out of range"),
+        },
+        rmpv::Value::F32(f) => json_float(f64::from(f))?,
+        rmpv::Value::F64(f) => json_float(f)?,
+        rmpv::Value::String(s) => Value::String(s.into_str().ok_or("string is not valid UTF-8")?),
+        rmpv::Value::Array(items) => {
+            Value::Array(items.into_iter().map(msgpack_to_json).collect::<Result<_, _>>()?)
+        }
+        rmpv::Value::Map(entries) => {
+            let mut map = serde_json::Map::with_capacity(entries.len());
+            for (k, v) in entries {
+                let rmpv::Value::String(k) = k else {
+                    return Err("map keys must be strings");
+                };
+                let k = k.into_str().ok_or("string is not valid UTF-8")?;
+                map.insert(k, msgpack_to_json(v)?);
+            }
+            Value::Object(map)
+        }
+        rmpv::Value::Binary(_) | rmpv::Value::Ext(..) => {
+            return Err("binary and extension types are not supported");
+        }
+    })
+}
+
+fn json_float(f: f64) -> Result<Value, &'static str> {
+    serde_json::Number::from_f64(f).map(Value::Number).ok_or("NaN and infinity are not supported")
+}
+
+/// If `request` is a `set_codec` handshake, the codec it asks for.
+pub fn codec_request(request: &Value) -> Option<Result<Codec, &'static str>> {
+    if request.get("cmd").and_then(|v| v.as_str()) != Some("set_codec") {
+        return None;
+    }
+    Some(
+        request
+            .get("codec")
+            .and_then(|v| v.as_str())
+            .and_then(Codec::from_name)
+            .ok_or("'codec' must be \"json\" or \"msgpack\""),
+    )
+}
+
+/// Frame compression used for a connection's responses. Connections start
+/// out with `None` and opt in with a `set_compression` request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+}
+
+impl Compression {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Compression::None),
+            "gzip" => Some(Compression::Gzip),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+        }
+    }
+}
+
+/// If `request` is a `set_compression` handshake, the compression it asks for.
+pub fn compression_request(request: &Value) -> Option<Result<Compression, &'static str>> {
+    if request.get("cmd").and_then(|v| v.as_str()) != Some("set_compression") {
+        return None;
+    }
+    Some(
+        request
+            .get("compression")
+            .and_then(|v| v.as_str())
+            .and_then(Compression::from_name)
+            .ok_or("'compression' must be \"gzip\" or \"none\""),
+    )
+}
+
+/// The length prefix and body to send for `data`. The body is gzipped when
+/// `compression` asks for it, `data` is at least [`COMPRESSION_THRESHOLD`],
+/// and compressing actually makes it smaller.
+pub(crate) fn frame(data: &[u8], compression: Compression) -> (u32, Cow<'_, [u8]>) {
+    if compression == Compression::Gzip && data.len() >= COMPRESSION_THRESHOLD {
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        let gz = encoder.write_all(data).and_then(|()| encoder.finish()).ok();
+        if let Some(gz) = gz.filter(|gz| gz.len() < data.len()) {
+            return (gz.len() as u32 | GZIP_FLAG, Cow::Owned(gz));
+        }
+    }
+    (data.len() as u32, Cow::Borrowed(data))
+}
+
+/// Split a length prefix into the body length and whether it's gzipped.
+pub(crate) fn parse_prefix(prefix: [u8; 4]) -> io::Result<(usize, bool)> {
+    let raw = u32::from_le_bytes(prefix);
+    let len = (raw & !GZIP_FLAG) as usize;
+    if len > MAX_MESSAGE_LEN {
+        return Err(too_large());
+    }
+    Ok((len, raw & GZIP_FLAG != 0))
+}
+
+/// Decompress a gzipped body, holding it to the same size limit as an
+/// uncompressed one.
+pub(crate) fn gunzip(body: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    GzDecoder::new(body).take(MAX_MESSAGE_LEN as u64 + 1).read_to_end(&mut out)?;
+    if out.len() > MAX_MESSAGE_LEN {
+        return Err(too_large());
+    }
+    Ok(out)
+}
+
+/// Read a length-prefixed message: [u32 LE length][body bytes]. A gzipped
+/// body is decompressed before it's returned.
 pub fn read_message(reader: &mut impl Read) -> io::Result<Vec<u8>> {
     let mut len_buf = [0u8; 4];
     reader.read_exact(&mut len_buf)?;
-    let len = u32::from_le_bytes(len_buf) as usize;
-
-    if len > 16 * 1024 * 1024 {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "message too large (>16 MiB)",
-        ));
-    }
+    let (len, gzipped) = parse_prefix(len_buf)?;
 
     let mut buf = vec![0u8; len];
     reader.read_exact(&mut buf)?;
-    Ok(buf)
+    if gzipped { gunzip(&buf) } else { Ok(buf) }
 }
 
-/// Write a length-prefixed message: [u32 LE length][json bytes].
+/// Write a length-prefixed message: [u32 LE length][body bytes].
 pub fn write_message(writer: &mut impl Write, data: &[u8]) -> io::Result<()> {
-    let len = (data.len() as u32).to_le_bytes();
-    writer.write_all(&len)?;
-    writer.write_all(data)?;
+    write_message_with(writer, data, Compression::None)
+}
+
+/// Write a length-prefixed message, gzipping the body if `compression` is
+/// on and the body is large enough to be worth it.
+pub fn write_message_with(writer: &mut impl Write, data: &[u8], compression: Compression) -> io::Result<()> {
+    let (prefix, body) = frame(data, compression);
+    writer.write_all(&prefix.to_le_bytes())?;
+    writer.write_all(&body)?;
     writer.flush()
 }
+
+/// Poll for a length-prefixed message on a reader with a short read timeout.
+///
+/// Unlike `read_message`, a timeout never loses a partially received frame:
+/// bytes are accumulated in `buf` across calls. Returns `Ok(None)` until a
+/// complete message is available, and `UnexpectedEof` once the peer closes.
+pub fn poll_message(reader: &mut impl Read, buf: &mut Vec<u8>) -> io::Result<Option<Vec<u8>>> {
+    if let Some(msg) = take_message(buf)? {
+        return Ok(Some(msg));
+    }
+    let mut chunk = [0u8; 4096];
+    match reader.read(&mut chunk) {
+        Ok(0) => Err(io::ErrorKind::UnexpectedEof.into()),
+        Ok(n) => {
+            buf.extend_from_slice(&chunk[..n]);
+            take_message(buf)
+        }
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted
+            ) =>
+        {
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Split one complete frame off the front of `buf`, if there is one.
+fn take_message(buf: &mut Vec<u8>) -> io::Result<Option<Vec<u8>>> {
+    if buf.len() < 4 {
+        return Ok(None);
+    }
+    let (len, gzipped) = parse_prefix([buf[0], buf[1], buf[2], buf[3]])?;
+    if buf.len() < 4 + len {
+        return Ok(None);
+    }
+    let msg = if gzipped { gunzip(&buf[4..4 + len])? } else { buf[4..4 + len].to_vec() };
+    buf.drain(..4 + len);
+    Ok(Some(msg))
+}
@@ -8,6 +8,8 @@ use openraft::BasicNode;
 pub struct RaftConfig {
     pub node_id: u64,
     pub raft_addr: String,
+    /// Client address other nodes give out when redirecting clients here.
+    pub advertise_addr: String,
     pub peers: BTreeMap<u64, BasicNode>,
 }
 
@@ -19,6 +21,9 @@ impl RaftConfig {
 
         let raft_addr =
             env::var("OXIDB_RAFT_ADDR").unwrap_or_else(|_| "127.0.0.1:4445".to_string());
+        let advertise_addr = env::var("OXIDB_ADVERTISE_ADDR")
+            .or_else(|_| env::var("OXIDB_ADDR"))
+            .unwrap_or_else(|_| "127.0.0.1:4444".to_string());
 
         // Parse OXIDB_RAFT_PEERS: "1=host1:port1,2=host2:port2,..."
         let mut peers = BTreeMap::new();
@@ -41,6 +46,7 @@ impl RaftConfig {
         Some(Self {
             node_id,
             raft_addr,
+            advertise_addr,
             peers,
         })
     }
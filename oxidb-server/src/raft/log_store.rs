@@ -85,14 +85,26 @@ fn apply_request(db: &OxiDb, req: OxiDbRequest) -> OxiDbResponse {
             Ok(ids) => OxiDbResponse::Ok { data: json!(ids) },
             Err(e) => OxiDbResponse::Error { message: e.to_string() },
         },
-        OxiDbRequest::Update { collection, query, update } => match db.update(&collection, &query, &update) {
-            Ok(count) => OxiDbResponse::Ok { data: json!({ "modified": count }) },
-            Err(e) => OxiDbResponse::Error { message: e.to_string() },
-        },
-        OxiDbRequest::UpdateOne { collection, query, update } => match db.update_one(&collection, &query, &update) {
-            Ok(count) => OxiDbResponse::Ok { data: json!({ "modified": count }) },
-            Err(e) => OxiDbResponse::Error { message: e.to_string() },
-        },
+        OxiDbRequest::Update { collection, query, update, expected_version } => {
+            match db.update_with_version(&collection, &query, &update, expected_version) {
+                Ok(count) => OxiDbResponse::Ok { data: json!({ "modified": count }) },
+                Err(e) => OxiDbResponse::Error { message: e.to_string() },
+            }
+        }
+        OxiDbRequest::UpdateOne { collection, query, update, expected_version } => {
+            match db.update_one_with_version(&collection, &query, &update, expected_version) {
+                Ok(count) => OxiDbResponse::Ok { data: json!({ "modified": count }) },
+                Err(e) => OxiDbResponse::Error { message: e.to_string() },
+            }
+        }
+        OxiDbRequest::FindAndModify { collection, query, update, options } => {
+            let result = oxidb::query::parse_find_and_modify_options(&options)
+                .and_then(|opts| db.find_and_modify(&collection, &query, update.as_ref(), &opts));
+            match result {
+                Ok(doc) => OxiDbResponse::Ok { data: json!(doc) },
+                Err(e) => OxiDbResponse::Error { message: e.to_string() },
+            }
+        }
         OxiDbRequest::Delete { collection, query } => match db.delete(&collection, &query) {
             Ok(count) => OxiDbResponse::Ok { data: json!({ "deleted": count }) },
             Err(e) => OxiDbResponse::Error { message: e.to_string() },
@@ -101,6 +113,12 @@ fn apply_request(db: &OxiDb, req: OxiDbRequest) -> OxiDbResponse {
             Ok(count) => OxiDbResponse::Ok { data: json!({ "deleted": count }) },
             Err(e) => OxiDbResponse::Error { message: e.to_string() },
         },
+        OxiDbRequest::BulkWrite { collection, ops } => {
+            match oxidb::bulk::parse_bulk_ops(&ops).and_then(|ops| db.bulk_write(&collection, ops)) {
+                Ok(results) => OxiDbResponse::Ok { data: json!(results.iter().map(|r| r.to_json()).collect::<Vec<_>>()) },
+                Err(e) => OxiDbResponse::Error { message: e.to_string() },
+            }
+        }
         OxiDbRequest::CreateCollection { name } => match db.create_collection(&name) {
             Ok(()) => OxiDbResponse::Ok { data: json!("collection created") },
             Err(e) => OxiDbResponse::Error { message: e.to_string() },
@@ -115,10 +133,21 @@ fn apply_request(db: &OxiDb, req: OxiDbRequest) -> OxiDbResponse {
             },
             Err(e) => OxiDbResponse::Error { message: e.to_string() },
         },
-        OxiDbRequest::CreateIndex { collection, field } => match db.create_index(&collection, &field) {
-            Ok(()) => OxiDbResponse::Ok { data: json!("index created") },
-            Err(e) => OxiDbResponse::Error { message: e.to_string() },
-        },
+        OxiDbRequest::CreateIndex { collection, field, descending, sparse } => {
+            let created = if sparse {
+                db.create_sparse_index(&collection, &field)
+            } else {
+                db.create_index(&collection, &field)
+            };
+            let result = match created {
+                Ok(()) if descending => db.create_descending_index(&collection, &field),
+                other => other,
+            };
+            match result {
+                Ok(()) => OxiDbResponse::Ok { data: json!("index created") },
+                Err(e) => OxiDbResponse::Error { message: e.to_string() },
+            }
+        }
         OxiDbRequest::CreateUniqueIndex { collection, field } => match db.create_unique_index(&collection, &field) {
             Ok(()) => OxiDbResponse::Ok { data: json!("unique index created") },
             Err(e) => OxiDbResponse::Error { message: e.to_string() },
@@ -131,18 +160,90 @@ fn apply_request(db: &OxiDb, req: OxiDbRequest) -> OxiDbResponse {
             Ok(()) => OxiDbResponse::Ok { data: json!("text index created") },
             Err(e) => OxiDbResponse::Error { message: e.to_string() },
         },
-        OxiDbRequest::DropIndex { collection, index } => match db.drop_index(&collection, &index) {
-            Ok(()) => OxiDbResponse::Ok { data: json!("index dropped") },
+        OxiDbRequest::CreateGeoIndex { collection, field } => match db.create_geo_index(&collection, &field) {
+            Ok(()) => OxiDbResponse::Ok { data: json!("geo index created") },
             Err(e) => OxiDbResponse::Error { message: e.to_string() },
         },
-        OxiDbRequest::CreateBucket { bucket } => match db.create_bucket(&bucket) {
-            Ok(()) => OxiDbResponse::Ok { data: json!("bucket created") },
+        OxiDbRequest::DropIndex { collection, index } => match db.drop_index(&collection, &index) {
+            Ok(()) => OxiDbResponse::Ok { data: json!("index dropped") },
             Err(e) => OxiDbResponse::Error { message: e.to_string() },
         },
+        OxiDbRequest::SetSchema { collection, schema, level, coerce } => {
+            let schema = match schema {
+                serde_json::Value::Null => Ok(None),
+                schema => oxidb::ValidationLevel::parse(&level)
+                    .and_then(|level| oxidb::CollectionSchema::new(schema, level, coerce))
+                    .map(Some),
+            };
+            let msg = if matches!(schema, Ok(None)) { "schema removed" } else { "schema set" };
+            match schema.and_then(|schema| db.set_collection_schema(&collection, schema)) {
+                Ok(()) => OxiDbResponse::Ok { data: json!(msg) },
+                Err(e) => OxiDbResponse::Error { message: e.to_string() },
+            }
+        }
+        OxiDbRequest::SetDefaults { collection, defaults } => {
+            let defaults = match defaults {
+                serde_json::Value::Null => Ok(oxidb::FieldDefaults::default()),
+                spec => oxidb::FieldDefaults::new(spec),
+            };
+            match defaults.and_then(|defaults| db.set_collection_defaults(&collection, defaults)) {
+                Ok(()) => OxiDbResponse::Ok { data: json!("defaults set") },
+                Err(e) => OxiDbResponse::Error { message: e.to_string() },
+            }
+        }
+        OxiDbRequest::CreateBucket { bucket, owner } => {
+            let created = match owner {
+                Some(owner) => db.create_owned_bucket(&bucket, &owner),
+                None => db.create_bucket(&bucket),
+            };
+            match created {
+                Ok(()) => OxiDbResponse::Ok { data: json!("bucket created") },
+                Err(e) => OxiDbResponse::Error { message: e.to_string() },
+            }
+        }
         OxiDbRequest::DeleteBucket { bucket } => match db.delete_bucket(&bucket) {
             Ok(()) => OxiDbResponse::Ok { data: json!("bucket deleted") },
             Err(e) => OxiDbResponse::Error { message: e.to_string() },
         },
+        OxiDbRequest::PurgeExpired { collection, now } => {
+            let now = match chrono::DateTime::parse_from_rfc3339(&now) {
+                Ok(t) => t.with_timezone(&chrono::Utc),
+                Err(e) => return OxiDbResponse::Error { message: format!("invalid sweep time '{now}': {e}") },
+            };
+            match db.purge_expired_documents_in(&collection, now) {
+                Ok(n) => OxiDbResponse::Ok { data: json!({ "deleted": n }) },
+                Err(e) => OxiDbResponse::Error { message: e.to_string() },
+            }
+        }
+        OxiDbRequest::SetBucketAcl { bucket, acl } => {
+            let acl = match acl {
+                serde_json::Value::Null => None,
+                acl => match serde_json::from_value::<oxidb::blob::BucketAcl>(acl) {
+                    Ok(acl) if !acl.owner.is_empty() => Some(acl),
+                    _ => {
+                        return OxiDbResponse::Error {
+                            message: "'acl' must be null or an object with 'owner', 'readers' and 'writers'".into(),
+                        };
+                    }
+                },
+            };
+            match db.set_bucket_acl(&bucket, acl.clone()) {
+                Ok(()) => OxiDbResponse::Ok { data: json!({"bucket": bucket, "acl": acl}) },
+                Err(e) => OxiDbResponse::Error { message: e.to_string() },
+            }
+        }
+        OxiDbRequest::SetBucketTokenizer { bucket, tokenizer } => {
+            let tokenizer = match oxidb::fts::Tokenizer::parse(&tokenizer) {
+                Ok(t) => t,
+                Err(e) => return OxiDbResponse::Error { message: e },
+            };
+            match db.set_bucket_tokenizer(&bucket, tokenizer) {
+                Ok(skipped) => OxiDbResponse::Ok {
+                    data: json!({"bucket": bucket, "tokenizer": tokenizer.as_str(), "not_reindexed": skipped}),
+                },
+                Err(e) => OxiDbResponse::Error { message: e.to_string() },
+            }
+        }
         OxiDbRequest::PutObject { bucket, key, data_b64, content_type, metadata } => {
             let data = match base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &data_b64) {
                 Ok(d) => d,
@@ -1,31 +1,38 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
+use std::time::Duration;
 
-use openraft::BasicNode;
+use openraft::{BasicNode, ChangeMembers};
+use openraft::error::{CheckIsLeaderError, RaftError};
+use oxidb::OxiDb;
 use serde_json::{Value, json};
 
 use crate::handler;
 
-use super::types::OxiRaft;
+use super::network;
+use super::types::{OxiDbRequest, OxiDbResponse, OxiRaft};
 
 /// Handle Raft cluster management commands.
-pub async fn handle_raft_command(cmd: &str, request: &Value, raft: &Arc<OxiRaft>) -> Vec<u8> {
+/// `raft_addr` is this node's Raft address, which `raft_init` registers for
+/// it so that peers can reach it once they join.
+pub async fn handle_raft_command(cmd: &str, request: &Value, raft: &Arc<OxiRaft>, raft_addr: &str) -> Vec<u8> {
     match cmd {
-        "raft_init" => raft_init(raft).await,
+        "raft_init" => raft_init(raft, raft_addr).await,
         "raft_add_learner" => raft_add_learner(request, raft).await,
         "raft_change_membership" => raft_change_membership(request, raft).await,
+        "raft_remove_node" => raft_remove_node(request, raft).await,
         "raft_metrics" => raft_metrics(raft).await,
         _ => handler::err_bytes(&format!("unknown raft command: {cmd}")),
     }
 }
 
 /// Initialize a single-node Raft cluster.
-async fn raft_init(raft: &OxiRaft) -> Vec<u8> {
+async fn raft_init(raft: &OxiRaft, raft_addr: &str) -> Vec<u8> {
     let mut members = BTreeMap::new();
     // Get node_id from the raft metrics
     let metrics = raft.metrics().borrow().clone();
     let node_id = metrics.id;
-    members.insert(node_id, BasicNode::default());
+    members.insert(node_id, BasicNode { addr: raft_addr.to_string() });
 
     match raft.initialize(members).await {
         Ok(()) => handler::ok_bytes(json!("cluster initialized")),
@@ -61,7 +68,7 @@ async fn raft_change_membership(request: &Value, raft: &OxiRaft) -> Vec<u8> {
         Some(arr) => {
             let ids: Option<Vec<u64>> = arr.iter().map(|v| v.as_u64()).collect();
             match ids {
-                Some(ids) => ids.into_iter().collect::<std::collections::BTreeSet<u64>>(),
+                Some(ids) => ids.into_iter().collect::<BTreeSet<u64>>(),
                 None => return handler::err_bytes("'members' must be array of node IDs"),
             }
         }
@@ -77,6 +84,131 @@ async fn raft_change_membership(request: &Value, raft: &OxiRaft) -> Vec<u8> {
     }
 }
 
+/// Remove a node from the cluster: a voter leaves the voting set and, like a
+/// learner, stops receiving replication. The last voter can't be removed.
+async fn raft_remove_node(request: &Value, raft: &OxiRaft) -> Vec<u8> {
+    let node_id = match request.get("node_id").and_then(|v| v.as_u64()) {
+        Some(id) => id,
+        None => return handler::err_bytes("missing 'node_id'"),
+    };
+
+    let membership = raft.metrics().borrow().membership_config.membership().clone();
+    let change = if membership.voter_ids().any(|id| id == node_id) {
+        if membership.voter_ids().count() == 1 {
+            return handler::err_bytes("can't remove the last voter");
+        }
+        ChangeMembers::RemoveVoters(BTreeSet::from([node_id]))
+    } else if membership.get_node(&node_id).is_some() {
+        ChangeMembers::RemoveNodes(BTreeSet::from([node_id]))
+    } else {
+        return handler::err_bytes(&format!("node {node_id} is not a cluster member"));
+    };
+
+    match raft.change_membership(change, false).await {
+        Ok(resp) => handler::ok_bytes(json!({
+            "log_id": format!("{}", resp.log_id),
+            "membership": format!("{:?}", resp.membership),
+        })),
+        Err(e) => handler::err_bytes(&format!("remove node failed: {e}")),
+    }
+}
+
+/// Why this node can't serve requests yet, if it can't: it must be a voter
+/// in the cluster and know the current leader.
+pub fn not_ready_reason(raft: &OxiRaft) -> Option<String> {
+    let metrics = raft.metrics().borrow().clone();
+    if !metrics.membership_config.membership().voter_ids().any(|id| id == metrics.id) {
+        return Some(format!("node {} is not a voting cluster member", metrics.id));
+    }
+    if metrics.current_leader.is_none() {
+        return Some("no raft leader".to_string());
+    }
+    None
+}
+
+/// Wait until a read on this node would be linearizable: it must still be
+/// the leader (confirmed with a quorum) and have applied every write
+/// committed before now. On a follower the error is a `not_leader` response
+/// naming the leader, so the client can resend the read there.
+pub async fn ensure_linearizable(raft: &OxiRaft) -> Result<(), Vec<u8>> {
+    match raft.ensure_linearizable().await {
+        Ok(_) => Ok(()),
+        Err(RaftError::APIError(CheckIsLeaderError::ForwardToLeader(_))) => Err(not_leader_bytes(raft).await),
+        Err(e) => Err(handler::err_bytes(&format!("raft error: {e}"))),
+    }
+}
+
+/// Whether this node is the leader, per its Raft metrics.
+pub fn is_leader(raft: &OxiRaft) -> bool {
+    let metrics = raft.metrics().borrow().clone();
+    metrics.current_leader == Some(metrics.id)
+}
+
+/// Delete documents past their `_expireAt` every `interval`. Only the
+/// leader sweeps: for each collection holding expired documents it proposes
+/// a `PurgeExpired` entry stamped with its own clock, so every replica
+/// removes the same documents. Runs until its task is aborted.
+pub async fn run_ttl_sweeper(raft: Arc<OxiRaft>, db: Arc<OxiDb>, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        if !is_leader(&raft) {
+            continue;
+        }
+        let now = chrono::Utc::now();
+        let db = Arc::clone(&db);
+        let collections = match tokio::task::spawn_blocking(move || db.collections_with_expired_documents(now)).await {
+            Ok(Ok(collections)) => collections,
+            Ok(Err(e)) => {
+                eprintln!("[warn] TTL sweep failed: {e}");
+                continue;
+            }
+            Err(e) => {
+                eprintln!("[warn] TTL sweep failed: {e}");
+                continue;
+            }
+        };
+        for collection in collections {
+            let request = OxiDbRequest::PurgeExpired { collection, now: now.to_rfc3339() };
+            match raft.client_write(request).await {
+                Ok(resp) => {
+                    if let OxiDbResponse::Error { message } = resp.data {
+                        eprintln!("[warn] TTL sweep failed: {message}");
+                    }
+                }
+                // Leadership moved: the new leader sweeps from now on
+                Err(e) => {
+                    eprintln!("[warn] TTL sweep failed: {e}");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// A `not_leader` error response for a request the leader must handle.
+/// `leader` is the leader's client address, asked of the leader over its
+/// Raft port; it is null while there is no leader or it can't be reached.
+pub async fn not_leader_bytes(raft: &OxiRaft) -> Vec<u8> {
+    let (leader_id, leader_raft_addr) = {
+        let metrics = raft.metrics().borrow().clone();
+        let leader_id = metrics.current_leader;
+        let node = leader_id.and_then(|id| metrics.membership_config.membership().get_node(&id));
+        (leader_id, node.map(|node| node.addr.clone()))
+    };
+    let leader = match leader_raft_addr {
+        Some(addr) => network::fetch_client_addr(&addr).await.ok(),
+        None => None,
+    };
+    serde_json::to_vec(&json!({
+        "ok": false,
+        "error": "not leader: send this request to the leader",
+        "code": "not_leader",
+        "leader_id": leader_id,
+        "leader": leader,
+    }))
+    .unwrap()
+}
+
 /// Return current Raft metrics.
 async fn raft_metrics(raft: &OxiRaft) -> Vec<u8> {
     let metrics = raft.metrics().borrow().clone();
@@ -172,8 +172,23 @@ impl RaftNetwork<TypeConfig> for OxiDbNetwork {
     }
 }
 
-/// Handle incoming Raft RPC on the Raft listener port.
-pub async fn handle_raft_rpc(mut stream: TcpStream, raft: &openraft::Raft<TypeConfig>) {
+/// Ask the node whose Raft RPC listener is at `raft_addr` for its client
+/// address, so clients can be redirected to it.
+pub async fn fetch_client_addr(raft_addr: &str) -> Result<String, String> {
+    let msg = RaftRpc {
+        kind: "client_addr".to_string(),
+        payload: Value::Null,
+    };
+    let resp = rpc_call(raft_addr, &msg).await?;
+    match (resp.kind.as_str(), resp.payload) {
+        ("client_addr", Value::String(addr)) => Ok(addr),
+        (_, payload) => Err(payload.as_str().unwrap_or("unexpected response").to_string()),
+    }
+}
+
+/// Handle incoming Raft RPC on the Raft listener port. `client_addr` is this
+/// node's advertised client address, returned to peers that ask for it.
+pub async fn handle_raft_rpc(mut stream: TcpStream, raft: &openraft::Raft<TypeConfig>, client_addr: &str) {
     // Read a single RPC message.
     let mut len_buf = [0u8; 4];
     if stream.read_exact(&mut len_buf).await.is_err() {
@@ -253,6 +268,10 @@ pub async fn handle_raft_rpc(mut stream: TcpStream, raft: &openraft::Raft<TypeCo
                 },
             }
         }
+        "client_addr" => RaftRpcResponse {
+            kind: "client_addr".to_string(),
+            payload: Value::String(client_addr.to_string()),
+        },
         _ => RaftRpcResponse {
             kind: "error".to_string(),
             payload: Value::String("unknown rpc kind".to_string()),
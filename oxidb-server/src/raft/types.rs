@@ -32,11 +32,22 @@ pub enum OxiDbRequest {
         collection: String,
         query: Value,
         update: Value,
+        #[serde(default)]
+        expected_version: Option<u64>,
     },
     UpdateOne {
         collection: String,
         query: Value,
         update: Value,
+        #[serde(default)]
+        expected_version: Option<u64>,
+    },
+    /// `options` holds the request's `sort`, `new`, and `remove` fields.
+    FindAndModify {
+        collection: String,
+        query: Value,
+        update: Option<Value>,
+        options: Value,
     },
     Delete {
         collection: String,
@@ -46,6 +57,11 @@ pub enum OxiDbRequest {
         collection: String,
         query: Value,
     },
+    /// `ops` is the request's list of insert/update/delete ops.
+    BulkWrite {
+        collection: String,
+        ops: Value,
+    },
     CreateCollection {
         name: String,
     },
@@ -58,6 +74,10 @@ pub enum OxiDbRequest {
     CreateIndex {
         collection: String,
         field: String,
+        #[serde(default)]
+        descending: bool,
+        #[serde(default)]
+        sparse: bool,
     },
     CreateUniqueIndex {
         collection: String,
@@ -71,16 +91,50 @@ pub enum OxiDbRequest {
         collection: String,
         fields: Vec<String>,
     },
+    CreateGeoIndex {
+        collection: String,
+        field: String,
+    },
     DropIndex {
         collection: String,
         index: String,
     },
+    /// `schema` is null to remove the collection's schema.
+    SetSchema {
+        collection: String,
+        schema: Value,
+        level: String,
+        coerce: bool,
+    },
+    /// `defaults` is null or `{}` to remove the collection's defaults.
+    SetDefaults {
+        collection: String,
+        defaults: Value,
+    },
+    /// `owner` is the creating user, whose ACL a new bucket starts with.
     CreateBucket {
         bucket: String,
+        #[serde(default)]
+        owner: Option<String>,
     },
     DeleteBucket {
         bucket: String,
     },
+    /// Remove the collection's documents whose `_expireAt` is at or before
+    /// `now` (RFC 3339), the leader's clock when it swept.
+    PurgeExpired {
+        collection: String,
+        now: String,
+    },
+    /// `acl` is null to remove the bucket's ACL.
+    SetBucketAcl {
+        bucket: String,
+        acl: Value,
+    },
+    SetBucketTokenizer {
+        bucket: String,
+        tokenizer: String,
+    },
     PutObject {
         bucket: String,
         key: String,
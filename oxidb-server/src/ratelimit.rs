@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Buckets kept before idle (full) ones are dropped.
+const MAX_IDLE_BUCKETS: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Token-bucket rate limiter with one bucket per client key (a username, or
+/// a peer IP for anonymous clients). Each bucket holds up to `burst`
+/// tokens, refills at `rate` tokens per second, and each request takes one.
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64, burst: f64) -> Self {
+        Self {
+            rate,
+            burst: burst.max(1.0),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Read `OXIDB_RATE_LIMIT` (requests per second) and `OXIDB_RATE_BURST`
+    /// (default: the rate). Returns `None` when the limit is unset or 0.
+    pub fn from_env() -> Option<Self> {
+        let rate: f64 = std::env::var("OXIDB_RATE_LIMIT")
+            .ok()?
+            .parse()
+            .expect("OXIDB_RATE_LIMIT must be a number (requests per second)");
+        if rate <= 0.0 {
+            return None;
+        }
+        let burst = match std::env::var("OXIDB_RATE_BURST") {
+            Ok(v) => v.parse().expect("OXIDB_RATE_BURST must be a number"),
+            Err(_) => rate,
+        };
+        Some(Self::new(rate, burst))
+    }
+
+    /// Take a token from `key`'s bucket at `now`. Returns false if it is empty.
+    pub fn check(&self, key: &str, now: Instant) -> bool {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_IDLE_BUCKETS && !buckets.contains_key(key) {
+            let (rate, burst) = (self.rate, self.burst);
+            buckets.retain(|_, b| b.tokens + now.duration_since(b.refilled_at).as_secs_f64() * rate < burst);
+        }
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket { tokens: self.burst, refilled_at: now });
+        let elapsed = now.saturating_duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.refilled_at = bucket.refilled_at.max(now);
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+}
+
+/// The rate-limit key for a client: its username once authenticated,
+/// otherwise its peer's IP. The port is dropped so that reconnecting from the
+/// same host doesn't get a fresh bucket.
+pub fn client_key(username: Option<&str>, peer: &str) -> String {
+    match username {
+        Some(user) => format!("user:{user}"),
+        None => match peer.parse::<SocketAddr>() {
+            Ok(addr) => format!("peer:{}", addr.ip()),
+            Err(_) => format!("peer:{peer}"),
+        },
+    }
+}
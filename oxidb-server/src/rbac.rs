@@ -1,36 +1,70 @@
-use crate::auth::Role;
+use std::sync::Mutex;
+
+use oxidb::OxiDb;
+use serde_json::Value;
+
+use crate::auth::{Role, UserStore};
+use crate::session::Session;
 
 /// Check if a role is permitted to execute a given command.
 ///
 /// - **Admin**: all commands
-/// - **ReadWrite**: CRUD, indexes, transactions, blobs, search, compact, list_collections
-/// - **Read**: find, find_one, count, aggregate, list_*, get_object, head_object, search, ping
+/// - **ReadWrite**: CRUD, indexes, transactions, blobs, search, compact, list_collections, metrics
+/// - **Read**: find, find_one, explain, count, aggregate, list_*, get_object, head_object, search, ping, metrics
+///
+/// User-defined roles are checked by `session_permitted`.
 pub fn is_permitted(role: Role, cmd: &str) -> bool {
     match role {
         Role::Admin => true,
         Role::ReadWrite => matches!(
             cmd,
             "ping"
+                | "metrics"
+                | "change_password"
                 | "insert"
                 | "insert_many"
                 | "find"
                 | "find_one"
+                | "explain"
                 | "update"
+                | "find_and_modify"
+                | "bulk_write"
                 | "delete"
                 | "count"
+                | "distinct"
+                | "describe"
+                | "get_schema"
+                | "get_defaults"
                 | "create_index"
                 | "create_unique_index"
                 | "create_composite_index"
+                | "create_geo_index"
                 | "create_collection"
                 | "list_collections"
                 | "compact"
+                | "flush"
                 | "aggregate"
                 | "begin_tx"
                 | "commit_tx"
                 | "rollback_tx"
+                | "savepoint"
+                | "rollback_to"
                 | "create_bucket"
                 | "put_object"
+                | "copy_object"
+                | "create_multipart_upload"
+                | "upload_part"
+                | "complete_multipart_upload"
+                | "abort_multipart_upload"
                 | "get_object"
+                | "verify_object"
+                | "get_object_range"
+                | "list_object_versions"
+                | "enable_versioning"
+                | "set_bucket_tokenizer"
+                | "get_bucket_tokenizer"
+                | "set_bucket_acl"
+                | "get_bucket_acl"
                 | "head_object"
                 | "delete_object"
                 | "list_objects"
@@ -40,27 +74,214 @@ pub fn is_permitted(role: Role, cmd: &str) -> bool {
                 | "call_procedure"
                 | "enable_schedule"
                 | "disable_schedule"
+                | "run_schedule"
                 | "create_vector_index"
                 | "vector_search"
         ),
         Role::Read => matches!(
             cmd,
             "ping"
+                | "metrics"
+                | "change_password"
                 | "find"
                 | "find_one"
+                | "explain"
                 | "count"
+                | "distinct"
+                | "describe"
+                | "get_schema"
+                | "get_defaults"
                 | "aggregate"
                 | "list_collections"
                 | "list_buckets"
                 | "list_objects"
                 | "get_object"
+                | "verify_object"
+                | "get_object_range"
+                | "list_object_versions"
+                | "get_bucket_tokenizer"
+                | "get_bucket_acl"
                 | "head_object"
                 | "search"
                 | "list_procedures"
                 | "get_procedure"
                 | "list_schedules"
                 | "get_schedule"
+                | "schedule_history"
                 | "vector_search"
         ),
     }
 }
+
+/// User, role and API key management, cluster membership changes, audit
+/// and slow query log queries, and collection repair, which only Admin may
+/// run.
+pub fn is_admin_only(cmd: &str) -> bool {
+    matches!(
+        cmd,
+        "create_user"
+            | "drop_user"
+            | "update_user"
+            | "list_users"
+            | "create_role"
+            | "drop_role"
+            | "list_roles"
+            | "create_api_key"
+            | "revoke_api_key"
+            | "list_api_keys"
+            | "raft_init"
+            | "raft_add_learner"
+            | "raft_change_membership"
+            | "raft_remove_node"
+            | "query_audit"
+            | "query_slow_log"
+            | "repair"
+    )
+}
+
+/// Check if an authenticated session may execute a command. A session with
+/// a user-defined role may run `ping`, `change_password`, and exactly the
+/// commands the role lists, never user or role management; built-in roles
+/// follow `is_permitted`.
+pub fn session_permitted(session: &Session, user_store: Option<&Mutex<UserStore>>, cmd: &str) -> bool {
+    match (&session.custom_role, session.role()) {
+        (Some(name), _) => {
+            if is_admin_only(cmd) {
+                return false;
+            }
+            matches!(cmd, "ping" | "change_password")
+                || user_store.is_some_and(|store| {
+                    let store = store.lock().unwrap();
+                    store.get_role(name).is_some_and(|role| role.commands.contains(cmd))
+                })
+        }
+        (None, Some(role)) => is_permitted(role, cmd),
+        (None, None) => false,
+    }
+}
+
+/// Whether `cmd` is blocked because the session's user must change their
+/// password first. Only `change_password` and `ping` are allowed until then.
+pub fn password_change_required(session: &Session, cmd: &str) -> bool {
+    session.must_change_password && !matches!(cmd, "change_password" | "ping")
+}
+
+/// Access a blob command needs to a bucket with an ACL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BucketAccess {
+    Read,
+    Write,
+    Own,
+}
+
+impl BucketAccess {
+    fn as_str(self) -> &'static str {
+        match self {
+            BucketAccess::Read => "read",
+            BucketAccess::Write => "write",
+            BucketAccess::Own => "owner",
+        }
+    }
+}
+
+/// The buckets a blob command names, with the access it needs to each.
+/// Multipart part/complete/abort name their bucket through the upload.
+fn bucket_access(db: &OxiDb, cmd: &str, request: &Value) -> Vec<(String, BucketAccess)> {
+    let field = |name: &str| request.get(name).and_then(|v| v.as_str()).map(str::to_string);
+    let (bucket, access) = match cmd {
+        "get_object" | "verify_object" | "get_object_range" | "head_object" | "list_objects"
+        | "list_object_versions" | "get_bucket_tokenizer" | "get_bucket_acl" | "search" => (field("bucket"), BucketAccess::Read),
+        "create_bucket" | "put_object" | "delete_object" | "create_multipart_upload" | "set_bucket_tokenizer" => {
+            (field("bucket"), BucketAccess::Write)
+        }
+        "upload_part" | "complete_multipart_upload" | "abort_multipart_upload" => (
+            field("upload_id").and_then(|id| db.multipart_upload_bucket(&id)),
+            BucketAccess::Write,
+        ),
+        "delete_bucket" | "enable_versioning" | "set_bucket_acl" => (field("bucket"), BucketAccess::Own),
+        "copy_object" => {
+            let src = field("src_bucket");
+            let dst = field("dst_bucket").or_else(|| src.clone());
+            return [(src, BucketAccess::Read), (dst, BucketAccess::Write)]
+                .into_iter()
+                .filter_map(|(bucket, access)| bucket.map(|b| (b, access)))
+                .collect();
+        }
+        _ => return Vec::new(),
+    };
+    bucket.map(|b| vec![(b, access)]).unwrap_or_default()
+}
+
+/// Check a blob command against the ACLs of the buckets it names. Admins
+/// bypass ACLs, and a bucket without one is open to every session
+/// `session_permitted` lets through. Only admins may put an ACL on an open
+/// bucket; users get their own through `create_bucket` (see
+/// `with_bucket_owner`). Returns the denial message.
+pub fn bucket_permitted(db: &OxiDb, session: &Session, cmd: &str, request: &Value) -> Result<(), String> {
+    if session.role() == Some(Role::Admin) {
+        return Ok(());
+    }
+    let user = session.username_str();
+    for (bucket, access) in bucket_access(db, cmd, request) {
+        let allowed = match (db.bucket_acl(&bucket), access) {
+            (Some(acl), BucketAccess::Read) => acl.can_read(user),
+            (Some(acl), BucketAccess::Write) => acl.can_write(user),
+            (Some(acl), BucketAccess::Own) => acl.owner == user,
+            (None, _) if cmd == "set_bucket_acl" => false,
+            (None, _) => true,
+        };
+        if !allowed {
+            return Err(format!(
+                "permission denied: '{cmd}' needs {} access to bucket '{bucket}'",
+                access.as_str()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Stamp a `create_bucket` request with the session's user as the bucket's
+/// `owner`, replacing any owner the client sent, so a new bucket starts out
+/// private to whoever created it. Unauthenticated sessions (auth disabled)
+/// create open buckets. Other requests pass through unchanged.
+pub fn with_bucket_owner(session: &Session, cmd: &str, mut request: Value) -> Value {
+    if cmd != "create_bucket" {
+        return request;
+    }
+    if let Some(obj) = request.as_object_mut() {
+        obj.remove("owner");
+        if let Some(user) = session.username.as_ref().filter(|_| session.is_authenticated()) {
+            obj.insert("owner".to_string(), Value::String(user.clone()));
+        }
+    }
+    request
+}
+
+/// Drop the buckets the session can't read from a `list_buckets` or
+/// bucket-less `search` response; `bucket` is the request's. Other responses
+/// pass through unchanged.
+pub fn filter_readable_buckets(db: &OxiDb, session: &Session, cmd: &str, bucket: Option<&str>, resp: Vec<u8>) -> Vec<u8> {
+    let cross_bucket = match cmd {
+        "list_buckets" => true,
+        "search" => bucket.is_none(),
+        _ => false,
+    };
+    if !cross_bucket || session.role() == Some(Role::Admin) {
+        return resp;
+    }
+    let mut value: Value = match serde_json::from_slice(&resp) {
+        Ok(v) => v,
+        Err(_) => return resp,
+    };
+    let Some(items) = value.get_mut("data").and_then(|d| d.as_array_mut()) else {
+        return resp;
+    };
+    let user = session.username_str();
+    items.retain(|item| {
+        let bucket = item.as_str().or_else(|| item.get("bucket").and_then(|b| b.as_str()));
+        bucket
+            .and_then(|b| db.bucket_acl(b))
+            .is_none_or(|acl| acl.can_read(user))
+    });
+    serde_json::to_vec(&value).unwrap_or(resp)
+}
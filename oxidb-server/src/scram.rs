@@ -7,6 +7,10 @@ type HmacSha256 = Hmac<Sha256>;
 
 const ITERATION_COUNT: u32 = 4096;
 
+/// The channel binding type offered over TLS (RFC 5929): the hash of the
+/// server certificate, see [`crate::tls::server_end_point`].
+pub const CHANNEL_BINDING_TYPE: &str = "tls-server-end-point";
+
 /// Server-side SCRAM-SHA-256 state machine (simplified RFC 7677).
 ///
 /// Flow:
@@ -14,6 +18,12 @@ const ITERATION_COUNT: u32 = 4096;
 /// 2. Server responds: `r=<client_nonce><server_nonce>,s=<salt>,i=<iterations>`
 /// 3. Client sends: `c=biws,r=<combined_nonce>,p=<client_proof>`
 /// 4. Server verifies and responds: `v=<server_signature>` (success) or error
+///
+/// Over TLS the client may bind the exchange to the connection by starting
+/// with `p=tls-server-end-point,,` instead of `n,,`; `c=` is then the
+/// base64 of that header followed by the server certificate hash, so a
+/// relayed exchange fails. `y,,` (client supports binding but thinks the
+/// server doesn't) is rejected over TLS as a downgrade.
 pub struct ScramState {
     username: String,
     #[allow(dead_code)]
@@ -23,6 +33,8 @@ pub struct ScramState {
     combined_nonce: String,
     #[allow(dead_code)]
     salt: Vec<u8>,
+    /// Expected `c=` attribute of the client-final message.
+    channel_binding: String,
     auth_message: String,
     stored_key: Vec<u8>,
     server_key: Vec<u8>,
@@ -30,16 +42,40 @@ pub struct ScramState {
 
 impl ScramState {
     /// Process client-first message. Returns (server_first_message, state).
+    ///
+    /// `channel_binding` is the connection's `tls-server-end-point` data,
+    /// `None` on plain connections.
     pub fn process_client_first(
         client_msg: &str,
         user_store: &UserStore,
+        channel_binding: Option<&[u8]>,
     ) -> Result<(String, Self), String> {
-        // Parse: n,,n=<user>,r=<nonce>
-        let msg = if let Some(stripped) = client_msg.strip_prefix("n,,") {
-            stripped
-        } else {
-            return Err("invalid client-first: must start with 'n,,'".into());
+        // Parse: <gs2-header>n=<user>,r=<nonce>
+        let mut header = client_msg.splitn(3, ',');
+        let (Some(flag), Some(authzid), Some(msg)) = (header.next(), header.next(), header.next()) else {
+            return Err("invalid client-first: missing GS2 header".into());
         };
+        if !authzid.is_empty() && !authzid.starts_with("a=") {
+            return Err("invalid client-first: bad authzid".into());
+        }
+        let gs2_header = &client_msg[..flag.len() + authzid.len() + 2];
+        let mut cbind_input = gs2_header.as_bytes().to_vec();
+        match flag {
+            "n" => {}
+            "y" if channel_binding.is_some() => {
+                return Err("channel binding is available on this connection; use p=tls-server-end-point".into());
+            }
+            "y" => {}
+            _ => match flag.strip_prefix("p=") {
+                Some(CHANNEL_BINDING_TYPE) => {
+                    let data = channel_binding.ok_or("channel binding requires a TLS connection")?;
+                    cbind_input.extend_from_slice(data);
+                }
+                Some(other) => return Err(format!("unsupported channel binding type '{other}'")),
+                None => return Err("invalid client-first: bad GS2 header".into()),
+            },
+        }
+        let channel_binding = base64_encode_simple(&cbind_input);
 
         let mut username = None;
         let mut client_nonce = None;
@@ -75,8 +111,8 @@ impl ScramState {
         );
 
         let auth_message = format!(
-            "{},{},c=biws,r={}",
-            client_first_bare, server_first, combined_nonce
+            "{},{},c={},r={}",
+            client_first_bare, server_first, channel_binding, combined_nonce
         );
 
         // Derive keys from the user's password hash
@@ -97,6 +133,7 @@ impl ScramState {
                 server_nonce,
                 combined_nonce,
                 salt,
+                channel_binding,
                 auth_message,
                 stored_key,
                 server_key,
@@ -110,21 +147,29 @@ impl ScramState {
         client_msg: &str,
         user_store: &UserStore,
     ) -> Result<(String, Role), String> {
-        // Parse: c=biws,r=<combined_nonce>,p=<proof>
+        // Parse: c=<channel_binding>,r=<combined_nonce>,p=<proof>
+        let mut received_binding = None;
         let mut received_nonce = None;
         let mut proof_b64 = None;
 
         for part in client_msg.split(',') {
-            if let Some(r) = part.strip_prefix("r=") {
+            if let Some(c) = part.strip_prefix("c=") {
+                received_binding = Some(c);
+            } else if let Some(r) = part.strip_prefix("r=") {
                 received_nonce = Some(r.to_string());
             } else if let Some(p) = part.strip_prefix("p=") {
                 proof_b64 = Some(p.to_string());
             }
         }
 
+        let received_binding = received_binding.ok_or("missing channel binding in client-final")?;
         let received_nonce = received_nonce.ok_or("missing nonce in client-final")?;
         let proof_b64 = proof_b64.ok_or("missing proof in client-final")?;
 
+        if received_binding != self.channel_binding {
+            return Err("channel binding mismatch".into());
+        }
+
         // Verify nonce
         if received_nonce != self.combined_nonce {
             return Err("nonce mismatch".into());
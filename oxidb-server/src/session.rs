@@ -1,12 +1,47 @@
-use crate::auth::Role;
+use std::time::{Duration, Instant};
+
+use crate::auth::{Role, UserRecord};
 use crate::scram::ScramState;
 
+/// How long an authenticated session stays valid. `None` means no limit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SessionLimits {
+    /// Longest time a session stays authenticated after logging in.
+    pub max_age: Option<Duration>,
+    /// Longest time a session stays authenticated without a request.
+    pub idle_timeout: Option<Duration>,
+}
+
+impl SessionLimits {
+    /// Read `OXIDB_SESSION_MAX_AGE` and `OXIDB_SESSION_IDLE_TIMEOUT` (seconds;
+    /// unset or 0 disables the limit).
+    pub fn from_env() -> Self {
+        let secs = |var: &str| {
+            std::env::var(var)
+                .ok()
+                .map(|v| v.parse::<u64>().unwrap_or_else(|_| panic!("{var} must be a valid u64 (seconds)")))
+                .filter(|&s| s > 0)
+                .map(Duration::from_secs)
+        };
+        Self {
+            max_age: secs("OXIDB_SESSION_MAX_AGE"),
+            idle_timeout: secs("OXIDB_SESSION_IDLE_TIMEOUT"),
+        }
+    }
+}
+
 /// Per-connection session tracking authentication state.
 pub struct Session {
     pub authenticated: bool,
     pub username: Option<String>,
     pub role: Option<Role>,
+    /// User-defined role that decides what the session may run, if any.
+    pub custom_role: Option<String>,
+    /// Only `change_password` may run until the user changes their password.
+    pub must_change_password: bool,
     pub scram_state: Option<ScramState>,
+    authenticated_at: Option<Instant>,
+    last_active: Option<Instant>,
 }
 
 impl Session {
@@ -15,7 +50,11 @@ impl Session {
             authenticated: false,
             username: None,
             role: None,
+            custom_role: None,
+            must_change_password: false,
             scram_state: None,
+            authenticated_at: None,
+            last_active: None,
         }
     }
 
@@ -24,7 +63,52 @@ impl Session {
         self.authenticated = true;
         self.username = Some(username);
         self.role = Some(role);
+        self.custom_role = None;
+        self.must_change_password = false;
         self.scram_state = None;
+        let now = Instant::now();
+        self.authenticated_at = Some(now);
+        self.last_active = Some(now);
+    }
+
+    /// Clear the session's authentication, as if the client had just connected.
+    pub fn logout(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Log the session out if it has outlived `limits` at `now`, returning
+    /// true if so. Otherwise record `now` as its latest activity.
+    pub fn check_expiry(&mut self, limits: &SessionLimits, now: Instant) -> bool {
+        let (Some(authenticated_at), Some(last_active)) = (self.authenticated_at, self.last_active) else {
+            return false;
+        };
+        let too_old = limits.max_age.is_some_and(|max| now.duration_since(authenticated_at) >= max);
+        let idle = limits.idle_timeout.is_some_and(|max| now.duration_since(last_active) >= max);
+        if too_old || idle {
+            self.logout();
+            return true;
+        }
+        self.last_active = Some(now);
+        false
+    }
+
+    /// Mark session as authenticated as `user`, with their built-in or custom role.
+    pub fn set_authenticated_user(&mut self, user: &UserRecord) {
+        self.set_authenticated(user.username.clone(), user.role);
+        self.custom_role = user.custom_role.clone();
+        self.must_change_password = user.must_change;
+    }
+
+    /// Mark session as authenticated as `username`, who isn't a stored user
+    /// (a client certificate identity), with the built-in or custom `role`.
+    pub fn set_authenticated_as(&mut self, username: String, role: &str) {
+        match Role::from_str(role) {
+            Some(role) => self.set_authenticated(username, role),
+            None => {
+                self.set_authenticated(username, Role::Read);
+                self.custom_role = Some(role.to_string());
+            }
+        }
     }
 
     /// Return true if the session is authenticated (or auth is not required).
@@ -36,6 +120,15 @@ impl Session {
         self.role
     }
 
+    /// The custom role name, or the built-in role's.
+    pub fn role_name(&self) -> &str {
+        match (&self.custom_role, self.role) {
+            (Some(name), _) => name,
+            (None, Some(role)) => role.as_str(),
+            (None, None) => "none",
+        }
+    }
+
     pub fn username_str(&self) -> &str {
         self.username.as_deref().unwrap_or("anonymous")
     }
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::net::{Shutdown as SocketShutdown, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Graceful shutdown state shared by the accept loop and the connection
+/// workers. Once shutdown is requested, new connections are turned away and
+/// the read side of every open one is closed: a request already being
+/// handled still gets its response, then the connection sees end-of-stream,
+/// rolls back its transaction, and exits.
+#[derive(Default)]
+pub struct Shutdown {
+    requested: AtomicBool,
+    connections: Mutex<Connections>,
+    closed: Condvar,
+}
+
+#[derive(Default)]
+struct Connections {
+    next_id: u64,
+    open: HashMap<u64, TcpStream>,
+}
+
+/// Keeps a connection registered until it's dropped.
+pub struct ConnectionGuard<'a> {
+    shutdown: &'a Shutdown,
+    id: u64,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Register an accepted connection for the time it's served. `None` once
+    /// shutdown has been requested: the caller should close it unserved.
+    pub fn track(&self, stream: &TcpStream) -> Option<ConnectionGuard<'_>> {
+        let mut conns = self.connections.lock().unwrap();
+        if self.is_requested() {
+            return None;
+        }
+        let handle = stream.try_clone().ok()?;
+        let id = conns.next_id;
+        conns.next_id += 1;
+        conns.open.insert(id, handle);
+        Some(ConnectionGuard { shutdown: self, id })
+    }
+
+    /// Start shutting down: refuse new connections and close the read side
+    /// of the open ones.
+    pub fn request(&self) {
+        let conns = self.connections.lock().unwrap();
+        self.requested.store(true, Ordering::SeqCst);
+        for stream in conns.open.values() {
+            let _ = stream.shutdown(SocketShutdown::Read);
+        }
+    }
+
+    /// Number of connections still being served.
+    pub fn open_connections(&self) -> usize {
+        self.connections.lock().unwrap().open.len()
+    }
+
+    /// Wait for every open connection to finish, up to `timeout`. Returns
+    /// whether they all did.
+    pub fn wait_for_connections(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut conns = self.connections.lock().unwrap();
+        while !conns.open.is_empty() {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            conns = self.closed.wait_timeout(conns, deadline - now).unwrap().0;
+        }
+        true
+    }
+}
+
+impl Drop for ConnectionGuard<'_> {
+    fn drop(&mut self) {
+        let mut conns = self.shutdown.connections.lock().unwrap();
+        conns.open.remove(&self.id);
+        self.shutdown.closed.notify_all();
+    }
+}
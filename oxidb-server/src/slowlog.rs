@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use oxidb::OxiDb;
+use serde_json::{Value, json};
+
+use crate::audit;
+use crate::gelf::{GelfLevel, GelfLogger};
+
+/// Entries kept by default; older ones are dropped as new ones arrive.
+pub const DEFAULT_MAX_ENTRIES: usize = 1000;
+
+/// Commands the slow query log times.
+const TIMED_COMMANDS: &[&str] = &[
+    "find", "find_one", "count", "distinct", "aggregate", "update", "update_one", "delete", "delete_one",
+];
+
+/// Records queries that take longer than a threshold: each one is kept in
+/// memory, for admins to read with `query_slow_log`, and, when GELF is
+/// configured, sent as a warning. Entries carry the command, collection,
+/// duration, query and the indexes the planner would use for it, so a
+/// missing index shows up as `"indexed": false`.
+///
+/// Like the audit log, the slow query log belongs to the node that ran the
+/// queries: it isn't stored in a collection, so it is never replicated.
+pub struct SlowQueryLog {
+    threshold: Duration,
+    gelf: Option<Arc<GelfLogger>>,
+    max_entries: usize,
+    entries: Mutex<VecDeque<Value>>,
+}
+
+impl SlowQueryLog {
+    pub fn new(threshold: Duration, gelf: Option<Arc<GelfLogger>>) -> Self {
+        Self::with_max_entries(threshold, gelf, DEFAULT_MAX_ENTRIES)
+    }
+
+    /// Like [`SlowQueryLog::new`], keeping at most `max_entries` entries.
+    pub fn with_max_entries(threshold: Duration, gelf: Option<Arc<GelfLogger>>, max_entries: usize) -> Self {
+        Self { threshold, gelf, max_entries, entries: Mutex::new(VecDeque::new()) }
+    }
+
+    /// Read `OXIDB_SLOW_QUERY_MS` (milliseconds; unset or 0 disables the
+    /// log) and `OXIDB_SLOW_QUERY_MAX_ENTRIES`.
+    pub fn from_env(gelf: Option<Arc<GelfLogger>>) -> Option<Self> {
+        let max_entries = std::env::var("OXIDB_SLOW_QUERY_MAX_ENTRIES")
+            .ok()
+            .map(|v| {
+                v.parse::<usize>()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .expect("OXIDB_SLOW_QUERY_MAX_ENTRIES must be a positive integer")
+            })
+            .unwrap_or(DEFAULT_MAX_ENTRIES);
+        std::env::var("OXIDB_SLOW_QUERY_MS")
+            .ok()
+            .map(|v| v.parse::<u64>().expect("OXIDB_SLOW_QUERY_MS must be a valid u64 (milliseconds)"))
+            .filter(|&ms| ms > 0)
+            .map(|ms| Self::with_max_entries(Duration::from_millis(ms), gelf, max_entries))
+    }
+
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// The most recent `limit` entries, oldest first, optionally only those
+    /// for `collection`.
+    pub fn entries(&self, collection: Option<&str>, limit: usize) -> Vec<Value> {
+        let entries = self.entries.lock().unwrap();
+        let mut recent: Vec<Value> = entries
+            .iter()
+            .rev()
+            .filter(|e| collection.is_none_or(|c| e["collection"] == c))
+            .take(limit)
+            .cloned()
+            .collect();
+        recent.reverse();
+        recent
+    }
+
+    /// Whether `request` is a query the log times.
+    pub fn is_timed(request: &Value) -> bool {
+        let cmd = request.get("cmd").and_then(|v| v.as_str()).unwrap_or("");
+        let collection = request.get("collection").and_then(|v| v.as_str());
+        TIMED_COMMANDS.contains(&cmd) && collection.is_some()
+    }
+
+    /// Record `request` if it's a timed query that took `elapsed` or longer.
+    /// Returns whether it was recorded.
+    pub fn observe(&self, db: &OxiDb, request: &Value, elapsed: Duration) -> bool {
+        if elapsed < self.threshold || !Self::is_timed(request) {
+            return false;
+        }
+        let cmd = request["cmd"].as_str().unwrap_or("");
+        let collection = request["collection"].as_str().unwrap_or("");
+        let query = match cmd {
+            // A leading $match is the part of a pipeline that can use an index
+            "aggregate" => request["pipeline"].get(0).and_then(|stage| stage.get("$match")),
+            _ => request.get("query"),
+        };
+        let indexes = query.map_or_else(Vec::new, |q| db.query_indexes(collection, q).unwrap_or_default());
+        let indexed = !indexes.is_empty();
+        let duration_ms = elapsed.as_secs_f64() * 1000.0;
+
+        let mut entry = json!({
+            "ts": audit::now_rfc3339(),
+            "cmd": cmd,
+            "collection": collection,
+            "duration_ms": duration_ms,
+            "indexed": indexed,
+            "indexes": indexes,
+        });
+        if let Some(query) = query {
+            entry["query"] = query.clone();
+        }
+        if cmd == "aggregate" {
+            entry["pipeline"] = request["pipeline"].clone();
+        }
+        {
+            let mut entries = self.entries.lock().unwrap();
+            if entries.len() >= self.max_entries {
+                entries.pop_front();
+            }
+            entries.push_back(entry);
+        }
+
+        if let Some(gelf) = &self.gelf {
+            gelf.send(
+                GelfLevel::Warning,
+                &format!("slow query: {cmd} on {collection} took {duration_ms:.1}ms"),
+                &[
+                    ("cmd", cmd),
+                    ("collection", collection),
+                    ("duration_ms", &format!("{duration_ms:.1}")),
+                    ("indexed", &indexed.to_string()),
+                ],
+            );
+        }
+        true
+    }
+}
@@ -1,23 +1,28 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
 use std::sync::Arc;
 
-use rustls::ServerConfig;
-use rustls::pki_types::PrivateKeyDer;
+use rustls::server::WebPkiClientVerifier;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{RootCertStore, ServerConfig};
+use x509_parser::extensions::GeneralName;
 
 /// Load TLS configuration from PEM certificate chain and private key files.
 pub fn load_tls_config(cert_path: &Path, key_path: &Path) -> Result<Arc<ServerConfig>, String> {
-    let cert_file = File::open(cert_path)
-        .map_err(|e| format!("failed to open cert file {}: {}", cert_path.display(), e))?;
-    let mut cert_reader = BufReader::new(cert_file);
-    let certs: Vec<_> = rustls_pemfile::certs(&mut cert_reader)
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("failed to parse certificates: {e}"))?;
+    load_tls_config_with_client_ca(cert_path, key_path, None)
+}
 
-    if certs.is_empty() {
-        return Err("no certificates found in cert file".into());
-    }
+/// Like [`load_tls_config`]. With `client_ca`, every client must present a
+/// certificate signed by one of the CA certificates in that PEM file; the
+/// handshake fails otherwise.
+pub fn load_tls_config_with_client_ca(
+    cert_path: &Path,
+    key_path: &Path,
+    client_ca: Option<&Path>,
+) -> Result<Arc<ServerConfig>, String> {
+    let certs = load_certs(cert_path)?;
 
     let key_file = File::open(key_path)
         .map_err(|e| format!("failed to open key file {}: {}", key_path.display(), e))?;
@@ -27,10 +32,116 @@ pub fn load_tls_config(cert_path: &Path, key_path: &Path) -> Result<Arc<ServerCo
         .map_err(|e| format!("failed to parse private key: {e}"))?
         .ok_or_else(|| "no private key found in key file".to_string())?;
 
-    let config = ServerConfig::builder()
-        .with_no_client_auth()
+    let builder = ServerConfig::builder();
+    let builder = match client_ca {
+        Some(ca_path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(ca_path)? {
+                roots.add(cert).map_err(|e| format!("invalid client CA certificate: {e}"))?;
+            }
+            let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+                .build()
+                .map_err(|e| format!("client certificate verifier: {e}"))?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let config = builder
         .with_single_cert(certs, key)
         .map_err(|e| format!("TLS config error: {e}"))?;
 
     Ok(Arc::new(config))
 }
+
+/// `tls-server-end-point` channel binding data for the certificate chain in
+/// `cert_path` (RFC 5929): the hash of the leaf certificate, using its
+/// signature's hash function, or SHA-256 when that is weaker or unknown.
+pub fn server_end_point(cert_path: &Path) -> Result<Vec<u8>, String> {
+    use sha2::{Digest, Sha256, Sha384, Sha512};
+    use x509_parser::oid_registry::{
+        OID_PKCS1_SHA384WITHRSA, OID_PKCS1_SHA512WITHRSA, OID_SIG_ECDSA_WITH_SHA384, OID_SIG_ECDSA_WITH_SHA512,
+    };
+
+    let certs = load_certs(cert_path)?;
+    let der = certs[0].as_ref();
+    let (_, cert) = x509_parser::parse_x509_certificate(der).map_err(|e| format!("invalid certificate: {e}"))?;
+    let algorithm = &cert.signature_algorithm.algorithm;
+    Ok(if *algorithm == OID_SIG_ECDSA_WITH_SHA384 || *algorithm == OID_PKCS1_SHA384WITHRSA {
+        Sha384::digest(der).to_vec()
+    } else if *algorithm == OID_SIG_ECDSA_WITH_SHA512 || *algorithm == OID_PKCS1_SHA512WITHRSA {
+        Sha512::digest(der).to_vec()
+    } else {
+        Sha256::digest(der).to_vec()
+    })
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    let file = File::open(path)
+        .map_err(|e| format!("failed to open cert file {}: {}", path.display(), e))?;
+    let certs: Vec<_> = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("failed to parse certificates: {e}"))?;
+
+    if certs.is_empty() {
+        return Err(format!("no certificates found in {}", path.display()));
+    }
+    Ok(certs)
+}
+
+/// Who a verified client certificate authenticates as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertIdentity {
+    /// The certificate name that matched: its subject CN or a DNS SAN.
+    pub name: String,
+    /// Built-in or custom role granted to the connection.
+    pub role: String,
+}
+
+/// Maps client certificate names to roles, from `OXIDB_TLS_CLIENT_ROLES`
+/// (e.g. `ingest-svc=readWrite,reports.internal=read`).
+#[derive(Debug, Clone, Default)]
+pub struct ClientCertRoles {
+    roles: HashMap<String, String>,
+}
+
+impl ClientCertRoles {
+    /// Parse comma-separated `name=role` pairs.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut roles = HashMap::new();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry.split_once('=') {
+                Some((name, role)) if !name.trim().is_empty() && !role.trim().is_empty() => {
+                    roles.insert(name.trim().to_string(), role.trim().to_string());
+                }
+                _ => return Err(format!("invalid client certificate mapping '{entry}': expected name=role")),
+            }
+        }
+        Ok(Self { roles })
+    }
+
+    /// Read the mapping from `OXIDB_TLS_CLIENT_ROLES`; empty when unset.
+    pub fn from_env() -> Self {
+        match std::env::var("OXIDB_TLS_CLIENT_ROLES") {
+            Ok(spec) => Self::parse(&spec).unwrap_or_else(|e| panic!("OXIDB_TLS_CLIENT_ROLES: {e}")),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// The identity for a client's certificate chain: the subject CN if it
+    /// has a mapping, otherwise the first mapped DNS subject alternative
+    /// name. `None` without a certificate or a matching name, in which case
+    /// the client authenticates as usual.
+    pub fn identify(&self, certs: Option<&[CertificateDer<'_>]>) -> Option<CertIdentity> {
+        let cert = certs?.first()?;
+        let (_, cert) = x509_parser::parse_x509_certificate(cert.as_ref()).ok()?;
+        let common_names = cert.subject().iter_common_name().filter_map(|cn| cn.as_str().ok());
+        let san = cert.subject_alternative_name().ok().flatten();
+        let dns_names = san.iter().flat_map(|ext| &ext.value.general_names).filter_map(|name| match name {
+            GeneralName::DNSName(dns) => Some(*dns),
+            _ => None,
+        });
+        common_names.chain(dns_names).find_map(|name| {
+            self.roles.get(name).map(|role| CertIdentity { name: name.to_string(), role: role.clone() })
+        })
+    }
+}
@@ -0,0 +1,136 @@
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+use oxidb::{ChangeEvent, OxiDb, WatchHandle};
+
+use crate::handler;
+use crate::protocol;
+
+/// How a watch session ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchExit {
+    /// The client sent `unwatch`; the connection can return to request mode.
+    Unwatched,
+    /// The client closed the connection.
+    Disconnected,
+    /// The client missed its keepalive; the connection should be closed.
+    KeepaliveExpired,
+}
+
+/// Encode a change event as a `{"event": "change", ...}` push message.
+pub fn change_message(event: &ChangeEvent) -> Vec<u8> {
+    serde_json::json!({
+        "event": "change",
+        "data": event,
+    })
+    .to_string()
+    .into_bytes()
+}
+
+/// Encode a backpressure notification for `dropped` lost events.
+pub fn overflow_message(dropped: u64) -> Vec<u8> {
+    serde_json::json!({
+        "event": "overflow",
+        "data": { "dropped": dropped },
+    })
+    .to_string()
+    .into_bytes()
+}
+
+/// Returns `true` if a raw client message is an `unwatch` command.
+pub fn is_unwatch(data: &[u8]) -> bool {
+    is_command(data, "unwatch")
+}
+
+/// Returns `true` if a raw client message is a `ping`, which watch mode
+/// answers with `pong` so clients can heartbeat without leaving it.
+pub fn is_ping(data: &[u8]) -> bool {
+    is_command(data, "ping")
+}
+
+fn is_command(data: &[u8], cmd: &str) -> bool {
+    serde_json::from_slice::<serde_json::Value>(data)
+        .ok()
+        .and_then(|v| v.get("cmd").and_then(|c| c.as_str()).map(|c| c == cmd))
+        .unwrap_or(false)
+}
+
+/// The reply to a `ping` in watch mode.
+pub fn pong_message() -> Vec<u8> {
+    handler::ok_bytes(serde_json::json!("pong"))
+}
+
+/// Write one event, preceded by an overflow message if events were dropped.
+pub fn send_event<W: Write>(writer: &mut W, handle: &WatchHandle, event: &ChangeEvent) -> io::Result<()> {
+    let dropped = handle.take_dropped();
+    if dropped > 0 {
+        protocol::write_message(writer, &overflow_message(dropped))?;
+    }
+    protocol::write_message(writer, &change_message(event))
+}
+
+/// Watch mode over a single Read+Write stream that can't be split (e.g. TLS).
+///
+/// Reads are interleaved with event pushes instead of running on a separate
+/// reader thread, so the underlying socket must have a short read timeout:
+/// each iteration drains pending events, then polls the client for at most
+/// one timeout period. The subscription is always removed before returning.
+pub fn watch_single<S: Read + Write>(
+    stream: &mut S,
+    db: &OxiDb,
+    handle: WatchHandle,
+) -> io::Result<WatchExit> {
+    watch_single_with_keepalive(stream, db, handle, None)
+}
+
+/// [`watch_single`], ending with [`WatchExit::KeepaliveExpired`] once the
+/// client has sent nothing for `keepalive_limit`.
+pub fn watch_single_with_keepalive<S: Read + Write>(
+    stream: &mut S,
+    db: &OxiDb,
+    handle: WatchHandle,
+    keepalive_limit: Option<Duration>,
+) -> io::Result<WatchExit> {
+    let result = watch_single_loop(stream, &handle, keepalive_limit);
+    db.unwatch(handle.id);
+    if let Ok(WatchExit::Unwatched) = result {
+        protocol::write_message(stream, &handler::ok_bytes(serde_json::json!("unwatched")))?;
+    }
+    result
+}
+
+fn watch_single_loop<S: Read + Write>(
+    stream: &mut S,
+    handle: &WatchHandle,
+    keepalive_limit: Option<Duration>,
+) -> io::Result<WatchExit> {
+    let mut inbox = Vec::new();
+    let mut last_heard = Instant::now();
+    loop {
+        loop {
+            match handle.rx.try_recv() {
+                Ok(event) => send_event(stream, handle, &event)?,
+                Err(std::sync::mpsc::TryRecvError::Empty) => break,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => return Ok(WatchExit::Disconnected),
+            }
+        }
+
+        match protocol::poll_message(stream, &mut inbox) {
+            Ok(Some(data)) => {
+                last_heard = Instant::now();
+                if is_unwatch(&data) {
+                    return Ok(WatchExit::Unwatched);
+                }
+                if is_ping(&data) {
+                    protocol::write_message(stream, &pong_message())?;
+                }
+            }
+            Ok(None) if keepalive_limit.is_some_and(|limit| last_heard.elapsed() >= limit) => {
+                return Ok(WatchExit::KeepaliveExpired);
+            }
+            Ok(None) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(WatchExit::Disconnected),
+            Err(e) => return Err(e),
+        }
+    }
+}
@@ -183,6 +183,14 @@ impl Client {
     fn rollback_tx(&mut self) -> Value {
         self.send(&json!({"cmd": "rollback_tx"}))
     }
+
+    fn savepoint(&mut self, name: &str) -> Value {
+        self.send(&json!({"cmd": "savepoint", "name": name}))
+    }
+
+    fn rollback_to(&mut self, name: &str) -> Value {
+        self.send(&json!({"cmd": "rollback_to", "name": name}))
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -322,6 +330,7 @@ fn test_consistency_version_conflict() {
         "expected TransactionConflict error, got: {}",
         commit_resp["error"]
     );
+    assert_eq!(commit_resp["code"], "tx_conflict");
 }
 
 // 5. Isolation — uncommitted writes not visible
@@ -495,3 +504,53 @@ fn test_commit_without_begin_rejected() {
         resp["error"]
     );
 }
+
+// 12. Savepoints — rollback_to discards only later writes
+#[test]
+fn test_savepoint_rollback_to() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    assert_ok(&c.begin_tx());
+    assert_ok(&c.insert("users", json!({"name": "Alice"})));
+    assert_ok(&c.savepoint("sp1"));
+    assert_ok(&c.insert("users", json!({"name": "Bob"})));
+    assert_ok(&c.insert("orders", json!({"item": "Widget"})));
+    assert_ok(&c.rollback_to("sp1"));
+    assert_err(&c.rollback_to("missing"));
+    assert_ok(&c.commit_tx());
+
+    let users = find_docs(&mut c, "users", json!({}));
+    let orders = find_docs(&mut c, "orders", json!({}));
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0]["name"], "Alice");
+    assert_eq!(orders.len(), 0);
+
+    // Outside a transaction savepoints are rejected
+    assert_err(&c.savepoint("sp2"));
+}
+
+// 13. Read-only transactions — writes rejected, commit leaves data unchanged
+#[test]
+fn test_read_only_tx() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+    assert_ok(&c.insert("users", json!({"name": "Alice"})));
+
+    assert_ok(&c.send(&json!({"cmd": "begin_tx", "read_only": true})));
+    let resp = c.send(&json!({"cmd": "find", "collection": "users", "query": {}}));
+    assert_ok(&resp);
+    assert_eq!(resp["data"].as_array().unwrap().len(), 1);
+
+    let resp = c.insert("users", json!({"name": "Bob"}));
+    assert_err(&resp);
+    assert!(
+        resp["error"].as_str().unwrap().contains("read-only"),
+        "expected read-only error, got: {}",
+        resp["error"]
+    );
+    assert_ok(&c.commit_tx());
+
+    let users = find_docs(&mut c, "users", json!({}));
+    assert_eq!(users.len(), 1);
+}
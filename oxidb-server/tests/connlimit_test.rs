@@ -0,0 +1,130 @@
+//! Connection limit: an accept loop built like main.rs's, with one worker
+//! and room for two connections, one served and one queued.
+
+use std::io::Read;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+use tempfile::TempDir;
+
+use oxidb::OxiDb;
+use oxidb_server::connlimit::{self, ConnectionLimiter, ConnectionPermit};
+use oxidb_server::protocol::{read_message, write_message};
+
+fn start_server(db: Arc<OxiDb>, limiter: Arc<ConnectionLimiter>, pool_size: usize) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::sync_channel::<(TcpStream, ConnectionPermit)>(limiter.max());
+    let rx = Arc::new(Mutex::new(rx));
+
+    for _ in 0..pool_size {
+        let (rx, db) = (Arc::clone(&rx), Arc::clone(&db));
+        std::thread::spawn(move || loop {
+            let Ok((stream, _permit)) = rx.lock().unwrap().recv() else { break };
+            handle_client(stream, &db);
+        });
+    }
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = stream.unwrap();
+            match limiter.try_acquire() {
+                Some(permit) => tx.send((stream, permit)).unwrap(),
+                None => connlimit::reject(stream),
+            }
+        }
+    });
+    addr
+}
+
+fn handle_client(mut stream: TcpStream, db: &Arc<OxiDb>) {
+    let mut active_tx: Option<u64> = None;
+    while let Ok(msg) = read_message(&mut stream) {
+        let request: Value = serde_json::from_slice(&msg).unwrap();
+        let resp = oxidb_server::handler::handle_request(db, request, &mut active_tx);
+        if write_message(&mut stream, &resp).is_err() {
+            break;
+        }
+    }
+}
+
+fn connect(addr: SocketAddr) -> TcpStream {
+    let stream = TcpStream::connect(addr).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream
+}
+
+fn ping(stream: &mut TcpStream) -> Value {
+    write_message(stream, json!({"cmd": "ping"}).to_string().as_bytes()).unwrap();
+    serde_json::from_slice(&read_message(stream).unwrap()).unwrap()
+}
+
+fn wait_for_active(limiter: &ConnectionLimiter, n: usize) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while limiter.active() != n {
+        assert!(Instant::now() < deadline, "expected {n} active connections, have {}", limiter.active());
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn excess_connections_are_rejected_while_admitted_ones_work() {
+    let dir = TempDir::new().unwrap();
+    let db = Arc::new(OxiDb::open(dir.path()).unwrap());
+    let limiter = ConnectionLimiter::new(2);
+    let addr = start_server(db, Arc::clone(&limiter), 1);
+
+    // One connection served, one waiting for the only worker
+    let mut served = connect(addr);
+    assert_eq!(ping(&mut served)["data"], "pong");
+    let mut queued = connect(addr);
+    write_message(&mut queued, json!({"cmd": "ping"}).to_string().as_bytes()).unwrap();
+    wait_for_active(&limiter, 2);
+
+    // Over the limit: an error, then the close
+    for _ in 0..3 {
+        let mut rejected = connect(addr);
+        let resp: Value = serde_json::from_slice(&read_message(&mut rejected).unwrap()).unwrap();
+        assert_eq!(resp["ok"], false);
+        assert_eq!(resp["error"], "too many connections");
+        assert_eq!(rejected.read(&mut [0u8; 1]).unwrap(), 0, "rejected connection should be closed");
+    }
+    assert_eq!(limiter.active(), 2);
+    assert_eq!(ping(&mut served)["data"], "pong");
+
+    // Closing the served connection lets the queued one in and frees a slot
+    drop(served);
+    let resp: Value = serde_json::from_slice(&read_message(&mut queued).unwrap()).unwrap();
+    assert_eq!(resp["data"], "pong");
+    wait_for_active(&limiter, 1);
+    let mut next = connect(addr);
+    write_message(&mut next, json!({"cmd": "ping"}).to_string().as_bytes()).unwrap();
+    wait_for_active(&limiter, 2);
+    drop(queued);
+    let resp: Value = serde_json::from_slice(&read_message(&mut next).unwrap()).unwrap();
+    assert_eq!(resp["data"], "pong");
+}
+
+#[test]
+fn permits_are_counted_until_dropped() {
+    let limiter = ConnectionLimiter::new(2);
+    let first = limiter.try_acquire().unwrap();
+    let second = limiter.try_acquire().unwrap();
+    assert!(limiter.try_acquire().is_none());
+    assert_eq!(limiter.active(), 2);
+    drop(first);
+    assert_eq!(limiter.active(), 1);
+    let third = limiter.try_acquire().unwrap();
+    drop((second, third));
+    assert_eq!(limiter.active(), 0);
+
+    // Concurrent acquirers never exceed the limit
+    let limiter = ConnectionLimiter::new(8);
+    let held: Vec<_> = std::thread::scope(|s| {
+        let handles: Vec<_> = (0..32).map(|_| s.spawn(|| limiter.try_acquire())).collect();
+        handles.into_iter().filter_map(|h| h.join().unwrap()).collect()
+    });
+    assert_eq!(held.len(), 8);
+    assert_eq!(limiter.active(), 8);
+}
@@ -0,0 +1,89 @@
+//! GELF transports: null-delimited TCP with reconnection, and plain UDP.
+
+use std::io::{BufRead, BufReader};
+use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use oxidb_server::gelf::{GelfLevel, GelfLogger};
+
+/// Read one null-terminated frame and parse it.
+fn read_frame(reader: &mut BufReader<TcpStream>) -> Value {
+    let mut frame = Vec::new();
+    reader.read_until(0, &mut frame).unwrap();
+    assert_eq!(frame.pop(), Some(0), "frame should be null-terminated");
+    serde_json::from_slice(&frame).unwrap()
+}
+
+fn accept(listener: &TcpListener) -> BufReader<TcpStream> {
+    let (stream, _) = listener.accept().unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    BufReader::new(stream)
+}
+
+#[test]
+fn tcp_messages_are_null_terminated_json() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let logger = GelfLogger::new(&format!("tcp://{}", listener.local_addr().unwrap())).unwrap();
+
+    logger.send(GelfLevel::Warning, "disk almost full", &[("peer", "10.0.0.1:5000")]);
+    logger.send(GelfLevel::Error, "second", &[]);
+
+    let mut reader = accept(&listener);
+    let msg = read_frame(&mut reader);
+    assert_eq!(msg["version"], "1.1");
+    assert_eq!(msg["short_message"], "disk almost full");
+    assert_eq!(msg["level"], 4);
+    assert_eq!(msg["_peer"], "10.0.0.1:5000");
+    assert!(msg["host"].is_string());
+    assert!(msg["timestamp"].is_number());
+    assert_eq!(read_frame(&mut reader)["short_message"], "second");
+}
+
+#[test]
+fn tcp_reconnects_after_the_collector_drops_the_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let logger = GelfLogger::new(&format!("tcp://{}", listener.local_addr().unwrap())).unwrap();
+
+    logger.send(GelfLevel::Informational, "before", &[]);
+    let mut first = accept(&listener);
+    assert_eq!(read_frame(&mut first)["short_message"], "before");
+    drop(first);
+
+    // Keep logging until a message arrives on a new connection; the ones
+    // written into the closed connection are lost.
+    let (done_tx, done_rx) = mpsc::channel();
+    let sender = std::thread::spawn(move || {
+        while done_rx.try_recv().is_err() {
+            logger.send(GelfLevel::Informational, "after", &[]);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    });
+    let mut second = accept(&listener);
+    assert_eq!(read_frame(&mut second)["short_message"], "after");
+    done_tx.send(()).unwrap();
+    sender.join().unwrap();
+}
+
+#[test]
+fn tcp_rejects_an_address_without_a_port() {
+    assert!(GelfLogger::new("tcp://localhost").is_err());
+}
+
+#[test]
+fn udp_is_the_default_transport() {
+    let collector = UdpSocket::bind("127.0.0.1:0").unwrap();
+    collector.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let addr = collector.local_addr().unwrap();
+
+    for target in [addr.to_string(), format!("udp://{addr}")] {
+        let logger = GelfLogger::new(&target).unwrap();
+        logger.send(GelfLevel::Notice, "over udp", &[]);
+        let mut buf = [0u8; 2048];
+        let n = collector.recv(&mut buf).unwrap();
+        let msg: Value = serde_json::from_slice(&buf[..n]).unwrap();
+        assert_eq!(msg["short_message"], "over udp");
+    }
+}
@@ -1,9 +1,10 @@
 //! Integration tests for server handler commands that are not covered by acid_test.rs.
 //!
-//! Tests: insert_many, update_one, delete_one, create_collection, compact,
-//! create_index, create_unique_index, create_composite_index, create_text_index,
-//! list_indexes, drop_index, text_search, aggregate, blob commands,
-//! user management, auth/RBAC, and crash recovery.
+//! Tests: insert_many, find with_count, update_one, delete_one, dry-run update/delete, create_collection,
+//! compact, compact_all, create_index, create_unique_index, create_composite_index, create_text_index,
+//! create_vector_index HNSW parameters, metric validation and normalization, exact vector_search, min_similarity and count_only searches, filtered text_search, list_indexes, describe, drop_index, text_search, aggregate, blob commands,
+//! error codes, binary (MessagePack) codec, response compression, pipelined request ids, metrics, health probes, user management, auth/RBAC, collection backup/restore, schedule history
+//! and on-demand runs, parameterized SQL, paged blob search, bucket tokenizers, bucket ACLs, object checksums, collection repair, relaxed durability and flush, TTL-aware reads, and crash recovery.
 
 use std::net::{SocketAddr, TcpListener, TcpStream};
 use std::path::Path;
@@ -13,7 +14,10 @@ use serde_json::{Value, json};
 use tempfile::TempDir;
 
 use oxidb::OxiDb;
-use oxidb_server::protocol::{read_message, write_message};
+use oxidb_server::protocol::{
+    COMPRESSION_THRESHOLD, Codec, Compression, codec_request, compression_request, read_message, write_message,
+    write_message_with,
+};
 
 // ---------------------------------------------------------------------------
 // Test infrastructure (mirrors acid_test.rs)
@@ -87,6 +91,8 @@ impl TestServer {
 
 fn handle_client(mut stream: TcpStream, db: &Arc<OxiDb>) {
     let mut active_tx: Option<u64> = None;
+    let mut codec = Codec::Json;
+    let mut compression = Compression::None;
 
     loop {
         let msg = match read_message(&mut stream) {
@@ -94,18 +100,43 @@ fn handle_client(mut stream: TcpStream, db: &Arc<OxiDb>) {
             Err(_) => break,
         };
 
-        let request: Value = match serde_json::from_slice(&msg) {
+        let request = match codec.decode(&msg) {
             Ok(v) => v,
             Err(e) => {
-                let resp = json!({"ok": false, "error": format!("invalid JSON: {e}")});
-                let _ = write_message(&mut stream, resp.to_string().as_bytes());
+                let resp = json!({"ok": false, "error": e});
+                let _ = write_message(&mut stream, &codec.encode(resp.to_string().as_bytes()));
                 continue;
             }
         };
 
+        if let Some(requested) = codec_request(&request) {
+            let resp = match requested {
+                Ok(c) => json!({"ok": true, "data": {"codec": c.name()}}),
+                Err(msg) => json!({"ok": false, "error": msg}),
+            };
+            let _ = write_message(&mut stream, &codec.encode(resp.to_string().as_bytes()));
+            if let Ok(c) = requested {
+                codec = c;
+            }
+            continue;
+        }
+        if let Some(requested) = compression_request(&request) {
+            let resp = match requested {
+                Ok(c) => json!({"ok": true, "data": {"compression": c.name()}}),
+                Err(msg) => json!({"ok": false, "error": msg}),
+            };
+            let _ = write_message(&mut stream, &codec.encode(resp.to_string().as_bytes()));
+            if let Ok(c) = requested {
+                compression = c;
+            }
+            continue;
+        }
+
+        let id = request.get("request_id").cloned();
         let resp_bytes = oxidb_server::handler::handle_request(db, request, &mut active_tx);
+        let resp_bytes = oxidb_server::handler::with_request_id(resp_bytes, id.as_ref());
 
-        if write_message(&mut stream, &resp_bytes).is_err() {
+        if write_message_with(&mut stream, &codec.encode(&resp_bytes), compression).is_err() {
             break;
         }
     }
@@ -117,6 +148,7 @@ fn handle_client(mut stream: TcpStream, db: &Arc<OxiDb>) {
 
 struct Client {
     stream: TcpStream,
+    codec: Codec,
 }
 
 impl Client {
@@ -125,14 +157,21 @@ impl Client {
         stream
             .set_read_timeout(Some(std::time::Duration::from_secs(5)))
             .unwrap();
-        Self { stream }
+        Self { stream, codec: Codec::Json }
     }
 
     fn send(&mut self, request: &Value) -> Value {
-        let bytes = request.to_string().into_bytes();
+        let bytes = self.codec.encode_value(request);
         write_message(&mut self.stream, &bytes).expect("send failed");
         let resp = read_message(&mut self.stream).expect("recv failed");
-        serde_json::from_slice(&resp).expect("invalid JSON response")
+        self.codec.decode(&resp).expect("undecodable response")
+    }
+
+    /// Negotiate `codec` for the rest of the connection.
+    fn set_codec(&mut self, codec: Codec) {
+        let resp = self.send(&json!({"cmd": "set_codec", "codec": codec.name()}));
+        assert_eq!(resp["data"]["codec"], codec.name(), "{resp}");
+        self.codec = codec;
     }
 }
 
@@ -206,6 +245,44 @@ fn test_update_one() {
     assert_eq!(resp["data"].as_array().unwrap().len(), 2);
 }
 
+#[test]
+fn test_update_with_expected_version() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    assert_ok(&c.send(&json!({"cmd": "insert", "collection": "docs", "doc": {"name": "a", "title": "v1"}})));
+    let title = |c: &mut Client| {
+        let resp = c.send(&json!({"cmd": "find_one", "collection": "docs", "query": {"name": "a"}}));
+        (resp["data"]["title"].clone(), resp["data"]["_version"].clone())
+    };
+
+    // Current version: applied
+    let resp = c.send(&json!({
+        "cmd": "update_one", "collection": "docs", "query": {"name": "a"},
+        "update": {"$set": {"title": "v2"}}, "expected_version": 1
+    }));
+    assert_ok(&resp);
+    assert_eq!(resp["data"]["modified"], 1);
+    assert_eq!(title(&mut c), (json!("v2"), json!(2)));
+
+    // Stale version: rejected, nothing changes
+    let resp = c.send(&json!({
+        "cmd": "update", "collection": "docs", "query": {"name": "a"},
+        "update": {"$set": {"title": "stale"}}, "expected_version": 1
+    }));
+    assert_eq!(resp["ok"], false);
+    assert_eq!(resp["code"], "version_conflict");
+    assert_eq!(title(&mut c), (json!("v2"), json!(2)));
+
+    // No version: plain update
+    let resp = c.send(&json!({
+        "cmd": "update", "collection": "docs", "query": {"name": "a"},
+        "update": {"$set": {"title": "v3"}}
+    }));
+    assert_eq!(resp["data"]["modified"], 1);
+    assert_eq!(title(&mut c), (json!("v3"), json!(3)));
+}
+
 #[test]
 fn test_delete_one() {
     let server = TestServer::start();
@@ -230,6 +307,50 @@ fn test_delete_one() {
     assert_eq!(resp["data"]["count"], 2);
 }
 
+#[test]
+fn test_dry_run_update_and_delete() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    for name in ["a", "b", "c"] {
+        let status = if name == "c" { "keep" } else { "stale" };
+        assert_ok(&c.send(&json!({"cmd": "insert", "collection": "docs", "doc": {"name": name, "status": status}})));
+    }
+    let stale = json!({"status": "stale"});
+
+    let resp = c.send(&json!({"cmd": "delete", "collection": "docs", "query": stale, "dry_run": true}));
+    assert_ok(&resp);
+    assert_eq!(resp["data"]["dry_run"], true);
+    assert_eq!(resp["data"]["deleted"], 2);
+    assert_eq!(resp["data"]["ids"].as_array().unwrap().len(), 2);
+
+    let resp = c.send(&json!({
+        "cmd": "update", "collection": "docs", "query": stale,
+        "update": {"$set": {"status": "archived"}}, "dry_run": true
+    }));
+    assert_ok(&resp);
+    assert_eq!(resp["data"]["modified"], 2);
+    let docs = resp["data"]["documents"].as_array().unwrap();
+    assert!(docs.iter().all(|d| d["status"] == "archived" && d["_version"] == 2));
+    assert_eq!(resp["data"]["ids"], json!(docs.iter().map(|d| d["_id"].clone()).collect::<Vec<_>>()));
+
+    let resp = c.send(&json!({"cmd": "delete_one", "collection": "docs", "query": stale, "dry_run": true}));
+    assert_eq!(resp["data"]["deleted"], 1);
+
+    // Nothing changed
+    let resp = c.send(&json!({"cmd": "find", "collection": "docs", "query": stale}));
+    assert_eq!(resp["data"].as_array().unwrap().len(), 2);
+    assert!(resp["data"].as_array().unwrap().iter().all(|d| d["_version"] == 1));
+    let resp = c.send(&json!({"cmd": "count", "collection": "docs"}));
+    assert_eq!(resp["data"]["count"], 3);
+
+    // Dry runs see committed data only, so they're refused inside a transaction
+    assert_ok(&c.send(&json!({"cmd": "begin_tx"})));
+    let resp = c.send(&json!({"cmd": "delete", "collection": "docs", "query": stale, "dry_run": true}));
+    assert_eq!(resp["ok"], false);
+    assert_ok(&c.send(&json!({"cmd": "rollback_tx"})));
+}
+
 // ===========================================================================
 // Collection management
 // ===========================================================================
@@ -294,279 +415,1377 @@ fn test_compact() {
     assert_eq!(resp["data"]["count"], 5);
 }
 
-// ===========================================================================
-// Index management
-// ===========================================================================
-
 #[test]
-fn test_create_and_list_indexes() {
+fn test_relaxed_durability() {
     let server = TestServer::start();
     let mut c = Client::connect(server.addr);
 
-    // Insert some data first
-    c.send(&json!({"cmd": "insert", "collection": "idx_test", "doc": {"name": "a", "age": 1}}));
-
-    let resp = c.send(&json!({
-        "cmd": "create_index", "collection": "idx_test", "field": "name"
-    }));
+    let docs: Vec<Value> = (0..50).map(|i| json!({"idx": i})).collect();
+    let resp = c.send(&json!({"cmd": "insert_many", "collection": "load", "docs": docs, "durability": "relaxed"}));
     assert_ok(&resp);
-
+    assert_eq!(resp["data"].as_array().unwrap().len(), 50);
+    assert_ok(&c.send(&json!({"cmd": "insert", "collection": "load", "doc": {"idx": 50}, "durability": "relaxed"})));
     let resp = c.send(&json!({
-        "cmd": "list_indexes", "collection": "idx_test"
+        "cmd": "bulk_write", "collection": "load", "durability": "relaxed",
+        "ops": [{"op": "delete", "query": {"idx": {"$lt": 10}}}]
     }));
     assert_ok(&resp);
-    let indexes = resp["data"].as_array().unwrap();
-    assert!(indexes.iter().any(|v| v.as_str() == Some("name") || v.to_string().contains("name")));
+    assert_eq!(c.send(&json!({"cmd": "flush", "collection": "load"}))["data"], "flushed");
+    assert_eq!(c.send(&json!({"cmd": "count", "collection": "load"}))["data"]["count"], 41);
+
+    let resp = c.send(&json!({"cmd": "insert", "collection": "load", "doc": {}, "durability": "lazy"}));
+    assert_eq!(resp["error"], "'durability' must be \"full\" or \"relaxed\"");
+    assert_ok(&c.send(&json!({"cmd": "insert", "collection": "load", "doc": {}, "durability": "full"})));
+
+    c.send(&json!({"cmd": "begin_tx"}));
+    let resp = c.send(&json!({"cmd": "insert", "collection": "load", "doc": {}, "durability": "relaxed"}));
+    assert_eq!(resp["error"], "durability \"relaxed\" can't be used in a transaction");
+    c.send(&json!({"cmd": "rollback_tx"}));
+    assert_eq!(c.send(&json!({"cmd": "flush"}))["error"], "missing 'collection'");
 }
 
 #[test]
-fn test_create_unique_index() {
+fn test_respect_ttl() {
     let server = TestServer::start();
     let mut c = Client::connect(server.addr);
 
-    c.send(&json!({"cmd": "insert", "collection": "uniq", "doc": {"email": "a@b.c"}}));
+    c.send(&json!({"cmd": "insert", "collection": "sessions", "doc": {"user": "ann", "_expireAt": "2000-01-01T00:00:00Z"}}));
+    c.send(&json!({"cmd": "insert", "collection": "sessions", "doc": {"user": "bob", "_expireAt": 4102444800u64}}));
+    c.send(&json!({"cmd": "insert", "collection": "sessions", "doc": {"user": "cy"}}));
 
+    let resp = c.send(&json!({"cmd": "find", "collection": "sessions", "query": {}, "with_count": true}));
+    assert_eq!(resp["total"], 3);
     let resp = c.send(&json!({
-        "cmd": "create_unique_index", "collection": "uniq", "field": "email"
+        "cmd": "find", "collection": "sessions", "query": {}, "with_count": true,
+        "respect_ttl": true, "sort": {"user": 1}
     }));
     assert_ok(&resp);
+    assert_eq!(resp["total"], 2);
+    assert_eq!(resp["data"][0]["user"], "bob");
+    assert_eq!(resp["data"][1]["user"], "cy");
 
-    // Insert duplicate should fail
-    let resp = c.send(&json!({
-        "cmd": "insert", "collection": "uniq", "doc": {"email": "a@b.c"}
-    }));
-    assert_err(&resp);
+    let resp = c.send(&json!({"cmd": "find_one", "collection": "sessions", "query": {"user": "ann"}}));
+    assert_eq!(resp["data"]["user"], "ann");
+    let resp = c.send(&json!({"cmd": "find_one", "collection": "sessions", "query": {"user": "ann"}, "respect_ttl": true}));
+    assert_ok(&resp);
+    assert!(resp["data"].is_null());
 }
 
 #[test]
-fn test_create_composite_index() {
+fn test_repair() {
     let server = TestServer::start();
     let mut c = Client::connect(server.addr);
 
-    c.send(&json!({"cmd": "insert", "collection": "comp", "doc": {"a": 1, "b": 2}}));
+    for i in 0..4 {
+        c.send(&json!({"cmd": "insert", "collection": "damaged", "doc": {"idx": i}}));
+    }
+    assert_ok(&c.send(&json!({"cmd": "compact", "collection": "damaged"})));
 
-    let resp = c.send(&json!({
-        "cmd": "create_composite_index", "collection": "comp", "fields": ["a", "b"]
-    }));
+    // Cut the last record short, as a crash mid-append would
+    let dat = server.data_dir.join("damaged.dat");
+    let len = std::fs::metadata(&dat).unwrap().len();
+    std::fs::OpenOptions::new().write(true).open(&dat).unwrap().set_len(len - 4).unwrap();
+
+    let resp = c.send(&json!({"cmd": "repair", "collection": "damaged"}));
     assert_ok(&resp);
-    assert!(resp["data"]["index"].is_string());
+    assert_eq!(resp["data"]["recovered"], 3);
+    assert_eq!(resp["data"]["lost"], 1);
+    assert!(resp["data"]["quarantine"].as_str().unwrap().ends_with("damaged.corrupt"));
+
+    let resp = c.send(&json!({"cmd": "count", "collection": "damaged"}));
+    assert_eq!(resp["data"]["count"], 3);
+    assert_ok(&c.send(&json!({"cmd": "insert", "collection": "damaged", "doc": {"idx": 4}})));
+
+    let resp = c.send(&json!({"cmd": "repair", "collection": "nope"}));
+    assert_eq!(resp["ok"], false);
+    assert_eq!(c.send(&json!({"cmd": "repair"}))["error"], "missing 'collection'");
 }
 
 #[test]
-fn test_drop_index() {
+fn test_distinct() {
     let server = TestServer::start();
     let mut c = Client::connect(server.addr);
 
-    c.send(&json!({"cmd": "insert", "collection": "drop_idx", "doc": {"x": 1}}));
-    c.send(&json!({"cmd": "create_index", "collection": "drop_idx", "field": "x"}));
+    let docs = json!([
+        {"status": "open", "tags": ["a", "b"]},
+        {"status": "closed", "tags": ["b"]},
+        {"status": "open", "tags": ["c"]},
+    ]);
+    assert_ok(&c.send(&json!({"cmd": "insert_many", "collection": "tickets", "docs": docs})));
 
-    let resp = c.send(&json!({
-        "cmd": "drop_index", "collection": "drop_idx", "index": "x"
-    }));
+    let resp = c.send(&json!({"cmd": "distinct", "collection": "tickets", "field": "status"}));
     assert_ok(&resp);
-}
+    assert_eq!(resp["data"], json!(["closed", "open"]));
 
-#[test]
-fn test_create_text_index() {
-    let server = TestServer::start();
-    let mut c = Client::connect(server.addr);
+    let resp = c.send(&json!({"cmd": "distinct", "collection": "tickets", "field": "tags", "query": {"status": "open"}}));
+    assert_eq!(resp["data"], json!(["a", "b", "c"]));
 
-    c.send(&json!({"cmd": "insert", "collection": "text", "doc": {"title": "hello", "body": "world"}}));
+    // Same answer through an index
+    assert_ok(&c.send(&json!({"cmd": "create_index", "collection": "tickets", "field": "status"})));
+    let resp = c.send(&json!({"cmd": "distinct", "collection": "tickets", "field": "status"}));
+    assert_eq!(resp["data"], json!(["closed", "open"]));
 
-    let resp = c.send(&json!({
-        "cmd": "create_text_index", "collection": "text", "fields": ["title", "body"]
-    }));
-    assert_ok(&resp);
+    let resp = c.send(&json!({"cmd": "distinct", "collection": "tickets"}));
+    assert_eq!(resp["ok"], false);
 }
 
-// ===========================================================================
-// Text search
-// ===========================================================================
-
 #[test]
-fn test_text_search() {
+fn test_set_and_get_schema() {
     let server = TestServer::start();
     let mut c = Client::connect(server.addr);
 
-    // Insert docs and create text index
-    c.send(&json!({
-        "cmd": "insert", "collection": "articles",
-        "doc": {"title": "Rust programming", "body": "Systems language"}
-    }));
-    c.send(&json!({
-        "cmd": "insert", "collection": "articles",
-        "doc": {"title": "Python scripting", "body": "Dynamic language"}
-    }));
-    c.send(&json!({
-        "cmd": "create_text_index", "collection": "articles", "fields": ["title", "body"]
-    }));
+    let resp = c.send(&json!({"cmd": "get_schema", "collection": "users"}));
+    assert_ok(&resp);
+    assert_eq!(resp["data"], Value::Null);
 
-    // Allow time for background FTS indexing
-    std::thread::sleep(std::time::Duration::from_millis(500));
+    let schema = json!({"type": "object", "required": ["name"], "properties": {"name": {"type": "string"}}});
+    assert_ok(&c.send(&json!({"cmd": "set_schema", "collection": "users", "schema": schema})));
+    let resp = c.send(&json!({"cmd": "get_schema", "collection": "users"}));
+    assert_eq!(resp["data"], json!({"schema": schema, "level": "strict"}));
 
-    let resp = c.send(&json!({
-        "cmd": "text_search", "collection": "articles", "query": "Rust", "limit": 10
-    }));
-    assert_ok(&resp);
-}
+    assert_ok(&c.send(&json!({"cmd": "insert", "collection": "users", "doc": {"name": "ann"}})));
+    let resp = c.send(&json!({"cmd": "insert", "collection": "users", "doc": {"age": 3}}));
+    assert_err(&resp);
+    assert_eq!(resp["code"], "validation_failed");
 
-// ===========================================================================
-// Aggregate
-// ===========================================================================
+    let resp = c.send(&json!({"cmd": "set_schema", "collection": "users", "schema": {"type": "text"}}));
+    assert_eq!(resp["code"], "invalid_schema");
+    let resp = c.send(&json!({"cmd": "set_schema", "collection": "users", "schema": schema, "level": "loose"}));
+    assert_eq!(resp["code"], "invalid_schema");
+
+    assert_ok(&c.send(&json!({"cmd": "set_schema", "collection": "users", "schema": null})));
+    assert_ok(&c.send(&json!({"cmd": "insert", "collection": "users", "doc": {"age": 3}})));
+}
 
 #[test]
-fn test_aggregate() {
+fn test_set_and_get_defaults() {
     let server = TestServer::start();
     let mut c = Client::connect(server.addr);
 
-    c.send(&json!({"cmd": "insert", "collection": "sales", "doc": {"product": "A", "amount": 10}}));
-    c.send(&json!({"cmd": "insert", "collection": "sales", "doc": {"product": "B", "amount": 20}}));
-    c.send(&json!({"cmd": "insert", "collection": "sales", "doc": {"product": "A", "amount": 30}}));
+    let defaults = json!({"status": "new", "created_at": "$now"});
+    assert_ok(&c.send(&json!({"cmd": "set_defaults", "collection": "orders", "defaults": defaults})));
+    let resp = c.send(&json!({"cmd": "get_defaults", "collection": "orders"}));
+    assert_eq!(resp["data"], defaults);
 
-    let resp = c.send(&json!({
-        "cmd": "aggregate",
-        "collection": "sales",
-        "pipeline": [
-            {"$group": {"_id": "$product", "total": {"$sum": "$amount"}}},
-            {"$sort": {"_id": 1}}
-        ]
-    }));
-    assert_ok(&resp);
-    let data = resp["data"].as_array().unwrap();
-    assert_eq!(data.len(), 2);
-}
+    let resp = c.send(&json!({"cmd": "insert", "collection": "orders", "doc": {"item": "pen"}}));
+    let id = resp["data"]["id"].clone();
+    let resp = c.send(&json!({"cmd": "find_one", "collection": "orders", "query": {"_id": id}}));
+    assert_eq!(resp["data"]["status"], "new");
+    assert!(resp["data"]["created_at"].is_string());
 
-// ===========================================================================
-// Blob storage
-// ===========================================================================
+    let resp = c.send(&json!({"cmd": "set_defaults", "collection": "orders", "defaults": {"id": "$random"}}));
+    assert_eq!(resp["code"], "invalid_schema");
+
+    assert_ok(&c.send(&json!({"cmd": "set_defaults", "collection": "orders", "defaults": null})));
+    let resp = c.send(&json!({"cmd": "get_defaults", "collection": "orders"}));
+    assert_eq!(resp["data"], json!({}));
+}
 
 #[test]
-fn test_blob_crud() {
+fn test_find_and_modify() {
     let server = TestServer::start();
     let mut c = Client::connect(server.addr);
 
-    // Create bucket
-    let resp = c.send(&json!({"cmd": "create_bucket", "bucket": "files"}));
-    assert_ok(&resp);
-
-    // List buckets
-    let resp = c.send(&json!({"cmd": "list_buckets"}));
-    assert_ok(&resp);
-    let buckets = resp["data"].as_array().unwrap();
-    assert!(buckets.iter().any(|v| v == "files"));
-
-    // Put object (base64 of "hello world")
-    let data_b64 = base64_encode(b"hello world");
-    let resp = c.send(&json!({
-        "cmd": "put_object",
-        "bucket": "files",
-        "key": "greeting.txt",
-        "data": data_b64,
-        "content_type": "text/plain",
-        "metadata": {"author": "test"}
-    }));
-    assert_ok(&resp);
+    let jobs: Vec<Value> = ["a", "b", "c"].iter().map(|j| json!({"job": j, "state": "pending"})).collect();
+    assert_ok(&c.send(&json!({"cmd": "insert_many", "collection": "jobs", "docs": jobs})));
 
-    // Get object
+    // Claim the first pending job by name and get the updated document back
     let resp = c.send(&json!({
-        "cmd": "get_object", "bucket": "files", "key": "greeting.txt"
+        "cmd": "find_and_modify", "collection": "jobs", "query": {"state": "pending"},
+        "update": {"$set": {"state": "running"}}, "sort": {"job": 1}, "new": true
     }));
     assert_ok(&resp);
-    assert!(resp["data"]["content"].is_string());
+    assert_eq!(resp["data"]["job"], "a");
+    assert_eq!(resp["data"]["state"], "running");
 
-    // Head object
+    // Without "new" the document is returned as it was before the update
     let resp = c.send(&json!({
-        "cmd": "head_object", "bucket": "files", "key": "greeting.txt"
+        "cmd": "find_and_modify", "collection": "jobs", "query": {"state": "pending"},
+        "update": {"$set": {"state": "running"}}, "sort": {"job": 1}
     }));
-    assert_ok(&resp);
+    assert_eq!(resp["data"]["job"], "b");
+    assert_eq!(resp["data"]["state"], "pending");
 
-    // List objects
-    let resp = c.send(&json!({
-        "cmd": "list_objects", "bucket": "files"
-    }));
-    assert_ok(&resp);
+    // Remove returns the deleted document
+    let resp = c.send(&json!({"cmd": "find_and_modify", "collection": "jobs", "query": {"job": "c"}, "remove": true}));
+    assert_eq!(resp["data"]["job"], "c");
+    let count = c.send(&json!({"cmd": "count", "collection": "jobs"}));
+    assert_eq!(count["data"]["count"], 2);
 
-    // Delete object
+    // Nothing left to claim
     let resp = c.send(&json!({
-        "cmd": "delete_object", "bucket": "files", "key": "greeting.txt"
+        "cmd": "find_and_modify", "collection": "jobs", "query": {"state": "pending"},
+        "update": {"$set": {"state": "running"}}
     }));
     assert_ok(&resp);
+    assert_eq!(resp["data"], Value::Null);
 
-    // Delete bucket
-    let resp = c.send(&json!({"cmd": "delete_bucket", "bucket": "files"}));
-    assert_ok(&resp);
-}
-
-fn base64_encode(data: &[u8]) -> String {
-    use base64::Engine;
-    base64::engine::general_purpose::STANDARD.encode(data)
+    // Both or neither of update and remove is an error
+    let resp = c.send(&json!({"cmd": "find_and_modify", "collection": "jobs", "query": {}}));
+    assert_eq!(resp["ok"], false);
 }
 
-// ===========================================================================
-// FTS (blob-level search)
-// ===========================================================================
-
 #[test]
-fn test_search_command() {
+fn test_bulk_write() {
     let server = TestServer::start();
     let mut c = Client::connect(server.addr);
-
-    // search on empty DB should succeed
-    let resp = c.send(&json!({
-        "cmd": "search", "query": "test", "limit": 5
-    }));
+    assert_ok(&c.send(&json!({"cmd": "create_unique_index", "collection": "notes", "field": "slug"})));
+    assert_ok(&c.send(&json!({"cmd": "insert", "collection": "notes", "doc": {"slug": "a", "state": "draft"}})));
+
+    let resp = c.send(&json!({"cmd": "bulk_write", "collection": "notes", "ops": [
+        {"op": "insert", "doc": {"slug": "b", "state": "draft"}},
+        {"op": "update", "query": {"state": "draft"}, "update": {"$set": {"state": "live"}}},
+        {"op": "delete", "query": {"slug": "a"}}
+    ]}));
     assert_ok(&resp);
-}
-
-// ===========================================================================
-// Error handling
-// ===========================================================================
-
-#[test]
-fn test_unknown_command() {
-    let server = TestServer::start();
-    let mut c = Client::connect(server.addr);
+    assert_eq!(resp["data"][0]["id"], 2);
+    assert_eq!(resp["data"][1], json!({"modified": 2}));
+    assert_eq!(resp["data"][2], json!({"deleted": 1}));
+
+    // A duplicate slug anywhere in the batch rejects all of it
+    let resp = c.send(&json!({"cmd": "bulk_write", "collection": "notes", "ops": [
+        {"op": "delete", "query": {"slug": "b"}},
+        {"op": "insert", "doc": {"slug": "c"}},
+        {"op": "insert", "doc": {"slug": "c"}}
+    ]}));
+    assert_err(&resp);
+    assert_eq!(resp["code"], "unique_violation");
+    let resp = c.send(&json!({"cmd": "find", "collection": "notes", "query": {}}));
+    assert_eq!(resp["data"].as_array().unwrap().len(), 1);
+    assert_eq!(resp["data"][0]["slug"], "b");
+    assert_eq!(resp["data"][0]["state"], "live");
 
-    let resp = c.send(&json!({"cmd": "nonexistent_cmd"}));
+    let resp = c.send(&json!({"cmd": "bulk_write", "collection": "notes", "ops": [{"op": "upsert"}]}));
     assert_err(&resp);
-    assert!(resp["error"].as_str().unwrap().contains("unknown command"));
 }
 
 #[test]
-fn test_missing_cmd_field() {
+fn test_read_concern() {
     let server = TestServer::start();
     let mut c = Client::connect(server.addr);
+    assert_ok(&c.send(&json!({"cmd": "insert", "collection": "notes", "doc": {"name": "a"}})));
 
-    let resp = c.send(&json!({"not_cmd": "insert"}));
-    assert_err(&resp);
+    // A standalone server is always linearizable
+    for read_concern in ["local", "linearizable"] {
+        let resp = c.send(&json!({"cmd": "count", "collection": "notes", "read_concern": read_concern}));
+        assert_eq!(resp["data"]["count"], 1, "{resp}");
+    }
+    let resp = c.send(&json!({"cmd": "find_one", "collection": "notes", "query": {"name": "a"}, "read_concern": "linearizable"}));
+    assert_eq!(resp["data"]["name"], "a");
+
+    assert_err(&c.send(&json!({"cmd": "find", "collection": "notes", "read_concern": "majority"})));
+    assert_err(&c.send(&json!({"cmd": "find", "collection": "notes", "read_concern": 1})));
+
+    assert_ok(&c.send(&json!({"cmd": "begin_tx"})));
+    assert_err(&c.send(&json!({"cmd": "find", "collection": "notes", "read_concern": "linearizable"})));
+    assert_ok(&c.send(&json!({"cmd": "find", "collection": "notes", "read_concern": "local"})));
 }
 
 #[test]
-fn test_missing_collection() {
+fn test_find_with_count() {
     let server = TestServer::start();
     let mut c = Client::connect(server.addr);
 
-    let resp = c.send(&json!({"cmd": "insert", "doc": {"x": 1}}));
-    assert_err(&resp);
+    let docs: Vec<Value> = (0..25).map(|i| json!({"n": i, "size": if i < 10 { "small" } else { "big" }})).collect();
+    assert_ok(&c.send(&json!({"cmd": "insert_many", "collection": "pages", "docs": docs})));
+
+    let query = json!({"size": "big"});
+    let resp = c.send(&json!({
+        "cmd": "find", "collection": "pages", "query": query,
+        "sort": {"n": 1}, "skip": 5, "limit": 5, "with_count": true
+    }));
+    assert_ok(&resp);
+    assert_eq!(resp["total"], 15);
+    let page: Vec<u64> = resp["data"].as_array().unwrap().iter().map(|d| d["n"].as_u64().unwrap()).collect();
+    assert_eq!(page, [15, 16, 17, 18, 19]);
+
+    // The total agrees with count
+    let count = c.send(&json!({"cmd": "count", "collection": "pages", "query": query}));
+    assert_eq!(count["data"]["count"], resp["total"]);
+
+    // Past the end: empty page, same total
+    let resp = c.send(&json!({"cmd": "find", "collection": "pages", "query": query, "skip": 20, "with_count": true}));
+    assert_eq!(resp["data"], json!([]));
+    assert_eq!(resp["total"], 15);
+
+    // Without the flag the response has no total
+    let resp = c.send(&json!({"cmd": "find", "collection": "pages", "query": query, "limit": 2}));
+    assert_eq!(resp["data"].as_array().unwrap().len(), 2);
+    assert!(resp.get("total").is_none());
 }
 
 #[test]
-fn test_missing_doc() {
+fn test_compact_all() {
     let server = TestServer::start();
     let mut c = Client::connect(server.addr);
 
-    let resp = c.send(&json!({"cmd": "insert", "collection": "test"}));
-    assert_err(&resp);
+    for col in ["one", "two"] {
+        for i in 0..10 {
+            c.send(&json!({"cmd": "insert", "collection": col, "doc": {"idx": i}}));
+        }
+        c.send(&json!({"cmd": "delete", "collection": col, "query": {"idx": {"$lt": 5}}}));
+    }
+
+    let resp = c.send(&json!({"cmd": "compact_all"}));
+    assert_ok(&resp);
+    assert_eq!(resp["data"]["docs_kept"], 10);
+    assert!(resp["data"]["new_size"].as_u64().unwrap() < resp["data"]["old_size"].as_u64().unwrap());
+    let cols = resp["data"]["collections"].as_array().unwrap();
+    assert_eq!(cols.len(), 2);
+    assert_eq!(cols[0]["collection"], "one");
+    assert_eq!(cols[1]["docs_kept"], 5);
 }
 
+// ===========================================================================
+// Index management
+// ===========================================================================
+
 #[test]
-fn test_find_with_sort_skip_limit() {
+fn test_create_and_list_indexes() {
     let server = TestServer::start();
     let mut c = Client::connect(server.addr);
 
-    for i in 0..10 {
-        c.send(&json!({
-            "cmd": "insert", "collection": "sorted",
+    // Insert some data first
+    c.send(&json!({"cmd": "insert", "collection": "idx_test", "doc": {"name": "a", "age": 1}}));
+
+    let resp = c.send(&json!({
+        "cmd": "create_index", "collection": "idx_test", "field": "name"
+    }));
+    assert_ok(&resp);
+
+    let resp = c.send(&json!({
+        "cmd": "list_indexes", "collection": "idx_test"
+    }));
+    assert_ok(&resp);
+    let indexes = resp["data"].as_array().unwrap();
+    assert!(indexes.iter().any(|v| v.as_str() == Some("name") || v.to_string().contains("name")));
+}
+
+#[test]
+fn test_describe() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    c.send(&json!({"cmd": "insert_many", "collection": "desc", "docs": [
+        {"name": "a", "age": 1},
+        {"name": "b", "age": "two", "tags": []},
+    ]}));
+    assert_ok(&c.send(&json!({"cmd": "create_index", "collection": "desc", "field": "name"})));
+
+    let resp = c.send(&json!({"cmd": "describe", "collection": "desc"}));
+    assert_ok(&resp);
+    let desc = &resp["data"];
+    assert_eq!(desc["count"], 2);
+    assert_eq!(desc["sampled"], 2);
+    let fields: Vec<&Value> = desc["fields"].as_array().unwrap().iter().filter(|f| f["field"] != "_version").collect();
+    assert_eq!(fields, [
+        &json!({"field": "_id", "types": ["number"], "count": 2}),
+        &json!({"field": "age", "types": ["number", "string"], "count": 2}),
+        &json!({"field": "name", "types": ["string"], "count": 2}),
+        &json!({"field": "tags", "types": ["array"], "count": 1}),
+    ]);
+    let indexes = desc["indexes"].as_array().unwrap();
+    assert_eq!(indexes.len(), 1);
+    assert_eq!(indexes[0]["name"], "name");
+
+    let resp = c.send(&json!({"cmd": "describe", "collection": "desc", "sample": 1}));
+    assert_eq!(resp["data"]["sampled"], 1);
+
+    assert_err(&c.send(&json!({"cmd": "describe"})));
+}
+
+#[test]
+fn test_create_unique_index() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    c.send(&json!({"cmd": "insert", "collection": "uniq", "doc": {"email": "a@b.c"}}));
+
+    let resp = c.send(&json!({
+        "cmd": "create_unique_index", "collection": "uniq", "field": "email"
+    }));
+    assert_ok(&resp);
+
+    // Insert duplicate should fail
+    let resp = c.send(&json!({
+        "cmd": "insert", "collection": "uniq", "doc": {"email": "a@b.c"}
+    }));
+    assert_err(&resp);
+}
+
+#[test]
+fn test_create_composite_index() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    c.send(&json!({"cmd": "insert", "collection": "comp", "doc": {"a": 1, "b": 2}}));
+
+    let resp = c.send(&json!({
+        "cmd": "create_composite_index", "collection": "comp", "fields": ["a", "b"]
+    }));
+    assert_ok(&resp);
+    assert!(resp["data"]["index"].is_string());
+}
+
+#[test]
+fn test_drop_index() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    c.send(&json!({"cmd": "insert", "collection": "drop_idx", "doc": {"x": 1}}));
+    c.send(&json!({"cmd": "create_index", "collection": "drop_idx", "field": "x"}));
+
+    let resp = c.send(&json!({
+        "cmd": "drop_index", "collection": "drop_idx", "index": "x"
+    }));
+    assert_ok(&resp);
+}
+
+#[test]
+fn test_error_codes() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    c.send(&json!({"cmd": "create_unique_index", "collection": "codes", "field": "email"}));
+    assert_ok(&c.send(&json!({"cmd": "insert", "collection": "codes", "doc": {"email": "a@b.c"}})));
+
+    let resp = c.send(&json!({"cmd": "insert", "collection": "codes", "doc": {"email": "a@b.c"}}));
+    assert_err(&resp);
+    assert_eq!(resp["code"], "unique_violation");
+    assert!(resp["error"].as_str().unwrap().contains("email"));
+
+    let resp = c.send(&json!({"cmd": "drop_index", "collection": "codes", "index": "nope"}));
+    assert_eq!(resp["code"], "index_not_found");
+
+    let resp = c.send(&json!({"cmd": "aggregate", "collection": "codes", "pipeline": [{"$bogus": {}}]}));
+    assert_eq!(resp["code"], "invalid_pipeline");
+
+    let resp = c.send(&json!({"cmd": "commit_tx"}));
+    assert_eq!(resp["code"], "no_active_tx");
+
+    let resp = c.send(&json!({"cmd": "get_object", "bucket": "missing", "key": "k"}));
+    assert_eq!(resp["code"], "bucket_not_found");
+
+    // Request validation errors carry no code
+    let resp = c.send(&json!({"cmd": "insert", "collection": "codes"}));
+    assert_err(&resp);
+    assert!(resp.get("code").is_none());
+}
+
+// ===========================================================================
+// Binary codec
+// ===========================================================================
+
+#[test]
+fn test_msgpack_codec_matches_json() {
+    let script = [
+        json!({"cmd": "insert", "collection": "items", "doc": {"name": "alpha", "qty": -3, "price": 2.5, "tags": ["a", "b"], "meta": {"note": null}}}),
+        json!({"cmd": "insert_many", "collection": "items", "docs": [{"name": "beta", "qty": 7}, {"name": "gamma", "qty": 12}]}),
+        json!({"cmd": "update_one", "collection": "items", "query": {"name": "beta"}, "update": {"$set": {"name": "beta2"}}}),
+        json!({"cmd": "find", "collection": "items", "query": {}, "sort": {"name": 1}}),
+        json!({"cmd": "find_one", "collection": "items", "query": {"name": "alpha"}}),
+        json!({"cmd": "count", "collection": "items", "query": {"name": "gamma"}}),
+        json!({"cmd": "delete", "collection": "items"}),
+        json!({"cmd": "drop_index", "collection": "items", "index": "nope"}),
+    ];
+
+    let json_server = TestServer::start();
+    let mut json_client = Client::connect(json_server.addr);
+    let binary_server = TestServer::start();
+    let mut binary_client = Client::connect(binary_server.addr);
+    binary_client.set_codec(Codec::MessagePack);
+
+    for request in &script {
+        let expected = json_client.send(request);
+        let actual = binary_client.send(request);
+        assert_eq!(actual, expected, "{request}");
+    }
+
+    // Responses really are MessagePack, not JSON
+    let body = Codec::MessagePack.encode_value(&json!({"cmd": "ping"}));
+    write_message(&mut binary_client.stream, &body).unwrap();
+    let raw = read_message(&mut binary_client.stream).unwrap();
+    assert!(serde_json::from_slice::<Value>(&raw).is_err());
+    assert_eq!(Codec::MessagePack.decode(&raw).unwrap()["data"], "pong");
+
+    // A malformed body is reported in the connection's codec
+    write_message(&mut binary_client.stream, &[0x81, 0xa3, b'c', b'm', b'd']).unwrap();
+    let raw = read_message(&mut binary_client.stream).unwrap();
+    let resp = Codec::MessagePack.decode(&raw).unwrap();
+    assert_err(&resp);
+    assert!(resp["error"].as_str().unwrap().starts_with("invalid MessagePack"), "{resp}");
+
+    // A response that isn't valid JSON is encoded as an error, not a nil
+    let resp = Codec::MessagePack.decode(&Codec::MessagePack.encode(b"{\"ok\": tru")).unwrap();
+    assert_err(&resp);
+    assert_eq!(resp["code"], "json_error");
+
+    // Switching back to JSON works the same way
+    binary_client.set_codec(Codec::Json);
+    assert_eq!(binary_client.send(&json!({"cmd": "ping"}))["data"], "pong");
+}
+
+#[test]
+fn test_set_codec_rejects_unknown_codec() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    let resp = c.send(&json!({"cmd": "set_codec", "codec": "xml"}));
+    assert_err(&resp);
+    let resp = c.send(&json!({"cmd": "set_codec"}));
+    assert_err(&resp);
+    // The connection stays in JSON mode
+    assert_eq!(c.send(&json!({"cmd": "ping"}))["data"], "pong");
+}
+
+// ===========================================================================
+// Pipelining
+// ===========================================================================
+
+#[test]
+fn test_pipelined_requests_echo_ids() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    // Send all three before reading any response
+    let requests = [
+        json!({"cmd": "insert", "collection": "pipe", "doc": {"name": "a"}, "request_id": 1}),
+        json!({"cmd": "find", "collection": "pipe", "query": {"name": "a"}, "request_id": "two"}),
+        json!({"cmd": "drop_index", "collection": "pipe", "index": "nope", "request_id": 3}),
+    ];
+    for request in &requests {
+        write_message(&mut c.stream, request.to_string().as_bytes()).unwrap();
+    }
+    let responses: Vec<Value> =
+        (0..3).map(|_| serde_json::from_slice(&read_message(&mut c.stream).unwrap()).unwrap()).collect();
+
+    assert_eq!(responses[0]["request_id"], 1);
+    assert_ok(&responses[0]);
+    assert_eq!(responses[1]["request_id"], "two");
+    assert_eq!(responses[1]["data"][0]["name"], "a");
+    assert_eq!(responses[2]["request_id"], 3);
+    assert_eq!(responses[2]["code"], "index_not_found");
+
+    // Without a request_id, the response is unchanged; `id` is an ordinary
+    // command parameter and isn't echoed
+    let resp = c.send(&json!({"cmd": "ping"}));
+    assert_eq!(resp, json!({"ok": true, "data": "pong"}));
+    let resp = c.send(&json!({"cmd": "ping", "id": "key-1"}));
+    assert_eq!(resp, json!({"ok": true, "data": "pong"}));
+}
+
+// ===========================================================================
+// Response compression
+// ===========================================================================
+
+/// Read one frame without decoding it: whether the gzip flag is set, and the
+/// body as sent.
+fn read_raw_frame(stream: &mut TcpStream) -> (bool, Vec<u8>) {
+    use std::io::Read;
+    let mut prefix = [0u8; 4];
+    stream.read_exact(&mut prefix).unwrap();
+    let raw = u32::from_le_bytes(prefix);
+    let mut body = vec![0u8; (raw & !(1 << 31)) as usize];
+    stream.read_exact(&mut body).unwrap();
+    (raw & (1 << 31) != 0, body)
+}
+
+#[test]
+fn test_gzip_compression() {
+    use std::io::Read;
+
+    let server = TestServer::start();
+    let mut plain = Client::connect(server.addr);
+    let docs: Vec<Value> = (0..200)
+        .map(|i| json!({"name": format!("user{i:03}"), "bio": "lorem ipsum dolor sit amet ".repeat(4)}))
+        .collect();
+    assert_ok(&plain.send(&json!({"cmd": "insert_many", "collection": "people", "docs": docs})));
+
+    let mut gz = Client::connect(server.addr);
+    let resp = gz.send(&json!({"cmd": "set_compression", "compression": "gzip"}));
+    assert_eq!(resp["data"]["compression"], "gzip", "{resp}");
+
+    // A large response arrives compressed and inflates to the uncompressed bytes
+    let find = json!({"cmd": "find", "collection": "people", "query": {}, "sort": {"name": 1}});
+    write_message(&mut plain.stream, find.to_string().as_bytes()).unwrap();
+    let (flagged, expected) = read_raw_frame(&mut plain.stream);
+    assert!(!flagged, "compression wasn't negotiated on this connection");
+    assert!(expected.len() >= COMPRESSION_THRESHOLD);
+
+    write_message(&mut gz.stream, find.to_string().as_bytes()).unwrap();
+    let (flagged, body) = read_raw_frame(&mut gz.stream);
+    assert!(flagged, "large response should be compressed");
+    assert!(body.len() < expected.len());
+    let mut inflated = Vec::new();
+    flate2::read::GzDecoder::new(&body[..]).read_to_end(&mut inflated).unwrap();
+    assert_eq!(inflated, expected);
+
+    // read_message decompresses transparently
+    write_message(&mut gz.stream, find.to_string().as_bytes()).unwrap();
+    assert_eq!(read_message(&mut gz.stream).unwrap(), expected);
+
+    // Small responses stay uncompressed
+    write_message(&mut gz.stream, json!({"cmd": "ping"}).to_string().as_bytes()).unwrap();
+    let (flagged, body) = read_raw_frame(&mut gz.stream);
+    assert!(!flagged, "small response shouldn't be compressed");
+    assert_eq!(serde_json::from_slice::<Value>(&body).unwrap()["data"], "pong");
+
+    // Compressed requests are accepted too
+    let big_insert = json!({"cmd": "insert", "collection": "people", "doc": {"name": "zed", "bio": "x".repeat(10_000)}});
+    write_message_with(&mut gz.stream, big_insert.to_string().as_bytes(), Compression::Gzip).unwrap();
+    assert_ok(&serde_json::from_slice(&read_message(&mut gz.stream).unwrap()).unwrap());
+
+    let resp = gz.send(&json!({"cmd": "set_compression", "compression": "brotli"}));
+    assert_err(&resp);
+    let resp = gz.send(&json!({"cmd": "set_compression", "compression": "none"}));
+    assert_eq!(resp["data"]["compression"], "none", "{resp}");
+    write_message(&mut gz.stream, find.to_string().as_bytes()).unwrap();
+    assert!(!read_raw_frame(&mut gz.stream).0);
+}
+
+#[test]
+fn test_create_text_index() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    c.send(&json!({"cmd": "insert", "collection": "text", "doc": {"title": "hello", "body": "world"}}));
+
+    let resp = c.send(&json!({
+        "cmd": "create_text_index", "collection": "text", "fields": ["title", "body"]
+    }));
+    assert_ok(&resp);
+}
+
+#[test]
+fn test_create_vector_index_with_hnsw_params() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    c.send(&json!({"cmd": "insert", "collection": "vecs", "doc": {"name": "a", "embedding": [1.0, 0.0]}}));
+    let create = |extra: Value| {
+        let mut req = json!({"cmd": "create_vector_index", "collection": "vecs", "field": "embedding", "dimension": 2});
+        req.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        req
+    };
+
+    let resp = c.send(&create(json!({"m": 1})));
+    assert_eq!(resp["ok"], false);
+    assert!(resp["error"].as_str().unwrap().contains("m must be between"), "{resp}");
+    let resp = c.send(&create(json!({"m": 32, "ef_construction": 16})));
+    assert!(resp["error"].as_str().unwrap().contains("ef_construction"), "{resp}");
+    let resp = c.send(&create(json!({"m": "32"})));
+    assert_eq!(resp["error"], "'m' must be a non-negative integer");
+
+    assert_ok(&c.send(&create(json!({"m": 12, "ef_construction": 100}))));
+    let resp = c.send(&json!({"cmd": "list_indexes", "collection": "vecs"}));
+    let vector = resp["data"].as_array().unwrap().iter().find(|i| i["index_type"] == "vector").unwrap();
+    assert_eq!(vector["m"], 12);
+    assert_eq!(vector["ef_construction"], 100);
+}
+
+#[test]
+fn test_vector_search_exact() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    for (name, v) in [("far", [5.0, 5.0]), ("near", [1.0, 1.1]), ("mid", [2.0, 2.0])] {
+        c.send(&json!({"cmd": "insert", "collection": "vecs", "doc": {"name": name, "embedding": v}}));
+    }
+    assert_ok(&c.send(&json!({
+        "cmd": "create_vector_index", "collection": "vecs", "field": "embedding", "dimension": 2, "metric": "euclidean"
+    })));
+
+    let resp = c.send(&json!({
+        "cmd": "vector_search", "collection": "vecs", "field": "embedding", "vector": [1.0, 1.0], "limit": 2, "exact": true
+    }));
+    let names: Vec<&str> = resp["data"].as_array().unwrap().iter().map(|d| d["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["near", "mid"]);
+    let distance = resp["data"][0]["_distance"].as_f64().unwrap();
+    assert!((distance - 0.1).abs() < 1e-5, "{distance}");
+}
+
+#[test]
+fn test_create_vector_index_metric_and_normalize() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    for (name, v) in [("east", [10.0, 0.0]), ("northeast", [3.0, 2.0]), ("north", [0.0, 0.5])] {
+        c.send(&json!({"cmd": "insert", "collection": "vecs", "doc": {"name": name, "embedding": v}}));
+    }
+    let create = |metric: &str, normalize: Value| {
+        json!({
+            "cmd": "create_vector_index", "collection": "vecs", "field": "embedding", "dimension": 2,
+            "metric": metric, "normalize": normalize
+        })
+    };
+
+    let resp = c.send(&create("manhattan", json!(false)));
+    assert_eq!(resp["ok"], false);
+    assert_eq!(resp["error"], "unknown metric 'manhattan'; expected one of cosine, euclidean, dot_product");
+    let resp = c.send(&create("euclidean", json!(true)));
+    assert!(resp["error"].as_str().unwrap().contains("requires the cosine metric"), "{resp}");
+    let resp = c.send(&create("cosine", json!("yes")));
+    assert_eq!(resp["error"], "'normalize' must be a boolean");
+
+    assert_ok(&c.send(&create("cosine", json!(true))));
+    let resp = c.send(&json!({"cmd": "list_indexes", "collection": "vecs"}));
+    let vector = resp["data"].as_array().unwrap().iter().find(|i| i["index_type"] == "vector").unwrap();
+    assert_eq!(vector["normalize"], true);
+
+    // Magnitude doesn't matter, and documents keep the vectors they were inserted with
+    let resp = c.send(&json!({
+        "cmd": "vector_search", "collection": "vecs", "field": "embedding", "vector": [2.0, 0.1], "limit": 3
+    }));
+    let names: Vec<&str> = resp["data"].as_array().unwrap().iter().map(|d| d["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["east", "northeast", "north"]);
+    assert_eq!(resp["data"][0]["embedding"], json!([10.0, 0.0]));
+}
+
+// ===========================================================================
+// Text search
+// ===========================================================================
+
+#[test]
+fn test_text_search() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    // Insert docs and create text index
+    c.send(&json!({
+        "cmd": "insert", "collection": "articles",
+        "doc": {"title": "Rust programming", "body": "Systems language"}
+    }));
+    c.send(&json!({
+        "cmd": "insert", "collection": "articles",
+        "doc": {"title": "Python scripting", "body": "Dynamic language"}
+    }));
+    c.send(&json!({
+        "cmd": "create_text_index", "collection": "articles", "fields": ["title", "body"]
+    }));
+
+    // Allow time for background FTS indexing
+    std::thread::sleep(std::time::Duration::from_millis(500));
+
+    let resp = c.send(&json!({
+        "cmd": "text_search", "collection": "articles", "query": "Rust", "limit": 10
+    }));
+    assert_ok(&resp);
+}
+
+#[test]
+fn test_text_search_with_filter() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    for (title, status) in [("rust", "published"), ("rust guide", "draft"), ("rust database engine", "published")] {
+        c.send(&json!({"cmd": "insert", "collection": "articles", "doc": {"title": title, "status": status}}));
+    }
+    assert_ok(&c.send(&json!({"cmd": "create_text_index", "collection": "articles", "fields": ["title"]})));
+    assert_ok(&c.send(&json!({"cmd": "create_index", "collection": "articles", "field": "status"})));
+
+    let search = json!({
+        "cmd": "text_search", "collection": "articles", "query": "rust", "filter": {"status": "published"}
+    });
+    let resp = c.send(&search);
+    let titles: Vec<&str> = resp["data"].as_array().unwrap().iter().map(|d| d["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, ["rust", "rust database engine"]);
+
+    let mut count = search.clone();
+    count["count_only"] = json!(true);
+    assert_eq!(c.send(&count)["data"]["count"], 2);
+
+    let mut bad = search.clone();
+    bad["filter"] = json!({"status": {"$bogus": 1}});
+    assert_eq!(c.send(&bad)["ok"], false);
+}
+
+#[test]
+fn test_search_count_only() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    for (title, v) in [("rust guide", [1.0, 0.0]), ("rust news", [0.9, 0.3]), ("go guide", [0.0, 1.0])] {
+        c.send(&json!({"cmd": "insert", "collection": "docs", "doc": {"title": title, "embedding": v}}));
+    }
+    assert_ok(&c.send(&json!({"cmd": "create_text_index", "collection": "docs", "fields": ["title"]})));
+    assert_ok(&c.send(&json!({"cmd": "create_vector_index", "collection": "docs", "field": "embedding", "dimension": 2})));
+
+    for query in ["rust", "guide", "rust guide", "java"] {
+        let search = json!({"cmd": "text_search", "collection": "docs", "query": query, "limit": 10});
+        let hits = c.send(&search)["data"].as_array().unwrap().len();
+        let mut count = search.clone();
+        count["count_only"] = json!(true);
+        assert_eq!(c.send(&count)["data"]["count"], hits, "{query}");
+    }
+
+    let search = json!({
+        "cmd": "vector_search", "collection": "docs", "field": "embedding", "vector": [1.0, 0.1], "min_similarity": 0.9
+    });
+    let hits = c.send(&search)["data"].as_array().unwrap().len();
+    assert_eq!(hits, 2);
+    let mut count = search.clone();
+    count["count_only"] = json!(true);
+    assert_eq!(c.send(&count)["data"]["count"], 2);
+
+    count["min_similarity"] = json!("high");
+    assert_eq!(c.send(&count)["error"], "'min_similarity' must be a number");
+}
+
+// ===========================================================================
+// Aggregate
+// ===========================================================================
+
+#[test]
+fn test_aggregate() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    c.send(&json!({"cmd": "insert", "collection": "sales", "doc": {"product": "A", "amount": 10}}));
+    c.send(&json!({"cmd": "insert", "collection": "sales", "doc": {"product": "B", "amount": 20}}));
+    c.send(&json!({"cmd": "insert", "collection": "sales", "doc": {"product": "A", "amount": 30}}));
+
+    let resp = c.send(&json!({
+        "cmd": "aggregate",
+        "collection": "sales",
+        "pipeline": [
+            {"$group": {"_id": "$product", "total": {"$sum": "$amount"}}},
+            {"$sort": {"_id": 1}}
+        ]
+    }));
+    assert_ok(&resp);
+    let data = resp["data"].as_array().unwrap();
+    assert_eq!(data.len(), 2);
+}
+
+// ===========================================================================
+// Blob storage
+// ===========================================================================
+
+#[test]
+fn test_blob_crud() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    // Create bucket
+    let resp = c.send(&json!({"cmd": "create_bucket", "bucket": "files"}));
+    assert_ok(&resp);
+
+    // List buckets
+    let resp = c.send(&json!({"cmd": "list_buckets"}));
+    assert_ok(&resp);
+    let buckets = resp["data"].as_array().unwrap();
+    assert!(buckets.iter().any(|v| v == "files"));
+
+    // Put object (base64 of "hello world")
+    let data_b64 = base64_encode(b"hello world");
+    let resp = c.send(&json!({
+        "cmd": "put_object",
+        "bucket": "files",
+        "key": "greeting.txt",
+        "data": data_b64,
+        "content_type": "text/plain",
+        "metadata": {"author": "test"}
+    }));
+    assert_ok(&resp);
+
+    // Get object
+    let resp = c.send(&json!({
+        "cmd": "get_object", "bucket": "files", "key": "greeting.txt"
+    }));
+    assert_ok(&resp);
+    assert!(resp["data"]["content"].is_string());
+
+    // Head object
+    let resp = c.send(&json!({
+        "cmd": "head_object", "bucket": "files", "key": "greeting.txt"
+    }));
+    assert_ok(&resp);
+
+    // List objects
+    let resp = c.send(&json!({
+        "cmd": "list_objects", "bucket": "files"
+    }));
+    assert_ok(&resp);
+
+    // Delete object
+    let resp = c.send(&json!({
+        "cmd": "delete_object", "bucket": "files", "key": "greeting.txt"
+    }));
+    assert_ok(&resp);
+
+    // Delete bucket
+    let resp = c.send(&json!({"cmd": "delete_bucket", "bucket": "files"}));
+    assert_ok(&resp);
+}
+
+#[test]
+fn test_multipart_upload() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    let resp = c.send(&json!({
+        "cmd": "create_multipart_upload",
+        "bucket": "files",
+        "key": "big.txt",
+        "content_type": "text/plain"
+    }));
+    assert_ok(&resp);
+    let upload_id = resp["data"]["upload_id"].as_str().unwrap().to_string();
+
+    for (n, part) in [(2, "world"), (1, "hello ")] {
+        let resp = c.send(&json!({
+            "cmd": "upload_part",
+            "upload_id": upload_id,
+            "part_number": n,
+            "data": base64_encode(part.as_bytes())
+        }));
+        assert_ok(&resp);
+    }
+
+    let resp = c.send(&json!({"cmd": "complete_multipart_upload", "upload_id": upload_id}));
+    assert_ok(&resp);
+    assert_eq!(resp["data"]["size"], 11);
+
+    let resp = c.send(&json!({"cmd": "get_object", "bucket": "files", "key": "big.txt"}));
+    assert_ok(&resp);
+    assert_eq!(resp["data"]["content"], base64_encode(b"hello world"));
+
+    // Aborting a finished upload fails
+    let resp = c.send(&json!({"cmd": "abort_multipart_upload", "upload_id": upload_id}));
+    assert_err(&resp);
+}
+
+#[test]
+fn test_object_checksum() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+    let sha256 = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+    let resp = c.send(&json!({"cmd": "put_object", "bucket": "files", "key": "a.txt", "data": base64_encode(b"hello world")}));
+    assert_eq!(resp["data"]["sha256"], sha256);
+    let resp = c.send(&json!({"cmd": "head_object", "bucket": "files", "key": "a.txt"}));
+    assert_eq!(resp["data"]["sha256"], sha256);
+    let resp = c.send(&json!({"cmd": "verify_object", "bucket": "files", "key": "a.txt"}));
+    assert_eq!(resp["data"], json!({"bucket": "files", "key": "a.txt", "verified": true, "sha256": sha256}));
+    let get = json!({"cmd": "get_object", "bucket": "files", "key": "a.txt", "verify": true});
+    assert_eq!(c.send(&get)["data"]["content"], base64_encode(b"hello world"));
+
+    // Corrupt the stored bytes: plain reads still succeed, verified ones fail
+    std::fs::write(server.data_dir.join("_blobs/files/0.data"), b"hello w0rld").unwrap();
+    let resp = c.send(&json!({"cmd": "get_object", "bucket": "files", "key": "a.txt"}));
+    assert_eq!(resp["data"]["content"], base64_encode(b"hello w0rld"));
+    let resp = c.send(&json!({"cmd": "verify_object", "bucket": "files", "key": "a.txt"}));
+    assert_eq!(resp["ok"], false);
+    assert_eq!(resp["code"], "checksum_mismatch");
+    assert_eq!(c.send(&get)["code"], "checksum_mismatch");
+
+    let resp = c.send(&json!({"cmd": "get_object", "bucket": "files", "key": "a.txt", "verify": true, "version_id": "1"}));
+    assert_eq!(resp["error"], "'verify' can't be combined with 'version_id'");
+}
+
+#[test]
+fn test_get_object_range() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    let resp = c.send(&json!({
+        "cmd": "put_object", "bucket": "files", "key": "digits.txt",
+        "data": base64_encode(b"0123456789")
+    }));
+    assert_ok(&resp);
+
+    let resp = c.send(&json!({
+        "cmd": "get_object_range", "bucket": "files", "key": "digits.txt", "start": 2, "end": 5
+    }));
+    assert_ok(&resp);
+    assert_eq!(resp["data"]["content"], base64_encode(b"2345"));
+    assert_eq!(resp["data"]["content_range"], "bytes 2-5/10");
+
+    let resp = c.send(&json!({
+        "cmd": "get_object_range", "bucket": "files", "key": "digits.txt", "suffix": 3
+    }));
+    assert_ok(&resp);
+    assert_eq!(resp["data"]["content"], base64_encode(b"789"));
+    assert_eq!(resp["data"]["content_range"], "bytes 7-9/10");
+
+    let resp = c.send(&json!({
+        "cmd": "get_object_range", "bucket": "files", "key": "digits.txt", "start": 10
+    }));
+    assert_err(&resp);
+}
+
+#[test]
+fn test_object_versioning() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    let resp = c.send(&json!({"cmd": "enable_versioning", "bucket": "docs"}));
+    assert_ok(&resp);
+    assert_eq!(resp["data"]["versioning"], true);
+
+    let resp = c.send(&json!({
+        "cmd": "put_object", "bucket": "docs", "key": "a.txt", "data": base64_encode(b"v1")
+    }));
+    assert_ok(&resp);
+    let v1 = resp["data"]["version_id"].as_str().unwrap().to_string();
+    let resp = c.send(&json!({
+        "cmd": "put_object", "bucket": "docs", "key": "a.txt", "data": base64_encode(b"v2")
+    }));
+    assert_ok(&resp);
+
+    let resp = c.send(&json!({"cmd": "delete_object", "bucket": "docs", "key": "a.txt"}));
+    assert_ok(&resp);
+    let resp = c.send(&json!({"cmd": "get_object", "bucket": "docs", "key": "a.txt"}));
+    assert_err(&resp);
+
+    let resp = c.send(&json!({
+        "cmd": "get_object", "bucket": "docs", "key": "a.txt", "version_id": v1
+    }));
+    assert_ok(&resp);
+    assert_eq!(resp["data"]["content"], base64_encode(b"v1"));
+
+    let resp = c.send(&json!({"cmd": "list_object_versions", "bucket": "docs", "key": "a.txt"}));
+    assert_ok(&resp);
+    let versions = resp["data"].as_array().unwrap();
+    assert_eq!(versions.len(), 3);
+    assert_eq!(versions[0]["delete_marker"], true);
+    assert_eq!(versions[2]["version_id"], v1);
+}
+
+#[test]
+fn test_expired_object_is_absent() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    let resp = c.send(&json!({
+        "cmd": "put_object", "bucket": "tmp", "key": "old.txt", "data": base64_encode(b"x"),
+        "metadata": {"expires_at": "2000-01-01T00:00:00Z"}
+    }));
+    assert_ok(&resp);
+    assert_err(&c.send(&json!({"cmd": "get_object", "bucket": "tmp", "key": "old.txt"})));
+    assert_err(&c.send(&json!({"cmd": "head_object", "bucket": "tmp", "key": "old.txt"})));
+    let resp = c.send(&json!({"cmd": "list_objects", "bucket": "tmp"}));
+    assert_ok(&resp);
+    assert!(resp["data"].as_array().unwrap().is_empty());
+
+    let resp = c.send(&json!({
+        "cmd": "put_object", "bucket": "tmp", "key": "bad.txt", "data": base64_encode(b"x"),
+        "metadata": {"expires_at": "soon"}
+    }));
+    assert_err(&resp);
+}
+
+#[test]
+fn test_copy_object() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    let resp = c.send(&json!({
+        "cmd": "put_object", "bucket": "src", "key": "a.txt", "data": base64_encode(b"hello"),
+        "content_type": "text/plain", "metadata": {"author": "alice"}
+    }));
+    assert_ok(&resp);
+
+    let resp = c.send(&json!({
+        "cmd": "copy_object", "src_bucket": "src", "src_key": "a.txt",
+        "dst_bucket": "dst", "dst_key": "b.txt"
+    }));
+    assert_ok(&resp);
+    assert_eq!(resp["data"]["bucket"], "dst");
+    assert_eq!(resp["data"]["metadata"]["author"], "alice");
+
+    let resp = c.send(&json!({"cmd": "get_object", "bucket": "dst", "key": "b.txt"}));
+    assert_ok(&resp);
+    assert_eq!(resp["data"]["content"], base64_encode(b"hello"));
+    assert_eq!(resp["data"]["metadata"]["content_type"], "text/plain");
+
+    // dst_bucket defaults to the source bucket; metadata can be replaced
+    let resp = c.send(&json!({
+        "cmd": "copy_object", "src_bucket": "src", "src_key": "a.txt", "dst_key": "c.txt",
+        "metadata": {"author": "bob"}
+    }));
+    assert_ok(&resp);
+    assert_eq!(resp["data"]["bucket"], "src");
+    assert_eq!(resp["data"]["metadata"]["author"], "bob");
+
+    let resp = c.send(&json!({
+        "cmd": "copy_object", "src_bucket": "src", "src_key": "missing", "dst_key": "x"
+    }));
+    assert_err(&resp);
+}
+
+#[test]
+fn test_collection_backup_and_restore() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+    let backups = TempDir::new().unwrap();
+    let archive = backups.path().join("docs.tar.gz");
+    let archive = archive.to_str().unwrap();
+
+    for i in 0..5 {
+        assert_ok(&c.send(&json!({"cmd": "insert", "collection": "docs", "doc": {"n": i}})));
+    }
+    assert_ok(&c.send(&json!({"cmd": "create_index", "collection": "docs", "field": "n"})));
+
+    let resp = c.send(&json!({"cmd": "backup_collection", "collection": "docs", "path": archive}));
+    assert_ok(&resp);
+    assert_eq!(resp["data"]["collection"], "docs");
+
+    assert_ok(&c.send(&json!({"cmd": "drop_collection", "collection": "docs"})));
+    let resp = c.send(&json!({"cmd": "count", "collection": "docs"}));
+    assert_eq!(resp["data"]["count"], 0);
+
+    let resp = c.send(&json!({"cmd": "restore_collection", "collection": "docs", "archive": archive}));
+    assert_ok(&resp);
+    let resp = c.send(&json!({"cmd": "count", "collection": "docs"}));
+    assert_eq!(resp["data"]["count"], 5);
+    let resp = c.send(&json!({"cmd": "list_indexes", "collection": "docs"}));
+    assert!(resp["data"].as_array().unwrap().iter().any(|i| i["name"] == "n"));
+
+    let resp = c.send(&json!({"cmd": "restore_collection", "collection": "docs"}));
+    assert_eq!(resp["ok"], false);
+}
+
+// ===========================================================================
+// Schedule run history
+// ===========================================================================
+
+#[test]
+fn test_schedule_history() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    let resp = c.send(&json!({"cmd": "schedule_history", "name": "nightly"}));
+    assert_err(&resp);
+
+    // Runs are recorded by the scheduler thread; seed them directly
+    let sched = json!({"name": "nightly", "procedure": "p", "every": "1h", "enabled": false});
+    assert_ok(&c.send(&json!({"cmd": "insert", "collection": "_schedules", "doc": sched})));
+    for (epoch, status) in [(100, "ok"), (200, "error"), (300, "ok")] {
+        let run = json!({"schedule": "nightly", "epoch": epoch, "status": status, "duration_ms": 5});
+        assert_ok(&c.send(&json!({"cmd": "insert", "collection": "_schedule_runs", "doc": run})));
+    }
+
+    let resp = c.send(&json!({"cmd": "schedule_history", "name": "nightly", "limit": 2}));
+    assert_ok(&resp);
+    let runs = resp["data"].as_array().unwrap();
+    assert_eq!(runs.len(), 2);
+    assert_eq!(runs[0]["epoch"], 300);
+    assert_eq!(runs[1]["status"], "error");
+
+    assert_ok(&c.send(&json!({"cmd": "delete_schedule", "name": "nightly"})));
+    let resp = c.send(&json!({"cmd": "count", "collection": "_schedule_runs"}));
+    assert_eq!(resp["data"]["count"], 0);
+}
+
+#[test]
+fn test_run_schedule() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    let resp = c.send(&json!({"cmd": "run_schedule", "name": "adhoc"}));
+    assert_err(&resp);
+
+    assert_ok(&c.send(&json!({
+        "cmd": "create_procedure", "name": "greet", "params": ["who"],
+        "steps": [{"type": "return", "value": {"hello": "$param.who"}}]
+    })));
+    // Disabled, so only an explicit run executes it
+    assert_ok(&c.send(&json!({
+        "cmd": "create_schedule", "name": "adhoc", "procedure": "greet",
+        "every": "1h", "enabled": false, "params": {"who": "world"}
+    })));
+
+    for expected in [1, 2] {
+        assert_ok(&c.send(&json!({"cmd": "run_schedule", "name": "adhoc"})));
+        let resp = c.send(&json!({"cmd": "get_schedule", "name": "adhoc"}));
+        assert_eq!(resp["data"]["run_count"], expected);
+        assert_eq!(resp["data"]["last_status"], "ok");
+    }
+    let resp = c.send(&json!({"cmd": "schedule_history", "name": "adhoc"}));
+    assert_eq!(resp["data"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn test_sql_params() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    for name in ["Alice", "O'Brien"] {
+        assert_ok(&c.send(&json!({"cmd": "insert", "collection": "people", "doc": {"name": name}})));
+    }
+    let resp = c.send(&json!({
+        "cmd": "sql", "query": "SELECT name FROM people WHERE name = ?", "params": ["O'Brien"]
+    }));
+    assert_ok(&resp);
+    assert_eq!(resp["data"][0]["name"], "O'Brien");
+
+    let resp = c.send(&json!({
+        "cmd": "sql", "query": "SELECT * FROM people WHERE name = :n", "params": {"n": "x' OR '1'='1"}
+    }));
+    assert_ok(&resp);
+    assert_eq!(resp["data"].as_array().unwrap().len(), 0);
+
+    let resp = c.send(&json!({"cmd": "sql", "query": "SELECT * FROM people WHERE name = ?", "params": []}));
+    assert_err(&resp);
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD.encode(data)
+}
+
+// ===========================================================================
+// FTS (blob-level search)
+// ===========================================================================
+
+#[test]
+fn test_search_command() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    // search on empty DB should succeed
+    let resp = c.send(&json!({
+        "cmd": "search", "query": "test", "limit": 5
+    }));
+    assert_ok(&resp);
+}
+
+#[test]
+fn test_search_offset() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    for i in 0..6 {
+        let text = format!("invoice {}", vec!["line"; i].join(" "));
+        c.send(&json!({
+            "cmd": "put_object", "bucket": "docs", "key": format!("inv{i}.txt"),
+            "data": base64_encode(text.as_bytes()), "content_type": "text/plain"
+        }));
+    }
+    let search = |c: &mut Client, offset: u64, limit: u64| -> Vec<String> {
+        let resp = c.send(&json!({"cmd": "search", "query": "invoice", "offset": offset, "limit": limit}));
+        resp["data"].as_array().unwrap().iter().map(|r| r["key"].as_str().unwrap().to_string()).collect()
+    };
+    // Indexing happens on the background FTS worker
+    for _ in 0..50 {
+        if search(&mut c, 0, 10).len() == 6 {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let all = search(&mut c, 0, 6);
+    assert_eq!(all, ["inv0.txt", "inv1.txt", "inv2.txt", "inv3.txt", "inv4.txt", "inv5.txt"]);
+    let mut paged = search(&mut c, 0, 3);
+    paged.extend(search(&mut c, 3, 3));
+    assert_eq!(paged, all);
+    assert!(search(&mut c, 6, 3).is_empty());
+}
+
+#[test]
+fn test_bucket_acl() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    let resp = c.send(&json!({"cmd": "get_bucket_acl", "bucket": "payroll"}));
+    assert_eq!(resp["data"], json!({"bucket": "payroll", "acl": null}));
+    let resp = c.send(&json!({"cmd": "set_bucket_acl", "bucket": "payroll", "acl": {"readers": ["bob"]}}));
+    assert_eq!(resp["error"], "'acl' must be null or an object with 'owner', 'readers' and 'writers'");
+    let resp = c.send(&json!({"cmd": "set_bucket_acl", "bucket": "payroll"}));
+    assert_eq!(resp["error"], "missing 'acl'");
+
+    let acl = json!({"owner": "alice", "readers": ["bob"], "writers": []});
+    let resp = c.send(&json!({"cmd": "set_bucket_acl", "bucket": "payroll", "acl": {"owner": "alice", "readers": ["bob"]}}));
+    assert_eq!(resp["data"], json!({"bucket": "payroll", "acl": acl}));
+    assert_eq!(c.send(&json!({"cmd": "get_bucket_acl", "bucket": "payroll"}))["data"]["acl"], acl);
+    assert_eq!(c.send(&json!({"cmd": "list_buckets"}))["data"], json!(["payroll"]));
+
+    assert_ok(&c.send(&json!({"cmd": "set_bucket_acl", "bucket": "payroll", "acl": null})));
+    assert_eq!(c.send(&json!({"cmd": "get_bucket_acl", "bucket": "payroll"}))["data"]["acl"], Value::Null);
+}
+
+#[test]
+fn test_bucket_tokenizer() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    let resp = c.send(&json!({"cmd": "get_bucket_tokenizer", "bucket": "notes"}));
+    assert_eq!(resp["data"]["tokenizer"], "default");
+    let resp = c.send(&json!({"cmd": "set_bucket_tokenizer", "bucket": "notes", "tokenizer": "stemmed"}));
+    assert_eq!(resp["error"], "unknown tokenizer 'stemmed'; expected one of default, whitespace, cjk_bigram, code");
+
+    let resp = c.send(&json!({"cmd": "set_bucket_tokenizer", "bucket": "notes", "tokenizer": "cjk_bigram"}));
+    assert_eq!(resp["data"], json!({"bucket": "notes", "tokenizer": "cjk_bigram", "not_reindexed": []}));
+    let resp = c.send(&json!({"cmd": "get_bucket_tokenizer", "bucket": "notes"}));
+    assert_eq!(resp["data"]["tokenizer"], "cjk_bigram");
+
+    let data = base64_encode("分布式数据库管理系统".as_bytes());
+    assert_ok(&c.send(&json!({
+        "cmd": "put_object", "bucket": "notes", "key": "cn.txt", "data": data, "content_type": "text/plain"
+    })));
+    let search = json!({"cmd": "search", "bucket": "notes", "query": "数据库"});
+    let mut hits = Vec::new();
+    for _ in 0..50 {
+        hits = c.send(&search)["data"].as_array().unwrap().clone();
+        if !hits.is_empty() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["key"], "cn.txt");
+}
+
+// ===========================================================================
+// Error handling
+// ===========================================================================
+
+#[test]
+fn test_unknown_command() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    let resp = c.send(&json!({"cmd": "nonexistent_cmd"}));
+    assert_err(&resp);
+    assert!(resp["error"].as_str().unwrap().contains("unknown command"));
+}
+
+#[test]
+fn test_missing_cmd_field() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    let resp = c.send(&json!({"not_cmd": "insert"}));
+    assert_err(&resp);
+}
+
+#[test]
+fn test_missing_collection() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    let resp = c.send(&json!({"cmd": "insert", "doc": {"x": 1}}));
+    assert_err(&resp);
+}
+
+#[test]
+fn test_missing_doc() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    let resp = c.send(&json!({"cmd": "insert", "collection": "test"}));
+    assert_err(&resp);
+}
+
+#[test]
+fn test_find_with_sort_skip_limit() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    for i in 0..10 {
+        c.send(&json!({
+            "cmd": "insert", "collection": "sorted",
             "doc": {"idx": i}
         }));
     }
@@ -583,6 +1802,162 @@ fn test_find_with_sort_skip_limit() {
     assert_eq!(data.len(), 3);
 }
 
+// ===========================================================================
+// explain
+// ===========================================================================
+
+#[test]
+fn test_explain() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    for i in 0..10 {
+        c.send(&json!({
+            "cmd": "insert", "collection": "plans",
+            "doc": {"idx": i, "tag": format!("t{}", i % 3)}
+        }));
+    }
+    assert_ok(&c.send(&json!({"cmd": "create_index", "collection": "plans", "field": "tag"})));
+
+    let resp = c.send(&json!({
+        "cmd": "explain", "collection": "plans",
+        "query": {"tag": "t0"}
+    }));
+    assert_ok(&resp);
+    assert_eq!(resp["data"]["stage"], "index");
+    assert_eq!(resp["data"]["indexes"][0]["name"], "tag");
+    assert_eq!(resp["data"]["n_returned"], 4);
+
+    let resp = c.send(&json!({
+        "cmd": "explain", "collection": "plans",
+        "query": {"idx": {"$lt": 5}}
+    }));
+    assert_ok(&resp);
+    assert_eq!(resp["data"]["stage"], "collection scan");
+    assert_eq!(resp["data"]["docs_examined"], 10);
+    assert_eq!(resp["data"]["n_returned"], 5);
+
+    let resp = c.send(&json!({"cmd": "explain", "query": {}}));
+    assert_err(&resp);
+
+    // A hint overrides the planner, for explain and find alike
+    let resp = c.send(&json!({
+        "cmd": "explain", "collection": "plans",
+        "query": {"tag": "t0"}, "hint": {"$natural": 1}
+    }));
+    assert_eq!(resp["data"]["stage"], "collection scan");
+    assert_eq!(resp["data"]["n_returned"], 4);
+
+    let resp = c.send(&json!({
+        "cmd": "find", "collection": "plans",
+        "query": {"tag": "t0", "idx": {"$gt": 0}}, "hint": "tag"
+    }));
+    assert_eq!(resp["data"].as_array().unwrap().len(), 3);
+
+    let resp = c.send(&json!({"cmd": "find", "collection": "plans", "query": {}, "hint": "nope"}));
+    assert_err(&resp);
+    assert_eq!(resp["code"], "index_not_found");
+}
+
+// ===========================================================================
+// Metrics
+// ===========================================================================
+
+#[test]
+fn test_metrics() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    let resp = c.send(&json!({"cmd": "metrics"}));
+    assert_ok(&resp);
+    let text = resp["data"].as_str().unwrap();
+    for name in [
+        "# TYPE oxidb_commands_total counter",
+        "# TYPE oxidb_command_errors_total counter",
+        "# TYPE oxidb_active_transactions gauge",
+        "# TYPE oxidb_collections gauge",
+    ] {
+        assert!(text.contains(name), "missing {name:?} in:\n{text}");
+    }
+    assert!(text.contains("oxidb_active_transactions 0\n"));
+
+    assert_ok(&c.send(&json!({"cmd": "insert", "collection": "events", "doc": {"kind": "a"}})));
+    assert_ok(&c.send(&json!({"cmd": "insert", "collection": "events", "doc": {"kind": "b"}})));
+    assert_err(&c.send(&json!({"cmd": "insert", "collection": "events"})));
+    assert_ok(&c.send(&json!({"cmd": "find", "collection": "events", "query": {"kind": "a"}})));
+    assert_ok(&c.send(&json!({"cmd": "begin_tx"})));
+
+    let resp = c.send(&json!({"cmd": "metrics"}));
+    let text = resp["data"].as_str().unwrap();
+    assert!(text.contains("oxidb_commands_total{command=\"insert\"} 3\n"), "{text}");
+    assert!(text.contains("oxidb_command_errors_total{command=\"insert\"} 1\n"), "{text}");
+    assert!(text.contains("oxidb_commands_total{command=\"find\"} 1\n"), "{text}");
+    assert!(text.contains("oxidb_command_errors_total{command=\"find\"} 0\n"), "{text}");
+    assert!(text.contains("oxidb_commands_total{command=\"metrics\"} 1\n"), "{text}");
+    assert!(text.contains("oxidb_active_transactions 1\n"), "{text}");
+    assert!(text.contains("oxidb_collections 1\n"), "{text}");
+}
+
+#[test]
+fn test_metrics_http_endpoint() {
+    use std::io::{Read, Write};
+
+    let dir = TempDir::new().unwrap();
+    let db = Arc::new(OxiDb::open(dir.path()).unwrap());
+    db.metrics().record_command("ping", false);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || oxidb_server::metrics_http::serve(listener, db));
+
+    let get = |request: &str| {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).unwrap();
+        resp
+    };
+
+    let resp = get("GET /metrics HTTP/1.1\r\nHost: localhost\r\nAccept: text/plain\r\n\r\n");
+    assert!(resp.starts_with("HTTP/1.1 200 OK\r\n"), "{resp}");
+    assert!(resp.contains("Content-Type: text/plain; version=0.0.4\r\n"));
+    let body = resp.split_once("\r\n\r\n").unwrap().1;
+    assert!(body.contains("oxidb_commands_total{command=\"ping\"} 1\n"), "{body}");
+    assert!(resp.contains(&format!("Content-Length: {}\r\n", body.len())));
+
+    assert!(get("GET / HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    assert!(get("POST /metrics HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+}
+
+// ===========================================================================
+// Health probes
+// ===========================================================================
+
+#[test]
+fn test_healthz_and_readyz() {
+    let server = TestServer::start();
+    let mut c = Client::connect(server.addr);
+
+    let resp = c.send(&json!({"cmd": "healthz"}));
+    assert_ok(&resp);
+    assert_eq!(resp["data"], json!({"status": "ok"}));
+    let resp = c.send(&json!({"cmd": "readyz"}));
+    assert_ok(&resp);
+    assert_eq!(resp["data"], json!({"status": "ready"}));
+
+    // The data directory stops accepting writes (root ignores permission
+    // bits, so replace it with a file)
+    std::fs::remove_dir_all(&server.data_dir).unwrap();
+    std::fs::write(&server.data_dir, b"").unwrap();
+    let resp = c.send(&json!({"cmd": "readyz"}));
+    assert_err(&resp);
+    assert_eq!(resp["code"], "not_ready");
+    assert!(resp["error"].as_str().unwrap().starts_with("data directory not writable"), "{resp}");
+
+    // Liveness doesn't depend on storage
+    assert_eq!(c.send(&json!({"cmd": "healthz"}))["data"], json!({"status": "ok"}));
+    std::fs::remove_file(&server.data_dir).unwrap();
+}
+
 // ===========================================================================
 // Crash recovery: commit survives restart
 // ===========================================================================
@@ -829,8 +2204,9 @@ fn test_rbac_read_permissions() {
 
     // Allowed
     let allowed = [
-        "ping", "find", "find_one", "count", "aggregate",
-        "list_collections", "list_buckets", "get_object", "head_object", "search",
+        "ping", "find", "find_one", "explain", "count", "aggregate",
+        "list_collections", "list_buckets", "get_object", "get_object_range", "list_object_versions",
+        "head_object", "search", "metrics",
     ];
     for cmd in allowed {
         assert!(is_permitted(Role::Read, cmd), "Read should permit: {cmd}");
@@ -839,7 +2215,7 @@ fn test_rbac_read_permissions() {
     // Denied
     let denied = [
         "insert", "update", "delete", "create_index",
-        "create_user", "drop_collection", "put_object",
+        "create_user", "drop_collection", "put_object", "enable_versioning",
     ];
     for cmd in denied {
         assert!(!is_permitted(Role::Read, cmd), "Read should deny: {cmd}");
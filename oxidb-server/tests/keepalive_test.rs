@@ -0,0 +1,153 @@
+//! Keepalive: a server loop built like main.rs's, with a 100ms heartbeat
+//! interval and three missed intervals allowed, in request and watch mode.
+
+use std::io::Read;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+use tempfile::TempDir;
+
+use oxidb::{OxiDb, WatchFilter};
+use oxidb_server::keepalive::Keepalive;
+use oxidb_server::protocol::{read_message, write_message};
+use oxidb_server::watch::{self, WatchExit};
+
+const INTERVAL: Duration = Duration::from_millis(100);
+
+fn keepalive() -> Keepalive {
+    Keepalive { interval: Some(INTERVAL), max_missed: 3 }
+}
+
+fn start_server(db: Arc<OxiDb>) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let db = Arc::clone(&db);
+            std::thread::spawn(move || handle_client(stream.unwrap(), &db));
+        }
+    });
+    addr
+}
+
+fn handle_client(mut stream: TcpStream, db: &Arc<OxiDb>) {
+    let keepalive = keepalive();
+    let read_timeout = keepalive.read_timeout(Duration::from_secs(30));
+    stream.set_read_timeout(read_timeout).unwrap();
+    let mut active_tx: Option<u64> = None;
+    while let Ok(msg) = read_message(&mut stream) {
+        let request: Value = serde_json::from_slice(&msg).unwrap();
+        if request["cmd"] == "watch" {
+            let handle = db.watch(WatchFilter::All, None).unwrap();
+            write_message(&mut stream, &oxidb_server::handler::ok_bytes(json!("watching"))).unwrap();
+            stream.set_read_timeout(Some(Duration::from_millis(20))).unwrap();
+            let exit = watch::watch_single_with_keepalive(&mut stream, db, handle, keepalive.limit()).unwrap();
+            stream.set_read_timeout(read_timeout).unwrap();
+            if exit == WatchExit::Unwatched {
+                continue;
+            }
+            break;
+        }
+        let resp = oxidb_server::handler::handle_request(db, request, &mut active_tx);
+        if write_message(&mut stream, &resp).is_err() {
+            break;
+        }
+    }
+}
+
+fn connect(addr: SocketAddr) -> TcpStream {
+    let stream = TcpStream::connect(addr).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream
+}
+
+fn send(stream: &mut TcpStream, request: Value) {
+    write_message(stream, request.to_string().as_bytes()).unwrap();
+}
+
+fn recv(stream: &mut TcpStream) -> Value {
+    serde_json::from_slice(&read_message(stream).unwrap()).unwrap()
+}
+
+/// Wait for the server to close the connection; returns how long it took.
+fn wait_closed(stream: &mut TcpStream) -> Duration {
+    let start = Instant::now();
+    assert_eq!(stream.read(&mut [0u8; 1]).unwrap(), 0, "connection should be closed");
+    start.elapsed()
+}
+
+#[test]
+fn silent_client_is_disconnected_after_missed_heartbeats() {
+    let dir = TempDir::new().unwrap();
+    let addr = start_server(Arc::new(OxiDb::open(dir.path()).unwrap()));
+
+    let mut client = connect(addr);
+    send(&mut client, json!({"cmd": "ping"}));
+    assert_eq!(recv(&mut client)["data"], "pong");
+    let waited = wait_closed(&mut client);
+    assert!(waited >= INTERVAL * 2, "closed too early: {waited:?}");
+    assert!(waited < Duration::from_secs(2), "closed too late: {waited:?}");
+}
+
+#[test]
+fn heartbeating_client_is_retained() {
+    let dir = TempDir::new().unwrap();
+    let addr = start_server(Arc::new(OxiDb::open(dir.path()).unwrap()));
+
+    let mut client = connect(addr);
+    let start = Instant::now();
+    while start.elapsed() < INTERVAL * 8 {
+        send(&mut client, json!({"cmd": "ping"}));
+        assert_eq!(recv(&mut client)["data"], "pong");
+        std::thread::sleep(INTERVAL);
+    }
+    send(&mut client, json!({"cmd": "insert", "collection": "beats", "doc": {"name": "still here"}}));
+    assert_eq!(recv(&mut client)["ok"], true);
+}
+
+#[test]
+fn watch_connections_heartbeat_with_ping() {
+    let dir = TempDir::new().unwrap();
+    let db = Arc::new(OxiDb::open(dir.path()).unwrap());
+    let addr = start_server(Arc::clone(&db));
+
+    // Pinging keeps a watch alive and doesn't interrupt events
+    let mut client = connect(addr);
+    send(&mut client, json!({"cmd": "watch"}));
+    assert_eq!(recv(&mut client)["data"], "watching");
+    for _ in 0..6 {
+        std::thread::sleep(INTERVAL);
+        send(&mut client, json!({"cmd": "ping"}));
+        assert_eq!(recv(&mut client)["data"], "pong");
+    }
+    db.insert("orders", json!({"item": "Widget"})).unwrap();
+    assert_eq!(recv(&mut client)["event"], "change");
+    send(&mut client, json!({"cmd": "unwatch"}));
+    assert_eq!(recv(&mut client)["data"], "unwatched");
+
+    // A watcher that goes quiet is dropped, though events still flow to it
+    let mut silent = connect(addr);
+    send(&mut silent, json!({"cmd": "watch"}));
+    assert_eq!(recv(&mut silent)["data"], "watching");
+    db.insert("orders", json!({"item": "Gadget"})).unwrap();
+    assert_eq!(recv(&mut silent)["event"], "change");
+    wait_closed(&mut silent);
+}
+
+#[test]
+fn read_timeout_combines_idle_timeout_and_keepalive() {
+    let off = Keepalive::default();
+    assert_eq!(off.limit(), None);
+    assert_eq!(off.read_timeout(Duration::from_secs(30)), Some(Duration::from_secs(30)));
+    assert_eq!(off.read_timeout(Duration::ZERO), None);
+
+    let on = Keepalive { interval: Some(Duration::from_secs(5)), max_missed: 3 };
+    assert_eq!(on.limit(), Some(Duration::from_secs(15)));
+    assert_eq!(on.read_timeout(Duration::from_secs(30)), Some(Duration::from_secs(15)));
+    assert_eq!(on.read_timeout(Duration::from_secs(10)), Some(Duration::from_secs(10)));
+    assert_eq!(on.read_timeout(Duration::ZERO), Some(Duration::from_secs(15)));
+    assert!(on.expired_by(Some(Duration::from_secs(15))));
+    assert!(!on.expired_by(Some(Duration::from_secs(10))));
+}
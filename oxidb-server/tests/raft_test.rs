@@ -1,7 +1,7 @@
 #![cfg(feature = "cluster")]
 
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use openraft::storage::Adaptor;
@@ -14,9 +14,14 @@ use tokio::task::JoinHandle;
 use tokio::time::sleep;
 
 use oxidb_server::async_server::{self, ServerState};
+use oxidb_server::auth::{Role, UserStore};
 use oxidb_server::raft::log_store::OxiDbStore;
+use oxidb_server::raft::management;
 use oxidb_server::raft::network::{self, OxiDbNetworkFactory};
 use oxidb_server::raft::types::OxiRaft;
+use oxidb_server::session::SessionLimits;
+use oxidb_server::slowlog::SlowQueryLog;
+use oxidb_server::tls::ClientCertRoles;
 
 // ---------------------------------------------------------------------------
 // AsyncClient — lightweight async TCP client using length-prefixed JSON
@@ -106,6 +111,7 @@ async fn start_node(
     client_addr: SocketAddr,
     raft_addr: SocketAddr,
     data_dir: &std::path::Path,
+    user_store: Option<Arc<Mutex<UserStore>>>,
 ) -> (Arc<OxiRaft>, Vec<JoinHandle<()>>) {
     let db = OxiDb::open(data_dir).expect("failed to open db");
     let db = Arc::new(db);
@@ -120,16 +126,25 @@ async fn start_node(
         .expect("failed to create raft node");
     let raft = Arc::new(raft);
 
+    let mut tasks = Vec::new();
+    let sweeper = management::run_ttl_sweeper(Arc::clone(&raft), Arc::clone(&db), Duration::from_millis(200));
+    tasks.push(tokio::spawn(sweeper));
+
     let state = Arc::new(ServerState {
         db,
-        user_store: None,
+        auth_enabled: user_store.is_some(),
+        user_store,
         audit_log: None,
-        auth_enabled: false,
+        session_limits: SessionLimits::default(),
+        rate_limiter: None,
+        client_cert_roles: ClientCertRoles::default(),
+        tls_channel_binding: None,
         raft: Some(Arc::clone(&raft)),
+        raft_addr: raft_addr.to_string(),
+        // Records every timed query
+        slow_query_log: Some(Arc::new(SlowQueryLog::new(Duration::ZERO, None))),
     });
 
-    let mut tasks = Vec::new();
-
     // Spawn Raft RPC listener
     let raft_clone = Arc::clone(&raft);
     let raft_listener = TcpListener::bind(raft_addr).await.expect("bind raft listener");
@@ -139,7 +154,7 @@ async fn start_node(
                 Ok((stream, _)) => {
                     let r = Arc::clone(&raft_clone);
                     tokio::spawn(async move {
-                        network::handle_raft_rpc(stream, &r).await;
+                        network::handle_raft_rpc(stream, &r, &client_addr.to_string()).await;
                     });
                 }
                 Err(_) => break,
@@ -174,11 +189,29 @@ async fn start_node(
 // ---------------------------------------------------------------------------
 
 async fn create_test_node(node_id: u64) -> TestNode {
+    create_node(node_id, false).await
+}
+
+/// Like `create_test_node`, with authentication enabled. Every node knows the
+/// same users: `root` (admin) and `alice` and `bob` (read-write), each with
+/// the password `secret`.
+async fn create_auth_test_node(node_id: u64) -> TestNode {
+    create_node(node_id, true).await
+}
+
+async fn create_node(node_id: u64, auth: bool) -> TestNode {
     let client_addr = allocate_port().await;
     let raft_addr = allocate_port().await;
     let dir = TempDir::new().unwrap();
 
-    let (raft, tasks) = start_node(node_id, client_addr, raft_addr, dir.path()).await;
+    let user_store = auth.then(|| {
+        let mut store = UserStore::open(dir.path()).unwrap();
+        store.create_user("root", "secret", Role::Admin).unwrap();
+        store.create_user("alice", "secret", Role::ReadWrite).unwrap();
+        store.create_user("bob", "secret", Role::ReadWrite).unwrap();
+        Arc::new(Mutex::new(store))
+    });
+    let (raft, tasks) = start_node(node_id, client_addr, raft_addr, dir.path(), user_store).await;
 
     // Brief pause to let the listeners start accepting
     sleep(Duration::from_millis(50)).await;
@@ -209,6 +242,15 @@ async fn form_cluster(count: u64) -> (Vec<TestNode>, Vec<AsyncClient>) {
     for node in &nodes {
         clients.push(AsyncClient::connect(node.client_addr).await);
     }
+    bootstrap_cluster(&nodes, &mut clients).await;
+
+    (nodes, clients)
+}
+
+/// Initialize a cluster of `nodes` through `clients`, one connection per
+/// node, and wait for it to elect a leader.
+async fn bootstrap_cluster(nodes: &[TestNode], clients: &mut [AsyncClient]) {
+    let count = nodes.len() as u64;
 
     // Initialize cluster on node 1
     let resp = clients[0].send(&json!({"cmd": "raft_init"})).await;
@@ -238,9 +280,7 @@ async fn form_cluster(count: u64) -> (Vec<TestNode>, Vec<AsyncClient>) {
     assert!(resp["ok"].as_bool().unwrap_or(false), "change_membership failed: {resp}");
 
     // Wait for a leader to be elected
-    wait_for_leader(&mut clients, Duration::from_secs(15)).await;
-
-    (nodes, clients)
+    wait_for_leader(clients, Duration::from_secs(15)).await;
 }
 
 // ---------------------------------------------------------------------------
@@ -721,3 +761,301 @@ async fn test_minority_cannot_elect_leader() {
         node.kill().await;
     }
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_linearizable_read_sees_committed_write() {
+    let (mut nodes, mut clients) = form_cluster(3).await;
+    let leader_idx = find_leader(&mut clients).await.expect("no leader");
+    let follower_idx = (leader_idx + 1) % clients.len();
+
+    // A linearizable read on the leader right after the write sees it
+    let resp = clients[leader_idx]
+        .send(&json!({"cmd": "insert", "collection": "test", "doc": {"name": "fresh"}}))
+        .await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "insert failed: {resp}");
+    let resp = clients[leader_idx]
+        .send(&json!({
+            "cmd": "find_one",
+            "collection": "test",
+            "query": {"name": "fresh"},
+            "read_concern": "linearizable",
+        }))
+        .await;
+    assert_eq!(resp["data"]["name"], "fresh", "linearizable read on leader: {resp}");
+    let resp = clients[leader_idx]
+        .send(&json!({"cmd": "count", "collection": "test", "read_concern": "linearizable"}))
+        .await;
+    assert_eq!(resp["data"]["count"], 1);
+
+    // A follower redirects linearizable reads to the leader
+    let resp = clients[follower_idx]
+        .send(&json!({"cmd": "find", "collection": "test", "read_concern": "linearizable"}))
+        .await;
+    assert_eq!(resp["ok"], false);
+    assert_eq!(resp["code"], "not_leader");
+    assert_eq!(resp["leader_id"], (leader_idx + 1) as u64);
+    assert_eq!(resp["leader"], nodes[leader_idx].client_addr.to_string());
+
+    // A bad read_concern is rejected on any node
+    let resp = clients[follower_idx]
+        .send(&json!({"cmd": "find", "collection": "test", "read_concern": "majority"}))
+        .await;
+    assert_eq!(resp["ok"], false);
+
+    for node in &mut nodes {
+        node.kill().await;
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_follower_redirects_writes_to_leader() {
+    let (mut nodes, mut clients) = form_cluster(3).await;
+    let leader_idx = find_leader(&mut clients).await.expect("no leader");
+    let follower_idx = (leader_idx + 1) % clients.len();
+
+    let insert = json!({"cmd": "insert", "collection": "test", "doc": {"name": "redirected"}});
+    let resp = clients[follower_idx].send(&insert).await;
+    assert_eq!(resp["ok"], false, "{resp}");
+    assert_eq!(resp["code"], "not_leader");
+    assert_eq!(resp["leader_id"], (leader_idx + 1) as u64);
+    assert_eq!(resp["leader"], nodes[leader_idx].client_addr.to_string());
+
+    // Following the redirect works, and reads are still served by the follower
+    let leader_addr: SocketAddr = resp["leader"].as_str().unwrap().parse().unwrap();
+    let resp = AsyncClient::connect(leader_addr).await.send(&insert).await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "insert on leader failed: {resp}");
+    wait_for_replication(&mut clients, "test", 1, Duration::from_secs(10)).await;
+    let resp = clients[follower_idx]
+        .send(&json!({"cmd": "find_one", "collection": "test", "query": {"name": "redirected"}}))
+        .await;
+    assert_eq!(resp["data"]["name"], "redirected");
+
+    for node in &mut nodes {
+        node.kill().await;
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_add_and_remove_node_at_runtime() {
+    // Start with a single-node cluster holding some data
+    let mut first = create_test_node(1).await;
+    let mut c1 = AsyncClient::connect(first.client_addr).await;
+    let resp = c1.send(&json!({"cmd": "raft_init"})).await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "raft_init failed: {resp}");
+    wait_for_leader(std::slice::from_mut(&mut c1), Duration::from_secs(15)).await;
+    let resp = c1.send(&json!({"cmd": "insert", "collection": "test", "doc": {"name": "before"}})).await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "insert failed: {resp}");
+
+    // Add a second node as a learner, then promote it
+    let mut second = create_test_node(2).await;
+    let resp = c1
+        .send(&json!({"cmd": "raft_add_learner", "node_id": 2, "addr": second._raft_addr.to_string()}))
+        .await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "add_learner failed: {resp}");
+    let resp = c1.send(&json!({"cmd": "raft_change_membership", "members": [1, 2]})).await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "change_membership failed: {resp}");
+
+    // The new voter has the data committed before and after it joined
+    let resp = c1.send(&json!({"cmd": "insert", "collection": "test", "doc": {"name": "after"}})).await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "insert failed: {resp}");
+    let mut clients = vec![c1, AsyncClient::connect(second.client_addr).await];
+    wait_for_replication(&mut clients, "test", 2, Duration::from_secs(10)).await;
+    let resp = clients[1].send(&json!({"cmd": "raft_metrics"})).await;
+    assert_eq!(resp["data"]["current_leader"], 1);
+
+    // Writes on the new voter redirect to the node that bootstrapped the cluster
+    let resp = clients[1].send(&json!({"cmd": "insert", "collection": "test", "doc": {"name": "follower"}})).await;
+    assert_eq!(resp["code"], "not_leader", "{resp}");
+    assert_eq!(resp["leader"], first.client_addr.to_string());
+
+    // Remove it again; the last voter can't be removed
+    let resp = clients[0].send(&json!({"cmd": "raft_remove_node", "node_id": 2})).await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "remove_node failed: {resp}");
+    let resp = clients[0].send(&json!({"cmd": "raft_remove_node", "node_id": 1})).await;
+    assert_eq!(resp["ok"], false);
+    let resp = clients[0].send(&json!({"cmd": "raft_remove_node", "node_id": 7})).await;
+    assert_eq!(resp["ok"], false);
+
+    // The single remaining node still commits on its own
+    let resp = clients[0].send(&json!({"cmd": "insert", "collection": "test", "doc": {"name": "solo"}})).await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "insert after removal failed: {resp}");
+
+    first.kill().await;
+    second.kill().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_logout_on_cluster_node() {
+    let mut node = create_test_node(1).await;
+    let mut client = AsyncClient::connect(node.client_addr).await;
+    let resp = client.send(&json!({"cmd": "raft_init"})).await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "raft_init failed: {resp}");
+    wait_for_leader(std::slice::from_mut(&mut client), Duration::from_secs(15)).await;
+
+    let resp = client.send(&json!({"cmd": "logout"})).await;
+    assert_eq!(resp["data"], "logged out");
+
+    // The connection stays usable for replicated writes afterwards
+    let resp = client.send(&json!({"cmd": "insert", "collection": "test", "doc": {"name": "after"}})).await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "insert after logout failed: {resp}");
+
+    node.kill().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_node_local_durability_is_rejected() {
+    let mut node = create_test_node(1).await;
+    let mut client = AsyncClient::connect(node.client_addr).await;
+    let resp = client.send(&json!({"cmd": "raft_init"})).await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "raft_init failed: {resp}");
+    wait_for_leader(std::slice::from_mut(&mut client), Duration::from_secs(15)).await;
+
+    for durability in ["relaxed", "full", "bogus"] {
+        let request = json!({"cmd": "insert", "collection": "test", "doc": {"n": 1}, "durability": durability});
+        let resp = client.send(&request).await;
+        assert_eq!(resp["error"], "'durability' is not supported in cluster mode");
+    }
+    let resp = client.send(&json!({"cmd": "flush", "collection": "test"})).await;
+    assert_eq!(resp["error"], "'flush' is not supported in cluster mode");
+    let resp = client.send(&json!({"cmd": "count", "collection": "test", "query": {}})).await;
+    assert_eq!(resp["data"]["count"], 0);
+
+    node.kill().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_expired_documents_are_swept_on_every_node() {
+    let (mut nodes, mut clients) = form_cluster(3).await;
+    let docs = json!([
+        {"name": "stale", "_expireAt": "2000-01-01T00:00:00Z"},
+        {"name": "fresh"},
+    ]);
+    let resp = send_to_leader(&mut clients, &json!({"cmd": "insert_many", "collection": "sessions", "docs": docs})).await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "insert_many failed: {resp}");
+
+    // The leader's sweep reaches the followers through the log
+    wait_for_replication(&mut clients, "sessions", 1, Duration::from_secs(10)).await;
+    for client in &mut clients {
+        let resp = client.send(&json!({"cmd": "find", "collection": "sessions", "query": {}})).await;
+        assert_eq!(resp["data"][0]["name"], "fresh");
+    }
+
+    for node in &mut nodes {
+        node.kill().await;
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_slow_query_log_is_node_local() {
+    let (mut nodes, mut clients) = form_cluster(2).await;
+    let resp = clients[0].send(&json!({"cmd": "find", "collection": "orders", "query": {"status": "open"}})).await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "find failed: {resp}");
+
+    let query_log = json!({"cmd": "query_slow_log", "collection": "orders"});
+    let logged = clients[0].send(&query_log).await["data"].clone();
+    assert_eq!(logged.as_array().map(Vec::len), Some(1), "{logged}");
+    assert_eq!(logged[0]["query"], json!({"status": "open"}));
+    assert_eq!(clients[1].send(&query_log).await["data"], json!([]));
+
+    for node in &mut nodes {
+        node.kill().await;
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_generated_defaults_match_on_every_node() {
+    let (mut nodes, mut clients) = form_cluster(3).await;
+    let defaults = json!({"created_at": "$now", "ref": "$uuid"});
+    let resp = send_to_leader(&mut clients, &json!({"cmd": "set_defaults", "collection": "test", "defaults": defaults})).await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "set_defaults failed: {resp}");
+
+    for request in [
+        json!({"cmd": "insert", "collection": "test", "doc": {"name": "one"}}),
+        json!({"cmd": "insert_many", "collection": "test", "docs": [{"name": "two"}]}),
+        json!({"cmd": "bulk_write", "collection": "test", "ops": [{"op": "insert", "doc": {"name": "three"}}]}),
+    ] {
+        let resp = send_to_leader(&mut clients, &request).await;
+        assert!(resp["ok"].as_bool().unwrap_or(false), "{request} failed: {resp}");
+    }
+    wait_for_replication(&mut clients, "test", 3, Duration::from_secs(10)).await;
+
+    let mut stored = Vec::new();
+    for client in &mut clients {
+        let resp = client.send(&json!({"cmd": "find", "collection": "test", "query": {}})).await;
+        let mut docs = resp["data"].as_array().expect("data should be array").clone();
+        docs.sort_by_key(|doc| doc["name"].as_str().unwrap_or("").to_string());
+        assert!(docs.iter().all(|doc| doc["ref"].is_string() && doc["created_at"].is_string()), "{docs:?}");
+        stored.push(docs);
+    }
+    assert!(stored.windows(2).all(|pair| pair[0] == pair[1]), "replicas differ: {stored:?}");
+
+    for node in &mut nodes {
+        node.kill().await;
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_bucket_acl_applies_on_every_node() {
+    async fn login(addr: SocketAddr, user: &str) -> AsyncClient {
+        let mut client = AsyncClient::connect(addr).await;
+        let resp = client.send(&json!({"cmd": "auth_simple", "username": user, "password": "secret"})).await;
+        assert!(resp["ok"].as_bool().unwrap_or(false), "login as {user} failed: {resp}");
+        client
+    }
+
+    let mut nodes = vec![create_auth_test_node(1).await, create_auth_test_node(2).await];
+    let mut admins = Vec::new();
+    for node in &nodes {
+        admins.push(login(node.client_addr, "root").await);
+    }
+    bootstrap_cluster(&nodes, &mut admins).await;
+    let leader = wait_for_leader(&mut admins, Duration::from_secs(15)).await;
+    let follower = 1 - leader;
+
+    // Alice creates the bucket on the leader, so it's hers alone everywhere
+    let mut alice = login(nodes[leader].client_addr, "alice").await;
+    let resp = alice.send(&json!({"cmd": "create_bucket", "bucket": "payroll"})).await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "create_bucket failed: {resp}");
+    let put = json!({"cmd": "put_object", "bucket": "payroll", "key": "a.txt", "data": "aGVsbG8="});
+    let resp = alice.send(&put).await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "put_object failed: {resp}");
+
+    let mut bob_on_leader = login(nodes[leader].client_addr, "bob").await;
+    let mut bob = login(nodes[follower].client_addr, "bob").await;
+    let claim = json!({"cmd": "set_bucket_acl", "bucket": "payroll", "acl": {"owner": "bob"}});
+    let resp = bob_on_leader.send(&claim).await;
+    assert!(resp["error"].as_str().unwrap_or("").starts_with("permission denied"), "{resp}");
+
+    // The follower denies Bob once the bucket has replicated to it
+    let get = json!({"cmd": "get_object", "bucket": "payroll", "key": "a.txt"});
+    let start = tokio::time::Instant::now();
+    loop {
+        let resp = bob.send(&get).await;
+        if resp["error"].as_str().unwrap_or("").starts_with("permission denied") {
+            break;
+        }
+        assert!(start.elapsed() < Duration::from_secs(10), "follower never denied bob: {resp}");
+        sleep(Duration::from_millis(100)).await;
+    }
+
+    // An ACL change made on the leader opens the bucket to him there too
+    let share = json!({"cmd": "set_bucket_acl", "bucket": "payroll", "acl": {"owner": "alice", "readers": ["bob"]}});
+    let resp = alice.send(&share).await;
+    assert!(resp["ok"].as_bool().unwrap_or(false), "set_bucket_acl failed: {resp}");
+    let start = tokio::time::Instant::now();
+    loop {
+        let resp = bob.send(&get).await;
+        if resp["ok"].as_bool().unwrap_or(false) {
+            assert_eq!(resp["data"]["content"], "aGVsbG8=");
+            break;
+        }
+        assert!(start.elapsed() < Duration::from_secs(10), "follower never let bob read: {resp}");
+        sleep(Duration::from_millis(100)).await;
+    }
+    let resp = bob.send(&put).await;
+    assert!(resp["error"].as_str().unwrap_or("").starts_with("permission denied"), "{resp}");
+
+    for node in &mut nodes {
+        node.kill().await;
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use oxidb::{EncryptionKey, OxiDb};
 use serde_json::json;
@@ -153,6 +154,8 @@ fn rbac_permissions() {
     assert!(rbac::is_permitted(Role::ReadWrite, "delete"));
     assert!(rbac::is_permitted(Role::ReadWrite, "begin_tx"));
     assert!(!rbac::is_permitted(Role::ReadWrite, "drop_collection"));
+    assert!(!rbac::is_permitted(Role::ReadWrite, "repair"));
+    assert!(rbac::is_permitted(Role::Admin, "repair"));
 
     // Read can only read
     assert!(rbac::is_permitted(Role::Read, "find"));
@@ -165,6 +168,192 @@ fn rbac_permissions() {
     assert!(!rbac::is_permitted(Role::Read, "drop_collection"));
 }
 
+/// Test a user-defined role that can only read and aggregate.
+#[test]
+fn custom_role_permissions() {
+    use oxidb_server::auth::{Role, UserStore};
+    use oxidb_server::handler::handle_user_command;
+    use oxidb_server::rbac::session_permitted;
+    use oxidb_server::session::Session;
+    use std::sync::{Arc, Mutex};
+
+    let dir = tempfile::tempdir().unwrap();
+    let store = Arc::new(Mutex::new(UserStore::open(dir.path()).unwrap()));
+    let run = |cmd: &str, req: serde_json::Value| -> serde_json::Value {
+        serde_json::from_slice(&handle_user_command(cmd, &req, &store).unwrap()).unwrap()
+    };
+
+    let resp = run("create_role", json!({"name": "reporting", "commands": ["find", "aggregate"]}));
+    assert_eq!(resp["ok"], true);
+    assert_eq!(run("create_role", json!({"name": "reporting", "commands": []}))["ok"], false);
+    assert_eq!(run("create_role", json!({"name": "admin", "commands": ["find"]}))["ok"], false);
+    let resp = run("create_user", json!({"username": "carol", "password": "pw", "role": "reporting"}));
+    assert_eq!(resp["ok"], true);
+    assert_eq!(run("create_user", json!({"username": "dan", "password": "pw", "role": "nope"}))["ok"], false);
+
+    let mut session = Session::new();
+    session.set_authenticated_user(store.lock().unwrap().get_user("carol").unwrap());
+    assert_eq!(session.role_name(), "reporting");
+    for cmd in ["find", "aggregate", "ping"] {
+        assert!(session_permitted(&session, Some(&store), cmd), "reporting should permit: {cmd}");
+    }
+    for cmd in ["insert", "update", "delete", "count", "drop_collection", "create_user", "list_roles"] {
+        assert!(!session_permitted(&session, Some(&store), cmd), "reporting should deny: {cmd}");
+    }
+
+    // Cluster membership changes stay Admin-only even when a role lists them
+    let resp = run("create_role", json!({"name": "ops", "commands": ["raft_metrics", "raft_add_learner", "raft_remove_node"]}));
+    assert_eq!(resp["ok"], true);
+    assert_eq!(run("create_user", json!({"username": "erin", "password": "pw", "role": "ops"}))["ok"], true);
+    session.set_authenticated_user(store.lock().unwrap().get_user("erin").unwrap());
+    assert!(session_permitted(&session, Some(&store), "raft_metrics"));
+    for cmd in ["raft_add_learner", "raft_remove_node", "raft_change_membership", "raft_init", "repair"] {
+        assert!(!session_permitted(&session, Some(&store), cmd), "ops should deny: {cmd}");
+    }
+    session.set_authenticated("root".into(), Role::Admin);
+    assert!(session_permitted(&session, Some(&store), "raft_remove_node"));
+    assert_eq!(run("drop_user", json!({"username": "erin"}))["ok"], true);
+    assert_eq!(run("drop_role", json!({"name": "ops"}))["ok"], true);
+
+    // Built-in roles are unaffected
+    session.set_authenticated("bob".into(), Role::ReadWrite);
+    assert!(session_permitted(&session, Some(&store), "insert"));
+    assert!(!session_permitted(&session, Some(&store), "create_role"));
+
+    // Roles persist, and can't be dropped while assigned
+    let store = Arc::new(Mutex::new(UserStore::open(dir.path()).unwrap()));
+    let run = |cmd: &str, req: serde_json::Value| -> serde_json::Value {
+        serde_json::from_slice(&handle_user_command(cmd, &req, &store).unwrap()).unwrap()
+    };
+    let roles = run("list_roles", json!({}));
+    assert_eq!(roles["data"], json!([{"name": "reporting", "commands": ["aggregate", "find"]}]));
+    let users = run("list_users", json!({}));
+    assert!(users["data"].as_array().unwrap().contains(&json!({"username": "carol", "role": "reporting"})));
+    assert_eq!(run("drop_role", json!({"name": "reporting"}))["ok"], false);
+    assert_eq!(run("update_user", json!({"username": "carol", "role": "read"}))["ok"], true);
+    assert_eq!(store.lock().unwrap().get_user("carol").unwrap().custom_role, None);
+    assert_eq!(run("drop_role", json!({"name": "reporting"}))["ok"], true);
+    assert_eq!(run("list_roles", json!({}))["data"], json!([]));
+}
+
+/// Test API keys: a valid key authenticates, revoked and expired keys don't.
+#[test]
+fn api_key_authentication() {
+    use oxidb_server::auth::UserStore;
+    use oxidb_server::handler::handle_user_command;
+    use oxidb_server::session::Session;
+    use std::sync::{Arc, Mutex};
+
+    let dir = tempfile::tempdir().unwrap();
+    let store = Arc::new(Mutex::new(UserStore::open(dir.path()).unwrap()));
+    let run = |cmd: &str, req: serde_json::Value| -> serde_json::Value {
+        serde_json::from_slice(&handle_user_command(cmd, &req, &store).unwrap()).unwrap()
+    };
+    run("create_user", json!({"username": "svc", "password": "pw", "role": "readwrite"}));
+
+    // Valid key
+    let resp = run("create_api_key", json!({"username": "svc"}));
+    assert_eq!(resp["ok"], true);
+    let id = resp["data"]["id"].as_str().unwrap().to_string();
+    let key = resp["data"]["key"].as_str().unwrap().to_string();
+    let mut session = Session::new();
+    session.set_authenticated_user(store.lock().unwrap().authenticate_api_key(&key).unwrap());
+    assert_eq!(session.role_name(), "readWrite");
+    assert!(store.lock().unwrap().authenticate_api_key("oxk_wrong").is_none());
+    assert_eq!(run("create_api_key", json!({"username": "nobody"}))["ok"], false);
+
+    // Only the hash is stored
+    let saved = std::fs::read_to_string(dir.path().join("_auth/api_keys.json")).unwrap();
+    assert!(!saved.contains(&key));
+
+    // Revoked key
+    assert_eq!(run("revoke_api_key", json!({"id": id}))["ok"], true);
+    assert!(store.lock().unwrap().authenticate_api_key(&key).is_none());
+    assert_eq!(run("revoke_api_key", json!({"id": "missing"}))["ok"], false);
+
+    // Expired key
+    let resp = run("create_api_key", json!({"username": "svc", "expires_in_secs": 0}));
+    let expired = resp["data"]["key"].as_str().unwrap();
+    assert!(store.lock().unwrap().authenticate_api_key(expired).is_none());
+
+    // Keys persist and are dropped with their user
+    let resp = run("create_api_key", json!({"username": "svc", "expires_in_secs": 3600}));
+    let key = resp["data"]["key"].as_str().unwrap().to_string();
+    let store = Arc::new(Mutex::new(UserStore::open(dir.path()).unwrap()));
+    let run = |cmd: &str, req: serde_json::Value| -> serde_json::Value {
+        serde_json::from_slice(&handle_user_command(cmd, &req, &store).unwrap()).unwrap()
+    };
+    assert!(store.lock().unwrap().authenticate_api_key(&key).is_some());
+    let keys = run("list_api_keys", json!({}));
+    assert_eq!(keys["data"].as_array().unwrap().len(), 3);
+    assert_eq!(keys["data"][0]["revoked"], true);
+    assert!(keys["data"][0].get("key_hash").is_none());
+    run("drop_user", json!({"username": "svc"}));
+    assert!(store.lock().unwrap().authenticate_api_key(&key).is_none());
+    assert_eq!(run("list_api_keys", json!({}))["data"], json!([]));
+}
+
+/// Test a forced password change: only `change_password` runs until it succeeds.
+#[test]
+fn forced_password_change() {
+    use oxidb_server::auth::UserStore;
+    use oxidb_server::handler::{handle_change_password, handle_user_command};
+    use oxidb_server::rbac::{password_change_required, session_permitted};
+    use oxidb_server::session::Session;
+    use std::sync::{Arc, Mutex};
+
+    let dir = tempfile::tempdir().unwrap();
+    let store = Arc::new(Mutex::new(UserStore::open(dir.path()).unwrap()));
+    let run = |cmd: &str, req: serde_json::Value| -> serde_json::Value {
+        serde_json::from_slice(&handle_user_command(cmd, &req, &store).unwrap()).unwrap()
+    };
+    let resp = run("create_user", json!({"username": "erin", "password": "temp", "role": "read", "must_change": true}));
+    assert_eq!(resp["ok"], true);
+    let created_at = store.lock().unwrap().get_user("erin").unwrap().password_changed_at;
+    assert!(created_at > 0);
+
+    let mut session = Session::new();
+    session.set_authenticated_user(store.lock().unwrap().get_user("erin").unwrap());
+    for cmd in ["find", "count", "list_collections"] {
+        assert!(password_change_required(&session, cmd), "should block: {cmd}");
+    }
+    assert!(!password_change_required(&session, "change_password"));
+    assert!(session_permitted(&session, Some(&store), "change_password"));
+
+    // A wrong current password, or reusing it, doesn't lift the block
+    let change = |session: &mut Session, old: &str, new: &str| -> serde_json::Value {
+        let req = json!({"old_password": old, "new_password": new});
+        serde_json::from_slice(&handle_change_password(session, &req, &store)).unwrap()
+    };
+    assert_eq!(change(&mut session, "wrong", "fresh")["ok"], false);
+    assert_eq!(change(&mut session, "temp", "temp")["ok"], false);
+    assert!(password_change_required(&session, "find"));
+
+    let resp = change(&mut session, "temp", "fresh");
+    assert_eq!(resp["ok"], true);
+    assert!(!password_change_required(&session, "find"));
+    assert!(session_permitted(&session, Some(&store), "find"));
+
+    // The change persists
+    let store = Arc::new(Mutex::new(UserStore::open(dir.path()).unwrap()));
+    let guard = store.lock().unwrap();
+    assert!(guard.authenticate("erin", "fresh").is_some());
+    assert!(guard.authenticate("erin", "temp").is_none());
+    let user = guard.get_user("erin").unwrap();
+    assert!(!user.must_change);
+    assert!(user.password_changed_at >= created_at);
+    drop(guard);
+
+    // An admin can require another change
+    let resp: serde_json::Value = serde_json::from_slice(
+        &handle_user_command("update_user", &json!({"username": "erin", "must_change": true}), &store).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(resp["ok"], true);
+    session.set_authenticated_user(store.lock().unwrap().get_user("erin").unwrap());
+    assert!(password_change_required(&session, "find"));
+}
+
 /// Test audit log writes entries.
 #[test]
 fn audit_log_writes_entries() {
@@ -205,6 +394,196 @@ fn audit_log_writes_entries() {
     assert_eq!(entry2["cmd"], "find");
 }
 
+/// Test querying the audit log by user, command, result and time window.
+#[test]
+fn audit_log_query() {
+    use oxidb_server::audit::{AuditEvent, AuditLog};
+    use oxidb_server::handler::handle_query_audit;
+
+    let dir = tempfile::tempdir().unwrap();
+    let log = AuditLog::open(dir.path()).unwrap();
+    let events = [
+        ("2024-01-01T09:00:00Z", "admin", "create_user", None, "ok"),
+        ("2024-01-01T10:00:00Z", "alice", "insert", Some("orders"), "ok"),
+        ("2024-01-01T11:00:00Z", "bob", "drop_collection", Some("orders"), "denied"),
+        ("2024-01-01T12:00:00Z", "alice", "find", Some("orders"), "ok"),
+        ("2024-01-02T08:00:00Z", "alice", "insert", Some("invoices"), "ok"),
+    ];
+    for (ts, user, cmd, collection, result) in events {
+        log.log(&AuditEvent { ts: ts.to_string(), user, cmd, collection, result, detail: "" });
+    }
+    let query = |request: serde_json::Value| -> serde_json::Value {
+        let resp: serde_json::Value = serde_json::from_slice(&handle_query_audit(&request, Some(&log))).unwrap();
+        assert_eq!(resp["ok"], true, "{resp}");
+        resp["data"].clone()
+    };
+    let timestamps = |events: serde_json::Value| -> Vec<String> {
+        events.as_array().unwrap().iter().map(|e| e["ts"].as_str().unwrap().to_string()).collect()
+    };
+
+    // By user
+    let found = query(json!({"cmd": "query_audit", "user": "alice"}));
+    assert_eq!(timestamps(found.clone()), ["2024-01-01T10:00:00Z", "2024-01-01T12:00:00Z", "2024-01-02T08:00:00Z"]);
+    assert_eq!(found[0]["cmd"], "insert");
+    assert_eq!(found[0]["collection"], "orders");
+
+    // By time window, inclusive at both ends, with an offset timestamp
+    let found = query(json!({"cmd": "query_audit", "since": "2024-01-01T10:00:00Z", "until": "2024-01-01T13:00:00+01:00"}));
+    assert_eq!(timestamps(found), ["2024-01-01T10:00:00Z", "2024-01-01T11:00:00Z", "2024-01-01T12:00:00Z"]);
+
+    // Filters combine
+    let found = query(json!({"cmd": "query_audit", "user": "alice", "command": "insert", "since": "2024-01-02T00:00:00Z"}));
+    assert_eq!(timestamps(found), ["2024-01-02T08:00:00Z"]);
+    let found = query(json!({"cmd": "query_audit", "result": "denied"}));
+    assert_eq!(found[0]["user"], "bob");
+    assert_eq!(found.as_array().unwrap().len(), 1);
+    assert_eq!(query(json!({"cmd": "query_audit", "collection": "invoices"})).as_array().unwrap().len(), 1);
+
+    // limit keeps the most recent matches
+    let found = query(json!({"cmd": "query_audit", "limit": 2}));
+    assert_eq!(timestamps(found), ["2024-01-01T12:00:00Z", "2024-01-02T08:00:00Z"]);
+
+    // Bad filters, and a server without an audit log
+    for bad in [json!({"since": "yesterday"}), json!({"limit": 0}), json!({"user": 7})] {
+        let resp: serde_json::Value = serde_json::from_slice(&handle_query_audit(&bad, Some(&log))).unwrap();
+        assert_eq!(resp["ok"], false, "{bad}");
+    }
+    let resp: serde_json::Value = serde_json::from_slice(&handle_query_audit(&json!({}), None)).unwrap();
+    assert!(resp["error"].as_str().unwrap().contains("OXIDB_AUDIT"));
+
+    // Admin only, and can't be granted through a custom role
+    assert!(oxidb_server::rbac::is_admin_only("query_audit"));
+    assert!(!oxidb_server::rbac::is_permitted(oxidb_server::auth::Role::ReadWrite, "query_audit"));
+    assert!(!oxidb_server::rbac::is_permitted(oxidb_server::auth::Role::Read, "query_audit"));
+}
+
+/// Audit event with a sequence number in `detail`, for rotation tests.
+fn log_numbered(log: &oxidb_server::audit::AuditLog, n: usize) {
+    let detail = format!("event {n}");
+    log.log(&oxidb_server::audit::AuditEvent {
+        ts: "2024-01-01T00:00:00Z".to_string(),
+        user: "alice",
+        cmd: "insert",
+        collection: Some("orders"),
+        result: "ok",
+        detail: &detail,
+    });
+}
+
+/// Audit files in `{dir}/_audit` other than the active log, sorted.
+fn audit_segments(dir: &std::path::Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir.join("_audit"))
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name != "audit.log")
+        .collect();
+    names.sort();
+    names
+}
+
+fn event_details(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["detail"].as_str().unwrap().to_string())
+        .collect()
+}
+
+/// Test size-based audit log rotation with compressed, bounded segments.
+#[test]
+fn audit_log_rotates_by_size() {
+    use std::io::Read;
+
+    use oxidb_server::audit::{AuditLog, AuditQuery, AuditRotation};
+
+    let dir = tempfile::tempdir().unwrap();
+    let rotation = AuditRotation { max_bytes: Some(1000), max_segments: 2, compress: true, ..Default::default() };
+    let log = AuditLog::open_with_rotation(dir.path(), rotation).unwrap();
+
+    // Each event is ~130 bytes: the file rolls at the first write past 1000
+    let mut n = 0;
+    while audit_segments(dir.path()).is_empty() {
+        log_numbered(&log, n);
+        n += 1;
+        assert!(n < 100, "log never rotated");
+    }
+    let segments = audit_segments(dir.path());
+    assert_eq!(segments.len(), 1);
+    assert!(segments[0].starts_with("audit-") && segments[0].ends_with(".log.gz"), "{segments:?}");
+
+    // The old segment holds the earlier events, the fresh file only the last
+    let mut rolled = String::new();
+    let file = std::fs::File::open(dir.path().join("_audit").join(&segments[0])).unwrap();
+    flate2::read::GzDecoder::new(file).read_to_string(&mut rolled).unwrap();
+    let rolled = event_details(&rolled);
+    assert_eq!(rolled.len(), n - 1);
+    assert_eq!(rolled[0], "event 0");
+    let active = event_details(&std::fs::read_to_string(dir.path().join("_audit/audit.log")).unwrap());
+    assert_eq!(active, [format!("event {}", n - 1)]);
+
+    // Queries read the segments as well as the active file
+    let all = log.query(&AuditQuery { limit: 1000, ..Default::default() }).unwrap();
+    assert_eq!(all.len(), n);
+    assert_eq!(all[0]["detail"], "event 0");
+
+    // Only the newest two segments are kept
+    for _ in 0..4 * n {
+        log_numbered(&log, n);
+        n += 1;
+    }
+    let segments = audit_segments(dir.path());
+    assert_eq!(segments.len(), 2, "{segments:?}");
+    assert!(segments.iter().all(|name| name.ends_with(".log.gz")));
+    let all = log.query(&AuditQuery { limit: 1000, ..Default::default() }).unwrap();
+    assert_eq!(all.last().unwrap()["detail"], format!("event {}", n - 1));
+    assert_ne!(all[0]["detail"], "event 0");
+}
+
+/// Test age-based rotation, concurrent appends, and reopening a rotated log.
+#[test]
+fn audit_log_rotates_by_age() {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use oxidb_server::audit::{AuditLog, AuditQuery, AuditRotation};
+
+    let dir = tempfile::tempdir().unwrap();
+    let rotation = AuditRotation { max_age: Some(Duration::from_millis(100)), ..Default::default() };
+    let log = Arc::new(AuditLog::open_with_rotation(dir.path(), rotation.clone()).unwrap());
+
+    log_numbered(&log, 0);
+    std::thread::sleep(Duration::from_millis(150));
+    // Several threads log at once as the file comes due
+    let threads: Vec<_> = (1..=4)
+        .map(|t| {
+            let log = Arc::clone(&log);
+            std::thread::spawn(move || {
+                for i in 0..25 {
+                    log_numbered(&log, t * 100 + i);
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    let segments = audit_segments(dir.path());
+    assert_eq!(segments.len(), 1, "{segments:?}");
+    assert!(segments[0].ends_with(".log"), "uncompressed by default: {segments:?}");
+    let rolled = event_details(&std::fs::read_to_string(dir.path().join("_audit").join(&segments[0])).unwrap());
+    assert_eq!(rolled, ["event 0"]);
+    let active = event_details(&std::fs::read_to_string(dir.path().join("_audit/audit.log")).unwrap());
+    assert_eq!(active.len(), 100, "every concurrent event lands in the fresh file");
+
+    // A reopened log dates itself from its first event (2024 here), so it
+    // is already due and rolls on the next event
+    drop(log);
+    let log = AuditLog::open_with_rotation(dir.path(), rotation).unwrap();
+    log_numbered(&log, 999);
+    assert_eq!(audit_segments(dir.path()).len(), 2);
+    assert_eq!(log.query(&AuditQuery { limit: 1000, ..Default::default() }).unwrap().len(), 102);
+}
+
 /// Test encryption with transactions.
 #[test]
 fn encrypted_transactions() {
@@ -240,6 +619,118 @@ fn session_tracking() {
     assert_eq!(session.role(), Some(Role::ReadWrite));
 }
 
+/// Test sessions expire after their max age or idle timeout, and on logout.
+#[test]
+fn session_expiry_and_logout() {
+    use oxidb_server::auth::Role;
+    use oxidb_server::rbac::session_permitted;
+    use oxidb_server::session::{Session, SessionLimits};
+    use std::time::{Duration, Instant};
+
+    let limits = SessionLimits {
+        max_age: Some(Duration::from_secs(3600)),
+        idle_timeout: Some(Duration::from_secs(60)),
+    };
+    let start = Instant::now();
+
+    // Idle expiry: activity keeps the session alive, a long pause ends it
+    let mut session = Session::new();
+    session.set_authenticated("alice".to_string(), Role::ReadWrite);
+    assert!(!session.check_expiry(&limits, start + Duration::from_secs(50)));
+    assert!(!session.check_expiry(&limits, start + Duration::from_secs(100)));
+    assert!(session.check_expiry(&limits, start + Duration::from_secs(161)));
+    assert!(!session.is_authenticated());
+    assert!(!session_permitted(&session, None, "find"));
+    assert_eq!(session.username_str(), "anonymous");
+
+    // Max age: expires even while active
+    session.set_authenticated("alice".to_string(), Role::ReadWrite);
+    let mut at = start;
+    for _ in 0..61 {
+        at += Duration::from_secs(59);
+        assert!(!session.check_expiry(&limits, at));
+    }
+    assert!(session.check_expiry(&limits, at + Duration::from_secs(59)));
+    assert!(!session.is_authenticated());
+
+    // Re-authenticating restores access
+    session.set_authenticated("alice".to_string(), Role::ReadWrite);
+    assert!(session_permitted(&session, None, "find"));
+
+    // No limits: never expires
+    assert!(!session.check_expiry(&SessionLimits::default(), start + Duration::from_secs(1_000_000)));
+
+    // Logout
+    session.logout();
+    assert!(!session.is_authenticated());
+    assert_eq!(session.role(), None);
+    assert!(!session_permitted(&session, None, "find"));
+    assert!(!session.check_expiry(&limits, start + Duration::from_secs(1_000_000)));
+}
+
+/// Test the rate limiter rejects a flooding client without affecting others.
+#[test]
+fn rate_limit_per_client() {
+    use oxidb_server::ratelimit::{RateLimiter, client_key};
+    use std::time::{Duration, Instant};
+
+    let limiter = RateLimiter::new(10.0, 5.0);
+    let flooder = client_key(Some("flooder"), "10.0.0.1:5000");
+    let polite = client_key(Some("polite"), "10.0.0.1:5001");
+    let start = Instant::now();
+
+    // 100 requests/sec from one user, 5/sec from another, for two seconds
+    let (mut allowed, mut rejected) = (0, 0);
+    for i in 0..200u64 {
+        let now = start + Duration::from_millis(i * 10);
+        if limiter.check(&flooder, now) {
+            allowed += 1;
+        } else {
+            rejected += 1;
+        }
+        if i % 20 == 0 {
+            assert!(limiter.check(&polite, now), "compliant client rejected at {i}");
+        }
+    }
+    // The burst, then about 10 per second
+    assert!((20..=26).contains(&allowed), "allowed {allowed}");
+    assert_eq!(allowed + rejected, 200);
+
+    // Tokens refill up to the burst
+    let later = start + Duration::from_secs(60);
+    let refilled = (0..10).filter(|_| limiter.check(&flooder, later)).count();
+    assert_eq!(refilled, 5);
+
+    // Anonymous clients are keyed by peer IP, whatever the port
+    assert_eq!(client_key(None, "10.0.0.1:5000"), "peer:10.0.0.1");
+    assert_eq!(client_key(None, "[::1]:5000"), "peer:::1");
+    assert_ne!(client_key(None, "flooder"), client_key(Some("flooder"), "x"));
+}
+
+/// Test anonymous connections from one host share a bucket, so reconnecting
+/// from a new port doesn't reset the limit.
+#[test]
+fn rate_limit_shared_across_connections_from_one_ip() {
+    use oxidb_server::ratelimit::{RateLimiter, client_key};
+    use std::net::{TcpListener, TcpStream};
+    use std::time::Instant;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (_first, _second) = (TcpStream::connect(addr).unwrap(), TcpStream::connect(addr).unwrap());
+    let peers: Vec<String> = (0..2)
+        .map(|_| listener.accept().unwrap().0.peer_addr().unwrap().to_string())
+        .collect();
+    assert_ne!(peers[0], peers[1]);
+
+    let limiter = RateLimiter::new(1.0, 3.0);
+    let now = Instant::now();
+    let allowed = (0..6)
+        .filter(|i| limiter.check(&client_key(None, &peers[i % 2]), now))
+        .count();
+    assert_eq!(allowed, 3);
+}
+
 /// Test user store persistence across restarts.
 #[test]
 fn user_store_persistence() {
@@ -261,3 +752,205 @@ fn user_store_persistence() {
         assert!(store.authenticate("bob", "bobpass").is_some());
     }
 }
+
+/// Client side of SCRAM-SHA-256: the client-final message for `server_first`,
+/// binding `channel_binding` after the GS2 header. The server uses the stored
+/// password hash as the SCRAM password.
+fn scram_client_final(password: &str, client_first: &str, server_first: &str, channel_binding: &[u8]) -> String {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use hmac::{Hmac, Mac};
+    use sha2::{Digest, Sha256};
+
+    let hmac = |key: &[u8], data: &[u8]| {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+        mac.update(data);
+        mac.finalize().into_bytes().to_vec()
+    };
+    let attr = |name: &str| {
+        server_first.split(',').find_map(|part| part.strip_prefix(name)).unwrap().to_string()
+    };
+    let (nonce, salt, iterations) = (attr("r="), STANDARD.decode(attr("s=")).unwrap(), attr("i=").parse::<u32>().unwrap());
+
+    let mut block = hmac(password.as_bytes(), &[salt.as_slice(), &1u32.to_be_bytes()].concat());
+    let mut salted = block.clone();
+    for _ in 1..iterations {
+        block = hmac(password.as_bytes(), &block);
+        salted.iter_mut().zip(&block).for_each(|(s, b)| *s ^= b);
+    }
+
+    let bare_start = client_first.match_indices(',').nth(1).unwrap().0 + 1;
+    let (gs2_header, bare) = client_first.split_at(bare_start);
+    let without_proof = format!("c={},r={nonce}", STANDARD.encode([gs2_header.as_bytes(), channel_binding].concat()));
+    let auth_message = format!("{bare},{server_first},{without_proof}");
+    let client_key = hmac(&salted, b"Client Key");
+    let signature = hmac(&Sha256::digest(&client_key), auth_message.as_bytes());
+    let proof: Vec<u8> = client_key.iter().zip(&signature).map(|(k, s)| k ^ s).collect();
+    format!("{without_proof},p={}", STANDARD.encode(proof))
+}
+
+/// Test SCRAM bound to the TLS server certificate (tls-server-end-point).
+#[test]
+fn scram_channel_binding() {
+    use oxidb_server::auth::{Role, UserStore};
+    use oxidb_server::scram::ScramState;
+    use oxidb_server::tls::server_end_point;
+
+    let fixture = |name: &str| std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tls").join(name);
+    let binding = server_end_point(&fixture("server.pem")).unwrap();
+    assert_eq!(binding.len(), 32, "ECDSA-SHA256 certificates hash with SHA-256");
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut store = UserStore::open(dir.path()).unwrap();
+    store.create_user("alice", "pass123", Role::ReadWrite).unwrap();
+    let password = store.get_user("alice").unwrap().password_hash.clone();
+
+    // Bound to the certificate the server presents
+    let client_first = "p=tls-server-end-point,,n=alice,r=clientnonce";
+    let (server_first, state) = ScramState::process_client_first(client_first, &store, Some(&binding)).unwrap();
+    let client_final = scram_client_final(&password, client_first, &server_first, &binding);
+    let (server_final, role) = state.process_client_final(&client_final, &store).unwrap();
+    assert!(server_final.starts_with("v="));
+    assert_eq!(role, Role::ReadWrite);
+
+    // A relay terminating TLS with a different certificate
+    let relay_binding = server_end_point(&fixture("client.pem")).unwrap();
+    let (server_first, state) = ScramState::process_client_first(client_first, &store, Some(&binding)).unwrap();
+    let client_final = scram_client_final(&password, client_first, &server_first, &relay_binding);
+    assert_eq!(state.process_client_final(&client_final, &store).unwrap_err(), "channel binding mismatch");
+
+    // Plain connections: no binding, and none can be requested
+    let client_first = "n,,n=alice,r=clientnonce";
+    let (server_first, state) = ScramState::process_client_first(client_first, &store, None).unwrap();
+    let client_final = scram_client_final(&password, client_first, &server_first, &[]);
+    assert!(state.process_client_final(&client_final, &store).is_ok());
+    assert!(client_final.starts_with("c=biws,"));
+    assert!(ScramState::process_client_first("p=tls-server-end-point,,n=alice,r=x", &store, None).is_err());
+
+    // Over TLS, "y" means the binding was stripped somewhere; other types aren't offered
+    assert!(ScramState::process_client_first("y,,n=alice,r=x", &store, Some(&binding)).is_err());
+    assert!(ScramState::process_client_first("y,,n=alice,r=x", &store, None).is_ok());
+    let err = ScramState::process_client_first("p=tls-unique,,n=alice,r=x", &store, Some(&binding)).err().unwrap();
+    assert_eq!(err, "unsupported channel binding type 'tls-unique'");
+}
+
+/// Run a request the way `dispatch_request` does once the role check has
+/// passed: bucket ACL check, owner stamping, handler, then listing filter.
+fn run_as(db: &Arc<OxiDb>, session: &oxidb_server::session::Session, request: serde_json::Value) -> serde_json::Value {
+    use oxidb_server::{handler, rbac};
+
+    let cmd = request["cmd"].as_str().unwrap().to_string();
+    if let Err(msg) = rbac::bucket_permitted(db, session, &cmd, &request) {
+        return serde_json::from_slice(&handler::err_bytes(&msg)).unwrap();
+    }
+    let bucket = request.get("bucket").and_then(|v| v.as_str()).map(str::to_string);
+    let request = rbac::with_bucket_owner(session, &cmd, request);
+    let resp = handler::handle_request(db, request, &mut None);
+    let resp = rbac::filter_readable_buckets(db, session, &cmd, bucket.as_deref(), resp);
+    serde_json::from_slice(&resp).unwrap()
+}
+
+/// Test a private bucket is usable by its owner and closed to other users.
+#[test]
+fn bucket_acl_restricts_other_users() {
+    use oxidb_server::auth::Role;
+    use oxidb_server::session::Session;
+
+    let dir = tempfile::tempdir().unwrap();
+    let db = Arc::new(OxiDb::open(dir.path()).unwrap());
+    let mut alice = Session::new();
+    alice.set_authenticated("alice".to_string(), Role::ReadWrite);
+    let mut bob = Session::new();
+    bob.set_authenticated("bob".to_string(), Role::ReadWrite);
+    let mut admin = Session::new();
+    admin.set_authenticated("root".to_string(), Role::Admin);
+    let data = "aGVsbG8="; // "hello"
+
+    // Creating a bucket makes the session's user its owner, whatever the client sends
+    let create = json!({"cmd": "create_bucket", "bucket": "alice-private", "owner": "bob"});
+    assert_eq!(run_as(&db, &alice, create.clone())["ok"], true);
+    let resp = run_as(&db, &alice, json!({"cmd": "get_bucket_acl", "bucket": "alice-private"}));
+    assert_eq!(resp["data"]["acl"], json!({"owner": "alice", "readers": [], "writers": []}));
+    assert_eq!(run_as(&db, &bob, create)["ok"], false);
+
+    // The owner reads and writes as before
+    let put = json!({"cmd": "put_object", "bucket": "alice-private", "key": "a.txt", "data": data});
+    assert_eq!(run_as(&db, &alice, put.clone())["ok"], true);
+    let get = json!({"cmd": "get_object", "bucket": "alice-private", "key": "a.txt"});
+    assert_eq!(run_as(&db, &alice, get.clone())["data"]["content"], data);
+
+    // Everyone else is denied, reads and writes alike
+    for request in [
+        get.clone(),
+        put.clone(),
+        json!({"cmd": "head_object", "bucket": "alice-private", "key": "a.txt"}),
+        json!({"cmd": "list_objects", "bucket": "alice-private"}),
+        json!({"cmd": "delete_object", "bucket": "alice-private", "key": "a.txt"}),
+        json!({"cmd": "delete_bucket", "bucket": "alice-private"}),
+        json!({"cmd": "copy_object", "src_bucket": "alice-private", "src_key": "a.txt", "dst_bucket": "bob", "dst_key": "a.txt"}),
+        json!({"cmd": "set_bucket_acl", "bucket": "alice-private", "acl": null}),
+    ] {
+        let resp = run_as(&db, &bob, request.clone());
+        assert_eq!(resp["ok"], false, "{request} should be denied");
+        assert!(resp["error"].as_str().unwrap().starts_with("permission denied"), "{resp}");
+    }
+    let upload = run_as(&db, &alice, json!({"cmd": "create_multipart_upload", "bucket": "alice-private", "key": "big"}));
+    let upload_id = upload["data"]["upload_id"].as_str().unwrap();
+    let part = json!({"cmd": "upload_part", "upload_id": upload_id, "part_number": 1, "data": data});
+    assert_eq!(run_as(&db, &bob, part.clone())["ok"], false);
+    assert_eq!(run_as(&db, &alice, part)["ok"], true);
+
+    // Listed readers may read but not write; admins bypass the ACL
+    let shared = json!({"owner": "alice", "readers": ["bob"], "writers": []});
+    assert_eq!(run_as(&db, &alice, json!({"cmd": "set_bucket_acl", "bucket": "alice-private", "acl": shared}))["ok"], true);
+    assert_eq!(run_as(&db, &bob, get.clone())["data"]["content"], data);
+    assert_eq!(run_as(&db, &bob, put)["ok"], false);
+    assert_eq!(run_as(&db, &admin, get)["data"]["content"], data);
+
+    // Buckets without an ACL stay open, and only an admin can give them one
+    let resp = run_as(&db, &bob, json!({"cmd": "put_object", "bucket": "shared", "key": "b.txt", "data": data}));
+    assert_eq!(resp["ok"], true);
+    let resp = run_as(&db, &alice, json!({"cmd": "get_object", "bucket": "shared", "key": "b.txt"}));
+    assert_eq!(resp["ok"], true);
+    let claim = json!({"cmd": "set_bucket_acl", "bucket": "shared", "acl": {"owner": "bob"}});
+    assert_eq!(run_as(&db, &bob, claim.clone())["ok"], false);
+    assert_eq!(run_as(&db, &admin, claim)["ok"], true);
+    let resp = run_as(&db, &alice, json!({"cmd": "get_object", "bucket": "shared", "key": "b.txt"}));
+    assert_eq!(resp["ok"], false);
+}
+
+/// Test bucket listings and cross-bucket search hide unreadable buckets.
+#[test]
+fn bucket_acl_filters_listings() {
+    use oxidb_server::auth::Role;
+    use oxidb_server::session::Session;
+
+    let dir = tempfile::tempdir().unwrap();
+    let db = Arc::new(OxiDb::open(dir.path()).unwrap());
+    let mut alice = Session::new();
+    alice.set_authenticated("alice".to_string(), Role::ReadWrite);
+    let mut bob = Session::new();
+    bob.set_authenticated("bob".to_string(), Role::Read);
+
+    let text = "secret quarterly forecast";
+    assert_eq!(run_as(&db, &alice, json!({"cmd": "create_bucket", "bucket": "alice-private"}))["ok"], true);
+    db.put_object("alice-private", "plan.txt", text.as_bytes(), "text/plain", HashMap::new()).unwrap();
+    db.put_object("public", "notes.txt", text.as_bytes(), "text/plain", HashMap::new()).unwrap();
+
+    assert_eq!(run_as(&db, &alice, json!({"cmd": "list_buckets"}))["data"], json!(["alice-private", "public"]));
+    assert_eq!(run_as(&db, &bob, json!({"cmd": "list_buckets"}))["data"], json!(["public"]));
+
+    let search = json!({"cmd": "search", "query": "quarterly"});
+    for _ in 0..50 {
+        if run_as(&db, &alice, search.clone())["data"].as_array().unwrap().len() == 2 {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert_eq!(run_as(&db, &alice, search.clone())["data"].as_array().unwrap().len(), 2);
+    let hits = run_as(&db, &bob, search)["data"].clone();
+    assert_eq!(hits.as_array().unwrap().len(), 1);
+    assert_eq!(hits[0]["bucket"], "public");
+    let resp = run_as(&db, &bob, json!({"cmd": "search", "query": "quarterly", "bucket": "alice-private"}));
+    assert_eq!(resp["ok"], false);
+}
@@ -0,0 +1,147 @@
+//! Graceful shutdown: a server loop built like main.rs's, using `Shutdown` to
+//! stop accepting, drain connections, and roll back open transactions.
+
+use std::io::Read;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use serde_json::{Value, json};
+use tempfile::TempDir;
+
+use oxidb::OxiDb;
+use oxidb_server::protocol::{read_message, write_message};
+use oxidb_server::shutdown::Shutdown;
+
+/// Lets the test hold a request between reading it and handling it: requests
+/// with `"hold": true` report in on `held` and wait for `release`.
+struct Gate {
+    held: mpsc::Sender<()>,
+    release: Mutex<mpsc::Receiver<()>>,
+}
+
+fn start_server(db: Arc<OxiDb>, shutdown: Arc<Shutdown>, gate: Arc<Gate>) -> (SocketAddr, JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let accept = std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            if shutdown.is_requested() {
+                break;
+            }
+            let (db, shutdown, gate) = (Arc::clone(&db), Arc::clone(&shutdown), Arc::clone(&gate));
+            std::thread::spawn(move || handle_client(stream.unwrap(), &db, &shutdown, &gate));
+        }
+    });
+    (addr, accept)
+}
+
+fn handle_client(mut stream: TcpStream, db: &Arc<OxiDb>, shutdown: &Shutdown, gate: &Gate) {
+    let Some(_guard) = shutdown.track(&stream) else {
+        return;
+    };
+    let mut active_tx: Option<u64> = None;
+    while let Ok(msg) = read_message(&mut stream) {
+        let request: Value = serde_json::from_slice(&msg).unwrap();
+        if request["hold"] == true {
+            gate.held.send(()).unwrap();
+            gate.release.lock().unwrap().recv().unwrap();
+        }
+        let resp = oxidb_server::handler::handle_request(db, request, &mut active_tx);
+        if write_message(&mut stream, &resp).is_err() || shutdown.is_requested() {
+            break;
+        }
+    }
+    if let Some(tx_id) = active_tx {
+        let _ = db.rollback_transaction(tx_id);
+    }
+}
+
+fn send(stream: &mut TcpStream, request: &Value) {
+    write_message(stream, request.to_string().as_bytes()).unwrap();
+}
+
+fn recv(stream: &mut TcpStream) -> Value {
+    serde_json::from_slice(&read_message(stream).unwrap()).unwrap()
+}
+
+fn connect(addr: SocketAddr) -> TcpStream {
+    let stream = TcpStream::connect(addr).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream
+}
+
+fn assert_closed(stream: &mut TcpStream) {
+    let mut buf = [0u8; 1];
+    assert_eq!(stream.read(&mut buf).unwrap(), 0, "connection should be closed");
+}
+
+#[test]
+fn shutdown_drains_requests_and_rolls_back_transactions() {
+    let dir = TempDir::new().unwrap();
+    let db = Arc::new(OxiDb::open(dir.path()).unwrap());
+    db.create_index("notes", "name").unwrap();
+    let fidx = dir.path().join("notes.fidx");
+
+    let shutdown = Arc::new(Shutdown::new());
+    let (held_tx, held_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel();
+    let gate = Arc::new(Gate { held: held_tx, release: Mutex::new(release_rx) });
+    let (addr, accept) = start_server(Arc::clone(&db), Arc::clone(&shutdown), gate);
+
+    // An idle connection with an uncommitted transaction
+    let mut idle = connect(addr);
+    send(&mut idle, &json!({"cmd": "begin_tx"}));
+    assert_eq!(recv(&mut idle)["ok"], true);
+    send(&mut idle, &json!({"cmd": "insert", "collection": "notes", "doc": {"name": "uncommitted"}}));
+    assert_eq!(recv(&mut idle)["data"], "buffered");
+
+    // A request in flight when shutdown starts
+    let mut busy = connect(addr);
+    send(&mut busy, &json!({"cmd": "insert", "collection": "notes", "doc": {"name": "in-flight"}, "hold": true}));
+    held_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(shutdown.open_connections(), 2);
+
+    shutdown.request();
+    release_tx.send(()).unwrap();
+
+    // The in-flight request completes, then both connections are closed
+    let resp = recv(&mut busy);
+    assert_eq!(resp["ok"], true, "{resp}");
+    assert_closed(&mut busy);
+    assert_closed(&mut idle);
+    assert!(shutdown.wait_for_connections(Duration::from_secs(5)));
+    assert_eq!(db.rollback_all_transactions(), 0, "connections roll back their own transactions");
+
+    // New connections are turned away and the accept loop stops
+    let mut late = connect(addr);
+    accept.join().unwrap();
+    assert_closed(&mut late);
+    assert!(shutdown.track(&late).is_none());
+
+    // Flushing writes the index cache for the final document count
+    assert!(oxidb::index_persist::load_field_indexes(&fidx, 1, 2).is_none());
+    db.flush_indexes();
+    assert!(oxidb::index_persist::load_field_indexes(&fidx, 1, 2).is_some());
+
+    drop(db);
+    let db = OxiDb::open(dir.path()).unwrap();
+    let names: Vec<Value> = db.find("notes", &json!({})).unwrap().iter().map(|d| d["name"].clone()).collect();
+    assert_eq!(names, [json!("in-flight")]);
+}
+
+#[test]
+fn wait_for_connections_times_out_on_a_stuck_connection() {
+    let shutdown = Shutdown::new();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server_side, _) = listener.accept().unwrap();
+
+    let guard = shutdown.track(&server_side).unwrap();
+    shutdown.request();
+    assert!(!shutdown.wait_for_connections(Duration::from_millis(50)));
+    assert_eq!(shutdown.open_connections(), 1);
+    drop(guard);
+    assert!(shutdown.wait_for_connections(Duration::from_millis(50)));
+    drop(client);
+}
@@ -0,0 +1,156 @@
+//! Slow query log: the server binary times each request and keeps the
+//! queries that run past the threshold for admins to read back with
+//! `query_slow_log`.
+
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use serde_json::{Value, json};
+use tempfile::TempDir;
+
+use oxidb::{Durability, OxiDb};
+use oxidb_server::auth::{Role, UserStore};
+use oxidb_server::protocol::{read_message, write_message};
+use oxidb_server::slowlog::SlowQueryLog;
+
+const THRESHOLD: Duration = Duration::from_millis(10);
+
+/// A server process, killed when dropped.
+struct Server {
+    child: Child,
+    addr: SocketAddr,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn start_server(data_dir: &Path, envs: &[(&str, &str)]) -> Server {
+    let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let child = Command::new(env!("CARGO_BIN_EXE_oxidb-server"))
+        .env("OXIDB_ADDR", addr.to_string())
+        .env("OXIDB_DATA", data_dir)
+        .env("OXIDB_SLOW_QUERY_MS", THRESHOLD.as_millis().to_string())
+        .envs(envs.iter().copied())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let server = Server { child, addr };
+    for _ in 0..100 {
+        if TcpStream::connect(server.addr).is_ok() {
+            return server;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    panic!("server didn't start listening on {addr}");
+}
+
+fn send(stream: &mut TcpStream, request: Value) -> Value {
+    write_message(stream, request.to_string().as_bytes()).unwrap();
+    serde_json::from_slice(&read_message(stream).unwrap()).unwrap()
+}
+
+fn login(addr: SocketAddr, user: &str) -> TcpStream {
+    let mut client = TcpStream::connect(addr).unwrap();
+    let resp = send(&mut client, json!({"cmd": "auth_simple", "username": user, "password": "secret"}));
+    assert_eq!(resp["ok"], true, "login as {user} failed: {resp}");
+    client
+}
+
+#[test]
+fn slow_scan_is_logged_and_indexed_lookup_is_not() {
+    let dir = TempDir::new().unwrap();
+    {
+        let db = OxiDb::open(dir.path()).unwrap();
+        let docs: Vec<Value> = (0..50_000).map(|i| json!({"n": i, "tag": format!("item-{i}")})).collect();
+        db.insert_many_with_durability("items", docs, Durability::Relaxed).unwrap();
+        db.create_index("items", "n").unwrap();
+        let mut users = UserStore::open(dir.path()).unwrap();
+        users.create_user("root", "secret", Role::Admin).unwrap();
+        users.create_user("alice", "secret", Role::ReadWrite).unwrap();
+    }
+    let server = start_server(dir.path(), &[("OXIDB_AUTH", "true")]);
+    let mut admin = login(server.addr, "root");
+    let mut alice = login(server.addr, "alice");
+    // Load the collection first, with a command the log doesn't time
+    assert_eq!(send(&mut alice, json!({"cmd": "describe", "collection": "items"}))["ok"], true);
+
+    let resp = send(&mut alice, json!({"cmd": "find", "collection": "items", "query": {"n": 4242}}));
+    assert_eq!(resp["data"][0]["tag"], "item-4242");
+    assert_eq!(send(&mut admin, json!({"cmd": "query_slow_log"}))["data"], json!([]));
+
+    let query = json!({"tag": {"$regex": "^item-4242$"}});
+    let resp = send(&mut alice, json!({"cmd": "find", "collection": "items", "query": query}));
+    assert_eq!(resp["data"][0]["n"], 4242);
+
+    let logged = send(&mut admin, json!({"cmd": "query_slow_log", "collection": "items"}))["data"].clone();
+    assert_eq!(logged.as_array().unwrap().len(), 1, "{logged}");
+    assert_eq!(logged[0]["cmd"], "find");
+    assert_eq!(logged[0]["collection"], "items");
+    assert_eq!(logged[0]["query"], query);
+    assert_eq!(logged[0]["indexed"], false);
+    assert!(logged[0]["duration_ms"].as_f64().unwrap() >= THRESHOLD.as_millis() as f64);
+    assert!(logged[0]["ts"].is_string());
+
+    // The query literals are for admins only, and nothing lands in a collection
+    let resp = send(&mut alice, json!({"cmd": "query_slow_log"}));
+    assert!(resp["error"].as_str().unwrap().starts_with("permission denied"), "{resp}");
+    let resp = send(&mut admin, json!({"cmd": "list_collections"}));
+    assert!(!resp["data"].as_array().unwrap().contains(&json!("_slow_queries")), "{resp}");
+}
+
+#[test]
+fn query_slow_log_needs_the_log_enabled() {
+    let dir = TempDir::new().unwrap();
+    let server = start_server(dir.path(), &[("OXIDB_SLOW_QUERY_MS", "0")]);
+    let mut client = TcpStream::connect(server.addr).unwrap();
+    let resp = send(&mut client, json!({"cmd": "query_slow_log"}));
+    assert_eq!(resp["error"], "the slow query log is disabled (set OXIDB_SLOW_QUERY_MS)");
+}
+
+#[test]
+fn only_timed_commands_past_the_threshold_are_recorded() {
+    let dir = TempDir::new().unwrap();
+    let db = OxiDb::open(dir.path()).unwrap();
+    db.create_index("items", "n").unwrap();
+    let log = SlowQueryLog::new(THRESHOLD, None);
+    let slow = THRESHOLD * 2;
+
+    let find = json!({"cmd": "find", "collection": "items", "query": {"n": {"$gt": 5}}});
+    assert!(!log.observe(&db, &find, THRESHOLD / 2));
+    assert!(!log.observe(&db, &json!({"cmd": "insert", "collection": "items", "doc": {}}), slow));
+    assert!(log.entries(None, usize::MAX).is_empty());
+
+    assert!(log.observe(&db, &find, slow));
+    let pipeline = json!([{"$match": {"n": 3}}, {"$group": {"_id": null, "total": {"$sum": "$n"}}}]);
+    assert!(log.observe(&db, &json!({"cmd": "aggregate", "collection": "items", "pipeline": pipeline}), slow));
+
+    let logged = log.entries(None, usize::MAX);
+    assert_eq!(logged.len(), 2);
+    assert_eq!(logged[0]["cmd"], "find");
+    assert_eq!(logged[0]["indexed"], true);
+    assert_eq!(logged[0]["duration_ms"], 20.0);
+    assert_eq!(logged[1]["cmd"], "aggregate");
+    assert_eq!(logged[1]["query"], json!({"n": 3}));
+    assert_eq!(logged[1]["pipeline"], pipeline);
+    assert_eq!(logged[1]["indexes"], json!(["n"]));
+    assert_eq!(log.entries(None, 1), logged[1..]);
+    assert!(log.entries(Some("other"), usize::MAX).is_empty());
+}
+
+#[test]
+fn oldest_entries_are_dropped_past_the_cap() {
+    let dir = TempDir::new().unwrap();
+    let db = OxiDb::open(dir.path()).unwrap();
+    let log = SlowQueryLog::with_max_entries(THRESHOLD, None, 2);
+    for tag in ["a", "b", "c"] {
+        assert!(log.observe(&db, &json!({"cmd": "find", "collection": "items", "query": {"tag": tag}}), THRESHOLD));
+    }
+    let tags: Vec<Value> = log.entries(None, usize::MAX).iter().map(|e| e["query"]["tag"].clone()).collect();
+    assert_eq!(tags, [json!("b"), json!("c")]);
+}
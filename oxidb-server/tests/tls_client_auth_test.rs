@@ -0,0 +1,174 @@
+//! Client certificate authentication: a TLS server built like main.rs's with
+//! `OXIDB_TLS_CLIENT_CA` set, using the fixtures under `tests/fixtures/tls`.
+//! `client.pem` (CN `ingest-svc`) and `reporting.pem` (SAN
+//! `reports.internal`) are signed by `client-ca.pem`; `untrusted.pem` isn't.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, mpsc};
+use std::time::Duration;
+
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use serde_json::{Value, json};
+
+use oxidb_server::protocol::{read_message, write_message};
+use oxidb_server::rbac;
+use oxidb_server::session::Session;
+use oxidb_server::tls::{CertIdentity, ClientCertRoles};
+
+type ClientStream = rustls::StreamOwned<rustls::ClientConnection, TcpStream>;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/tls")
+        .join(name)
+}
+
+fn certs(name: &str) -> Vec<CertificateDer<'static>> {
+    let mut reader = BufReader::new(File::open(fixture(name)).unwrap());
+    rustls_pemfile::certs(&mut reader).collect::<Result<_, _>>().unwrap()
+}
+
+fn key(name: &str) -> PrivateKeyDer<'static> {
+    let mut reader = BufReader::new(File::open(fixture(name)).unwrap());
+    rustls_pemfile::private_key(&mut reader).unwrap().unwrap()
+}
+
+fn roles() -> ClientCertRoles {
+    ClientCertRoles::parse("ingest-svc=readWrite, reports.internal=read").unwrap()
+}
+
+/// Accept one connection, finish the handshake and report its outcome. On
+/// success, answer one request with the session the certificate produced.
+fn start_server() -> (SocketAddr, mpsc::Receiver<Result<Option<CertIdentity>, String>>) {
+    let config = oxidb_server::tls::load_tls_config_with_client_ca(
+        &fixture("server.pem"),
+        &fixture("server.key"),
+        Some(&fixture("client-ca.pem")),
+    )
+    .expect("failed to load TLS config");
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let (sock, _) = listener.accept().unwrap();
+        sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let conn = rustls::ServerConnection::new(config).unwrap();
+        let mut stream = rustls::StreamOwned::new(conn, sock);
+        while stream.conn.is_handshaking() {
+            if let Err(e) = stream.conn.complete_io(&mut stream.sock) {
+                tx.send(Err(e.to_string())).unwrap();
+                return;
+            }
+        }
+        let identity = roles().identify(stream.conn.peer_certificates());
+        tx.send(Ok(identity.clone())).unwrap();
+
+        let mut session = Session::new();
+        if let Some(identity) = identity {
+            session.set_authenticated_as(identity.name, &identity.role);
+        }
+        if read_message(&mut stream).is_ok() {
+            let resp = json!({
+                "authenticated": session.is_authenticated(),
+                "user": session.username_str(),
+                "role": session.role_name(),
+                "can_insert": rbac::session_permitted(&session, None, "insert"),
+                "can_find": rbac::session_permitted(&session, None, "find"),
+            });
+            write_message(&mut stream, resp.to_string().as_bytes()).unwrap();
+        }
+    });
+
+    (addr, rx)
+}
+
+fn connect(addr: SocketAddr, client_cert: Option<(&str, &str)>) -> ClientStream {
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in certs("ca.pem") {
+        roots.add(cert).unwrap();
+    }
+    let builder = rustls::ClientConfig::builder().with_root_certificates(roots);
+    let config = match client_cert {
+        Some((cert, key_file)) => builder.with_client_auth_cert(certs(cert), key(key_file)).unwrap(),
+        None => builder.with_no_client_auth(),
+    };
+    let conn = rustls::ClientConnection::new(Arc::new(config), ServerName::try_from("localhost").unwrap()).unwrap();
+    let sock = TcpStream::connect(addr).unwrap();
+    sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    rustls::StreamOwned::new(conn, sock)
+}
+
+/// Send one request and return the server's description of the session.
+fn whoami(stream: &mut ClientStream) -> Value {
+    write_message(stream, json!({"cmd": "ping"}).to_string().as_bytes()).unwrap();
+    serde_json::from_slice(&read_message(stream).unwrap()).unwrap()
+}
+
+#[test]
+fn common_name_maps_to_role() {
+    let (addr, outcome) = start_server();
+    let mut client = connect(addr, Some(("client.pem", "client.key")));
+    let resp = whoami(&mut client);
+
+    let identity = outcome.recv().unwrap().unwrap().unwrap();
+    assert_eq!(identity, CertIdentity { name: "ingest-svc".into(), role: "readWrite".into() });
+    assert_eq!(resp["authenticated"], true);
+    assert_eq!(resp["user"], "ingest-svc");
+    assert_eq!(resp["role"], "readWrite");
+    assert_eq!(resp["can_insert"], true);
+}
+
+#[test]
+fn subject_alternative_name_maps_to_role() {
+    let (addr, outcome) = start_server();
+    let mut client = connect(addr, Some(("reporting.pem", "reporting.key")));
+    let resp = whoami(&mut client);
+
+    assert_eq!(outcome.recv().unwrap().unwrap().unwrap().name, "reports.internal");
+    assert_eq!(resp["user"], "reports.internal");
+    assert_eq!(resp["role"], "read");
+    assert_eq!(resp["can_find"], true);
+    assert_eq!(resp["can_insert"], false);
+}
+
+#[test]
+fn unmapped_certificate_has_no_identity() {
+    // Neither reporting-svc nor reports.internal is mapped: the connection
+    // stays unauthenticated and logs in with a password as usual
+    let only_ingest = ClientCertRoles::parse("ingest-svc=readWrite").unwrap();
+    assert_eq!(only_ingest.identify(Some(&certs("reporting.pem"))), None);
+    assert_eq!(only_ingest.identify(None), None);
+    assert_eq!(only_ingest.identify(Some(&certs("client.pem"))).unwrap().name, "ingest-svc");
+}
+
+#[test]
+fn untrusted_certificate_is_rejected_at_handshake() {
+    let (addr, outcome) = start_server();
+    let mut client = connect(addr, Some(("untrusted.pem", "untrusted.key")));
+    assert!(write_message(&mut client, b"{\"cmd\":\"ping\"}").is_err() || read_message(&mut client).is_err());
+
+    let err = outcome.recv_timeout(Duration::from_secs(5)).unwrap().unwrap_err();
+    assert!(err.contains("UnknownIssuer") || err.contains("certificate"), "{err}");
+}
+
+#[test]
+fn missing_certificate_is_rejected_at_handshake() {
+    let (addr, outcome) = start_server();
+    let mut client = connect(addr, None);
+    assert!(write_message(&mut client, b"{\"cmd\":\"ping\"}").is_err() || read_message(&mut client).is_err());
+
+    let err = outcome.recv_timeout(Duration::from_secs(5)).unwrap().unwrap_err();
+    assert!(err.contains("certificate"), "{err}");
+}
+
+#[test]
+fn role_mapping_rejects_malformed_entries() {
+    assert!(ClientCertRoles::parse("").unwrap().identify(Some(&certs("client.pem"))).is_none());
+    assert!(ClientCertRoles::parse("ingest-svc").is_err());
+    assert!(ClientCertRoles::parse("=read").is_err());
+    assert!(ClientCertRoles::parse("ingest-svc=").is_err());
+}
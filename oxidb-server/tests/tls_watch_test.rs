@@ -0,0 +1,162 @@
+//! Change streams over TLS.
+//!
+//! Starts a TLS server on a random port that mirrors the watch handling in
+//! oxidb-server/src/main.rs, using the test CA and certificate under
+//! `tests/fixtures/tls`.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rustls::pki_types::ServerName;
+use serde_json::{Value, json};
+use tempfile::TempDir;
+
+use oxidb::{OxiDb, WatchFilter};
+use oxidb_server::protocol::{read_message, write_message};
+use oxidb_server::watch::{self, WatchExit};
+
+type ServerStream = rustls::StreamOwned<rustls::ServerConnection, TcpStream>;
+type ClientStream = rustls::StreamOwned<rustls::ClientConnection, TcpStream>;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/tls")
+        .join(name)
+}
+
+fn start_tls_server(db: Arc<OxiDb>) -> SocketAddr {
+    let config = oxidb_server::tls::load_tls_config(&fixture("server.pem"), &fixture("server.key"))
+        .expect("failed to load TLS config");
+    let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind");
+    let addr = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { break };
+            let db = Arc::clone(&db);
+            let config = Arc::clone(&config);
+            std::thread::spawn(move || {
+                let conn = rustls::ServerConnection::new(config).unwrap();
+                let mut tls = rustls::StreamOwned::new(conn, stream);
+                handle_tls_client(&mut tls, &db);
+            });
+        }
+    });
+
+    addr
+}
+
+fn handle_tls_client(stream: &mut ServerStream, db: &Arc<OxiDb>) {
+    let mut active_tx: Option<u64> = None;
+    while let Ok(msg) = read_message(stream) {
+        let request: Value = serde_json::from_slice(&msg).unwrap();
+        if request["cmd"] == "watch" {
+            let handle = db.watch(WatchFilter::All, None).unwrap();
+            let ack = oxidb_server::handler::ok_bytes(json!("watching"));
+            write_message(stream, &ack).unwrap();
+
+            stream.sock.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+            let exit = watch::watch_single(stream, db, handle).unwrap();
+            stream.sock.set_read_timeout(None).unwrap();
+            if exit == WatchExit::Disconnected {
+                break;
+            }
+            continue;
+        }
+        let resp = oxidb_server::handler::handle_request(db, request, &mut active_tx);
+        if write_message(stream, &resp).is_err() {
+            break;
+        }
+    }
+}
+
+fn connect_tls(addr: SocketAddr) -> ClientStream {
+    let mut roots = rustls::RootCertStore::empty();
+    let mut reader = BufReader::new(File::open(fixture("ca.pem")).unwrap());
+    for cert in rustls_pemfile::certs(&mut reader) {
+        roots.add(cert.unwrap()).unwrap();
+    }
+    let config = rustls::ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let conn = rustls::ClientConnection::new(
+        Arc::new(config),
+        ServerName::try_from("localhost").unwrap(),
+    )
+    .unwrap();
+    let sock = TcpStream::connect(addr).unwrap();
+    sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    rustls::StreamOwned::new(conn, sock)
+}
+
+fn send(stream: &mut ClientStream, request: Value) {
+    write_message(stream, request.to_string().as_bytes()).unwrap();
+}
+
+fn recv(stream: &mut ClientStream) -> Value {
+    serde_json::from_slice(&read_message(stream).unwrap()).unwrap()
+}
+
+#[test]
+fn watch_over_tls_receives_events_and_unwatches() {
+    let dir = TempDir::new().unwrap();
+    let db = Arc::new(OxiDb::open(dir.path()).unwrap());
+    let addr = start_tls_server(Arc::clone(&db));
+    let mut client = connect_tls(addr);
+
+    send(&mut client, json!({"cmd": "watch"}));
+    let ack = recv(&mut client);
+    assert_eq!(ack["ok"], true);
+    assert_eq!(ack["data"], "watching");
+
+    let id = db.insert("orders", json!({"item": "Widget"})).unwrap();
+    let event = recv(&mut client);
+    assert_eq!(event["event"], "change");
+    assert_eq!(event["data"]["operation"], "insert");
+    assert_eq!(event["data"]["collection"], "orders");
+    assert_eq!(event["data"]["doc_id"], id);
+
+    send(&mut client, json!({"cmd": "unwatch"}));
+    let ack = recv(&mut client);
+    assert_eq!(ack["ok"], true);
+    assert_eq!(ack["data"], "unwatched");
+
+    // The connection is back in request mode.
+    send(&mut client, json!({"cmd": "ping"}));
+    let resp = recv(&mut client);
+    assert_eq!(resp["data"], "pong");
+}
+
+#[test]
+fn watch_over_tls_reports_overflow() {
+    let dir = TempDir::new().unwrap();
+    let db = Arc::new(OxiDb::open(dir.path()).unwrap());
+    let addr = start_tls_server(Arc::clone(&db));
+    let mut client = connect_tls(addr);
+
+    send(&mut client, json!({"cmd": "watch"}));
+    assert_eq!(recv(&mut client)["data"], "watching");
+
+    // Flood past the 256-event subscriber buffer faster than it drains.
+    let docs: Vec<Value> = (0..2000).map(|i| json!({"n": i})).collect();
+    db.insert_many("bulk", docs).unwrap();
+
+    let mut saw_overflow = false;
+    let mut changes = 0;
+    while !saw_overflow && changes < 2000 {
+        let msg = recv(&mut client);
+        match msg["event"].as_str() {
+            Some("overflow") => {
+                assert!(msg["data"]["dropped"].as_u64().unwrap() > 0);
+                saw_overflow = true;
+            }
+            Some("change") => changes += 1,
+            other => panic!("unexpected message: {other:?}"),
+        }
+    }
+    assert!(saw_overflow);
+}
@@ -1,8 +1,12 @@
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::crypto::EncryptionKey;
 use crate::error::{Error, Result};
@@ -16,16 +20,171 @@ pub struct ObjectMeta {
     pub etag: String,
     pub created_at: String,
     pub metadata: HashMap<String, String>,
+    /// Set on objects written while the bucket has versioning enabled.
+    /// Objects that predate versioning are archived as version `"null"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+    /// `true` for the marker left by deleting a key in a versioned bucket.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub delete_marker: bool,
+    /// Hex SHA-256 of the content, checked by `get_object_verified`.
+    /// Objects written before checksums were recorded have none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// Version ID given to an object archived without one (written before
+/// versioning was enabled on its bucket).
+const NULL_VERSION: &str = "null";
+
+/// Marker file whose presence enables versioning for a bucket.
+const VERSIONING_MARKER: &str = "versioning";
+
+/// File holding a bucket's access control list, if it has one.
+const ACL_FILE: &str = "acl.json";
+
+/// Who may use a bucket. A bucket without an ACL is open to every user whose
+/// role allows the command; once it has one, only the owner and the listed
+/// users may read it, and only the owner and writers may change it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BucketAcl {
+    pub owner: String,
+    #[serde(default)]
+    pub readers: Vec<String>,
+    #[serde(default)]
+    pub writers: Vec<String>,
+}
+
+impl BucketAcl {
+    /// Owners and writers can read as well.
+    pub fn can_read(&self, user: &str) -> bool {
+        self.can_write(user) || self.readers.iter().any(|u| u == user)
+    }
+
+    pub fn can_write(&self, user: &str) -> bool {
+        self.owner == user || self.writers.iter().any(|u| u == user)
+    }
+}
+
+/// Object metadata key holding an RFC 3339 expiry time. Expired objects read
+/// as absent and are removed by `purge_expired`.
+pub const EXPIRES_AT_KEY: &str = "expires_at";
+
+impl ObjectMeta {
+    /// The object's expiry time, if it has a valid `expires_at` entry.
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.metadata
+            .get(EXPIRES_AT_KEY)
+            .and_then(|v| DateTime::parse_from_rfc3339(v).ok())
+            .map(|t| t.with_timezone(&Utc))
+    }
+}
+
+/// Validate the `expires_at` entry of user-supplied metadata.
+fn parse_expiry(metadata: &HashMap<String, String>) -> Result<Option<DateTime<Utc>>> {
+    match metadata.get(EXPIRES_AT_KEY) {
+        Some(v) => DateTime::parse_from_rfc3339(v)
+            .map(|t| Some(t.with_timezone(&Utc)))
+            .map_err(|e| Error::InvalidExpiry(format!("'{v}' is not an RFC 3339 timestamp: {e}"))),
+        None => Ok(None),
+    }
+}
+
+/// A byte range read from an object. `start` and `end` are inclusive offsets.
+#[derive(Debug, Clone)]
+pub struct ObjectRange {
+    pub data: Vec<u8>,
+    pub start: u64,
+    pub end: u64,
+    pub total_size: u64,
 }
 
 struct BucketState {
     keys: HashMap<String, u64>,
     next_id: u64,
+    versioned: bool,
+    /// Keys with no current object (deleted) whose ID still holds archived versions.
+    archived: HashMap<String, u64>,
+    next_version: u64,
+    /// Expiry times of current objects that have one.
+    expiries: HashMap<String, DateTime<Utc>>,
+    acl: Option<BucketAcl>,
+}
+
+impl BucketState {
+    fn new() -> Self {
+        Self {
+            keys: HashMap::new(),
+            next_id: 0,
+            versioned: false,
+            archived: HashMap::new(),
+            next_version: 1,
+            expiries: HashMap::new(),
+            acl: None,
+        }
+    }
+
+    /// Look up the storage ID of a live (present and unexpired) object.
+    fn live_id(&self, bucket: &str, key: &str) -> Result<u64> {
+        let expired = self.expiries.get(key).is_some_and(|t| *t <= Utc::now());
+        match self.keys.get(key) {
+            Some(&id) if !expired => Ok(id),
+            _ => Err(Error::BlobNotFound {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+            }),
+        }
+    }
+
+    fn set_expiry(&mut self, key: &str, expiry: Option<DateTime<Utc>>) {
+        match expiry {
+            Some(t) => self.expiries.insert(key.to_string(), t),
+            None => self.expiries.remove(key),
+        };
+    }
+
+    /// Reuse the existing ID if the key already exists (or has archived
+    /// versions), otherwise allocate a new one.
+    fn id_for(&mut self, key: &str) -> u64 {
+        if let Some(&existing_id) = self.keys.get(key) {
+            existing_id
+        } else {
+            let id = self.archived.remove(key).unwrap_or_else(|| {
+                let id = self.next_id;
+                self.next_id += 1;
+                id
+            });
+            self.keys.insert(key.to_string(), id);
+            id
+        }
+    }
+
+    /// Allocate the next version ID if versioning is enabled.
+    fn new_version(&mut self) -> Option<String> {
+        if !self.versioned {
+            return None;
+        }
+        let v = self.next_version;
+        self.next_version += 1;
+        Some(format!("{:020}", v))
+    }
+}
+
+/// An in-progress multipart upload. Persisted as `upload.json` next to its
+/// part files so uploads survive a restart.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct MultipartUpload {
+    bucket: String,
+    key: String,
+    content_type: String,
+    metadata: HashMap<String, String>,
 }
 
 pub struct BlobStore {
     base_dir: PathBuf,
     buckets: RwLock<HashMap<String, BucketState>>,
+    uploads_dir: PathBuf,
+    uploads: RwLock<HashMap<String, MultipartUpload>>,
     encryption: Option<Arc<EncryptionKey>>,
 }
 
@@ -51,16 +210,39 @@ impl BlobStore {
             }
         }
 
+        // Uploads live outside `_blobs` so they are never mistaken for buckets.
+        let uploads_dir = data_dir.join("_blob_uploads");
+        let mut uploads = HashMap::new();
+        if uploads_dir.exists() {
+            for entry in std::fs::read_dir(&uploads_dir)? {
+                let entry = entry?;
+                let manifest = entry.path().join("upload.json");
+                if entry.file_type()?.is_dir() && manifest.exists() {
+                    let raw = std::fs::read(manifest)?;
+                    let bytes = match &encryption {
+                        Some(key) => key.decrypt(&raw)?,
+                        None => raw,
+                    };
+                    let upload: MultipartUpload = serde_json::from_slice(&bytes)?;
+                    uploads.insert(entry.file_name().to_string_lossy().to_string(), upload);
+                }
+            }
+        }
+
         Ok(Self {
             base_dir,
             buckets: RwLock::new(buckets),
+            uploads_dir,
+            uploads: RwLock::new(uploads),
             encryption,
         })
     }
 
     fn scan_bucket(bucket_path: &Path, encryption: &Option<Arc<EncryptionKey>>) -> Result<BucketState> {
         let mut keys = HashMap::new();
+        let mut expiries = HashMap::new();
         let mut max_id: u64 = 0;
+        let mut max_version: u64 = 0;
 
         for entry in std::fs::read_dir(bucket_path)? {
             let entry = entry?;
@@ -73,6 +255,10 @@ impl BlobStore {
                         None => raw_meta,
                     };
                     let meta: ObjectMeta = serde_json::from_slice(&meta_bytes)?;
+                    max_version = max_version.max(Self::version_number(&meta));
+                    if let Some(t) = meta.expires_at() {
+                        expiries.insert(meta.key.clone(), t);
+                    }
                     keys.insert(meta.key.clone(), id);
                     if id >= max_id {
                         max_id = id + 1;
@@ -81,12 +267,68 @@ impl BlobStore {
             }
         }
 
+        // Archived versions: `_versions/<id>/<version>.meta`
+        let mut archived = HashMap::new();
+        let versions_root = bucket_path.join("_versions");
+        if versions_root.exists() {
+            for entry in std::fs::read_dir(&versions_root)? {
+                let entry = entry?;
+                let Ok(id) = entry.file_name().to_string_lossy().parse::<u64>() else {
+                    continue;
+                };
+                for version in std::fs::read_dir(entry.path())? {
+                    let version = version?;
+                    if !version.file_name().to_string_lossy().ends_with(".meta") {
+                        continue;
+                    }
+                    let raw_meta = std::fs::read(version.path())?;
+                    let meta_bytes = match encryption {
+                        Some(key) => key.decrypt(&raw_meta)?,
+                        None => raw_meta,
+                    };
+                    let meta: ObjectMeta = serde_json::from_slice(&meta_bytes)?;
+                    max_version = max_version.max(Self::version_number(&meta));
+                    if !keys.contains_key(&meta.key) {
+                        archived.insert(meta.key.clone(), id);
+                    }
+                }
+                if id >= max_id {
+                    max_id = id + 1;
+                }
+            }
+        }
+
+        let acl_path = bucket_path.join(ACL_FILE);
+        let acl = if acl_path.exists() {
+            let raw = std::fs::read(acl_path)?;
+            let bytes = match encryption {
+                Some(key) => key.decrypt(&raw)?,
+                None => raw,
+            };
+            Some(serde_json::from_slice(&bytes)?)
+        } else {
+            None
+        };
+
         Ok(BucketState {
             keys,
             next_id: max_id,
+            versioned: bucket_path.join(VERSIONING_MARKER).exists(),
+            archived,
+            next_version: max_version + 1,
+            expiries,
+            acl,
         })
     }
 
+    /// Numeric value of a meta's version ID (0 for unversioned or `"null"`).
+    fn version_number(meta: &ObjectMeta) -> u64 {
+        meta.version_id
+            .as_deref()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0)
+    }
+
     fn bucket_path(&self, bucket: &str) -> PathBuf {
         self.base_dir.join(bucket)
     }
@@ -99,13 +341,78 @@ impl BlobStore {
         self.base_dir.join(bucket).join(format!("{}.meta", id))
     }
 
+    fn versions_path(&self, bucket: &str, id: u64) -> PathBuf {
+        self.base_dir.join(bucket).join("_versions").join(id.to_string())
+    }
+
+    fn read_meta(&self, path: &Path) -> Result<ObjectMeta> {
+        let raw_meta = std::fs::read(path)?;
+        let meta_bytes = match &self.encryption {
+            Some(key) => key.decrypt(&raw_meta)?,
+            None => raw_meta,
+        };
+        Ok(serde_json::from_slice(&meta_bytes)?)
+    }
+
+    fn write_meta(&self, path: &Path, meta: &ObjectMeta) -> Result<()> {
+        let meta_json = serde_json::to_vec(meta)?;
+        std::fs::write(path, self.encrypt_bytes(&meta_json)?)?;
+        Ok(())
+    }
+
+    /// Move the current object stored under `id` into its version history.
+    /// No-op if there is no current object.
+    fn archive_current(&self, bucket: &str, id: u64) -> Result<()> {
+        let meta_path = self.meta_path(bucket, id);
+        if !meta_path.exists() {
+            return Ok(());
+        }
+        let mut meta = self.read_meta(&meta_path)?;
+        let version_id = meta.version_id.get_or_insert_with(|| NULL_VERSION.to_string()).clone();
+        let dir = self.versions_path(bucket, id);
+        std::fs::create_dir_all(&dir)?;
+        std::fs::rename(self.data_path(bucket, id), dir.join(format!("{version_id}.data")))?;
+        self.write_meta(&dir.join(format!("{version_id}.meta")), &meta)?;
+        std::fs::remove_file(meta_path)?;
+        Ok(())
+    }
+
+    fn upload_path(&self, upload_id: &str) -> PathBuf {
+        self.uploads_dir.join(upload_id)
+    }
+
+    fn part_path(&self, upload_id: &str, part_number: u32) -> PathBuf {
+        self.uploads_dir.join(upload_id).join(format!("{}.part", part_number))
+    }
+
+    fn encrypt_bytes(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match &self.encryption {
+            Some(key) => key.encrypt(data),
+            None => Ok(data.to_vec()),
+        }
+    }
+
     pub fn create_bucket(&self, name: &str) -> Result<()> {
         std::fs::create_dir_all(self.bucket_path(name))?;
         let mut buckets = self.buckets.write().unwrap();
-        buckets.entry(name.to_string()).or_insert(BucketState {
-            keys: HashMap::new(),
-            next_id: 0,
-        });
+        buckets.entry(name.to_string()).or_insert(BucketState::new());
+        Ok(())
+    }
+
+    /// Create a bucket whose ACL names `owner` as its only user. An existing
+    /// bucket is left as it is, ACL included.
+    pub fn create_owned_bucket(&self, name: &str, owner: &str) -> Result<()> {
+        let mut buckets = self.buckets.write().unwrap();
+        if buckets.contains_key(name) {
+            return Ok(());
+        }
+        std::fs::create_dir_all(self.bucket_path(name))?;
+        let acl = BucketAcl { owner: owner.to_string(), ..BucketAcl::default() };
+        let path = self.bucket_path(name).join(ACL_FILE);
+        std::fs::write(path, self.encrypt_bytes(&serde_json::to_vec(&acl)?)?)?;
+        let mut state = BucketState::new();
+        state.acl = Some(acl);
+        buckets.insert(name.to_string(), state);
         Ok(())
     }
 
@@ -129,6 +436,29 @@ impl BlobStore {
         Ok(())
     }
 
+    /// Pick the storage ID for a write to `key` and, if the bucket keeps
+    /// versions, archive the current object first. Returns the ID and the new
+    /// object's version ID.
+    fn begin_write(&self, state: &mut BucketState, bucket: &str, key: &str) -> Result<(u64, Option<String>)> {
+        let id = state.id_for(key);
+        let version_id = state.new_version();
+        if version_id.is_some() || self.current_is_versioned(state, bucket, id)? {
+            self.archive_current(bucket, id)?;
+        }
+        Ok((id, version_id))
+    }
+
+    /// Versioned objects are kept even after versioning is suspended; only an
+    /// unversioned current object may be overwritten or removed in place.
+    fn current_is_versioned(&self, state: &BucketState, bucket: &str, id: u64) -> Result<bool> {
+        // No version has ever been assigned in this bucket
+        if state.next_version == 1 {
+            return Ok(false);
+        }
+        let meta_path = self.meta_path(bucket, id);
+        Ok(meta_path.exists() && self.read_meta(&meta_path)?.version_id.is_some())
+    }
+
     pub fn put_object(
         &self,
         bucket: &str,
@@ -137,26 +467,17 @@ impl BlobStore {
         content_type: &str,
         metadata: HashMap<String, String>,
     ) -> Result<ObjectMeta> {
+        let expiry = parse_expiry(&metadata)?;
+
         // Auto-create bucket if it doesn't exist
         std::fs::create_dir_all(self.bucket_path(bucket))?;
 
         let mut buckets = self.buckets.write().unwrap();
         let state = buckets
             .entry(bucket.to_string())
-            .or_insert(BucketState {
-                keys: HashMap::new(),
-                next_id: 0,
-            });
+            .or_insert(BucketState::new());
 
-        // Reuse existing ID if key already exists, otherwise allocate new
-        let id = if let Some(&existing_id) = state.keys.get(key) {
-            existing_id
-        } else {
-            let id = state.next_id;
-            state.next_id += 1;
-            state.keys.insert(key.to_string(), id);
-            id
-        };
+        let (id, version_id) = self.begin_write(state, bucket, key)?;
 
         let etag = format!("{:08x}", crc32fast::hash(data));
         let created_at = now_rfc3339();
@@ -169,6 +490,9 @@ impl BlobStore {
             etag,
             created_at,
             metadata,
+            version_id,
+            delete_marker: false,
+            sha256: Some(sha256_hex(Sha256::digest(data))),
         };
 
         let data_to_write = match &self.encryption {
@@ -182,19 +506,217 @@ impl BlobStore {
             None => meta_json,
         };
         std::fs::write(self.meta_path(bucket, id), meta_to_write)?;
+        state.set_expiry(key, expiry);
 
         Ok(meta)
     }
 
+    /// Copy an object server-side, optionally replacing its content type and
+    /// user metadata. The destination bucket is created if needed and an
+    /// existing destination key is overwritten like `put_object`.
+    pub fn copy_object(
+        &self,
+        src_bucket: &str,
+        src_key: &str,
+        dst_bucket: &str,
+        dst_key: &str,
+        content_type: Option<&str>,
+        metadata: Option<HashMap<String, String>>,
+    ) -> Result<ObjectMeta> {
+        if let Some(metadata) = &metadata {
+            parse_expiry(metadata)?;
+        }
+        std::fs::create_dir_all(self.bucket_path(dst_bucket))?;
+
+        let mut buckets = self.buckets.write().unwrap();
+        let src_id = buckets
+            .get(src_bucket)
+            .ok_or_else(|| Error::BucketNotFound(src_bucket.to_string()))?
+            .live_id(src_bucket, src_key)?;
+        let src = self.read_meta(&self.meta_path(src_bucket, src_id))?;
+
+        // Stage the bytes first: writing the destination may archive or
+        // replace the source when both name the same object. Encrypted data
+        // is copied as-is, since it is sealed with the same key.
+        let staged = self.bucket_path(dst_bucket).join(format!("{}.copy", rand::random::<u64>()));
+        std::fs::copy(self.data_path(src_bucket, src_id), &staged)?;
+
+        let state = buckets
+            .entry(dst_bucket.to_string())
+            .or_insert(BucketState::new());
+        let (id, version_id) = match self.begin_write(state, dst_bucket, dst_key) {
+            Ok(v) => v,
+            Err(e) => {
+                let _ = std::fs::remove_file(&staged);
+                return Err(e);
+            }
+        };
+        std::fs::rename(&staged, self.data_path(dst_bucket, id))?;
+
+        let meta = ObjectMeta {
+            key: dst_key.to_string(),
+            bucket: dst_bucket.to_string(),
+            size: src.size,
+            content_type: content_type.map_or(src.content_type, str::to_string),
+            etag: src.etag,
+            created_at: now_rfc3339(),
+            metadata: metadata.unwrap_or(src.metadata),
+            version_id,
+            delete_marker: false,
+            sha256: src.sha256,
+        };
+        self.write_meta(&self.meta_path(dst_bucket, id), &meta)?;
+        state.set_expiry(dst_key, meta.expires_at());
+
+        Ok(meta)
+    }
+
+    /// Start a multipart upload for `bucket/key`. Parts are staged on disk
+    /// until `complete_multipart_upload` assembles them. Returns the upload ID.
+    pub fn create_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: &str,
+        metadata: HashMap<String, String>,
+    ) -> Result<String> {
+        parse_expiry(&metadata)?;
+        let upload_id = format!("{:016x}{:016x}", rand::random::<u64>(), rand::random::<u64>());
+        let upload = MultipartUpload {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            content_type: content_type.to_string(),
+            metadata,
+        };
+        std::fs::create_dir_all(self.upload_path(&upload_id))?;
+        let manifest = self.encrypt_bytes(&serde_json::to_vec(&upload)?)?;
+        std::fs::write(self.upload_path(&upload_id).join("upload.json"), manifest)?;
+        self.uploads.write().unwrap().insert(upload_id.clone(), upload);
+        Ok(upload_id)
+    }
+
+    /// Stage one part of a multipart upload. Part numbers start at 1 and may
+    /// arrive in any order; re-uploading a part number replaces it.
+    /// Returns the part's etag.
+    pub fn upload_part(&self, upload_id: &str, part_number: u32, data: &[u8]) -> Result<String> {
+        if part_number == 0 {
+            return Err(Error::InvalidUpload("part numbers start at 1".into()));
+        }
+        // Hold the read lock so the upload can't be completed or aborted mid-write.
+        let uploads = self.uploads.read().unwrap();
+        if !uploads.contains_key(upload_id) {
+            return Err(Error::UploadNotFound(upload_id.to_string()));
+        }
+        std::fs::write(self.part_path(upload_id, part_number), self.encrypt_bytes(data)?)?;
+        Ok(format!("{:08x}", crc32fast::hash(data)))
+    }
+
+    /// Concatenate all staged parts in part-number order into the final object,
+    /// then discard the upload. Overwrites an existing key like `put_object`.
+    ///
+    /// Unencrypted parts are streamed to the data file; with encryption at rest
+    /// the object is sealed as a whole, so the parts are assembled in memory.
+    pub fn complete_multipart_upload(&self, upload_id: &str) -> Result<ObjectMeta> {
+        let mut uploads = self.uploads.write().unwrap();
+        let upload = uploads
+            .get(upload_id)
+            .cloned()
+            .ok_or_else(|| Error::UploadNotFound(upload_id.to_string()))?;
+
+        let mut parts: Vec<(u32, PathBuf)> = Vec::new();
+        for entry in std::fs::read_dir(self.upload_path(upload_id))? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if let Some(n) = name.strip_suffix(".part").and_then(|n| n.parse::<u32>().ok()) {
+                parts.push((n, entry.path()));
+            }
+        }
+        if parts.is_empty() {
+            return Err(Error::InvalidUpload(format!("upload {upload_id} has no parts")));
+        }
+        parts.sort_by_key(|(n, _)| *n);
+
+        std::fs::create_dir_all(self.bucket_path(&upload.bucket))?;
+        let mut buckets = self.buckets.write().unwrap();
+        let state = buckets
+            .entry(upload.bucket.clone())
+            .or_insert(BucketState::new());
+        let (id, version_id) = self.begin_write(state, &upload.bucket, &upload.key)?;
+        let data_path = self.data_path(&upload.bucket, id);
+
+        let (size, crc, digest) = match &self.encryption {
+            Some(key) => {
+                let mut data = Vec::new();
+                for (_, path) in &parts {
+                    data.extend(key.decrypt(&std::fs::read(path)?)?);
+                }
+                std::fs::write(&data_path, key.encrypt(&data)?)?;
+                (data.len() as u64, crc32fast::hash(&data), Sha256::digest(&data))
+            }
+            None => {
+                let mut out = BufWriter::new(File::create(&data_path)?);
+                let mut hasher = crc32fast::Hasher::new();
+                let mut sha = Sha256::new();
+                let mut size = 0u64;
+                let mut buf = vec![0u8; 64 * 1024];
+                for (_, path) in &parts {
+                    let mut part = File::open(path)?;
+                    loop {
+                        let n = part.read(&mut buf)?;
+                        if n == 0 {
+                            break;
+                        }
+                        hasher.update(&buf[..n]);
+                        sha.update(&buf[..n]);
+                        out.write_all(&buf[..n])?;
+                        size += n as u64;
+                    }
+                }
+                out.flush()?;
+                (size, hasher.finalize(), sha.finalize())
+            }
+        };
+
+        let meta = ObjectMeta {
+            key: upload.key,
+            bucket: upload.bucket,
+            size,
+            content_type: upload.content_type,
+            etag: format!("{:08x}", crc),
+            created_at: now_rfc3339(),
+            metadata: upload.metadata,
+            version_id,
+            delete_marker: false,
+            sha256: Some(sha256_hex(digest)),
+        };
+        let meta_json = serde_json::to_vec(&meta)?;
+        std::fs::write(self.meta_path(&meta.bucket, id), self.encrypt_bytes(&meta_json)?)?;
+        state.set_expiry(&meta.key, meta.expires_at());
+
+        uploads.remove(upload_id);
+        std::fs::remove_dir_all(self.upload_path(upload_id))?;
+        Ok(meta)
+    }
+
+    /// Discard a multipart upload and all of its staged parts.
+    pub fn abort_multipart_upload(&self, upload_id: &str) -> Result<()> {
+        let mut uploads = self.uploads.write().unwrap();
+        if uploads.remove(upload_id).is_none() {
+            return Err(Error::UploadNotFound(upload_id.to_string()));
+        }
+        let path = self.upload_path(upload_id);
+        if path.exists() {
+            std::fs::remove_dir_all(path)?;
+        }
+        Ok(())
+    }
+
     pub fn get_object(&self, bucket: &str, key: &str) -> Result<(Vec<u8>, ObjectMeta)> {
         let buckets = self.buckets.read().unwrap();
         let state = buckets
             .get(bucket)
             .ok_or_else(|| Error::BucketNotFound(bucket.to_string()))?;
-        let &id = state.keys.get(key).ok_or_else(|| Error::BlobNotFound {
-            bucket: bucket.to_string(),
-            key: key.to_string(),
-        })?;
+        let id = state.live_id(bucket, key)?;
 
         let raw_data = std::fs::read(self.data_path(bucket, id))?;
         let data = match &self.encryption {
@@ -211,15 +733,112 @@ impl BlobStore {
         Ok((data, meta))
     }
 
+    /// Like `get_object`, but recompute the content's SHA-256 and fail with
+    /// `ChecksumMismatch` if it differs from the stored one. Objects without
+    /// a stored checksum are returned unchecked.
+    pub fn get_object_verified(&self, bucket: &str, key: &str) -> Result<(Vec<u8>, ObjectMeta)> {
+        let (data, meta) = self.get_object(bucket, key)?;
+        let corrupt = meta
+            .sha256
+            .as_ref()
+            .is_some_and(|expected| sha256_hex(Sha256::digest(&data)) != *expected);
+        if corrupt {
+            return Err(Error::ChecksumMismatch {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+            });
+        }
+        Ok((data, meta))
+    }
+
+    /// Check an object's content against its stored SHA-256. Returns the
+    /// checksum it matched, or `None` if the object has none to check.
+    pub fn verify_object(&self, bucket: &str, key: &str) -> Result<Option<String>> {
+        let (_, meta) = self.get_object_verified(bucket, key)?;
+        Ok(meta.sha256)
+    }
+
+    /// Read a byte range of an object, following HTTP `Range: bytes=` rules:
+    /// `start..=end`, `start..` when `end` is `None`, or the last `end` bytes
+    /// when `start` is `None`. An `end` past the object is clamped; a start
+    /// past the end of the object, or `start > end`, is an error.
+    ///
+    /// Unencrypted objects are read with a seek so only the range is loaded.
+    /// Encrypted objects are sealed as a whole and must be decrypted in full.
+    pub fn get_object_range(
+        &self,
+        bucket: &str,
+        key: &str,
+        start: Option<u64>,
+        end: Option<u64>,
+    ) -> Result<(ObjectRange, ObjectMeta)> {
+        let buckets = self.buckets.read().unwrap();
+        let state = buckets
+            .get(bucket)
+            .ok_or_else(|| Error::BucketNotFound(bucket.to_string()))?;
+        let id = state.live_id(bucket, key)?;
+
+        let raw_meta = std::fs::read(self.meta_path(bucket, id))?;
+        let meta_bytes = match &self.encryption {
+            Some(key) => key.decrypt(&raw_meta)?,
+            None => raw_meta,
+        };
+        let meta: ObjectMeta = serde_json::from_slice(&meta_bytes)?;
+
+        let total = meta.size;
+        let (first, last) = match (start, end) {
+            (Some(s), Some(e)) if s > e => {
+                return Err(Error::InvalidRange(format!("start {s} is after end {e}")));
+            }
+            (Some(s), _) if s >= total => {
+                return Err(Error::InvalidRange(format!(
+                    "start {s} is beyond object size {total}"
+                )));
+            }
+            (Some(s), e) => (s, e.map_or(total - 1, |e| e.min(total - 1))),
+            (None, Some(0)) => {
+                return Err(Error::InvalidRange("suffix length must be positive".into()));
+            }
+            (None, Some(n)) if total > 0 => (total.saturating_sub(n), total - 1),
+            (None, _) => {
+                return Err(Error::InvalidRange(format!(
+                    "object size {total} cannot satisfy the range"
+                )));
+            }
+        };
+        let len = (last - first + 1) as usize;
+
+        let data = match &self.encryption {
+            Some(key) => {
+                let full = key.decrypt(&std::fs::read(self.data_path(bucket, id))?)?;
+                full[first as usize..=last as usize].to_vec()
+            }
+            None => {
+                let mut file = File::open(self.data_path(bucket, id))?;
+                file.seek(SeekFrom::Start(first))?;
+                let mut buf = vec![0u8; len];
+                file.read_exact(&mut buf)?;
+                buf
+            }
+        };
+
+        Ok((
+            ObjectRange {
+                data,
+                start: first,
+                end: last,
+                total_size: total,
+            },
+            meta,
+        ))
+    }
+
     pub fn head_object(&self, bucket: &str, key: &str) -> Result<ObjectMeta> {
         let buckets = self.buckets.read().unwrap();
         let state = buckets
             .get(bucket)
             .ok_or_else(|| Error::BucketNotFound(bucket.to_string()))?;
-        let &id = state.keys.get(key).ok_or_else(|| Error::BlobNotFound {
-            bucket: bucket.to_string(),
-            key: key.to_string(),
-        })?;
+        let id = state.live_id(bucket, key)?;
 
         let raw_meta = std::fs::read(self.meta_path(bucket, id))?;
         let meta_bytes = match &self.encryption {
@@ -235,6 +854,33 @@ impl BlobStore {
         let state = buckets
             .get_mut(bucket)
             .ok_or_else(|| Error::BucketNotFound(bucket.to_string()))?;
+        self.remove_key(state, bucket, key)
+    }
+
+    /// Delete every object whose expiry has passed, like `delete_object`
+    /// (versioned buckets get a delete marker). Returns the removed
+    /// `(bucket, key)` pairs.
+    pub fn purge_expired(&self) -> Result<Vec<(String, String)>> {
+        let now = Utc::now();
+        let mut buckets = self.buckets.write().unwrap();
+        let mut removed = Vec::new();
+        for (bucket, state) in buckets.iter_mut() {
+            let mut expired: Vec<String> = state
+                .expiries
+                .iter()
+                .filter(|(_, t)| **t <= now)
+                .map(|(k, _)| k.clone())
+                .collect();
+            expired.sort();
+            for key in expired {
+                self.remove_key(state, bucket, &key)?;
+                removed.push((bucket.clone(), key));
+            }
+        }
+        Ok(removed)
+    }
+
+    fn remove_key(&self, state: &mut BucketState, bucket: &str, key: &str) -> Result<()> {
         let id = state
             .keys
             .remove(key)
@@ -242,19 +888,198 @@ impl BlobStore {
                 bucket: bucket.to_string(),
                 key: key.to_string(),
             })?;
+        state.expiries.remove(key);
+
+        if let Some(version_id) = state.new_version() {
+            // Versioned delete: keep the data and record a delete marker
+            self.archive_current(bucket, id)?;
+            let marker = ObjectMeta {
+                key: key.to_string(),
+                bucket: bucket.to_string(),
+                size: 0,
+                content_type: String::new(),
+                etag: String::new(),
+                created_at: now_rfc3339(),
+                metadata: HashMap::new(),
+                version_id: Some(version_id.clone()),
+                delete_marker: true,
+                sha256: None,
+            };
+            let dir = self.versions_path(bucket, id);
+            std::fs::create_dir_all(&dir)?;
+            self.write_meta(&dir.join(format!("{version_id}.meta")), &marker)?;
+            state.archived.insert(key.to_string(), id);
+            return Ok(());
+        }
 
-        let data_path = self.data_path(bucket, id);
-        if data_path.exists() {
-            std::fs::remove_file(data_path)?;
+        if self.current_is_versioned(state, bucket, id)? {
+            self.archive_current(bucket, id)?;
+        } else {
+            let data_path = self.data_path(bucket, id);
+            if data_path.exists() {
+                std::fs::remove_file(data_path)?;
+            }
+            let meta_path = self.meta_path(bucket, id);
+            if meta_path.exists() {
+                std::fs::remove_file(meta_path)?;
+            }
         }
-        let meta_path = self.meta_path(bucket, id);
-        if meta_path.exists() {
-            std::fs::remove_file(meta_path)?;
+        if self.versions_path(bucket, id).exists() {
+            state.archived.insert(key.to_string(), id);
         }
 
         Ok(())
     }
 
+    /// Enable or suspend versioning for a bucket (creating it if needed).
+    /// Suspending keeps existing versions; new writes overwrite in place.
+    pub fn set_versioning(&self, bucket: &str, enabled: bool) -> Result<()> {
+        std::fs::create_dir_all(self.bucket_path(bucket))?;
+        let mut buckets = self.buckets.write().unwrap();
+        let state = buckets
+            .entry(bucket.to_string())
+            .or_insert(BucketState::new());
+        let marker = self.bucket_path(bucket).join(VERSIONING_MARKER);
+        if enabled {
+            std::fs::write(marker, b"")?;
+        } else if marker.exists() {
+            std::fs::remove_file(marker)?;
+        }
+        state.versioned = enabled;
+        Ok(())
+    }
+
+    /// Set or (with `None`) remove a bucket's access control list, creating
+    /// the bucket if needed.
+    pub fn set_bucket_acl(&self, bucket: &str, acl: Option<BucketAcl>) -> Result<()> {
+        std::fs::create_dir_all(self.bucket_path(bucket))?;
+        let mut buckets = self.buckets.write().unwrap();
+        let state = buckets
+            .entry(bucket.to_string())
+            .or_insert(BucketState::new());
+        let path = self.bucket_path(bucket).join(ACL_FILE);
+        match &acl {
+            Some(acl) => std::fs::write(path, self.encrypt_bytes(&serde_json::to_vec(acl)?)?)?,
+            None if path.exists() => std::fs::remove_file(path)?,
+            None => {}
+        }
+        state.acl = acl;
+        Ok(())
+    }
+
+    /// The bucket's access control list, or `None` if it has none (or
+    /// doesn't exist).
+    pub fn bucket_acl(&self, bucket: &str) -> Option<BucketAcl> {
+        self.buckets
+            .read()
+            .unwrap()
+            .get(bucket)
+            .and_then(|s| s.acl.clone())
+    }
+
+    /// The bucket a multipart upload will write to.
+    pub fn upload_bucket(&self, upload_id: &str) -> Option<String> {
+        self.uploads
+            .read()
+            .unwrap()
+            .get(upload_id)
+            .map(|u| u.bucket.clone())
+    }
+
+    /// Returns `true` if versioning is enabled for the bucket.
+    pub fn is_versioning_enabled(&self, bucket: &str) -> bool {
+        self.buckets
+            .read()
+            .unwrap()
+            .get(bucket)
+            .is_some_and(|s| s.versioned)
+    }
+
+    /// Read a specific version of an object, current or archived.
+    /// A delete marker reads as not found.
+    pub fn get_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<(Vec<u8>, ObjectMeta)> {
+        let buckets = self.buckets.read().unwrap();
+        let state = buckets
+            .get(bucket)
+            .ok_or_else(|| Error::BucketNotFound(bucket.to_string()))?;
+        let not_found = || Error::BlobNotFound {
+            bucket: bucket.to_string(),
+            key: format!("{key}?version_id={version_id}"),
+        };
+        let &id = state
+            .keys
+            .get(key)
+            .or_else(|| state.archived.get(key))
+            .ok_or_else(not_found)?;
+
+        let current_meta = self.meta_path(bucket, id);
+        if current_meta.exists() {
+            let meta = self.read_meta(&current_meta)?;
+            if meta.version_id.as_deref().unwrap_or(NULL_VERSION) == version_id {
+                let raw_data = std::fs::read(self.data_path(bucket, id))?;
+                let data = match &self.encryption {
+                    Some(key) => key.decrypt(&raw_data)?,
+                    None => raw_data,
+                };
+                return Ok((data, meta));
+            }
+        }
+
+        let dir = self.versions_path(bucket, id);
+        let meta_path = dir.join(format!("{version_id}.meta"));
+        // Version IDs are generated digits or "null"; anything else can't exist
+        let valid_id = version_id == NULL_VERSION || version_id.bytes().all(|b| b.is_ascii_digit());
+        if !valid_id || !meta_path.exists() {
+            return Err(not_found());
+        }
+        let meta = self.read_meta(&meta_path)?;
+        if meta.delete_marker {
+            return Err(not_found());
+        }
+        let raw_data = std::fs::read(dir.join(format!("{version_id}.data")))?;
+        let data = match &self.encryption {
+            Some(key) => key.decrypt(&raw_data)?,
+            None => raw_data,
+        };
+        Ok((data, meta))
+    }
+
+    /// List every version of a key, newest first. The first entry is the
+    /// latest version, which is a delete marker if the key was deleted.
+    pub fn list_object_versions(&self, bucket: &str, key: &str) -> Result<Vec<ObjectMeta>> {
+        let buckets = self.buckets.read().unwrap();
+        let state = buckets
+            .get(bucket)
+            .ok_or_else(|| Error::BucketNotFound(bucket.to_string()))?;
+        let Some(&id) = state.keys.get(key).or_else(|| state.archived.get(key)) else {
+            return Ok(Vec::new());
+        };
+
+        let mut versions = Vec::new();
+        let dir = self.versions_path(bucket, id);
+        if dir.exists() {
+            for entry in std::fs::read_dir(dir)? {
+                let entry = entry?;
+                if entry.file_name().to_string_lossy().ends_with(".meta") {
+                    versions.push(self.read_meta(&entry.path())?);
+                }
+            }
+        }
+        // Newest first; an archived "null" version predates all numbered ones
+        versions.sort_by_key(|m| std::cmp::Reverse(Self::version_number(m)));
+        // The current object, if any, is always the latest
+        let current_meta = self.meta_path(bucket, id);
+        if current_meta.exists() {
+            versions.insert(0, self.read_meta(&current_meta)?);
+        }
+        Ok(versions)
+    }
+
     pub fn list_objects(
         &self,
         bucket: &str,
@@ -266,6 +1091,7 @@ impl BlobStore {
             .get(bucket)
             .ok_or_else(|| Error::BucketNotFound(bucket.to_string()))?;
 
+        let now = Utc::now();
         let mut matching_keys: Vec<(&String, &u64)> = state
             .keys
             .iter()
@@ -273,6 +1099,7 @@ impl BlobStore {
                 Some(p) => k.starts_with(p),
                 None => true,
             })
+            .filter(|(k, _)| state.expiries.get(*k).is_none_or(|t| *t > now))
             .collect();
 
         matching_keys.sort_by(|a, b| a.0.cmp(b.0));
@@ -294,6 +1121,11 @@ impl BlobStore {
     }
 }
 
+/// Lowercase hex form of a SHA-256 digest, as stored in `ObjectMeta::sha256`.
+fn sha256_hex(digest: impl AsRef<[u8]>) -> String {
+    digest.as_ref().iter().map(|b| format!("{b:02x}")).collect()
+}
+
 fn now_rfc3339() -> String {
     use std::time::SystemTime;
     let dur = SystemTime::now()
@@ -457,6 +1289,45 @@ mod tests {
         assert_eq!(list.len(), 1);
     }
 
+    #[test]
+    fn checksum_detects_corruption() {
+        let (dir, store) = temp_store();
+        let meta = store
+            .put_object("b", "f.txt", b"hello", "text/plain", HashMap::new())
+            .unwrap();
+        let expected = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert_eq!(meta.sha256.as_deref(), Some(expected));
+        assert_eq!(store.head_object("b", "f.txt").unwrap().sha256.as_deref(), Some(expected));
+        assert_eq!(store.verify_object("b", "f.txt").unwrap().as_deref(), Some(expected));
+        assert_eq!(store.get_object_verified("b", "f.txt").unwrap().0, b"hello");
+
+        // Flip a byte behind the store's back
+        std::fs::write(dir.path().join("_blobs/b/0.data"), b"jello").unwrap();
+        assert_eq!(store.get_object("b", "f.txt").unwrap().0, b"jello");
+        let err = store.verify_object("b", "f.txt").unwrap_err();
+        assert!(matches!(err, Error::ChecksumMismatch { .. }));
+        assert!(store.get_object_verified("b", "f.txt").is_err());
+    }
+
+    #[test]
+    fn checksum_covers_multipart_and_copies() {
+        let (_dir, store) = temp_store();
+        let id = store
+            .create_multipart_upload("b", "big", "text/plain", HashMap::new())
+            .unwrap();
+        store.upload_part(&id, 2, b"lo").unwrap();
+        store.upload_part(&id, 1, b"hel").unwrap();
+        let meta = store.complete_multipart_upload(&id).unwrap();
+        let single = store
+            .put_object("b", "small", b"hello", "text/plain", HashMap::new())
+            .unwrap();
+        assert_eq!(meta.sha256, single.sha256);
+
+        let copy = store.copy_object("b", "big", "c", "copy", None, None).unwrap();
+        assert_eq!(copy.sha256, single.sha256);
+        assert!(store.verify_object("c", "copy").unwrap().is_some());
+    }
+
     #[test]
     fn get_from_missing_bucket() {
         let (_dir, store) = temp_store();
@@ -478,4 +1349,511 @@ mod tests {
         let err = store.get_object("b", "f.txt").unwrap_err();
         assert!(err.to_string().contains("bucket not found"));
     }
+
+    #[test]
+    fn multipart_upload_out_of_order() {
+        let (_dir, store) = temp_store();
+        let mut metadata = HashMap::new();
+        metadata.insert("owner".to_string(), "alice".to_string());
+        let upload_id = store
+            .create_multipart_upload("videos", "clip.bin", "application/octet-stream", metadata)
+            .unwrap();
+
+        store.upload_part(&upload_id, 3, b"-part3").unwrap();
+        store.upload_part(&upload_id, 1, b"part1").unwrap();
+        store.upload_part(&upload_id, 2, b"-part2").unwrap();
+
+        let meta = store.complete_multipart_upload(&upload_id).unwrap();
+        assert_eq!(meta.size, 17);
+        assert_eq!(meta.etag, format!("{:08x}", crc32fast::hash(b"part1-part2-part3")));
+
+        let (data, got_meta) = store.get_object("videos", "clip.bin").unwrap();
+        assert_eq!(data, b"part1-part2-part3");
+        assert_eq!(got_meta.content_type, "application/octet-stream");
+        assert_eq!(got_meta.metadata["owner"], "alice");
+
+        // The upload is gone once completed
+        let err = store.upload_part(&upload_id, 4, b"late").unwrap_err();
+        assert!(matches!(err, Error::UploadNotFound(_)));
+    }
+
+    #[test]
+    fn multipart_upload_encrypted() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_path = dir.path().join("test.key");
+        std::fs::write(&key_path, [7u8; 32]).unwrap();
+        let key = EncryptionKey::load_from_file(&key_path).unwrap();
+        let store = BlobStore::open_with_encryption(dir.path(), Some(key)).unwrap();
+        let upload_id = store
+            .create_multipart_upload("b", "f.txt", "text/plain", HashMap::new())
+            .unwrap();
+        store.upload_part(&upload_id, 2, b"world").unwrap();
+        store.upload_part(&upload_id, 1, b"hello ").unwrap();
+        store.complete_multipart_upload(&upload_id).unwrap();
+
+        let (data, meta) = store.get_object("b", "f.txt").unwrap();
+        assert_eq!(data, b"hello world");
+        assert_eq!(meta.size, 11);
+    }
+
+    #[test]
+    fn multipart_upload_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let upload_id = {
+            let store = BlobStore::open(dir.path()).unwrap();
+            let id = store
+                .create_multipart_upload("b", "f.txt", "text/plain", HashMap::new())
+                .unwrap();
+            store.upload_part(&id, 1, b"abc").unwrap();
+            id
+        };
+
+        let store = BlobStore::open(dir.path()).unwrap();
+        // The staging area is not mistaken for a bucket
+        assert!(store.list_buckets().is_empty());
+        store.upload_part(&upload_id, 2, b"def").unwrap();
+        store.complete_multipart_upload(&upload_id).unwrap();
+        let (data, _) = store.get_object("b", "f.txt").unwrap();
+        assert_eq!(data, b"abcdef");
+    }
+
+    #[test]
+    fn abort_multipart_upload_discards_parts() {
+        let (dir, store) = temp_store();
+        let upload_id = store
+            .create_multipart_upload("b", "f.txt", "text/plain", HashMap::new())
+            .unwrap();
+        store.upload_part(&upload_id, 1, b"abc").unwrap();
+        store.abort_multipart_upload(&upload_id).unwrap();
+
+        assert!(!dir.path().join("_blob_uploads").join(&upload_id).exists());
+        assert!(matches!(
+            store.complete_multipart_upload(&upload_id).unwrap_err(),
+            Error::UploadNotFound(_)
+        ));
+        assert!(store.get_object("b", "f.txt").is_err());
+    }
+
+    #[test]
+    fn complete_multipart_upload_requires_parts() {
+        let (_dir, store) = temp_store();
+        let upload_id = store
+            .create_multipart_upload("b", "f.txt", "text/plain", HashMap::new())
+            .unwrap();
+        assert!(matches!(
+            store.upload_part(&upload_id, 0, b"x").unwrap_err(),
+            Error::InvalidUpload(_)
+        ));
+        assert!(matches!(
+            store.complete_multipart_upload(&upload_id).unwrap_err(),
+            Error::InvalidUpload(_)
+        ));
+    }
+
+    #[test]
+    fn get_object_range_mid_object() {
+        let (_dir, store) = temp_store();
+        store
+            .put_object("b", "f.txt", b"0123456789", "text/plain", HashMap::new())
+            .unwrap();
+
+        let (range, meta) = store.get_object_range("b", "f.txt", Some(2), Some(5)).unwrap();
+        assert_eq!(range.data, b"2345");
+        assert_eq!((range.start, range.end, range.total_size), (2, 5, 10));
+        assert_eq!(meta.key, "f.txt");
+
+        // Open-ended and clamped ranges run to the last byte
+        let (range, _) = store.get_object_range("b", "f.txt", Some(7), None).unwrap();
+        assert_eq!(range.data, b"789");
+        let (range, _) = store.get_object_range("b", "f.txt", Some(8), Some(100)).unwrap();
+        assert_eq!(range.data, b"89");
+        assert_eq!(range.end, 9);
+    }
+
+    #[test]
+    fn get_object_range_suffix() {
+        let (_dir, store) = temp_store();
+        store
+            .put_object("b", "f.txt", b"0123456789", "text/plain", HashMap::new())
+            .unwrap();
+
+        let (range, _) = store.get_object_range("b", "f.txt", None, Some(3)).unwrap();
+        assert_eq!(range.data, b"789");
+        assert_eq!((range.start, range.end), (7, 9));
+
+        // A suffix longer than the object returns the whole object
+        let (range, _) = store.get_object_range("b", "f.txt", None, Some(50)).unwrap();
+        assert_eq!(range.data, b"0123456789");
+    }
+
+    #[test]
+    fn get_object_range_invalid() {
+        let (_dir, store) = temp_store();
+        store
+            .put_object("b", "f.txt", b"0123456789", "text/plain", HashMap::new())
+            .unwrap();
+
+        for (start, end) in [(Some(10), None), (Some(6), Some(2)), (None, Some(0))] {
+            let err = store.get_object_range("b", "f.txt", start, end).unwrap_err();
+            assert!(matches!(err, Error::InvalidRange(_)), "{start:?}..{end:?}");
+        }
+    }
+
+    #[test]
+    fn get_object_range_encrypted() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_path = dir.path().join("test.key");
+        std::fs::write(&key_path, [7u8; 32]).unwrap();
+        let key = EncryptionKey::load_from_file(&key_path).unwrap();
+        let store = BlobStore::open_with_encryption(dir.path(), Some(key)).unwrap();
+        store
+            .put_object("b", "f.txt", b"0123456789", "text/plain", HashMap::new())
+            .unwrap();
+
+        let (range, _) = store.get_object_range("b", "f.txt", Some(3), Some(4)).unwrap();
+        assert_eq!(range.data, b"34");
+    }
+
+    #[test]
+    fn versioning_keeps_previous_versions() {
+        let (_dir, store) = temp_store();
+        store.set_versioning("b", true).unwrap();
+        let v1 = store
+            .put_object("b", "f.txt", b"first", "text/plain", HashMap::new())
+            .unwrap();
+        let v2 = store
+            .put_object("b", "f.txt", b"second", "text/plain", HashMap::new())
+            .unwrap();
+        let v1_id = v1.version_id.unwrap();
+        let v2_id = v2.version_id.unwrap();
+        assert_ne!(v1_id, v2_id);
+
+        let (data, _) = store.get_object("b", "f.txt").unwrap();
+        assert_eq!(data, b"second");
+        let (data, meta) = store.get_object_version("b", "f.txt", &v1_id).unwrap();
+        assert_eq!(data, b"first");
+        assert_eq!(meta.version_id.as_deref(), Some(v1_id.as_str()));
+        let (data, _) = store.get_object_version("b", "f.txt", &v2_id).unwrap();
+        assert_eq!(data, b"second");
+
+        let versions = store.list_object_versions("b", "f.txt").unwrap();
+        let ids: Vec<_> = versions.iter().map(|m| m.version_id.clone().unwrap()).collect();
+        assert_eq!(ids, vec![v2_id, v1_id]);
+    }
+
+    #[test]
+    fn versioned_delete_adds_marker() {
+        let (_dir, store) = temp_store();
+        store.set_versioning("b", true).unwrap();
+        let v1 = store
+            .put_object("b", "f.txt", b"first", "text/plain", HashMap::new())
+            .unwrap();
+        let v2 = store
+            .put_object("b", "f.txt", b"second", "text/plain", HashMap::new())
+            .unwrap();
+        store.delete_object("b", "f.txt").unwrap();
+
+        // The latest version is hidden...
+        assert!(matches!(
+            store.get_object("b", "f.txt").unwrap_err(),
+            Error::BlobNotFound { .. }
+        ));
+        assert!(store.list_objects("b", None, None).unwrap().is_empty());
+
+        // ...but older versions are still retrievable
+        let versions = store.list_object_versions("b", "f.txt").unwrap();
+        assert_eq!(versions.len(), 3);
+        assert!(versions[0].delete_marker);
+        let marker_id = versions[0].version_id.clone().unwrap();
+        assert!(store.get_object_version("b", "f.txt", &marker_id).is_err());
+        let (data, _) = store
+            .get_object_version("b", "f.txt", v2.version_id.as_deref().unwrap())
+            .unwrap();
+        assert_eq!(data, b"second");
+        let (data, _) = store
+            .get_object_version("b", "f.txt", v1.version_id.as_deref().unwrap())
+            .unwrap();
+        assert_eq!(data, b"first");
+
+        // Writing the key again makes it visible and keeps the history
+        store
+            .put_object("b", "f.txt", b"third", "text/plain", HashMap::new())
+            .unwrap();
+        let (data, _) = store.get_object("b", "f.txt").unwrap();
+        assert_eq!(data, b"third");
+        assert_eq!(store.list_object_versions("b", "f.txt").unwrap().len(), 4);
+    }
+
+    #[test]
+    fn versioning_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let v1 = {
+            let store = BlobStore::open(dir.path()).unwrap();
+            // Written before versioning: archived as the "null" version
+            store
+                .put_object("b", "f.txt", b"legacy", "text/plain", HashMap::new())
+                .unwrap();
+            store.set_versioning("b", true).unwrap();
+            let v1 = store
+                .put_object("b", "f.txt", b"first", "text/plain", HashMap::new())
+                .unwrap();
+            store.delete_object("b", "f.txt").unwrap();
+            v1.version_id.unwrap()
+        };
+
+        let store = BlobStore::open(dir.path()).unwrap();
+        assert!(store.is_versioning_enabled("b"));
+        assert!(store.get_object("b", "f.txt").is_err());
+        let (data, _) = store.get_object_version("b", "f.txt", &v1).unwrap();
+        assert_eq!(data, b"first");
+        let (data, _) = store.get_object_version("b", "f.txt", "null").unwrap();
+        assert_eq!(data, b"legacy");
+
+        // New versions continue after the highest existing one
+        let v2 = store
+            .put_object("b", "f.txt", b"second", "text/plain", HashMap::new())
+            .unwrap();
+        assert!(v2.version_id.unwrap() > v1);
+        let versions = store.list_object_versions("b", "f.txt").unwrap();
+        assert_eq!(versions.len(), 4);
+        assert_eq!(versions.last().unwrap().version_id.as_deref(), Some("null"));
+    }
+
+    #[test]
+    fn unversioned_bucket_has_no_version_ids() {
+        let (_dir, store) = temp_store();
+        let meta = store
+            .put_object("b", "f.txt", b"data", "text/plain", HashMap::new())
+            .unwrap();
+        assert!(meta.version_id.is_none());
+        let json = serde_json::to_value(&meta).unwrap();
+        assert!(json.get("version_id").is_none());
+        assert!(json.get("delete_marker").is_none());
+    }
+
+    fn expiring(at: &str) -> HashMap<String, String> {
+        HashMap::from([(EXPIRES_AT_KEY.to_string(), at.to_string())])
+    }
+
+    #[test]
+    fn expired_object_reads_as_absent() {
+        let (_dir, store) = temp_store();
+        store
+            .put_object("tmp", "old.txt", b"stale", "text/plain", expiring("2000-01-01T00:00:00Z"))
+            .unwrap();
+        store
+            .put_object("tmp", "new.txt", b"fresh", "text/plain", expiring("2999-01-01T00:00:00Z"))
+            .unwrap();
+
+        for err in [
+            store.get_object("tmp", "old.txt").unwrap_err(),
+            store.head_object("tmp", "old.txt").unwrap_err(),
+            store.get_object_range("tmp", "old.txt", Some(0), None).unwrap_err(),
+        ] {
+            assert!(matches!(err, Error::BlobNotFound { .. }));
+        }
+        let keys: Vec<_> = store
+            .list_objects("tmp", None, None)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.key)
+            .collect();
+        assert_eq!(keys, vec!["new.txt"]);
+
+        let (data, meta) = store.get_object("tmp", "new.txt").unwrap();
+        assert_eq!(data, b"fresh");
+        assert!(meta.expires_at().is_some());
+    }
+
+    #[test]
+    fn purge_expired_removes_files() {
+        let (dir, store) = temp_store();
+        store
+            .put_object("tmp", "old.txt", b"stale", "text/plain", expiring("2000-01-01T00:00:00+02:00"))
+            .unwrap();
+        store
+            .put_object("tmp", "keep.txt", b"keep", "text/plain", HashMap::new())
+            .unwrap();
+
+        let removed = store.purge_expired().unwrap();
+        assert_eq!(removed, vec![("tmp".to_string(), "old.txt".to_string())]);
+        assert!(store.purge_expired().unwrap().is_empty());
+        assert!(!dir.path().join("_blobs/tmp/0.data").exists());
+        assert!(!dir.path().join("_blobs/tmp/0.meta").exists());
+        assert!(store.get_object("tmp", "keep.txt").is_ok());
+
+        // Overwriting without an expiry clears it
+        store
+            .put_object("tmp", "keep.txt", b"x", "text/plain", expiring("2000-01-01T00:00:00Z"))
+            .unwrap();
+        store
+            .put_object("tmp", "keep.txt", b"y", "text/plain", HashMap::new())
+            .unwrap();
+        assert!(store.purge_expired().unwrap().is_empty());
+    }
+
+    #[test]
+    fn expiry_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let store = BlobStore::open(dir.path()).unwrap();
+            store
+                .put_object("tmp", "old.txt", b"stale", "text/plain", expiring("2000-01-01T00:00:00Z"))
+                .unwrap();
+        }
+        let store = BlobStore::open(dir.path()).unwrap();
+        assert!(store.get_object("tmp", "old.txt").is_err());
+        assert_eq!(store.purge_expired().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn bucket_acl_grants_by_role() {
+        let acl = BucketAcl {
+            owner: "alice".into(),
+            readers: vec!["bob".into()],
+            writers: vec!["carol".into()],
+        };
+        assert!(acl.can_read("alice") && acl.can_write("alice"));
+        assert!(acl.can_read("bob") && !acl.can_write("bob"));
+        assert!(acl.can_read("carol") && acl.can_write("carol"));
+        assert!(!acl.can_read("mallory") && !acl.can_write("mallory"));
+    }
+
+    #[test]
+    fn bucket_acl_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let acl = BucketAcl { owner: "alice".into(), ..Default::default() };
+        {
+            let store = BlobStore::open(dir.path()).unwrap();
+            store.put_object("private", "a.txt", b"x", "text/plain", HashMap::new()).unwrap();
+            store.set_bucket_acl("private", Some(acl.clone())).unwrap();
+            assert_eq!(store.bucket_acl("public"), None);
+        }
+        let store = BlobStore::open(dir.path()).unwrap();
+        assert_eq!(store.bucket_acl("private"), Some(acl));
+        assert_eq!(store.list_objects("private", None, None).unwrap().len(), 1);
+
+        store.set_bucket_acl("private", None).unwrap();
+        drop(store);
+        let store = BlobStore::open(dir.path()).unwrap();
+        assert_eq!(store.bucket_acl("private"), None);
+    }
+
+    #[test]
+    fn owned_bucket_keeps_its_first_owner() {
+        let (_dir, store) = temp_store();
+        store.create_owned_bucket("mine", "alice").unwrap();
+        store.create_owned_bucket("mine", "bob").unwrap();
+        assert_eq!(store.bucket_acl("mine").unwrap().owner, "alice");
+
+        // Buckets that already exist without an ACL don't gain one
+        store.create_bucket("open").unwrap();
+        store.create_owned_bucket("open", "bob").unwrap();
+        assert_eq!(store.bucket_acl("open"), None);
+    }
+
+    #[test]
+    fn invalid_expiry_is_rejected() {
+        let (_dir, store) = temp_store();
+        let err = store
+            .put_object("tmp", "f.txt", b"x", "text/plain", expiring("tomorrow"))
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidExpiry(_)));
+        assert!(store.get_object("tmp", "f.txt").is_err());
+
+        let err = store
+            .create_multipart_upload("tmp", "f.txt", "text/plain", expiring("tomorrow"))
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidExpiry(_)));
+    }
+
+    #[test]
+    fn copy_object_within_bucket() {
+        let (_dir, store) = temp_store();
+        let meta = HashMap::from([("author".to_string(), "alice".to_string())]);
+        let src = store
+            .put_object("b", "a.txt", b"hello", "text/plain", meta.clone())
+            .unwrap();
+
+        let copy = store.copy_object("b", "a.txt", "b", "c.txt", None, None).unwrap();
+        assert_eq!(copy.key, "c.txt");
+        assert_eq!(copy.etag, src.etag);
+        assert_eq!(copy.content_type, "text/plain");
+        assert_eq!(copy.metadata, meta);
+
+        let (data, head) = store.get_object("b", "c.txt").unwrap();
+        assert_eq!(data, b"hello");
+        assert_eq!(head.metadata, meta);
+        // The source is untouched
+        assert_eq!(store.get_object("b", "a.txt").unwrap().0, b"hello");
+
+        // Copying onto an existing key overwrites it
+        store
+            .put_object("b", "d.txt", b"old", "text/plain", HashMap::new())
+            .unwrap();
+        store.copy_object("b", "a.txt", "b", "d.txt", None, None).unwrap();
+        assert_eq!(store.get_object("b", "d.txt").unwrap().0, b"hello");
+        assert_eq!(store.list_objects("b", None, None).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn copy_object_across_buckets_replacing_metadata() {
+        let (_dir, store) = temp_store();
+        store
+            .put_object("src", "a.bin", b"\x00\x01\x02", "application/octet-stream", HashMap::new())
+            .unwrap();
+
+        let new_meta = HashMap::from([("tag".to_string(), "copied".to_string())]);
+        let copy = store
+            .copy_object("src", "a.bin", "dst", "b.bin", Some("image/png"), Some(new_meta.clone()))
+            .unwrap();
+        assert_eq!(copy.bucket, "dst");
+        assert_eq!(copy.content_type, "image/png");
+
+        let (data, head) = store.get_object("dst", "b.bin").unwrap();
+        assert_eq!(data, b"\x00\x01\x02");
+        assert_eq!(head.metadata, new_meta);
+        assert_eq!(store.list_buckets(), vec!["dst", "src"]);
+
+        assert!(matches!(
+            store.copy_object("src", "missing", "dst", "x", None, None).unwrap_err(),
+            Error::BlobNotFound { .. }
+        ));
+    }
+
+    #[test]
+    fn copy_object_onto_itself_in_versioned_bucket() {
+        let (_dir, store) = temp_store();
+        store.set_versioning("b", true).unwrap();
+        let v1 = store
+            .put_object("b", "a.txt", b"hello", "text/plain", HashMap::new())
+            .unwrap();
+        let copy = store
+            .copy_object("b", "a.txt", "b", "a.txt", Some("text/markdown"), None)
+            .unwrap();
+        assert_ne!(copy.version_id, v1.version_id);
+
+        let (data, head) = store.get_object("b", "a.txt").unwrap();
+        assert_eq!(data, b"hello");
+        assert_eq!(head.content_type, "text/markdown");
+        let (old, _) = store
+            .get_object_version("b", "a.txt", v1.version_id.as_deref().unwrap())
+            .unwrap();
+        assert_eq!(old, b"hello");
+    }
+
+    #[test]
+    fn copy_object_encrypted() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_path = dir.path().join("key");
+        std::fs::write(&key_path, [7u8; 32]).unwrap();
+        let key = EncryptionKey::load_from_file(&key_path).unwrap();
+        let store = BlobStore::open_with_encryption(&dir.path().join("db"), Some(key)).unwrap();
+
+        store
+            .put_object("b", "secret.txt", b"top secret", "text/plain", HashMap::new())
+            .unwrap();
+        store.copy_object("b", "secret.txt", "c", "copy.txt", None, None).unwrap();
+        assert_eq!(store.get_object("c", "copy.txt").unwrap().0, b"top secret");
+    }
 }
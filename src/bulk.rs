@@ -0,0 +1,86 @@
+use serde_json::{json, Value};
+
+use crate::document::DocumentId;
+use crate::error::{Error, Result};
+
+/// One write in a `bulk_write` batch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BulkOp {
+    Insert(Value),
+    Update { query: Value, update: Value },
+    Delete { query: Value },
+}
+
+/// What one `BulkOp` did: the inserted id, or the ids it updated or deleted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BulkOpResult {
+    Inserted(DocumentId),
+    Modified(Vec<DocumentId>),
+    Deleted(Vec<DocumentId>),
+}
+
+impl BulkOpResult {
+    /// `{"id": N}`, `{"modified": N}`, or `{"deleted": N}`, matching the
+    /// responses of the single-op commands.
+    pub fn to_json(&self) -> Value {
+        match self {
+            BulkOpResult::Inserted(id) => json!({ "id": id }),
+            BulkOpResult::Modified(ids) => json!({ "modified": ids.len() }),
+            BulkOpResult::Deleted(ids) => json!({ "deleted": ids.len() }),
+        }
+    }
+}
+
+/// Parse a batch like
+/// `[{"op": "insert", "doc": {..}}, {"op": "update", "query": {..}, "update": {..}}, {"op": "delete", "query": {..}}]`.
+pub fn parse_bulk_ops(ops: &Value) -> Result<Vec<BulkOp>> {
+    let ops = ops
+        .as_array()
+        .ok_or_else(|| Error::InvalidQuery("bulk ops must be an array".into()))?;
+    ops.iter()
+        .enumerate()
+        .map(|(i, op)| {
+            let field = |name: &str| {
+                op.get(name)
+                    .cloned()
+                    .ok_or_else(|| Error::InvalidQuery(format!("bulk op {i}: missing '{name}'")))
+            };
+            match op.get("op").and_then(|v| v.as_str()) {
+                Some("insert") => Ok(BulkOp::Insert(field("doc")?)),
+                Some("update") => Ok(BulkOp::Update { query: field("query")?, update: field("update")? }),
+                Some("delete") => Ok(BulkOp::Delete { query: field("query")? }),
+                Some(other) => Err(Error::InvalidQuery(format!(
+                    "bulk op {i}: unknown op '{other}' (expected insert, update, or delete)"
+                ))),
+                None => Err(Error::InvalidQuery(format!("bulk op {i}: missing 'op'"))),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_each_op_kind() {
+        let ops = parse_bulk_ops(&json!([
+            {"op": "insert", "doc": {"a": 1}},
+            {"op": "update", "query": {"a": 1}, "update": {"$set": {"b": 2}}},
+            {"op": "delete", "query": {}}
+        ]))
+        .unwrap();
+        assert_eq!(
+            ops,
+            [
+                BulkOp::Insert(json!({"a": 1})),
+                BulkOp::Update { query: json!({"a": 1}), update: json!({"$set": {"b": 2}}) },
+                BulkOp::Delete { query: json!({}) },
+            ]
+        );
+
+        for bad in [json!({}), json!([{"op": "upsert"}]), json!([{"op": "delete"}]), json!([{"doc": {}}])] {
+            assert!(matches!(parse_bulk_ops(&bad), Err(Error::InvalidQuery(_))), "{bad}");
+        }
+    }
+}
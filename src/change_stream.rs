@@ -3,6 +3,7 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
 use std::sync::{Arc, RwLock};
 
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 use serde_json::Value;
 
@@ -20,15 +21,29 @@ pub enum OperationType {
     Delete,
 }
 
+impl OperationType {
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "insert" => Some(OperationType::Insert),
+            "update" => Some(OperationType::Update),
+            "delete" => Some(OperationType::Delete),
+            _ => None,
+        }
+    }
+}
+
 /// A change event emitted when a document is mutated.
 #[derive(Debug, Clone, Serialize)]
 pub struct ChangeEvent {
     /// Monotonic sequence number for resume support.
     pub token: u64,
+    /// Wall-clock time the event was emitted, assigned by `emit()` like `token`.
+    pub timestamp: DateTime<Utc>,
     pub operation: OperationType,
     pub collection: String,
     pub doc_id: DocumentId,
-    /// Present for insert operations; `None` for update/delete.
+    /// Present for inserts, and for updates when the subscriber asked for
+    /// the full document; `None` for deletes.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub document: Option<Value>,
     /// Transaction ID if the mutation was part of a transaction.
@@ -43,6 +58,23 @@ pub enum WatchFilter {
     All,
     /// Receive events only from the named collection.
     Collection(String),
+    /// Receive only the listed operation types, optionally restricted to one
+    /// collection.
+    Operations {
+        collection: Option<String>,
+        operations: Vec<OperationType>,
+    },
+}
+
+/// Per-subscriber options for `subscribe_with_options()`.
+#[derive(Debug, Clone, Default)]
+pub struct WatchOptions {
+    /// Replay buffered events with `token > resume_after` before live events.
+    pub resume_after: Option<u64>,
+    /// Replay buffered events emitted at or after this time before live events.
+    pub resume_after_time: Option<DateTime<Utc>>,
+    /// Include the post-update document in update events.
+    pub full_document: bool,
 }
 
 /// Error returned when a resume token is no longer available in the replay buffer.
@@ -79,6 +111,7 @@ impl WatchHandle {
 struct Subscriber {
     id: SubscriberId,
     filter: WatchFilter,
+    full_document: bool,
     sender: SyncSender<ChangeEvent>,
     dropped: Arc<AtomicU64>,
 }
@@ -94,6 +127,7 @@ pub struct ChangeStreamBroker {
     subscribers: RwLock<Vec<Subscriber>>,
     next_id: AtomicU64,
     subscriber_count: AtomicU64,
+    full_document_count: AtomicU64,
     next_token: AtomicU64,
     event_log: RwLock<VecDeque<ChangeEvent>>,
 }
@@ -104,6 +138,7 @@ impl ChangeStreamBroker {
             subscribers: RwLock::new(Vec::new()),
             next_id: AtomicU64::new(1),
             subscriber_count: AtomicU64::new(0),
+            full_document_count: AtomicU64::new(0),
             next_token: AtomicU64::new(1),
             event_log: RwLock::new(VecDeque::new()),
         }
@@ -116,6 +151,13 @@ impl ChangeStreamBroker {
         self.subscriber_count.load(Ordering::Relaxed) > 0
     }
 
+    /// Returns `true` if any subscriber wants post-update documents. Emitters
+    /// use this to skip capturing the new document for update events.
+    #[inline]
+    pub fn wants_full_document(&self) -> bool {
+        self.full_document_count.load(Ordering::Relaxed) > 0
+    }
+
     /// Create a new subscription. Returns a `WatchHandle` with the subscriber ID,
     /// event receiver, and backpressure tracking.
     ///
@@ -128,29 +170,42 @@ impl ChangeStreamBroker {
         filter: WatchFilter,
         buffer: usize,
         resume_after: Option<u64>,
+    ) -> std::result::Result<WatchHandle, ResumeError> {
+        let opts = WatchOptions { resume_after, ..Default::default() };
+        self.subscribe_with_options(filter, buffer, &opts)
+    }
+
+    /// Like `subscribe()`, but with the full set of `WatchOptions`.
+    pub fn subscribe_with_options(
+        &self,
+        filter: WatchFilter,
+        buffer: usize,
+        opts: &WatchOptions,
     ) -> std::result::Result<WatchHandle, ResumeError> {
         let id = self.next_id.fetch_add(1, Ordering::Relaxed);
         let (tx, rx) = mpsc::sync_channel(buffer);
         let dropped = Arc::new(AtomicU64::new(0));
 
         // Replay from event_log if requested
-        if let Some(token) = resume_after {
+        if opts.resume_after.is_some() || opts.resume_after_time.is_some() {
             let log = self.event_log.read().unwrap();
-            // Check if the requested token is still in the buffer
-            let oldest_token = log.front().map(|e| e.token);
-            if let Some(oldest) = oldest_token {
-                if token < oldest {
-                    return Err(ResumeError::TokenTooOld);
-                }
+            // Check that the requested token / time is still in the buffer
+            if let Some(oldest) = log.front()
+                && (opts.resume_after.is_some_and(|token| token < oldest.token)
+                    || opts.resume_after_time.is_some_and(|time| time < oldest.timestamp))
+            {
+                return Err(ResumeError::TokenTooOld);
             }
             // Even if log is empty and token > 0, that means no events have been
             // emitted since the token — valid resume with nothing to replay.
             // Replay matching events
             for event in log.iter() {
-                if event.token > token && Self::matches_filter(&filter, &event.collection) {
+                let after_token = opts.resume_after.is_none_or(|token| event.token > token);
+                let after_time = opts.resume_after_time.is_none_or(|time| event.timestamp >= time);
+                if after_token && after_time && Self::matches_filter(&filter, event) {
                     // Use try_send; if the channel fills up, the rest will be dropped
                     // (caller chose a small buffer).
-                    let _ = tx.try_send(event.clone());
+                    let _ = tx.try_send(Self::event_for(opts.full_document, event));
                 }
             }
         }
@@ -158,11 +213,15 @@ impl ChangeStreamBroker {
         let sub = Subscriber {
             id,
             filter,
+            full_document: opts.full_document,
             sender: tx,
             dropped: Arc::clone(&dropped),
         };
         self.subscribers.write().unwrap().push(sub);
         self.subscriber_count.fetch_add(1, Ordering::Relaxed);
+        if opts.full_document {
+            self.full_document_count.fetch_add(1, Ordering::Relaxed);
+        }
 
         Ok(WatchHandle { id, rx, dropped })
     }
@@ -170,26 +229,20 @@ impl ChangeStreamBroker {
     /// Remove a subscriber by ID.
     pub fn unsubscribe(&self, id: SubscriberId) {
         let mut subs = self.subscribers.write().unwrap();
-        let before = subs.len();
-        subs.retain(|s| s.id != id);
-        let removed = before - subs.len();
-        if removed > 0 {
-            self.subscriber_count.fetch_sub(removed as u64, Ordering::Relaxed);
-        }
+        self.remove_where(&mut subs, |s| s.id == id);
     }
 
     /// Emit an event to all matching subscribers.
-    /// Assigns a monotonic token, stores in replay buffer, then fans out.
+    /// Assigns a monotonic token and timestamp, stores in replay buffer, then fans out.
     /// Uses `try_send` so a slow subscriber never blocks the mutation path.
     /// Dead subscribers (disconnected receivers) are lazily cleaned up.
     pub fn emit(&self, mut event: ChangeEvent) {
-        // Assign monotonic token
-        let token = self.next_token.fetch_add(1, Ordering::Relaxed);
-        event.token = token;
-
-        // Store in replay buffer
+        // Store in replay buffer. Token and timestamp are assigned under the
+        // log lock so both are ordered within the buffer.
         {
             let mut log = self.event_log.write().unwrap();
+            event.token = self.next_token.fetch_add(1, Ordering::Relaxed);
+            event.timestamp = Utc::now();
             if log.len() >= REPLAY_BUFFER_CAPACITY {
                 log.pop_front();
             }
@@ -200,10 +253,10 @@ impl ChangeStreamBroker {
         let mut dead_ids: Vec<SubscriberId> = Vec::new();
 
         for sub in subs.iter() {
-            if !Self::matches_filter(&sub.filter, &event.collection) {
+            if !Self::matches_filter(&sub.filter, &event) {
                 continue;
             }
-            match sub.sender.try_send(event.clone()) {
+            match sub.sender.try_send(Self::event_for(sub.full_document, &event)) {
                 Ok(()) => {}
                 Err(TrySendError::Disconnected(_)) => {
                     dead_ids.push(sub.id);
@@ -219,19 +272,56 @@ impl ChangeStreamBroker {
         // Lazy cleanup of dead subscribers
         if !dead_ids.is_empty() {
             let mut subs = self.subscribers.write().unwrap();
-            let before = subs.len();
-            subs.retain(|s| !dead_ids.contains(&s.id));
-            let removed = before - subs.len();
-            if removed > 0 {
-                self.subscriber_count.fetch_sub(removed as u64, Ordering::Relaxed);
+            self.remove_where(&mut subs, |s| dead_ids.contains(&s.id));
+        }
+    }
+
+    /// Remove matching subscribers and keep the subscriber counters in sync.
+    fn remove_where(&self, subs: &mut Vec<Subscriber>, pred: impl Fn(&Subscriber) -> bool) {
+        let before = subs.len();
+        let mut full_removed = 0u64;
+        subs.retain(|s| {
+            let remove = pred(s);
+            if remove && s.full_document {
+                full_removed += 1;
+            }
+            !remove
+        });
+        let removed = before - subs.len();
+        if removed > 0 {
+            self.subscriber_count.fetch_sub(removed as u64, Ordering::Relaxed);
+        }
+        if full_removed > 0 {
+            self.full_document_count.fetch_sub(full_removed, Ordering::Relaxed);
+        }
+    }
+
+    /// Shape an event for a subscriber: update documents are only delivered
+    /// to subscribers that asked for them.
+    fn event_for(full_document: bool, event: &ChangeEvent) -> ChangeEvent {
+        if !full_document && event.operation == OperationType::Update && event.document.is_some() {
+            ChangeEvent {
+                token: event.token,
+                timestamp: event.timestamp,
+                operation: event.operation.clone(),
+                collection: event.collection.clone(),
+                doc_id: event.doc_id,
+                document: None,
+                tx_id: event.tx_id,
             }
+        } else {
+            event.clone()
         }
     }
 
-    fn matches_filter(filter: &WatchFilter, collection: &str) -> bool {
+    fn matches_filter(filter: &WatchFilter, event: &ChangeEvent) -> bool {
         match filter {
             WatchFilter::All => true,
-            WatchFilter::Collection(name) => name == collection,
+            WatchFilter::Collection(name) => *name == event.collection,
+            WatchFilter::Operations { collection, operations } => {
+                collection.as_ref().is_none_or(|name| *name == event.collection)
+                    && operations.contains(&event.operation)
+            }
         }
     }
 }
@@ -245,6 +335,7 @@ mod tests {
     fn make_event(op: OperationType, collection: &str, doc_id: DocumentId) -> ChangeEvent {
         ChangeEvent {
             token: 0, // will be assigned by emit()
+            timestamp: DateTime::default(),
             operation: op,
             collection: collection.to_string(),
             doc_id,
@@ -260,6 +351,7 @@ mod tests {
 
         broker.emit(ChangeEvent {
             token: 0,
+            timestamp: DateTime::default(),
             operation: OperationType::Insert,
             collection: "users".to_string(),
             doc_id: 1,
@@ -283,6 +375,7 @@ mod tests {
         // Emit to "users" — should NOT be received
         broker.emit(ChangeEvent {
             token: 0,
+            timestamp: DateTime::default(),
             operation: OperationType::Insert,
             collection: "users".to_string(),
             doc_id: 1,
@@ -293,6 +386,7 @@ mod tests {
         // Emit to "orders" — should be received
         broker.emit(ChangeEvent {
             token: 0,
+            timestamp: DateTime::default(),
             operation: OperationType::Insert,
             collection: "orders".to_string(),
             doc_id: 2,
@@ -318,6 +412,7 @@ mod tests {
 
         broker.emit(ChangeEvent {
             token: 0,
+            timestamp: DateTime::default(),
             operation: OperationType::Delete,
             collection: "users".to_string(),
             doc_id: 1,
@@ -340,6 +435,7 @@ mod tests {
         // Emit triggers lazy cleanup
         broker.emit(ChangeEvent {
             token: 0,
+            timestamp: DateTime::default(),
             operation: OperationType::Update,
             collection: "users".to_string(),
             doc_id: 1,
@@ -402,4 +498,116 @@ mod tests {
         // take_dropped resets the counter
         assert_eq!(handle.take_dropped(), 0);
     }
+
+    #[test]
+    fn full_document_only_for_opted_in_subscribers() {
+        let broker = ChangeStreamBroker::new();
+        let plain = broker.subscribe(WatchFilter::All, 16, None).unwrap();
+        assert!(!broker.wants_full_document());
+        let opts = WatchOptions { full_document: true, ..Default::default() };
+        let full = broker.subscribe_with_options(WatchFilter::All, 16, &opts).unwrap();
+        assert!(broker.wants_full_document());
+
+        broker.emit(ChangeEvent {
+            token: 0,
+            timestamp: DateTime::default(),
+            operation: OperationType::Update,
+            collection: "users".to_string(),
+            doc_id: 1,
+            document: Some(json!({"_id": 1, "name": "Bob", "_version": 2})),
+            tx_id: None,
+        });
+
+        let event = plain.rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert!(event.document.is_none());
+        let event = full.rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(event.document.unwrap()["name"], "Bob");
+
+        broker.unsubscribe(full.id);
+        assert!(!broker.wants_full_document());
+    }
+
+    #[test]
+    fn operations_filter_drops_unrequested_types() {
+        let broker = ChangeStreamBroker::new();
+        let filter = WatchFilter::Operations {
+            collection: None,
+            operations: vec![OperationType::Insert],
+        };
+        let handle = broker.subscribe(filter, 1, None).unwrap();
+
+        broker.emit(make_event(OperationType::Update, "users", 1));
+        broker.emit(make_event(OperationType::Delete, "users", 1));
+        broker.emit(make_event(OperationType::Insert, "users", 2));
+
+        // Filtered events never reach the channel, so they can't cause drops.
+        assert_eq!(handle.take_dropped(), 0);
+        let event = handle.rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(event.operation, OperationType::Insert);
+        assert_eq!(event.doc_id, 2);
+        assert!(handle.rx.recv_timeout(Duration::from_millis(50)).is_err());
+    }
+
+    #[test]
+    fn operations_filter_with_collection() {
+        let broker = ChangeStreamBroker::new();
+        let filter = WatchFilter::Operations {
+            collection: Some("orders".to_string()),
+            operations: vec![OperationType::Insert, OperationType::Delete],
+        };
+        let handle = broker.subscribe(filter, 16, None).unwrap();
+
+        broker.emit(make_event(OperationType::Insert, "users", 1));
+        broker.emit(make_event(OperationType::Update, "orders", 2));
+        broker.emit(make_event(OperationType::Delete, "orders", 3));
+
+        let event = handle.rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(event.operation, OperationType::Delete);
+        assert_eq!(event.doc_id, 3);
+        assert!(handle.rx.recv_timeout(Duration::from_millis(50)).is_err());
+    }
+
+    #[test]
+    fn operation_type_from_name() {
+        assert_eq!(OperationType::from_name("insert"), Some(OperationType::Insert));
+        assert_eq!(OperationType::from_name("delete"), Some(OperationType::Delete));
+        assert_eq!(OperationType::from_name("upsert"), None);
+    }
+
+    #[test]
+    fn resume_after_time_replays_from_timestamp() {
+        let broker = ChangeStreamBroker::new();
+        for i in 1..=3 {
+            broker.emit(make_event(OperationType::Insert, "users", i));
+        }
+        std::thread::sleep(Duration::from_millis(5));
+        let checkpoint = Utc::now();
+        std::thread::sleep(Duration::from_millis(5));
+        for i in 4..=5 {
+            broker.emit(make_event(OperationType::Insert, "users", i));
+        }
+
+        let opts = WatchOptions { resume_after_time: Some(checkpoint), ..Default::default() };
+        let handle = broker.subscribe_with_options(WatchFilter::All, 16, &opts).unwrap();
+
+        let mut received = Vec::new();
+        while let Ok(event) = handle.rx.recv_timeout(Duration::from_millis(100)) {
+            received.push(event);
+        }
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0].doc_id, 4);
+        assert_eq!(received[1].doc_id, 5);
+        assert!(received[0].timestamp >= checkpoint);
+    }
+
+    #[test]
+    fn resume_after_time_too_old_returns_error() {
+        let broker = ChangeStreamBroker::new();
+        let before = Utc::now() - chrono::Duration::seconds(1);
+        broker.emit(make_event(OperationType::Insert, "users", 1));
+
+        let opts = WatchOptions { resume_after_time: Some(before), ..Default::default() };
+        let result = broker.subscribe_with_options(WatchFilter::All, 16, &opts);
+        assert_eq!(result.err(), Some(ResumeError::TokenTooOld));
+    }
 }
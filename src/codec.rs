@@ -1,7 +1,21 @@
+use std::io::{Read, Write};
+
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
 use serde_json::Value;
 
 use crate::error::{Error, Result};
 
+/// First byte of a compressed record: `[0x01][deflate(JSONB)]`. JSONB headers
+/// start with 0x20, 0x40 or 0x80 and JSON text with `{` or `[`, so the marker
+/// can't be confused with an uncompressed record.
+const COMPRESSED_MARKER: u8 = 0x01;
+
+/// Documents whose JSONB encoding is smaller than this are never compressed;
+/// deflate's framing would outweigh any savings.
+pub const COMPRESS_MIN_BYTES: usize = 64;
+
 /// Encode a `serde_json::Value` into JSONB binary format.
 pub fn encode_doc(value: &Value) -> Result<Vec<u8>> {
     let owned = jsonb::to_owned_jsonb(value)
@@ -9,12 +23,38 @@ pub fn encode_doc(value: &Value) -> Result<Vec<u8>> {
     Ok(owned.to_vec())
 }
 
+/// Encode a document, deflate-compressing the JSONB when `compress` is set.
+/// Small documents, and documents that don't shrink, are stored as plain JSONB.
+pub fn encode_doc_with(value: &Value, compress: bool) -> Result<Vec<u8>> {
+    let jsonb = encode_doc(value)?;
+    if !compress || jsonb.len() < COMPRESS_MIN_BYTES {
+        return Ok(jsonb);
+    }
+
+    let mut out = Vec::with_capacity(jsonb.len() / 2 + 1);
+    out.push(COMPRESSED_MARKER);
+    let mut encoder = DeflateEncoder::new(out, Compression::fast());
+    encoder.write_all(&jsonb)?;
+    let compressed = encoder.finish()?;
+    if compressed.len() < jsonb.len() {
+        Ok(compressed)
+    } else {
+        Ok(jsonb)
+    }
+}
+
+/// Returns `true` if the record was written compressed by `encode_doc_with`.
+pub fn is_compressed(bytes: &[u8]) -> bool {
+    bytes.first() == Some(&COMPRESSED_MARKER)
+}
+
 /// Decode bytes into a `serde_json::Value`.
 ///
 /// Auto-detects the format: if the first byte is `{` (0x7B) or `[` (0x5B),
 /// the payload is treated as JSON text; otherwise it is decoded as JSONB binary.
 /// This allows transparent reading of legacy JSON `.dat` files alongside new
-/// JSONB records without requiring a migration step.
+/// JSONB records without requiring a migration step. Compressed records are
+/// inflated first, so a file may mix compressed and uncompressed records.
 pub fn decode_doc(bytes: &[u8]) -> Result<Value> {
     if bytes.is_empty() {
         return Err(Error::Codec("empty payload".into()));
@@ -25,6 +65,14 @@ pub fn decode_doc(bytes: &[u8]) -> Result<Value> {
             // Legacy JSON text
             serde_json::from_slice(bytes).map_err(|e| Error::Codec(e.to_string()))
         }
+        COMPRESSED_MARKER => {
+            let mut jsonb = Vec::with_capacity(bytes.len() * 2);
+            DeflateDecoder::new(&bytes[1..])
+                .read_to_end(&mut jsonb)
+                .map_err(|e| Error::Codec(format!("corrupt compressed record: {e}")))?;
+            let raw = jsonb::RawJsonb::new(&jsonb);
+            jsonb::from_raw_jsonb(&raw).map_err(|e| Error::Codec(e.to_string()))
+        }
         _ => {
             // JSONB binary
             let raw = jsonb::RawJsonb::new(bytes);
@@ -85,4 +133,53 @@ mod tests {
         let decoded = decode_doc(&encoded).unwrap();
         assert_eq!(val, decoded);
     }
+
+    #[test]
+    fn compressed_roundtrip() {
+        let val = json!({
+            "_id": 7,
+            "body": "lorem ipsum dolor sit amet ".repeat(20),
+            "tags": ["a", "b", "c"],
+        });
+        let plain = encode_doc(&val).unwrap();
+        let compressed = encode_doc_with(&val, true).unwrap();
+        assert!(is_compressed(&compressed));
+        assert!(compressed.len() < plain.len());
+        assert_eq!(decode_doc(&compressed).unwrap(), val);
+    }
+
+    #[test]
+    fn small_docs_stay_uncompressed() {
+        let val = json!({"s": "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"});
+        let plain = encode_doc(&val).unwrap();
+        assert!(plain.len() < COMPRESS_MIN_BYTES);
+        let encoded = encode_doc_with(&val, true).unwrap();
+        assert!(!is_compressed(&encoded));
+        assert_eq!(encoded, plain);
+    }
+
+    #[test]
+    fn incompressible_docs_stay_uncompressed() {
+        // Distinct short keys and numbers give deflate little to work with
+        let mut obj = serde_json::Map::new();
+        for i in 0..12u64 {
+            obj.insert(format!("{i:x}"), json!(i.wrapping_mul(0x9E37_79B9_7F4A_7C15)));
+        }
+        let val = Value::Object(obj);
+        let plain = encode_doc(&val).unwrap();
+        let encoded = encode_doc_with(&val, true).unwrap();
+        assert!(encoded.len() <= plain.len());
+        assert_eq!(decode_doc(&encoded).unwrap(), val);
+    }
+
+    #[test]
+    fn compression_disabled_is_plain_jsonb() {
+        let val = json!({"body": "x".repeat(500)});
+        assert_eq!(encode_doc_with(&val, false).unwrap(), encode_doc(&val).unwrap());
+    }
+
+    #[test]
+    fn corrupt_compressed_record_errors() {
+        assert!(decode_doc(&[COMPRESSED_MARKER, 0xff, 0xff, 0xff]).is_err());
+    }
 }
@@ -1,21 +1,27 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use serde_json::Value;
 
+use crate::bulk::{BulkOp, BulkOpResult};
 use crate::crypto::EncryptionKey;
-use crate::document::DocumentId;
+use crate::defaults::FieldDefaults;
+use crate::doc_cache::DocCache;
+use crate::document::{self, DocumentId};
 use crate::engine::LogCallback;
 use crate::error::{Error, Result};
 use crate::fts::CollectionTextIndex;
+use crate::geo::{self, GeoIndex, GeoQuery};
 use crate::index::{CompositeIndex, FieldIndex};
 use crate::index_persist;
-use crate::vector::{DistanceMetric, VectorIndex};
-use crate::query::{self, FindOptions, Query, SortOrder};
-use crate::storage::{DocLocation, Storage};
+use crate::vector::{DistanceMetric, HnswParams, VectorIndex, VectorSearchOptions};
+use crate::query::{self, FindAndModifyOptions, FindOptions, IndexHint, Query, QueryPlan, SortOrder};
+use crate::schema::{CollectionSchema, ValidationLevel};
+use crate::storage::{DocLocation, Salvaged, Storage, SyncMode};
 use crate::value::IndexValue;
-use crate::wal::{Wal, WalEntry};
+use crate::wal::{CommitTicket, DEFAULT_GROUP_COMMIT_WINDOW, GroupCommit, RecoveryTargets, Wal, WalEntry};
 
 /// Resolve a field path (with dot notation) directly on a &Value.
 fn resolve_field_in_value<'a>(data: &'a Value, path: &str) -> Option<&'a Value> {
@@ -26,6 +32,114 @@ fn resolve_field_in_value<'a>(data: &'a Value, path: &str) -> Option<&'a Value>
     Some(current)
 }
 
+/// Collections with at least this many documents run unindexed scans on
+/// several threads; smaller ones aren't worth the thread startup.
+const PARALLEL_SCAN_MIN_DOCS: usize = 50_000;
+
+/// Fail with `DocumentTooLarge` if `data` serializes to more than `max` bytes.
+pub(crate) fn check_document_size(data: &Value, max: Option<usize>) -> Result<()> {
+    let Some(max) = max else {
+        return Ok(());
+    };
+    let size = serde_json::to_vec(data)?.len();
+    if size > max {
+        return Err(Error::DocumentTooLarge { size, max });
+    }
+    Ok(())
+}
+
+/// Default number of scan threads: one per available CPU.
+pub fn default_scan_workers() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Match a raw record against a query, extracting only the referenced fields
+/// from JSONB and falling back to a full decode for other formats.
+fn record_matches(query: &Query, bytes: &[u8]) -> Result<bool> {
+    match query::matches_raw_jsonb(query, bytes) {
+        Some(matched) => Ok(matched),
+        None => Ok(query::matches_value(query, &crate::codec::decode_doc(bytes)?)),
+    }
+}
+
+/// Documents a composite index narrows `query` to: those matching the
+/// query's equality conditions on a prefix of its fields, and any range
+/// condition on the field after that prefix. `None` if neither applies.
+fn composite_candidates(comp_idx: &CompositeIndex, query: &Query) -> Option<BTreeSet<DocumentId>> {
+    let eq = query::extract_eq_conditions(query).unwrap_or_default();
+    let prefix: Vec<IndexValue> = comp_idx.fields.iter().map_while(|f| eq.get(f).cloned()).collect();
+    let (start, end) = match comp_idx.fields.get(prefix.len()) {
+        Some(next) => query::extract_range_bounds(query, next),
+        None => (Bound::Unbounded, Bound::Unbounded),
+    };
+    if !matches!((start, end), (Bound::Unbounded, Bound::Unbounded)) {
+        Some(comp_idx.find_prefix_range(&prefix, start, end))
+    } else if !prefix.is_empty() {
+        Some(comp_idx.find_prefix(&prefix))
+    } else {
+        None
+    }
+}
+
+/// Apply a find's sort, then skip, then limit to the collected matches.
+fn sort_skip_limit(mut results: Vec<Arc<Value>>, opts: &FindOptions) -> Vec<Arc<Value>> {
+    if let Some(sort_fields) = &opts.sort {
+        results.sort_by(|a, b| {
+            for (field, order) in sort_fields {
+                let av = a.pointer(&format!("/{}", field.replace('.', "/")));
+                let bv = b.pointer(&format!("/{}", field.replace('.', "/")));
+                let aiv = av.map(IndexValue::from_json).unwrap_or(IndexValue::Null);
+                let biv = bv.map(IndexValue::from_json).unwrap_or(IndexValue::Null);
+                let cmp = aiv.cmp(&biv);
+                let cmp = match order {
+                    SortOrder::Asc => cmp,
+                    SortOrder::Desc => cmp.reverse(),
+                };
+                if cmp != std::cmp::Ordering::Equal {
+                    return cmp;
+                }
+            }
+            std::cmp::Ordering::Equal
+        });
+    }
+
+    if let Some(skip) = opts.skip {
+        let skip = skip as usize;
+        if skip >= results.len() {
+            results.clear();
+        } else {
+            results = results.into_iter().skip(skip).collect();
+        }
+    }
+
+    if let Some(limit) = opts.limit {
+        results.truncate(limit as usize);
+    }
+
+    results
+}
+
+/// Add one value to a `distinct` result, keyed by its index ordering.
+fn add_distinct(values: &mut BTreeMap<IndexValue, Value>, value: &Value) {
+    let key = IndexValue::from_json(value);
+    let value = match value {
+        Value::Object(_) | Value::Array(_) => value.clone(),
+        _ => key.to_json(),
+    };
+    values.entry(key).or_insert(value);
+}
+
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
 /// Metadata about an index on a collection.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct IndexInfo {
@@ -37,13 +151,75 @@ pub struct IndexInfo {
     pub dimension: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metric: Option<String>,
+    /// Vector index HNSW `M`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub m: Option<usize>,
+    /// Vector index HNSW `ef_construction`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ef_construction: Option<usize>,
+    /// Vector index stores unit-normalized vectors.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub normalize: bool,
+    /// Vectors a vector index holds. Reported by `list_indexes`, not persisted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vectors: Option<usize>,
+    /// Removed entries left in a vector index's HNSW graph until the next
+    /// compaction. Reported by `list_indexes`, not persisted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tombstones: Option<usize>,
+    /// Field index keeps a bloom filter for equality lookups.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bloom: bool,
+    /// Field index is walked from its highest key by sort pushdown.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub descending: bool,
+    /// Field index leaves out documents where the field is null.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sparse: bool,
+}
+
+/// Summary of a collection returned by `describe`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CollectionDescription {
+    pub count: usize,
+    /// Number of documents the field profile was built from.
+    pub sampled: usize,
+    /// Top-level fields of the sampled documents, sorted by name.
+    pub fields: Vec<FieldProfile>,
+    pub indexes: Vec<IndexInfo>,
+}
+
+/// A top-level field seen while sampling a collection.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct FieldProfile {
+    pub field: String,
+    /// JSON types the field was seen with, sorted by name.
+    pub types: Vec<String>,
+    /// Number of sampled documents that have the field.
+    pub count: usize,
 }
 
+/// Documents sampled by `describe` when no sample size is given.
+pub const DEFAULT_DESCRIBE_SAMPLE: usize = 100;
+
 /// Persisted index metadata (written to .idx files).
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 struct IndexMetadata {
     version: u32,
     indexes: Vec<IndexInfo>,
+    /// Compress new records at rest (see `codec::encode_doc_with`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    compress: bool,
+    /// Keep at most this many documents in memory and read the rest from
+    /// disk on demand. `None` keeps every document cached.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    doc_cache_limit: Option<usize>,
+    /// JSON Schema that written documents are validated against.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    schema: Option<CollectionSchema>,
+    /// Values filled in for fields an inserted document omits.
+    #[serde(default, skip_serializing_if = "FieldDefaults::is_empty")]
+    defaults: FieldDefaults,
 }
 
 /// Statistics returned after a compaction run.
@@ -54,6 +230,46 @@ pub struct CompactStats {
     pub docs_kept: usize,
 }
 
+/// Whether a write waits for its data to be fsynced before returning.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Durability {
+    /// fsync before returning.
+    #[default]
+    Full,
+    /// Skip the fsync, for bulk loads. The data reaches disk when the OS
+    /// writes it back, with the next full-durability write to the
+    /// collection, or at `flush`; a crash may lose everything written since.
+    Relaxed,
+}
+
+/// Outcome of repairing a collection's data file.
+#[derive(Debug, Clone)]
+pub struct RepairReport {
+    /// Documents that were readable and kept.
+    pub recovered: usize,
+    /// Records dropped: failed checksums, undecodable payloads, and a
+    /// truncated or unparseable tail (counted as one).
+    pub lost: usize,
+    /// File the dropped records' raw bytes were appended to, if any.
+    pub quarantine: Option<PathBuf>,
+}
+
+/// A document about to be deleted.
+struct DeleteOp {
+    id: DocumentId,
+    loc: DocLocation,
+    data: Value,
+}
+
+/// A document about to be replaced by its updated version.
+struct UpdateOp {
+    id: DocumentId,
+    old_loc: DocLocation,
+    old_data: Value,
+    new_data: Value,
+    new_bytes: Vec<u8>,
+}
+
 /// A prepared mutation from transactional prepare_tx_* methods.
 pub struct PreparedMutation {
     pub wal_entry: WalEntry,
@@ -70,15 +286,24 @@ pub struct Collection {
     data_dir: PathBuf,
     storage: Storage,
     wal: Wal,
+    /// Shares fsyncs between concurrent `insert_grouped` callers.
+    group_commit: Arc<GroupCommit>,
     primary_index: HashMap<DocumentId, DocLocation>,
-    doc_cache: HashMap<DocumentId, Arc<Value>>,
+    doc_cache: DocCache,
     field_indexes: HashMap<String, FieldIndex>,
     composite_indexes: Vec<CompositeIndex>,
     text_index: Option<CollectionTextIndex>,
     vector_indexes: HashMap<String, VectorIndex>,
+    geo_indexes: HashMap<String, GeoIndex>,
     version_index: HashMap<DocumentId, u64>,
     next_id: DocumentId,
     encryption: Option<Arc<EncryptionKey>>,
+    compress: bool,
+    scan_workers: usize,
+    /// Largest document, in serialized bytes, that `insert` accepts.
+    max_doc_size: Option<usize>,
+    schema: Option<CollectionSchema>,
+    defaults: FieldDefaults,
     verbose: bool,
     log_callback: Option<LogCallback>,
 }
@@ -93,24 +318,32 @@ impl Collection {
     }
 }
 
-/// Load persisted index definitions from a .idx file.
-fn load_index_metadata(path: &Path) -> Result<Vec<IndexInfo>> {
+/// Load persisted index definitions and collection settings from a .idx file.
+fn load_index_metadata(path: &Path) -> Result<IndexMetadata> {
     if !path.exists() {
-        return Ok(Vec::new());
+        return Ok(IndexMetadata::default());
     }
     let bytes = std::fs::read(path)?;
     let meta: IndexMetadata = serde_json::from_slice(&bytes)
         .map_err(|e| Error::InvalidQuery(format!("corrupt .idx file: {}", e)))?;
-    Ok(meta.indexes)
+    Ok(meta)
 }
 
 impl Collection {
     /// Persist current index definitions to a .idx file alongside the .dat file.
     fn save_index_metadata(&self) -> Result<()> {
-        let indexes = self.list_indexes();
+        let mut indexes = self.list_indexes();
+        for info in &mut indexes {
+            info.vectors = None;
+            info.tombstones = None;
+        }
         let meta = IndexMetadata {
             version: 1,
             indexes,
+            compress: self.compress,
+            doc_cache_limit: self.doc_cache_limit(),
+            schema: self.schema.clone(),
+            defaults: self.defaults.clone(),
         };
         let path = self.data_dir.join(format!("{}.idx", self.name));
         let json = serde_json::to_vec_pretty(&meta)?;
@@ -148,7 +381,7 @@ impl Collection {
 
     /// Create or open a collection backed by a data file.
     pub fn open(name: &str, data_dir: &Path) -> Result<Self> {
-        Self::open_with_options(name, data_dir, &HashSet::new(), None, false, None)
+        Self::open_with_options(name, data_dir, &HashSet::new(), None, false, None, false)
     }
 
     /// Create or open a collection, filtering WAL recovery by committed tx_ids.
@@ -157,10 +390,12 @@ impl Collection {
         data_dir: &Path,
         committed_tx_ids: &HashSet<u64>,
     ) -> Result<Self> {
-        Self::open_with_options(name, data_dir, committed_tx_ids, None, false, None)
+        Self::open_with_options(name, data_dir, committed_tx_ids, None, false, None, false)
     }
 
     /// Create or open a collection with optional encryption and tx recovery.
+    /// With `skip_corrupt_records`, records that fail their checksum are
+    /// logged and left out instead of failing the open.
     pub fn open_with_options(
         name: &str,
         data_dir: &Path,
@@ -168,6 +403,7 @@ impl Collection {
         encryption: Option<Arc<EncryptionKey>>,
         verbose: bool,
         log_callback: Option<LogCallback>,
+        skip_corrupt_records: bool,
     ) -> Result<Self> {
         let vlog = |msg: &str| {
             eprintln!("{msg}");
@@ -179,7 +415,13 @@ impl Collection {
         let data_path = data_dir.join(format!("{}.dat", name));
         let wal_path = data_dir.join(format!("{}.wal", name));
         let storage = Storage::open_with_encryption(&data_path, encryption.clone())?;
-        let wal = Wal::open_with_encryption(&wal_path, encryption.clone())?;
+        let mut wal = Wal::open_with_encryption(&wal_path, encryption.clone())?;
+
+        // WAL retention is on while the history file exists
+        let history_path = data_dir.join(format!("{}{}", name, crate::wal::HISTORY_SUFFIX));
+        if history_path.exists() {
+            wal.set_history(Some(&history_path))?;
+        }
 
         if verbose {
             let file_size = storage.file_size();
@@ -188,7 +430,18 @@ impl Collection {
 
         // Load persisted index definitions (if any)
         let idx_path = data_dir.join(format!("{}.idx", name));
-        let persisted_indexes = load_index_metadata(&idx_path)?;
+        let IndexMetadata {
+            indexes: persisted_indexes,
+            compress,
+            doc_cache_limit,
+            schema,
+            defaults,
+            ..
+        } = load_index_metadata(&idx_path)?;
+        // Patterns aren't persisted; recompile them
+        let schema = schema
+            .map(|s| CollectionSchema::new(s.schema, s.level, s.coerce))
+            .transpose()?;
         let has_persisted_indexes = !persisted_indexes.is_empty();
 
         // Pre-create empty index structures from metadata
@@ -196,14 +449,17 @@ impl Collection {
         let mut composite_indexes: Vec<CompositeIndex> = Vec::new();
         let mut text_index: Option<CollectionTextIndex> = None;
         let mut vector_indexes: HashMap<String, VectorIndex> = HashMap::new();
+        let mut geo_indexes: HashMap<String, GeoIndex> = HashMap::new();
 
         for info in &persisted_indexes {
             match info.index_type.as_str() {
                 "field" => {
-                    field_indexes.insert(
-                        info.name.clone(),
-                        FieldIndex::new(info.name.clone()),
-                    );
+                    let idx = if info.sparse {
+                        FieldIndex::new_sparse(info.name.clone())
+                    } else {
+                        FieldIndex::new(info.name.clone())
+                    };
+                    field_indexes.insert(info.name.clone(), idx);
                 }
                 "unique" => {
                     field_indexes.insert(
@@ -221,12 +477,18 @@ impl Collection {
                     if let (Some(dim), Some(metric_str)) = (info.dimension, info.metric.as_deref()) {
                         let field = info.fields.first().cloned().unwrap_or_default();
                         let metric = VectorIndex::parse_metric(metric_str);
+                        // Indexes created before the parameters were stored use the defaults
+                        let params = HnswParams::new(info.m, info.ef_construction).unwrap_or_default();
                         vector_indexes.insert(
                             field.clone(),
-                            VectorIndex::new(field, dim, metric),
+                            VectorIndex::new(field, dim, metric, params).with_normalization(info.normalize),
                         );
                     }
                 }
+                "geo" => {
+                    let field = info.fields.first().cloned().unwrap_or_default();
+                    geo_indexes.insert(field.clone(), GeoIndex::new(field));
+                }
                 _ => {}
             }
         }
@@ -240,7 +502,10 @@ impl Collection {
         }
 
         let mut primary_index = HashMap::new();
-        let mut doc_cache: HashMap<DocumentId, Arc<Value>> = HashMap::new();
+        let mut doc_cache = match doc_cache_limit {
+            Some(limit) => DocCache::lazy(limit),
+            None => DocCache::full(),
+        };
         let mut version_index = HashMap::new();
         let mut next_id: DocumentId = 1;
 
@@ -253,7 +518,7 @@ impl Collection {
         // Phase 1: Scan .dat for primary_index, doc_cache, version_index, next_id.
         // Also rebuild text index (always from docs — not cached).
         // Field/composite indexes are NOT rebuilt here; we try the cache first.
-        storage.for_each_active(|loc, bytes| {
+        let load_doc = |loc, bytes: Vec<u8>| {
             let doc: Value = crate::codec::decode_doc(&bytes)?;
             if let Some(id) = doc.get("_id").and_then(|v| v.as_u64()) {
                 primary_index.insert(id, loc);
@@ -265,10 +530,13 @@ impl Collection {
 
                 let doc_arc = Arc::new(doc);
 
-                // Text index is always rebuilt from docs (not cached)
+                // Text and geo indexes are always rebuilt from docs (not cached)
                 if let Some(ref mut ti) = text_index {
                     ti.index_doc(id, &doc_arc);
                 }
+                for gi in geo_indexes.values_mut() {
+                    gi.insert(id, &doc_arc);
+                }
 
                 doc_cache.insert(id, doc_arc);
             }
@@ -286,7 +554,16 @@ impl Collection {
                 }
             }
             Ok(())
-        })?;
+        };
+        let mut corrupt = Vec::new();
+        if skip_corrupt_records {
+            storage.for_each_active_skipping_corrupt(load_doc, |_, e| corrupt.push(e))?;
+        } else {
+            storage.for_each_active(load_doc)?;
+        }
+        for e in &corrupt {
+            vlog(&format!("[warn] {}: skipped record: {}", name, e));
+        }
 
         if verbose {
             vlog(&format!(
@@ -354,27 +631,29 @@ impl Collection {
             }
         }
 
-        // Phase 2b: If cache was invalid, rebuild indexes from doc_cache (zero disk I/O)
+        // Phase 2b: If cache was invalid, rebuild indexes from doc_cache (zero disk I/O),
+        // or from a second pass over the .dat file when documents are loaded lazily.
         if has_persisted_indexes && !indexes_from_cache {
             if verbose {
                 vlog(&format!(
-                    "[verbose] {}: index cache invalid, rebuilding {} indexes from doc_cache...",
+                    "[verbose] {}: index cache invalid, rebuilding {} indexes from {}...",
                     name,
                     persisted_indexes.len(),
+                    if doc_cache.is_lazy() { "storage" } else { "doc_cache" },
                 ));
             }
             let rebuild_start = std::time::Instant::now();
             let mut rebuild_count = 0u64;
 
-            for (&id, arc) in &doc_cache {
+            let mut rebuild_doc = |id: DocumentId, doc: &Value| {
                 for idx in field_indexes.values_mut() {
-                    idx.insert_value(id, arc);
+                    idx.insert_value(id, doc);
                 }
                 for idx in &mut composite_indexes {
-                    idx.insert_value(id, arc);
+                    idx.insert_value(id, doc);
                 }
                 for idx in vector_indexes.values_mut() {
-                    let _ = idx.insert(id, arc);
+                    let _ = idx.insert(id, doc);
                 }
                 rebuild_count += 1;
                 if verbose && rebuild_count % 500_000 == 0 {
@@ -387,6 +666,23 @@ impl Collection {
                     );
                     vlog(&msg);
                 }
+            };
+
+            match doc_cache.as_map() {
+                Some(map) => {
+                    for (&id, arc) in map {
+                        rebuild_doc(id, arc);
+                    }
+                }
+                None => {
+                    storage.for_each_active(|_loc, bytes| {
+                        let doc: Value = crate::codec::decode_doc(&bytes)?;
+                        if let Some(id) = doc.get("_id").and_then(|v| v.as_u64()) {
+                            rebuild_doc(id, &doc);
+                        }
+                        Ok(())
+                    })?;
+                }
             }
 
             if verbose {
@@ -399,19 +695,29 @@ impl Collection {
             }
         }
 
+        // Bloom filters aren't part of the index cache; build them from the
+        // loaded keys so WAL replay below keeps them current.
+        for info in persisted_indexes.iter().filter(|info| info.bloom) {
+            if let Some(idx) = field_indexes.get_mut(&info.name) {
+                idx.enable_bloom();
+            }
+        }
+        for info in persisted_indexes.iter().filter(|info| info.descending || info.sparse) {
+            if let Some(idx) = field_indexes.get_mut(&info.name) {
+                idx.descending = info.descending;
+                idx.sparse = info.sparse;
+            }
+        }
+
         // Phase 3: WAL recovery (updates indexes and doc_cache too)
-        wal.recover(
-            &storage,
-            &mut primary_index,
-            &mut doc_cache,
-            &mut next_id,
-            committed_tx_ids,
-            &mut version_index,
-            &mut field_indexes,
-            &mut composite_indexes,
-            verbose,
-            &log_callback,
-        )?;
+        let targets = RecoveryTargets {
+            primary_index: &mut primary_index,
+            doc_cache: &mut doc_cache,
+            version_index: &mut version_index,
+            field_indexes: &mut field_indexes,
+            composite_indexes: &mut composite_indexes,
+        };
+        wal.recover(&storage, targets, &mut next_id, committed_tx_ids, verbose, &log_callback)?;
 
         if verbose {
             vlog(&format!("[verbose] {}: collection ready", name));
@@ -422,15 +728,22 @@ impl Collection {
             data_dir: data_dir.to_path_buf(),
             storage,
             wal,
+            group_commit: Arc::new(GroupCommit::new(DEFAULT_GROUP_COMMIT_WINDOW)),
             primary_index,
             doc_cache,
             field_indexes,
             composite_indexes,
             text_index,
             vector_indexes,
+            geo_indexes,
             version_index,
             next_id,
             encryption,
+            compress,
+            scan_workers: default_scan_workers(),
+            max_doc_size: None,
+            schema,
+            defaults,
             verbose,
             log_callback,
         };
@@ -447,24 +760,277 @@ impl Collection {
         &self.name
     }
 
+    /// Encode a document for storage using this collection's codec settings.
+    fn encode(&self, doc: &Value) -> Result<Vec<u8>> {
+        crate::codec::encode_doc_with(doc, self.compress)
+    }
+
+    /// Whether new records are compressed at rest.
+    pub fn compression_enabled(&self) -> bool {
+        self.compress
+    }
+
+    /// Enable or disable compression at rest. Only affects records written
+    /// from now on; run `compact` to rewrite existing records. Records in
+    /// either format can always be read.
+    pub fn set_compression(&mut self, enabled: bool) -> Result<()> {
+        self.compress = enabled;
+        self.save_index_metadata()
+    }
+
+    /// Whether checkpointed WAL entries are retained for point-in-time
+    /// recovery.
+    pub fn wal_retention_enabled(&self) -> bool {
+        self.wal.has_history()
+    }
+
+    /// Enable or disable WAL retention. Enabling starts the history file
+    /// with a snapshot of every current document, so the history alone can
+    /// rebuild the collection. Disabling deletes the history.
+    pub fn set_wal_retention(&mut self, enabled: bool) -> Result<()> {
+        let history_path = self
+            .data_dir
+            .join(format!("{}{}", self.name, crate::wal::HISTORY_SUFFIX));
+        if enabled == self.wal.has_history() {
+            return Ok(());
+        }
+        if !enabled {
+            self.wal.set_history(None)?;
+            if history_path.exists() {
+                std::fs::remove_file(&history_path)?;
+            }
+            return Ok(());
+        }
+
+        self.wal.checkpoint()?;
+        let mut locations: Vec<(DocumentId, DocLocation)> =
+            self.primary_index.iter().map(|(&id, &loc)| (id, loc)).collect();
+        locations.sort_unstable_by_key(|(_, loc)| loc.offset);
+        let mut snapshot = Vec::with_capacity(locations.len());
+        for (id, loc) in locations {
+            snapshot.push(WalEntry::insert(id, self.storage.read(loc)?));
+        }
+        // Start from an empty file so a stale history can't precede the snapshot
+        std::fs::File::create(&history_path)?;
+        self.wal.set_history(Some(&history_path))?;
+        self.wal.append_history(&snapshot)
+    }
+
+    /// Set how many threads unindexed scans of large collections may use.
+    /// 1 keeps every scan on the calling thread.
+    pub fn set_scan_workers(&mut self, workers: usize) {
+        self.scan_workers = workers.max(1);
+    }
+
+    /// Set when the data file and WAL are fsynced (see `SyncMode`).
+    pub fn set_sync_mode(&mut self, mode: SyncMode) {
+        self.storage.set_sync_mode(mode);
+        self.wal.set_sync_mode(mode);
+    }
+
+    /// Set how long a group commit waits for other writers to join its
+    /// fsync (see `GroupCommit`).
+    pub fn set_group_commit_window(&self, window: std::time::Duration) {
+        self.group_commit.set_window(window);
+    }
+
+    /// The group commit that `insert_grouped` tickets are waited on.
+    pub fn group_commit(&self) -> Arc<GroupCommit> {
+        Arc::clone(&self.group_commit)
+    }
+
+    /// Fraction of the data file taken by deleted or superseded records,
+    /// from 0.0 (none) to 1.0. `compact` reclaims it.
+    pub fn dead_space_ratio(&self) -> f64 {
+        let file_size = self.storage.file_size();
+        if file_size == 0 {
+            return 0.0;
+        }
+        let live: u64 = self.primary_index.values().map(DocLocation::record_len).sum();
+        1.0 - live.min(file_size) as f64 / file_size as f64
+    }
+
+    /// Set the largest document `insert` and `insert_many` accept, in
+    /// serialized JSON bytes. `None` removes the limit.
+    pub fn set_max_document_size(&mut self, max: Option<usize>) {
+        self.max_doc_size = max;
+    }
+
+    fn check_document_size(&self, data: &Value) -> Result<()> {
+        check_document_size(data, self.max_doc_size)
+    }
+
+    /// The JSON Schema written documents are validated against, if any.
+    pub fn schema(&self) -> Option<&CollectionSchema> {
+        self.schema.as_ref()
+    }
+
+    /// Validate documents written from now on against `schema`, or stop
+    /// validating with `None`. Existing documents aren't checked. The
+    /// setting is persisted.
+    pub fn set_schema(&mut self, schema: Option<CollectionSchema>) -> Result<()> {
+        self.schema = schema;
+        self.save_index_metadata()
+    }
+
+    /// The values filled in for fields an inserted document omits.
+    pub fn defaults(&self) -> &FieldDefaults {
+        &self.defaults
+    }
+
+    /// Fill in `defaults` for fields documents inserted from now on omit.
+    /// Pass an empty `FieldDefaults` to stop. The setting is persisted.
+    pub fn set_defaults(&mut self, defaults: FieldDefaults) -> Result<()> {
+        self.defaults = defaults;
+        self.save_index_metadata()
+    }
+
+    /// Validate a document about to be written against the collection's
+    /// schema, coercing its values first if the schema allows. A failure is
+    /// an error at the strict level and a logged warning at the warn level.
+    fn validate_document(&self, data: &mut Value) -> Result<()> {
+        let Some(schema) = &self.schema else {
+            return Ok(());
+        };
+        let errors = schema.validate(data);
+        if errors.is_empty() {
+            return Ok(());
+        }
+        let msg = errors.join("; ");
+        match schema.level {
+            ValidationLevel::Strict => Err(Error::ValidationFailed(msg)),
+            ValidationLevel::Warn => {
+                self.vlog(&format!("[warn] {}: document fails schema validation: {msg}", self.name));
+                Ok(())
+            }
+        }
+    }
+
+    /// Threads to use for a full scan of this collection.
+    fn scan_workers_for_scan(&self) -> usize {
+        if self.primary_index.len() >= PARALLEL_SCAN_MIN_DOCS {
+            self.scan_workers
+        } else {
+            1
+        }
+    }
+
+    /// Active record locations in file order, split into at most `workers`
+    /// contiguous chunks.
+    fn scan_chunks(&self, workers: usize) -> Vec<Vec<DocLocation>> {
+        let mut locations: Vec<DocLocation> = self.primary_index.values().copied().collect();
+        locations.sort_unstable_by_key(|loc| loc.offset);
+        let chunk_size = locations.len().div_ceil(workers).max(1);
+        locations.chunks(chunk_size).map(<[DocLocation]>::to_vec).collect()
+    }
+
+    /// Collect every document matching `query` using `workers` threads. Each
+    /// thread matches a contiguous chunk and results are concatenated in chunk
+    /// order, so the output order is the same as a sequential scan.
+    fn scan_matching(&self, query: &Query, workers: usize) -> Result<Vec<Arc<Value>>> {
+        let chunks: Vec<Vec<Arc<Value>>> = match self.doc_cache.as_map() {
+            Some(map) => {
+                let docs: Vec<&Arc<Value>> = map.values().collect();
+                let chunk_size = docs.len().div_ceil(workers).max(1);
+                std::thread::scope(|scope| {
+                    let handles: Vec<_> = docs
+                        .chunks(chunk_size)
+                        .map(|chunk| {
+                            scope.spawn(move || {
+                                chunk
+                                    .iter()
+                                    .filter(|arc| query::matches_value(query, arc))
+                                    .map(|arc| Arc::clone(arc))
+                                    .collect::<Vec<_>>()
+                            })
+                        })
+                        .collect();
+                    handles.into_iter().map(|h| h.join().unwrap()).collect()
+                })
+            }
+            None => {
+                let chunks = self.scan_chunks(workers);
+                std::thread::scope(|scope| {
+                    let handles: Vec<_> = chunks
+                        .iter()
+                        .map(|chunk| {
+                            scope.spawn(move || -> Result<Vec<Arc<Value>>> {
+                                let mut found = Vec::new();
+                                self.storage.scan_locations_readonly(chunk, |_loc, bytes| {
+                                    let doc = crate::codec::decode_doc(bytes)?;
+                                    if query::matches_value(query, &doc) {
+                                        found.push(Arc::new(doc));
+                                    }
+                                    Ok(true)
+                                })?;
+                                Ok(found)
+                            })
+                        })
+                        .collect();
+                    handles.into_iter().map(|h| h.join().unwrap()).collect::<Result<_>>()
+                })?
+            }
+        };
+        Ok(chunks.into_iter().flatten().collect())
+    }
+
     /// Access the field indexes for index-accelerated aggregation.
     pub fn field_indexes(&self) -> &HashMap<String, FieldIndex> {
         &self.field_indexes
     }
 
     /// Access the document cache for index-accelerated aggregation.
-    pub fn doc_cache(&self) -> &HashMap<DocumentId, Arc<Value>> {
-        &self.doc_cache
+    /// Returns `None` when documents are loaded lazily.
+    pub fn doc_cache(&self) -> Option<&HashMap<DocumentId, Arc<Value>>> {
+        self.doc_cache.as_map()
+    }
+
+    /// Maximum number of cached documents in lazy mode, or `None` when every
+    /// document is kept in memory.
+    pub fn doc_cache_limit(&self) -> Option<usize> {
+        match &self.doc_cache {
+            DocCache::Full(_) => None,
+            DocCache::Lazy(lru) => Some(lru.lock().unwrap().capacity()),
+        }
     }
 
-    /// Read a document by its ID from the in-memory cache.
+    /// Switch between caching every document (`None`) and lazy loading with
+    /// an LRU cache of at most `limit` documents. The setting is persisted.
+    pub fn set_doc_cache_limit(&mut self, limit: Option<usize>) -> Result<()> {
+        match limit {
+            Some(limit) => self.doc_cache = DocCache::lazy(limit),
+            None if self.doc_cache.is_lazy() => {
+                let mut cache = DocCache::full();
+                self.for_each_doc_arc_while(|id, arc| {
+                    cache.insert(id, Arc::clone(arc));
+                    Ok(true)
+                })?;
+                self.doc_cache = cache;
+            }
+            None => {}
+        }
+        self.save_index_metadata()
+    }
+
+    /// Read a document by its ID, from the cache or (in lazy mode) from disk.
     fn read_doc(&self, id: DocumentId) -> Result<Option<Value>> {
-        Ok(self.doc_cache.get(&id).map(|arc| (**arc).clone()))
+        Ok(self.read_doc_arc(id)?.map(|arc| (*arc).clone()))
     }
 
-    /// Read a document by its ID, returning an Arc (zero-copy from cache).
-    fn read_doc_arc(&self, id: DocumentId) -> Option<Arc<Value>> {
-        self.doc_cache.get(&id).map(Arc::clone)
+    /// Read a document by its ID, returning an Arc (zero-copy on a cache hit).
+    fn read_doc_arc(&self, id: DocumentId) -> Result<Option<Arc<Value>>> {
+        if let Some(arc) = self.doc_cache.get(id) {
+            return Ok(Some(arc));
+        }
+        if !self.doc_cache.is_lazy() {
+            return Ok(None);
+        }
+        let Some(&loc) = self.primary_index.get(&id) else {
+            return Ok(None);
+        };
+        let arc = Arc::new(crate::codec::decode_doc(&self.storage.read(loc)?)?);
+        self.doc_cache.remember(id, Arc::clone(&arc));
+        Ok(Some(arc))
     }
 
     /// Iterate all documents, calling `f` for each one.
@@ -472,20 +1038,37 @@ impl Collection {
     where
         F: FnMut(DocumentId, Value) -> Result<()>,
     {
-        for (&id, arc) in &self.doc_cache {
+        self.for_each_doc_arc_while(|id, arc| {
             f(id, (**arc).clone())?;
-        }
-        Ok(())
+            Ok(true)
+        })
     }
 
-    /// Iterate all documents as Arc references. Zero-clone.
+    /// Iterate all documents as Arc references. Zero-clone when every
+    /// document is cached; in lazy mode documents are read from disk in file
+    /// order without populating the LRU cache.
     /// Stops early when `f` returns `Ok(false)`.
     fn for_each_doc_arc_while<F>(&self, mut f: F) -> Result<()>
     where
         F: FnMut(DocumentId, &Arc<Value>) -> Result<bool>,
     {
-        for (&id, arc) in &self.doc_cache {
-            if !f(id, arc)? {
+        if let Some(map) = self.doc_cache.as_map() {
+            for (&id, arc) in map {
+                if !f(id, arc)? {
+                    break;
+                }
+            }
+            return Ok(());
+        }
+        let mut locations: Vec<(DocumentId, DocLocation)> =
+            self.primary_index.iter().map(|(&id, &loc)| (id, loc)).collect();
+        locations.sort_unstable_by_key(|(_, loc)| loc.offset);
+        for (id, loc) in locations {
+            let arc = match self.doc_cache.get(id) {
+                Some(arc) => arc,
+                None => Arc::new(crate::codec::decode_doc(&self.storage.read(loc)?)?),
+            };
+            if !f(id, &arc)? {
                 break;
             }
         }
@@ -500,7 +1083,22 @@ impl Collection {
     /// If the index already exists (e.g. rebuilt from persisted metadata on load),
     /// returns Ok immediately — making this call idempotent.
     pub fn create_index(&mut self, field: &str) -> Result<()> {
-        if self.field_indexes.contains_key(field) {
+        self.build_field_index(FieldIndex::new(field.to_string()))
+    }
+
+    /// Create a single-field index that leaves out documents where the field
+    /// is null (documents lacking it are never indexed). Queries a null field
+    /// could satisfy, such as `{field: null}` or `$ne`, don't use it.
+    /// If an index on `field` already exists, returns Ok immediately.
+    pub fn create_sparse_index(&mut self, field: &str) -> Result<()> {
+        self.build_field_index(FieldIndex::new_sparse(field.to_string()))
+    }
+
+    /// Backfill a new non-unique field index from existing documents and
+    /// persist it, unless an index on its field already exists.
+    fn build_field_index(&mut self, mut idx: FieldIndex) -> Result<()> {
+        let field = idx.field.clone();
+        if self.field_indexes.contains_key(&field) {
             return Ok(());
         }
 
@@ -513,10 +1111,9 @@ impl Collection {
         }
         let start = std::time::Instant::now();
         let mut count = 0u64;
-        let mut idx = FieldIndex::new(field.to_string());
 
-        // Backfill from doc cache (zero disk I/O)
-        for (&id, arc) in &self.doc_cache {
+        // Backfill from existing documents (zero disk I/O unless lazy)
+        self.for_each_doc_arc_while(|id, arc| {
             idx.insert_value(id, arc);
             count += 1;
             if self.verbose && count % 500_000 == 0 {
@@ -525,7 +1122,8 @@ impl Collection {
                     self.name, field, count, total, start.elapsed().as_secs_f64()
                 ));
             }
-        }
+            Ok(true)
+        })?;
 
         if self.verbose {
             self.vlog(&format!(
@@ -533,12 +1131,20 @@ impl Collection {
                 self.name, field, count, start.elapsed().as_secs_f64()
             ));
         }
-        self.field_indexes.insert(field.to_string(), idx);
+        self.field_indexes.insert(field, idx);
         self.save_index_metadata()?;
         self.save_index_data();
         Ok(())
     }
 
+    /// Create a single-field index that sort pushdown walks from its highest
+    /// key, e.g. for newest-first listings. An existing index on `field` is
+    /// marked descending in place.
+    pub fn create_descending_index(&mut self, field: &str) -> Result<()> {
+        self.create_index(field)?;
+        self.set_index_descending(field, true)
+    }
+
     /// Create a unique single-field index. Rebuilds from existing documents.
     /// Returns error if existing data violates uniqueness.
     /// If the index already exists, returns Ok immediately (idempotent).
@@ -559,8 +1165,8 @@ impl Collection {
         let mut idx = FieldIndex::new_unique(field.to_string());
         let field_owned = field.to_string();
 
-        // Backfill from doc cache (zero disk I/O)
-        for (&id, arc) in &self.doc_cache {
+        // Backfill from existing documents (zero disk I/O unless lazy)
+        self.for_each_doc_arc_while(|id, arc| {
             if let Some(value) = resolve_field_in_value(arc, &field_owned) {
                 let iv = IndexValue::from_json(value);
                 if idx.check_unique(&iv, None) {
@@ -577,7 +1183,8 @@ impl Collection {
                     self.name, field, count, total, start.elapsed().as_secs_f64()
                 ));
             }
-        }
+            Ok(true)
+        })?;
 
         if self.verbose {
             self.vlog(&format!(
@@ -610,8 +1217,8 @@ impl Collection {
         let mut count = 0u64;
         let mut idx = CompositeIndex::new(fields);
 
-        // Backfill from doc cache (zero disk I/O)
-        for (&id, arc) in &self.doc_cache {
+        // Backfill from existing documents (zero disk I/O unless lazy)
+        self.for_each_doc_arc_while(|id, arc| {
             idx.insert_value(id, arc);
             count += 1;
             if self.verbose && count % 500_000 == 0 {
@@ -620,7 +1227,8 @@ impl Collection {
                     self.name, name, count, total, start.elapsed().as_secs_f64()
                 ));
             }
-        }
+            Ok(true)
+        })?;
 
         if self.verbose {
             self.vlog(&format!(
@@ -654,8 +1262,8 @@ impl Collection {
         let mut count = 0u64;
         let mut idx = CollectionTextIndex::new(fields);
 
-        // Backfill from doc cache (zero disk I/O)
-        for (&id, arc) in &self.doc_cache {
+        // Backfill from existing documents (zero disk I/O unless lazy)
+        self.for_each_doc_arc_while(|id, arc| {
             idx.index_doc(id, arc);
             count += 1;
             if self.verbose && count % 500_000 == 0 {
@@ -664,7 +1272,8 @@ impl Collection {
                     self.name, count, total, start.elapsed().as_secs_f64()
                 ));
             }
-        }
+            Ok(true)
+        })?;
 
         if self.verbose {
             self.vlog(&format!(
@@ -688,6 +1297,14 @@ impl Collection {
                 unique: idx.unique,
                 dimension: None,
                 metric: None,
+                m: None,
+                ef_construction: None,
+                normalize: false,
+                vectors: None,
+                tombstones: None,
+                bloom: idx.has_bloom(),
+                descending: idx.descending,
+                sparse: idx.sparse,
             });
         }
         for idx in &self.composite_indexes {
@@ -698,6 +1315,14 @@ impl Collection {
                 unique: false,
                 dimension: None,
                 metric: None,
+                m: None,
+                ef_construction: None,
+                normalize: false,
+                vectors: None,
+                tombstones: None,
+                bloom: false,
+                descending: false,
+                sparse: false,
             });
         }
         if let Some(ref text_idx) = self.text_index {
@@ -708,6 +1333,14 @@ impl Collection {
                 unique: false,
                 dimension: None,
                 metric: None,
+                m: None,
+                ef_construction: None,
+                normalize: false,
+                vectors: None,
+                tombstones: None,
+                bloom: false,
+                descending: false,
+                sparse: false,
             });
         }
         for idx in self.vector_indexes.values() {
@@ -718,11 +1351,90 @@ impl Collection {
                 unique: false,
                 dimension: Some(idx.dimension),
                 metric: Some(idx.metric_str().to_string()),
+                m: Some(idx.params().m),
+                ef_construction: Some(idx.params().ef_construction),
+                normalize: idx.is_normalized(),
+                vectors: Some(idx.len()),
+                tombstones: Some(idx.tombstones()),
+                bloom: false,
+                descending: false,
+                sparse: false,
+            });
+        }
+        for idx in self.geo_indexes.values() {
+            indexes.push(IndexInfo {
+                name: format!("_geo_{}", idx.field),
+                index_type: "geo".to_string(),
+                fields: vec![idx.field.clone()],
+                unique: false,
+                dimension: None,
+                metric: None,
+                m: None,
+                ef_construction: None,
+                normalize: false,
+                vectors: None,
+                tombstones: None,
+                bloom: false,
+                descending: false,
+                sparse: false,
             });
         }
         indexes
     }
 
+    /// Document count, index list, and the top-level fields and JSON types
+    /// seen in up to `sample` documents.
+    pub fn describe(&self, sample: usize) -> Result<CollectionDescription> {
+        let mut profile: BTreeMap<String, (BTreeSet<&'static str>, usize)> = BTreeMap::new();
+        let mut sampled = 0;
+        if sample > 0 {
+            self.for_each_doc_arc_while(|_id, doc| {
+                for (key, value) in doc.as_object().into_iter().flatten() {
+                    let entry = profile.entry(key.clone()).or_default();
+                    entry.0.insert(json_type_name(value));
+                    entry.1 += 1;
+                }
+                sampled += 1;
+                Ok(sampled < sample)
+            })?;
+        }
+        let fields = profile
+            .into_iter()
+            .map(|(field, (types, count))| FieldProfile {
+                field,
+                types: types.into_iter().map(String::from).collect(),
+                count,
+            })
+            .collect();
+        Ok(CollectionDescription { count: self.count(), sampled, fields, indexes: self.list_indexes() })
+    }
+
+    /// Enable or disable the bloom filter on a single-field index. The filter
+    /// lets equality lookups for values that were never indexed skip the
+    /// index tree entirely. The setting is persisted.
+    pub fn set_index_bloom(&mut self, field: &str, enabled: bool) -> Result<()> {
+        let idx = self.field_indexes.get_mut(field).ok_or_else(|| {
+            Error::InvalidQuery(format!("no field index on '{}'", field))
+        })?;
+        if enabled {
+            idx.enable_bloom();
+        } else {
+            idx.disable_bloom();
+        }
+        self.save_index_metadata()
+    }
+
+    /// Mark a single-field index as descending or ascending. Only the
+    /// direction sort pushdown treats as forward changes; the entries are
+    /// kept as they are. The setting is persisted.
+    pub fn set_index_descending(&mut self, field: &str, descending: bool) -> Result<()> {
+        let idx = self.field_indexes.get_mut(field).ok_or_else(|| {
+            Error::InvalidQuery(format!("no field index on '{}'", field))
+        })?;
+        idx.descending = descending;
+        self.save_index_metadata()
+    }
+
     /// Drop an index by name and update persisted metadata.
     pub fn drop_index(&mut self, name: &str) -> Result<()> {
         if self.field_indexes.remove(name).is_some() {
@@ -746,16 +1458,18 @@ impl Collection {
             self.save_index_data();
             return Ok(());
         }
+        if let Some(field) = name.strip_prefix("_geo_")
+            && self.geo_indexes.remove(field).is_some()
+        {
+            self.save_index_metadata()?;
+            return Ok(());
+        }
         Err(Error::IndexNotFound(name.to_string()))
     }
 
     /// Full-text search on collection documents. Returns matching documents with `_score` field.
     pub fn text_search(&self, query: &str, limit: usize) -> Result<Vec<Value>> {
-        let idx = self.text_index.as_ref().ok_or_else(|| {
-            Error::InvalidQuery("no text index on this collection; create one with create_text_index".into())
-        })?;
-
-        let search_results = idx.search(query, limit);
+        let search_results = self.text_index()?.search(query, limit);
         let mut docs = Vec::with_capacity(search_results.len());
         for result in search_results {
             if let Some(mut doc) = self.read_doc(result.doc_id)? {
@@ -768,31 +1482,89 @@ impl Collection {
         Ok(docs)
     }
 
+    /// Full-text search restricted to documents matching `filter_json`.
+    /// Text hits are walked in score order and kept if they're among the
+    /// filter's indexed candidates and, unless the filter is fully indexed,
+    /// if the document matches it, until `limit` are found.
+    pub fn text_search_filtered(&self, query: &str, filter_json: &Value, limit: usize) -> Result<Vec<Value>> {
+        let idx = self.text_index()?;
+        let filter = query::parse_query(filter_json)?;
+        let candidate_ids = query::execute_indexed(&filter, &self.field_indexes, &self.composite_indexes);
+        let skip_post_filter = candidate_ids.is_some() && query::is_fully_indexed(&filter, &self.field_indexes);
+
+        let mut docs = Vec::new();
+        for result in idx.search(query, usize::MAX) {
+            if docs.len() >= limit {
+                break;
+            }
+            if candidate_ids.as_ref().is_some_and(|ids| !ids.contains(&result.doc_id)) {
+                continue;
+            }
+            let Some(arc) = self.read_doc_arc(result.doc_id)? else { continue };
+            if !skip_post_filter && !query::matches_value(&filter, &arc) {
+                continue;
+            }
+            let mut doc = (*arc).clone();
+            if let Some(obj) = doc.as_object_mut() {
+                obj.insert("_score".to_string(), serde_json::json!(result.score));
+            }
+            docs.push(doc);
+        }
+        Ok(docs)
+    }
+
+    /// Number of documents `text_search` would return, read from the
+    /// index alone.
+    pub fn text_search_count(&self, query: &str, limit: usize) -> Result<usize> {
+        Ok(self.text_index()?.count(query, limit))
+    }
+
+    fn text_index(&self) -> Result<&CollectionTextIndex> {
+        self.text_index.as_ref().ok_or_else(|| {
+            Error::InvalidQuery("no text index on this collection; create one with create_text_index".into())
+        })
+    }
+
     // -----------------------------------------------------------------------
     // Vector index methods
     // -----------------------------------------------------------------------
 
-    /// Create a vector index on the specified field.
-    /// Rebuilds from existing documents in doc cache.
+    /// Create a vector index on the specified field, with the given HNSW
+    /// build parameters. With `normalize`, which requires the cosine metric,
+    /// vectors are stored at unit length. Rebuilds from existing documents.
     /// If an index already exists on this field, returns Ok immediately (idempotent).
-    pub fn create_vector_index(&mut self, field: &str, dimension: usize, metric: DistanceMetric) -> Result<()> {
+    pub fn create_vector_index(
+        &mut self,
+        field: &str,
+        dimension: usize,
+        metric: DistanceMetric,
+        params: HnswParams,
+        normalize: bool,
+    ) -> Result<()> {
         if self.vector_indexes.contains_key(field) {
             return Ok(());
         }
+        params.validate().map_err(Error::InvalidQuery)?;
+        if normalize && metric != DistanceMetric::Cosine {
+            return Err(Error::InvalidQuery(format!(
+                "'normalize' requires the cosine metric, not {}",
+                metric.as_str()
+            )));
+        }
 
         let total = self.primary_index.len();
         if self.verbose {
             self.vlog(&format!(
-                "[verbose] {}: creating vector index on '{}' (dim={}, metric={}, {} docs to scan)",
-                self.name, field, dimension, metric.as_str(), total
+                "[verbose] {}: creating vector index on '{}' (dim={}, metric={}, m={}, ef_construction={}, {} docs to scan)",
+                self.name, field, dimension, metric.as_str(), params.m, params.ef_construction, total
             ));
         }
         let start = std::time::Instant::now();
         let mut count = 0u64;
-        let mut idx = VectorIndex::new(field.to_string(), dimension, metric);
+        let mut idx = VectorIndex::new(field.to_string(), dimension, metric, params).with_normalization(normalize);
 
-        // Backfill from doc cache (zero disk I/O)
-        for (&id, arc) in &self.doc_cache {
+        // Backfill from existing documents (zero disk I/O unless lazy)
+        self.for_each_doc_arc_while(|id, arc| {
             if let Err(e) = idx.insert(id, arc) {
                 if self.verbose {
                     self.vlog(&format!(
@@ -808,7 +1580,8 @@ impl Collection {
                     self.name, field, count, total, start.elapsed().as_secs_f64()
                 ));
             }
-        }
+            Ok(true)
+        })?;
 
         if self.verbose {
             self.vlog(&format!(
@@ -823,16 +1596,17 @@ impl Collection {
     }
 
     /// Perform vector similarity search. Returns matching documents with `_similarity` score.
-    pub fn vector_search(&self, field: &str, query_vector: &[f32], limit: usize, ef_search: Option<usize>) -> Result<Vec<Value>> {
-        let idx = self.vector_indexes.get(field).ok_or_else(|| {
-            Error::InvalidQuery(format!(
-                "no vector index on field '{}'; create one with create_vector_index",
-                field
-            ))
-        })?;
-
-        let search_results = idx.search(query_vector, limit, ef_search)
-            .map_err(|e| Error::InvalidQuery(e))?;
+    pub fn vector_search(
+        &self,
+        field: &str,
+        query_vector: &[f32],
+        limit: usize,
+        opts: &VectorSearchOptions,
+    ) -> Result<Vec<Value>> {
+        let search_results = self
+            .vector_index(field)?
+            .search_with(query_vector, limit, opts)
+            .map_err(Error::InvalidQuery)?;
 
         let mut docs = Vec::with_capacity(search_results.len());
         for result in search_results {
@@ -847,15 +1621,118 @@ impl Collection {
         Ok(docs)
     }
 
+    /// Number of documents `vector_search` would return, read from the
+    /// index alone.
+    pub fn vector_search_count(
+        &self,
+        field: &str,
+        query_vector: &[f32],
+        limit: usize,
+        opts: &VectorSearchOptions,
+    ) -> Result<usize> {
+        self.vector_index(field)?
+            .search_with(query_vector, limit, opts)
+            .map(|results| results.len())
+            .map_err(Error::InvalidQuery)
+    }
+
+    fn vector_index(&self, field: &str) -> Result<&VectorIndex> {
+        self.vector_indexes.get(field).ok_or_else(|| {
+            Error::InvalidQuery(format!(
+                "no vector index on field '{}'; create one with create_vector_index",
+                field
+            ))
+        })
+    }
+
     // -----------------------------------------------------------------------
-    // Unique constraint checks
+    // Geo index methods
     // -----------------------------------------------------------------------
 
-    /// Check unique constraints for a document about to be inserted.
-    fn check_unique_constraints(
+    /// Create a geo index on a field holding `{"lat": .., "lng": ..}` or
+    /// `[lat, lng]` points, for `$near` and `$geoWithin` queries. Like the
+    /// text index it is rebuilt from documents on open rather than cached.
+    /// If an index already exists on this field, returns Ok immediately.
+    pub fn create_geo_index(&mut self, field: &str) -> Result<()> {
+        if self.geo_indexes.contains_key(field) {
+            return Ok(());
+        }
+        let mut idx = GeoIndex::new(field.to_string());
+        self.for_each_doc_arc_while(|id, arc| {
+            idx.insert(id, arc);
+            Ok(true)
+        })?;
+        if self.verbose {
+            self.vlog(&format!("[verbose] {}: geo index '{}' ready ({} points)", self.name, field, idx.len()));
+        }
+        self.geo_indexes.insert(field.to_string(), idx);
+        self.save_index_metadata()
+    }
+
+    /// Run a find whose query has a `$near` or `$geoWithin` condition on
+    /// `field`. Results come nearest first unless `opts` sorts them, and
+    /// carry a `_distance` in meters when the condition has a center point.
+    fn find_geo(
         &self,
-        data: &Value,
-        exclude_id: Option<DocumentId>,
+        field: &str,
+        geo: &GeoQuery,
+        rest_json: &Value,
+        opts: &FindOptions,
+        plan: &mut QueryPlan,
+    ) -> Result<Vec<Arc<Value>>> {
+        let idx = self.geo_indexes.get(field).ok_or_else(|| {
+            Error::InvalidQuery(format!(
+                "no geo index on field '{}'; create one with create_geo_index",
+                field
+            ))
+        })?;
+        let rest = query::parse_query(rest_json)?;
+        let matches = idx.search(geo);
+        plan.stage = "geo";
+        plan.indexes.push((format!("_geo_{}", field), "geo"));
+        plan.estimated_candidates = Some(matches.len());
+        plan.post_filter = !matches!(rest, Query::All);
+        plan.in_memory_sort = opts.sort.is_some();
+
+        let need = match opts.sort {
+            None => opts.skip.unwrap_or(0) as usize + opts.limit.unwrap_or(u64::MAX) as usize,
+            Some(_) => usize::MAX,
+        };
+        let mut results = Vec::new();
+        for m in matches {
+            if results.len() >= need {
+                break;
+            }
+            let Some(arc) = self.read_doc_arc(m.doc_id)? else {
+                continue;
+            };
+            plan.docs_examined += 1;
+            if !query::matches_value(&rest, &arc) {
+                continue;
+            }
+            results.push(match m.distance {
+                Some(distance) => {
+                    let mut doc = (*arc).clone();
+                    if let Some(obj) = doc.as_object_mut() {
+                        obj.insert("_distance".to_string(), serde_json::json!(distance));
+                    }
+                    Arc::new(doc)
+                }
+                None => arc,
+            });
+        }
+        Ok(sort_skip_limit(results, opts))
+    }
+
+    // -----------------------------------------------------------------------
+    // Unique constraint checks
+    // -----------------------------------------------------------------------
+
+    /// Check unique constraints for a document about to be inserted.
+    fn check_unique_constraints(
+        &self,
+        data: &Value,
+        exclude_id: Option<DocumentId>,
     ) -> Result<()> {
         for idx in self.field_indexes.values() {
             if !idx.unique {
@@ -878,10 +1755,32 @@ impl Collection {
     // -----------------------------------------------------------------------
 
     /// Insert a document. Returns the assigned _id.
-    pub fn insert(&mut self, mut data: Value) -> Result<DocumentId> {
+    pub fn insert(&mut self, data: Value) -> Result<DocumentId> {
+        self.insert_with_sync(data, true)
+    }
+
+    /// fsync the data file, making every write so far durable, including
+    /// those made with `Durability::Relaxed`.
+    pub fn flush(&self) -> Result<()> {
+        self.storage.force_sync()
+    }
+
+    /// Insert a document without waiting for it to reach disk. The caller
+    /// must release the collection and pass the ticket to
+    /// [`GroupCommit::wait`] (see [`Collection::group_commit`]) before
+    /// reporting the insert as done, so that concurrent inserts share an
+    /// fsync. The document is visible to readers before the wait returns.
+    pub fn insert_grouped(&mut self, data: Value) -> Result<(DocumentId, CommitTicket)> {
+        let id = self.insert_with_sync(data, false)?;
+        Ok((id, self.group_commit.register(self.storage.sync_handle())))
+    }
+
+    fn insert_with_sync(&mut self, mut data: Value, sync: bool) -> Result<DocumentId> {
         if !data.is_object() {
             return Err(Error::NotAnObject);
         }
+        self.defaults.apply(&mut data);
+        self.check_document_size(&data)?;
 
         let id = self.next_id;
 
@@ -890,17 +1789,22 @@ impl Collection {
         obj.insert("_id".to_string(), Value::Number(id.into()));
         obj.insert("_version".to_string(), Value::Number(1.into()));
 
-        // Check unique constraints BEFORE any disk writes
+        // Check the schema and unique constraints BEFORE any disk writes
+        self.validate_document(&mut data)?;
         self.check_unique_constraints(&data, None)?;
 
         self.next_id += 1;
 
-        let bytes = crate::codec::encode_doc(&data)?;
+        let bytes = self.encode(&data)?;
 
-        // WAL: log before mutating .dat (no fsync — storage.append will fsync)
+        // WAL: log before mutating .dat (no fsync — storage.append, or the
+        // group commit, will fsync)
         self.wal.log_no_sync(&WalEntry::insert(id, bytes.clone()))?;
 
-        let loc = self.storage.append(&bytes)?;
+        let loc = match sync {
+            true => self.storage.append(&bytes)?,
+            false => self.storage.append_no_sync(&bytes)?,
+        };
 
         // WAL: lazy checkpoint (no fsync — stale entries replay idempotently)
         self.wal.checkpoint_no_sync()?;
@@ -923,6 +1827,9 @@ impl Collection {
         for idx in self.vector_indexes.values_mut() {
             let _ = idx.insert(id, &data_arc);
         }
+        for idx in self.geo_indexes.values_mut() {
+            idx.insert(id, &data_arc);
+        }
 
         self.doc_cache.insert(id, data_arc);
 
@@ -932,6 +1839,15 @@ impl Collection {
     /// Insert multiple documents in a single atomic batch (3 fsyncs total).
     /// Either all documents are inserted or none (on constraint violation).
     pub fn insert_many(&mut self, docs: Vec<Value>) -> Result<Vec<DocumentId>> {
+        self.insert_many_with_durability(docs, Durability::Full)
+    }
+
+    /// `insert_many`, skipping the fsync when `durability` is `Relaxed`.
+    pub fn insert_many_with_durability(
+        &mut self,
+        docs: Vec<Value>,
+        durability: Durability,
+    ) -> Result<Vec<DocumentId>> {
         if docs.is_empty() {
             return Ok(vec![]);
         }
@@ -945,12 +1861,15 @@ impl Collection {
             if !data.is_object() {
                 return Err(Error::NotAnObject);
             }
+            self.defaults.apply(&mut data);
+            self.check_document_size(&data)?;
             let id = self.next_id + prepared.len() as u64;
             let obj = data.as_object_mut().unwrap();
             obj.insert("_id".to_string(), Value::Number(id.into()));
             obj.insert("_version".to_string(), Value::Number(1.into()));
 
-            // Check against existing index
+            // Check against the schema and existing index
+            self.validate_document(&mut data)?;
             self.check_unique_constraints(&data, None)?;
 
             // Check intra-batch uniqueness (no Document clone needed)
@@ -970,7 +1889,7 @@ impl Collection {
                 }
             }
 
-            let bytes = crate::codec::encode_doc(&data)?;
+            let bytes = self.encode(&data)?;
             prepared.push((id, data, bytes));
         }
 
@@ -981,10 +1900,13 @@ impl Collection {
             .collect();
         self.wal.log_batch_no_sync(&wal_entries)?;
 
-        // Phase 3: append all to .dat → single fsync (the only fsync in this method)
+        // Phase 3: append all to .dat → single fsync (the only fsync in this
+        // method, skipped for relaxed durability)
         let byte_slices: Vec<&[u8]> = prepared.iter().map(|(_, _, bytes)| bytes.as_slice()).collect();
         let batch_locs = self.storage.append_batch_no_sync(&byte_slices)?;
-        self.storage.sync()?;
+        if durability == Durability::Full {
+            self.storage.sync()?;
+        }
 
         let mut ids = Vec::with_capacity(prepared.len());
         let mut locs = Vec::with_capacity(prepared.len());
@@ -1015,6 +1937,9 @@ impl Collection {
             for idx in self.vector_indexes.values_mut() {
                 let _ = idx.insert(id, &data_arc);
             }
+            for idx in self.geo_indexes.values_mut() {
+                idx.insert(id, &data_arc);
+            }
             self.doc_cache.insert(id, data_arc);
         }
 
@@ -1050,15 +1975,76 @@ impl Collection {
         query_json: &Value,
         opts: &FindOptions,
     ) -> Result<Vec<Arc<Value>>> {
+        if opts.respect_ttl {
+            // Expired documents are dropped before paging, so skip and limit
+            // count live documents only. Fetch the first skip + limit
+            // matches, and fetch twice as many again while expired ones
+            // leave the page short
+            let now = chrono::Utc::now();
+            let skip = opts.skip.unwrap_or(0) as usize;
+            let limit = opts.limit.map_or(usize::MAX, |n| n as usize);
+            let want = opts.limit.map(|_| skip.saturating_add(limit));
+            let mut fetch = want;
+            loop {
+                let window = FindOptions { skip: None, limit: fetch.map(|n| n as u64), respect_ttl: false, ..opts.clone() };
+                let docs = self.find_planned(query_json, &window, &mut QueryPlan::default())?;
+                let full = fetch.is_some_and(|n| docs.len() >= n);
+                let live: Vec<_> = docs.into_iter().filter(|doc| !document::is_expired(doc, now)).collect();
+                match (want, fetch) {
+                    (Some(want), Some(n)) if full && live.len() < want => fetch = Some(n.saturating_mul(2)),
+                    _ => return Ok(live.into_iter().skip(skip).take(limit).collect()),
+                }
+            }
+        }
+        self.find_planned(query_json, opts, &mut QueryPlan::default())
+    }
+
+    /// Names of the indexes the planner would use to match `query`, worked
+    /// out without running it. Empty for a collection scan.
+    pub fn query_indexes(&self, query_json: &Value) -> Result<Vec<String>> {
+        let query = query::parse_query(query_json)?;
+        Ok(query::indexes_used(&query, &self.field_indexes, &self.composite_indexes)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    /// Run a query and report the plan it used instead of the documents.
+    pub fn explain(&self, query_json: &Value, opts: &FindOptions) -> Result<Value> {
+        let mut plan = QueryPlan::default();
+        let results = self.find_planned(query_json, opts, &mut plan)?;
+        plan.n_returned = results.len();
+        Ok(plan.to_json())
+    }
+
+    /// Shared body of `find_with_options_arcs` and `explain`. Each decision
+    /// point records which strategy it took into `plan`.
+    fn find_planned(
+        &self,
+        query_json: &Value,
+        opts: &FindOptions,
+        plan: &mut QueryPlan,
+    ) -> Result<Vec<Arc<Value>>> {
+        if let Some((field, geo, rest)) = geo::extract_geo_query(query_json)? {
+            return self.find_geo(&field, &geo, &rest, opts, plan);
+        }
         let query = query::parse_query(query_json)?;
 
+        if let Some(hint) = &opts.hint {
+            let results = self.find_hinted(&query, opts, hint, plan)?;
+            return Ok(sort_skip_limit(results, opts));
+        }
+
         // Fast path: Query::All with no sort — iterate doc cache directly.
         if matches!(query, Query::All) && opts.sort.is_none() {
+            plan.stage = "collection scan";
+            plan.estimated_candidates = Some(self.count());
             let skip = opts.skip.unwrap_or(0) as usize;
             let limit = opts.limit.map(|l| l as usize).unwrap_or(usize::MAX);
             let mut results = Vec::new();
             let mut skipped = 0;
             self.for_each_doc_arc_while(|_id, arc| {
+                plan.docs_examined += 1;
                 if skipped < skip {
                     skipped += 1;
                     return Ok(true);
@@ -1076,7 +2062,20 @@ impl Collection {
         if let Some(sort_fields) = &opts.sort {
             if sort_fields.len() == 1 {
                 let (sort_field, sort_order) = &sort_fields[0];
-                if let Some(field_idx) = self.field_indexes.get(sort_field) {
+                let field_idx = self.field_indexes.get(sort_field).filter(|idx| {
+                    // A sparse index would drop documents whose field is null
+                    !idx.sparse || query::excludes_null(&query, sort_field)
+                });
+                if let Some(field_idx) = field_idx {
+                    plan.stage = "index sort";
+                    plan.indexes.push((sort_field.clone(), "field"));
+                    plan.direction = Some(if matches!(sort_order, SortOrder::Desc) == field_idx.descending {
+                        "forward"
+                    } else {
+                        "backward"
+                    });
+                    plan.estimated_candidates = Some(field_idx.count_all());
+                    plan.post_filter = !matches!(query, Query::All);
                     let need = opts.skip.unwrap_or(0) as usize + opts.limit.unwrap_or(u64::MAX) as usize;
                     let mut results = Vec::new();
 
@@ -1084,7 +2083,8 @@ impl Collection {
                         SortOrder::Asc => {
                             'outer_asc: for (_value, doc_ids) in field_idx.iter_asc() {
                                 for &id in doc_ids {
-                                    if let Some(arc) = self.read_doc_arc(id) {
+                                    if let Some(arc) = self.read_doc_arc(id)? {
+                                        plan.docs_examined += 1;
                                         if query::matches_value(&query, &arc) {
                                             results.push(arc);
                                             if results.len() >= need {
@@ -1098,7 +2098,8 @@ impl Collection {
                         SortOrder::Desc => {
                             'outer_desc: for (_value, doc_ids) in field_idx.iter_desc() {
                                 for &id in doc_ids.iter().rev() {
-                                    if let Some(arc) = self.read_doc_arc(id) {
+                                    if let Some(arc) = self.read_doc_arc(id)? {
+                                        plan.docs_examined += 1;
                                         if query::matches_value(&query, &arc) {
                                             results.push(arc);
                                             if results.len() >= need {
@@ -1159,27 +2160,53 @@ impl Collection {
                             let need = opts.skip.unwrap_or(0) as usize
                                 + opts.limit.unwrap_or(u64::MAX) as usize;
 
+                            plan.stage = "index sort";
+                            plan.indexes.push((comp_idx.name(), "composite"));
+                            plan.post_filter = true;
+
                             // Read + filter docs inline during composite index iteration.
-                            let doc_cache = &self.doc_cache;
                             let mut results: Vec<Arc<Value>> = Vec::new();
+                            let docs_examined = &mut plan.docs_examined;
+                            let mut read_err = None;
 
                             let mut handler = |id: DocumentId| -> bool {
-                                if let Some(arc) = doc_cache.get(&id) {
-                                    if query::matches_value(&query, arc) {
-                                        results.push(Arc::clone(arc));
-                                        return results.len() < need;
+                                let arc = match self.read_doc_arc(id) {
+                                    Ok(Some(arc)) => arc,
+                                    Ok(None) => return true,
+                                    Err(e) => {
+                                        read_err = Some(e);
+                                        return false;
                                     }
+                                };
+                                *docs_examined += 1;
+                                if query::matches_value(&query, &arc) {
+                                    results.push(arc);
+                                    return results.len() < need;
                                 }
                                 true
                             };
 
-                            match sort_order {
-                                SortOrder::Asc => {
+                            // Range conditions on the sort field narrow the scan
+                            // to the matching key range within the prefix.
+                            let (start, end) = query::extract_range_bounds(&query, sort_field);
+                            let ranged = !matches!((start, end), (Bound::Unbounded, Bound::Unbounded));
+
+                            match (sort_order, ranged) {
+                                (SortOrder::Asc, false) => {
                                     comp_idx.for_each_prefix_asc(&prefix, &mut handler);
                                 }
-                                SortOrder::Desc => {
+                                (SortOrder::Desc, false) => {
                                     comp_idx.for_each_prefix_desc(&prefix, &mut handler);
                                 }
+                                (SortOrder::Asc, true) => {
+                                    comp_idx.for_each_prefix_range(&prefix, start, end, &mut handler);
+                                }
+                                (SortOrder::Desc, true) => {
+                                    comp_idx.for_each_prefix_range_desc(&prefix, start, end, &mut handler);
+                                }
+                            }
+                            if let Some(e) = read_err {
+                                return Err(e);
                             }
 
                             // Apply skip
@@ -1219,23 +2246,37 @@ impl Collection {
         // Fast path: lazy index iteration for limit queries without sort/skip.
         // Avoids materializing full BTreeSet of IDs.
         if let Some(limit) = early_limit {
-            let doc_cache = &self.doc_cache;
+            let docs_examined = &mut plan.docs_examined;
+            let mut read_err = None;
             let lazy_result = query::execute_indexed_lazy(
                 &query,
                 &self.field_indexes,
                 &mut |id| {
-                    if let Some(arc) = doc_cache.get(&id) {
-                        if skip_post_filter || query::matches_value(&query, arc) {
-                            results.push(Arc::clone(arc));
-                            if results.len() >= limit {
-                                return false;
-                            }
+                    let arc = match self.read_doc_arc(id) {
+                        Ok(Some(arc)) => arc,
+                        Ok(None) => return true,
+                        Err(e) => {
+                            read_err = Some(e);
+                            return false;
+                        }
+                    };
+                    *docs_examined += 1;
+                    if skip_post_filter || query::matches_value(&query, &arc) {
+                        results.push(arc);
+                        if results.len() >= limit {
+                            return false;
                         }
                     }
                     true
                 },
             );
+            if let Some(e) = read_err {
+                return Err(e);
+            }
             if lazy_result.is_some() {
+                plan.stage = "index";
+                plan.indexes = query::indexes_used(&query, &self.field_indexes, &[]);
+                plan.post_filter = !skip_post_filter;
                 return Ok(results);
             }
         }
@@ -1246,9 +2287,16 @@ impl Collection {
             &self.composite_indexes,
         );
 
+        plan.in_memory_sort = opts.sort.is_some();
         if let Some(ref indexed_ids) = candidate_ids {
+            plan.stage = "index";
+            plan.indexes =
+                query::indexes_used(&query, &self.field_indexes, &self.composite_indexes);
+            plan.estimated_candidates = Some(indexed_ids.len());
+            plan.post_filter = !skip_post_filter;
             for &id in indexed_ids {
-                if let Some(arc) = self.read_doc_arc(id) {
+                if let Some(arc) = self.read_doc_arc(id)? {
+                    plan.docs_examined += 1;
                     if skip_post_filter || query::matches_value(&query, &arc) {
                         results.push(arc);
                         if let Some(limit) = early_limit {
@@ -1260,58 +2308,109 @@ impl Collection {
                 }
             }
         } else {
-            self.for_each_doc_arc_while(|_id, arc| {
-                if query::matches_value(&query, arc) {
-                    results.push(Arc::clone(arc));
-                    if let Some(limit) = early_limit {
-                        if results.len() >= limit {
+            plan.stage = "collection scan";
+            plan.estimated_candidates = Some(self.count());
+            plan.post_filter = true;
+            let workers = self.scan_workers_for_scan();
+            if early_limit.is_none() && workers > 1 {
+                plan.docs_examined = self.count();
+                results = self.scan_matching(&query, workers)?;
+            } else {
+                self.for_each_doc_arc_while(|_id, arc| {
+                    plan.docs_examined += 1;
+                    if query::matches_value(&query, arc) {
+                        results.push(Arc::clone(arc));
+                        if let Some(limit) = early_limit
+                            && results.len() >= limit
+                        {
                             return Ok(false);
                         }
                     }
-                }
-                Ok(true)
-            })?;
+                    Ok(true)
+                })?;
+            }
         }
 
-        // Apply sort → skip → limit pipeline
-        if let Some(sort_fields) = &opts.sort {
-            results.sort_by(|a, b| {
-                for (field, order) in sort_fields {
-                    let av = a.pointer(&format!("/{}", field.replace('.', "/")));
-                    let bv = b.pointer(&format!("/{}", field.replace('.', "/")));
-                    let aiv = av.map(IndexValue::from_json).unwrap_or(IndexValue::Null);
-                    let biv = bv.map(IndexValue::from_json).unwrap_or(IndexValue::Null);
-                    let cmp = aiv.cmp(&biv);
-                    let cmp = match order {
-                        SortOrder::Asc => cmp,
-                        SortOrder::Desc => cmp.reverse(),
-                    };
-                    if cmp != std::cmp::Ordering::Equal {
-                        return cmp;
-                    }
-                }
-                std::cmp::Ordering::Equal
-            });
-        }
+        Ok(sort_skip_limit(results, opts))
+    }
 
-        if let Some(skip) = opts.skip {
-            let skip = skip as usize;
-            if skip >= results.len() {
-                results.clear();
-            } else {
-                results = results.into_iter().skip(skip).collect();
+    /// Collect the matches of a find with a `hint`, before sort/skip/limit.
+    /// A hinted index narrows the candidates only through the query's
+    /// conditions on it; if it has none, the collection is scanned. Every
+    /// candidate is checked against the full query.
+    fn find_hinted(
+        &self,
+        query: &Query,
+        opts: &FindOptions,
+        hint: &IndexHint,
+        plan: &mut QueryPlan,
+    ) -> Result<Vec<Arc<Value>>> {
+        let candidate_ids = match hint {
+            IndexHint::Natural => None,
+            IndexHint::Index(name) if self.field_indexes.contains_key(name) => {
+                plan.indexes.push((name.clone(), "field"));
+                query::conditions_on_field(query, name)
+                    .and_then(|conds| query::execute_indexed(&conds, &self.field_indexes, &[]))
             }
-        }
+            IndexHint::Index(name) => {
+                let comp_idx = self
+                    .composite_indexes
+                    .iter()
+                    .find(|c| c.name() == *name)
+                    .ok_or_else(|| Error::IndexNotFound(name.clone()))?;
+                plan.indexes.push((name.clone(), "composite"));
+                composite_candidates(comp_idx, query)
+            }
+        };
 
-        if let Some(limit) = opts.limit {
-            results.truncate(limit as usize);
+        let early_limit = match (&opts.sort, opts.skip) {
+            (None, None) => opts.limit.map(|l| l as usize),
+            _ => None,
+        };
+        plan.post_filter = true;
+        plan.in_memory_sort = opts.sort.is_some();
+        let mut results = Vec::new();
+        match candidate_ids {
+            Some(ids) => {
+                plan.stage = "index";
+                plan.estimated_candidates = Some(ids.len());
+                for id in ids {
+                    if early_limit.is_some_and(|l| results.len() >= l) {
+                        break;
+                    }
+                    if let Some(arc) = self.read_doc_arc(id)? {
+                        plan.docs_examined += 1;
+                        if query::matches_value(query, &arc) {
+                            results.push(arc);
+                        }
+                    }
+                }
+            }
+            None => {
+                plan.stage = "collection scan";
+                plan.indexes.clear();
+                plan.estimated_candidates = Some(self.count());
+                self.for_each_doc_arc_while(|_id, arc| {
+                    if early_limit.is_some_and(|l| results.len() >= l) {
+                        return Ok(false);
+                    }
+                    plan.docs_examined += 1;
+                    if query::matches_value(query, arc) {
+                        results.push(Arc::clone(arc));
+                    }
+                    Ok(true)
+                })?;
+            }
         }
-
         Ok(results)
     }
 
     /// Find a single document matching a query.
     pub fn find_one(&self, query_json: &Value) -> Result<Option<Value>> {
+        if geo::extract_geo_query(query_json)?.is_some() {
+            let opts = FindOptions { limit: Some(1), ..FindOptions::default() };
+            return Ok(self.find_with_options(query_json, &opts)?.pop());
+        }
         let query = query::parse_query(query_json)?;
 
         let skip_post_filter = query::is_fully_indexed(&query, &self.field_indexes);
@@ -1319,20 +2418,29 @@ impl Collection {
         // Try lazy index path first — avoids materializing full BTreeSet
         if !matches!(query, Query::All) {
             let mut found: Option<Value> = None;
-            let doc_cache = &self.doc_cache;
+            let mut read_err = None;
             let lazy_result = query::execute_indexed_lazy(
                 &query,
                 &self.field_indexes,
                 &mut |id| {
-                    if let Some(arc) = doc_cache.get(&id) {
-                        if skip_post_filter || query::matches_value(&query, arc) {
-                            found = Some((**arc).clone());
+                    let arc = match self.read_doc_arc(id) {
+                        Ok(Some(arc)) => arc,
+                        Ok(None) => return true,
+                        Err(e) => {
+                            read_err = Some(e);
                             return false;
                         }
+                    };
+                    if skip_post_filter || query::matches_value(&query, &arc) {
+                        found = Some((*arc).clone());
+                        return false;
                     }
                     true
                 },
             );
+            if let Some(e) = read_err {
+                return Err(e);
+            }
             if lazy_result.is_some() {
                 return Ok(found);
             }
@@ -1387,7 +2495,59 @@ impl Collection {
     /// Update documents matching a query atomically. Returns IDs of updated documents.
     /// If any unique constraint is violated, no documents are modified.
     /// `limit` caps the number of documents to update (e.g. `Some(1)` for update_one).
-    pub fn update(&mut self, query_json: &Value, update_json: &Value, limit: Option<usize>) -> Result<Vec<DocumentId>> {
+    /// With `expected_version`, every matched document must be at that
+    /// `_version` or nothing is modified and `VersionConflict` is returned.
+    pub fn update(
+        &mut self,
+        query_json: &Value,
+        update_json: &Value,
+        limit: Option<usize>,
+        expected_version: Option<u64>,
+    ) -> Result<Vec<DocumentId>> {
+        Ok(self
+            .update_returning(query_json, update_json, limit, expected_version)?
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect())
+    }
+
+    /// Same as `update`, but also returns the post-update document for each
+    /// updated ID (the same `Arc` stored in the document cache).
+    pub fn update_returning(
+        &mut self,
+        query_json: &Value,
+        update_json: &Value,
+        limit: Option<usize>,
+        expected_version: Option<u64>,
+    ) -> Result<Vec<(DocumentId, Arc<Value>)>> {
+        let matches = self.find_update_matches(query_json, update_json, limit, expected_version)?;
+        self.update_matches(matches, update_json)
+    }
+
+    /// What `update` would do, without writing anything: the ID and new
+    /// version of each document it would change. Fails the same way `update`
+    /// would (bad operators, schema or unique-constraint violations, version
+    /// conflicts).
+    pub fn update_dry_run(
+        &self,
+        query_json: &Value,
+        update_json: &Value,
+        limit: Option<usize>,
+        expected_version: Option<u64>,
+    ) -> Result<Vec<(DocumentId, Value)>> {
+        let matches = self.find_update_matches(query_json, update_json, limit, expected_version)?;
+        Ok(self.prepare_updates(matches, update_json)?.into_iter().map(|op| (op.id, op.new_data)).collect())
+    }
+
+    /// Phase 1 of `update`: check the update document, then find the matched
+    /// documents and check their versions.
+    fn find_update_matches(
+        &self,
+        query_json: &Value,
+        update_json: &Value,
+        limit: Option<usize>,
+        expected_version: Option<u64>,
+    ) -> Result<Vec<(DocumentId, Value, DocLocation)>> {
         // Validate update document has at least one operator
         let update_obj = update_json
             .as_object()
@@ -1399,34 +2559,63 @@ impl Collection {
         }
 
         let query = query::parse_query(query_json)?;
+        let matches = self.find_matches_for_write(&query, limit)?;
+
+        if let Some(expected_version) = expected_version {
+            for (id, _, _) in &matches {
+                let actual_version = self.get_version(*id);
+                if actual_version != expected_version {
+                    return Err(Error::VersionConflict { doc_id: *id, expected_version, actual_version });
+                }
+            }
+        }
+
+        Ok(matches)
+    }
 
+    /// Documents matching `query` with their current data and location, up
+    /// to `limit`: Phase 1 of `update` and `delete`.
+    fn find_matches_for_write(
+        &self,
+        query: &Query,
+        limit: Option<usize>,
+    ) -> Result<Vec<(DocumentId, Value, DocLocation)>> {
         // Phase 1: Find matching docs (with early termination via limit)
         let mut matches: Vec<(DocumentId, Value, DocLocation)> = Vec::new();
 
-        // Try lazy index path first for limited updates
+        // Try lazy index path first for limited writes
         let mut lazy_handled = false;
         if limit.is_some() {
-            let doc_cache = &self.doc_cache;
             let primary_index = &self.primary_index;
-            let skip_post_filter = query::is_fully_indexed(&query, &self.field_indexes);
+            let skip_post_filter = query::is_fully_indexed(query, &self.field_indexes);
             let lim = limit.unwrap();
+            let mut read_err = None;
             let lazy_result = query::execute_indexed_lazy(
-                &query,
+                query,
                 &self.field_indexes,
                 &mut |id| {
-                    if let Some(arc) = doc_cache.get(&id) {
-                        if skip_post_filter || query::matches_value(&query, arc) {
-                            if let Some(&old_loc) = primary_index.get(&id) {
-                                matches.push((id, (**arc).clone(), old_loc));
-                                if matches.len() >= lim {
-                                    return false;
-                                }
-                            }
+                    let arc = match self.read_doc_arc(id) {
+                        Ok(Some(arc)) => arc,
+                        Ok(None) => return true,
+                        Err(e) => {
+                            read_err = Some(e);
+                            return false;
+                        }
+                    };
+                    if (skip_post_filter || query::matches_value(query, &arc))
+                        && let Some(&old_loc) = primary_index.get(&id)
+                    {
+                        matches.push((id, (*arc).clone(), old_loc));
+                        if matches.len() >= lim {
+                            return false;
                         }
                     }
                     true
                 },
             );
+            if let Some(e) = read_err {
+                return Err(e);
+            }
             if lazy_result.is_some() {
                 lazy_handled = true;
             }
@@ -1434,7 +2623,7 @@ impl Collection {
 
         if !lazy_handled {
             let candidate_ids = query::execute_indexed(
-                &query,
+                query,
                 &self.field_indexes,
                 &self.composite_indexes,
             );
@@ -1443,7 +2632,7 @@ impl Collection {
                 for &id in indexed_ids {
                     if let Some(&old_loc) = self.primary_index.get(&id) {
                         if let Some(data) = self.read_doc(id)? {
-                            if query::matches_value(&query, &data) {
+                            if query::matches_value(query, &data) {
                                 matches.push((id, data, old_loc));
                                 if limit.is_some_and(|l| matches.len() >= l) { break; }
                             }
@@ -1453,54 +2642,28 @@ impl Collection {
             } else {
                 // No index — iterate doc store (zero-copy: clone only matches)
                 self.for_each_doc_arc_while(|id, arc| {
-                    if query::matches_value(&query, arc) {
-                        if let Some(&old_loc) = self.primary_index.get(&id) {
-                            matches.push((id, (**arc).clone(), old_loc));
-                            if limit.is_some_and(|l| matches.len() >= l) { return Ok(false); }
-                        }
+                    if query::matches_value(query, arc)
+                        && let Some(&old_loc) = self.primary_index.get(&id)
+                    {
+                        matches.push((id, (**arc).clone(), old_loc));
+                        if limit.is_some_and(|l| matches.len() >= l) { return Ok(false); }
                     }
                     Ok(true)
                 })?;
             }
         }
 
-        if matches.is_empty() {
-            return Ok(Vec::new());
-        }
-
-        // Phase 2: Prepare all updates and validate constraints upfront
-        struct UpdateOp {
-            id: DocumentId,
-            old_loc: DocLocation,
-            old_data: Value,
-            new_data: Value,
-            new_bytes: Vec<u8>,
-        }
-        let mut ops = Vec::with_capacity(matches.len());
-
-        for (id, data, old_loc) in matches {
-            let mut mutable_data = data.clone();
-
-            crate::update::apply_update(&mut mutable_data, update_json)?;
-
-            let old_version = mutable_data.get("_version").and_then(|v| v.as_u64()).unwrap_or(0);
-            let new_version = old_version + 1;
-            mutable_data.as_object_mut()
-                .unwrap()
-                .insert("_version".to_string(), Value::Number(new_version.into()));
-
-            self.check_unique_constraints(&mutable_data, Some(id))?;
-
-            let new_bytes = crate::codec::encode_doc(&mutable_data)?;
-            ops.push(UpdateOp {
-                id,
-                old_loc,
-                old_data: data,
-                new_data: mutable_data,
-                new_bytes,
-            });
-        }
+        Ok(matches)
+    }
 
+    /// Apply `update_json` to matched documents (ID, current data, location)
+    /// atomically: every new version is checked before any is written.
+    fn update_matches(
+        &mut self,
+        matches: Vec<(DocumentId, Value, DocLocation)>,
+        update_json: &Value,
+    ) -> Result<Vec<(DocumentId, Arc<Value>)>> {
+        let ops = self.prepare_updates(matches, update_json)?;
         if ops.is_empty() {
             return Ok(Vec::new());
         }
@@ -1525,9 +2688,8 @@ impl Collection {
         self.wal.checkpoint_no_sync()?;
 
         // Phase 5: update in-memory state
-        let mut updated_ids = Vec::with_capacity(ops.len());
+        let mut updated = Vec::with_capacity(ops.len());
         for (op, new_loc) in ops.into_iter().zip(new_locs) {
-            updated_ids.push(op.id);
             self.primary_index.insert(op.id, new_loc);
             let new_version = op.new_data.get("_version").and_then(|v| v.as_u64()).unwrap_or(1);
             self.version_index.insert(op.id, new_version);
@@ -1546,86 +2708,98 @@ impl Collection {
                 idx.remove(op.id);
                 let _ = idx.insert(op.id, &op.new_data);
             }
-            self.doc_cache.insert(op.id, Arc::new(op.new_data));
+            for idx in self.geo_indexes.values_mut() {
+                idx.insert(op.id, &op.new_data);
+            }
+            let new_data = Arc::new(op.new_data);
+            self.doc_cache.insert(op.id, Arc::clone(&new_data));
+            updated.push((op.id, new_data));
+        }
+
+        Ok(updated)
+    }
+
+    /// Phase 2 of `update`: apply `update_json` to each matched document,
+    /// bump its `_version`, and check the result against the schema and
+    /// unique indexes, without writing anything.
+    fn prepare_updates(
+        &self,
+        matches: Vec<(DocumentId, Value, DocLocation)>,
+        update_json: &Value,
+    ) -> Result<Vec<UpdateOp>> {
+        let mut ops = Vec::with_capacity(matches.len());
+        for (id, data, old_loc) in matches {
+            let mut mutable_data = data.clone();
+
+            crate::update::apply_update(&mut mutable_data, update_json)?;
+
+            let old_version = mutable_data.get("_version").and_then(|v| v.as_u64()).unwrap_or(0);
+            let new_version = old_version + 1;
+            mutable_data.as_object_mut()
+                .unwrap()
+                .insert("_version".to_string(), Value::Number(new_version.into()));
+
+            self.validate_document(&mut mutable_data)?;
+            self.check_unique_constraints(&mutable_data, Some(id))?;
+
+            let new_bytes = self.encode(&mutable_data)?;
+            ops.push(UpdateOp {
+                id,
+                old_loc,
+                old_data: data,
+                new_data: mutable_data,
+                new_bytes,
+            });
         }
 
-        Ok(updated_ids)
+        Ok(ops)
     }
 
     /// Delete documents matching a query atomically. Returns IDs of deleted documents.
     /// `limit` caps the number of documents to delete (e.g. `Some(1)` for delete_one).
     pub fn delete(&mut self, query_json: &Value, limit: Option<usize>) -> Result<Vec<DocumentId>> {
         let query = query::parse_query(query_json)?;
+        let ops = self
+            .find_matches_for_write(&query, limit)?
+            .into_iter()
+            .map(|(id, data, loc)| DeleteOp { id, loc, data })
+            .collect();
+        self.delete_matches(ops)
+    }
 
-        // Phase 1: Find matching docs (with early termination via limit)
-        struct DeleteOp {
-            id: DocumentId,
-            loc: DocLocation,
-            data: Value,
-        }
-        let mut ops = Vec::new();
+    /// Delete every document whose `_expireAt` is at or before `now`.
+    /// Returns IDs of deleted documents.
+    pub fn delete_expired(&mut self, now: chrono::DateTime<chrono::Utc>) -> Result<Vec<DocumentId>> {
+        let ops = self
+            .expired_matches(now)?
+            .into_iter()
+            .map(|(id, data, loc)| DeleteOp { id, loc, data })
+            .collect();
+        self.delete_matches(ops)
+    }
 
-        // Try lazy index path first for limited deletes
-        let mut lazy_handled = false;
-        if limit.is_some() {
-            let doc_cache = &self.doc_cache;
-            let primary_index = &self.primary_index;
-            let skip_post_filter = query::is_fully_indexed(&query, &self.field_indexes);
-            let lim = limit.unwrap();
-            let lazy_result = query::execute_indexed_lazy(
-                &query,
-                &self.field_indexes,
-                &mut |id| {
-                    if let Some(arc) = doc_cache.get(&id) {
-                        if skip_post_filter || query::matches_value(&query, arc) {
-                            if let Some(&loc) = primary_index.get(&id) {
-                                ops.push(DeleteOp { id, loc, data: (**arc).clone() });
-                                if ops.len() >= lim {
-                                    return false;
-                                }
-                            }
-                        }
-                    }
-                    true
-                },
-            );
-            if lazy_result.is_some() {
-                lazy_handled = true;
-            }
-        }
+    /// Whether any document's `_expireAt` is at or before `now`.
+    pub fn has_expired(&self, now: chrono::DateTime<chrono::Utc>) -> Result<bool> {
+        Ok(!self.expired_matches(now)?.is_empty())
+    }
 
-        if !lazy_handled {
-            let candidate_ids = query::execute_indexed(
-                &query,
-                &self.field_indexes,
-                &self.composite_indexes,
-            );
+    fn expired_matches(&self, now: chrono::DateTime<chrono::Utc>) -> Result<Vec<(DocumentId, Value, DocLocation)>> {
+        let query = query::parse_query(&serde_json::json!({ (document::EXPIRE_AT_FIELD): {"$exists": true} }))?;
+        Ok(self
+            .find_matches_for_write(&query, None)?
+            .into_iter()
+            .filter(|(_, data, _)| document::is_expired(data, now))
+            .collect())
+    }
 
-            if let Some(ref indexed_ids) = candidate_ids {
-                for &id in indexed_ids {
-                    if let Some(&loc) = self.primary_index.get(&id) {
-                        if let Some(data) = self.read_doc(id)? {
-                            if query::matches_value(&query, &data) {
-                                ops.push(DeleteOp { id, loc, data });
-                                if limit.is_some_and(|l| ops.len() >= l) { break; }
-                            }
-                        }
-                    }
-                }
-            } else {
-                // No index — iterate doc store (zero-copy: clone only matches)
-                self.for_each_doc_arc_while(|id, arc| {
-                    if query::matches_value(&query, arc) {
-                        if let Some(&loc) = self.primary_index.get(&id) {
-                            ops.push(DeleteOp { id, loc, data: (**arc).clone() });
-                            if limit.is_some_and(|l| ops.len() >= l) { return Ok(false); }
-                        }
-                    }
-                    Ok(true)
-                })?;
-            }
-        }
+    /// IDs of the documents `delete` would remove, without removing them.
+    pub fn delete_dry_run(&self, query_json: &Value, limit: Option<usize>) -> Result<Vec<DocumentId>> {
+        let query = query::parse_query(query_json)?;
+        Ok(self.find_matches_for_write(&query, limit)?.into_iter().map(|(id, _, _)| id).collect())
+    }
 
+    /// Delete matched documents atomically.
+    fn delete_matches(&mut self, ops: Vec<DeleteOp>) -> Result<Vec<DocumentId>> {
         if ops.is_empty() {
             return Ok(Vec::new());
         }
@@ -1652,7 +2826,7 @@ impl Collection {
             deleted_ids.push(op.id);
             self.primary_index.remove(&op.id);
             self.version_index.remove(&op.id);
-            self.doc_cache.remove(&op.id);
+            self.doc_cache.remove(op.id);
             for idx in self.field_indexes.values_mut() {
                 idx.remove_value(op.id, &op.data);
             }
@@ -1665,18 +2839,283 @@ impl Collection {
             for idx in self.vector_indexes.values_mut() {
                 idx.remove(op.id);
             }
+            for idx in self.geo_indexes.values_mut() {
+                idx.remove(op.id);
+            }
         }
 
         Ok(deleted_ids)
     }
 
+    /// Update or (with `opts.remove`) delete the first document matching
+    /// `query_json` in `opts.sort` order, and return it as it was before the
+    /// change, or after an update with `opts.return_new`. `None` if nothing
+    /// matches. The find and the write happen under the same `&mut self`, so
+    /// no other writer can change or claim the document in between.
+    pub fn find_and_modify(
+        &mut self,
+        query_json: &Value,
+        update_json: Option<&Value>,
+        opts: &FindAndModifyOptions,
+    ) -> Result<Option<Value>> {
+        let update_json = match (update_json, opts.remove) {
+            (Some(_), true) => {
+                return Err(Error::InvalidQuery("find_and_modify takes an update or remove, not both".into()));
+            }
+            (None, false) => return Err(Error::InvalidQuery("find_and_modify needs an update or remove".into())),
+            (update_json, _) => update_json,
+        };
+        if update_json.is_some_and(|u| u.as_object().is_none_or(|u| u.is_empty())) {
+            return Err(Error::InvalidQuery("update must contain at least one operator".into()));
+        }
+
+        let find_opts = FindOptions {
+            sort: opts.sort.clone(),
+            limit: Some(1),
+            ..FindOptions::default()
+        };
+        let Some(found) = self.find_with_options_arcs(query_json, &find_opts)?.into_iter().next() else {
+            return Ok(None);
+        };
+        let Some((id, loc)) = found
+            .get("_id")
+            .and_then(Value::as_u64)
+            .and_then(|id| self.primary_index.get(&id).map(|&loc| (id, loc)))
+        else {
+            return Ok(None);
+        };
+        // The stored document, without the `_distance` a geo match carries
+        let Some(before) = self.read_doc(id)? else {
+            return Ok(None);
+        };
+
+        match update_json {
+            None => {
+                self.delete_matches(vec![DeleteOp { id, loc, data: before.clone() }])?;
+                Ok(Some(before))
+            }
+            Some(update_json) => {
+                let updated = self.update_matches(vec![(id, before.clone(), loc)], update_json)?;
+                match updated.into_iter().next() {
+                    Some((_, after)) if opts.return_new => Ok(Some((*after).clone())),
+                    _ => Ok(Some(before)),
+                }
+            }
+        }
+    }
+
+    /// Apply `ops` in order as one atomic batch. Each op sees the effects of
+    /// the ones before it; unique constraints are checked against the state
+    /// the whole batch leaves behind. If any op fails, nothing is written.
+    /// All changes go to the WAL and storage together, with a single fsync.
+    pub fn bulk_write(&mut self, ops: Vec<BulkOp>) -> Result<Vec<BulkOpResult>> {
+        self.bulk_write_with_durability(ops, Durability::Full)
+    }
+
+    /// `bulk_write`, skipping the fsync when `durability` is `Relaxed`.
+    pub fn bulk_write_with_durability(
+        &mut self,
+        ops: Vec<BulkOp>,
+        durability: Durability,
+    ) -> Result<Vec<BulkOpResult>> {
+        // The new state of every document the batch touches (None: deleted)
+        let mut staged: BTreeMap<DocumentId, Option<Value>> = BTreeMap::new();
+        let mut next_id = self.next_id;
+        let mut results = Vec::with_capacity(ops.len());
+
+        for op in ops {
+            let result = match op {
+                BulkOp::Insert(mut data) => {
+                    if !data.is_object() {
+                        return Err(Error::NotAnObject);
+                    }
+                    self.defaults.apply(&mut data);
+                    self.check_document_size(&data)?;
+                    let obj = data.as_object_mut().unwrap();
+                    obj.insert("_id".to_string(), Value::Number(next_id.into()));
+                    obj.insert("_version".to_string(), Value::Number(1.into()));
+                    self.validate_document(&mut data)?;
+                    staged.insert(next_id, Some(data));
+                    next_id += 1;
+                    BulkOpResult::Inserted(next_id - 1)
+                }
+                BulkOp::Update { query, update } => {
+                    if update.as_object().is_none_or(|u| u.is_empty()) {
+                        return Err(Error::InvalidQuery("update must contain at least one operator".into()));
+                    }
+                    let matches = self.staged_matches(&query, &staged)?;
+                    for (id, mut data) in matches.iter().cloned() {
+                        crate::update::apply_update(&mut data, &update)?;
+                        let new_version = data.get("_version").and_then(|v| v.as_u64()).unwrap_or(0) + 1;
+                        data.as_object_mut()
+                            .unwrap()
+                            .insert("_version".to_string(), Value::Number(new_version.into()));
+                        self.validate_document(&mut data)?;
+                        staged.insert(id, Some(data));
+                    }
+                    BulkOpResult::Modified(matches.into_iter().map(|(id, _)| id).collect())
+                }
+                BulkOp::Delete { query } => {
+                    let ids: Vec<DocumentId> =
+                        self.staged_matches(&query, &staged)?.into_iter().map(|(id, _)| id).collect();
+                    for &id in &ids {
+                        staged.insert(id, None);
+                    }
+                    BulkOpResult::Deleted(ids)
+                }
+            };
+            results.push(result);
+        }
+
+        self.check_staged_unique(&staged)?;
+
+        let mut mutations = Vec::with_capacity(staged.len());
+        for (id, new_data) in staged {
+            let old_loc = self.primary_index.get(&id).copied();
+            let old_data = match old_loc {
+                Some(_) => self.read_doc(id)?,
+                None => None,
+            };
+            let (wal_entry, new_bytes, new_data) = match (old_loc, new_data) {
+                // Inserted and deleted again within the batch
+                (None, None) => continue,
+                (None, Some(data)) => {
+                    let bytes = self.encode(&data)?;
+                    (WalEntry::insert(id, bytes.clone()), bytes, data)
+                }
+                (Some(_), Some(data)) => {
+                    let bytes = self.encode(&data)?;
+                    (WalEntry::update(id, bytes.clone()), bytes, data)
+                }
+                (Some(_), None) => (WalEntry::delete(id), Vec::new(), Value::Null),
+            };
+            mutations.push(PreparedMutation {
+                is_delete: matches!(wal_entry, WalEntry::Delete { .. }),
+                wal_entry,
+                doc_id: id,
+                new_bytes,
+                old_loc,
+                old_data,
+                new_data,
+            });
+        }
+
+        let wal_entries: Vec<WalEntry> = mutations.iter().map(|m| m.wal_entry.clone()).collect();
+        self.wal.log_batch_no_sync(&wal_entries)?;
+        self.apply_prepared_with_durability(&mut mutations, durability)?;
+        self.wal.checkpoint_no_sync()?;
+        self.next_id = next_id;
+
+        Ok(results)
+    }
+
+    /// Documents matching `query_json` as the batch so far has left them,
+    /// in `_id` order: stored documents the batch hasn't touched, plus the
+    /// staged new versions.
+    fn staged_matches(
+        &self,
+        query_json: &Value,
+        staged: &BTreeMap<DocumentId, Option<Value>>,
+    ) -> Result<Vec<(DocumentId, Value)>> {
+        let query = query::parse_query(query_json)?;
+        let mut matches = Vec::new();
+        for doc in self.find_arcs(query_json)? {
+            let Some(id) = doc.get("_id").and_then(|v| v.as_u64()).filter(|id| !staged.contains_key(id)) else {
+                continue;
+            };
+            // Re-read the stored document: geo matches carry a `_distance`
+            if let Some(data) = self.read_doc(id)? {
+                matches.push((id, data));
+            }
+        }
+        for (&id, data) in staged {
+            if let Some(data) = data.as_ref().filter(|d| query::matches_value(&query, d)) {
+                matches.push((id, data.clone()));
+            }
+        }
+        matches.sort_by_key(|(id, _)| *id);
+        Ok(matches)
+    }
+
+    /// Fail with `UniqueViolation` if two staged documents share a uniquely
+    /// indexed value, or one shares it with a stored document the batch
+    /// leaves alone.
+    fn check_staged_unique(&self, staged: &BTreeMap<DocumentId, Option<Value>>) -> Result<()> {
+        for idx in self.field_indexes.values().filter(|idx| idx.unique) {
+            let mut seen = HashSet::new();
+            for (&id, data) in staged {
+                let Some(value) = data.as_ref().and_then(|d| resolve_field_in_value(d, &idx.field)) else {
+                    continue;
+                };
+                let iv = IndexValue::from_json(value);
+                let taken = idx.find_eq(&iv).into_iter().any(|other| other != id && !staged.contains_key(&other));
+                if taken || !seen.insert(iv) {
+                    return Err(Error::UniqueViolation { field: idx.field.clone() });
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Returns the number of documents in the collection.
     pub fn count(&self) -> usize {
         self.primary_index.len()
     }
 
+    /// Sorted unique values of `field` among documents matching `query_json`.
+    /// Array values contribute their elements. When `field` is indexed and
+    /// the query can be answered from indexes alone, the values are read from
+    /// the index keys without touching documents; otherwise matching
+    /// documents are scanned. Either way, values compare as they do in
+    /// indexes, so dates come back in RFC 3339 form.
+    pub fn distinct(&self, field: &str, query_json: &Value) -> Result<Vec<Value>> {
+        let query = query::parse_query(query_json)?;
+        if let Some(values) = self.distinct_from_index(field, &query) {
+            return Ok(values);
+        }
+
+        let mut values: BTreeMap<IndexValue, Value> = BTreeMap::new();
+        self.for_each_doc_arc_while(|_, doc| {
+            if query::matches_value(&query, doc) {
+                match resolve_field_in_value(doc, field) {
+                    Some(Value::Array(items)) => items.iter().for_each(|v| add_distinct(&mut values, v)),
+                    Some(v) => add_distinct(&mut values, v),
+                    None => {}
+                }
+            }
+            Ok(true)
+        })?;
+        Ok(values.into_values().collect())
+    }
+
+    fn distinct_from_index(&self, field: &str, query: &Query) -> Option<Vec<Value>> {
+        let index = self.field_indexes.get(field)?;
+        if index.sparse || !query::is_fully_indexed(query, &self.field_indexes) {
+            return None;
+        }
+        let candidates = match query {
+            Query::All => None,
+            _ => Some(query::execute_indexed(query, &self.field_indexes, &self.composite_indexes)?),
+        };
+        let mut values = Vec::new();
+        for (key, ids) in index.iter_asc() {
+            // Arrays and objects are indexed as their JSON text; only a scan
+            // can split an array into its elements.
+            if matches!(key, IndexValue::String(s) if s.starts_with('[') || s.starts_with('{')) {
+                return None;
+            }
+            if candidates.as_ref().is_none_or(|c| ids.iter().any(|id| c.contains(id))) {
+                values.push(key.to_json());
+            }
+        }
+        Some(values)
+    }
+
     /// Count documents matching a query without building a Vec<Value>.
     pub fn count_matching(&self, query_json: &Value) -> Result<usize> {
+        if geo::extract_geo_query(query_json)?.is_some() {
+            return Ok(self.find_arcs(query_json)?.len());
+        }
         let query = query::parse_query(query_json)?;
 
         // Fast path: count directly from index (no BTreeSet, no doc reads)
@@ -1706,10 +3145,10 @@ impl Collection {
             }
             // Small candidate set — random access via doc cache
             for &id in indexed_ids {
-                if let Some(arc) = self.doc_cache.get(&id) {
-                    if query::matches_value(&query, arc) {
-                        count += 1;
-                    }
+                if let Some(arc) = self.read_doc_arc(id)?
+                    && query::matches_value(&query, &arc)
+                {
+                    count += 1;
                 }
             }
         } else {
@@ -1719,28 +3158,51 @@ impl Collection {
         Ok(count)
     }
 
+    /// Count using a file scan with raw JSONB field extraction, split across
+    /// threads for large collections.
+    fn count_with_scan(&self, query: &query::Query) -> Result<usize> {
+        self.count_with_scan_using(query, self.scan_workers_for_scan())
+    }
+
     /// Count using sequential file scan with raw JSONB field extraction.
     /// Opens a separate read-only file handle (no mutex contention with concurrent
     /// reads) and uses BufReader for efficient sequential I/O. For each record,
     /// extracts only the fields referenced by the query from raw JSONB instead of
-    /// deserializing the entire document.
-    fn count_with_scan(&self, query: &query::Query) -> Result<usize> {
-        let mut count = 0;
-        self.storage.scan_readonly_while(|bytes| {
-            if let Some(matched) = query::matches_raw_jsonb(query, bytes) {
-                if matched {
-                    count += 1;
-                }
-            } else {
-                // Fallback for legacy JSON text or complex value types
-                let data = crate::codec::decode_doc(bytes)?;
-                if query::matches_value(query, &data) {
+    /// deserializing the entire document. With more than one worker, each thread
+    /// scans a contiguous chunk of records through its own handle and the
+    /// per-chunk counts are summed.
+    fn count_with_scan_using(&self, query: &query::Query, workers: usize) -> Result<usize> {
+        if workers <= 1 {
+            let mut count = 0;
+            self.storage.scan_readonly_while(|bytes| {
+                if record_matches(query, bytes)? {
                     count += 1;
                 }
-            }
-            Ok(true)
+                Ok(true)
+            })?;
+            return Ok(count);
+        }
+
+        let chunks = self.scan_chunks(workers);
+        let counts: Vec<usize> = std::thread::scope(|scope| {
+            let handles: Vec<_> = chunks
+                .iter()
+                .map(|chunk| {
+                    scope.spawn(move || -> Result<usize> {
+                        let mut count = 0;
+                        self.storage.scan_locations_readonly(chunk, |_loc, bytes| {
+                            if record_matches(query, bytes)? {
+                                count += 1;
+                            }
+                            Ok(true)
+                        })?;
+                        Ok(count)
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect::<Result<_>>()
         })?;
-        Ok(count)
+        Ok(counts.into_iter().sum())
     }
 
     /// Compact the data file by rewriting only active records.
@@ -1751,12 +3213,25 @@ impl Collection {
 
         let old_size = self.storage.file_size();
 
+        // Copy active records to new file. Corrupt records skipped when the
+        // collection was loaded are dropped; a live one still fails.
+        let mut active_records = Vec::new();
+        let mut corrupt = Vec::new();
+        self.storage.for_each_active_skipping_corrupt(
+            |loc, bytes| {
+                active_records.push((loc, bytes));
+                Ok(())
+            },
+            |loc, e| corrupt.push((loc, e)),
+        )?;
+        let live: HashSet<u64> = self.primary_index.values().map(|loc| loc.offset).collect();
+        if let Some((_, e)) = corrupt.into_iter().find(|(loc, _)| live.contains(&loc.offset)) {
+            return Err(e);
+        }
+
         // Create temp storage (with same encryption key if present)
         let tmp_path = self.data_dir.join(format!("{}.dat.tmp", self.name));
         let new_storage = Storage::open_with_encryption(&tmp_path, self.encryption.clone())?;
-
-        // Copy active records to new file
-        let active_records = self.storage.iter_active()?;
         let mut new_primary_index = HashMap::new();
         let mut next_id: DocumentId = 1;
 
@@ -1766,8 +3241,9 @@ impl Collection {
                 Error::InvalidQuery("document missing _id during compaction".into())
             })?;
 
-            // Re-encode as JSONB (converts legacy JSON records on compact)
-            let new_bytes = crate::codec::encode_doc(&doc)?;
+            // Re-encode with the current codec (converts legacy JSON records
+            // and applies or removes compression)
+            let new_bytes = self.encode(&doc)?;
             let loc = new_storage.append_no_sync(&new_bytes)?;
             new_primary_index.insert(id, loc);
             if id >= next_id {
@@ -1784,7 +3260,9 @@ impl Collection {
         std::fs::rename(&tmp_path, &dat_path)?;
 
         // Replace storage with new instance pointing to the renamed file
+        let sync_mode = self.storage.sync_mode();
         self.storage = Storage::open_with_encryption(&dat_path, self.encryption.clone())?;
+        self.storage.set_sync_mode(sync_mode);
         self.primary_index = new_primary_index;
         self.next_id = next_id;
 
@@ -1800,7 +3278,12 @@ impl Collection {
         if let Some(ref mut text_idx) = self.text_index {
             text_idx.clear();
         }
+        // Document ids survive compaction, so vector indexes keep their
+        // vectors and only rebuild the graph without tombstones
         for idx in self.vector_indexes.values_mut() {
+            idx.compact();
+        }
+        for idx in self.geo_indexes.values_mut() {
             idx.clear();
         }
         for (&id, &loc) in &self.primary_index.clone() {
@@ -1818,8 +3301,8 @@ impl Collection {
             if let Some(ref mut text_idx) = self.text_index {
                 text_idx.index_doc(id, &data_arc);
             }
-            for idx in self.vector_indexes.values_mut() {
-                let _ = idx.insert(id, &data_arc);
+            for idx in self.geo_indexes.values_mut() {
+                idx.insert(id, &data_arc);
             }
             self.doc_cache.insert(id, data_arc);
         }
@@ -1834,6 +3317,82 @@ impl Collection {
         })
     }
 
+    /// Rewrite the data file of collection `name`, which must not be open,
+    /// keeping only the records that pass their checksum and decode to a
+    /// document. Everything else is appended to `<name>.corrupt` as
+    /// `[offset u64][len u32][bytes]` entries. The index caches are removed
+    /// so the next open rebuilds the indexes from the kept documents.
+    pub fn repair(name: &str, data_dir: &Path, encryption: Option<Arc<EncryptionKey>>) -> Result<RepairReport> {
+        let dat_path = data_dir.join(format!("{}.dat", name));
+        if !dat_path.exists() {
+            return Err(Error::CollectionNotFound(name.to_string()));
+        }
+
+        // Payloads are copied as stored, so the new file is written without
+        // the key even when the collection is encrypted
+        let tmp_path = data_dir.join(format!("{}.dat.tmp", name));
+        if tmp_path.exists() {
+            std::fs::remove_file(&tmp_path)?;
+        }
+        let new_storage = Storage::open(&tmp_path)?;
+        let mut recovered = 0;
+        let mut damaged: Vec<(u64, Vec<u8>)> = Vec::new();
+        Storage::salvage(&dat_path, |record| {
+            match record {
+                Salvaged::Intact { offset, payload } => {
+                    let plain = match &encryption {
+                        Some(key) => key.decrypt(&payload),
+                        None => Ok(payload.clone()),
+                    };
+                    let readable = plain
+                        .and_then(|bytes| crate::codec::decode_doc(&bytes))
+                        .is_ok_and(|doc| doc.get("_id").and_then(Value::as_u64).is_some());
+                    match readable {
+                        true => {
+                            new_storage.append_no_sync(&payload)?;
+                            recovered += 1;
+                        }
+                        false => damaged.push((offset, payload)),
+                    }
+                }
+                Salvaged::Damaged { offset, bytes } => damaged.push((offset, bytes)),
+            }
+            Ok(())
+        })?;
+        new_storage.sync()?;
+        drop(new_storage);
+
+        let quarantine = match damaged.is_empty() {
+            true => None,
+            false => {
+                use std::io::Write;
+                let path = data_dir.join(format!("{}.corrupt", name));
+                let mut out = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+                for (offset, bytes) in &damaged {
+                    out.write_all(&offset.to_le_bytes())?;
+                    out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+                    out.write_all(bytes)?;
+                }
+                out.sync_all()?;
+                Some(path)
+            }
+        };
+
+        std::fs::rename(&tmp_path, &dat_path)?;
+        for ext in ["fidx", "cidx", "vidx"] {
+            let path = data_dir.join(format!("{}.{}", name, ext));
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        Ok(RepairReport {
+            recovered,
+            lost: damaged.len(),
+            quarantine,
+        })
+    }
+
     // -----------------------------------------------------------------------
     // Version tracking
     // -----------------------------------------------------------------------
@@ -1863,17 +3422,20 @@ impl Collection {
         if !data.is_object() {
             return Err(Error::NotAnObject);
         }
+        self.defaults.apply(&mut data);
+        self.check_document_size(&data)?;
 
         let id = self.next_id;
         let obj = data.as_object_mut().unwrap();
         obj.insert("_id".to_string(), Value::Number(id.into()));
         obj.insert("_version".to_string(), Value::Number(1.into()));
 
+        self.validate_document(&mut data)?;
         self.check_unique_constraints(&data, None)?;
 
         self.next_id += 1;
 
-        let bytes = crate::codec::encode_doc(&data)?;
+        let bytes = self.encode(&data)?;
 
         Ok(PreparedMutation {
             wal_entry: WalEntry::Insert { doc_id: id, doc_bytes: bytes.clone(), tx_id },
@@ -1927,9 +3489,10 @@ impl Collection {
                 .unwrap()
                 .insert("_version".to_string(), Value::Number(new_version.into()));
 
+            self.validate_document(&mut data)?;
             self.check_unique_constraints(&data, Some(id))?;
 
-            let new_bytes = crate::codec::encode_doc(&data)?;
+            let new_bytes = self.encode(&data)?;
             mutations.push(PreparedMutation {
                 wal_entry: WalEntry::Update { doc_id: id, doc_bytes: new_bytes.clone(), tx_id },
                 doc_id: id,
@@ -2027,6 +3590,14 @@ impl Collection {
     /// Apply a batch of prepared mutations to storage and update indexes.
     /// WAL should already have been logged by the caller.
     pub fn apply_prepared(&mut self, mutations: &mut Vec<PreparedMutation>) -> Result<()> {
+        self.apply_prepared_with_durability(mutations, Durability::Full)
+    }
+
+    fn apply_prepared_with_durability(
+        &mut self,
+        mutations: &mut [PreparedMutation],
+        durability: Durability,
+    ) -> Result<()> {
         // Apply to storage
         for m in mutations.iter() {
             if m.is_delete {
@@ -2049,14 +3620,16 @@ impl Collection {
                 new_locs.push(Some(loc));
             }
         }
-        self.storage.sync()?;
+        if durability == Durability::Full {
+            self.storage.sync()?;
+        }
 
         // Update in-memory indexes, doc_cache, and doc store
         for (i, m) in mutations.iter().enumerate() {
             if m.is_delete {
                 self.primary_index.remove(&m.doc_id);
                 self.version_index.remove(&m.doc_id);
-                self.doc_cache.remove(&m.doc_id);
+                self.doc_cache.remove(m.doc_id);
                 if let Some(ref old_data) = m.old_data {
                     for idx in self.field_indexes.values_mut() {
                         idx.remove_value(m.doc_id, old_data);
@@ -2071,6 +3644,9 @@ impl Collection {
                 for idx in self.vector_indexes.values_mut() {
                     idx.remove(m.doc_id);
                 }
+                for idx in self.geo_indexes.values_mut() {
+                    idx.remove(m.doc_id);
+                }
             } else if let Some(loc) = new_locs[i] {
                 self.primary_index.insert(m.doc_id, loc);
                 let ver = m.new_data.get("_version").and_then(|v| v.as_u64()).unwrap_or(1);
@@ -2096,6 +3672,9 @@ impl Collection {
                     idx.remove(m.doc_id);
                     let _ = idx.insert(m.doc_id, &m.new_data);
                 }
+                for idx in self.geo_indexes.values_mut() {
+                    idx.insert(m.doc_id, &m.new_data);
+                }
                 self.doc_cache.insert(m.doc_id, Arc::new(m.new_data.clone()));
             }
         }
@@ -2139,12 +3718,28 @@ mod tests {
         let (_dir, mut col) = temp_collection("test");
         let id = col.insert(json!({"name": "Alice"})).unwrap();
         assert_eq!(col.get_version(id), 1);
-        col.update(&json!({"_id": id}), &json!({"$set": {"name": "Bob"}}), None).unwrap();
+        col.update(&json!({"_id": id}), &json!({"$set": {"name": "Bob"}}), None, None).unwrap();
         let doc = col.get(id).unwrap().unwrap();
         assert_eq!(doc["_version"], 2);
         assert_eq!(col.get_version(id), 2);
     }
 
+    #[test]
+    fn min_max_updates_reindex_and_bump_version() {
+        let (_dir, mut col) = temp_collection("test");
+        col.create_index("qty").unwrap();
+        let id = col.insert(json!({"sku": "a", "qty": 10})).unwrap();
+
+        col.update(&json!({"_id": id}), &json!({"$min": {"qty": 3}}), None, None).unwrap();
+        assert_eq!(col.get_version(id), 2);
+        assert_eq!(col.find(&json!({"qty": 3})).unwrap().len(), 1);
+        assert!(col.find(&json!({"qty": 10})).unwrap().is_empty());
+
+        col.update(&json!({"_id": id}), &json!({"$max": {"qty": 8}}), None, None).unwrap();
+        assert_eq!(col.find(&json!({"qty": 8})).unwrap().len(), 1);
+        assert!(col.find(&json!({"qty": 3})).unwrap().is_empty());
+    }
+
     #[test]
     fn find_with_index() {
         let (_dir, mut col) = temp_collection("test");
@@ -2160,6 +3755,56 @@ mod tests {
         assert_eq!(results.len(), 2);
     }
 
+    #[test]
+    fn distinct_index_and_scan_agree() {
+        let (_dir, mut scanned) = temp_collection("scan");
+        let (_dir2, mut indexed) = temp_collection("idx");
+        indexed.create_index("status").unwrap();
+        indexed.create_index("region").unwrap();
+        let docs = [
+            json!({"status": "open", "region": "eu", "meta": {"owner": "ann"}}),
+            json!({"status": "closed", "region": "us", "meta": {"owner": "bob"}}),
+            json!({"status": "open", "region": "us", "meta": {"owner": "ann"}}),
+            json!({"status": "pending", "region": "eu"}),
+            json!({"region": "eu"}),
+            json!({"status": 3, "region": "apac"}),
+        ];
+        for doc in &docs {
+            scanned.insert(doc.clone()).unwrap();
+            indexed.insert(doc.clone()).unwrap();
+        }
+
+        for query in [json!({}), json!({"region": "eu"}), json!({"region": {"$in": ["us", "apac"]}})] {
+            let from_scan = scanned.distinct("status", &query).unwrap();
+            let from_index = indexed.distinct("status", &query).unwrap();
+            assert_eq!(from_scan, from_index, "query {query}");
+        }
+        assert_eq!(
+            indexed.distinct("status", &json!({})).unwrap(),
+            vec![json!(3), json!("closed"), json!("open"), json!("pending")]
+        );
+        assert_eq!(indexed.distinct("status", &json!({"region": "eu"})).unwrap(), vec![json!("open"), json!("pending")]);
+        // A query the indexes can't answer falls back to a scan
+        assert_eq!(indexed.distinct("region", &json!({"meta.owner": "ann"})).unwrap(), vec![json!("eu"), json!("us")]);
+        // Dotted paths
+        assert_eq!(scanned.distinct("meta.owner", &json!({})).unwrap(), vec![json!("ann"), json!("bob")]);
+        assert!(scanned.distinct("missing", &json!({})).unwrap().is_empty());
+    }
+
+    #[test]
+    fn distinct_array_fields_yield_elements() {
+        let (_dir, mut col) = temp_collection("test");
+        col.insert(json!({"tags": ["rust", "db"]})).unwrap();
+        col.insert(json!({"tags": ["db", "embedded"]})).unwrap();
+        col.insert(json!({"tags": "solo"})).unwrap();
+        let expected = vec![json!("db"), json!("embedded"), json!("rust"), json!("solo")];
+        assert_eq!(col.distinct("tags", &json!({})).unwrap(), expected);
+
+        // An index holds whole arrays, so distinct still scans
+        col.create_index("tags").unwrap();
+        assert_eq!(col.distinct("tags", &json!({})).unwrap(), expected);
+    }
+
     #[test]
     fn date_range_query() {
         let (_dir, mut col) = temp_collection("test");
@@ -2187,7 +3832,7 @@ mod tests {
         let id = col.insert(json!({"name": "Alice", "age": 30})).unwrap();
 
         let ids = col
-            .update(&json!({"name": "Alice"}), &json!({"$set": {"age": 31}}), None)
+            .update(&json!({"name": "Alice"}), &json!({"$set": {"age": 31}}), None, None)
             .unwrap();
         assert_eq!(ids.len(), 1);
 
@@ -2195,6 +3840,32 @@ mod tests {
         assert_eq!(doc["age"], 31);
     }
 
+    #[test]
+    fn update_checks_expected_version() {
+        let (_dir, mut col) = temp_collection("test");
+        let id = col.insert(json!({"name": "Alice", "city": "Paris"})).unwrap();
+        let query = json!({"name": "Alice"});
+
+        // Matching version: applied and bumped
+        col.update(&query, &json!({"$set": {"city": "Lyon"}}), Some(1), Some(1)).unwrap();
+        let doc = col.get(id).unwrap().unwrap();
+        assert_eq!((doc["city"].as_str(), doc["_version"].as_u64()), (Some("Lyon"), Some(2)));
+
+        // Stale version: rejected, document unchanged
+        let err = col.update(&query, &json!({"$set": {"city": "Nice"}}), Some(1), Some(1)).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::VersionConflict { doc_id, expected_version: 1, actual_version: 2 } if doc_id == id
+        ));
+        let doc = col.get(id).unwrap().unwrap();
+        assert_eq!((doc["city"].as_str(), doc["_version"].as_u64()), (Some("Lyon"), Some(2)));
+
+        // No version: updates whatever is there
+        col.update(&query, &json!({"$set": {"city": "Nice"}}), Some(1), None).unwrap();
+        let doc = col.get(id).unwrap().unwrap();
+        assert_eq!((doc["city"].as_str(), doc["_version"].as_u64()), (Some("Nice"), Some(3)));
+    }
+
     #[test]
     fn delete_doc() {
         let (_dir, mut col) = temp_collection("test");
@@ -2206,6 +3877,90 @@ mod tests {
         assert_eq!(col.count(), 1);
     }
 
+    #[test]
+    fn dry_runs_report_matches_without_writing() {
+        let (dir, mut col) = temp_collection("test");
+        col.create_unique_index("email").unwrap();
+        let alice = col.insert(json!({"name": "Alice", "email": "a@test.com", "team": "red"})).unwrap();
+        let bob = col.insert(json!({"name": "Bob", "email": "b@test.com", "team": "red"})).unwrap();
+        col.insert(json!({"name": "Carol", "email": "c@test.com", "team": "blue"})).unwrap();
+        let files = || {
+            let mut sizes: Vec<(String, u64)> = std::fs::read_dir(dir.path())
+                .unwrap()
+                .map(|e| e.unwrap())
+                .map(|e| (e.file_name().to_string_lossy().into_owned(), e.metadata().unwrap().len()))
+                .collect();
+            sizes.sort();
+            sizes
+        };
+        let before = files();
+        let red = json!({"team": "red"});
+
+        let updated = col.update_dry_run(&red, &json!({"$set": {"team": "green"}}), None, None).unwrap();
+        let mut ids: Vec<DocumentId> = updated.iter().map(|(id, _)| *id).collect();
+        ids.sort();
+        assert_eq!(ids, [alice, bob]);
+        assert!(updated.iter().all(|(_, doc)| doc["team"] == "green" && doc["_version"] == 2));
+        assert_eq!(col.update_dry_run(&red, &json!({"$set": {"team": "green"}}), Some(1), None).unwrap().len(), 1);
+
+        let mut deleted = col.delete_dry_run(&red, None).unwrap();
+        deleted.sort();
+        assert_eq!(deleted, [alice, bob]);
+        assert_eq!(col.delete_dry_run(&red, Some(1)).unwrap().len(), 1);
+        assert!(col.delete_dry_run(&json!({"team": "none"}), None).unwrap().is_empty());
+
+        // Fails where the real update would
+        let conflict = col.update_dry_run(&red, &json!({"$set": {"email": "c@test.com"}}), None, None);
+        assert!(matches!(conflict, Err(Error::UniqueViolation { .. })));
+        let stale = col.update_dry_run(&red, &json!({"$set": {"team": "x"}}), None, Some(7));
+        assert!(matches!(stale, Err(Error::VersionConflict { .. })));
+
+        assert_eq!(files(), before);
+        assert_eq!(col.count(), 3);
+        assert_eq!(col.find(&red).unwrap().len(), 2);
+        assert_eq!(col.get_version(alice), 1);
+    }
+
+    #[test]
+    fn describe_profiles_fields_and_indexes() {
+        let (_dir, mut col) = temp_collection("test");
+        col.create_unique_index("email").unwrap();
+        col.create_composite_index(vec!["city".into(), "age".into()]).unwrap();
+        col.insert(json!({"email": "a@test.com", "age": 30, "city": "NYC", "tags": ["x"]})).unwrap();
+        col.insert(json!({"email": "b@test.com", "age": "unknown", "address": {"zip": "1"}})).unwrap();
+        col.insert(json!({"email": "c@test.com", "age": null, "active": true})).unwrap();
+
+        let desc = col.describe(DEFAULT_DESCRIBE_SAMPLE).unwrap();
+        assert_eq!(desc.count, 3);
+        assert_eq!(desc.sampled, 3);
+        let profile = |field: &str, types: &[&str], count: usize| FieldProfile {
+            field: field.to_string(),
+            types: types.iter().map(|t| t.to_string()).collect(),
+            count,
+        };
+        assert_eq!(
+            desc.fields,
+            vec![
+                profile("_id", &["number"], 3),
+                profile("_version", &["number"], 3),
+                profile("active", &["boolean"], 1),
+                profile("address", &["object"], 1),
+                profile("age", &["null", "number", "string"], 3),
+                profile("city", &["string"], 1),
+                profile("email", &["string"], 3),
+                profile("tags", &["array"], 1),
+            ]
+        );
+        let indexes: Vec<(&str, &str)> =
+            desc.indexes.iter().map(|i| (i.name.as_str(), i.index_type.as_str())).collect();
+        assert_eq!(indexes, vec![("email", "unique"), ("city_age", "composite")]);
+
+        // The sample size caps how many documents are profiled
+        let desc = col.describe(2).unwrap();
+        assert_eq!((desc.count, desc.sampled), (3, 2));
+        assert_eq!(col.describe(0).unwrap().fields, vec![]);
+    }
+
     #[test]
     fn unique_index_enforced() {
         let (_dir, mut col) = temp_collection("test");
@@ -2241,6 +3996,7 @@ mod tests {
                 &json!({"email": "alice@test.com"}),
                 &json!({"$set": {"name": "Alicia"}}),
                 None,
+                None,
             )
             .unwrap();
         assert_eq!(ids.len(), 1);
@@ -2260,6 +4016,7 @@ mod tests {
             &json!({"name": "Bob"}),
             &json!({"$set": {"email": "alice@test.com"}}),
             None,
+            None,
         );
         assert!(result.is_err());
 
@@ -2311,6 +4068,7 @@ mod tests {
                 &json!({"status": "draft"}),
                 &json!({"$set": {"status": "published"}}),
                 None,
+                None,
             )
             .unwrap();
         assert_eq!(ids.len(), 2);
@@ -2351,6 +4109,8 @@ mod tests {
             sort: Some(vec![("age".to_string(), SortOrder::Asc)]),
             skip: None,
             limit: None,
+            hint: None,
+            respect_ttl: false,
         };
         let results = col.find_with_options(&json!({}), &opts).unwrap();
         assert_eq!(results[0]["name"], "Alice");
@@ -2369,6 +4129,8 @@ mod tests {
             sort: Some(vec![("age".to_string(), SortOrder::Desc)]),
             skip: None,
             limit: None,
+            hint: None,
+            respect_ttl: false,
         };
         let results = col.find_with_options(&json!({}), &opts).unwrap();
         assert_eq!(results[0]["name"], "Charlie");
@@ -2391,6 +4153,8 @@ mod tests {
             ]),
             skip: None,
             limit: None,
+            hint: None,
+            respect_ttl: false,
         };
         let results = col.find_with_options(&json!({}), &opts).unwrap();
         // eng group sorted by age: Alice(25), Bob(30), Dave(35)
@@ -2412,6 +4176,8 @@ mod tests {
             sort: Some(vec![("n".to_string(), SortOrder::Asc)]),
             skip: Some(3),
             limit: Some(4),
+            hint: None,
+            respect_ttl: false,
         };
         let results = col.find_with_options(&json!({}), &opts).unwrap();
         assert_eq!(results.len(), 4);
@@ -2432,6 +4198,8 @@ mod tests {
             sort: Some(vec![("n".to_string(), SortOrder::Asc)]),
             skip: None,
             limit: Some(3),
+            hint: None,
+            respect_ttl: false,
         };
         let results = col.find_with_options(&json!({}), &opts).unwrap();
         assert_eq!(results.len(), 3);
@@ -2450,6 +4218,8 @@ mod tests {
             sort: Some(vec![("n".to_string(), SortOrder::Asc)]),
             skip: Some(3),
             limit: None,
+            hint: None,
+            respect_ttl: false,
         };
         let results = col.find_with_options(&json!({}), &opts).unwrap();
         assert_eq!(results.len(), 2);
@@ -2495,6 +4265,211 @@ mod tests {
         }
     }
 
+    fn compressed_records(col: &Collection) -> usize {
+        col.storage
+            .iter_active()
+            .unwrap()
+            .iter()
+            .filter(|(_, bytes)| crate::codec::is_compressed(bytes))
+            .count()
+    }
+
+    #[test]
+    fn vector_index_params_survive_reopen() {
+        let dir = tempdir().unwrap();
+        let mut col = Collection::open("vecs", dir.path()).unwrap();
+        col.insert(json!({"name": "a", "embedding": [1.0, 0.0]})).unwrap();
+        let bad = HnswParams { m: 1, ef_construction: 200 };
+        assert!(matches!(
+            col.create_vector_index("embedding", 2, DistanceMetric::Euclidean, bad, false),
+            Err(Error::InvalidQuery(_))
+        ));
+        let params = HnswParams { m: 8, ef_construction: 50 };
+        col.create_vector_index("embedding", 2, DistanceMetric::Euclidean, params, false).unwrap();
+        let vector_info = |col: &Collection| {
+            let info = col.list_indexes().into_iter().find(|i| i.index_type == "vector").unwrap();
+            (info.m, info.ef_construction)
+        };
+        assert_eq!(vector_info(&col), (Some(8), Some(50)));
+        drop(col);
+
+        // From the .vidx cache, then rebuilt from the index metadata
+        let col = Collection::open("vecs", dir.path()).unwrap();
+        assert_eq!(vector_info(&col), (Some(8), Some(50)));
+        assert_eq!(col.vector_indexes["embedding"].params(), params);
+        drop(col);
+        std::fs::remove_file(dir.path().join("vecs.vidx")).unwrap();
+        let col = Collection::open("vecs", dir.path()).unwrap();
+        assert_eq!(col.vector_indexes["embedding"].params(), params);
+        assert_eq!(col.vector_indexes["embedding"].len(), 1);
+    }
+
+    #[test]
+    fn compact_drops_vector_tombstones() {
+        let (_dir, mut col) = temp_collection("vecs");
+        let params = HnswParams { m: 8, ef_construction: 32 };
+        col.create_vector_index("embedding", 2, DistanceMetric::Euclidean, params, false).unwrap();
+        let docs: Vec<Value> = (0..1100)
+            .map(|i| {
+                let group = if i % 10 == 0 { "drop" } else { "keep" };
+                json!({"group": group, "embedding": [(i % 40) as f64, (i / 40) as f64]})
+            })
+            .collect();
+        col.insert_many(docs).unwrap();
+        let removed = col.delete(&json!({"group": "drop"}), None).unwrap();
+        assert_eq!(removed.len(), 110);
+
+        let vector_info = |col: &Collection| {
+            let info = col.list_indexes().into_iter().find(|i| i.index_type == "vector").unwrap();
+            (info.vectors, info.tombstones)
+        };
+        assert_eq!(vector_info(&col), (Some(990), Some(110)));
+        let results = col.vector_search("embedding", &[0.0, 0.0], 50, &VectorSearchOptions::default()).unwrap();
+        assert!(results.iter().all(|d| d["group"] == "keep"));
+
+        col.compact().unwrap();
+        assert_eq!(vector_info(&col), (Some(990), Some(0)));
+        let results = col.vector_search("embedding", &[0.0, 0.0], 50, &VectorSearchOptions::default()).unwrap();
+        let exact = VectorSearchOptions { exact: true, ..Default::default() };
+        let exact = col.vector_search("embedding", &[0.0, 0.0], 50, &exact).unwrap();
+        assert!(results.iter().all(|d| d["group"] == "keep"));
+        assert_eq!(results[0]["_id"], exact[0]["_id"]);
+    }
+
+    #[test]
+    fn text_search_filtered_keeps_matching_docs_in_score_order() {
+        let (_dir, mut col) = temp_collection("articles");
+        let docs = [
+            ("rust", "published", 1),
+            ("rust rust guide", "draft", 2),
+            ("rust tips", "published", 3),
+            ("rust database engine guide", "published", 4),
+            ("python guide", "published", 5),
+            ("rust news", "draft", 6),
+        ];
+        for (title, status, n) in docs {
+            col.insert(json!({"title": title, "status": status, "n": n})).unwrap();
+        }
+        col.create_text_index(vec!["title".into()]).unwrap();
+
+        let titles = |docs: Vec<Value>| -> Vec<String> {
+            docs.iter().map(|d| d["title"].as_str().unwrap().to_string()).collect()
+        };
+        let published = json!({"status": "published"});
+        let expected = ["rust", "rust tips", "rust database engine guide"];
+
+        // Post-filtered without an index, then from the index's candidates
+        assert_eq!(titles(col.text_search_filtered("rust", &published, 10).unwrap()), expected);
+        col.create_index("status").unwrap();
+        let results = col.text_search_filtered("rust", &published, 10).unwrap();
+        let scores: Vec<f64> = results.iter().map(|d| d["_score"].as_f64().unwrap()).collect();
+        assert!(scores.windows(2).all(|w| w[0] >= w[1]), "{scores:?}");
+        assert_eq!(titles(results), expected);
+
+        assert_eq!(titles(col.text_search_filtered("rust", &published, 2).unwrap()), expected[..2]);
+        let recent = json!({"status": "published", "n": {"$gte": 3}});
+        assert_eq!(titles(col.text_search_filtered("rust", &recent, 10).unwrap()), expected[1..]);
+        assert!(col.text_search_filtered("python", &json!({"status": "draft"}), 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn search_counts_match_result_lengths() {
+        let (_dir, mut col) = temp_collection("docs");
+        let docs: Vec<Value> = (0..40)
+            .map(|i| {
+                let title = match i % 4 {
+                    0 => "rust database engine",
+                    1 => "rust web server",
+                    2 => "python notebook",
+                    _ => "database tuning guide",
+                };
+                json!({"title": title, "embedding": [(i % 7) as f64 - 3.0, (i % 5) as f64 - 2.0]})
+            })
+            .collect();
+        col.insert_many(docs).unwrap();
+        col.create_text_index(vec!["title".into()]).unwrap();
+        col.create_vector_index("embedding", 2, DistanceMetric::Cosine, HnswParams::default(), false).unwrap();
+
+        for (query, limit) in [("rust", 100), ("database", 100), ("rust database", 100), ("rust database", 12), ("java", 100)] {
+            let results = col.text_search(query, limit).unwrap();
+            assert_eq!(col.text_search_count(query, limit).unwrap(), results.len(), "{query} / {limit}");
+        }
+
+        for (min_similarity, limit) in [(None, 10), (Some(0.9), 100), (Some(0.5), 100), (Some(0.99), 5)] {
+            let opts = VectorSearchOptions { min_similarity, ..Default::default() };
+            let results = col.vector_search("embedding", &[1.0, 0.5], limit, &opts).unwrap();
+            assert!(results.iter().all(|d| d["_similarity"].as_f64().unwrap() as f32 >= min_similarity.unwrap_or(0.0)));
+            let count = col.vector_search_count("embedding", &[1.0, 0.5], limit, &opts).unwrap();
+            assert_eq!(count, results.len(), "{min_similarity:?} / {limit}");
+        }
+        let opts = VectorSearchOptions { min_similarity: Some(0.9), ..Default::default() };
+        assert!(col.vector_search_count("embedding", &[1.0, 0.5], 100, &opts).unwrap() < 40);
+    }
+
+    #[test]
+    fn compressed_docs_roundtrip() {
+        let dir = tempdir().unwrap();
+        let mut col = Collection::open("zip", dir.path()).unwrap();
+        col.set_compression(true).unwrap();
+
+        let big = json!({"kind": "big", "body": "lorem ipsum ".repeat(50)});
+        let id = col.insert(big.clone()).unwrap();
+        col.insert(json!({"kind": "other", "body": "dolor sit amet ".repeat(50)})).unwrap();
+        col.update(&json!({"kind": "other"}), &json!({"$set": {"n": 1}}), None, None).unwrap();
+        assert_eq!(compressed_records(&col), 2);
+
+        let doc = col.get(id).unwrap().unwrap();
+        assert_eq!(doc["body"], big["body"]);
+        drop(col);
+
+        // The setting and the records survive a reopen
+        let col = Collection::open("zip", dir.path()).unwrap();
+        assert!(col.compression_enabled());
+        assert_eq!(col.get(id).unwrap().unwrap()["body"], big["body"]);
+        assert_eq!(col.count_matching(&json!({"kind": "big"})).unwrap(), 1);
+        assert_eq!(col.find(&json!({"n": 1})).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn compact_rewrites_mixed_records_with_compression() {
+        let dir = tempdir().unwrap();
+        let mut col = Collection::open("mixed", dir.path()).unwrap();
+        for i in 0..5 {
+            let group = if i % 2 == 0 { "even" } else { "odd" };
+            col.insert(json!({"n": i, "group": group, "payload": "abc".repeat(100)})).unwrap();
+        }
+        col.set_compression(true).unwrap();
+        for i in 5..10 {
+            let group = if i % 2 == 0 { "even" } else { "odd" };
+            col.insert(json!({"n": i, "group": group, "payload": "abc".repeat(100)})).unwrap();
+        }
+        assert_eq!(compressed_records(&col), 5);
+        drop(col);
+
+        // A file mixing compressed and uncompressed records loads and queries
+        let mut col = Collection::open("mixed", dir.path()).unwrap();
+        assert_eq!(col.count(), 10);
+        // Plain records match on raw JSONB, compressed ones are decoded first
+        assert_eq!(col.count_matching(&json!({"group": "even"})).unwrap(), 5);
+
+        let stats = col.compact().unwrap();
+        assert_eq!(stats.docs_kept, 10);
+        assert!(stats.new_size < stats.old_size);
+        assert_eq!(compressed_records(&col), 10);
+        drop(col);
+
+        let mut col = Collection::open("mixed", dir.path()).unwrap();
+        let docs = col.find(&json!({})).unwrap();
+        assert_eq!(docs.len(), 10);
+        assert!(docs.iter().all(|d| d["payload"] == "abc".repeat(100)));
+
+        // Disabling compression and compacting converts back to plain JSONB
+        col.set_compression(false).unwrap();
+        col.compact().unwrap();
+        assert_eq!(compressed_records(&col), 0);
+        assert_eq!(col.count(), 10);
+    }
+
     #[test]
     fn composite_index_backed_sort_desc() {
         let (_dir, mut col) = temp_collection("comp_sort");
@@ -2514,6 +4489,8 @@ mod tests {
             sort: Some(vec![("createdAt".into(), SortOrder::Desc)]),
             skip: None,
             limit: Some(2),
+            hint: None,
+            respect_ttl: false,
         };
         let results = col.find_with_options(&json!({"formId": "1"}), &opts).unwrap();
         assert_eq!(results.len(), 2);
@@ -2525,6 +4502,8 @@ mod tests {
             sort: Some(vec![("createdAt".into(), SortOrder::Asc)]),
             skip: None,
             limit: Some(2),
+            hint: None,
+            respect_ttl: false,
         };
         let results_asc = col.find_with_options(&json!({"formId": "1"}), &opts_asc).unwrap();
         assert_eq!(results_asc.len(), 2);
@@ -2536,6 +4515,8 @@ mod tests {
             sort: Some(vec![("createdAt".into(), SortOrder::Desc)]),
             skip: Some(1),
             limit: Some(2),
+            hint: None,
+            respect_ttl: false,
         };
         let results_skip = col.find_with_options(&json!({"formId": "1"}), &opts_skip).unwrap();
         assert_eq!(results_skip.len(), 2);
@@ -2563,6 +4544,8 @@ mod tests {
             sort: Some(vec![("score".into(), SortOrder::Asc)]),
             skip: None,
             limit: Some(2),
+            hint: None,
+            respect_ttl: false,
         };
         let results = col.find_with_options(&json!({"status": "active"}), &opts).unwrap();
         assert_eq!(results.len(), 2);
@@ -2570,6 +4553,179 @@ mod tests {
         assert_eq!(results[1]["name"], "mid");  // score 50
     }
 
+    #[test]
+    fn composite_index_prefix_range_query() {
+        let (_dir, mut col) = temp_collection("comp_range");
+        col.create_composite_index(vec!["formId".into(), "createdAt".into()])
+            .unwrap();
+
+        col.insert(json!({"formId": "1", "createdAt": "2023-12-31", "name": "a"})).unwrap();
+        col.insert(json!({"formId": "1", "createdAt": "2024-01-01", "name": "b"})).unwrap();
+        col.insert(json!({"formId": "1", "createdAt": "2024-03-01", "name": "c"})).unwrap();
+        col.insert(json!({"formId": "1", "createdAt": "2024-07-01", "name": "d"})).unwrap();
+        col.insert(json!({"formId": "2", "createdAt": "2024-03-01", "name": "e"})).unwrap();
+
+        let query = json!({"formId": "1", "createdAt": {"$gte": "2024-01-01", "$lt": "2024-07-01"}});
+        let mut names: Vec<String> = col
+            .find(&query)
+            .unwrap()
+            .iter()
+            .map(|d| d["name"].as_str().unwrap().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["b", "c"]);
+
+        // Range on the sort field with the composite-backed sort path
+        let opts = FindOptions {
+            sort: Some(vec![("createdAt".into(), SortOrder::Desc)]),
+            skip: None,
+            limit: Some(10),
+            hint: None,
+            respect_ttl: false,
+        };
+        let query = json!({"formId": "1", "createdAt": {"$gt": "2024-01-01", "$lte": "2024-07-01"}});
+        let results = col.find_with_options(&query, &opts).unwrap();
+        let names: Vec<&str> = results.iter().map(|d| d["name"].as_str().unwrap()).collect();
+        assert_eq!(names, vec!["d", "c"]);
+
+        let opts_asc = FindOptions {
+            sort: Some(vec![("createdAt".into(), SortOrder::Asc)]),
+            skip: None,
+            limit: Some(1),
+            hint: None,
+            respect_ttl: false,
+        };
+        let results = col.find_with_options(&query, &opts_asc).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["name"], "c");
+    }
+
+    #[test]
+    fn explain_reports_index_vs_collection_scan() {
+        let (_dir, mut col) = temp_collection("explain");
+        col.create_index("status").unwrap();
+        for i in 0..10 {
+            let status = if i % 2 == 0 { "active" } else { "inactive" };
+            col.insert(json!({"status": status, "n": i})).unwrap();
+        }
+
+        let plan = col
+            .explain(&json!({"status": "active"}), &FindOptions::default())
+            .unwrap();
+        assert_eq!(plan["stage"], "index");
+        assert_eq!(plan["indexes"][0]["name"], "status");
+        assert_eq!(plan["indexes"][0]["type"], "field");
+        assert_eq!(plan["estimated_candidates"], 5);
+        assert_eq!(plan["docs_examined"], 5);
+        assert_eq!(plan["post_filter"], false);
+        assert_eq!(plan["n_returned"], 5);
+
+        let plan = col
+            .explain(&json!({"n": {"$gte": 7}}), &FindOptions::default())
+            .unwrap();
+        assert_eq!(plan["stage"], "collection scan");
+        assert_eq!(plan["indexes"].as_array().unwrap().len(), 0);
+        assert_eq!(plan["docs_examined"], 10);
+        assert_eq!(plan["post_filter"], true);
+        assert_eq!(plan["n_returned"], 3);
+
+        // Indexed condition plus an unindexed one still uses the index but post-filters
+        let plan = col
+            .explain(&json!({"status": "active", "n": {"$gte": 4}}), &FindOptions::default())
+            .unwrap();
+        assert_eq!(plan["stage"], "index");
+        assert_eq!(plan["post_filter"], true);
+        assert_eq!(plan["n_returned"], 3);
+
+        let opts = FindOptions {
+            sort: Some(vec![("status".into(), SortOrder::Asc)]),
+            skip: None,
+            limit: Some(2),
+            hint: None,
+            respect_ttl: false,
+        };
+        let plan = col.explain(&json!({}), &opts).unwrap();
+        assert_eq!(plan["stage"], "index sort");
+        assert_eq!(plan["n_returned"], 2);
+    }
+
+    #[test]
+    fn hint_forces_index_or_scan() {
+        let (_dir, mut col) = temp_collection("hint");
+        col.create_index("status").unwrap();
+        col.create_index("region").unwrap();
+        col.create_composite_index(vec!["region".into(), "n".into()]).unwrap();
+        for i in 0..12 {
+            let status = if i % 2 == 0 { "active" } else { "inactive" };
+            let region = if i < 4 { "eu" } else { "us" };
+            col.insert(json!({"status": status, "region": region, "n": i})).unwrap();
+        }
+        let query = json!({"status": "active", "region": "eu"});
+        let hinted = |hint: IndexHint| FindOptions { hint: Some(hint), ..Default::default() };
+        let ns = |docs: Vec<Arc<Value>>| docs.iter().map(|d| d["n"].as_i64().unwrap()).collect::<Vec<_>>();
+        // Unsorted results come back in no particular order
+        let sorted_ns = |docs: Vec<Arc<Value>>| {
+            let mut ns = ns(docs);
+            ns.sort();
+            ns
+        };
+
+        // Each hinted index narrows by its own condition and post-filters the rest
+        for (name, kind, candidates) in [("status", "field", 6), ("region", "field", 4), ("region_n", "composite", 4)] {
+            let opts = hinted(IndexHint::Index(name.into()));
+            let plan = col.explain(&query, &opts).unwrap();
+            assert_eq!(plan["stage"], "index", "{name}");
+            assert_eq!(plan["indexes"], json!([{"name": name, "type": kind}]));
+            assert_eq!(plan["estimated_candidates"], candidates, "{name}");
+            assert_eq!(plan["post_filter"], true);
+            assert_eq!(sorted_ns(col.find_with_options_arcs(&query, &opts).unwrap()), [0, 2]);
+        }
+
+        // $natural scans even though indexes could answer the query
+        let opts = hinted(IndexHint::Natural);
+        let plan = col.explain(&query, &opts).unwrap();
+        assert_eq!(plan["stage"], "collection scan");
+        assert_eq!(plan["docs_examined"], 12);
+        assert_eq!(sorted_ns(col.find_with_options_arcs(&query, &opts).unwrap()), [0, 2]);
+
+        // A hinted index the query doesn't constrain falls back to a scan, with sort/skip/limit applied
+        let opts = FindOptions {
+            sort: Some(vec![("n".into(), SortOrder::Desc)]),
+            skip: Some(1),
+            limit: Some(2),
+            hint: Some(IndexHint::Index("status".into())),
+            respect_ttl: false,
+        };
+        let plan = col.explain(&json!({"region": "us"}), &opts).unwrap();
+        assert_eq!(plan["stage"], "collection scan");
+        assert_eq!(ns(col.find_with_options_arcs(&json!({"region": "us"}), &opts).unwrap()), [10, 9]);
+
+        let err = col.find_with_options_arcs(&query, &hinted(IndexHint::Index("email".into()))).unwrap_err();
+        assert!(matches!(err, Error::IndexNotFound(ref name) if name == "email"), "{err}");
+    }
+
+    #[test]
+    fn explain_reports_composite_index() {
+        let (_dir, mut col) = temp_collection("explain_comp");
+        col.create_composite_index(vec!["formId".into(), "createdAt".into()])
+            .unwrap();
+        col.insert(json!({"formId": "1", "createdAt": "2024-01-01"})).unwrap();
+        col.insert(json!({"formId": "1", "createdAt": "2024-05-01"})).unwrap();
+        col.insert(json!({"formId": "2", "createdAt": "2024-05-01"})).unwrap();
+
+        let plan = col
+            .explain(
+                &json!({"formId": "1", "createdAt": {"$gte": "2024-02-01"}}),
+                &FindOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(plan["stage"], "index");
+        assert_eq!(plan["indexes"][0]["name"], "formId_createdAt");
+        assert_eq!(plan["indexes"][0]["type"], "composite");
+        assert_eq!(plan["estimated_candidates"], 1);
+        assert_eq!(plan["n_returned"], 1);
+    }
+
     #[test]
     fn composite_index_sort_with_post_filter() {
         // Test that composite index sort works with extra query conditions
@@ -2590,6 +4746,8 @@ mod tests {
             sort: Some(vec![("createdAt".into(), SortOrder::Desc)]),
             skip: None,
             limit: Some(2),
+            hint: None,
+            respect_ttl: false,
         };
         let query = json!({"$and": [{"formId": "1"}, {"data.level": "Junior"}]});
         let results = col.find_with_options(&query, &opts).unwrap();
@@ -2598,4 +4756,558 @@ mod tests {
         assert_eq!(results[0]["createdAt"], "2024-01-05");
         assert_eq!(results[1]["createdAt"], "2024-01-03");
     }
+
+    #[test]
+    fn lazy_mode_reads_documents_from_storage() {
+        let dir = tempdir().unwrap();
+        let mut col = Collection::open("lazy", dir.path()).unwrap();
+        col.set_doc_cache_limit(Some(3)).unwrap();
+
+        let mut ids = Vec::new();
+        for i in 0..20 {
+            let group = if i % 2 == 0 { "even" } else { "odd" };
+            ids.push(col.insert(json!({"n": i, "group": group})).unwrap());
+        }
+        assert!(col.doc_cache.len() <= 3);
+
+        for (i, &id) in ids.iter().enumerate() {
+            assert_eq!(col.get(id).unwrap().unwrap()["n"], i);
+        }
+        assert!(col.doc_cache.len() <= 3);
+
+        // Index builds and indexed queries read evicted docs from disk
+        col.create_index("group").unwrap();
+        col.create_index("n").unwrap();
+        assert_eq!(col.find(&json!({"group": "even"})).unwrap().len(), 10);
+        assert_eq!(col.find(&json!({})).unwrap().len(), 20);
+        assert_eq!(col.find_one(&json!({"n": 7})).unwrap().unwrap()["group"], "odd");
+        let opts = FindOptions {
+            sort: Some(vec![("n".to_string(), SortOrder::Desc)]),
+            skip: None,
+            limit: Some(2),
+            hint: None,
+            respect_ttl: false,
+        };
+        let top = col.find_with_options(&json!({}), &opts).unwrap();
+        assert_eq!(top[0]["n"], 19);
+        assert_eq!(top[1]["n"], 18);
+
+        col.update(&json!({"group": "odd"}), &json!({"$set": {"tag": "x"}}), Some(2), None).unwrap();
+        assert_eq!(col.find(&json!({"tag": "x"})).unwrap().len(), 2);
+        col.delete(&json!({"group": "even"}), None).unwrap();
+        assert_eq!(col.count(), 10);
+        assert!(col.get(ids[0]).unwrap().is_none());
+        assert!(col.doc_cache.len() <= 3);
+        drop(col);
+
+        // The limit is persisted and a reopened collection stays lazy
+        let col = Collection::open("lazy", dir.path()).unwrap();
+        assert_eq!(col.doc_cache_limit(), Some(3));
+        assert!(col.doc_cache().is_none());
+        assert!(col.doc_cache.len() <= 3);
+        assert_eq!(col.find(&json!({"group": "odd"})).unwrap().len(), 10);
+        assert_eq!(col.get(ids[1]).unwrap().unwrap()["n"], 1);
+    }
+
+    #[test]
+    fn switching_back_to_full_cache_loads_all_docs() {
+        let dir = tempdir().unwrap();
+        let mut col = Collection::open("lazyfull", dir.path()).unwrap();
+        col.set_doc_cache_limit(Some(1)).unwrap();
+        for i in 0..5 {
+            col.insert(json!({"n": i})).unwrap();
+        }
+        assert_eq!(col.doc_cache.len(), 1);
+
+        col.set_doc_cache_limit(None).unwrap();
+        assert_eq!(col.doc_cache_limit(), None);
+        assert_eq!(col.doc_cache().unwrap().len(), 5);
+        drop(col);
+
+        let col = Collection::open("lazyfull", dir.path()).unwrap();
+        assert_eq!(col.doc_cache().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn index_bloom_survives_reopen_and_compact() {
+        let dir = tempdir().unwrap();
+        let mut col = Collection::open("bloom", dir.path()).unwrap();
+        for i in 0..20 {
+            col.insert(json!({"sku": format!("sku-{i}")})).unwrap();
+        }
+        assert!(col.set_index_bloom("sku", true).is_err());
+        col.create_index("sku").unwrap();
+        col.set_index_bloom("sku", true).unwrap();
+        assert!(col.find(&json!({"sku": "missing"})).unwrap().is_empty());
+        assert_eq!(col.find(&json!({"sku": "sku-3"})).unwrap().len(), 1);
+        drop(col);
+
+        let mut col = Collection::open("bloom", dir.path()).unwrap();
+        assert!(col.list_indexes().iter().any(|i| i.name == "sku" && i.bloom));
+        let missing = IndexValue::from_json(&json!("missing"));
+        assert!(!col.field_indexes()["sku"].may_contain(&missing));
+        assert_eq!(col.find(&json!({"sku": "sku-7"})).unwrap().len(), 1);
+
+        // Compaction rebuilds the filter without deleted values
+        col.delete(&json!({"sku": "sku-7"}), None).unwrap();
+        let deleted = IndexValue::from_json(&json!("sku-7"));
+        assert!(col.field_indexes()["sku"].may_contain(&deleted));
+        col.compact().unwrap();
+        assert!(!col.field_indexes()["sku"].may_contain(&deleted));
+        assert_eq!(col.find(&json!({"sku": "sku-8"})).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn descending_index_serves_desc_sort_and_survives_reopen() {
+        let dir = tempdir().unwrap();
+        let mut col = Collection::open("events", dir.path()).unwrap();
+        for day in 1..=20 {
+            col.insert(json!({"created_at": format!("2024-01-{day:02}")})).unwrap();
+        }
+        assert!(col.set_index_descending("created_at", true).is_err());
+        col.create_descending_index("created_at").unwrap();
+
+        let newest_first = FindOptions {
+            sort: Some(vec![("created_at".into(), SortOrder::Desc)]),
+            skip: None,
+            limit: Some(3),
+            hint: None,
+            respect_ttl: false,
+        };
+        let plan = col.explain(&json!({}), &newest_first).unwrap();
+        assert_eq!(plan["stage"], "index sort");
+        assert_eq!(plan["direction"], "forward");
+        assert_eq!(plan["docs_examined"], 3);
+        let docs = col.find_with_options(&json!({}), &newest_first).unwrap();
+        let dates: Vec<&str> = docs.iter().map(|d| d["created_at"].as_str().unwrap()).collect();
+        assert_eq!(dates, ["2024-01-20", "2024-01-19", "2024-01-18"]);
+
+        // The opposite order still uses the index, walking it backward
+        let oldest_first = FindOptions {
+            sort: Some(vec![("created_at".into(), SortOrder::Asc)]),
+            ..newest_first.clone()
+        };
+        let plan = col.explain(&json!({}), &oldest_first).unwrap();
+        assert_eq!(plan["direction"], "backward");
+        assert_eq!(plan["docs_examined"], 3);
+
+        // Without a sort, a limited range scan follows the index's direction
+        let before_10th = json!({"created_at": {"$lt": "2024-01-10"}});
+        let first_3 = FindOptions { sort: None, ..newest_first.clone() };
+        let docs = col.find_with_options(&before_10th, &first_3).unwrap();
+        let dates: Vec<&str> = docs.iter().map(|d| d["created_at"].as_str().unwrap()).collect();
+        assert_eq!(dates, ["2024-01-09", "2024-01-08", "2024-01-07"]);
+        drop(col);
+
+        let mut col = Collection::open("events", dir.path()).unwrap();
+        assert!(col.list_indexes().iter().any(|i| i.name == "created_at" && i.descending));
+        let plan = col.explain(&json!({}), &newest_first).unwrap();
+        assert_eq!(plan["direction"], "forward");
+
+        col.set_index_descending("created_at", false).unwrap();
+        let docs = col.find_with_options(&before_10th, &first_3).unwrap();
+        let dates: Vec<&str> = docs.iter().map(|d| d["created_at"].as_str().unwrap()).collect();
+        assert_eq!(dates, ["2024-01-01", "2024-01-02", "2024-01-03"]);
+        drop(col);
+        let col = Collection::open("events", dir.path()).unwrap();
+        assert!(col.list_indexes().iter().all(|i| !i.descending));
+    }
+
+    #[test]
+    fn sparse_index_skips_null_and_missing_fields() {
+        let dir = tempdir().unwrap();
+        let mut col = Collection::open("users", dir.path()).unwrap();
+        col.insert(json!({"name": "a", "ref": "alice"})).unwrap();
+        col.insert(json!({"name": "b", "ref": "bob"})).unwrap();
+        col.insert(json!({"name": "c", "ref": null})).unwrap();
+        col.insert(json!({"name": "d"})).unwrap();
+        col.create_sparse_index("ref").unwrap();
+        assert_eq!(col.field_indexes()["ref"].count_all(), 2);
+
+        let plan = col.explain(&json!({"ref": "alice"}), &FindOptions::default()).unwrap();
+        assert_eq!(plan["stage"], "index");
+        assert_eq!(plan["n_returned"], 1);
+        assert_eq!(col.count_matching(&json!({"ref": {"$in": ["alice", "bob"]}})).unwrap(), 2);
+
+        // Conditions a null field satisfies bypass the index
+        for (query, expected) in [
+            (json!({"ref": null}), vec!["c"]),
+            (json!({"ref": {"$exists": false}}), vec!["d"]),
+            (json!({"ref": {"$ne": "alice"}}), vec!["b", "c"]),
+            (json!({"ref": {"$in": ["bob", null]}}), vec!["b", "c"]),
+        ] {
+            let plan = col.explain(&query, &FindOptions::default()).unwrap();
+            assert_eq!(plan["stage"], "collection scan", "{query}");
+            let docs = col.find(&query).unwrap();
+            let mut names: Vec<&str> = docs.iter().map(|d| d["name"].as_str().unwrap()).collect();
+            names.sort();
+            assert_eq!(names, expected, "{query}");
+            assert_eq!(col.count_matching(&query).unwrap(), expected.len(), "{query}");
+        }
+
+        // Sorting on the field keeps the null document
+        let by_ref = FindOptions {
+            sort: Some(vec![("ref".into(), SortOrder::Asc)]),
+            ..FindOptions::default()
+        };
+        let plan = col.explain(&json!({"name": {"$in": ["a", "c"]}}), &by_ref).unwrap();
+        assert_ne!(plan["stage"], "index sort");
+        assert_eq!(plan["n_returned"], 2);
+
+        drop(col);
+
+        let mut col = Collection::open("users", dir.path()).unwrap();
+        assert!(col.list_indexes().iter().any(|i| i.name == "ref" && i.sparse));
+        assert!(col.field_indexes()["ref"].sparse);
+
+        // Updates keep nulls out of the index
+        col.update(&json!({"name": "a"}), &json!({"$set": {"ref": null}}), None, None).unwrap();
+        col.update(&json!({"name": "d"}), &json!({"$set": {"ref": "dave"}}), None, None).unwrap();
+        assert_eq!(col.field_indexes()["ref"].count_all(), 2);
+        assert_eq!(col.find(&json!({"ref": "dave"})).unwrap().len(), 1);
+        assert_eq!(col.find(&json!({"ref": null})).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn geo_matches_are_updated_without_their_distance() {
+        let dir = tempdir().unwrap();
+        let mut col = Collection::open("places", dir.path()).unwrap();
+        col.insert(json!({"name": "louvre", "location": [48.8606, 2.3376]})).unwrap();
+        col.insert(json!({"name": "orsay", "location": [48.8600, 2.3266]})).unwrap();
+        col.create_geo_index("location").unwrap();
+        let near = json!({"location": {"$near": {"point": [48.8566, 2.3522]}}});
+
+        let opts = FindAndModifyOptions { return_new: true, ..FindAndModifyOptions::default() };
+        let after = col.find_and_modify(&near, Some(&json!({"$set": {"visited": "yes"}})), &opts).unwrap().unwrap();
+        assert_eq!(after["name"], "louvre");
+        assert!(after.get("_distance").is_none());
+
+        let stored = col.find(&json!({"visited": "yes"})).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert!(stored[0].get("_distance").is_none());
+
+        // A bulk update can't match on distance, so it has nothing to write back
+        let op = BulkOp::Update { query: near.clone(), update: json!({"$set": {"open": "yes"}}) };
+        assert!(col.bulk_write(vec![op]).is_err());
+        assert!(col.find(&json!({"open": "yes"})).unwrap().is_empty());
+    }
+
+    #[test]
+    fn geo_index_serves_near_and_within_queries() {
+        let dir = tempdir().unwrap();
+        let mut col = Collection::open("places", dir.path()).unwrap();
+        col.insert(json!({"name": "louvre", "kind": "museum", "location": {"lat": 48.8606, "lng": 2.3376}})).unwrap();
+        col.insert(json!({"name": "orsay", "kind": "museum", "location": {"lat": 48.8600, "lng": 2.3266}})).unwrap();
+        col.insert(json!({"name": "flore", "kind": "cafe", "location": [48.8541, 2.3326]})).unwrap();
+        col.insert(json!({"name": "tate", "kind": "museum", "location": {"lat": 51.5076, "lng": -0.0994}})).unwrap();
+        col.insert(json!({"name": "nowhere", "kind": "museum"})).unwrap();
+
+        let near = json!({"location": {"$near": {"point": [48.8566, 2.3522], "maxDistance": 5000}}});
+        assert!(col.find(&near).is_err());
+        col.create_geo_index("location").unwrap();
+
+        let names = |docs: &[Value]| docs.iter().map(|d| d["name"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+        let docs = col.find(&near).unwrap();
+        assert_eq!(names(&docs), ["louvre", "flore", "orsay"]);
+        let distances: Vec<f64> = docs.iter().map(|d| d["_distance"].as_f64().unwrap()).collect();
+        assert!(distances.windows(2).all(|w| w[0] <= w[1]));
+        assert!(distances.iter().all(|d| *d <= 5000.0));
+
+        // Other conditions post-filter; limit keeps the nearest
+        let museums = json!({
+            "location": {"$near": {"point": [48.8566, 2.3522]}},
+            "kind": "museum",
+        });
+        let opts = FindOptions { limit: Some(3), ..FindOptions::default() };
+        let docs = col.find_with_options(&museums, &opts).unwrap();
+        assert_eq!(names(&docs), ["louvre", "orsay", "tate"]);
+        let plan = col.explain(&museums, &FindOptions { limit: Some(1), ..FindOptions::default() }).unwrap();
+        assert_eq!(plan["stage"], "geo");
+        assert_eq!(plan["indexes"], json!([{"name": "_geo_location", "type": "geo"}]));
+        assert_eq!(plan["n_returned"], 1);
+
+        let within = json!({"location": {"$geoWithin": {"center": [51.5, -0.1], "radius": 2000}}});
+        assert_eq!(names(&col.find(&within).unwrap()), ["tate"]);
+        let in_box = json!({"location": {"$geoWithin": {"box": [[48.85, 2.33], [48.87, 2.34]]}}});
+        let docs = col.find(&in_box).unwrap();
+        assert_eq!(names(&docs), ["louvre", "flore"]);
+        assert!(docs[0].get("_distance").is_none());
+        assert_eq!(col.count_matching(&in_box).unwrap(), 2);
+        assert_eq!(col.find_one(&near).unwrap().unwrap()["name"], "louvre");
+
+        // Moving a document re-indexes it; the index is rebuilt on reopen
+        col.update(&json!({"name": "tate"}), &json!({"$set": {"location": [48.8570, 2.3530]}}), None, None).unwrap();
+        drop(col);
+        let col = Collection::open("places", dir.path()).unwrap();
+        assert!(col.list_indexes().iter().any(|i| i.name == "_geo_location" && i.index_type == "geo"));
+        assert_eq!(names(&col.find(&near).unwrap()), ["tate", "louvre", "flore", "orsay"]);
+        assert!(col.count_matching(&json!({"$or": [{"location": {"$near": {"point": [0, 0]}}}]})).is_err());
+    }
+
+    fn synthetic_collection(dir: &Path, name: &str) -> Collection {
+        let mut col = Collection::open(name, dir).unwrap();
+        let docs: Vec<Value> = (0..5000)
+            .map(|i| {
+                let color = ["red", "green", "blue"][i % 3];
+                json!({"n": i, "color": color, "tag": format!("t{}", i % 7)})
+            })
+            .collect();
+        col.insert_many(docs).unwrap();
+        col.delete(&json!({"tag": "t0"}), None).unwrap();
+        col
+    }
+
+    #[test]
+    fn parallel_scan_matches_sequential() {
+        let dir = tempdir().unwrap();
+        let col = synthetic_collection(dir.path(), "par");
+        let queries = [
+            json!({"color": "red"}),
+            json!({"color": {"$in": ["green", "blue"]}, "tag": "t3"}),
+            json!({"$or": [{"tag": "t1"}, {"color": "blue"}]}),
+            json!({"color": "purple"}),
+        ];
+        for q in &queries {
+            let query = query::parse_query(q).unwrap();
+            let sequential = col.count_with_scan_using(&query, 1).unwrap();
+            for workers in [2, 3, 8] {
+                assert_eq!(col.count_with_scan_using(&query, workers).unwrap(), sequential);
+                let found = col.scan_matching(&query, workers).unwrap();
+                assert_eq!(found.len(), sequential);
+                // Chunk-order concatenation keeps the sequential scan order
+                let mut seq_ids = Vec::new();
+                col.for_each_doc_arc_while(|id, arc| {
+                    if query::matches_value(&query, arc) {
+                        seq_ids.push(id);
+                    }
+                    Ok(true)
+                })
+                .unwrap();
+                let par_ids: Vec<u64> =
+                    found.iter().map(|d| d["_id"].as_u64().unwrap()).collect();
+                assert_eq!(par_ids, seq_ids);
+            }
+        }
+        assert_eq!(col.count_matching(&json!({"color": "red"})).unwrap(), {
+            let query = query::parse_query(&json!({"color": "red"})).unwrap();
+            col.count_with_scan_using(&query, 4).unwrap()
+        });
+    }
+
+    #[test]
+    fn parallel_scan_reads_from_storage_in_lazy_mode() {
+        let dir = tempdir().unwrap();
+        let mut col = synthetic_collection(dir.path(), "parlazy");
+        col.set_doc_cache_limit(Some(10)).unwrap();
+        let query = query::parse_query(&json!({"tag": "t5", "color": "green"})).unwrap();
+        let mut expected: Vec<u64> = Vec::new();
+        col.for_each_doc_arc_while(|id, arc| {
+            if query::matches_value(&query, arc) {
+                expected.push(id);
+            }
+            Ok(true)
+        })
+        .unwrap();
+        assert!(!expected.is_empty());
+        let found: Vec<u64> = col
+            .scan_matching(&query, 4)
+            .unwrap()
+            .iter()
+            .map(|d| d["_id"].as_u64().unwrap())
+            .collect();
+        assert_eq!(found, expected);
+        assert!(col.doc_cache.len() <= 10);
+    }
+
+    #[test]
+    fn schema_validates_inserts_and_updates() {
+        let dir = tempdir().unwrap();
+        let mut col = Collection::open("users", dir.path()).unwrap();
+        let schema = json!({
+            "type": "object",
+            "required": ["name"],
+            "properties": {"name": {"type": "string"}, "age": {"type": "integer", "maximum": 40}}
+        });
+        col.set_schema(Some(CollectionSchema::new(schema, ValidationLevel::Strict, false).unwrap())).unwrap();
+
+        col.insert(json!({"name": "ann", "age": 30})).unwrap();
+        col.insert(json!({"name": "bob", "age": 40})).unwrap();
+        let err = col.insert(json!({"age": 20})).unwrap_err();
+        assert!(matches!(err, Error::ValidationFailed(ref msg) if msg == "document: missing required field 'name'"), "{err}");
+        assert!(matches!(
+            col.insert_many(vec![json!({"name": "cy"}), json!({"name": 5})]),
+            Err(Error::ValidationFailed(_))
+        ));
+        assert_eq!(col.count(), 2);
+
+        // bob would go over the maximum, so neither document changes
+        let err = col.update(&json!({}), &json!({"$inc": {"age": 1}}), None, None).unwrap_err();
+        assert!(matches!(err, Error::ValidationFailed(_)), "{err}");
+        let ages = |col: &Collection| {
+            let mut docs = col.find(&json!({})).unwrap();
+            docs.sort_by_key(|d| d["_id"].as_u64());
+            docs.iter().map(|d| d["age"].clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(ages(&col), vec![json!(30), json!(40)]);
+        col.update(&json!({"name": "ann"}), &json!({"$inc": {"age": 1}}), None, None).unwrap();
+        assert_eq!(ages(&col), vec![json!(31), json!(40)]);
+
+        // The schema survives a reopen
+        drop(col);
+        let mut col = Collection::open("users", dir.path()).unwrap();
+        assert!(col.schema().is_some());
+        assert!(matches!(col.insert(json!({"name": "dan", "age": 99})), Err(Error::ValidationFailed(_))));
+
+        // Warn level writes the document anyway; coercion converts values
+        let schema = col.schema().unwrap().schema.clone();
+        col.set_schema(Some(CollectionSchema::new(schema, ValidationLevel::Warn, true).unwrap())).unwrap();
+        let id = col.insert(json!({"name": "dan", "age": "99"})).unwrap();
+        assert_eq!(col.get(id).unwrap().unwrap()["age"], json!(99));
+
+        col.set_schema(None).unwrap();
+        col.insert(json!({"age": 1})).unwrap();
+        assert_eq!(col.count(), 4);
+    }
+
+    #[test]
+    fn defaults_fill_omitted_fields_on_insert() {
+        let dir = tempdir().unwrap();
+        let mut col = Collection::open("orders", dir.path()).unwrap();
+        let defaults = FieldDefaults::new(json!({"status": "new", "created_at": "$now", "ref": "$uuid"})).unwrap();
+        col.set_defaults(defaults).unwrap();
+        col.create_unique_index("ref").unwrap();
+
+        let a = col.insert(json!({"item": "pen"})).unwrap();
+        let b = col.insert(json!({"item": "ink", "status": "paid", "ref": "r-1"})).unwrap();
+        let ids = col.insert_many(vec![json!({"item": "cap"}), json!({"item": "nib"})]).unwrap();
+
+        let a = col.get(a).unwrap().unwrap();
+        assert_eq!(a["status"], "new");
+        assert!(chrono::DateTime::parse_from_rfc3339(a["created_at"].as_str().unwrap()).is_ok());
+        assert_eq!(a["ref"].as_str().unwrap().len(), 36);
+        let b = col.get(b).unwrap().unwrap();
+        assert_eq!((&b["status"], &b["ref"]), (&json!("paid"), &json!("r-1")));
+        let refs: HashSet<Value> = ids.iter().map(|&id| col.get(id).unwrap().unwrap()["ref"].clone()).collect();
+        assert_eq!(refs.len(), 2);
+
+        // Defaults are applied before unique checks
+        col.set_defaults(FieldDefaults::new(json!({"ref": "fixed"})).unwrap()).unwrap();
+        col.insert(json!({"item": "pad"})).unwrap();
+        assert!(matches!(col.insert(json!({"item": "pad"})), Err(Error::UniqueViolation { .. })));
+
+        drop(col);
+        let mut col = Collection::open("orders", dir.path()).unwrap();
+        assert_eq!(col.defaults(), &FieldDefaults::new(json!({"ref": "fixed"})).unwrap());
+        col.set_defaults(FieldDefaults::default()).unwrap();
+        let id = col.insert(json!({"item": "pad"})).unwrap();
+        assert!(col.get(id).unwrap().unwrap().get("ref").is_none());
+    }
+
+    #[test]
+    fn find_and_modify_returns_requested_state() {
+        let dir = tempdir().unwrap();
+        let mut col = Collection::open("jobs", dir.path()).unwrap();
+        for (name, priority) in [("a", 2), ("b", 5), ("c", 1)] {
+            col.insert(json!({"name": name, "priority": priority, "state": "pending"})).unwrap();
+        }
+        let claim = json!({"$set": {"state": "claimed"}});
+        let by_priority = FindAndModifyOptions {
+            sort: Some(vec![("priority".into(), SortOrder::Desc)]),
+            ..FindAndModifyOptions::default()
+        };
+
+        // Highest priority first, returned as it was before the update
+        let before = col.find_and_modify(&json!({"state": "pending"}), Some(&claim), &by_priority).unwrap().unwrap();
+        assert_eq!((&before["name"], &before["state"], &before["_version"]), (&json!("b"), &json!("pending"), &json!(1)));
+        assert_eq!(col.get(before["_id"].as_u64().unwrap()).unwrap().unwrap()["state"], "claimed");
+
+        let opts = FindAndModifyOptions { return_new: true, ..by_priority.clone() };
+        let after = col.find_and_modify(&json!({"state": "pending"}), Some(&claim), &opts).unwrap().unwrap();
+        assert_eq!((&after["name"], &after["state"], &after["_version"]), (&json!("a"), &json!("claimed"), &json!(2)));
+
+        // Claim and delete
+        let opts = FindAndModifyOptions { remove: true, ..FindAndModifyOptions::default() };
+        let removed = col.find_and_modify(&json!({"state": "pending"}), None, &opts).unwrap().unwrap();
+        assert_eq!(removed["name"], "c");
+        assert_eq!(col.count(), 2);
+        assert!(col.find_and_modify(&json!({"state": "pending"}), None, &opts).unwrap().is_none());
+
+        assert!(matches!(
+            col.find_and_modify(&json!({}), Some(&claim), &opts),
+            Err(Error::InvalidQuery(_))
+        ));
+        assert!(matches!(
+            col.find_and_modify(&json!({}), None, &FindAndModifyOptions::default()),
+            Err(Error::InvalidQuery(_))
+        ));
+    }
+
+    #[test]
+    fn bulk_write_applies_mixed_ops_in_order() {
+        let dir = tempdir().unwrap();
+        let mut col = Collection::open("notes", dir.path()).unwrap();
+        col.create_unique_index("slug").unwrap();
+        let a = col.insert(json!({"slug": "a", "state": "draft"})).unwrap();
+        let b = col.insert(json!({"slug": "b", "state": "draft"})).unwrap();
+
+        let results = col
+            .bulk_write(vec![
+                BulkOp::Insert(json!({"slug": "c", "state": "draft"})),
+                // Sees the document inserted above
+                BulkOp::Update { query: json!({"state": "draft"}), update: json!({"$set": {"state": "live"}}) },
+                BulkOp::Delete { query: json!({"slug": "b"}) },
+                // b's slug is free again once b is gone
+                BulkOp::Update { query: json!({"slug": "a"}), update: json!({"$set": {"slug": "b"}}) },
+            ])
+            .unwrap();
+        let c = b + 1;
+        assert_eq!(
+            results,
+            [
+                BulkOpResult::Inserted(c),
+                BulkOpResult::Modified(vec![a, b, c]),
+                BulkOpResult::Deleted(vec![b]),
+                BulkOpResult::Modified(vec![a]),
+            ]
+        );
+
+        let doc = col.get(a).unwrap().unwrap();
+        assert_eq!((&doc["slug"], &doc["state"], &doc["_version"]), (&json!("b"), &json!("live"), &json!(3)));
+        assert!(col.get(b).unwrap().is_none());
+        assert_eq!(col.get(c).unwrap().unwrap()["state"], "live");
+        assert_eq!(col.find(&json!({"slug": "b"})).unwrap().len(), 1);
+        assert_eq!(col.insert(json!({"slug": "d"})).unwrap(), c + 1);
+
+        drop(col);
+        let col = Collection::open("notes", dir.path()).unwrap();
+        assert_eq!(col.count(), 3);
+        assert_eq!(col.get(a).unwrap().unwrap()["slug"], "b");
+    }
+
+    #[test]
+    fn bulk_write_failure_leaves_collection_unchanged() {
+        let dir = tempdir().unwrap();
+        let mut col = Collection::open("notes", dir.path()).unwrap();
+        col.create_unique_index("slug").unwrap();
+        let a = col.insert(json!({"slug": "a", "state": "draft"})).unwrap();
+
+        let unique_clash = vec![
+            BulkOp::Update { query: json!({"slug": "a"}), update: json!({"$set": {"state": "live"}}) },
+            BulkOp::Insert(json!({"slug": "x"})),
+            BulkOp::Insert(json!({"slug": "x"})),
+        ];
+        assert!(matches!(col.bulk_write(unique_clash), Err(Error::UniqueViolation { .. })));
+
+        let bad_update = vec![
+            BulkOp::Insert(json!({"slug": "y"})),
+            BulkOp::Delete { query: json!({"slug": "a"}) },
+            BulkOp::Update { query: json!({}), update: json!({"$bogus": {"n": 1}}) },
+        ];
+        assert!(col.bulk_write(bad_update).is_err());
+
+        assert_eq!(col.count(), 1);
+        let doc = col.get(a).unwrap().unwrap();
+        assert_eq!((&doc["state"], &doc["_version"]), (&json!("draft"), &json!(1)));
+        assert_eq!(col.insert(json!({"slug": "x"})).unwrap(), a + 1);
+    }
 }
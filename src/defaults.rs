@@ -0,0 +1,142 @@
+use serde_json::{Map, Value};
+
+use crate::error::{Error, Result};
+
+/// Default stored as the insert time, an RFC 3339 string.
+const NOW: &str = "$now";
+
+/// Default stored as a random (version 4) UUID string.
+const UUID: &str = "$uuid";
+
+/// Values filled in for fields an inserted document omits, keyed by field
+/// path (dot notation reaches into nested objects). A default is a literal
+/// JSON value, `"$now"`, or `"$uuid"`; other strings starting with `$` are
+/// reserved.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct FieldDefaults(Map<String, Value>);
+
+impl FieldDefaults {
+    /// Check a defaults spec: an object mapping field paths to defaults.
+    pub fn new(spec: Value) -> Result<Self> {
+        let Value::Object(fields) = spec else {
+            return Err(Error::InvalidSchema("defaults must be an object of field paths to values".into()));
+        };
+        for (path, default) in &fields {
+            if path.is_empty() || path.split('.').any(str::is_empty) {
+                return Err(Error::InvalidSchema(format!("invalid default field path '{path}'")));
+            }
+            if path == "_id" || path == "_version" {
+                return Err(Error::InvalidSchema(format!("'{path}' is assigned by the database")));
+            }
+            if let Some(s) = default.as_str().filter(|s| s.starts_with('$') && *s != NOW && *s != UUID) {
+                return Err(Error::InvalidSchema(format!(
+                    "unknown default '{s}' for '{path}' (expected {NOW}, {UUID}, or a literal)"
+                )));
+            }
+        }
+        Ok(Self(fields))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Fill in each field `doc` doesn't have. A field present with any
+    /// value, null included, is left alone, as is one whose parent isn't an
+    /// object. Every `$now` in one call gets the same instant.
+    pub fn apply(&self, doc: &mut Value) {
+        let now = chrono::Utc::now().to_rfc3339();
+        for (path, default) in &self.0 {
+            let Some((parent, key)) = parent_object(doc, path) else {
+                continue;
+            };
+            if parent.contains_key(key) {
+                continue;
+            }
+            let value = match default.as_str() {
+                Some(NOW) => Value::String(now.clone()),
+                Some(UUID) => Value::String(new_uuid()),
+                _ => default.clone(),
+            };
+            parent.insert(key.to_string(), value);
+        }
+    }
+}
+
+/// The object that holds the last part of `path` in `doc`, creating missing
+/// parent objects, and that last part. `None` if a parent isn't an object.
+fn parent_object<'a, 'p>(doc: &'a mut Value, path: &'p str) -> Option<(&'a mut Map<String, Value>, &'p str)> {
+    let (parents, key) = match path.rsplit_once('.') {
+        Some((parents, key)) => (Some(parents), key),
+        None => (None, path),
+    };
+    let mut current = doc.as_object_mut()?;
+    for part in parents.into_iter().flat_map(|p| p.split('.')) {
+        current = current
+            .entry(part.to_string())
+            .or_insert_with(|| Value::Object(Map::new()))
+            .as_object_mut()?;
+    }
+    Some((current, key))
+}
+
+/// A random version 4 UUID in its hyphenated lowercase form.
+fn new_uuid() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn is_uuid_v4(s: &str) -> bool {
+        let parts: Vec<&str> = s.split('-').collect();
+        parts.iter().map(|p| p.len()).eq([8, 4, 4, 4, 12])
+            && s.chars().all(|c| c == '-' || (c.is_ascii_hexdigit() && !c.is_ascii_uppercase()))
+            && parts[2].starts_with('4')
+            && parts[3].starts_with(['8', '9', 'a', 'b'])
+    }
+
+    #[test]
+    fn fills_only_omitted_fields() {
+        let defaults = FieldDefaults::new(json!({
+            "status": "new",
+            "tags": [],
+            "meta.source": "api",
+            "note": "x"
+        }))
+        .unwrap();
+        let mut doc = json!({"status": "done", "note": null, "meta": {"owner": "ann"}});
+        defaults.apply(&mut doc);
+        assert_eq!(doc, json!({"status": "done", "tags": [], "note": null, "meta": {"owner": "ann", "source": "api"}}));
+
+        // A parent that isn't an object is left alone
+        let mut doc = json!({"meta": 5});
+        defaults.apply(&mut doc);
+        assert_eq!(doc["meta"], json!(5));
+    }
+
+    #[test]
+    fn generates_now_and_uuid() {
+        let defaults = FieldDefaults::new(json!({"created_at": "$now", "ref": "$uuid"})).unwrap();
+        let (mut a, mut b) = (json!({}), json!({}));
+        defaults.apply(&mut a);
+        defaults.apply(&mut b);
+        assert!(chrono::DateTime::parse_from_rfc3339(a["created_at"].as_str().unwrap()).is_ok());
+        assert!(is_uuid_v4(a["ref"].as_str().unwrap()), "{}", a["ref"]);
+        assert_ne!(a["ref"], b["ref"]);
+    }
+
+    #[test]
+    fn rejects_bad_specs() {
+        for spec in [json!([]), json!({"x": "$today"}), json!({"_id": 1}), json!({"a..b": 1})] {
+            assert!(matches!(FieldDefaults::new(spec), Err(Error::InvalidSchema(_))));
+        }
+    }
+}
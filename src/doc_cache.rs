@@ -0,0 +1,223 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+
+use crate::document::DocumentId;
+
+/// Documents a collection keeps in memory.
+///
+/// `Full` (the default) holds every document, so reads never touch disk.
+/// `Lazy` holds at most `capacity` recently used documents and the collection
+/// reads everything else from storage on demand, so memory use no longer
+/// grows with the size of the collection.
+pub enum DocCache {
+    Full(HashMap<DocumentId, Arc<Value>>),
+    Lazy(Mutex<LruCache>),
+}
+
+impl DocCache {
+    pub fn full() -> Self {
+        DocCache::Full(HashMap::new())
+    }
+
+    pub fn lazy(capacity: usize) -> Self {
+        DocCache::Lazy(Mutex::new(LruCache::new(capacity)))
+    }
+
+    pub fn is_lazy(&self) -> bool {
+        matches!(self, DocCache::Lazy(_))
+    }
+
+    /// The complete id → document map, if every document is in memory.
+    pub fn as_map(&self) -> Option<&HashMap<DocumentId, Arc<Value>>> {
+        match self {
+            DocCache::Full(map) => Some(map),
+            DocCache::Lazy(_) => None,
+        }
+    }
+
+    /// Look up a cached document. In lazy mode a miss doesn't mean the
+    /// document doesn't exist.
+    pub fn get(&self, id: DocumentId) -> Option<Arc<Value>> {
+        match self {
+            DocCache::Full(map) => map.get(&id).cloned(),
+            DocCache::Lazy(lru) => lru.lock().unwrap().get(id),
+        }
+    }
+
+    /// Cache a document just read from storage. No-op in full mode, where
+    /// every document is already present.
+    pub fn remember(&self, id: DocumentId, doc: Arc<Value>) {
+        if let DocCache::Lazy(lru) = self {
+            lru.lock().unwrap().insert(id, doc);
+        }
+    }
+
+    /// Record a written document.
+    pub fn insert(&mut self, id: DocumentId, doc: Arc<Value>) {
+        match self {
+            DocCache::Full(map) => {
+                map.insert(id, doc);
+            }
+            DocCache::Lazy(lru) => lru.get_mut().unwrap().insert(id, doc),
+        }
+    }
+
+    pub fn remove(&mut self, id: DocumentId) {
+        match self {
+            DocCache::Full(map) => {
+                map.remove(&id);
+            }
+            DocCache::Lazy(lru) => lru.get_mut().unwrap().remove(id),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        match self {
+            DocCache::Full(map) => map.clear(),
+            DocCache::Lazy(lru) => lru.get_mut().unwrap().clear(),
+        }
+    }
+
+    /// Number of documents currently held in memory.
+    pub fn len(&self) -> usize {
+        match self {
+            DocCache::Full(map) => map.len(),
+            DocCache::Lazy(lru) => lru.lock().unwrap().len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A fixed-capacity least-recently-used document cache.
+pub struct LruCache {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<DocumentId, (Arc<Value>, u64)>,
+    /// Last-use tick → document, oldest first.
+    order: BTreeMap<u64, DocumentId>,
+}
+
+impl LruCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    pub fn get(&mut self, id: DocumentId) -> Option<Arc<Value>> {
+        let tick = self.next_tick();
+        let (doc, last_used) = self.entries.get_mut(&id)?;
+        self.order.remove(last_used);
+        *last_used = tick;
+        self.order.insert(tick, id);
+        Some(Arc::clone(doc))
+    }
+
+    pub fn insert(&mut self, id: DocumentId, doc: Arc<Value>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let tick = self.next_tick();
+        if let Some((_, last_used)) = self.entries.insert(id, (doc, tick)) {
+            self.order.remove(&last_used);
+        }
+        self.order.insert(tick, id);
+        while self.entries.len() > self.capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    pub fn remove(&mut self, id: DocumentId) {
+        if let Some((_, last_used)) = self.entries.remove(&id) {
+            self.order.remove(&last_used);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn doc(n: u64) -> Arc<Value> {
+        Arc::new(json!({"_id": n}))
+    }
+
+    #[test]
+    fn lru_evicts_least_recently_used() {
+        let mut lru = LruCache::new(2);
+        lru.insert(1, doc(1));
+        lru.insert(2, doc(2));
+        // Touch 1 so 2 becomes the eviction candidate
+        assert!(lru.get(1).is_some());
+        lru.insert(3, doc(3));
+        assert_eq!(lru.len(), 2);
+        assert!(lru.get(2).is_none());
+        assert!(lru.get(1).is_some());
+        assert!(lru.get(3).is_some());
+    }
+
+    #[test]
+    fn lru_reinsert_replaces() {
+        let mut lru = LruCache::new(2);
+        lru.insert(1, doc(1));
+        lru.insert(1, Arc::new(json!({"_id": 1, "v": 2})));
+        assert_eq!(lru.len(), 1);
+        assert_eq!(lru.get(1).unwrap()["v"], 2);
+        lru.remove(1);
+        assert!(lru.is_empty());
+    }
+
+    #[test]
+    fn lru_zero_capacity_caches_nothing() {
+        let mut lru = LruCache::new(0);
+        lru.insert(1, doc(1));
+        assert!(lru.get(1).is_none());
+    }
+
+    #[test]
+    fn full_cache_ignores_remember() {
+        let cache = DocCache::full();
+        cache.remember(1, doc(1));
+        assert!(cache.get(1).is_none());
+        assert!(cache.as_map().is_some());
+
+        let lazy = DocCache::lazy(4);
+        lazy.remember(1, doc(1));
+        assert!(lazy.get(1).is_some());
+        assert!(lazy.as_map().is_none());
+    }
+}
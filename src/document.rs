@@ -1,9 +1,32 @@
+use chrono::{DateTime, Utc};
 use serde_json::{Map, Value};
 
 use crate::error::{Error, Result};
 
 pub type DocumentId = u64;
 
+/// Top-level field holding a document's expiry time: an RFC 3339 string or
+/// seconds since the Unix epoch. Expired documents are removed by
+/// `OxiDb::purge_expired_documents`.
+pub const EXPIRE_AT_FIELD: &str = "_expireAt";
+
+/// The document's expiry time, if it has a valid `_expireAt` field.
+pub fn expire_at(data: &Value) -> Option<DateTime<Utc>> {
+    match data.get(EXPIRE_AT_FIELD)? {
+        Value::String(s) => DateTime::parse_from_rfc3339(s).ok().map(|t| t.with_timezone(&Utc)),
+        Value::Number(n) => {
+            let millis = n.as_f64()? * 1000.0;
+            DateTime::from_timestamp_millis(millis as i64)
+        }
+        _ => None,
+    }
+}
+
+/// Whether the document's `_expireAt` is at or before `now`.
+pub fn is_expired(data: &Value, now: DateTime<Utc>) -> bool {
+    expire_at(data).is_some_and(|t| t <= now)
+}
+
 #[derive(Debug, Clone)]
 pub struct Document {
     pub id: DocumentId,
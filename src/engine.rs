@@ -1,24 +1,32 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::time::Duration;
 
+use chrono::DateTime;
 use flate2::Compression;
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use serde_json::{json, Value};
 
-use crate::blob::BlobStore;
-use crate::change_stream::{ChangeEvent, ChangeStreamBroker, OperationType, ResumeError, SubscriberId, WatchFilter, WatchHandle};
-use crate::collection::{Collection, CompactStats, IndexInfo};
+use crate::blob::{BlobStore, BucketAcl, ObjectMeta, ObjectRange};
+use crate::bulk::{BulkOp, BulkOpResult};
+use crate::change_stream::{ChangeEvent, ChangeStreamBroker, OperationType, ResumeError, SubscriberId, WatchFilter, WatchHandle, WatchOptions};
+use crate::collection::{self, Collection, CollectionDescription, CompactStats, Durability, IndexInfo, RepairReport};
 use crate::crypto::EncryptionKey;
+use crate::defaults::FieldDefaults;
 use crate::document::DocumentId;
 use crate::error::{Error, Result};
-use crate::fts::{self, FtsIndex};
+use crate::fts::{self, FtsIndex, Tokenizer};
+use crate::metrics::Metrics;
 use crate::pipeline::Pipeline;
-use crate::query::FindOptions;
-use crate::transaction::{ReadRecord, Transaction, WriteOp};
-use crate::tx_log::{TransactionId, TxCommitLog};
+use crate::query::{FindAndModifyOptions, FindOptions};
+use crate::schema::CollectionSchema;
+use crate::storage::SyncMode;
+use crate::transaction::{ReadRecord, RetryPolicy, Transaction, WriteOp};
+use crate::tx_log::{TransactionId, TxCommitLog, TxHistory};
 
 /// Callback type for forwarding engine log messages to an external sink.
 pub type LogCallback = Arc<dyn Fn(&str) + Send + Sync>;
@@ -29,6 +37,49 @@ pub struct BackupInfo {
     pub path: String,
     pub size_bytes: u64,
     pub collections: usize,
+    /// Collections whose files are in the archive. Equal to `collections`
+    /// for a full backup; only the changed ones for an incremental backup.
+    pub archived_collections: usize,
+    /// Manifest written next to the archive, used as the base of the next
+    /// incremental backup. `None` for single-collection backups.
+    pub manifest_path: Option<String>,
+}
+
+/// Name of the manifest stored inside every backup archive.
+const BACKUP_MANIFEST_NAME: &str = "backup-manifest.json";
+
+/// Files in the data directory that belong to a collection.
+const COLLECTION_FILE_EXTENSIONS: &[&str] = &["dat", "wal", "idx", "fidx", "cidx", "vidx"];
+
+/// Fingerprint of a collection's files at backup time.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CollectionFingerprint {
+    pub dat_size: u64,
+    pub dat_crc32: u32,
+    /// CRC32 of the `.idx` index definitions, 0 if the file doesn't exist.
+    pub idx_crc32: u32,
+}
+
+/// Describes the collections captured by a backup.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BackupManifest {
+    pub created_at: String,
+    pub incremental: bool,
+    /// Every collection in the database at backup time.
+    pub collections: BTreeMap<String, CollectionFingerprint>,
+    /// Collections whose files are in this archive.
+    pub archived: Vec<String>,
+}
+
+impl BackupManifest {
+    /// Load a manifest written next to a previous backup.
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path).map_err(|e| {
+            Error::Backup(format!("cannot read manifest {}: {}", path.display(), e))
+        })?;
+        serde_json::from_slice(&bytes)
+            .map_err(|e| Error::Backup(format!("invalid manifest {}: {}", path.display(), e)))
+    }
 }
 
 /// Information about a completed restore operation.
@@ -38,6 +89,19 @@ pub struct RestoreInfo {
     pub collections: usize,
 }
 
+/// Outcome of compacting every collection with `OxiDb::compact_all`.
+#[derive(Debug)]
+pub struct CompactAllStats {
+    /// Totals over the collections that compacted successfully.
+    pub total: CompactStats,
+    /// Each collection by name, with its stats or the error that skipped it.
+    pub collections: Vec<(String, Result<CompactStats>)>,
+}
+
+/// Largest object written server-side (multipart upload, copy) that is read
+/// back for FTS indexing.
+const FTS_READBACK_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
 enum FtsJob {
     Index {
         data: Vec<u8>,
@@ -63,13 +127,32 @@ pub struct OxiDb {
     fts_index: Arc<RwLock<FtsIndex>>,
     fts_tx: mpsc::SyncSender<FtsJob>,
     tx_log: TxCommitLog,
+    /// Commit history, present while WAL retention is enabled.
+    tx_history: Mutex<Option<TxHistory>>,
     next_tx_id: AtomicU64,
     active_transactions: RwLock<HashMap<TransactionId, Mutex<Transaction>>>,
+    /// Timeout applied by `begin_transaction`, in milliseconds (0 = none).
+    default_tx_timeout_ms: AtomicU64,
+    /// Threads per unindexed scan of a large collection.
+    scan_workers: AtomicUsize,
+    /// Batching window for group commits, in microseconds.
+    group_commit_window_us: AtomicU64,
+    sync_mode: Mutex<SyncMode>,
+    /// Largest insertable document in bytes (0 = no limit).
+    max_doc_size: AtomicUsize,
+    /// Most documents a `find` may return (0 = no limit).
+    max_find_results: AtomicUsize,
+    /// Skip records that fail their checksum when loading a collection.
+    skip_corrupt_records: AtomicBool,
     encryption: Option<Arc<EncryptionKey>>,
     verbose: bool,
     log_callback: Option<LogCallback>,
     change_broker: ChangeStreamBroker,
     scheduler_shutdown: Mutex<Option<mpsc::SyncSender<()>>>,
+    /// Schedules currently running, shared by the scheduler thread and
+    /// `run_schedule_now`.
+    schedule_runs: crate::scheduler::RunTracker,
+    metrics: Metrics,
 }
 
 impl OxiDb {
@@ -165,6 +248,15 @@ impl OxiDb {
             tx_log.clear()?;
         }
 
+        // With WAL retention on, keep tx ids unique across restarts so the
+        // commit history stays unambiguous
+        let (tx_history, next_tx_id) = if TxHistory::exists(data_dir) {
+            let last = TxHistory::read(data_dir)?.iter().map(|&(id, _)| id).max().unwrap_or(0);
+            (Some(TxHistory::open(data_dir)?), last + 1)
+        } else {
+            (None, 1)
+        };
+
         Ok(Self {
             data_dir: data_dir.to_path_buf(),
             collections: RwLock::new(HashMap::new()),
@@ -172,13 +264,23 @@ impl OxiDb {
             fts_index,
             fts_tx,
             tx_log,
-            next_tx_id: AtomicU64::new(1),
+            tx_history: Mutex::new(tx_history),
+            next_tx_id: AtomicU64::new(next_tx_id),
             active_transactions: RwLock::new(HashMap::new()),
+            default_tx_timeout_ms: AtomicU64::new(0),
+            scan_workers: AtomicUsize::new(collection::default_scan_workers()),
+            group_commit_window_us: AtomicU64::new(crate::wal::DEFAULT_GROUP_COMMIT_WINDOW.as_micros() as u64),
+            sync_mode: Mutex::new(SyncMode::default()),
+            max_doc_size: AtomicUsize::new(0),
+            skip_corrupt_records: AtomicBool::new(false),
+            max_find_results: AtomicUsize::new(0),
             encryption,
             verbose,
             log_callback,
             change_broker: ChangeStreamBroker::new(),
             scheduler_shutdown: Mutex::new(None),
+            schedule_runs: crate::scheduler::RunTracker::default(),
+            metrics: Metrics::new(),
         })
     }
 
@@ -193,14 +295,22 @@ impl OxiDb {
         }
         // Slow path: load the collection OUTSIDE the write lock so that other
         // collections remain accessible while a large collection is loading.
-        let col = Collection::open_with_options(
+        let mut col = Collection::open_with_options(
             name,
             &self.data_dir,
             &std::collections::HashSet::new(),
             self.encryption.clone(),
             self.verbose,
             self.log_callback.clone(),
+            self.skip_corrupt_records.load(Ordering::Relaxed),
         )?;
+        col.set_scan_workers(self.scan_workers.load(Ordering::Relaxed));
+        col.set_group_commit_window(self.group_commit_window());
+        col.set_sync_mode(self.sync_mode());
+        col.set_max_document_size(self.max_document_size());
+        if self.wal_retention_enabled() {
+            col.set_wal_retention(true)?;
+        }
         let arc = Arc::new(RwLock::new(col));
         // Briefly acquire write lock to insert
         let mut cols = self.collections.write().unwrap();
@@ -218,14 +328,22 @@ impl OxiDb {
         if cols.contains_key(name) {
             return Err(Error::CollectionAlreadyExists(name.to_string()));
         }
-        let col = Collection::open_with_options(
+        let mut col = Collection::open_with_options(
             name,
             &self.data_dir,
             &std::collections::HashSet::new(),
             self.encryption.clone(),
             self.verbose,
             self.log_callback.clone(),
+            self.skip_corrupt_records.load(Ordering::Relaxed),
         )?;
+        col.set_scan_workers(self.scan_workers.load(Ordering::Relaxed));
+        col.set_group_commit_window(self.group_commit_window());
+        col.set_sync_mode(self.sync_mode());
+        col.set_max_document_size(self.max_document_size());
+        if self.wal_retention_enabled() {
+            col.set_wal_retention(true)?;
+        }
         cols.insert(name.to_string(), Arc::new(RwLock::new(col)));
         Ok(())
     }
@@ -236,6 +354,31 @@ impl OxiDb {
         cols.keys().cloned().collect()
     }
 
+    /// Request counters, for the server to record the commands it handles.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Render the request counters, open transactions, and collection count in
+    /// Prometheus text exposition format.
+    pub fn metrics_text(&self) -> String {
+        let active_transactions = self.active_transactions.read().unwrap().len();
+        let collections = self.collections.read().unwrap().len();
+        self.metrics.render(active_transactions, collections)
+    }
+
+    /// Check that the data directory still accepts writes by creating and
+    /// removing a small probe file. Each call uses its own file, so that
+    /// concurrent checks don't remove each other's probes.
+    pub fn check_writable(&self) -> Result<()> {
+        static PROBES: AtomicU64 = AtomicU64::new(0);
+        let n = PROBES.fetch_add(1, Ordering::Relaxed);
+        let probe = self.data_dir.join(format!(".write_probe.{}.{n}", std::process::id()));
+        std::fs::write(&probe, b"ok")?;
+        std::fs::remove_file(&probe)?;
+        Ok(())
+    }
+
     /// Flush all index data to disk for every loaded collection.
     pub fn flush_indexes(&self) {
         let cols = self.collections.read().unwrap();
@@ -250,12 +393,22 @@ impl OxiDb {
     pub fn drop_collection(&self, name: &str) -> Result<()> {
         let mut cols = self.collections.write().unwrap();
         cols.remove(name);
-        for ext in &["dat", "wal", "idx", "fidx", "cidx", "vidx"] {
+        for ext in COLLECTION_FILE_EXTENSIONS {
             let path = self.data_dir.join(format!("{}.{}", name, ext));
             if path.exists() {
                 std::fs::remove_file(path)?;
             }
         }
+        self.remove_wal_history(name)
+    }
+
+    /// Delete a collection's retained WAL history. Its documents no longer
+    /// follow from the history once the collection is dropped or replaced.
+    fn remove_wal_history(&self, name: &str) -> Result<()> {
+        let path = self.data_dir.join(format!("{}{}", name, crate::wal::HISTORY_SUFFIX));
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
         Ok(())
     }
 
@@ -277,6 +430,16 @@ impl OxiDb {
         self.change_broker.subscribe(filter, 256, resume_after)
     }
 
+    /// Subscribe to change events with the full set of `WatchOptions`
+    /// (e.g. `full_document` to receive post-update documents).
+    pub fn watch_with_options(
+        &self,
+        filter: WatchFilter,
+        opts: &WatchOptions,
+    ) -> std::result::Result<WatchHandle, ResumeError> {
+        self.change_broker.subscribe_with_options(filter, 256, opts)
+    }
+
     /// Unsubscribe from change events.
     pub fn unwatch(&self, id: SubscriberId) {
         self.change_broker.unsubscribe(id);
@@ -287,10 +450,31 @@ impl OxiDb {
     // -----------------------------------------------------------------------
 
     pub fn insert(&self, collection: &str, doc: Value) -> Result<DocumentId> {
+        self.insert_with_durability(collection, doc, Durability::Full)
+    }
+
+    /// Insert a document; with `Durability::Relaxed` it returns without
+    /// waiting for the fsync (see `flush`).
+    pub fn insert_with_durability(&self, collection: &str, doc: Value, durability: Durability) -> Result<DocumentId> {
         let col = self.get_or_create_collection(collection)?;
         let emit = self.change_broker.has_subscribers();
         let doc_clone = if emit { Some(doc.clone()) } else { None };
-        let id = col.write().unwrap().insert(doc)?;
+        let (id, pending) = {
+            let mut col = col.write().unwrap();
+            match (durability, self.sync_mode()) {
+                (Durability::Full, SyncMode::Full) => (col.insert(doc)?, None),
+                (Durability::Full, SyncMode::Group) => {
+                    let (id, ticket) = col.insert_grouped(doc)?;
+                    (id, Some((ticket, col.group_commit())))
+                }
+                _ => (col.insert_grouped(doc)?.0, None),
+            }
+        };
+        // Wait for the fsync after releasing the collection, so that
+        // concurrent inserts can share it
+        if let Some((ticket, group)) = pending {
+            group.wait(ticket)?;
+        }
         if let Some(mut d) = doc_clone {
             if let Some(obj) = d.as_object_mut() {
                 obj.insert("_id".to_string(), Value::Number(id.into()));
@@ -298,6 +482,7 @@ impl OxiDb {
             }
             self.change_broker.emit(ChangeEvent {
                 token: 0,
+                timestamp: DateTime::default(),
                 operation: OperationType::Insert,
                 collection: collection.to_string(),
                 doc_id: id,
@@ -309,10 +494,21 @@ impl OxiDb {
     }
 
     pub fn insert_many(&self, collection: &str, docs: Vec<Value>) -> Result<Vec<DocumentId>> {
+        self.insert_many_with_durability(collection, docs, Durability::Full)
+    }
+
+    /// `insert_many`, skipping the fsync when `durability` is `Relaxed`; for
+    /// bulk loads, followed by `flush`.
+    pub fn insert_many_with_durability(
+        &self,
+        collection: &str,
+        docs: Vec<Value>,
+        durability: Durability,
+    ) -> Result<Vec<DocumentId>> {
         let col = self.get_or_create_collection(collection)?;
         let emit = self.change_broker.has_subscribers();
         let doc_clones: Option<Vec<Value>> = if emit { Some(docs.iter().cloned().collect()) } else { None };
-        let ids = col.write().unwrap().insert_many(docs)?;
+        let ids = col.write().unwrap().insert_many_with_durability(docs, durability)?;
         if let Some(clones) = doc_clones {
             for (mut d, &id) in clones.into_iter().zip(ids.iter()) {
                 if let Some(obj) = d.as_object_mut() {
@@ -321,6 +517,7 @@ impl OxiDb {
                 }
                 self.change_broker.emit(ChangeEvent {
                     token: 0,
+                    timestamp: DateTime::default(),
                     operation: OperationType::Insert,
                     collection: collection.to_string(),
                     doc_id: id,
@@ -333,6 +530,11 @@ impl OxiDb {
     }
 
     pub fn find(&self, collection: &str, query: &Value) -> Result<Vec<Value>> {
+        self.find_with_options(collection, query, &FindOptions::default())
+    }
+
+    /// `find` without the result limit, for the engine's own reads.
+    pub(crate) fn find_unlimited(&self, collection: &str, query: &Value) -> Result<Vec<Value>> {
         let col = self.get_or_create_collection(collection)?;
         col.read().unwrap().find(query)
     }
@@ -344,7 +546,10 @@ impl OxiDb {
         opts: &FindOptions,
     ) -> Result<Vec<Value>> {
         let col = self.get_or_create_collection(collection)?;
-        col.read().unwrap().find_with_options(query, opts)
+        let (opts, max) = self.capped_find_options(opts);
+        let docs = col.read().unwrap().find_with_options(query, &opts)?;
+        self.check_result_size(docs.len(), max)?;
+        Ok(docs)
     }
 
     pub fn find_with_options_arcs(
@@ -354,7 +559,53 @@ impl OxiDb {
         opts: &FindOptions,
     ) -> Result<Vec<Arc<Value>>> {
         let col = self.get_or_create_collection(collection)?;
-        col.read().unwrap().find_with_options_arcs(query, opts)
+        let (opts, max) = self.capped_find_options(opts);
+        let docs = col.read().unwrap().find_with_options_arcs(query, &opts)?;
+        self.check_result_size(docs.len(), max)?;
+        Ok(docs)
+    }
+
+    /// `find_with_options_arcs` plus the number of documents matching `query`
+    /// ignoring `skip` and `limit`. Both are read under one lock, so the
+    /// total always agrees with the page.
+    pub fn find_with_count_arcs(
+        &self,
+        collection: &str,
+        query: &Value,
+        opts: &FindOptions,
+    ) -> Result<(Vec<Arc<Value>>, usize)> {
+        let col = self.get_or_create_collection(collection)?;
+        let (opts, max) = self.capped_find_options(opts);
+        let col = col.read().unwrap();
+        let (docs, total) = match opts.respect_ttl {
+            // Counting live documents means reading every match anyway, so
+            // one unpaged pass gives both the total and the page
+            true => {
+                let unpaged = FindOptions { skip: None, limit: None, ..opts.clone() };
+                let live = col.find_with_options_arcs(query, &unpaged)?;
+                let total = live.len();
+                let skip = opts.skip.unwrap_or(0) as usize;
+                let limit = opts.limit.map_or(usize::MAX, |n| n as usize);
+                (live.into_iter().skip(skip).take(limit).collect::<Vec<_>>(), total)
+            }
+            false => (col.find_with_options_arcs(query, &opts)?, Self::count_in(&col, query)?),
+        };
+        self.check_result_size(docs.len(), max)?;
+        Ok((docs, total))
+    }
+
+    /// Run a query and return the plan it used (index vs. collection scan,
+    /// candidate counts, post-filtering) instead of the matching documents.
+    pub fn explain(&self, collection: &str, query: &Value, opts: &FindOptions) -> Result<Value> {
+        let col = self.get_or_create_collection(collection)?;
+        col.read().unwrap().explain(query, opts)
+    }
+
+    /// Names of the indexes the planner would use to match `query`, without
+    /// running it.
+    pub fn query_indexes(&self, collection: &str, query: &Value) -> Result<Vec<String>> {
+        let col = self.get_or_create_collection(collection)?;
+        col.read().unwrap().query_indexes(query)
     }
 
     pub fn find_one(&self, collection: &str, query: &Value) -> Result<Option<Value>> {
@@ -363,31 +614,128 @@ impl OxiDb {
     }
 
     pub fn update(&self, collection: &str, query: &Value, update: &Value) -> Result<u64> {
+        self.update_with_version(collection, query, update, None)
+    }
+
+    pub fn update_one(&self, collection: &str, query: &Value, update: &Value) -> Result<u64> {
+        self.update_one_with_version(collection, query, update, None)
+    }
+
+    /// `update`, rejected with `VersionConflict` (and nothing modified) if a
+    /// matched document's `_version` isn't `expected_version`.
+    pub fn update_with_version(
+        &self,
+        collection: &str,
+        query: &Value,
+        update: &Value,
+        expected_version: Option<u64>,
+    ) -> Result<u64> {
+        self.update_limited(collection, query, update, None, expected_version)
+    }
+
+    /// `update_one` with the same version check as `update_with_version`.
+    pub fn update_one_with_version(
+        &self,
+        collection: &str,
+        query: &Value,
+        update: &Value,
+        expected_version: Option<u64>,
+    ) -> Result<u64> {
+        self.update_limited(collection, query, update, Some(1), expected_version)
+    }
+
+    fn update_limited(
+        &self,
+        collection: &str,
+        query: &Value,
+        update: &Value,
+        limit: Option<usize>,
+        expected_version: Option<u64>,
+    ) -> Result<u64> {
         let col = self.get_or_create_collection(collection)?;
-        let ids = col.write().unwrap().update(query, update, None)?;
-        if self.change_broker.has_subscribers() {
-            for &id in &ids {
-                self.change_broker.emit(ChangeEvent {
-                    token: 0,
-                    operation: OperationType::Update,
-                    collection: collection.to_string(),
-                    doc_id: id,
-                    document: None,
-                    tx_id: None,
-                });
-            }
+        let updated = col.write().unwrap().update_returning(query, update, limit, expected_version)?;
+        self.emit_update_events(collection, &updated);
+        Ok(updated.len() as u64)
+    }
+
+    /// The documents `update` would change, as they would be after it,
+    /// without changing anything. `limit` is `Some(1)` for `update_one`.
+    pub fn update_dry_run(
+        &self,
+        collection: &str,
+        query: &Value,
+        update: &Value,
+        limit: Option<usize>,
+        expected_version: Option<u64>,
+    ) -> Result<Vec<Value>> {
+        let col = self.get_or_create_collection(collection)?;
+        let updated = col.read().unwrap().update_dry_run(query, update, limit, expected_version)?;
+        Ok(updated.into_iter().map(|(_, doc)| doc).collect())
+    }
+
+    fn emit_update_events(&self, collection: &str, updated: &[(DocumentId, Arc<Value>)]) {
+        if !self.change_broker.has_subscribers() {
+            return;
+        }
+        let full = self.change_broker.wants_full_document();
+        for (id, doc) in updated {
+            self.change_broker.emit(ChangeEvent {
+                token: 0,
+                timestamp: DateTime::default(),
+                operation: OperationType::Update,
+                collection: collection.to_string(),
+                doc_id: *id,
+                document: if full { Some((**doc).clone()) } else { None },
+                tx_id: None,
+            });
         }
-        Ok(ids.len() as u64)
     }
 
-    pub fn update_one(&self, collection: &str, query: &Value, update: &Value) -> Result<u64> {
+    /// Atomically update or delete the first document matching `query` and
+    /// return it; see `Collection::find_and_modify`. The collection's write
+    /// lock is held throughout, so concurrent callers never get the same
+    /// document.
+    pub fn find_and_modify(
+        &self,
+        collection: &str,
+        query: &Value,
+        update: Option<&Value>,
+        opts: &FindAndModifyOptions,
+    ) -> Result<Option<Value>> {
+        let col = self.get_or_create_collection(collection)?;
+        let mut col = col.write().unwrap();
+        let doc = col.find_and_modify(query, update, opts)?;
+        let Some(id) = doc.as_ref().and_then(|d| d.get("_id")).and_then(Value::as_u64) else {
+            return Ok(doc);
+        };
+        if !self.change_broker.has_subscribers() {
+            return Ok(doc);
+        }
+        if opts.remove {
+            self.change_broker.emit(ChangeEvent {
+                token: 0,
+                timestamp: DateTime::default(),
+                operation: OperationType::Delete,
+                collection: collection.to_string(),
+                doc_id: id,
+                document: None,
+                tx_id: None,
+            });
+        } else if let Some(after) = col.get(id)? {
+            self.emit_update_events(collection, &[(id, Arc::new(after))]);
+        }
+        Ok(doc)
+    }
+
+    pub fn delete(&self, collection: &str, query: &Value) -> Result<u64> {
         let col = self.get_or_create_collection(collection)?;
-        let ids = col.write().unwrap().update(query, update, Some(1))?;
+        let ids = col.write().unwrap().delete(query, None)?;
         if self.change_broker.has_subscribers() {
             for &id in &ids {
                 self.change_broker.emit(ChangeEvent {
                     token: 0,
-                    operation: OperationType::Update,
+                    timestamp: DateTime::default(),
+                    operation: OperationType::Delete,
                     collection: collection.to_string(),
                     doc_id: id,
                     document: None,
@@ -398,13 +746,21 @@ impl OxiDb {
         Ok(ids.len() as u64)
     }
 
-    pub fn delete(&self, collection: &str, query: &Value) -> Result<u64> {
+    /// IDs of the documents `delete` would remove, without removing them.
+    /// `limit` is `Some(1)` for `delete_one`.
+    pub fn delete_dry_run(&self, collection: &str, query: &Value, limit: Option<usize>) -> Result<Vec<DocumentId>> {
         let col = self.get_or_create_collection(collection)?;
-        let ids = col.write().unwrap().delete(query, None)?;
+        col.read().unwrap().delete_dry_run(query, limit)
+    }
+
+    pub fn delete_one(&self, collection: &str, query: &Value) -> Result<u64> {
+        let col = self.get_or_create_collection(collection)?;
+        let ids = col.write().unwrap().delete(query, Some(1))?;
         if self.change_broker.has_subscribers() {
             for &id in &ids {
                 self.change_broker.emit(ChangeEvent {
                     token: 0,
+                    timestamp: DateTime::default(),
                     operation: OperationType::Delete,
                     collection: collection.to_string(),
                     doc_id: id,
@@ -416,22 +772,51 @@ impl OxiDb {
         Ok(ids.len() as u64)
     }
 
-    pub fn delete_one(&self, collection: &str, query: &Value) -> Result<u64> {
+    /// Apply a mixed batch of inserts, updates, and deletes atomically; see
+    /// `Collection::bulk_write`. Change events go out only once the whole
+    /// batch is written.
+    pub fn bulk_write(&self, collection: &str, ops: Vec<BulkOp>) -> Result<Vec<BulkOpResult>> {
+        self.bulk_write_with_durability(collection, ops, Durability::Full)
+    }
+
+    /// `bulk_write`, skipping the fsync when `durability` is `Relaxed`.
+    pub fn bulk_write_with_durability(
+        &self,
+        collection: &str,
+        ops: Vec<BulkOp>,
+        durability: Durability,
+    ) -> Result<Vec<BulkOpResult>> {
         let col = self.get_or_create_collection(collection)?;
-        let ids = col.write().unwrap().delete(query, Some(1))?;
-        if self.change_broker.has_subscribers() {
-            for &id in &ids {
+        let mut col = col.write().unwrap();
+        let results = col.bulk_write_with_durability(ops, durability)?;
+        if !self.change_broker.has_subscribers() {
+            return Ok(results);
+        }
+        let full = self.change_broker.wants_full_document();
+        for result in &results {
+            let (operation, ids) = match result {
+                BulkOpResult::Inserted(id) => (OperationType::Insert, std::slice::from_ref(id)),
+                BulkOpResult::Modified(ids) => (OperationType::Update, ids.as_slice()),
+                BulkOpResult::Deleted(ids) => (OperationType::Delete, ids.as_slice()),
+            };
+            for &id in ids {
+                let document = match operation {
+                    OperationType::Insert => col.get(id)?,
+                    OperationType::Update if full => col.get(id)?,
+                    _ => None,
+                };
                 self.change_broker.emit(ChangeEvent {
                     token: 0,
-                    operation: OperationType::Delete,
+                    timestamp: DateTime::default(),
+                    operation: operation.clone(),
                     collection: collection.to_string(),
                     doc_id: id,
-                    document: None,
+                    document,
                     tx_id: None,
                 });
             }
         }
-        Ok(ids.len() as u64)
+        Ok(results)
     }
 
     pub fn create_index(&self, collection: &str, field: &str) -> Result<()> {
@@ -439,6 +824,18 @@ impl OxiDb {
         col.write().unwrap().create_index(field)
     }
 
+    /// Create a field index that leaves out documents where the field is null.
+    pub fn create_sparse_index(&self, collection: &str, field: &str) -> Result<()> {
+        let col = self.get_or_create_collection(collection)?;
+        col.write().unwrap().create_sparse_index(field)
+    }
+
+    /// Create a field index that sort pushdown walks from its highest key.
+    pub fn create_descending_index(&self, collection: &str, field: &str) -> Result<()> {
+        let col = self.get_or_create_collection(collection)?;
+        col.write().unwrap().create_descending_index(field)
+    }
+
     pub fn create_unique_index(&self, collection: &str, field: &str) -> Result<()> {
         let col = self.get_or_create_collection(collection)?;
         col.write().unwrap().create_unique_index(field)
@@ -458,6 +855,13 @@ impl OxiDb {
         Ok(col.read().unwrap().list_indexes())
     }
 
+    /// Document count, indexes, and a field/type profile built from up to
+    /// `sample` documents of a collection.
+    pub fn describe(&self, collection: &str, sample: usize) -> Result<CollectionDescription> {
+        let col = self.get_or_create_collection(collection)?;
+        col.read().unwrap().describe(sample)
+    }
+
     pub fn drop_index(&self, collection: &str, index_name: &str) -> Result<()> {
         let col = self.get_or_create_collection(collection)?;
         col.write().unwrap().drop_index(index_name)
@@ -466,6 +870,10 @@ impl OxiDb {
     pub fn count(&self, collection: &str, query: &Value) -> Result<usize> {
         let col = self.get_or_create_collection(collection)?;
         let col = col.read().unwrap();
+        Self::count_in(&col, query)
+    }
+
+    fn count_in(col: &Collection, query: &Value) -> Result<usize> {
         if query.as_object().is_some_and(|m| m.is_empty()) {
             Ok(col.count())
         } else {
@@ -473,106 +881,490 @@ impl OxiDb {
         }
     }
 
-    pub fn compact(&self, collection: &str) -> Result<CompactStats> {
+    /// fsync a collection's data file, making writes made with
+    /// `Durability::Relaxed` durable. A crash loses relaxed writes made
+    /// since the last flush (or full-durability write).
+    pub fn flush(&self, collection: &str) -> Result<()> {
         let col = self.get_or_create_collection(collection)?;
-        col.write().unwrap().compact()
+        let col = col.read().unwrap();
+        col.flush()
     }
 
-    pub fn create_text_index(&self, collection: &str, fields: Vec<String>) -> Result<()> {
+    /// Sorted unique values of `field` among documents matching `query`
+    /// (`{}` for all). See [`Collection::distinct`].
+    pub fn distinct(&self, collection: &str, field: &str, query: &Value) -> Result<Vec<Value>> {
         let col = self.get_or_create_collection(collection)?;
-        col.write().unwrap().create_text_index(fields)
+        col.read().unwrap().distinct(field, query)
     }
 
-    pub fn text_search(
-        &self,
-        collection: &str,
-        query: &str,
-        limit: usize,
-    ) -> Result<Vec<Value>> {
+    pub fn compact(&self, collection: &str) -> Result<CompactStats> {
         let col = self.get_or_create_collection(collection)?;
-        col.read().unwrap().text_search(query, limit)
+        col.write().unwrap().compact()
     }
 
-    pub fn create_vector_index(
-        &self,
-        collection: &str,
-        field: &str,
-        dimension: usize,
-        metric: crate::vector::DistanceMetric,
-    ) -> Result<()> {
-        let col = self.get_or_create_collection(collection)?;
-        col.write().unwrap().create_vector_index(field, dimension, metric)
+    /// Salvage a damaged collection: rewrite its data file without the
+    /// records that fail their checksum or can't be decoded, quarantining
+    /// them next to it, then reload it so the indexes are rebuilt from the
+    /// documents that survived. Works whether or not the collection loads.
+    pub fn repair(&self, collection: &str) -> Result<RepairReport> {
+        let report = {
+            let mut cols = self.collections.write().unwrap();
+            if let Some(old) = cols.remove(collection) {
+                // Wait for in-flight operations on the old collection
+                drop(old.write().unwrap());
+            }
+            Collection::repair(collection, &self.data_dir, self.encryption.clone())?
+        };
+        self.get_or_create_collection(collection)?;
+        Ok(report)
     }
 
-    pub fn vector_search(
-        &self,
-        collection: &str,
-        field: &str,
-        query_vector: &[f32],
-        limit: usize,
-        ef_search: Option<usize>,
-    ) -> Result<Vec<Value>> {
-        let col = self.get_or_create_collection(collection)?;
-        col.read().unwrap().vector_search(field, query_vector, limit, ef_search)
+    /// Compact every collection, loaded or on disk, one at a time so that
+    /// only the collection being compacted is locked. A collection that
+    /// fails is skipped and its error reported in the result.
+    pub fn compact_all(&self) -> Result<CompactAllStats> {
+        let mut names: BTreeSet<String> = self.list_collections().into_iter().collect();
+        names.extend(Self::discover_collection_names_on_disk(&self.data_dir)?);
+        let mut total = CompactStats { old_size: 0, new_size: 0, docs_kept: 0 };
+        let mut collections = Vec::with_capacity(names.len());
+        for name in names {
+            let result = self.compact(&name);
+            if let Ok(stats) = &result {
+                total.old_size += stats.old_size;
+                total.new_size += stats.new_size;
+                total.docs_kept += stats.docs_kept;
+            }
+            collections.push((name, result));
+        }
+        Ok(CompactAllStats { total, collections })
     }
 
-    pub fn aggregate(&self, collection: &str, pipeline_json: &Value) -> Result<Vec<Value>> {
-        let pipeline = Pipeline::parse(pipeline_json)?;
-        let (leading_match, start_idx) = pipeline.take_leading_match();
+    /// Set how many threads unindexed `count` and `find` scans may use on
+    /// large collections. 1 disables parallel scans. Applies to every loaded
+    /// collection and to collections opened later.
+    pub fn set_scan_workers(&self, workers: usize) {
+        let workers = workers.max(1);
+        self.scan_workers.store(workers, Ordering::Relaxed);
+        for col in self.collections.read().unwrap().values() {
+            col.write().unwrap().set_scan_workers(workers);
+        }
+    }
 
-        let query = match leading_match {
-            Some(q) => q.clone(),
-            None => json!({}),
-        };
+    /// Set how long an insert leading a group commit waits for concurrent
+    /// inserts to the same collection to join its fsync. Zero syncs at once;
+    /// inserts arriving during an fsync still share the next one. Applies to
+    /// every loaded collection and to collections opened later.
+    pub fn set_group_commit_window(&self, window: Duration) {
+        self.group_commit_window_us.store(window.as_micros() as u64, Ordering::Relaxed);
+        for col in self.collections.read().unwrap().values() {
+            col.read().unwrap().set_group_commit_window(window);
+        }
+    }
 
-        let lookup_fn = |foreign: &str, query: &Value| -> Result<Vec<Value>> {
-            self.find(foreign, query)
-        };
+    /// Set when data and WAL files are fsynced; see `SyncMode`. Applies to
+    /// every loaded collection and to collections opened later.
+    pub fn set_sync_mode(&self, mode: SyncMode) {
+        *self.sync_mode.lock().unwrap() = mode;
+        for col in self.collections.read().unwrap().values() {
+            col.write().unwrap().set_sync_mode(mode);
+        }
+    }
 
-        // Fast path: use Arc-based pipeline to avoid cloning all initial docs.
-        // This is critical for aggregation over large datasets (200K+ docs).
-        let col = self.get_or_create_collection(collection)?;
-        let col_guard = col.read().unwrap();
-        let arcs = col_guard.find_arcs(&query)?;
-        let field_indexes = col_guard.field_indexes();
-        let doc_cache = col_guard.doc_cache();
-        pipeline.execute_from_arcs(start_idx, arcs, &lookup_fn, Some(field_indexes), Some(doc_cache))
+    pub fn sync_mode(&self) -> SyncMode {
+        *self.sync_mode.lock().unwrap()
     }
 
-    // -----------------------------------------------------------------------
-    // Transaction methods
-    // -----------------------------------------------------------------------
+    fn group_commit_window(&self) -> Duration {
+        Duration::from_micros(self.group_commit_window_us.load(Ordering::Relaxed))
+    }
 
-    /// Begin a new transaction. Returns the transaction ID.
-    pub fn begin_transaction(&self) -> TransactionId {
-        let tx_id = self.next_tx_id.fetch_add(1, Ordering::SeqCst);
-        let tx = Transaction::new(tx_id);
-        self.active_transactions.write().unwrap().insert(tx_id, Mutex::new(tx));
-        tx_id
+    /// Compact each loaded collection whose dead space (see
+    /// `Collection::dead_space_ratio`) exceeds `threshold`, skipping
+    /// collections an active transaction is using. Returns the collections
+    /// compacted, with their stats or the error that stopped them.
+    pub fn auto_compact(&self, threshold: f64) -> Vec<(String, Result<CompactStats>)> {
+        let candidates: Vec<(String, Arc<RwLock<Collection>>)> = self
+            .collections
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, col)| (name.clone(), Arc::clone(col)))
+            .collect();
+        let mut compacted = Vec::new();
+        for (name, col) in candidates {
+            if col.read().unwrap().dead_space_ratio() <= threshold || self.collection_in_transaction(&name) {
+                continue;
+            }
+            let result = col.write().unwrap().compact();
+            compacted.push((name, result));
+        }
+        compacted
     }
 
-    /// Buffer an insert within a transaction.
-    pub fn tx_insert(&self, tx_id: TransactionId, collection: &str, doc: Value) -> Result<()> {
+    /// Whether an active transaction has read or written `collection`.
+    fn collection_in_transaction(&self, collection: &str) -> bool {
         let txs = self.active_transactions.read().unwrap();
-        let tx_mutex = txs.get(&tx_id).ok_or(Error::TransactionNotFound(tx_id))?;
-        let mut tx = tx_mutex.lock().unwrap();
-        tx.collections_involved.insert(collection.to_string());
-        tx.write_ops.push(WriteOp::Insert {
-            collection: collection.to_string(),
-            data: doc,
+        txs.values().any(|tx| tx.lock().unwrap().collections_involved.contains(collection))
+    }
+
+    /// Run `auto_compact(threshold)` every `interval` on a background thread
+    /// until the database is dropped.
+    pub fn start_auto_compactor(self: &Arc<Self>, interval: Duration, threshold: f64) {
+        let db = Arc::downgrade(self);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            match db.upgrade() {
+                Some(db) => {
+                    for (name, result) in db.auto_compact(threshold) {
+                        if let Err(e) = result {
+                            eprintln!("[warn] auto-compaction of '{name}' failed: {e}");
+                        }
+                    }
+                }
+                None => break,
+            }
         });
-        Ok(())
     }
 
-    /// Execute a read within a transaction, recording versions for OCC.
-    pub fn tx_find(&self, tx_id: TransactionId, collection: &str, query: &Value) -> Result<Vec<Value>> {
-        let col = self.get_or_create_collection(collection)?;
-        let col_guard = col.read().unwrap();
-        let results = col_guard.find(query)?;
+    /// Set the largest document, in serialized JSON bytes, that inserts
+    /// accept; larger ones fail with `DocumentTooLarge` before anything is
+    /// written. `None` removes the limit.
+    pub fn set_max_document_size(&self, max: Option<usize>) {
+        self.max_doc_size.store(max.unwrap_or(0), Ordering::Relaxed);
+        for col in self.collections.read().unwrap().values() {
+            col.write().unwrap().set_max_document_size(max);
+        }
+    }
 
-        // Record read versions
-        let txs = self.active_transactions.read().unwrap();
-        let tx_mutex = txs.get(&tx_id).ok_or(Error::TransactionNotFound(tx_id))?;
+    /// Load collections past records that fail their checksum, logging and
+    /// leaving them out, instead of failing with `Corruption`. Applies to
+    /// collections opened later; the skipped records are dropped for good by
+    /// the next `compact`.
+    pub fn set_skip_corrupt_records(&self, skip: bool) {
+        self.skip_corrupt_records.store(skip, Ordering::Relaxed);
+    }
+
+    fn max_document_size(&self) -> Option<usize> {
+        Some(self.max_doc_size.load(Ordering::Relaxed)).filter(|&n| n > 0)
+    }
+
+    /// Set the most documents a `find` may return. A query matching more
+    /// (after `skip` and `limit`) fails with `ResultTooLarge`. `None`
+    /// removes the limit.
+    pub fn set_max_find_results(&self, max: Option<usize>) {
+        self.max_find_results.store(max.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Cap `opts.limit` one past the result limit, so an oversized result is
+    /// detected without materializing all of it.
+    fn capped_find_options(&self, opts: &FindOptions) -> (FindOptions, Option<usize>) {
+        let max = Some(self.max_find_results.load(Ordering::Relaxed)).filter(|&n| n > 0);
+        let mut capped = opts.clone();
+        if let Some(max) = max {
+            let cap = max as u64 + 1;
+            capped.limit = Some(opts.limit.map_or(cap, |l| l.min(cap)));
+        }
+        (capped, max)
+    }
+
+    fn check_result_size(&self, len: usize, max: Option<usize>) -> Result<()> {
+        match max {
+            Some(max) if len > max => Err(Error::ResultTooLarge(max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Enable or disable compression at rest for a collection. Existing
+    /// records keep their format until the next `compact`.
+    pub fn set_compression(&self, collection: &str, enabled: bool) -> Result<()> {
+        let col = self.get_or_create_collection(collection)?;
+        col.write().unwrap().set_compression(enabled)
+    }
+
+    /// Enable or disable the bloom filter on a collection's field index.
+    pub fn set_index_bloom(&self, collection: &str, field: &str, enabled: bool) -> Result<()> {
+        let col = self.get_or_create_collection(collection)?;
+        col.write().unwrap().set_index_bloom(field, enabled)
+    }
+
+    /// Whether WAL retention (and so point-in-time recovery) is enabled.
+    pub fn wal_retention_enabled(&self) -> bool {
+        self.tx_history.lock().unwrap().is_some()
+    }
+
+    /// Enable or disable WAL retention for every collection.
+    ///
+    /// While enabled, WAL checkpoints keep their entries in
+    /// `<collection>.wal.history` and commits are recorded in `_tx_history`,
+    /// so `restore_to_tx` and `restore_to_time` can rebuild any later state.
+    /// Enabling snapshots each collection into its history first; history
+    /// starts there. The setting persists across restarts. Disabling deletes
+    /// all retained history.
+    pub fn set_wal_retention(&self, enabled: bool) -> Result<()> {
+        for name in Self::discover_collection_names_on_disk(&self.data_dir)? {
+            self.get_or_create_collection(&name)?;
+        }
+        {
+            let mut history = self.tx_history.lock().unwrap();
+            if enabled && history.is_none() {
+                *history = Some(TxHistory::open(&self.data_dir)?);
+            } else if !enabled {
+                *history = None;
+                TxHistory::remove(&self.data_dir)?;
+            }
+        }
+        let cols: Vec<_> = self.collections.read().unwrap().values().cloned().collect();
+        for col in cols {
+            col.write().unwrap().set_wal_retention(enabled)?;
+        }
+        Ok(())
+    }
+
+    /// Validate documents inserted into or updated in a collection against a
+    /// JSON Schema, or pass `None` to remove it. The schema is persisted with
+    /// the collection's settings.
+    pub fn set_collection_schema(&self, collection: &str, schema: Option<CollectionSchema>) -> Result<()> {
+        let col = self.get_or_create_collection(collection)?;
+        col.write().unwrap().set_schema(schema)
+    }
+
+    /// The JSON Schema a collection validates documents against, if any.
+    pub fn get_collection_schema(&self, collection: &str) -> Result<Option<CollectionSchema>> {
+        let col = self.get_or_create_collection(collection)?;
+        Ok(col.read().unwrap().schema().cloned())
+    }
+
+    /// Fill in default values for fields that documents inserted into a
+    /// collection omit. An empty `FieldDefaults` removes them. The spec is
+    /// persisted with the collection's settings.
+    pub fn set_collection_defaults(&self, collection: &str, defaults: FieldDefaults) -> Result<()> {
+        let col = self.get_or_create_collection(collection)?;
+        col.write().unwrap().set_defaults(defaults)
+    }
+
+    /// The default values a collection fills in on insert.
+    pub fn get_collection_defaults(&self, collection: &str) -> Result<FieldDefaults> {
+        let col = self.get_or_create_collection(collection)?;
+        Ok(col.read().unwrap().defaults().clone())
+    }
+
+    /// Load a collection's documents lazily, keeping at most `limit` of them
+    /// in an LRU cache, or pass `None` to keep every document in memory.
+    pub fn set_doc_cache_limit(&self, collection: &str, limit: Option<usize>) -> Result<()> {
+        let col = self.get_or_create_collection(collection)?;
+        col.write().unwrap().set_doc_cache_limit(limit)
+    }
+
+    pub fn create_text_index(&self, collection: &str, fields: Vec<String>) -> Result<()> {
+        let col = self.get_or_create_collection(collection)?;
+        col.write().unwrap().create_text_index(fields)
+    }
+
+    /// Create a geo index for `$near` / `$geoWithin` queries on `field`.
+    pub fn create_geo_index(&self, collection: &str, field: &str) -> Result<()> {
+        let col = self.get_or_create_collection(collection)?;
+        col.write().unwrap().create_geo_index(field)
+    }
+
+    pub fn text_search(
+        &self,
+        collection: &str,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<Value>> {
+        let col = self.get_or_create_collection(collection)?;
+        col.read().unwrap().text_search(query, limit)
+    }
+
+    /// Full-text search over the documents matching `filter`.
+    pub fn text_search_filtered(
+        &self,
+        collection: &str,
+        query: &str,
+        filter: &Value,
+        limit: usize,
+    ) -> Result<Vec<Value>> {
+        let col = self.get_or_create_collection(collection)?;
+        col.read().unwrap().text_search_filtered(query, filter, limit)
+    }
+
+    /// Number of hits `text_search` would return, without reading documents.
+    pub fn text_search_count(&self, collection: &str, query: &str, limit: usize) -> Result<usize> {
+        let col = self.get_or_create_collection(collection)?;
+        col.read().unwrap().text_search_count(query, limit)
+    }
+
+    pub fn create_vector_index(
+        &self,
+        collection: &str,
+        field: &str,
+        dimension: usize,
+        metric: crate::vector::DistanceMetric,
+        params: crate::vector::HnswParams,
+        normalize: bool,
+    ) -> Result<()> {
+        let col = self.get_or_create_collection(collection)?;
+        col.write().unwrap().create_vector_index(field, dimension, metric, params, normalize)
+    }
+
+    pub fn vector_search(
+        &self,
+        collection: &str,
+        field: &str,
+        query_vector: &[f32],
+        limit: usize,
+        opts: &crate::vector::VectorSearchOptions,
+    ) -> Result<Vec<Value>> {
+        let col = self.get_or_create_collection(collection)?;
+        col.read().unwrap().vector_search(field, query_vector, limit, opts)
+    }
+
+    /// Number of hits `vector_search` would return, without reading documents.
+    pub fn vector_search_count(
+        &self,
+        collection: &str,
+        field: &str,
+        query_vector: &[f32],
+        limit: usize,
+        opts: &crate::vector::VectorSearchOptions,
+    ) -> Result<usize> {
+        let col = self.get_or_create_collection(collection)?;
+        col.read().unwrap().vector_search_count(field, query_vector, limit, opts)
+    }
+
+    pub fn aggregate(&self, collection: &str, pipeline_json: &Value) -> Result<Vec<Value>> {
+        let pipeline = Pipeline::parse(pipeline_json)?;
+        let (leading_match, start_idx) = pipeline.take_leading_match();
+
+        let query = match leading_match {
+            Some(q) => q.clone(),
+            None => json!({}),
+        };
+
+        let lookup_fn = |foreign: &str, query: &Value| -> Result<Vec<Value>> {
+            self.find_unlimited(foreign, query)
+        };
+
+        // Fast path: use Arc-based pipeline to avoid cloning all initial docs.
+        // This is critical for aggregation over large datasets (200K+ docs).
+        let col = self.get_or_create_collection(collection)?;
+        let col_guard = col.read().unwrap();
+        let arcs = col_guard.find_arcs(&query)?;
+        let field_indexes = col_guard.field_indexes();
+        let doc_cache = col_guard.doc_cache();
+        pipeline.execute_from_arcs(start_idx, arcs, &lookup_fn, Some(field_indexes), doc_cache)
+    }
+
+    // -----------------------------------------------------------------------
+    // Transaction methods
+    // -----------------------------------------------------------------------
+
+    /// Set the timeout applied to transactions started without an explicit
+    /// one. `None` disables it.
+    pub fn set_default_tx_timeout(&self, timeout: Option<Duration>) {
+        let ms = timeout.map(|t| (t.as_millis() as u64).max(1)).unwrap_or(0);
+        self.default_tx_timeout_ms.store(ms, Ordering::Relaxed);
+    }
+
+    fn default_tx_timeout(&self) -> Option<Duration> {
+        match self.default_tx_timeout_ms.load(Ordering::Relaxed) {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        }
+    }
+
+    /// Begin a new transaction. Returns the transaction ID.
+    pub fn begin_transaction(&self) -> TransactionId {
+        self.begin_transaction_with_timeout(self.default_tx_timeout())
+    }
+
+    /// Begin a new transaction that expires after `timeout`. Once expired,
+    /// every operation on it fails with `TransactionTimedOut`.
+    pub fn begin_transaction_with_timeout(&self, timeout: Option<Duration>) -> TransactionId {
+        let tx_id = self.next_tx_id.fetch_add(1, Ordering::SeqCst);
+        let mut tx = Transaction::new(tx_id);
+        tx.timeout = timeout;
+        self.active_transactions.write().unwrap().insert(tx_id, Mutex::new(tx));
+        tx_id
+    }
+
+    /// Begin a read-only transaction. It records reads like any other
+    /// transaction, rejects writes, and commits as a no-op.
+    pub fn begin_read_transaction(&self) -> TransactionId {
+        let tx_id = self.next_tx_id.fetch_add(1, Ordering::SeqCst);
+        let mut tx = Transaction::new_read_only(tx_id);
+        tx.timeout = self.default_tx_timeout();
+        self.active_transactions.write().unwrap().insert(tx_id, Mutex::new(tx));
+        tx_id
+    }
+
+    /// Fail with `TransactionTimedOut` if the transaction has expired,
+    /// removing it from the active set.
+    fn check_tx_timeout(&self, tx_id: TransactionId) -> Result<()> {
+        let expired = {
+            let txs = self.active_transactions.read().unwrap();
+            let tx_mutex = txs.get(&tx_id).ok_or(Error::TransactionNotFound(tx_id))?;
+            tx_mutex.lock().unwrap().is_expired()
+        };
+        if expired {
+            self.active_transactions.write().unwrap().remove(&tx_id);
+            return Err(Error::TransactionTimedOut(tx_id));
+        }
+        Ok(())
+    }
+
+    /// Remove every transaction that has outlived its timeout.
+    /// Returns the number removed.
+    pub fn reap_expired_transactions(&self) -> usize {
+        let mut txs = self.active_transactions.write().unwrap();
+        let before = txs.len();
+        txs.retain(|_, tx| !tx.get_mut().unwrap().is_expired());
+        before - txs.len()
+    }
+
+    /// Start a background thread that reaps expired transactions every
+    /// `interval`. The thread exits once the database is dropped.
+    pub fn start_tx_reaper(self: &Arc<Self>, interval: Duration) {
+        let db = Arc::downgrade(self);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            match db.upgrade() {
+                Some(db) => {
+                    db.reap_expired_transactions();
+                }
+                None => break,
+            }
+        });
+    }
+
+    /// Buffer an insert within a transaction.
+    pub fn tx_insert(&self, tx_id: TransactionId, collection: &str, doc: Value) -> Result<()> {
+        self.check_tx_timeout(tx_id)?;
+        let txs = self.active_transactions.read().unwrap();
+        let tx_mutex = txs.get(&tx_id).ok_or(Error::TransactionNotFound(tx_id))?;
+        let mut tx = tx_mutex.lock().unwrap();
+        if tx.read_only {
+            return Err(Error::ReadOnlyTransaction(tx_id));
+        }
+        collection::check_document_size(&doc, self.max_document_size())?;
+        tx.collections_involved.insert(collection.to_string());
+        tx.write_ops.push(WriteOp::Insert {
+            collection: collection.to_string(),
+            data: doc,
+        });
+        Ok(())
+    }
+
+    /// Execute a read within a transaction, recording versions for OCC.
+    pub fn tx_find(&self, tx_id: TransactionId, collection: &str, query: &Value) -> Result<Vec<Value>> {
+        self.check_tx_timeout(tx_id)?;
+        let col = self.get_or_create_collection(collection)?;
+        let col_guard = col.read().unwrap();
+        let results = col_guard.find(query)?;
+
+        // Record read versions
+        let txs = self.active_transactions.read().unwrap();
+        let tx_mutex = txs.get(&tx_id).ok_or(Error::TransactionNotFound(tx_id))?;
         let mut tx = tx_mutex.lock().unwrap();
         tx.collections_involved.insert(collection.to_string());
 
@@ -598,6 +1390,7 @@ impl OxiDb {
         query: &Value,
         update: &Value,
     ) -> Result<()> {
+        self.check_tx_timeout(tx_id)?;
         // Read to find matching docs and record their versions
         let col = self.get_or_create_collection(collection)?;
         let col_guard = col.read().unwrap();
@@ -606,6 +1399,9 @@ impl OxiDb {
         let txs = self.active_transactions.read().unwrap();
         let tx_mutex = txs.get(&tx_id).ok_or(Error::TransactionNotFound(tx_id))?;
         let mut tx = tx_mutex.lock().unwrap();
+        if tx.read_only {
+            return Err(Error::ReadOnlyTransaction(tx_id));
+        }
         tx.collections_involved.insert(collection.to_string());
 
         for doc in &matching {
@@ -634,6 +1430,7 @@ impl OxiDb {
         collection: &str,
         query: &Value,
     ) -> Result<()> {
+        self.check_tx_timeout(tx_id)?;
         let col = self.get_or_create_collection(collection)?;
         let col_guard = col.read().unwrap();
         let matching = col_guard.find(query)?;
@@ -641,6 +1438,9 @@ impl OxiDb {
         let txs = self.active_transactions.read().unwrap();
         let tx_mutex = txs.get(&tx_id).ok_or(Error::TransactionNotFound(tx_id))?;
         let mut tx = tx_mutex.lock().unwrap();
+        if tx.read_only {
+            return Err(Error::ReadOnlyTransaction(tx_id));
+        }
         tx.collections_involved.insert(collection.to_string());
 
         for doc in &matching {
@@ -670,6 +1470,14 @@ impl OxiDb {
                 .ok_or(Error::TransactionNotFound(tx_id))?
         };
         let tx = tx.into_inner().unwrap();
+        if tx.is_expired() {
+            return Err(Error::TransactionTimedOut(tx_id));
+        }
+
+        // Read-only: nothing buffered, nothing to validate or log.
+        if tx.read_only {
+            return Ok(());
+        }
 
         // 2. Acquire write locks on all involved collections in BTreeSet order (deadlock-free)
         let mut locked_collections: Vec<(String, Arc<RwLock<Collection>>)> = Vec::new();
@@ -756,9 +1564,13 @@ impl OxiDb {
 
         // 6. COMMIT POINT: mark transaction as committed in the global log
         self.tx_log.mark_committed(tx_id)?;
+        if let Some(history) = self.tx_history.lock().unwrap().as_ref() {
+            history.record(tx_id, chrono::Utc::now().timestamp_millis())?;
+        }
 
         // 7. Collect event data before consuming mutations
         let emit = self.change_broker.has_subscribers();
+        let full_document = self.change_broker.wants_full_document();
         let pending_events: Vec<ChangeEvent> = if emit {
             all_mutations
                 .iter()
@@ -767,6 +1579,7 @@ impl OxiDb {
                         if m.is_delete {
                             ChangeEvent {
                                 token: 0,
+                                timestamp: DateTime::default(),
                                 operation: OperationType::Delete,
                                 collection: col_name.clone(),
                                 doc_id: m.doc_id,
@@ -776,15 +1589,17 @@ impl OxiDb {
                         } else if m.old_loc.is_some() {
                             ChangeEvent {
                                 token: 0,
+                                timestamp: DateTime::default(),
                                 operation: OperationType::Update,
                                 collection: col_name.clone(),
                                 doc_id: m.doc_id,
-                                document: None,
+                                document: if full_document { Some(m.new_data.clone()) } else { None },
                                 tx_id: Some(tx_id),
                             }
                         } else {
                             ChangeEvent {
                                 token: 0,
+                                timestamp: DateTime::default(),
                                 operation: OperationType::Insert,
                                 collection: col_name.clone(),
                                 doc_id: m.doc_id,
@@ -823,6 +1638,61 @@ impl OxiDb {
         Ok(())
     }
 
+    /// Run `f` inside a transaction and commit it, retrying the whole
+    /// transaction on OCC conflicts according to `policy`.
+    ///
+    /// `f` receives a fresh transaction ID on every attempt and should issue
+    /// its `tx_*` calls against it. If `f` fails, the transaction is rolled
+    /// back and the error returned without retrying. After the last attempt
+    /// the conflict is returned to the caller.
+    pub fn run_transaction<T, F>(&self, policy: &RetryPolicy, mut f: F) -> Result<T>
+    where
+        F: FnMut(&OxiDb, TransactionId) -> Result<T>,
+    {
+        let max_attempts = policy.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            let tx_id = self.begin_transaction();
+            let value = match f(self, tx_id) {
+                Ok(v) => v,
+                Err(e) => {
+                    let _ = self.rollback_transaction(tx_id);
+                    return Err(e);
+                }
+            };
+            match self.commit_transaction(tx_id) {
+                Ok(()) => return Ok(value),
+                Err(Error::TransactionConflict { .. }) if attempt < max_attempts => {
+                    std::thread::sleep(policy.backoff(attempt));
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Create a named savepoint at the current position of a transaction's buffers.
+    pub fn tx_savepoint(&self, tx_id: TransactionId, name: &str) -> Result<()> {
+        self.check_tx_timeout(tx_id)?;
+        let txs = self.active_transactions.read().unwrap();
+        let tx_mutex = txs.get(&tx_id).ok_or(Error::TransactionNotFound(tx_id))?;
+        tx_mutex.lock().unwrap().savepoint(name);
+        Ok(())
+    }
+
+    /// Discard operations buffered since the named savepoint. The transaction
+    /// stays open and the savepoint remains usable.
+    pub fn tx_rollback_to(&self, tx_id: TransactionId, name: &str) -> Result<()> {
+        self.check_tx_timeout(tx_id)?;
+        let txs = self.active_transactions.read().unwrap();
+        let tx_mutex = txs.get(&tx_id).ok_or(Error::TransactionNotFound(tx_id))?;
+        if tx_mutex.lock().unwrap().rollback_to(name) {
+            Ok(())
+        } else {
+            Err(Error::SavepointNotFound(name.to_string()))
+        }
+    }
+
     /// Rollback a transaction, discarding all buffered operations.
     pub fn rollback_transaction(&self, tx_id: TransactionId) -> Result<()> {
         let mut txs = self.active_transactions.write().unwrap();
@@ -830,6 +1700,15 @@ impl OxiDb {
         Ok(())
     }
 
+    /// Roll back every open transaction, e.g. when shutting down. Returns
+    /// how many there were.
+    pub fn rollback_all_transactions(&self) -> usize {
+        let mut txs = self.active_transactions.write().unwrap();
+        let count = txs.len();
+        txs.clear();
+        count
+    }
+
     // -----------------------------------------------------------------------
     // Blob storage methods
     // -----------------------------------------------------------------------
@@ -838,6 +1717,11 @@ impl OxiDb {
         self.blob_store.create_bucket(name)
     }
 
+    /// Create a bucket private to `owner`; see `BlobStore::create_owned_bucket`.
+    pub fn create_owned_bucket(&self, name: &str, owner: &str) -> Result<()> {
+        self.blob_store.create_owned_bucket(name, owner)
+    }
+
     pub fn list_buckets(&self) -> Vec<String> {
         self.blob_store.list_buckets()
     }
@@ -868,28 +1752,290 @@ impl OxiDb {
         Ok(serde_json::to_value(&meta)?)
     }
 
+    pub fn create_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: &str,
+        metadata: HashMap<String, String>,
+    ) -> Result<String> {
+        self.blob_store
+            .create_multipart_upload(bucket, key, content_type, metadata)
+    }
+
+    pub fn upload_part(&self, upload_id: &str, part_number: u32, data: &[u8]) -> Result<String> {
+        self.blob_store.upload_part(upload_id, part_number, data)
+    }
+
+    /// Assemble a multipart upload into its final object.
+    pub fn complete_multipart_upload(&self, upload_id: &str) -> Result<Value> {
+        let meta = self.blob_store.complete_multipart_upload(upload_id)?;
+        self.reindex_stored_object(&meta)?;
+        Ok(serde_json::to_value(&meta)?)
+    }
+
+    /// Copy an object server-side. See `BlobStore::copy_object`.
+    pub fn copy_object(
+        &self,
+        src_bucket: &str,
+        src_key: &str,
+        dst_bucket: &str,
+        dst_key: &str,
+        content_type: Option<&str>,
+        metadata: Option<HashMap<String, String>>,
+    ) -> Result<Value> {
+        let meta = self.blob_store.copy_object(
+            src_bucket,
+            src_key,
+            dst_bucket,
+            dst_key,
+            content_type,
+            metadata,
+        )?;
+        self.reindex_stored_object(&meta)?;
+        Ok(serde_json::to_value(&meta)?)
+    }
+
+    /// Index an object whose bytes the caller never held in memory. Objects
+    /// up to `FTS_READBACK_MAX_BYTES` are read back and indexed; larger ones
+    /// are skipped and any stale index entry is dropped.
+    fn reindex_stored_object(&self, meta: &ObjectMeta) -> Result<()> {
+        let job = if meta.size <= FTS_READBACK_MAX_BYTES {
+            let (data, _) = self.blob_store.get_object(&meta.bucket, &meta.key)?;
+            FtsJob::Index {
+                data,
+                content_type: meta.content_type.clone(),
+                bucket: meta.bucket.clone(),
+                key: meta.key.clone(),
+            }
+        } else {
+            FtsJob::Remove {
+                bucket: meta.bucket.clone(),
+                key: meta.key.clone(),
+            }
+        };
+        let _ = self.fts_tx.send(job);
+        Ok(())
+    }
+
+    pub fn abort_multipart_upload(&self, upload_id: &str) -> Result<()> {
+        self.blob_store.abort_multipart_upload(upload_id)
+    }
+
     pub fn get_object(&self, bucket: &str, key: &str) -> Result<(Vec<u8>, Value)> {
         let (data, meta) = self.blob_store.get_object(bucket, key)?;
         Ok((data, serde_json::to_value(&meta)?))
     }
 
-    pub fn head_object(&self, bucket: &str, key: &str) -> Result<Value> {
-        let meta = self.blob_store.head_object(bucket, key)?;
-        Ok(serde_json::to_value(&meta)?)
+    /// `get_object` that fails with `ChecksumMismatch` if the content no
+    /// longer matches its stored SHA-256.
+    pub fn get_object_verified(&self, bucket: &str, key: &str) -> Result<(Vec<u8>, Value)> {
+        let (data, meta) = self.blob_store.get_object_verified(bucket, key)?;
+        Ok((data, serde_json::to_value(&meta)?))
     }
 
-    pub fn delete_object(&self, bucket: &str, key: &str) -> Result<()> {
-        self.blob_store.delete_object(bucket, key)?;
-
-        let _ = self.fts_tx.send(FtsJob::Remove {
-            bucket: bucket.to_string(),
-            key: key.to_string(),
-        });
+    /// Recompute an object's SHA-256 and compare it with the stored one.
+    /// See `BlobStore::verify_object`.
+    pub fn verify_object(&self, bucket: &str, key: &str) -> Result<Option<String>> {
+        self.blob_store.verify_object(bucket, key)
+    }
 
-        Ok(())
+    /// Read a byte range of an object. See `BlobStore::get_object_range`.
+    pub fn get_object_range(
+        &self,
+        bucket: &str,
+        key: &str,
+        start: Option<u64>,
+        end: Option<u64>,
+    ) -> Result<(ObjectRange, Value)> {
+        let (range, meta) = self.blob_store.get_object_range(bucket, key, start, end)?;
+        Ok((range, serde_json::to_value(&meta)?))
     }
 
-    pub fn list_objects(
+    pub fn get_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<(Vec<u8>, Value)> {
+        let (data, meta) = self.blob_store.get_object_version(bucket, key, version_id)?;
+        Ok((data, serde_json::to_value(&meta)?))
+    }
+
+    pub fn list_object_versions(&self, bucket: &str, key: &str) -> Result<Vec<Value>> {
+        let versions = self.blob_store.list_object_versions(bucket, key)?;
+        versions
+            .iter()
+            .map(|m| serde_json::to_value(m).map_err(Error::from))
+            .collect()
+    }
+
+    pub fn set_bucket_versioning(&self, bucket: &str, enabled: bool) -> Result<()> {
+        self.blob_store.set_versioning(bucket, enabled)
+    }
+
+    pub fn is_bucket_versioning_enabled(&self, bucket: &str) -> bool {
+        self.blob_store.is_versioning_enabled(bucket)
+    }
+
+    /// Set or remove a bucket's access control list (creating the bucket if
+    /// needed). The server enforces it; the engine itself doesn't.
+    pub fn set_bucket_acl(&self, bucket: &str, acl: Option<BucketAcl>) -> Result<()> {
+        self.blob_store.set_bucket_acl(bucket, acl)
+    }
+
+    pub fn bucket_acl(&self, bucket: &str) -> Option<BucketAcl> {
+        self.blob_store.bucket_acl(bucket)
+    }
+
+    /// The bucket an in-progress multipart upload targets.
+    pub fn multipart_upload_bucket(&self, upload_id: &str) -> Option<String> {
+        self.blob_store.upload_bucket(upload_id)
+    }
+
+    /// Choose the full-text tokenizer for a bucket's blobs (creating the
+    /// bucket if needed) and re-index the objects already in it. Objects
+    /// over `FTS_READBACK_MAX_BYTES` aren't read back: they drop out of
+    /// search until they're written again, and their keys are returned.
+    pub fn set_bucket_tokenizer(&self, bucket: &str, tokenizer: Tokenizer) -> Result<Vec<String>> {
+        self.blob_store.create_bucket(bucket)?;
+        let objects = self.blob_store.list_objects(bucket, None, None)?;
+        self.fts_index.write().unwrap().set_tokenizer(bucket, tokenizer)?;
+        let mut skipped = Vec::new();
+        for meta in &objects {
+            if meta.size > FTS_READBACK_MAX_BYTES {
+                skipped.push(meta.key.clone());
+            }
+            self.reindex_stored_object(meta)?;
+        }
+        Ok(skipped)
+    }
+
+    pub fn bucket_tokenizer(&self, bucket: &str) -> Tokenizer {
+        self.fts_index.read().unwrap().tokenizer(bucket)
+    }
+
+    pub fn head_object(&self, bucket: &str, key: &str) -> Result<Value> {
+        let meta = self.blob_store.head_object(bucket, key)?;
+        Ok(serde_json::to_value(&meta)?)
+    }
+
+    pub fn delete_object(&self, bucket: &str, key: &str) -> Result<()> {
+        self.blob_store.delete_object(bucket, key)?;
+
+        let _ = self.fts_tx.send(FtsJob::Remove {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+        });
+
+        Ok(())
+    }
+
+    /// Delete every blob whose `expires_at` has passed and drop it from the
+    /// full-text index. Returns the number of objects removed.
+    pub fn purge_expired_objects(&self) -> Result<usize> {
+        let removed = self.blob_store.purge_expired()?;
+        for (bucket, key) in &removed {
+            let _ = self.fts_tx.send(FtsJob::Remove {
+                bucket: bucket.clone(),
+                key: key.clone(),
+            });
+        }
+        Ok(removed.len())
+    }
+
+    /// Delete every document whose `_expireAt` has passed, across the loaded
+    /// collections. Returns the number of documents removed.
+    pub fn purge_expired_documents(&self) -> Result<usize> {
+        let now = chrono::Utc::now();
+        let names: Vec<String> = self.collections.read().unwrap().keys().cloned().collect();
+        let mut removed = 0;
+        for name in names {
+            removed += self.purge_expired_documents_in(&name, now)?;
+        }
+        Ok(removed)
+    }
+
+    /// Delete the documents of `collection` whose `_expireAt` is at or before
+    /// `now`. Replicas apply this with the leader's `now`, so each removes
+    /// the same documents. Returns the number removed.
+    pub fn purge_expired_documents_in(&self, collection: &str, now: DateTime<chrono::Utc>) -> Result<usize> {
+        let col = self.get_or_create_collection(collection)?;
+        let ids = col.write().unwrap().delete_expired(now)?;
+        if self.change_broker.has_subscribers() {
+            for &id in &ids {
+                self.change_broker.emit(ChangeEvent {
+                    token: 0,
+                    timestamp: DateTime::default(),
+                    operation: OperationType::Delete,
+                    collection: collection.to_string(),
+                    doc_id: id,
+                    document: None,
+                    tx_id: None,
+                });
+            }
+        }
+        Ok(ids.len())
+    }
+
+    /// Names of the loaded collections holding a document whose `_expireAt`
+    /// is at or before `now`.
+    pub fn collections_with_expired_documents(&self, now: DateTime<chrono::Utc>) -> Result<Vec<String>> {
+        let cols: Vec<_> = self
+            .collections
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, col)| (name.clone(), Arc::clone(col)))
+            .collect();
+        let mut names = Vec::new();
+        for (name, col) in cols {
+            if col.read().unwrap().has_expired(now)? {
+                names.push(name);
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Start a background thread that deletes documents past their
+    /// `_expireAt` every `interval`. The thread exits once the database is
+    /// dropped. Not for replicated databases, where only the leader may
+    /// sweep, by proposing `purge_expired_documents_in` for each of
+    /// `collections_with_expired_documents`.
+    pub fn start_ttl_sweeper(self: &Arc<Self>, interval: Duration) {
+        let db = Arc::downgrade(self);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            match db.upgrade() {
+                Some(db) => {
+                    if let Err(e) = db.purge_expired_documents() {
+                        eprintln!("[warn] TTL sweep failed: {e}");
+                    }
+                }
+                None => break,
+            }
+        });
+    }
+
+    /// Start a background thread that purges expired blobs every `interval`.
+    /// The thread exits once the database is dropped.
+    pub fn start_blob_sweeper(self: &Arc<Self>, interval: Duration) {
+        let db = Arc::downgrade(self);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            match db.upgrade() {
+                Some(db) => {
+                    if let Err(e) = db.purge_expired_objects() {
+                        eprintln!("[warn] blob sweep failed: {e}");
+                    }
+                }
+                None => break,
+            }
+        });
+    }
+
+    pub fn list_objects(
         &self,
         bucket: &str,
         prefix: Option<&str>,
@@ -902,13 +2048,16 @@ impl OxiDb {
             .collect()
     }
 
+    /// Full-text search over blobs; `offset` skips that many results for
+    /// paging.
     pub fn search(
         &self,
         bucket: Option<&str>,
         query: &str,
+        offset: usize,
         limit: usize,
     ) -> Result<Vec<Value>> {
-        let results = self.fts_index.read().unwrap().search(bucket, query, limit);
+        let results = self.fts_index.read().unwrap().search(bucket, query, offset, limit);
         Ok(results
             .into_iter()
             .map(|r| {
@@ -1029,6 +2178,22 @@ impl OxiDb {
         if let Some(every_str) = def.get("every").and_then(|v| v.as_str()) {
             crate::scheduler::parse_interval(every_str)?;
         }
+        // Validate run history cap if present
+        if def.get("history_limit").is_some_and(|v| !v.is_u64()) {
+            return Err(Error::ScheduleError(
+                "'history_limit' must be a non-negative integer".into(),
+            ));
+        }
+        // Validate timezone if present
+        match def.get("timezone") {
+            None | Some(Value::Null) => {}
+            Some(Value::String(tz)) => {
+                crate::scheduler::parse_timezone(tz)?;
+            }
+            Some(_) => {
+                return Err(Error::ScheduleError("'timezone' must be a string".into()));
+            }
+        }
 
         // Validate procedure exists
         let procedure = def
@@ -1081,14 +2246,29 @@ impl OxiDb {
             .ok_or_else(|| Error::ScheduleError(format!("schedule not found: {name}")))
     }
 
-    /// Delete a schedule by name.
+    /// Recent runs of a schedule, most recent first: `timestamp`, `status`,
+    /// `duration_ms` and `error` for each. At most `limit` runs if given.
+    pub fn schedule_history(&self, name: &str, limit: Option<usize>) -> Result<Vec<Value>> {
+        self.get_schedule(name)?;
+        let mut runs = self.find_unlimited(crate::scheduler::RUNS_COLLECTION, &json!({"schedule": name}))?;
+        runs.sort_by_key(|run| std::cmp::Reverse(run.get("_id").and_then(|v| v.as_u64())));
+        if let Some(limit) = limit {
+            runs.truncate(limit);
+        }
+        Ok(runs)
+    }
+
+    /// Delete a schedule by name, along with its run history.
     pub fn delete_schedule(&self, name: &str) -> Result<()> {
-        let col = self.get_or_create_collection("_schedules")?;
-        let mut col_guard = col.write().unwrap();
-        let deleted = col_guard.delete(&json!({"name": name}), None)?;
-        if deleted.is_empty() {
-            return Err(Error::ScheduleError(format!("schedule not found: {name}")));
+        {
+            let col = self.get_or_create_collection("_schedules")?;
+            let mut col_guard = col.write().unwrap();
+            let deleted = col_guard.delete(&json!({"name": name}), None)?;
+            if deleted.is_empty() {
+                return Err(Error::ScheduleError(format!("schedule not found: {name}")));
+            }
         }
+        self.delete(crate::scheduler::RUNS_COLLECTION, &json!({"schedule": name}))?;
         Ok(())
     }
 
@@ -1104,6 +2284,22 @@ impl OxiDb {
         Ok(())
     }
 
+    /// Run a schedule's procedure immediately with its stored params,
+    /// recording the run exactly as the scheduler would, and return the
+    /// procedure's result. Fails if the schedule is already running.
+    pub fn run_schedule_now(&self, name: &str) -> Result<Value> {
+        let sched = self.get_schedule(name)?;
+        let run: crate::scheduler::RunFn = Arc::new(|db: &OxiDb, procedure: &str, params: Value| {
+            db.call_procedure(procedure, params)
+        });
+        crate::scheduler::run_schedule_now(self, &self.schedule_runs, &sched, &run)
+    }
+
+    /// Schedules currently running.
+    pub(crate) fn schedule_runs(&self) -> &crate::scheduler::RunTracker {
+        &self.schedule_runs
+    }
+
     /// Disable (pause) a schedule.
     pub fn disable_schedule(&self, name: &str) -> Result<()> {
         // Verify it exists
@@ -1124,13 +2320,38 @@ impl OxiDb {
     ///
     /// The backup flushes all indexes and WAL checkpoints before archiving,
     /// then holds read locks on all collections to ensure a consistent snapshot.
+    /// A manifest is written next to the archive (see `backup_manifest_path`)
+    /// so it can serve as the base of an incremental backup.
     pub fn backup(&self, output_path: &Path) -> Result<BackupInfo> {
-        // 1. Validate output path doesn't already exist
-        if output_path.exists() {
-            return Err(Error::Backup(format!(
-                "output path already exists: {}",
-                output_path.display()
-            )));
+        self.backup_since(output_path, None)
+    }
+
+    /// Create an incremental backup containing only the collections that
+    /// changed since the backup described by `previous_manifest`, plus all
+    /// non-collection files (blobs, full-text index, etc.). Restore it on top
+    /// of its base with `restore_chain`.
+    pub fn backup_incremental(&self, output_path: &Path, previous_manifest: &Path) -> Result<BackupInfo> {
+        let previous = BackupManifest::load(previous_manifest)?;
+        self.backup_since(output_path, Some(&previous))
+    }
+
+    /// Path of the manifest written next to a backup archive.
+    pub fn backup_manifest_path(archive_path: &Path) -> PathBuf {
+        let mut name = archive_path.file_name().unwrap_or_default().to_os_string();
+        name.push(".manifest.json");
+        archive_path.with_file_name(name)
+    }
+
+    fn backup_since(&self, output_path: &Path, previous: Option<&BackupManifest>) -> Result<BackupInfo> {
+        // 1. Validate output paths don't already exist
+        let manifest_path = Self::backup_manifest_path(output_path);
+        for path in [output_path, manifest_path.as_path()] {
+            if path.exists() {
+                return Err(Error::Backup(format!(
+                    "output path already exists: {}",
+                    path.display()
+                )));
+            }
         }
 
         // Ensure parent directory exists
@@ -1140,15 +2361,40 @@ impl OxiDb {
             }
         }
 
-        // 2. Discover all collection names from .dat files on disk
+        // 2. Write the archive, then the manifest next to it
+        let file = std::fs::File::create(output_path)?;
+        let manifest = self.write_backup(file, previous)?;
+        std::fs::write(&manifest_path, serde_json::to_vec_pretty(&manifest)?)?;
+
+        // 3. Return info
+        let metadata = std::fs::metadata(output_path)?;
+        Ok(BackupInfo {
+            path: output_path.to_string_lossy().into_owned(),
+            size_bytes: metadata.len(),
+            collections: manifest.collections.len(),
+            archived_collections: manifest.archived.len(),
+            manifest_path: Some(manifest_path.to_string_lossy().into_owned()),
+        })
+    }
+
+    /// Write a full tar.gz backup into `writer` instead of a file, e.g. to
+    /// stream it to object storage or over a socket. The archive is identical
+    /// to the one `backup` produces and embeds its manifest, which is also
+    /// returned. The writer is not flushed beyond the end of the gzip stream.
+    pub fn backup_to_writer<W: Write>(&self, writer: W) -> Result<BackupManifest> {
+        self.write_backup(writer, None)
+    }
+
+    fn write_backup<W: Write>(&self, writer: W, previous: Option<&BackupManifest>) -> Result<BackupManifest> {
+        // 1. Discover all collection names from .dat files on disk
         let disk_names = Self::discover_collection_names_on_disk(&self.data_dir)?;
 
-        // 3. Ensure all collections are loaded
+        // 2. Ensure all collections are loaded
         for name in &disk_names {
             let _ = self.get_or_create_collection(name)?;
         }
 
-        // 4. Flush indexes and checkpoint WALs for each collection
+        // 3. Flush indexes and checkpoint WALs for each collection
         {
             let cols = self.collections.read().unwrap();
             for col_arc in cols.values() {
@@ -1158,37 +2404,104 @@ impl OxiDb {
             }
         }
 
-        // 5. Acquire read locks on all collections for consistent snapshot
+        // 4. Acquire read locks on all collections for consistent snapshot
         let cols = self.collections.read().unwrap();
         let _read_guards: Vec<_> = cols.values()
             .map(|c| c.read().unwrap())
             .collect();
 
+        // 5. Fingerprint collections and pick the ones to archive
+        let mut collections = BTreeMap::new();
+        for name in &disk_names {
+            collections.insert(name.clone(), self.fingerprint_collection(name)?);
+        }
+        let archived: Vec<String> = collections
+            .iter()
+            .filter(|(name, fp)| previous.is_none_or(|p| p.collections.get(*name) != Some(*fp)))
+            .map(|(name, _)| name.clone())
+            .collect();
+        let manifest = BackupManifest {
+            created_at: chrono::Utc::now().to_rfc3339(),
+            incremental: previous.is_some(),
+            collections,
+            archived,
+        };
+        let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+
         // 6. Create tar.gz archive
-        let file = std::fs::File::create(output_path)?;
-        let enc = GzEncoder::new(file, Compression::default());
+        let enc = GzEncoder::new(writer, Compression::default());
         let mut archive = tar::Builder::new(enc);
 
-        Self::add_dir_to_tar(&mut archive, &self.data_dir, &self.data_dir)?;
+        let skip = |path: &Path| match Self::collection_of_file(&self.data_dir, path) {
+            Some(name) => !manifest.archived.contains(&name),
+            None => false,
+        };
+        Self::add_dir_to_tar(&mut archive, &self.data_dir, &self.data_dir, &skip)?;
+
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest_json.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        archive
+            .append_data(&mut header, BACKUP_MANIFEST_NAME, manifest_json.as_slice())
+            .map_err(|e| Error::Backup(e.to_string()))?;
 
         let enc = archive.into_inner().map_err(|e| Error::Backup(e.to_string()))?;
         enc.finish().map_err(|e| Error::Backup(e.to_string()))?;
+        Ok(manifest)
+    }
+
+    /// Back up a single collection's files (`.dat`, `.wal`, `.idx` and index
+    /// caches) to a tar.gz archive. Only that collection is locked.
+    pub fn backup_collection(&self, name: &str, output_path: &Path) -> Result<BackupInfo> {
+        if output_path.exists() {
+            return Err(Error::Backup(format!(
+                "output path already exists: {}",
+                output_path.display()
+            )));
+        }
+        if !self.data_dir.join(format!("{}.dat", name)).exists() {
+            return Err(Error::CollectionNotFound(name.to_string()));
+        }
+        if let Some(parent) = output_path.parent().filter(|p| !p.exists()) {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let col = self.get_or_create_collection(name)?;
+        let guard = col.read().unwrap();
+        guard.save_index_data();
+        guard.checkpoint_wal()?;
+
+        let file = std::fs::File::create(output_path)?;
+        let enc = GzEncoder::new(file, Compression::default());
+        let mut archive = tar::Builder::new(enc);
+        for ext in COLLECTION_FILE_EXTENSIONS {
+            let file_name = format!("{}.{}", name, ext);
+            let path = self.data_dir.join(&file_name);
+            if path.exists() {
+                archive
+                    .append_path_with_name(&path, &file_name)
+                    .map_err(|e| Error::Backup(e.to_string()))?;
+            }
+        }
+        let enc = archive.into_inner().map_err(|e| Error::Backup(e.to_string()))?;
+        enc.finish().map_err(|e| Error::Backup(e.to_string()))?;
+        drop(guard);
 
-        // 7. Return info
-        let metadata = std::fs::metadata(output_path)?;
         Ok(BackupInfo {
             path: output_path.to_string_lossy().into_owned(),
-            size_bytes: metadata.len(),
-            collections: disk_names.len(),
+            size_bytes: std::fs::metadata(output_path)?.len(),
+            collections: 1,
+            archived_collections: 1,
+            manifest_path: None,
         })
     }
 
-    /// Restore a tar.gz backup archive to a target directory.
-    ///
-    /// This is a static method — the caller should open a new `OxiDb` instance
-    /// on the target directory after restoration.
-    pub fn restore(archive_path: &Path, target_dir: &Path) -> Result<RestoreInfo> {
-        // 1. Validate archive exists
+    /// Restore a single-collection archive from `backup_collection` into this
+    /// live database as collection `name`, which may differ from the name it
+    /// was backed up under. An existing collection of that name is replaced:
+    /// its files are swapped out and it is reloaded from the restored data.
+    pub fn restore_collection(&self, archive_path: &Path, name: &str) -> Result<RestoreInfo> {
         if !archive_path.exists() {
             return Err(Error::Backup(format!(
                 "archive not found: {}",
@@ -1196,94 +2509,436 @@ impl OxiDb {
             )));
         }
 
-        // 2. Validate target directory is empty or doesn't exist
-        if target_dir.exists() {
-            let has_entries = std::fs::read_dir(target_dir)?
-                .next()
-                .is_some();
-            if has_entries {
-                return Err(Error::Backup(format!(
-                    "target directory is not empty: {}",
-                    target_dir.display()
-                )));
+        // Extract next to the live files without holding any lock
+        let mut staged: Vec<(PathBuf, PathBuf)> = Vec::new();
+        if let Err(e) = self.stage_collection_archive(archive_path, name, &mut staged) {
+            for (tmp, _) in &staged {
+                let _ = std::fs::remove_file(tmp);
             }
-        } else {
-            std::fs::create_dir_all(target_dir)?;
+            return Err(e);
         }
 
-        // 3. Extract tar.gz into target directory
-        let file = std::fs::File::open(archive_path)?;
-        let dec = GzDecoder::new(file);
-        let mut archive = tar::Archive::new(dec);
-        archive.unpack(target_dir)?;
-
-        // 4. Count .dat files
-        let collections = Self::discover_collection_names_on_disk(target_dir)?;
+        // Swap the files in while the collection is out of the map
+        {
+            let mut cols = self.collections.write().unwrap();
+            if let Some(old) = cols.remove(name) {
+                // Wait for in-flight operations on the old collection
+                drop(old.write().unwrap());
+            }
+            for ext in COLLECTION_FILE_EXTENSIONS {
+                let path = self.data_dir.join(format!("{}.{}", name, ext));
+                if path.exists() {
+                    std::fs::remove_file(path)?;
+                }
+            }
+            self.remove_wal_history(name)?;
+            for (tmp, live) in &staged {
+                std::fs::rename(tmp, live)?;
+            }
+        }
+        self.get_or_create_collection(name)?;
 
         Ok(RestoreInfo {
-            path: target_dir.to_string_lossy().into_owned(),
-            collections: collections.len(),
+            path: self.data_dir.to_string_lossy().into_owned(),
+            collections: 1,
         })
     }
 
-    /// Scan a directory for `*.dat` files and return collection names.
-    fn discover_collection_names_on_disk(dir: &Path) -> Result<Vec<String>> {
-        let mut names = Vec::new();
-        if !dir.exists() {
-            return Ok(names);
-        }
-        for entry in std::fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.extension().and_then(|e| e.to_str()) == Some("dat") {
-                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                    names.push(stem.to_string());
+    /// Extract a single-collection archive into `<name>.<ext>.tmp` files in
+    /// the data directory, recording (staged, live) path pairs in `staged`.
+    fn stage_collection_archive(
+        &self,
+        archive_path: &Path,
+        name: &str,
+        staged: &mut Vec<(PathBuf, PathBuf)>,
+    ) -> Result<()> {
+        let file = std::fs::File::open(archive_path)?;
+        let mut archive = tar::Archive::new(GzDecoder::new(file));
+        let mut source: Option<String> = None;
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let entry_path = entry.path()?.into_owned();
+            let (Some(stem), Some(ext)) = (
+                entry_path.file_stem().and_then(|s| s.to_str()),
+                entry_path.extension().and_then(|e| e.to_str()),
+            ) else {
+                continue;
+            };
+            if entry_path.components().count() != 1 || !COLLECTION_FILE_EXTENSIONS.contains(&ext) {
+                continue;
+            }
+            match &source {
+                Some(src) if src != stem => {
+                    return Err(Error::Backup(
+                        "archive contains more than one collection".into(),
+                    ));
                 }
+                Some(_) => {}
+                None => source = Some(stem.to_string()),
             }
+            let live = self.data_dir.join(format!("{}.{}", name, ext));
+            let tmp = self.data_dir.join(format!("{}.{}.tmp", name, ext));
+            entry.unpack(&tmp)?;
+            staged.push((tmp, live));
         }
-        Ok(names)
+        if source.is_none() || !staged.iter().any(|(_, live)| live.extension().is_some_and(|e| e == "dat")) {
+            return Err(Error::Backup(format!(
+                "no collection data file in {}",
+                archive_path.display()
+            )));
+        }
+        Ok(())
     }
 
-    /// Recursively add directory contents to a tar archive, skipping `.tmp` files.
-    fn add_dir_to_tar<W: std::io::Write>(
-        archive: &mut tar::Builder<W>,
-        dir: &Path,
-        base: &Path,
-    ) -> Result<()> {
-        for entry in std::fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
+    /// Size and checksums of a collection's `.dat` and `.idx` files.
+    fn fingerprint_collection(&self, name: &str) -> Result<CollectionFingerprint> {
+        let dat_path = self.data_dir.join(format!("{}.dat", name));
+        let idx_path = self.data_dir.join(format!("{}.idx", name));
+        Ok(CollectionFingerprint {
+            dat_size: std::fs::metadata(&dat_path)?.len(),
+            dat_crc32: Self::file_crc32(&dat_path)?,
+            idx_crc32: if idx_path.exists() { Self::file_crc32(&idx_path)? } else { 0 },
+        })
+    }
 
-            // Skip .tmp files
-            if path.extension().and_then(|e| e.to_str()) == Some("tmp") {
-                continue;
+    fn file_crc32(path: &Path) -> Result<u32> {
+        let mut file = std::fs::File::open(path)?;
+        let mut hasher = crc32fast::Hasher::new();
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                break;
             }
+            hasher.update(&buf[..n]);
+        }
+        Ok(hasher.finalize())
+    }
 
-            let rel = path.strip_prefix(base).unwrap_or(&path);
-
-            if path.is_dir() {
-                Self::add_dir_to_tar(archive, &path, base)?;
-            } else if path.is_file() {
-                archive
-                    .append_path_with_name(&path, rel)
-                    .map_err(|e| Error::Backup(e.to_string()))?;
-            }
+    /// The collection a top-level data directory file belongs to, if any.
+    fn collection_of_file(data_dir: &Path, path: &Path) -> Option<String> {
+        if path.parent() != Some(data_dir) {
+            return None;
         }
-        Ok(())
+        let ext = path.extension()?.to_str()?;
+        if !COLLECTION_FILE_EXTENSIONS.contains(&ext) {
+            return None;
+        }
+        let stem = path.file_stem()?.to_str()?;
+        data_dir
+            .join(format!("{}.dat", stem))
+            .exists()
+            .then(|| stem.to_string())
     }
-}
 
-impl Drop for OxiDb {
-    fn drop(&mut self) {
-        // Shut down the scheduler thread (dropping the sender causes it to exit)
-        let _ = self.scheduler_shutdown.lock().unwrap().take();
-        self.flush_indexes();
+    /// Read the manifest stored in a backup archive. Archives created before
+    /// manifests were added have none and are treated as full backups.
+    fn read_archive_manifest(archive_path: &Path) -> Result<Option<BackupManifest>> {
+        let file = std::fs::File::open(archive_path)?;
+        let mut archive = tar::Archive::new(GzDecoder::new(file));
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if entry.path()?.as_os_str() == BACKUP_MANIFEST_NAME {
+                let mut bytes = Vec::new();
+                entry.read_to_end(&mut bytes)?;
+                let manifest = serde_json::from_slice(&bytes)
+                    .map_err(|e| Error::Backup(format!("invalid backup manifest: {}", e)))?;
+                return Ok(Some(manifest));
+            }
+        }
+        Ok(None)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Restore a tar.gz backup archive to a target directory.
+    ///
+    /// This is a static method — the caller should open a new `OxiDb` instance
+    /// on the target directory after restoration.
+    pub fn restore(archive_path: &Path, target_dir: &Path) -> Result<RestoreInfo> {
+        // 1. Validate archive exists and is a full backup
+        if !archive_path.exists() {
+            return Err(Error::Backup(format!(
+                "archive not found: {}",
+                archive_path.display()
+            )));
+        }
+        if Self::read_archive_manifest(archive_path)?.is_some_and(|m| m.incremental) {
+            return Err(Error::Backup(format!(
+                "{} is an incremental backup; restore it on top of its base with restore_chain",
+                archive_path.display()
+            )));
+        }
+
+        let file = std::fs::File::open(archive_path)?;
+        Self::restore_from_reader(file, target_dir)
+    }
+
+    /// Restore a full backup read from `reader` (e.g. the output of
+    /// `backup_to_writer`) into `target_dir`, which must be empty or not
+    /// exist. Incremental archives are rejected after extraction and the
+    /// extracted files are removed again.
+    pub fn restore_from_reader<R: Read>(reader: R, target_dir: &Path) -> Result<RestoreInfo> {
+        // 1. Validate target directory is empty or doesn't exist
+        Self::prepare_restore_target(target_dir)?;
+
+        // 2. Extract tar.gz into target directory
+        let dec = GzDecoder::new(reader);
+        let mut archive = tar::Archive::new(dec);
+        archive.unpack(target_dir)?;
+        let manifest_path = target_dir.join(BACKUP_MANIFEST_NAME);
+        if manifest_path.exists() && BackupManifest::load(&manifest_path)?.incremental {
+            // The target was empty, so everything in it came from the archive
+            for entry in std::fs::read_dir(target_dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    std::fs::remove_dir_all(&path)?;
+                } else {
+                    std::fs::remove_file(&path)?;
+                }
+            }
+            return Err(Error::Backup(
+                "archive is an incremental backup; restore it on top of its base with restore_chain".to_string(),
+            ));
+        }
+        Self::remove_restored_manifest(target_dir)?;
+
+        // 3. Count .dat files
+        let collections = Self::discover_collection_names_on_disk(target_dir)?;
+
+        Ok(RestoreInfo {
+            path: target_dir.to_string_lossy().into_owned(),
+            collections: collections.len(),
+        })
+    }
+
+    /// Rebuild the database in `data_dir` as it was right after transaction
+    /// `tx_id` committed, into `target_dir` (which must be empty or not
+    /// exist). Requires WAL retention (see `set_wal_retention`).
+    ///
+    /// Committed transactions up to and including `tx_id` are replayed in
+    /// commit order; later ones are ignored. Non-transactional writes are
+    /// included if they were checkpointed no later than `tx_id` committed.
+    /// `data_dir` may belong to a running database.
+    pub fn restore_to_tx(data_dir: &Path, target_dir: &Path, tx_id: TransactionId) -> Result<RestoreInfo> {
+        let history = Self::read_tx_history(data_dir)?;
+        let pos = history
+            .iter()
+            .position(|&(id, _)| id == tx_id)
+            .ok_or_else(|| {
+                Error::Backup(format!("transaction {} is not in the retained history", tx_id))
+            })?;
+        let committed_at_ms = history[pos].1;
+        let included = history[..=pos].iter().map(|&(id, _)| id).collect();
+        Self::replay_wal_history(data_dir, target_dir, &included, committed_at_ms)
+    }
+
+    /// Rebuild the database in `data_dir` as it was at `at`, into
+    /// `target_dir`: transactions committed and non-transactional writes
+    /// checkpointed no later than `at` are replayed. Requires WAL retention
+    /// (see `set_wal_retention`); nothing before retention was enabled can
+    /// be recovered.
+    pub fn restore_to_time(data_dir: &Path, target_dir: &Path, at: DateTime<chrono::Utc>) -> Result<RestoreInfo> {
+        let at_ms = at.timestamp_millis();
+        let included = Self::read_tx_history(data_dir)?
+            .into_iter()
+            .filter(|&(_, committed_at_ms)| committed_at_ms <= at_ms)
+            .map(|(id, _)| id)
+            .collect();
+        Self::replay_wal_history(data_dir, target_dir, &included, at_ms)
+    }
+
+    fn read_tx_history(data_dir: &Path) -> Result<Vec<(TransactionId, i64)>> {
+        if !TxHistory::exists(data_dir) {
+            return Err(Error::Backup(format!(
+                "WAL retention is not enabled for {}",
+                data_dir.display()
+            )));
+        }
+        TxHistory::read(data_dir)
+    }
+
+    /// Write each collection's retained history, cut at the target, as the
+    /// WAL of an empty collection in `target_dir`. The target database
+    /// applies it through normal WAL recovery when it is opened.
+    fn replay_wal_history(
+        data_dir: &Path,
+        target_dir: &Path,
+        included_txs: &HashSet<TransactionId>,
+        cutoff_ms: i64,
+    ) -> Result<RestoreInfo> {
+        Self::prepare_restore_target(target_dir)?;
+
+        let mut collections = 0;
+        for entry in std::fs::read_dir(data_dir)? {
+            let path = entry?.path();
+            let Some(name) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_suffix(crate::wal::HISTORY_SUFFIX))
+            else {
+                continue;
+            };
+
+            crate::wal::write_history_as_wal(&path, &target_dir.join(format!("{}.wal", name)), |tx_id, at_ms| {
+                if tx_id == 0 {
+                    at_ms <= cutoff_ms
+                } else {
+                    included_txs.contains(&tx_id)
+                }
+            })?;
+            std::fs::File::create(target_dir.join(format!("{}.dat", name)))?;
+            // Index definitions (not the cached index data, which is rebuilt)
+            let idx_path = data_dir.join(format!("{}.idx", name));
+            if idx_path.exists() {
+                std::fs::copy(&idx_path, target_dir.join(format!("{}.idx", name)))?;
+            }
+            collections += 1;
+        }
+
+        Ok(RestoreInfo {
+            path: target_dir.to_string_lossy().into_owned(),
+            collections,
+        })
+    }
+
+    /// Check that a restore target is empty, creating it if missing.
+    fn prepare_restore_target(target_dir: &Path) -> Result<()> {
+        if target_dir.exists() {
+            let has_entries = std::fs::read_dir(target_dir)?
+                .next()
+                .is_some();
+            if has_entries {
+                return Err(Error::Backup(format!(
+                    "target directory is not empty: {}",
+                    target_dir.display()
+                )));
+            }
+        } else {
+            std::fs::create_dir_all(target_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Restore a full backup followed by incremental backups, in order.
+    ///
+    /// Each incremental replaces the collections it archived and removes
+    /// collections that no longer existed when it was taken.
+    pub fn restore_chain<P: AsRef<Path>>(archives: &[P], target_dir: &Path) -> Result<RestoreInfo> {
+        let (base, incrementals) = archives
+            .split_first()
+            .ok_or_else(|| Error::Backup("no backup archives given".into()))?;
+        Self::restore(base.as_ref(), target_dir)?;
+
+        for archive_path in incrementals {
+            let archive_path = archive_path.as_ref();
+            if !archive_path.exists() {
+                return Err(Error::Backup(format!(
+                    "archive not found: {}",
+                    archive_path.display()
+                )));
+            }
+            let manifest = Self::read_archive_manifest(archive_path)?
+                .filter(|m| m.incremental)
+                .ok_or_else(|| {
+                    Error::Backup(format!(
+                        "not an incremental backup: {}",
+                        archive_path.display()
+                    ))
+                })?;
+
+            // Drop the files of replaced and deleted collections first so no
+            // stale index cache survives next to the new data file
+            for name in Self::discover_collection_names_on_disk(target_dir)? {
+                if manifest.archived.contains(&name) || !manifest.collections.contains_key(&name) {
+                    for ext in COLLECTION_FILE_EXTENSIONS {
+                        let path = target_dir.join(format!("{}.{}", name, ext));
+                        if path.exists() {
+                            std::fs::remove_file(path)?;
+                        }
+                    }
+                }
+            }
+
+            let file = std::fs::File::open(archive_path)?;
+            let mut archive = tar::Archive::new(GzDecoder::new(file));
+            archive.unpack(target_dir)?;
+            Self::remove_restored_manifest(target_dir)?;
+        }
+
+        let collections = Self::discover_collection_names_on_disk(target_dir)?;
+        Ok(RestoreInfo {
+            path: target_dir.to_string_lossy().into_owned(),
+            collections: collections.len(),
+        })
+    }
+
+    fn remove_restored_manifest(target_dir: &Path) -> Result<()> {
+        let path = target_dir.join(BACKUP_MANIFEST_NAME);
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Scan a directory for `*.dat` files and return collection names.
+    fn discover_collection_names_on_disk(dir: &Path) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        if !dir.exists() {
+            return Ok(names);
+        }
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("dat") {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    names.push(stem.to_string());
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    /// Recursively add directory contents to a tar archive, skipping `.tmp`
+    /// files and any file for which `skip` returns true.
+    fn add_dir_to_tar<W: std::io::Write>(
+        archive: &mut tar::Builder<W>,
+        dir: &Path,
+        base: &Path,
+        skip: &dyn Fn(&Path) -> bool,
+    ) -> Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+
+            // Skip .tmp files
+            if path.extension().and_then(|e| e.to_str()) == Some("tmp") || skip(&path) {
+                continue;
+            }
+
+            let rel = path.strip_prefix(base).unwrap_or(&path);
+
+            if path.is_dir() {
+                Self::add_dir_to_tar(archive, &path, base, skip)?;
+            } else if path.is_file() {
+                archive
+                    .append_path_with_name(&path, rel)
+                    .map_err(|e| Error::Backup(e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for OxiDb {
+    fn drop(&mut self) {
+        // Shut down the scheduler thread (dropping the sender causes it to exit)
+        let _ = self.scheduler_shutdown.lock().unwrap().take();
+        self.flush_indexes();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
     use serde_json::json;
     use tempfile::tempdir;
 
@@ -1292,6 +2947,376 @@ mod tests {
         OxiDb::open(dir.path()).unwrap()
     }
 
+    #[test]
+    fn check_writable_fails_once_data_dir_is_gone() {
+        let dir = tempdir().unwrap();
+        let data_dir = dir.path().join("data");
+        let db = OxiDb::open(&data_dir).unwrap();
+        db.check_writable().unwrap();
+        let leftover = std::fs::read_dir(&data_dir).unwrap().any(|e| {
+            e.unwrap().file_name().to_string_lossy().starts_with(".write_probe")
+        });
+        assert!(!leftover);
+
+        // Concurrent checks don't trip over each other's probe files
+        let db = Arc::new(db);
+        let checks: Vec<_> = (0..8)
+            .map(|_| {
+                let db = Arc::clone(&db);
+                std::thread::spawn(move || (0..50).all(|_| db.check_writable().is_ok()))
+            })
+            .collect();
+        assert!(checks.into_iter().all(|c| c.join().unwrap()));
+
+        // Root ignores permission bits, so make the directory unusable outright
+        std::fs::remove_dir_all(&data_dir).unwrap();
+        std::fs::write(&data_dir, b"").unwrap();
+        assert!(matches!(db.check_writable(), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn find_and_modify_claims_each_document_once() {
+        let db = Arc::new(temp_db());
+        let jobs: Vec<Value> = (0..200).map(|n| json!({"job": n, "state": "pending"})).collect();
+        db.insert_many("queue", jobs).unwrap();
+
+        let workers: Vec<_> = (0..4)
+            .map(|w| {
+                let db = Arc::clone(&db);
+                std::thread::spawn(move || {
+                    let claim = json!({"$set": {"state": "claimed", "worker": w}});
+                    let opts = FindAndModifyOptions { return_new: true, ..FindAndModifyOptions::default() };
+                    let mut claimed = Vec::new();
+                    while let Some(doc) = db.find_and_modify("queue", &json!({"state": "pending"}), Some(&claim), &opts).unwrap() {
+                        assert_eq!(doc["worker"], w);
+                        claimed.push(doc["job"].as_u64().unwrap());
+                    }
+                    claimed
+                })
+            })
+            .collect();
+        let mut claimed: Vec<u64> = workers.into_iter().flat_map(|w| w.join().unwrap()).collect();
+        claimed.sort_unstable();
+        assert_eq!(claimed, (0..200).collect::<Vec<u64>>());
+    }
+
+    #[test]
+    fn document_size_limit() {
+        let db = temp_db();
+        db.insert("docs", json!({"n": 1})).unwrap();
+        db.set_max_document_size(Some(100));
+
+        let big = json!({"blob": "x".repeat(200)});
+        match db.insert("docs", big.clone()) {
+            Err(Error::DocumentTooLarge { max: 100, size }) => assert!(size > 200),
+            other => panic!("expected DocumentTooLarge, got {other:?}"),
+        }
+        // The whole batch is rejected, including the small document
+        assert!(matches!(
+            db.insert_many("docs", vec![json!({"n": 2}), big.clone()]),
+            Err(Error::DocumentTooLarge { .. })
+        ));
+        let tx = db.begin_transaction();
+        assert!(matches!(db.tx_insert(tx, "docs", big.clone()), Err(Error::DocumentTooLarge { .. })));
+        db.rollback_transaction(tx).unwrap();
+        assert_eq!(db.count("docs", &json!({})).unwrap(), 1);
+
+        // Collections opened later get the limit too
+        assert!(matches!(db.insert("other", big.clone()), Err(Error::DocumentTooLarge { .. })));
+        db.insert("docs", json!({"n": 2})).unwrap();
+        assert_eq!(db.find("docs", &json!({})).unwrap().len(), 2);
+
+        db.set_max_document_size(None);
+        db.insert("docs", big).unwrap();
+    }
+
+    #[test]
+    fn find_result_limit() {
+        let db = temp_db();
+        for i in 0..10 {
+            db.insert("items", json!({"i": i})).unwrap();
+        }
+        db.set_max_find_results(Some(5));
+
+        assert!(matches!(db.find("items", &json!({})), Err(Error::ResultTooLarge(5))));
+        let opts = FindOptions::default();
+        assert!(matches!(db.find_with_options_arcs("items", &json!({}), &opts), Err(Error::ResultTooLarge(5))));
+
+        // Results within the limit are returned whole
+        assert_eq!(db.find("items", &json!({"i": {"$lt": 5}})).unwrap().len(), 5);
+        let opts = FindOptions { limit: Some(5), ..Default::default() };
+        assert_eq!(db.find_with_options("items", &json!({}), &opts).unwrap().len(), 5);
+        let opts = FindOptions { skip: Some(6), ..Default::default() };
+        assert_eq!(db.find_with_options("items", &json!({}), &opts).unwrap().len(), 4);
+
+        db.set_max_find_results(None);
+        assert_eq!(db.find("items", &json!({})).unwrap().len(), 10);
+    }
+
+    #[test]
+    fn compact_all_collections() {
+        let dir = tempdir().unwrap();
+        let db = OxiDb::open(dir.path()).unwrap();
+        for col in ["alpha", "beta"] {
+            for i in 0..50 {
+                db.insert(col, json!({"i": i, "pad": "x".repeat(100)})).unwrap();
+            }
+            db.delete(col, &json!({"i": {"$gte": 10}})).unwrap();
+        }
+        let dat_size = |col: &str| std::fs::metadata(dir.path().join(format!("{col}.dat"))).unwrap().len();
+        let before = (dat_size("alpha"), dat_size("beta"));
+
+        let stats = db.compact_all().unwrap();
+        let names: Vec<&str> = stats.collections.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["alpha", "beta"]);
+        assert!(stats.collections.iter().all(|(_, r)| r.is_ok()));
+        assert_eq!(stats.total.docs_kept, 20);
+        assert!(dat_size("alpha") < before.0);
+        assert!(dat_size("beta") < before.1);
+        assert_eq!(stats.total.new_size, dat_size("alpha") + dat_size("beta"));
+
+        for col in ["alpha", "beta"] {
+            let docs = db.find(col, &json!({})).unwrap();
+            let mut ids: Vec<u64> = docs.iter().map(|d| d["i"].as_u64().unwrap()).collect();
+            ids.sort_unstable();
+            assert_eq!(ids, (0..10).collect::<Vec<_>>());
+        }
+
+        // Collections on disk but not yet loaded are compacted too
+        drop(db);
+        let db = OxiDb::open(dir.path()).unwrap();
+        assert!(db.list_collections().is_empty());
+        let stats = db.compact_all().unwrap();
+        assert_eq!(stats.collections.len(), 2);
+        assert_eq!(stats.total.docs_kept, 20);
+    }
+
+    #[test]
+    fn auto_compaction_by_dead_space() {
+        let dir = tempdir().unwrap();
+        let db = Arc::new(OxiDb::open(dir.path()).unwrap());
+        for i in 0..100 {
+            db.insert("logs", json!({"i": i, "msg": "x".repeat(100)})).unwrap();
+        }
+        let dat_size = || std::fs::metadata(dir.path().join("logs.dat")).unwrap().len();
+        let ratio = |db: &OxiDb| db.get_or_create_collection("logs").unwrap().read().unwrap().dead_space_ratio();
+        assert!(ratio(&db) < 0.01);
+
+        // Below the threshold: nothing happens
+        db.delete("logs", &json!({"i": {"$lt": 20}})).unwrap();
+        assert!((0.15..0.25).contains(&ratio(&db)));
+        assert!(db.auto_compact(0.5).is_empty());
+
+        // A transaction using the collection holds off compaction
+        db.delete("logs", &json!({"i": {"$lt": 70}})).unwrap();
+        assert!(ratio(&db) > 0.5);
+        let tx = db.begin_transaction();
+        db.tx_find(tx, "logs", &json!({"i": 99})).unwrap();
+        assert!(db.auto_compact(0.5).is_empty());
+        db.rollback_transaction(tx).unwrap();
+
+        // The background thread compacts once the threshold is crossed
+        let before = dat_size();
+        db.start_auto_compactor(Duration::from_millis(20), 0.5);
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        while dat_size() >= before {
+            assert!(std::time::Instant::now() < deadline, "auto-compaction didn't run");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        // Wait for the compaction to finish swapping in the new file
+        while ratio(&db) > 0.01 {
+            assert!(std::time::Instant::now() < deadline, "auto-compaction didn't finish");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(dat_size() < before / 2);
+        assert_eq!(db.count("logs", &json!({})).unwrap(), 30);
+        assert_eq!(db.find("logs", &json!({"i": 99})).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn corrupt_record_fails_load_unless_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let db = OxiDb::open(dir.path()).unwrap();
+            for i in 0..3 {
+                db.insert("logs", json!({"i": i})).unwrap();
+            }
+            db.compact("logs").unwrap();
+        }
+        // Flip the last byte of the last record
+        let dat = dir.path().join("logs.dat");
+        let mut raw = std::fs::read(&dat).unwrap();
+        *raw.last_mut().unwrap() ^= 0x01;
+        std::fs::write(&dat, raw).unwrap();
+
+        let db = OxiDb::open(dir.path()).unwrap();
+        let err = db.find("logs", &json!({})).unwrap_err();
+        assert!(matches!(err, Error::Corruption { .. }), "{err}");
+        drop(db);
+
+        let db = OxiDb::open(dir.path()).unwrap();
+        db.set_skip_corrupt_records(true);
+        assert_eq!(db.count("logs", &json!({})).unwrap(), 2);
+        db.compact("logs").unwrap();
+        drop(db);
+
+        // Compaction dropped the bad record for good
+        let db = OxiDb::open(dir.path()).unwrap();
+        assert_eq!(db.count("logs", &json!({})).unwrap(), 2);
+        assert_eq!(db.find("logs", &json!({"i": 2})).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn repair_salvages_truncated_and_corrupt_records() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let db = OxiDb::open(dir.path()).unwrap();
+            db.create_index("logs", "i").unwrap();
+            for i in 0..5 {
+                db.insert("logs", json!({"i": i})).unwrap();
+            }
+            db.compact("logs").unwrap();
+        }
+        // Flip a payload byte of the first record and cut the last one short
+        let dat = dir.path().join("logs.dat");
+        let mut raw = std::fs::read(&dat).unwrap();
+        raw[12] ^= 0x01;
+        raw.truncate(raw.len() - 3);
+        std::fs::write(&dat, raw).unwrap();
+
+        let db = OxiDb::open(dir.path()).unwrap();
+        assert!(db.find("logs", &json!({})).is_err());
+        let report = db.repair("logs").unwrap();
+        assert_eq!(report.recovered, 3);
+        assert_eq!(report.lost, 2);
+        let quarantine = report.quarantine.unwrap();
+        assert_eq!(quarantine, dir.path().join("logs.corrupt"));
+        assert!(std::fs::metadata(&quarantine).unwrap().len() > 0);
+
+        let mut found: Vec<i64> = db
+            .find("logs", &json!({"i": {"$gte": 0}}))
+            .unwrap()
+            .iter()
+            .map(|d| d["i"].as_i64().unwrap())
+            .collect();
+        found.sort();
+        assert_eq!(found, vec![1, 2, 3]);
+        db.insert("logs", json!({"i": 9})).unwrap();
+        drop(db);
+
+        // The repaired file loads normally and a clean one repairs to itself
+        let db = OxiDb::open(dir.path()).unwrap();
+        assert_eq!(db.count("logs", &json!({})).unwrap(), 4);
+        let report = db.repair("logs").unwrap();
+        assert_eq!((report.recovered, report.lost), (4, 0));
+        assert!(report.quarantine.is_none());
+        assert!(matches!(db.repair("missing"), Err(Error::CollectionNotFound(_))));
+    }
+
+    #[test]
+    fn concurrent_inserts_share_fsyncs() {
+        let dir = tempfile::tempdir().unwrap();
+        let (threads, per_thread) = (16, 25);
+        {
+            let db = Arc::new(OxiDb::open(dir.path()).unwrap());
+            let handles: Vec<_> = (0..threads)
+                .map(|t| {
+                    let db = Arc::clone(&db);
+                    std::thread::spawn(move || {
+                        for i in 0..per_thread {
+                            db.insert("events", json!({"t": t, "i": i})).unwrap();
+                        }
+                    })
+                })
+                .collect();
+            for h in handles {
+                h.join().unwrap();
+            }
+
+            let syncs = db.get_or_create_collection("events").unwrap().read().unwrap().group_commit().syncs();
+            assert!(syncs < (threads * per_thread / 2) as u64, "{syncs} fsyncs for {} inserts", threads * per_thread);
+            assert_eq!(db.count("events", &json!({})).unwrap(), threads * per_thread);
+        }
+
+        // Every acknowledged insert is in the data file
+        let db = OxiDb::open(dir.path()).unwrap();
+        assert_eq!(db.count("events", &json!({})).unwrap(), threads * per_thread);
+        assert_eq!(db.find("events", &json!({"t": 7})).unwrap().len(), per_thread);
+
+        // A lone writer still syncs every insert
+        db.set_group_commit_window(Duration::ZERO);
+        db.insert("solo", json!({"n": 1})).unwrap();
+        db.insert("solo", json!({"n": 2})).unwrap();
+        let col = db.get_or_create_collection("solo").unwrap();
+        assert_eq!(col.read().unwrap().group_commit().syncs(), 2);
+    }
+
+    #[test]
+    fn relaxed_bulk_load_persists_after_flush() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let db = OxiDb::open(dir.path()).unwrap();
+            for batch in 0..10 {
+                let docs = (0..100).map(|i| json!({"batch": batch, "i": i})).collect();
+                db.insert_many_with_durability("load", docs, Durability::Relaxed).unwrap();
+            }
+            let ops = vec![
+                BulkOp::Insert(json!({"batch": "extra"})),
+                BulkOp::Delete { query: json!({"batch": 0}) },
+            ];
+            db.bulk_write_with_durability("load", ops, Durability::Relaxed).unwrap();
+            db.insert_with_durability("load", json!({"batch": "last"}), Durability::Relaxed).unwrap();
+            assert_eq!(db.count("load", &json!({})).unwrap(), 902);
+            db.flush("load").unwrap();
+        }
+
+        let db = OxiDb::open(dir.path()).unwrap();
+        assert_eq!(db.count("load", &json!({})).unwrap(), 902);
+        assert_eq!(db.find("load", &json!({"batch": 9})).unwrap().len(), 100);
+        assert!(db.find("load", &json!({"batch": 0})).unwrap().is_empty());
+        assert_eq!(db.find("load", &json!({"batch": "last"})).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn sync_modes_keep_committed_data_across_a_crash() {
+        // A crash is simulated by leaking the database, so nothing runs at
+        // shutdown and only what the writes themselves put in the files is
+        // there on reopen. That covers a process crash in every mode. Only
+        // `full` and `group` also survive power loss, as they fsync before a
+        // write returns (`group` sharing fsyncs between concurrent inserts);
+        // with `none` the data is only as durable as the OS write-back, which
+        // this test can't observe.
+        for mode in [SyncMode::Full, SyncMode::Group, SyncMode::None] {
+            let dir = tempfile::tempdir().unwrap();
+            let db = OxiDb::open(dir.path()).unwrap();
+            db.set_sync_mode(mode);
+            db.insert("ledger", json!({"n": 1})).unwrap();
+            db.insert_many("ledger", vec![json!({"n": 2}), json!({"n": 3})]).unwrap();
+            db.update("ledger", &json!({"n": 1}), &json!({"$set": {"paid": true}})).unwrap();
+            db.delete("ledger", &json!({"n": 3})).unwrap();
+            let tx = db.begin_transaction();
+            db.tx_insert(tx, "ledger", json!({"n": 4})).unwrap();
+            db.commit_transaction(tx).unwrap();
+
+            // `full` and `none` never go through the group commit
+            let col = db.get_or_create_collection("ledger").unwrap();
+            let grouped = col.read().unwrap().group_commit().syncs();
+            assert_eq!(grouped > 0, mode == SyncMode::Group, "{mode:?}");
+            drop(col);
+            std::mem::forget(db);
+
+            let db = OxiDb::open(dir.path()).unwrap();
+            let mut ns: Vec<u64> =
+                db.find("ledger", &json!({})).unwrap().iter().map(|d| d["n"].as_u64().unwrap()).collect();
+            ns.sort();
+            assert_eq!(ns, vec![1, 2, 4], "{mode:?}");
+            assert_eq!(db.find("ledger", &json!({"paid": true})).unwrap().len(), 1, "{mode:?}");
+        }
+
+        assert_eq!(SyncMode::parse("group"), Some(SyncMode::Group));
+        assert_eq!(SyncMode::parse("always"), None);
+    }
+
     #[test]
     fn tx_insert_commit() {
         let db = temp_db();
@@ -1395,6 +3420,388 @@ mod tests {
         assert_eq!(bob["age"], 26);
     }
 
+    #[test]
+    fn tx_savepoint_rollback_to() {
+        let db = temp_db();
+        db.insert("users", json!({"name": "Alice", "age": 30})).unwrap();
+
+        let tx_id = db.begin_transaction();
+        db.tx_insert(tx_id, "users", json!({"name": "Bob"})).unwrap();
+        db.tx_savepoint(tx_id, "sp1").unwrap();
+        db.tx_insert(tx_id, "users", json!({"name": "Carol"})).unwrap();
+        db.tx_insert(tx_id, "orders", json!({"item": "Widget"})).unwrap();
+        db.tx_update(tx_id, "users", &json!({"name": "Alice"}), &json!({"$set": {"age": 31}}))
+            .unwrap();
+        db.tx_rollback_to(tx_id, "sp1").unwrap();
+
+        // The savepoint survives a rollback and can be reused
+        db.tx_insert(tx_id, "users", json!({"name": "Dave"})).unwrap();
+        db.tx_rollback_to(tx_id, "sp1").unwrap();
+        db.commit_transaction(tx_id).unwrap();
+
+        let mut names: Vec<String> = db
+            .find("users", &json!({}))
+            .unwrap()
+            .iter()
+            .map(|d| d["name"].as_str().unwrap().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["Alice", "Bob"]);
+        let alice = db.find_one("users", &json!({"name": "Alice"})).unwrap().unwrap();
+        assert_eq!(alice["age"], 30);
+        assert_eq!(db.count("orders", &json!({})).unwrap(), 0);
+    }
+
+    #[test]
+    fn tx_rollback_to_drops_later_savepoints() {
+        let db = temp_db();
+        let tx_id = db.begin_transaction();
+        db.tx_savepoint(tx_id, "a").unwrap();
+        db.tx_insert(tx_id, "users", json!({"name": "Bob"})).unwrap();
+        db.tx_savepoint(tx_id, "b").unwrap();
+        db.tx_insert(tx_id, "users", json!({"name": "Carol"})).unwrap();
+        db.tx_rollback_to(tx_id, "a").unwrap();
+
+        match db.tx_rollback_to(tx_id, "b") {
+            Err(Error::SavepointNotFound(name)) => assert_eq!(name, "b"),
+            other => panic!("expected SavepointNotFound, got: {other:?}"),
+        }
+        assert!(matches!(
+            db.tx_savepoint(9999, "x"),
+            Err(Error::TransactionNotFound(9999))
+        ));
+
+        db.commit_transaction(tx_id).unwrap();
+        assert_eq!(db.count("users", &json!({})).unwrap(), 0);
+    }
+
+    #[test]
+    fn read_only_tx_commit_is_noop() {
+        let db = temp_db();
+        db.insert("users", json!({"name": "Alice"})).unwrap();
+        db.insert("orders", json!({"item": "Widget"})).unwrap();
+
+        let tx_id = db.begin_read_transaction();
+        let users = db.tx_find(tx_id, "users", &json!({})).unwrap();
+        let orders = db.tx_find(tx_id, "orders", &json!({})).unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(orders.len(), 1);
+
+        // A concurrent write does not make the read-only commit conflict
+        db.update("users", &json!({"name": "Alice"}), &json!({"$set": {"age": 30}}))
+            .unwrap();
+        db.commit_transaction(tx_id).unwrap();
+
+        assert_eq!(db.count("users", &json!({})).unwrap(), 1);
+        assert_eq!(db.count("orders", &json!({})).unwrap(), 1);
+        assert!(matches!(
+            db.commit_transaction(tx_id),
+            Err(Error::TransactionNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn read_only_tx_rejects_writes() {
+        let db = temp_db();
+        db.insert("users", json!({"name": "Alice"})).unwrap();
+
+        let tx_id = db.begin_read_transaction();
+        assert!(matches!(
+            db.tx_insert(tx_id, "users", json!({"name": "Bob"})),
+            Err(Error::ReadOnlyTransaction(id)) if id == tx_id
+        ));
+        assert!(matches!(
+            db.tx_update(tx_id, "users", &json!({}), &json!({"$set": {"x": 1}})),
+            Err(Error::ReadOnlyTransaction(_))
+        ));
+        assert!(matches!(
+            db.tx_delete(tx_id, "users", &json!({})),
+            Err(Error::ReadOnlyTransaction(_))
+        ));
+        db.commit_transaction(tx_id).unwrap();
+
+        let docs = db.find("users", &json!({})).unwrap();
+        assert_eq!(docs.len(), 1);
+        assert!(docs[0].get("x").is_none());
+    }
+
+    fn fast_retry(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+        }
+    }
+
+    #[test]
+    fn run_transaction_retries_conflict() {
+        let db = temp_db();
+        db.insert("accounts", json!({"name": "Alice", "balance": 100})).unwrap();
+
+        let mut attempts = 0;
+        let balance = db
+            .run_transaction(&fast_retry(3), |db, tx_id| {
+                attempts += 1;
+                let docs = db.tx_find(tx_id, "accounts", &json!({"name": "Alice"}))?;
+                if attempts == 1 {
+                    // A concurrent writer bumps the version after our read
+                    db.update("accounts", &json!({"name": "Alice"}), &json!({"$inc": {"balance": 1}}))?;
+                }
+                let balance = docs[0]["balance"].as_i64().unwrap();
+                db.tx_update(
+                    tx_id,
+                    "accounts",
+                    &json!({"name": "Alice"}),
+                    &json!({"$set": {"balance": balance - 50}}),
+                )?;
+                Ok(balance - 50)
+            })
+            .unwrap();
+
+        assert_eq!(attempts, 2);
+        assert_eq!(balance, 51);
+        let alice = db.find_one("accounts", &json!({"name": "Alice"})).unwrap().unwrap();
+        assert_eq!(alice["balance"], 51);
+    }
+
+    #[test]
+    fn run_transaction_gives_up_after_max_attempts() {
+        let db = temp_db();
+        db.insert("accounts", json!({"name": "Alice", "balance": 100})).unwrap();
+
+        let mut attempts = 0;
+        let result = db.run_transaction(&fast_retry(3), |db, tx_id| {
+            attempts += 1;
+            db.tx_find(tx_id, "accounts", &json!({"name": "Alice"}))?;
+            db.update("accounts", &json!({"name": "Alice"}), &json!({"$inc": {"balance": 1}}))?;
+            db.tx_update(tx_id, "accounts", &json!({"name": "Alice"}), &json!({"$set": {"balance": 0}}))
+        });
+
+        assert!(matches!(result, Err(Error::TransactionConflict { .. })));
+        assert_eq!(attempts, 3);
+        let alice = db.find_one("accounts", &json!({"name": "Alice"})).unwrap().unwrap();
+        assert_eq!(alice["balance"], 103);
+    }
+
+    #[test]
+    fn run_transaction_closure_error_rolls_back() {
+        let db = temp_db();
+        let mut attempts = 0;
+        let result: Result<()> = db.run_transaction(&fast_retry(3), |db, tx_id| {
+            attempts += 1;
+            db.tx_insert(tx_id, "users", json!({"name": "Ghost"}))?;
+            Err(Error::InvalidQuery("abort".into()))
+        });
+        assert!(matches!(result, Err(Error::InvalidQuery(_))));
+        assert_eq!(attempts, 1);
+        assert_eq!(db.count("users", &json!({})).unwrap(), 0);
+    }
+
+    #[test]
+    fn retry_policy_backoff_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(40),
+        };
+        let first = policy.backoff(1);
+        assert!(first >= Duration::from_millis(5) && first <= Duration::from_millis(10));
+        for attempt in 1..40 {
+            assert!(policy.backoff(attempt) <= Duration::from_millis(40));
+        }
+        assert!(policy.backoff(8) >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn tx_timeout_rejects_operations() {
+        let db = temp_db();
+        let tx_id = db.begin_transaction_with_timeout(Some(Duration::from_millis(20)));
+        db.tx_insert(tx_id, "users", json!({"name": "Alice"})).unwrap();
+        std::thread::sleep(Duration::from_millis(40));
+
+        assert!(matches!(
+            db.tx_insert(tx_id, "users", json!({"name": "Bob"})),
+            Err(Error::TransactionTimedOut(id)) if id == tx_id
+        ));
+        // The expired entry is gone: further calls no longer find it
+        assert!(!db.active_transactions.read().unwrap().contains_key(&tx_id));
+        assert!(matches!(
+            db.commit_transaction(tx_id),
+            Err(Error::TransactionNotFound(_))
+        ));
+        assert_eq!(db.count("users", &json!({})).unwrap(), 0);
+    }
+
+    #[test]
+    fn tx_timeout_rejects_commit() {
+        let db = temp_db();
+        db.set_default_tx_timeout(Some(Duration::from_millis(20)));
+        let tx_id = db.begin_transaction();
+        db.tx_insert(tx_id, "users", json!({"name": "Alice"})).unwrap();
+        std::thread::sleep(Duration::from_millis(40));
+
+        assert!(matches!(
+            db.commit_transaction(tx_id),
+            Err(Error::TransactionTimedOut(_))
+        ));
+        assert_eq!(db.count("users", &json!({})).unwrap(), 0);
+
+        // Without a timeout, transactions live until commit
+        db.set_default_tx_timeout(None);
+        let tx_id = db.begin_transaction();
+        std::thread::sleep(Duration::from_millis(40));
+        db.tx_insert(tx_id, "users", json!({"name": "Bob"})).unwrap();
+        db.commit_transaction(tx_id).unwrap();
+        assert_eq!(db.count("users", &json!({})).unwrap(), 1);
+    }
+
+    #[test]
+    fn copy_object_is_searchable() {
+        let db = temp_db();
+        db.put_object("docs", "a.txt", b"quarterly revenue report", "text/plain", HashMap::new())
+            .unwrap();
+        db.copy_object("docs", "a.txt", "archive", "b.txt", None, None)
+            .unwrap();
+
+        let (data, _) = db.get_object("archive", "b.txt").unwrap();
+        assert_eq!(data, b"quarterly revenue report");
+
+        // Indexing happens on the background FTS worker
+        let mut hits = Vec::new();
+        for _ in 0..50 {
+            hits = db.search(Some("archive"), "revenue", 0, 10).unwrap();
+            if !hits.is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0]["key"], "b.txt");
+    }
+
+    #[test]
+    fn set_bucket_tokenizer_reindexes_existing_objects() {
+        let dir = tempdir().unwrap();
+        let db = OxiDb::open(dir.path()).unwrap();
+        db.put_object("notes", "cn.txt", "分布式数据库".as_bytes(), "text/plain", HashMap::new())
+            .unwrap();
+        let search = || {
+            let mut hits = Vec::new();
+            for _ in 0..50 {
+                hits = db.search(Some("notes"), "数据库", 0, 10).unwrap();
+                if !hits.is_empty() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            hits
+        };
+        assert!(search().is_empty());
+
+        assert!(db.set_bucket_tokenizer("notes", Tokenizer::CjkBigram).unwrap().is_empty());
+        assert_eq!(db.bucket_tokenizer("notes"), Tokenizer::CjkBigram);
+        assert_eq!(search().len(), 1);
+
+        // Objects too large to read back are reported rather than reindexed
+        let large = vec![b'x'; FTS_READBACK_MAX_BYTES as usize + 1];
+        db.put_object("notes", "large.bin", &large, "application/octet-stream", HashMap::new()).unwrap();
+        let skipped = db.set_bucket_tokenizer("notes", Tokenizer::Default).unwrap();
+        assert_eq!(skipped, ["large.bin"]);
+    }
+
+    #[test]
+    fn blob_sweeper_purges_expired_objects() {
+        let db = Arc::new(temp_db());
+        let metadata = HashMap::from([("expires_at".to_string(), "2000-01-01T00:00:00Z".to_string())]);
+        db.put_object("tmp", "old.txt", b"stale quarterly report", "text/plain", metadata)
+            .unwrap();
+        assert!(db.get_object("tmp", "old.txt").is_err());
+
+        db.start_blob_sweeper(Duration::from_millis(10));
+        std::thread::sleep(Duration::from_millis(100));
+
+        assert!(!db.data_dir.join("_blobs/tmp/0.data").exists());
+        assert!(db.search(Some("tmp"), "quarterly", 0, 10).unwrap().is_empty());
+        assert_eq!(db.purge_expired_objects().unwrap(), 0);
+    }
+
+    #[test]
+    fn ttl_sweeper_removes_expired_documents() {
+        let db = Arc::new(temp_db());
+        let future = (chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+        db.insert("sessions", json!({"user": "ann", "_expireAt": "2000-01-01T00:00:00Z"})).unwrap();
+        db.insert("sessions", json!({"user": "bob", "_expireAt": 946684800})).unwrap();
+        db.insert("sessions", json!({"user": "cy", "_expireAt": future})).unwrap();
+        db.insert("sessions", json!({"user": "dee"})).unwrap();
+        db.insert("sessions", json!({"user": "eve", "_expireAt": "not a date"})).unwrap();
+
+        // Before the sweep, expired documents are only hidden on request
+        assert_eq!(db.find("sessions", &json!({})).unwrap().len(), 5);
+        let live = FindOptions {
+            sort: Some(vec![("user".into(), crate::query::SortOrder::Asc)]),
+            respect_ttl: true,
+            ..Default::default()
+        };
+        let users = |docs: Vec<Value>| docs.iter().map(|d| d["user"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(users(db.find_with_options("sessions", &json!({}), &live).unwrap()), ["cy", "dee", "eve"]);
+        let paged = FindOptions { skip: Some(1), limit: Some(1), ..live.clone() };
+        let (page, total) = db.find_with_count_arcs("sessions", &json!({}), &paged).unwrap();
+        assert_eq!(page[0]["user"], "dee");
+        assert_eq!(total, 3);
+
+        let now = chrono::Utc::now();
+        assert_eq!(db.collections_with_expired_documents(now).unwrap(), ["sessions"]);
+        db.start_ttl_sweeper(Duration::from_millis(10));
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(db.collections_with_expired_documents(now).unwrap().is_empty());
+        let mut left = users(db.find("sessions", &json!({})).unwrap());
+        left.sort();
+        assert_eq!(left, ["cy", "dee", "eve"]);
+        assert_eq!(db.purge_expired_documents().unwrap(), 0);
+    }
+
+    #[test]
+    fn ttl_paging_looks_past_runs_of_expired_documents() {
+        let db = temp_db();
+        for user in ["a", "b", "c", "d", "e"] {
+            db.insert("sessions", json!({"user": user, "_expireAt": "2000-01-01T00:00:00Z"})).unwrap();
+        }
+        for user in ["f", "g", "h"] {
+            db.insert("sessions", json!({"user": user})).unwrap();
+        }
+        let users = |docs: Vec<Value>| docs.iter().map(|d| d["user"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+        let page = |skip, limit| FindOptions {
+            sort: Some(vec![("user".into(), crate::query::SortOrder::Asc)]),
+            skip,
+            limit,
+            respect_ttl: true,
+            ..Default::default()
+        };
+        assert_eq!(users(db.find_with_options("sessions", &json!({}), &page(None, Some(1))).unwrap()), ["f"]);
+        assert_eq!(users(db.find_with_options("sessions", &json!({}), &page(Some(1), Some(2))).unwrap()), ["g", "h"]);
+        assert!(db.find_with_options("sessions", &json!({}), &page(Some(3), Some(2))).unwrap().is_empty());
+
+        // Purging as of a time before the documents expired removes nothing
+        let before = DateTime::parse_from_rfc3339("1999-01-01T00:00:00Z").unwrap().with_timezone(&chrono::Utc);
+        assert_eq!(db.purge_expired_documents_in("sessions", before).unwrap(), 0);
+        assert_eq!(db.purge_expired_documents_in("sessions", chrono::Utc::now()).unwrap(), 5);
+    }
+
+    #[test]
+    fn tx_reaper_removes_expired() {
+        let db = Arc::new(temp_db());
+        let stale = db.begin_transaction_with_timeout(Some(Duration::from_millis(20)));
+        let live = db.begin_transaction();
+        db.start_tx_reaper(Duration::from_millis(10));
+        std::thread::sleep(Duration::from_millis(100));
+
+        let txs = db.active_transactions.read().unwrap();
+        assert!(!txs.contains_key(&stale));
+        assert!(txs.contains_key(&live));
+        drop(txs);
+        assert_eq!(db.reap_expired_transactions(), 0);
+    }
+
     #[test]
     fn auto_rollback_on_drop() {
         let db = temp_db();
@@ -1477,6 +3884,255 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("not empty"));
     }
 
+    #[test]
+    fn incremental_backup_chain_restores_all_collections() {
+        let data = tempdir().unwrap();
+        let backups = tempdir().unwrap();
+        let db = OxiDb::open(data.path()).unwrap();
+        for i in 0..50 {
+            db.insert("users", json!({"name": format!("user{i}")})).unwrap();
+        }
+        db.create_index("users", "name").unwrap();
+        db.insert("orders", json!({"item": "Widget"})).unwrap();
+        db.insert("logs", json!({"msg": "boot"})).unwrap();
+
+        let full = backups.path().join("full.tar.gz");
+        let info = db.backup(&full).unwrap();
+        assert_eq!(info.collections, 3);
+        assert_eq!(info.archived_collections, 3);
+        assert!(Path::new(info.manifest_path.as_deref().unwrap()).exists());
+
+        // Change one collection, drop one and add one
+        db.insert("orders", json!({"item": "Gadget"})).unwrap();
+        db.drop_collection("logs").unwrap();
+        db.insert("events", json!({"kind": "signup"})).unwrap();
+
+        let inc1 = backups.path().join("inc1.tar.gz");
+        let info = db.backup_incremental(&inc1, &OxiDb::backup_manifest_path(&full)).unwrap();
+        assert_eq!(info.collections, 3);
+        assert_eq!(info.archived_collections, 2);
+        let manifest = BackupManifest::load(Path::new(info.manifest_path.as_deref().unwrap())).unwrap();
+        assert!(manifest.incremental);
+        assert_eq!(manifest.archived, vec!["events".to_string(), "orders".to_string()]);
+        assert!(std::fs::metadata(&inc1).unwrap().len() < std::fs::metadata(&full).unwrap().len());
+
+        // Nothing changed since the first incremental
+        let inc2 = backups.path().join("inc2.tar.gz");
+        let info = db.backup_incremental(&inc2, &OxiDb::backup_manifest_path(&inc1)).unwrap();
+        assert_eq!(info.archived_collections, 0);
+
+        // An incremental can't be restored on its own
+        let err = OxiDb::restore(&inc1, &backups.path().join("alone")).unwrap_err();
+        assert!(err.to_string().contains("incremental"));
+
+        let target = backups.path().join("restored");
+        let info = OxiDb::restore_chain(&[&full, &inc1, &inc2], &target).unwrap();
+        assert_eq!(info.collections, 3);
+        assert!(!target.join(BACKUP_MANIFEST_NAME).exists());
+        drop(db);
+
+        let restored = OxiDb::open(&target).unwrap();
+        assert_eq!(restored.count("users", &json!({})).unwrap(), 50);
+        assert_eq!(restored.count("users", &json!({"name": "user7"})).unwrap(), 1);
+        assert!(restored.list_indexes("users").unwrap().iter().any(|i| i.name == "name"));
+        assert_eq!(restored.count("orders", &json!({})).unwrap(), 2);
+        assert_eq!(restored.count("events", &json!({"kind": "signup"})).unwrap(), 1);
+        assert!(!target.join("logs.dat").exists());
+    }
+
+    #[test]
+    fn collection_backup_restores_dropped_collection() {
+        let data = tempdir().unwrap();
+        let backups = tempdir().unwrap();
+        let db = OxiDb::open(data.path()).unwrap();
+        for i in 0..20 {
+            db.insert("users", json!({"name": format!("user{i}"), "n": i})).unwrap();
+        }
+        db.create_index("users", "name").unwrap();
+        db.insert("orders", json!({"item": "Widget"})).unwrap();
+
+        let archive = backups.path().join("users.tar.gz");
+        let info = db.backup_collection("users", &archive).unwrap();
+        assert_eq!(info.collections, 1);
+        assert!(info.manifest_path.is_none());
+        assert!(matches!(
+            db.backup_collection("missing", &backups.path().join("m.tar.gz")),
+            Err(Error::CollectionNotFound(_))
+        ));
+
+        db.drop_collection("users").unwrap();
+        assert_eq!(db.count("users", &json!({})).unwrap(), 0);
+
+        db.restore_collection(&archive, "users").unwrap();
+        assert_eq!(db.count("users", &json!({})).unwrap(), 20);
+        assert_eq!(db.find("users", &json!({"name": "user3"})).unwrap()[0]["n"], 3);
+        assert!(db.list_indexes("users").unwrap().iter().any(|i| i.name == "name"));
+        assert_eq!(db.count("orders", &json!({})).unwrap(), 1);
+
+        // Hot-swap over a live collection, and restore under another name
+        db.insert("users", json!({"name": "extra"})).unwrap();
+        db.restore_collection(&archive, "users").unwrap();
+        assert_eq!(db.count("users", &json!({})).unwrap(), 20);
+        db.restore_collection(&archive, "users_copy").unwrap();
+        assert_eq!(db.count("users_copy", &json!({"name": "user7"})).unwrap(), 1);
+        assert!(!data.path().join("users.dat.tmp").exists());
+        drop(db);
+
+        let db = OxiDb::open(data.path()).unwrap();
+        assert_eq!(db.count("users", &json!({})).unwrap(), 20);
+    }
+
+    #[test]
+    fn backup_to_writer_round_trips_through_memory() {
+        let data = tempdir().unwrap();
+        let db = OxiDb::open(data.path()).unwrap();
+        for i in 0..10 {
+            db.insert("users", json!({"name": format!("user{i}")})).unwrap();
+        }
+        db.create_index("users", "name").unwrap();
+        db.insert("orders", json!({"item": "Widget"})).unwrap();
+
+        let mut buf = Vec::new();
+        let manifest = db.backup_to_writer(&mut buf).unwrap();
+        assert!(!manifest.incremental);
+        assert_eq!(manifest.collections.len(), 2);
+        assert!(!buf.is_empty());
+
+        let target = tempdir().unwrap();
+        let restored_dir = target.path().join("restored");
+        let info = OxiDb::restore_from_reader(buf.as_slice(), &restored_dir).unwrap();
+        assert_eq!(info.collections, 2);
+        assert!(!restored_dir.join(BACKUP_MANIFEST_NAME).exists());
+
+        let restored = OxiDb::open(&restored_dir).unwrap();
+        assert_eq!(restored.count("users", &json!({})).unwrap(), 10);
+        assert_eq!(restored.count("users", &json!({"name": "user4"})).unwrap(), 1);
+        assert_eq!(restored.count("orders", &json!({})).unwrap(), 1);
+    }
+
+    #[test]
+    fn restore_from_reader_rejects_incremental_archive() {
+        let data = tempdir().unwrap();
+        let backups = tempdir().unwrap();
+        let db = OxiDb::open(data.path()).unwrap();
+        db.insert("users", json!({"name": "a"})).unwrap();
+        let full = backups.path().join("full.tar.gz");
+        db.backup(&full).unwrap();
+        db.insert("users", json!({"name": "b"})).unwrap();
+        let inc = backups.path().join("inc.tar.gz");
+        db.backup_incremental(&inc, &OxiDb::backup_manifest_path(&full)).unwrap();
+
+        let target = tempdir().unwrap();
+        let file = std::fs::File::open(&inc).unwrap();
+        assert!(matches!(
+            OxiDb::restore_from_reader(file, target.path()),
+            Err(Error::Backup(_))
+        ));
+        assert!(std::fs::read_dir(target.path()).unwrap().next().is_none());
+    }
+
+    // -----------------------------------------------------------------------
+    // Point-in-time recovery tests
+    // -----------------------------------------------------------------------
+
+    fn commit_tx(db: &OxiDb, ops: impl FnOnce(TransactionId)) -> TransactionId {
+        let tx_id = db.begin_transaction();
+        ops(tx_id);
+        db.commit_transaction(tx_id).unwrap();
+        // Keep checkpoint timestamps of consecutive steps apart
+        std::thread::sleep(Duration::from_millis(5));
+        tx_id
+    }
+
+    #[test]
+    fn restore_to_tx_ignores_later_transactions() {
+        let data = tempdir().unwrap();
+        let restored = tempdir().unwrap();
+        let db = OxiDb::open(data.path()).unwrap();
+        // Written before retention: recovered from the enable-time snapshot
+        db.insert("orders", json!({"item": "base", "status": "new"})).unwrap();
+        db.create_index("orders", "status").unwrap();
+        db.set_wal_retention(true).unwrap();
+        assert!(db.wal_retention_enabled());
+
+        let tx1 = commit_tx(&db, |tx| {
+            db.tx_insert(tx, "orders", json!({"item": "a", "status": "new"})).unwrap();
+        });
+        let tx2 = commit_tx(&db, |tx| {
+            db.tx_insert(tx, "orders", json!({"item": "b", "status": "new"})).unwrap();
+            db.tx_update(tx, "orders", &json!({"item": "a"}), &json!({"$set": {"status": "paid"}})).unwrap();
+        });
+        db.insert("orders", json!({"item": "c", "status": "new"})).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        let bad = commit_tx(&db, |tx| {
+            db.tx_update(tx, "orders", &json!({}), &json!({"$set": {"status": "broken"}})).unwrap();
+        });
+        commit_tx(&db, |tx| {
+            db.tx_delete(tx, "orders", &json!({"item": "base"})).unwrap();
+        });
+        assert!(tx1 < tx2 && tx2 < bad);
+
+        // Just before the bad bulk update
+        let info = OxiDb::restore_to_tx(data.path(), &restored.path().join("before"), tx2).unwrap();
+        assert_eq!(info.collections, 1);
+        let before = OxiDb::open(&restored.path().join("before")).unwrap();
+        assert_eq!(before.count("orders", &json!({})).unwrap(), 3);
+        assert_eq!(before.count("orders", &json!({"status": "broken"})).unwrap(), 0);
+        assert_eq!(before.count("orders", &json!({"item": "c"})).unwrap(), 0);
+        assert_eq!(before.find("orders", &json!({"item": "a"})).unwrap()[0]["status"], "paid");
+        assert!(before.list_indexes("orders").unwrap().iter().any(|i| i.name == "status"));
+
+        // Including the bad update, but not the delete after it
+        OxiDb::restore_to_tx(data.path(), &restored.path().join("after"), bad).unwrap();
+        let after = OxiDb::open(&restored.path().join("after")).unwrap();
+        assert_eq!(after.count("orders", &json!({})).unwrap(), 4);
+        assert_eq!(after.count("orders", &json!({"status": "broken"})).unwrap(), 4);
+
+        assert!(matches!(
+            OxiDb::restore_to_tx(data.path(), &restored.path().join("missing"), 999),
+            Err(Error::Backup(_))
+        ));
+    }
+
+    #[test]
+    fn restore_to_time_and_retention_persistence() {
+        let data = tempdir().unwrap();
+        let restored = tempdir().unwrap();
+        assert!(matches!(
+            OxiDb::restore_to_time(data.path(), restored.path(), chrono::Utc::now()),
+            Err(Error::Backup(_))
+        ));
+
+        let last_tx = {
+            let db = OxiDb::open(data.path()).unwrap();
+            db.set_wal_retention(true).unwrap();
+            commit_tx(&db, |tx| {
+                db.tx_insert(tx, "users", json!({"name": "early"})).unwrap();
+            })
+        };
+
+        // Retention survives a restart and tx ids keep increasing
+        let db = OxiDb::open(data.path()).unwrap();
+        assert!(db.wal_retention_enabled());
+        let cutoff = chrono::Utc::now();
+        std::thread::sleep(Duration::from_millis(5));
+        let late_tx = commit_tx(&db, |tx| {
+            db.tx_insert(tx, "users", json!({"name": "late"})).unwrap();
+        });
+        assert!(late_tx > last_tx);
+        db.insert("users", json!({"name": "later"})).unwrap();
+
+        OxiDb::restore_to_time(data.path(), restored.path(), cutoff).unwrap();
+        let at_cutoff = OxiDb::open(restored.path()).unwrap();
+        assert_eq!(at_cutoff.count("users", &json!({})).unwrap(), 1);
+        assert_eq!(at_cutoff.count("users", &json!({"name": "early"})).unwrap(), 1);
+
+        // Disabling deletes the history
+        db.set_wal_retention(false).unwrap();
+        assert!(!data.path().join("users.wal.history").exists());
+        assert!(!TxHistory::exists(data.path()));
+    }
+
     // -----------------------------------------------------------------------
     // Change stream tests
     // -----------------------------------------------------------------------
@@ -1514,6 +4170,48 @@ mod tests {
         assert!(event.document.is_none());
     }
 
+    #[test]
+    fn watch_full_document_on_update() {
+        let db = temp_db();
+        let id = db.insert("users", json!({"name": "Alice", "age": 30})).unwrap();
+
+        let plain = db.watch(WatchFilter::All, None).unwrap();
+        let opts = WatchOptions { full_document: true, ..Default::default() };
+        let full = db.watch_with_options(WatchFilter::All, &opts).unwrap();
+        db.update_one("users", &json!({"name": "Alice"}), &json!({"$set": {"age": 31}})).unwrap();
+
+        let event = plain.rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert!(event.document.is_none());
+        let event = full.rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(event.doc_id, id);
+        let doc = event.document.unwrap();
+        assert_eq!(doc["name"], "Alice");
+        assert_eq!(doc["age"], 31);
+        assert_eq!(doc["_version"], 2);
+    }
+
+    #[test]
+    fn watch_full_document_on_tx_update() {
+        let db = temp_db();
+        db.insert("users", json!({"name": "Alice", "age": 30})).unwrap();
+
+        let plain = db.watch(WatchFilter::All, None).unwrap();
+        let opts = WatchOptions { full_document: true, ..Default::default() };
+        let full = db.watch_with_options(WatchFilter::All, &opts).unwrap();
+        let tx_id = db.begin_transaction();
+        db.tx_update(tx_id, "users", &json!({"name": "Alice"}), &json!({"$inc": {"age": 5}})).unwrap();
+        db.commit_transaction(tx_id).unwrap();
+
+        let event = plain.rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(event.operation, OperationType::Update);
+        assert!(event.document.is_none());
+        let event = full.rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(event.tx_id, Some(tx_id));
+        let doc = event.document.unwrap();
+        assert_eq!(doc["name"], "Alice");
+        assert_eq!(doc["age"], 35);
+    }
+
     #[test]
     fn watch_delete_emits_event() {
         let db = temp_db();
@@ -1555,7 +4253,26 @@ mod tests {
         db.unwatch(handle.id);
         db.insert("users", json!({"name": "Alice"})).unwrap();
 
-        assert!(handle.rx.recv_timeout(std::time::Duration::from_millis(50)).is_err());
+        assert!(handle.rx.recv_timeout(Duration::from_millis(50)).is_err());
+    }
+
+    #[test]
+    fn watch_inserts_only() {
+        let db = temp_db();
+        let filter = WatchFilter::Operations {
+            collection: Some("users".to_string()),
+            operations: vec![OperationType::Insert],
+        };
+        let handle = db.watch(filter, None).unwrap();
+
+        let id = db.insert("users", json!({"name": "Alice"})).unwrap();
+        db.update("users", &json!({"name": "Alice"}), &json!({"$set": {"age": 1}})).unwrap();
+        db.delete("users", &json!({"name": "Alice"})).unwrap();
+
+        let event = handle.rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(event.operation, OperationType::Insert);
+        assert_eq!(event.doc_id, id);
+        assert!(handle.rx.recv_timeout(Duration::from_millis(50)).is_err());
     }
 
     #[test]
@@ -1571,7 +4288,7 @@ mod tests {
         assert_eq!(event.doc_id, order_id);
 
         // No more events (the users insert was filtered out)
-        assert!(handle.rx.recv_timeout(std::time::Duration::from_millis(50)).is_err());
+        assert!(handle.rx.recv_timeout(Duration::from_millis(50)).is_err());
     }
 
     #[test]
@@ -32,12 +32,42 @@ pub enum Error {
     #[error("document must be a JSON object")]
     NotAnObject,
 
+    #[error("document is {size} bytes, over the {max}-byte limit")]
+    DocumentTooLarge { size: usize, max: usize },
+
+    #[error("document failed schema validation: {0}")]
+    ValidationFailed(String),
+
+    #[error("invalid schema: {0}")]
+    InvalidSchema(String),
+
+    #[error("query matched more than {0} documents; narrow it or add a limit")]
+    ResultTooLarge(usize),
+
     #[error("blob not found: {bucket}/{key}")]
     BlobNotFound { bucket: String, key: String },
 
     #[error("bucket not found: {0}")]
     BucketNotFound(String),
 
+    #[error("multipart upload not found: {0}")]
+    UploadNotFound(String),
+
+    #[error("invalid multipart upload: {0}")]
+    InvalidUpload(String),
+
+    #[error("invalid range: {0}")]
+    InvalidRange(String),
+
+    #[error("invalid expiry: {0}")]
+    InvalidExpiry(String),
+
+    #[error("data corruption: record checksum mismatch at {location}")]
+    Corruption { location: String },
+
+    #[error("checksum mismatch: {bucket}/{key} does not match its stored SHA-256")]
+    ChecksumMismatch { bucket: String, key: String },
+
     #[error("transaction conflict on '{collection}' doc {doc_id}: expected version {expected_version}, found {actual_version}")]
     TransactionConflict {
         collection: String,
@@ -46,12 +76,28 @@ pub enum Error {
         actual_version: u64,
     },
 
+    #[error("version conflict on doc {doc_id}: expected version {expected_version}, found {actual_version}")]
+    VersionConflict {
+        doc_id: u64,
+        expected_version: u64,
+        actual_version: u64,
+    },
+
     #[error("transaction not found: {0}")]
     TransactionNotFound(u64),
 
     #[error("no active transaction")]
     NoActiveTransaction,
 
+    #[error("savepoint not found: {0}")]
+    SavepointNotFound(String),
+
+    #[error("transaction {0} is read-only")]
+    ReadOnlyTransaction(u64),
+
+    #[error("transaction {0} timed out")]
+    TransactionTimedOut(u64),
+
     #[error("index not found: {0}")]
     IndexNotFound(String),
 
@@ -77,4 +123,50 @@ pub enum Error {
     ScheduleError(String),
 }
 
+impl Error {
+    /// Stable machine-readable name of the error kind, included as `"code"`
+    /// in error responses so clients don't have to match on the message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::NotFound(_) => "not_found",
+            Error::CollectionNotFound(_) => "collection_not_found",
+            Error::CollectionAlreadyExists(_) => "collection_already_exists",
+            Error::IndexAlreadyExists(_) => "index_already_exists",
+            Error::Io(_) => "io_error",
+            Error::Json(_) => "json_error",
+            Error::InvalidQuery(_) => "invalid_query",
+            Error::UniqueViolation { .. } => "unique_violation",
+            Error::InvalidPipeline(_) => "invalid_pipeline",
+            Error::NotAnObject => "not_an_object",
+            Error::DocumentTooLarge { .. } => "document_too_large",
+            Error::ValidationFailed(_) => "validation_failed",
+            Error::InvalidSchema(_) => "invalid_schema",
+            Error::ResultTooLarge(_) => "result_too_large",
+            Error::BlobNotFound { .. } => "blob_not_found",
+            Error::BucketNotFound(_) => "bucket_not_found",
+            Error::UploadNotFound(_) => "upload_not_found",
+            Error::InvalidUpload(_) => "invalid_upload",
+            Error::InvalidRange(_) => "invalid_range",
+            Error::InvalidExpiry(_) => "invalid_expiry",
+            Error::Corruption { .. } => "corruption",
+            Error::ChecksumMismatch { .. } => "checksum_mismatch",
+            Error::TransactionConflict { .. } => "tx_conflict",
+            Error::VersionConflict { .. } => "version_conflict",
+            Error::TransactionNotFound(_) => "tx_not_found",
+            Error::NoActiveTransaction => "no_active_tx",
+            Error::SavepointNotFound(_) => "savepoint_not_found",
+            Error::ReadOnlyTransaction(_) => "tx_read_only",
+            Error::TransactionTimedOut(_) => "tx_timed_out",
+            Error::IndexNotFound(_) => "index_not_found",
+            Error::Encryption(_) => "encryption_error",
+            Error::Decryption(_) => "decryption_error",
+            Error::Codec(_) => "codec_error",
+            Error::Backup(_) => "backup_error",
+            Error::ProcedureNotFound(_) => "procedure_not_found",
+            Error::ProcedureError(_) => "procedure_error",
+            Error::ScheduleError(_) => "schedule_error",
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
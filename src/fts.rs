@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
@@ -23,6 +23,65 @@ struct DocInfo {
 struct IndexData {
     postings: HashMap<String, Vec<Posting>>,
     docs: HashMap<String, DocInfo>,
+    /// Buckets using a tokenizer other than the default.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    tokenizers: HashMap<String, Tokenizer>,
+}
+
+/// How a bucket's text is split into terms. Documents and queries for a
+/// bucket go through the same tokenizer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tokenizer {
+    /// Lowercased alphanumeric runs of two or more characters, without
+    /// common English stop words.
+    #[default]
+    Default,
+    /// Lowercased whitespace-separated words, punctuation included, so
+    /// `c++` and `node.js` stay whole.
+    Whitespace,
+    /// Like `Default`, but runs of CJK characters, which have no spaces
+    /// between words, become overlapping two-character terms.
+    CjkBigram,
+    /// Like `Default`, but identifiers are kept whole and also split at
+    /// underscores and camelCase boundaries, so `parseHttpRequest` is found
+    /// by `http` as well as by `parseHttpRequest`.
+    Code,
+}
+
+impl Tokenizer {
+    pub const NAMES: &'static [&'static str] = &["default", "whitespace", "cjk_bigram", "code"];
+
+    pub fn parse(s: &str) -> std::result::Result<Self, String> {
+        match s {
+            "default" => Ok(Tokenizer::Default),
+            "whitespace" => Ok(Tokenizer::Whitespace),
+            "cjk_bigram" => Ok(Tokenizer::CjkBigram),
+            "code" => Ok(Tokenizer::Code),
+            _ => Err(format!(
+                "unknown tokenizer '{s}'; expected one of {}",
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Tokenizer::Default => "default",
+            Tokenizer::Whitespace => "whitespace",
+            Tokenizer::CjkBigram => "cjk_bigram",
+            Tokenizer::Code => "code",
+        }
+    }
+
+    pub fn tokenize(self, text: &str) -> Vec<String> {
+        match self {
+            Tokenizer::Default => tokenize(text),
+            Tokenizer::Whitespace => text.to_lowercase().split_whitespace().map(String::from).collect(),
+            Tokenizer::CjkBigram => tokenize_cjk_bigram(text),
+            Tokenizer::Code => tokenize_code(text),
+        }
+    }
 }
 
 pub struct FtsIndex {
@@ -55,6 +114,83 @@ pub(crate) fn tokenize(text: &str) -> Vec<String> {
         .collect()
 }
 
+/// CJK ideographs, kana and hangul: scripts written without spaces.
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'      // hiragana, katakana
+        | '\u{3400}'..='\u{4dbf}'    // CJK extension A
+        | '\u{4e00}'..='\u{9fff}'    // CJK unified ideographs
+        | '\u{ac00}'..='\u{d7af}'    // hangul syllables
+        | '\u{f900}'..='\u{faff}'    // CJK compatibility ideographs
+        | '\u{20000}'..='\u{2fa1f}') // CJK extensions B onward
+}
+
+fn tokenize_cjk_bigram(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut rest = String::new();
+    let mut run: Vec<char> = Vec::new();
+    let flush_run = |run: &mut Vec<char>, tokens: &mut Vec<String>| {
+        match run.len() {
+            0 => {}
+            1 => tokens.push(run[0].to_string()),
+            _ => tokens.extend(run.windows(2).map(|w| w.iter().collect::<String>())),
+        }
+        run.clear();
+    };
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !rest.is_empty() {
+                tokens.extend(tokenize(&rest));
+                rest.clear();
+            }
+            run.push(c);
+        } else {
+            flush_run(&mut run, &mut tokens);
+            rest.push(c);
+        }
+    }
+    flush_run(&mut run, &mut tokens);
+    tokens.extend(tokenize(&rest));
+    tokens
+}
+
+fn tokenize_code(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for ident in text.split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+        let whole = ident.trim_matches('_').to_lowercase();
+        if whole.len() < 2 {
+            continue;
+        }
+        let parts = split_identifier(ident);
+        if parts.len() > 1 || parts.first() != Some(&whole) {
+            tokens.push(whole);
+        }
+        tokens.extend(parts.into_iter().filter(|p| p.len() > 1));
+    }
+    tokens
+}
+
+/// Split an identifier at underscores and case changes: `HTTPServer_v2`
+/// gives `http`, `server`, `v2`.
+fn split_identifier(ident: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    for word in ident.split('_').filter(|w| !w.is_empty()) {
+        let chars: Vec<char> = word.chars().collect();
+        let mut start = 0;
+        for i in 1..chars.len() {
+            let (prev, cur) = (chars[i - 1], chars[i]);
+            let next_lower = chars.get(i + 1).is_some_and(|c| c.is_lowercase());
+            // fooBar | HTTPServer
+            if (prev.is_lowercase() && cur.is_uppercase()) || (prev.is_uppercase() && cur.is_uppercase() && next_lower) {
+                parts.push(chars[start..i].iter().collect::<String>().to_lowercase());
+                start = i;
+            }
+        }
+        parts.push(chars[start..].iter().collect::<String>().to_lowercase());
+    }
+    parts
+}
+
 impl FtsIndex {
     pub fn open(data_dir: &Path) -> Result<Self> {
         let fts_dir = data_dir.join("_fts");
@@ -71,12 +207,28 @@ impl FtsIndex {
         Ok(Self { index_path, data })
     }
 
+    /// The tokenizer documents and queries in `bucket` go through.
+    pub fn tokenizer(&self, bucket: &str) -> Tokenizer {
+        self.data.tokenizers.get(bucket).copied().unwrap_or_default()
+    }
+
+    /// Choose the tokenizer for `bucket`. Documents already indexed keep
+    /// the terms they were indexed with until they're indexed again.
+    pub fn set_tokenizer(&mut self, bucket: &str, tokenizer: Tokenizer) -> Result<()> {
+        if tokenizer == Tokenizer::Default {
+            self.data.tokenizers.remove(bucket);
+        } else {
+            self.data.tokenizers.insert(bucket.to_string(), tokenizer);
+        }
+        self.persist()
+    }
+
     pub fn index_document(&mut self, bucket: &str, key: &str, text: &str) -> Result<()> {
         // Remove any existing entry for this doc first
         let doc_id = make_doc_id(bucket, key);
         self.remove_postings(&doc_id);
 
-        let tokens = tokenize(text);
+        let tokens = self.tokenizer(bucket).tokenize(text);
         let total_terms = tokens.len() as u32;
 
         if total_terms == 0 {
@@ -142,36 +294,41 @@ impl FtsIndex {
         }
     }
 
+    /// Search blobs, in one bucket or all of them, returning results
+    /// `offset..offset + limit` in score order.
     pub fn search(
         &self,
         bucket: Option<&str>,
         query: &str,
+        offset: usize,
         limit: usize,
     ) -> Vec<SearchResult> {
-        let query_terms = tokenize(query);
-        if query_terms.is_empty() || self.data.docs.is_empty() {
+        if self.data.docs.is_empty() {
             return Vec::new();
         }
 
+        // The query is split by each tokenizer in play, and each split is
+        // matched only against documents from buckets using that tokenizer
+        let tokenizers: HashSet<Tokenizer> = match bucket {
+            Some(b) => HashSet::from([self.tokenizer(b)]),
+            None => self.data.tokenizers.values().copied().chain([Tokenizer::Default]).collect(),
+        };
+
         let total_docs = self.data.docs.len() as f64;
         let mut scores: HashMap<String, f64> = HashMap::new();
 
-        for term in &query_terms {
-            if let Some(postings) = self.data.postings.get(term) {
-                let docs_with_term = postings.len() as f64;
-                let idf = (total_docs / docs_with_term).ln() + 1.0;
-
-                for posting in postings {
-                    // Optionally filter by bucket
-                    if let Some(b) = bucket {
-                        if let Some(doc_info) = self.data.docs.get(&posting.doc_id) {
-                            if doc_info.bucket != b {
-                                continue;
-                            }
+        for tokenizer in tokenizers {
+            for term in &tokenizer.tokenize(query) {
+                if let Some(postings) = self.data.postings.get(term) {
+                    let docs_with_term = postings.len() as f64;
+                    let idf = (total_docs / docs_with_term).ln() + 1.0;
+
+                    for posting in postings {
+                        let Some(doc_info) = self.data.docs.get(&posting.doc_id) else { continue };
+                        // Optionally filter by bucket
+                        if bucket.is_some_and(|b| doc_info.bucket != b) || self.tokenizer(&doc_info.bucket) != tokenizer {
+                            continue;
                         }
-                    }
-
-                    if let Some(doc_info) = self.data.docs.get(&posting.doc_id) {
                         let tf = posting.frequency as f64 / doc_info.total_terms as f64;
                         *scores.entry(posting.doc_id.clone()).or_insert(0.0) += tf * idf;
                     }
@@ -190,9 +347,15 @@ impl FtsIndex {
             })
             .collect();
 
-        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
-        results.truncate(limit);
-        results
+        // Equal scores are ordered by bucket and key, so pages don't shift
+        // between requests
+        results.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| (&a.bucket, &a.key).cmp(&(&b.bucket, &b.key)))
+        });
+        results.into_iter().skip(offset).take(limit).collect()
     }
 
     fn persist(&self) -> Result<()> {
@@ -344,6 +507,23 @@ impl CollectionTextIndex {
         results
     }
 
+    /// Number of documents `search` would return, without scoring them:
+    /// the documents with at least one query term, capped at `limit`.
+    pub fn count(&self, query: &str, limit: usize) -> usize {
+        let mut hits: HashSet<DocumentId> = HashSet::new();
+        for term in tokenize(query) {
+            if let Some(postings) = self.postings.get(&term) {
+                hits.extend(
+                    postings
+                        .iter()
+                        .map(|p| p.doc_id)
+                        .filter(|id| self.doc_term_counts.contains_key(id)),
+                );
+            }
+        }
+        hits.len().min(limit)
+    }
+
     /// Clear the entire index (used during compaction rebuild).
     pub fn clear(&mut self) {
         self.postings.clear();
@@ -402,12 +582,18 @@ pub fn extract_text(data: &[u8], content_type: &str) -> Option<String> {
 }
 
 fn extract_pdf(data: &[u8]) -> Option<String> {
-    pdf_extract::extract_text_from_mem(data).ok().and_then(|s| {
-        let trimmed = s.trim().to_string();
-        if trimmed.is_empty() { None } else { Some(trimmed) }
-    })
+    // pdf_extract panics on some malformed files; that must not take the
+    // FTS worker thread down with it
+    let text = std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem(data))
+        .ok()?
+        .ok()?;
+    let trimmed = text.trim().to_string();
+    if trimmed.is_empty() { None } else { Some(trimmed) }
 }
 
+/// Text of a DOCX body: the contents of its `<w:t>` elements, with the
+/// runs of a paragraph joined as written (Word often splits a word across
+/// runs) and paragraphs, breaks and tabs turned into whitespace.
 fn extract_docx(data: &[u8]) -> Option<String> {
     let cursor = std::io::Cursor::new(data);
     let mut archive = zip::ZipArchive::new(cursor).ok()?;
@@ -416,11 +602,74 @@ fn extract_docx(data: &[u8]) -> Option<String> {
         let mut file = archive.by_name("word/document.xml").ok()?;
         std::io::Read::read_to_string(&mut file, &mut xml).ok()?;
     }
-    let text = strip_html_tags(&xml);
+
+    let mut text = String::new();
+    let mut in_text = false;
+    let mut rest = xml.as_str();
+    while let Some(start) = rest.find('<') {
+        if in_text {
+            text.push_str(&decode_xml_entities(&rest[..start]));
+        }
+        let Some(len) = rest[start..].find('>') else { break };
+        let tag = &rest[start + 1..start + len];
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("");
+        match name {
+            "w:t" => in_text = !closing && !tag.ends_with('/'),
+            "w:tab" => text.push(' '),
+            "w:br" | "w:cr" => text.push('\n'),
+            "w:p" if closing => text.push('\n'),
+            _ => {}
+        }
+        rest = &rest[start + len + 1..];
+    }
     let trimmed = text.trim().to_string();
     if trimmed.is_empty() { None } else { Some(trimmed) }
 }
 
+/// Decode the predefined XML entities and numeric character references.
+fn decode_xml_entities(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').and_then(|semi| {
+            let c = match &rest[1..semi] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                entity => {
+                    let code = match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => entity.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, semi))
+        });
+        match decoded {
+            Some((c, semi)) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
 #[cfg(feature = "ocr")]
 fn extract_image_ocr(data: &[u8]) -> Option<String> {
     let mut lt = leptess::LepTess::new(None, "eng").ok()?;
@@ -525,7 +774,7 @@ mod tests {
         let (_dir, mut idx) = temp_index();
         idx.index_document("docs", "hello.txt", "Hello world database engine")
             .unwrap();
-        let results = idx.search(None, "database", 10);
+        let results = idx.search(None, "database", 0, 10);
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].key, "hello.txt");
         assert!(results[0].score > 0.0);
@@ -540,7 +789,7 @@ mod tests {
         idx.index_document("docs", "b.txt", "the quick brown fox database")
             .unwrap();
 
-        let results = idx.search(None, "database", 10);
+        let results = idx.search(None, "database", 0, 10);
         assert_eq!(results.len(), 2);
         assert_eq!(results[0].key, "a.txt");
         assert_eq!(results[1].key, "b.txt");
@@ -555,7 +804,7 @@ mod tests {
         idx.index_document("images", "b.txt", "database image")
             .unwrap();
 
-        let results = idx.search(Some("docs"), "database", 10);
+        let results = idx.search(Some("docs"), "database", 0, 10);
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].bucket, "docs");
     }
@@ -566,7 +815,7 @@ mod tests {
         idx.index_document("docs", "a.txt", "hello world")
             .unwrap();
         idx.remove_document("docs", "a.txt").unwrap();
-        let results = idx.search(None, "hello", 10);
+        let results = idx.search(None, "hello", 0, 10);
         assert!(results.is_empty());
     }
 
@@ -578,9 +827,9 @@ mod tests {
         idx.index_document("docs", "a.txt", "new content about dogs")
             .unwrap();
 
-        let results = idx.search(None, "cats", 10);
+        let results = idx.search(None, "cats", 0, 10);
         assert!(results.is_empty());
-        let results = idx.search(None, "dogs", 10);
+        let results = idx.search(None, "dogs", 0, 10);
         assert_eq!(results.len(), 1);
     }
 
@@ -617,7 +866,7 @@ mod tests {
     #[test]
     fn search_empty_index() {
         let (_dir, idx) = temp_index();
-        let results = idx.search(None, "anything", 10);
+        let results = idx.search(None, "anything", 0, 10);
         assert!(results.is_empty());
     }
 
@@ -626,7 +875,7 @@ mod tests {
         let (_dir, mut idx) = temp_index();
         idx.index_document("docs", "a.txt", "hello world")
             .unwrap();
-        let results = idx.search(None, "xyznonexistent", 10);
+        let results = idx.search(None, "xyznonexistent", 0, 10);
         assert!(results.is_empty());
     }
 
@@ -651,31 +900,67 @@ mod tests {
         assert!(text.contains("Bob"));
     }
 
-    #[test]
-    fn extract_text_docx() {
-        // Build a minimal DOCX (ZIP with word/document.xml)
+    /// A minimal DOCX: a ZIP holding just `word/document.xml`.
+    fn sample_docx(body: &str) -> Vec<u8> {
         let mut buf = std::io::Cursor::new(Vec::new());
         {
             let mut zip = zip::ZipWriter::new(&mut buf);
             let options = zip::write::SimpleFileOptions::default()
                 .compression_method(zip::CompressionMethod::Stored);
             zip.start_file("word/document.xml", options).unwrap();
-            std::io::Write::write_all(
-                &mut zip,
-                b"<w:document><w:body><w:p><w:r><w:t>Hello DOCX World</w:t></w:r></w:p></w:body></w:document>",
-            )
-            .unwrap();
+            let xml = format!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>{body}</w:body></w:document>"#
+            );
+            std::io::Write::write_all(&mut zip, xml.as_bytes()).unwrap();
             zip.finish().unwrap();
         }
-        let data = buf.into_inner();
-        let text = extract_text(
-            &data,
-            "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
-        )
-        .unwrap();
+        buf.into_inner()
+    }
+
+    /// A one-page PDF showing `text` in Helvetica, with a correct xref table.
+    fn sample_pdf(text: &str) -> Vec<u8> {
+        let content = format!("BT /F1 12 Tf 72 720 Td ({text}) Tj ET");
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R /Resources << /Font << /F1 5 0 R >> >> >>".to_string(),
+            format!("<< /Length {} >>\nstream\n{content}\nendstream", content.len()),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+        ];
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        for (i, obj) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend(format!("{} 0 obj\n{obj}\nendobj\n", i + 1).bytes());
+        }
+        let xref = pdf.len();
+        pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).bytes());
+        for offset in offsets {
+            pdf.extend(format!("{offset:010} 00000 n \n").bytes());
+        }
+        pdf.extend(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n", objects.len() + 1).bytes());
+        pdf
+    }
+
+    const DOCX: &str = "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+
+    #[test]
+    fn extract_text_docx() {
+        let data = sample_docx("<w:p><w:r><w:t>Hello DOCX World</w:t></w:r></w:p>");
+        let text = extract_text(&data, DOCX).unwrap();
         assert!(text.contains("Hello DOCX World"));
     }
 
+    #[test]
+    fn extract_text_docx_joins_runs_and_decodes_entities() {
+        let data = sample_docx(concat!(
+            r#"<w:p><w:pPr><w:jc w:val="left"/></w:pPr><w:r><w:t>Quar</w:t></w:r><w:r><w:rPr><w:b/></w:rPr><w:t>terly</w:t></w:r>"#,
+            r#"<w:r><w:t xml:space="preserve"> R&amp;D </w:t></w:r><w:r><w:t>budget</w:t><w:tab/><w:t>&#x20AC;5&lt;10</w:t></w:r></w:p>"#,
+            r#"<w:p><w:r><w:t>Next</w:t><w:br/><w:t>line</w:t></w:r></w:p>"#,
+        ));
+        assert_eq!(extract_text(&data, DOCX).unwrap(), "Quarterly R&D budget \u{20AC}5<10\nNext\nline");
+    }
+
     #[test]
     fn extract_text_xlsx() {
         // Build a minimal XLSX (ZIP with xl/sharedStrings.xml)
@@ -704,35 +989,32 @@ mod tests {
 
     #[test]
     fn extract_text_pdf() {
-        // Use a minimal valid PDF
-        let pdf_bytes = b"%PDF-1.0
-1 0 obj<</Type/Catalog/Pages 2 0 R>>endobj
-2 0 obj<</Type/Pages/Kids[3 0 R]/Count 1>>endobj
-3 0 obj<</Type/Page/MediaBox[0 0 612 792]/Parent 2 0 R/Contents 4 0 R/Resources<</Font<</F1 5 0 R>>>>>>endobj
-4 0 obj<</Length 44>>
-stream
-BT /F1 12 Tf 100 700 Td (Hello PDF) Tj ET
-endstream
-endobj
-5 0 obj<</Type/Font/Subtype/Type1/BaseFont/Helvetica>>endobj
-xref
-0 6
-0000000000 65535 f
-0000000009 00000 n
-0000000058 00000 n
-0000000115 00000 n
-0000000266 00000 n
-0000000360 00000 n
-trailer<</Size 6/Root 1 0 R>>
-startxref
-431
-%%EOF";
-        let result = extract_text(pdf_bytes, "application/pdf");
-        // pdf_extract may or may not parse this minimal PDF successfully,
-        // so we just verify it doesn't panic and returns Some or None
-        if let Some(text) = result {
-            assert!(text.contains("Hello PDF"));
-        }
+        let text = extract_text(&sample_pdf("Hello PDF"), "application/pdf").unwrap();
+        assert!(text.contains("Hello PDF"), "{text:?}");
+    }
+
+    #[test]
+    fn unparseable_documents_are_not_indexed() {
+        assert_eq!(extract_text(b"%PDF-1.4\nnot really a pdf", "application/pdf"), None);
+        let truncated = sample_pdf("Hello PDF");
+        assert_eq!(extract_text(&truncated[..truncated.len() / 2], "application/pdf"), None);
+        assert_eq!(extract_text(b"PK\x03\x04 not a zip", DOCX), None);
+        assert_eq!(extract_text(&sample_docx(""), DOCX), None);
+    }
+
+    #[test]
+    fn extracted_pdf_and_docx_are_searchable() {
+        let (_dir, mut idx) = temp_index();
+        let pdf = extract_text(&sample_pdf("Annual revenue forecast"), "application/pdf").unwrap();
+        idx.index_document("docs", "forecast.pdf", &pdf).unwrap();
+        let docx = extract_text(&sample_docx("<w:p><w:r><w:t>Revenue</w:t></w:r><w:r><w:t> summary memo</w:t></w:r></w:p>"), DOCX).unwrap();
+        idx.index_document("docs", "memo.docx", &docx).unwrap();
+
+        let mut keys: Vec<String> = idx.search(None, "revenue", 0, 10).into_iter().map(|r| r.key).collect();
+        keys.sort();
+        assert_eq!(keys, ["forecast.pdf", "memo.docx"]);
+        assert_eq!(idx.search(None, "forecast", 0, 10)[0].key, "forecast.pdf");
+        assert_eq!(idx.search(None, "memo", 0, 10)[0].key, "memo.docx");
     }
 
     #[test]
@@ -856,4 +1138,81 @@ startxref
         let results = idx.search("databases", 3);
         assert_eq!(results.len(), 3);
     }
+
+    #[test]
+    fn search_pages_with_offset() {
+        let (_dir, mut idx) = temp_index();
+        // Scores fall with document length; every length appears in two
+        // buckets, so ties are broken by bucket and key
+        for i in 0..12 {
+            let filler = vec!["filler"; i / 2].join(" ");
+            let bucket = if i % 2 == 0 { "a" } else { "b" };
+            idx.index_document(bucket, &format!("doc{i:02}"), &format!("report {filler}")).unwrap();
+        }
+
+        let keys = |results: Vec<SearchResult>| -> Vec<String> {
+            results.into_iter().map(|r| format!("{}/{}", r.bucket, r.key)).collect()
+        };
+        let top = keys(idx.search(None, "report", 0, 10));
+        assert_eq!(top.len(), 10);
+        assert_eq!(top[..4], ["a/doc00", "b/doc01", "a/doc02", "b/doc03"]);
+        let mut pages = keys(idx.search(None, "report", 0, 5));
+        pages.extend(keys(idx.search(None, "report", 5, 5)));
+        assert_eq!(pages, top);
+
+        assert_eq!(keys(idx.search(None, "report", 10, 5)), ["a/doc10", "b/doc11"]);
+        assert!(idx.search(None, "report", 12, 5).is_empty());
+        assert_eq!(keys(idx.search(Some("b"), "report", 1, 2)), ["b/doc03", "b/doc05"]);
+    }
+
+    #[test]
+    fn tokenizers_split_text() {
+        assert_eq!(Tokenizer::Whitespace.tokenize("Learn C++ and Node.js"), ["learn", "c++", "and", "node.js"]);
+        assert_eq!(Tokenizer::CjkBigram.tokenize("数据库 Rust 系统"), ["数据", "据库", "rust", "系统"]);
+        assert_eq!(Tokenizer::CjkBigram.tokenize("猫"), ["猫"]);
+        assert_eq!(
+            Tokenizer::Code.tokenize("fn parseHttpRequest(max_retries: HTTPServer)"),
+            ["fn", "parsehttprequest", "parse", "http", "request", "max_retries", "max", "retries", "httpserver", "http", "server"]
+        );
+        assert_eq!(Tokenizer::parse("cjk_bigram"), Ok(Tokenizer::CjkBigram));
+        let err = Tokenizer::parse("stemmed").unwrap_err();
+        assert!(err.contains("default, whitespace, cjk_bigram, code"), "{err}");
+    }
+
+    #[test]
+    fn cjk_bigram_bucket_finds_words_inside_runs() {
+        let (dir, mut idx) = temp_index();
+        let text = "分布式数据库管理系统";
+        idx.index_document("plain", "a.txt", text).unwrap();
+        idx.set_tokenizer("cjk", Tokenizer::CjkBigram).unwrap();
+        idx.index_document("cjk", "a.txt", text).unwrap();
+
+        // The default tokenizer keeps the whole run as one term
+        assert!(idx.search(Some("plain"), "数据库", 0, 10).is_empty());
+        let hits = idx.search(Some("cjk"), "数据库", 0, 10);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].key, "a.txt");
+
+        // Across buckets each document is matched with its own tokenizer
+        let hits = idx.search(None, "数据库", 0, 10);
+        assert_eq!(hits.iter().map(|h| h.bucket.as_str()).collect::<Vec<_>>(), ["cjk"]);
+        assert_eq!(idx.search(None, text, 0, 10).len(), 2);
+
+        // The choice is persisted with the index
+        drop(idx);
+        let idx = FtsIndex::open(dir.path()).unwrap();
+        assert_eq!(idx.tokenizer("cjk"), Tokenizer::CjkBigram);
+        assert_eq!(idx.tokenizer("plain"), Tokenizer::Default);
+        assert_eq!(idx.search(Some("cjk"), "管理", 0, 10).len(), 1);
+    }
+
+    #[test]
+    fn code_bucket_finds_identifier_parts() {
+        let (_dir, mut idx) = temp_index();
+        idx.set_tokenizer("src", Tokenizer::Code).unwrap();
+        idx.index_document("src", "server.rs", "fn handleHttpRequest(req: Request) {}").unwrap();
+        assert_eq!(idx.search(Some("src"), "http", 0, 10).len(), 1);
+        assert_eq!(idx.search(Some("src"), "handleHttpRequest", 0, 10).len(), 1);
+        assert!(idx.search(Some("src"), "handlehttp", 0, 10).is_empty());
+    }
 }
@@ -0,0 +1,378 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde_json::{Map, Value};
+
+use crate::document::DocumentId;
+use crate::error::{Error, Result};
+
+/// Mean Earth radius in meters, used for haversine distances.
+const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+/// Side of a grid cell in degrees (about 11 km of latitude).
+const CELL_DEGREES: f64 = 0.1;
+
+/// A latitude/longitude pair in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lng: f64,
+}
+
+impl GeoPoint {
+    /// Read a point stored as `{"lat": .., "lng": ..}` or `[lat, lng]`.
+    /// Returns `None` for anything else, including out-of-range coordinates.
+    pub fn from_json(value: &Value) -> Option<Self> {
+        let (lat, lng) = match value {
+            Value::Object(obj) => (obj.get("lat")?.as_f64()?, obj.get("lng")?.as_f64()?),
+            Value::Array(arr) if arr.len() == 2 => (arr[0].as_f64()?, arr[1].as_f64()?),
+            _ => return None,
+        };
+        if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lng) {
+            return None;
+        }
+        Some(Self { lat, lng })
+    }
+
+    /// Great-circle distance to `other` in meters.
+    pub fn distance_to(&self, other: &GeoPoint) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlng = (other.lng - self.lng).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlng / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_M * a.sqrt().min(1.0).asin()
+    }
+
+    fn cell(&self) -> (i32, i32) {
+        ((self.lat / CELL_DEGREES).floor() as i32, (self.lng / CELL_DEGREES).floor() as i32)
+    }
+}
+
+/// A `$near` or `$geoWithin` condition.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoQuery {
+    /// Points nearest `point` first, optionally no farther than `max_distance` meters.
+    Near { point: GeoPoint, max_distance: Option<f64> },
+    /// Points within `radius` meters of `center`.
+    WithinRadius { center: GeoPoint, radius: f64 },
+    /// Points inside the box between two corners.
+    WithinBox { south_west: GeoPoint, north_east: GeoPoint },
+}
+
+impl GeoQuery {
+    /// Parse `{"point": [lat, lng], "maxDistance": m}` for `$near`, or
+    /// `{"center": [lat, lng], "radius": m}` / `{"box": [[lat, lng], [lat, lng]]}`
+    /// for `$geoWithin`.
+    pub fn parse(op: &str, spec: &Value) -> Result<Self> {
+        let spec = spec
+            .as_object()
+            .ok_or_else(|| Error::InvalidQuery(format!("{op} must be an object")))?;
+        match op {
+            "$near" => {
+                let point = query_point(op, spec, "point")?;
+                let max_distance = match spec.get("maxDistance") {
+                    Some(v) => Some(meters(op, "maxDistance", v)?),
+                    None => None,
+                };
+                Ok(GeoQuery::Near { point, max_distance })
+            }
+            "$geoWithin" if spec.contains_key("center") => {
+                let center = query_point(op, spec, "center")?;
+                let radius = meters(op, "radius", spec.get("radius").unwrap_or(&Value::Null))?;
+                Ok(GeoQuery::WithinRadius { center, radius })
+            }
+            "$geoWithin" => {
+                let corners = spec.get("box").and_then(|b| b.as_array()).filter(|b| b.len() == 2);
+                let corners: Option<Vec<GeoPoint>> =
+                    corners.map(|c| c.iter().filter_map(GeoPoint::from_json).collect());
+                match corners.as_deref() {
+                    Some([a, b]) => Ok(GeoQuery::WithinBox {
+                        south_west: GeoPoint { lat: a.lat.min(b.lat), lng: a.lng.min(b.lng) },
+                        north_east: GeoPoint { lat: a.lat.max(b.lat), lng: a.lng.max(b.lng) },
+                    }),
+                    _ => Err(Error::InvalidQuery(
+                        "$geoWithin needs \"center\" and \"radius\", or \"box\" with two [lat, lng] corners".into(),
+                    )),
+                }
+            }
+            _ => Err(Error::InvalidQuery(format!("unknown geo operator: {op}"))),
+        }
+    }
+}
+
+fn query_point(op: &str, spec: &Map<String, Value>, key: &str) -> Result<GeoPoint> {
+    spec.get(key)
+        .and_then(GeoPoint::from_json)
+        .ok_or_else(|| Error::InvalidQuery(format!("{op} needs \"{key}\" as [lat, lng]")))
+}
+
+fn meters(op: &str, key: &str, value: &Value) -> Result<f64> {
+    value
+        .as_f64()
+        .filter(|m| m.is_finite() && *m >= 0.0)
+        .ok_or_else(|| Error::InvalidQuery(format!("{op} needs \"{key}\" as a non-negative number of meters")))
+}
+
+/// Split the geo condition out of a top-level find query. Returns the field,
+/// the condition, and the rest of the query (including other operators on the
+/// same field), or `None` if the query has no `$near` / `$geoWithin`.
+pub fn extract_geo_query(query: &Value) -> Result<Option<(String, GeoQuery, Value)>> {
+    let Some(obj) = query.as_object() else {
+        return Ok(None);
+    };
+    let mut found = None;
+    let mut rest = Map::new();
+    for (key, value) in obj {
+        let ops = value.as_object().filter(|_| !key.starts_with('$'));
+        let geo_op = ops.and_then(|ops| ops.keys().find(|k| *k == "$near" || *k == "$geoWithin"));
+        let (Some(ops), Some(geo_op)) = (ops, geo_op) else {
+            rest.insert(key.clone(), value.clone());
+            continue;
+        };
+        if found.is_some() {
+            return Err(Error::InvalidQuery("a query can have only one $near or $geoWithin".into()));
+        }
+        if ops.contains_key("$near") && ops.contains_key("$geoWithin") {
+            return Err(Error::InvalidQuery("$near and $geoWithin can't be combined".into()));
+        }
+        found = Some((key.clone(), GeoQuery::parse(geo_op, &ops[geo_op])?));
+        let others: Map<String, Value> =
+            ops.iter().filter(|(k, _)| *k != geo_op).map(|(k, v)| (k.clone(), v.clone())).collect();
+        if !others.is_empty() {
+            rest.insert(key.clone(), Value::Object(others));
+        }
+    }
+    Ok(found.map(|(field, geo)| (field, geo, Value::Object(rest))))
+}
+
+/// A document matched by a geo query, with its distance in meters from the
+/// query point when the query has one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoMatch {
+    pub doc_id: DocumentId,
+    pub distance: Option<f64>,
+}
+
+/// Index of the points stored in one field, bucketed into a lat/lng grid.
+/// Documents whose field isn't a valid point are left out.
+pub struct GeoIndex {
+    pub field: String,
+    cells: BTreeMap<(i32, i32), BTreeSet<DocumentId>>,
+    points: HashMap<DocumentId, GeoPoint>,
+}
+
+impl GeoIndex {
+    pub fn new(field: String) -> Self {
+        Self { field, cells: BTreeMap::new(), points: HashMap::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Index the point in `doc`, replacing any earlier one for `id`.
+    pub fn insert(&mut self, id: DocumentId, doc: &Value) {
+        self.remove(id);
+        let Some(point) = resolve(doc, &self.field).and_then(GeoPoint::from_json) else {
+            return;
+        };
+        self.cells.entry(point.cell()).or_default().insert(id);
+        self.points.insert(id, point);
+    }
+
+    pub fn remove(&mut self, id: DocumentId) {
+        let Some(point) = self.points.remove(&id) else {
+            return;
+        };
+        let cell = point.cell();
+        if let Some(ids) = self.cells.get_mut(&cell) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.points.clear();
+    }
+
+    /// Documents matching `query`. Queries around a point are ordered by
+    /// distance (nearest first); box queries by document id.
+    pub fn search(&self, query: &GeoQuery) -> Vec<GeoMatch> {
+        match *query {
+            GeoQuery::Near { point, max_distance } => self.around(point, max_distance),
+            GeoQuery::WithinRadius { center, radius } => self.around(center, Some(radius)),
+            GeoQuery::WithinBox { south_west, north_east } => {
+                let mut ids: Vec<DocumentId> = self
+                    .candidates(south_west, north_east)
+                    .into_iter()
+                    .filter(|id| {
+                        let p = self.points[id];
+                        (south_west.lat..=north_east.lat).contains(&p.lat)
+                            && (south_west.lng..=north_east.lng).contains(&p.lng)
+                    })
+                    .collect();
+                ids.sort_unstable();
+                ids.into_iter().map(|doc_id| GeoMatch { doc_id, distance: None }).collect()
+            }
+        }
+    }
+
+    fn around(&self, center: GeoPoint, max_distance: Option<f64>) -> Vec<GeoMatch> {
+        let ids = match max_distance {
+            Some(radius) => {
+                let (south_west, north_east) = bounding_box(center, radius);
+                self.candidates(south_west, north_east)
+            }
+            None => self.points.keys().copied().collect(),
+        };
+        let mut matches: Vec<GeoMatch> = ids
+            .into_iter()
+            .map(|doc_id| GeoMatch { doc_id, distance: Some(center.distance_to(&self.points[&doc_id])) })
+            .filter(|m| max_distance.is_none_or(|max| m.distance.unwrap_or(0.0) <= max))
+            .collect();
+        matches.sort_by(|a, b| {
+            a.distance.partial_cmp(&b.distance).unwrap_or(std::cmp::Ordering::Equal).then(a.doc_id.cmp(&b.doc_id))
+        });
+        matches
+    }
+
+    /// Ids in the grid cells overlapping a box: a superset of the points in it.
+    /// Falls back to every point when the box covers more rows than there are points.
+    fn candidates(&self, south_west: GeoPoint, north_east: GeoPoint) -> Vec<DocumentId> {
+        let (min_row, min_col) = south_west.cell();
+        let (max_row, max_col) = north_east.cell();
+        if (max_row - min_row) as usize >= self.points.len() {
+            return self.points.keys().copied().collect();
+        }
+        let mut ids = Vec::new();
+        for row in min_row..=max_row {
+            for (_, cell_ids) in self.cells.range((row, min_col)..=(row, max_col)) {
+                ids.extend(cell_ids.iter().copied());
+            }
+        }
+        ids
+    }
+}
+
+/// Corners of a box containing every point within `radius` meters of
+/// `center`. Spans all longitudes near the poles or across the antimeridian.
+fn bounding_box(center: GeoPoint, radius: f64) -> (GeoPoint, GeoPoint) {
+    let dlat = (radius / EARTH_RADIUS_M).to_degrees();
+    let min_lat = (center.lat - dlat).max(-90.0);
+    let max_lat = (center.lat + dlat).min(90.0);
+    let widest = center.lat.abs() + dlat;
+    let (mut min_lng, mut max_lng) = (-180.0, 180.0);
+    if widest < 90.0 {
+        let dlng = dlat / widest.to_radians().cos();
+        if center.lng - dlng >= -180.0 && center.lng + dlng <= 180.0 {
+            min_lng = center.lng - dlng;
+            max_lng = center.lng + dlng;
+        }
+    }
+    (GeoPoint { lat: min_lat, lng: min_lng }, GeoPoint { lat: max_lat, lng: max_lng })
+}
+
+/// Resolve a field path (with dot notation) on a document.
+fn resolve<'a>(doc: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut current = doc;
+    for part in path.split('.') {
+        current = current.as_object()?.get(part)?;
+    }
+    Some(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn point(lat: f64, lng: f64) -> GeoPoint {
+        GeoPoint { lat, lng }
+    }
+
+    #[test]
+    fn distance_between_cities() {
+        let paris = point(48.8566, 2.3522);
+        let london = point(51.5074, -0.1278);
+        let d = paris.distance_to(&london);
+        assert!((d - 343_500.0).abs() < 1_000.0, "{d}");
+        assert_eq!(paris.distance_to(&paris), 0.0);
+    }
+
+    #[test]
+    fn parse_points_and_queries() {
+        assert_eq!(GeoPoint::from_json(&json!({"lat": 1.5, "lng": -2})), Some(point(1.5, -2.0)));
+        assert_eq!(GeoPoint::from_json(&json!([1.5, -2.0])), Some(point(1.5, -2.0)));
+        assert_eq!(GeoPoint::from_json(&json!([91.0, 0.0])), None);
+        assert_eq!(GeoPoint::from_json(&json!("1,2")), None);
+
+        let q = GeoQuery::parse("$near", &json!({"point": [1.0, 2.0], "maxDistance": 500})).unwrap();
+        assert_eq!(q, GeoQuery::Near { point: point(1.0, 2.0), max_distance: Some(500.0) });
+        let q = GeoQuery::parse("$geoWithin", &json!({"box": [[2.0, 5.0], [1.0, 3.0]]})).unwrap();
+        assert_eq!(q, GeoQuery::WithinBox { south_west: point(1.0, 3.0), north_east: point(2.0, 5.0) });
+        assert!(GeoQuery::parse("$near", &json!({"point": [1.0, 2.0], "maxDistance": -1})).is_err());
+        assert!(GeoQuery::parse("$geoWithin", &json!({"center": [1.0, 2.0]})).is_err());
+        assert!(GeoQuery::parse("$geoWithin", &json!({"box": [[1.0, 2.0]]})).is_err());
+    }
+
+    #[test]
+    fn extract_splits_geo_condition_from_rest() {
+        let query = json!({
+            "loc": {"$near": {"point": [0.0, 0.0]}, "$exists": true},
+            "kind": "cafe",
+        });
+        let (field, geo, rest) = extract_geo_query(&query).unwrap().unwrap();
+        assert_eq!(field, "loc");
+        assert_eq!(geo, GeoQuery::Near { point: point(0.0, 0.0), max_distance: None });
+        assert_eq!(rest, json!({"loc": {"$exists": true}, "kind": "cafe"}));
+
+        assert!(extract_geo_query(&json!({"kind": "cafe"})).unwrap().is_none());
+        let two = json!({"a": {"$near": {"point": [0, 0]}}, "b": {"$near": {"point": [0, 0]}}});
+        assert!(extract_geo_query(&two).is_err());
+    }
+
+    #[test]
+    fn index_orders_by_distance_and_filters() {
+        let mut idx = GeoIndex::new("loc".into());
+        idx.insert(1, &json!({"loc": {"lat": 48.8566, "lng": 2.3522}})); // Paris
+        idx.insert(2, &json!({"loc": [48.8606, 2.3376]})); // Louvre, ~1.2 km away
+        idx.insert(3, &json!({"loc": [51.5074, -0.1278]})); // London
+        idx.insert(4, &json!({"loc": "nowhere"}));
+        assert_eq!(idx.len(), 3);
+
+        let near = |max| GeoQuery::Near { point: point(48.8566, 2.3522), max_distance: max };
+        let ids = |ms: Vec<GeoMatch>| ms.iter().map(|m| m.doc_id).collect::<Vec<_>>();
+        assert_eq!(ids(idx.search(&near(None))), [1, 2, 3]);
+        assert_eq!(ids(idx.search(&near(Some(5_000.0)))), [1, 2]);
+        assert_eq!(ids(idx.search(&near(Some(500.0)))), [1]);
+
+        let within = GeoQuery::WithinBox { south_west: point(48.0, -1.0), north_east: point(52.0, 1.0) };
+        assert_eq!(idx.search(&within), [GeoMatch { doc_id: 3, distance: None }]);
+
+        // Moving or removing a document updates the index
+        idx.insert(3, &json!({"loc": [48.8570, 2.3530]}));
+        assert_eq!(ids(idx.search(&near(Some(5_000.0)))), [1, 3, 2]);
+        idx.remove(1);
+        assert_eq!(ids(idx.search(&near(None))), [3, 2]);
+    }
+
+    #[test]
+    fn bounding_box_wraps_at_poles_and_antimeridian() {
+        let (sw, ne) = bounding_box(point(0.0, 179.99), 10_000.0);
+        assert_eq!((sw.lng, ne.lng), (-180.0, 180.0));
+        let (sw, ne) = bounding_box(point(89.99, 0.0), 10_000.0);
+        assert_eq!((sw.lng, ne.lng, ne.lat), (-180.0, 180.0, 90.0));
+
+        let mut idx = GeoIndex::new("loc".into());
+        idx.insert(1, &json!({"loc": [0.0, 179.99]}));
+        idx.insert(2, &json!({"loc": [0.0, -179.99]}));
+        let q = GeoQuery::WithinRadius { center: point(0.0, 179.99), radius: 5_000.0 };
+        assert_eq!(idx.search(&q).len(), 2);
+    }
+}
@@ -1,4 +1,6 @@
+use std::collections::btree_map::Entry;
 use std::collections::{BTreeMap, BTreeSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::io::{self, Read, Write};
 use std::ops::Bound;
 
@@ -16,6 +18,75 @@ fn resolve_value_field<'a>(data: &'a Value, path: &str) -> Option<&'a Value> {
     Some(current)
 }
 
+// ---------------------------------------------------------------------------
+// Bloom filter
+// ---------------------------------------------------------------------------
+
+const BLOOM_BITS_PER_KEY: usize = 10;
+const BLOOM_HASHES: u64 = 7;
+const BLOOM_MIN_KEYS: usize = 1024;
+
+/// Bloom filter over the distinct keys of a field index (~1% false positives
+/// at capacity). Keys can't be removed, so stale keys linger as false
+/// positives until the filter is rebuilt.
+#[derive(Debug)]
+struct BloomFilter {
+    bits: Vec<u64>,
+    capacity: usize,
+    inserted: usize,
+}
+
+impl BloomFilter {
+    fn with_capacity(keys: usize) -> Self {
+        let capacity = keys.max(BLOOM_MIN_KEYS);
+        Self {
+            bits: vec![0; (capacity * BLOOM_BITS_PER_KEY).div_ceil(64)],
+            capacity,
+            inserted: 0,
+        }
+    }
+
+    /// Build a filter holding `keys`, with room for as many again.
+    fn build<'a>(keys: impl ExactSizeIterator<Item = &'a IndexValue>) -> Self {
+        let mut bloom = Self::with_capacity(keys.len() * 2);
+        for key in keys {
+            bloom.insert(key);
+        }
+        bloom
+    }
+
+    fn positions(&self, key: &IndexValue) -> impl Iterator<Item = usize> {
+        let mut h = DefaultHasher::new();
+        // Integers and floats that compare equal must land on the same bits,
+        // so numbers are hashed the way `Ord` compares them: as f64
+        match key {
+            IndexValue::Integer(i) => IndexValue::Float(*i as f64).hash(&mut h),
+            other => other.hash(&mut h),
+        }
+        let h1 = h.finish();
+        h.write_u8(0xb1);
+        let h2 = h.finish() | 1;
+        let nbits = (self.bits.len() * 64) as u64;
+        (0..BLOOM_HASHES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % nbits) as usize)
+    }
+
+    fn insert(&mut self, key: &IndexValue) {
+        for pos in self.positions(key).collect::<Vec<_>>() {
+            self.bits[pos / 64] |= 1 << (pos % 64);
+        }
+        self.inserted += 1;
+    }
+
+    fn may_contain(&self, key: &IndexValue) -> bool {
+        self.positions(key)
+            .all(|pos| self.bits[pos / 64] & (1 << (pos % 64)) != 0)
+    }
+
+    fn is_full(&self) -> bool {
+        self.inserted > self.capacity
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Single-field index
 // ---------------------------------------------------------------------------
@@ -24,7 +95,16 @@ fn resolve_value_field<'a>(data: &'a Value, path: &str) -> Option<&'a Value> {
 pub struct FieldIndex {
     pub field: String,
     pub unique: bool,
+    /// Sort pushdown and range scans walk the index from its highest key. The
+    /// tree itself is ordered the same way either way, so the flag can be
+    /// changed freely.
+    pub descending: bool,
+    /// Documents whose field is null are left out, like those lacking it.
+    pub sparse: bool,
     tree: BTreeMap<IndexValue, BTreeSet<DocumentId>>,
+    /// Optional filter that lets equality lookups skip the tree for values
+    /// that were never indexed.
+    bloom: Option<BloomFilter>,
 }
 
 impl FieldIndex {
@@ -32,7 +112,10 @@ impl FieldIndex {
         Self {
             field,
             unique: false,
+            descending: false,
+            sparse: false,
             tree: BTreeMap::new(),
+            bloom: None,
         }
     }
 
@@ -40,7 +123,53 @@ impl FieldIndex {
         Self {
             field,
             unique: true,
+            descending: false,
+            sparse: false,
             tree: BTreeMap::new(),
+            bloom: None,
+        }
+    }
+
+    /// A non-unique index that skips documents where the field is null.
+    pub fn new_sparse(field: String) -> Self {
+        Self { sparse: true, ..Self::new(field) }
+    }
+
+    /// Build a bloom filter from the current keys and keep it up to date on
+    /// inserts. Removed values stay in the filter as false positives until
+    /// it is rebuilt by `clear` (e.g. during compaction) or by growing.
+    pub fn enable_bloom(&mut self) {
+        self.bloom = Some(BloomFilter::build(self.tree.keys()));
+    }
+
+    pub fn disable_bloom(&mut self) {
+        self.bloom = None;
+    }
+
+    pub fn has_bloom(&self) -> bool {
+        self.bloom.is_some()
+    }
+
+    /// Returns `false` only if no document has ever been indexed under
+    /// `value` since the filter was last built. Always `true` without a filter.
+    pub fn may_contain(&self, value: &IndexValue) -> bool {
+        self.bloom.as_ref().is_none_or(|b| b.may_contain(value))
+    }
+
+    fn add(&mut self, key: IndexValue, id: DocumentId) {
+        match self.tree.entry(key) {
+            Entry::Occupied(mut e) => {
+                e.get_mut().insert(id);
+            }
+            Entry::Vacant(e) => {
+                if let Some(bloom) = &mut self.bloom {
+                    bloom.insert(e.key());
+                }
+                e.insert(BTreeSet::from([id]));
+                if self.bloom.as_ref().is_some_and(BloomFilter::is_full) {
+                    self.enable_bloom();
+                }
+            }
         }
     }
 
@@ -56,43 +185,46 @@ impl FieldIndex {
         }
     }
 
+    /// The key a field value is indexed under; `None` if it isn't indexed.
+    fn key_for(&self, value: Option<&Value>) -> Option<IndexValue> {
+        match value? {
+            Value::Null if self.sparse => None,
+            value => Some(IndexValue::from_json(value)),
+        }
+    }
+
     pub fn insert(&mut self, doc: &Document) {
-        if let Some(value) = doc.get_field(&self.field) {
-            let key = IndexValue::from_json(value);
-            self.tree.entry(key).or_default().insert(doc.id);
+        if let Some(key) = self.key_for(doc.get_field(&self.field)) {
+            self.add(key, doc.id);
         }
     }
 
     /// Insert using a &Value directly — avoids constructing a Document.
     pub fn insert_value(&mut self, id: DocumentId, data: &Value) {
-        if let Some(value) = resolve_value_field(data, &self.field) {
-            let key = IndexValue::from_json(value);
-            self.tree.entry(key).or_default().insert(id);
+        if let Some(key) = self.key_for(resolve_value_field(data, &self.field)) {
+            self.add(key, id);
         }
     }
 
-    pub fn remove(&mut self, doc: &Document) {
-        if let Some(value) = doc.get_field(&self.field) {
-            let key = IndexValue::from_json(value);
-            if let Some(set) = self.tree.get_mut(&key) {
-                set.remove(&doc.id);
-                if set.is_empty() {
-                    self.tree.remove(&key);
-                }
+    fn remove_key(&mut self, key: &IndexValue, id: DocumentId) {
+        if let Some(set) = self.tree.get_mut(key) {
+            set.remove(&id);
+            if set.is_empty() {
+                self.tree.remove(key);
             }
         }
     }
 
+    pub fn remove(&mut self, doc: &Document) {
+        if let Some(key) = self.key_for(doc.get_field(&self.field)) {
+            self.remove_key(&key, doc.id);
+        }
+    }
+
     /// Remove using a &Value directly — avoids constructing a Document.
     pub fn remove_value(&mut self, id: DocumentId, data: &Value) {
-        if let Some(value) = resolve_value_field(data, &self.field) {
-            let key = IndexValue::from_json(value);
-            if let Some(set) = self.tree.get_mut(&key) {
-                set.remove(&id);
-                if set.is_empty() {
-                    self.tree.remove(&key);
-                }
-            }
+        if let Some(key) = self.key_for(resolve_value_field(data, &self.field)) {
+            self.remove_key(&key, id);
         }
     }
 
@@ -196,38 +328,42 @@ impl FieldIndex {
         }
     }
 
-    /// Iterate IDs NOT matching `value`, calling `f` per ID. Stops when `f` returns false.
-    pub fn for_each_ne<F>(&self, value: &IndexValue, mut f: F)
+    /// Iterate IDs NOT matching `value` in the index's direction, calling `f`
+    /// per ID. Stops when `f` returns false.
+    pub fn for_each_ne<F>(&self, value: &IndexValue, f: F)
     where
         F: FnMut(DocumentId) -> bool,
     {
-        for (k, ids) in &self.tree {
-            if k != value {
-                for &id in ids {
-                    if !f(id) {
-                        return;
-                    }
-                }
-            }
-        }
+        let entries = self.tree.iter().filter(|(k, _)| *k != value);
+        self.walk(entries, f);
     }
 
-    /// Iterate IDs in a range, calling `f` per ID. Stops when `f` returns false.
+    /// Iterate IDs in a range in the index's direction, calling `f` per ID.
+    /// Stops when `f` returns false.
     pub fn for_each_in_range<F>(
         &self,
         start: Bound<&IndexValue>,
         end: Bound<&IndexValue>,
-        mut f: F,
+        f: F,
     ) where
         F: FnMut(DocumentId) -> bool,
     {
-        for (_key, ids) in self.tree.range((start, end)) {
-            for &id in ids {
-                if !f(id) {
-                    return;
-                }
-            }
-        }
+        self.walk(self.tree.range((start, end)), f);
+    }
+
+    /// Feed the IDs of `entries` to `f`, from the highest key down for a
+    /// descending index.
+    fn walk<'a, I, F>(&self, entries: I, mut f: F)
+    where
+        I: DoubleEndedIterator<Item = (&'a IndexValue, &'a BTreeSet<DocumentId>)>,
+        F: FnMut(DocumentId) -> bool,
+    {
+        let mut ids: Box<dyn Iterator<Item = &DocumentId>> = if self.descending {
+            Box::new(entries.rev().flat_map(|(_, ids)| ids.iter().rev()))
+        } else {
+            Box::new(entries.flat_map(|(_, ids)| ids.iter()))
+        };
+        ids.all(|&id| f(id));
     }
 
     /// Iterate IDs matching any of the given values, calling `f` per ID.
@@ -258,8 +394,12 @@ impl FieldIndex {
     }
 
     /// Remove all entries from the index while keeping field/unique metadata.
+    /// An enabled bloom filter is reset, dropping any stale keys.
     pub fn clear(&mut self) {
         self.tree.clear();
+        if self.bloom.is_some() {
+            self.enable_bloom();
+        }
     }
 
     // -- Binary serialization -------------------------------------------------
@@ -316,7 +456,7 @@ impl FieldIndex {
             }
             tree.insert(key, ids);
         }
-        Ok(Self { field, unique, tree })
+        Ok(Self { field, unique, descending: false, sparse: false, tree, bloom: None })
     }
 }
 
@@ -571,37 +711,149 @@ impl CompositeIndex {
         range_end: Bound<&IndexValue>,
     ) -> BTreeSet<DocumentId> {
         let mut result = BTreeSet::new();
-        let prefix_len = prefix.len();
-        let range_idx = prefix_len;
+        self.for_each_prefix_range(prefix, range_start, range_end, |id| {
+            result.insert(id);
+            true
+        });
+        result
+    }
 
-        let scan_start = CompositeKey(prefix.to_vec());
+    /// Iterate entries sharing `prefix` whose next field lies within
+    /// `range_start..range_end`, in ascending order. The scan seeks directly to
+    /// the lower bound and stops at the upper bound instead of visiting the
+    /// whole prefix. Return `false` from `f` to stop early.
+    pub fn for_each_prefix_range<F>(
+        &self,
+        prefix: &[IndexValue],
+        range_start: Bound<&IndexValue>,
+        range_end: Bound<&IndexValue>,
+        mut f: F,
+    ) where
+        F: FnMut(DocumentId) -> bool,
+    {
+        let prefix_len = prefix.len();
+        let scan_start = prefix_range_lower_key(prefix, range_start);
 
         for (key, ids) in self.tree.range(scan_start..) {
-            // Must still share the prefix
-            if key.0.len() <= range_idx || key.0[..prefix_len] != *prefix {
+            if key.0.len() < prefix_len || key.0[..prefix_len] != *prefix {
                 break;
             }
-
-            let val = &key.0[range_idx];
-            let in_range = match (&range_start, &range_end) {
-                (Bound::Unbounded, Bound::Unbounded) => true,
-                (Bound::Included(s), Bound::Unbounded) => val >= s,
-                (Bound::Excluded(s), Bound::Unbounded) => val > s,
-                (Bound::Unbounded, Bound::Included(e)) => val <= e,
-                (Bound::Unbounded, Bound::Excluded(e)) => val < e,
-                (Bound::Included(s), Bound::Included(e)) => val >= s && val <= e,
-                (Bound::Included(s), Bound::Excluded(e)) => val >= s && val < e,
-                (Bound::Excluded(s), Bound::Included(e)) => val > s && val <= e,
-                (Bound::Excluded(s), Bound::Excluded(e)) => val > s && val < e,
+            let Some(val) = key.0.get(prefix_len) else {
+                continue;
             };
+            match range_end {
+                Bound::Included(e) if val > e => break,
+                Bound::Excluded(e) if val >= e => break,
+                _ => {}
+            }
+            if !bounds_contain(range_start, range_end, val) {
+                continue;
+            }
+            for &id in ids {
+                if !f(id) {
+                    return;
+                }
+            }
+        }
+    }
 
-            if in_range {
-                result.extend(ids);
+    /// Iterate entries sharing `prefix` whose next field lies within
+    /// `range_start..range_end`, in descending order.
+    /// Return `false` from `f` to stop early.
+    pub fn for_each_prefix_range_desc<F>(
+        &self,
+        prefix: &[IndexValue],
+        range_start: Bound<&IndexValue>,
+        range_end: Bound<&IndexValue>,
+        mut f: F,
+    ) where
+        F: FnMut(DocumentId) -> bool,
+    {
+        let prefix_len = prefix.len();
+        let lower = prefix_range_lower_key(prefix, range_start);
+
+        let matches = |key: &CompositeKey| -> bool {
+            key.0.len() > prefix_len
+                && key.0[..prefix_len] == *prefix
+                && bounds_contain(range_start, range_end, &key.0[prefix_len])
+        };
+
+        // Exclusive upper key that sorts after every entry in the range.
+        let upper = match range_end {
+            Bound::Excluded(e) => {
+                let mut upper_vec = prefix.to_vec();
+                upper_vec.push(e.clone());
+                Some(CompositeKey(upper_vec))
+            }
+            Bound::Included(e) => e.try_successor().map(|s| {
+                let mut upper_vec = prefix.to_vec();
+                upper_vec.push(s);
+                CompositeKey(upper_vec)
+            }),
+            Bound::Unbounded => prefix.last().and_then(|v| v.try_successor()).map(|s| {
+                let mut upper_vec = prefix.to_vec();
+                *upper_vec.last_mut().unwrap() = s;
+                CompositeKey(upper_vec)
+            }),
+        };
+
+        if let Some(upper) = upper {
+            if lower >= upper {
+                return;
+            }
+            for (key, ids) in self.tree.range(lower..upper).rev() {
+                if !matches(key) {
+                    continue;
+                }
+                for &id in ids.iter().rev() {
+                    if !f(id) {
+                        return;
+                    }
+                }
+            }
+        } else {
+            // Fallback: collect forward, iterate reverse.
+            let entries: Vec<_> = self
+                .tree
+                .range(lower..)
+                .take_while(|(key, _)| {
+                    key.0.len() >= prefix_len && key.0[..prefix_len] == *prefix
+                })
+                .filter(|(key, _)| matches(key))
+                .collect();
+
+            for (_, ids) in entries.iter().rev() {
+                for &id in ids.iter().rev() {
+                    if !f(id) {
+                        return;
+                    }
+                }
             }
         }
+    }
+}
 
-        result
+/// Smallest composite key a prefix-range scan can start from.
+fn prefix_range_lower_key(prefix: &[IndexValue], range_start: Bound<&IndexValue>) -> CompositeKey {
+    let mut key = prefix.to_vec();
+    if let Bound::Included(s) | Bound::Excluded(s) = range_start {
+        key.push(s.clone());
     }
+    CompositeKey(key)
+}
+
+fn bounds_contain(start: Bound<&IndexValue>, end: Bound<&IndexValue>, val: &IndexValue) -> bool {
+    let above_start = match start {
+        Bound::Included(s) => val >= s,
+        Bound::Excluded(s) => val > s,
+        Bound::Unbounded => true,
+    };
+    let below_end = match end {
+        Bound::Included(e) => val <= e,
+        Bound::Excluded(e) => val < e,
+        Bound::Unbounded => true,
+    };
+    above_start && below_end
 }
 
 #[cfg(test)]
@@ -832,6 +1084,64 @@ mod tests {
         assert_eq!(result, BTreeSet::from([2]));
     }
 
+    #[test]
+    fn composite_index_prefix_range_bounds() {
+        let mut idx = CompositeIndex::new(vec!["formId".into(), "createdAt".into()]);
+        idx.insert(&make_doc(1, json!({"formId": "1", "createdAt": "2023-12-31"})));
+        idx.insert(&make_doc(2, json!({"formId": "1", "createdAt": "2024-01-01"})));
+        idx.insert(&make_doc(3, json!({"formId": "1", "createdAt": "2024-03-15"})));
+        idx.insert(&make_doc(4, json!({"formId": "1", "createdAt": "2024-07-01"})));
+        idx.insert(&make_doc(5, json!({"formId": "2", "createdAt": "2024-03-15"})));
+
+        let prefix = &[IndexValue::String("1".into())];
+        let lo = IndexValue::from_json(&json!("2024-01-01"));
+        let hi = IndexValue::from_json(&json!("2024-07-01"));
+
+        let mut ids = Vec::new();
+        idx.for_each_prefix_range(prefix, Bound::Included(&lo), Bound::Excluded(&hi), |id| {
+            ids.push(id);
+            true
+        });
+        assert_eq!(ids, vec![2, 3]);
+
+        let mut ids = Vec::new();
+        idx.for_each_prefix_range(prefix, Bound::Excluded(&lo), Bound::Included(&hi), |id| {
+            ids.push(id);
+            true
+        });
+        assert_eq!(ids, vec![3, 4]);
+
+        let mut ids = Vec::new();
+        idx.for_each_prefix_range_desc(prefix, Bound::Included(&lo), Bound::Included(&hi), |id| {
+            ids.push(id);
+            true
+        });
+        assert_eq!(ids, vec![4, 3, 2]);
+
+        let mut ids = Vec::new();
+        idx.for_each_prefix_range_desc(prefix, Bound::Unbounded, Bound::Excluded(&hi), |id| {
+            ids.push(id);
+            true
+        });
+        assert_eq!(ids, vec![3, 2, 1]);
+
+        // Early termination
+        let mut ids = Vec::new();
+        idx.for_each_prefix_range(prefix, Bound::Included(&lo), Bound::Unbounded, |id| {
+            ids.push(id);
+            ids.len() < 2
+        });
+        assert_eq!(ids, vec![2, 3]);
+
+        // Inverted range yields nothing
+        let mut ids = Vec::new();
+        idx.for_each_prefix_range_desc(prefix, Bound::Included(&hi), Bound::Excluded(&lo), |id| {
+            ids.push(id);
+            true
+        });
+        assert!(ids.is_empty());
+    }
+
     #[test]
     fn composite_index_name() {
         let idx = CompositeIndex::new(vec!["a".into(), "b".into(), "c".into()]);
@@ -929,4 +1239,75 @@ mod tests {
         let result = decoded.find_prefix(&[IndexValue::String("active".into())]);
         assert_eq!(result, BTreeSet::from([1, 2]));
     }
+
+    #[test]
+    fn bloom_finds_existing_values() {
+        let mut idx = FieldIndex::new("sku".into());
+        idx.enable_bloom();
+        // Enough distinct keys to force the filter to grow past its initial size
+        for i in 0..5000u64 {
+            idx.insert_value(i, &json!({"sku": format!("sku-{i}")}));
+        }
+        for i in 0..5000u64 {
+            let v = IndexValue::from_json(&json!(format!("sku-{i}")));
+            assert!(idx.may_contain(&v));
+            assert_eq!(idx.find_eq(&v), BTreeSet::from([i]));
+        }
+    }
+
+    #[test]
+    fn bloom_short_circuits_absent_value() {
+        let mut idx = FieldIndex::new("sku".into());
+        for i in 0..1000u64 {
+            idx.insert_value(i, &json!({"sku": format!("sku-{i}")}));
+        }
+        idx.enable_bloom();
+        let absent = IndexValue::from_json(&json!("no-such-sku"));
+        assert!(!idx.may_contain(&absent));
+
+        let mut field_indexes = std::collections::HashMap::new();
+        field_indexes.insert("sku".to_string(), idx);
+        let query = crate::query::parse_query(&json!({"sku": "no-such-sku"})).unwrap();
+        let ids = crate::query::execute_indexed(&query, &field_indexes, &[]);
+        assert_eq!(ids, Some(BTreeSet::new()));
+        let query = crate::query::parse_query(&json!({"sku": "sku-42"})).unwrap();
+        let ids = crate::query::execute_indexed(&query, &field_indexes, &[]);
+        assert_eq!(ids, Some(BTreeSet::from([42])));
+    }
+
+    #[test]
+    fn bloom_matches_integers_and_floats_that_compare_equal() {
+        let mut idx = FieldIndex::new("n".into());
+        idx.enable_bloom();
+        idx.insert_value(1, &json!({"n": 1}));
+        idx.insert_value(2, &json!({"n": 2.0}));
+        assert!(idx.may_contain(&IndexValue::Float(1.0)));
+        assert!(idx.may_contain(&IndexValue::Integer(2)));
+
+        let mut field_indexes = std::collections::HashMap::new();
+        field_indexes.insert("n".to_string(), idx);
+        let query = crate::query::parse_query(&json!({"n": 1.0})).unwrap();
+        assert_eq!(crate::query::execute_indexed(&query, &field_indexes, &[]), Some(BTreeSet::from([1])));
+        let query = crate::query::parse_query(&json!({"n": 2})).unwrap();
+        assert_eq!(crate::query::execute_indexed(&query, &field_indexes, &[]), Some(BTreeSet::from([2])));
+    }
+
+    #[test]
+    fn bloom_keeps_removed_values_until_cleared() {
+        let mut idx = FieldIndex::new("status".into());
+        idx.enable_bloom();
+        idx.insert_value(1, &json!({"status": "gone"}));
+        idx.remove_value(1, &json!({"status": "gone"}));
+        let gone = IndexValue::from_json(&json!("gone"));
+        // A stale key is a false positive that falls through to the tree
+        assert!(idx.may_contain(&gone));
+        assert!(idx.find_eq(&gone).is_empty());
+
+        idx.clear();
+        assert!(idx.has_bloom());
+        assert!(!idx.may_contain(&gone));
+
+        idx.disable_bloom();
+        assert!(idx.may_contain(&gone));
+    }
 }
@@ -12,6 +12,9 @@ use crate::vector::VectorIndex;
 const MAGIC: &[u8; 4] = b"OXIX";
 /// Current format version.
 const VERSION: u32 = 1;
+/// Format version of `.vidx` files, which gained the HNSW parameters in
+/// version 2. Older files are ignored and the index is rebuilt.
+const VECTOR_VERSION: u32 = 3;
 /// Header size: MAGIC(4) + VERSION(4) + DOC_COUNT(8) + NEXT_ID(8) + BODY_CRC(4) + BODY_LEN(8) = 36
 const HEADER_SIZE: usize = 36;
 
@@ -39,7 +42,7 @@ pub fn save_field_indexes(
         idx.write_to(&mut body)?;
     }
 
-    write_cache_file(path, &body, doc_count, next_id)
+    write_cache_file(path, &body, VERSION, doc_count, next_id)
 }
 
 /// Load field indexes from a `.fidx` file.
@@ -50,7 +53,7 @@ pub fn load_field_indexes(
     expected_next_id: DocumentId,
 ) -> Option<Vec<FieldIndex>> {
     let data = fs::read(path).ok()?;
-    let body = validate_cache_file(&data, expected_doc_count, expected_next_id)?;
+    let body = validate_cache_file(&data, VERSION, expected_doc_count, expected_next_id)?;
 
     let mut cursor = Cursor::new(body);
     let mut len_buf = [0u8; 4];
@@ -86,7 +89,7 @@ pub fn save_composite_indexes(
         idx.write_to(&mut body)?;
     }
 
-    write_cache_file(path, &body, doc_count, next_id)
+    write_cache_file(path, &body, VERSION, doc_count, next_id)
 }
 
 /// Load composite indexes from a `.cidx` file.
@@ -96,7 +99,7 @@ pub fn load_composite_indexes(
     expected_next_id: DocumentId,
 ) -> Option<Vec<CompositeIndex>> {
     let data = fs::read(path).ok()?;
-    let body = validate_cache_file(&data, expected_doc_count, expected_next_id)?;
+    let body = validate_cache_file(&data, VERSION, expected_doc_count, expected_next_id)?;
 
     let mut cursor = Cursor::new(body);
     let mut len_buf = [0u8; 4];
@@ -132,7 +135,7 @@ pub fn save_vector_indexes(
         idx.write_to(&mut body)?;
     }
 
-    write_cache_file(path, &body, doc_count, next_id)
+    write_cache_file(path, &body, VECTOR_VERSION, doc_count, next_id)
 }
 
 /// Load vector indexes from a `.vidx` file.
@@ -143,7 +146,7 @@ pub fn load_vector_indexes(
     expected_next_id: DocumentId,
 ) -> Option<Vec<VectorIndex>> {
     let data = fs::read(path).ok()?;
-    let body = validate_cache_file(&data, expected_doc_count, expected_next_id)?;
+    let body = validate_cache_file(&data, VECTOR_VERSION, expected_doc_count, expected_next_id)?;
 
     let mut cursor = Cursor::new(body);
     let mut len_buf = [0u8; 4];
@@ -165,6 +168,7 @@ pub fn load_vector_indexes(
 fn write_cache_file(
     path: &Path,
     body: &[u8],
+    version: u32,
     doc_count: u64,
     next_id: DocumentId,
 ) -> io::Result<()> {
@@ -176,7 +180,7 @@ fn write_cache_file(
 
     let mut header = Vec::with_capacity(HEADER_SIZE);
     header.write_all(MAGIC)?;
-    header.write_all(&VERSION.to_le_bytes())?;
+    header.write_all(&version.to_le_bytes())?;
     header.write_all(&doc_count.to_le_bytes())?;
     header.write_all(&next_id.to_le_bytes())?;
     header.write_all(&body_crc.to_le_bytes())?;
@@ -196,6 +200,7 @@ fn write_cache_file(
 /// Validate a cache file's header and CRC. Returns the body slice on success.
 fn validate_cache_file(
     data: &[u8],
+    expected_version: u32,
     expected_doc_count: u64,
     expected_next_id: DocumentId,
 ) -> Option<&[u8]> {
@@ -210,7 +215,7 @@ fn validate_cache_file(
 
     // Version
     let version = u32::from_le_bytes(data[4..8].try_into().ok()?);
-    if version != VERSION {
+    if version != expected_version {
         return None;
     }
 
@@ -1,18 +1,24 @@
 pub mod blob;
+pub mod bulk;
 pub mod change_stream;
 pub mod codec;
 pub mod collection;
 pub mod crypto;
+pub mod defaults;
+pub mod doc_cache;
 pub mod document;
 pub mod engine;
 pub mod error;
 pub mod fts;
+pub mod geo;
 pub mod index;
 pub mod index_persist;
+pub mod metrics;
 pub mod pipeline;
 pub mod procedure;
 pub mod query;
 pub mod scheduler;
+pub mod schema;
 pub mod sql;
 pub mod transaction;
 pub mod tx_log;
@@ -22,12 +28,23 @@ pub mod value;
 pub mod vector;
 pub mod wal;
 
-pub use change_stream::{ChangeEvent, ChangeStreamBroker, OperationType, ResumeError, SubscriberId, WatchFilter, WatchHandle};
-pub use collection::{Collection, CompactStats, IndexInfo};
+pub use bulk::{BulkOp, BulkOpResult};
+pub use change_stream::{ChangeEvent, ChangeStreamBroker, OperationType, ResumeError, SubscriberId, WatchFilter, WatchHandle, WatchOptions};
+pub use collection::{
+    Collection, CollectionDescription, CompactStats, DEFAULT_DESCRIBE_SAMPLE, Durability, FieldProfile, IndexInfo,
+    RepairReport,
+};
 pub use crypto::EncryptionKey;
+pub use defaults::FieldDefaults;
 pub use document::DocumentId;
-pub use engine::{BackupInfo, LogCallback, OxiDb, RestoreInfo};
+pub use engine::{
+    BackupInfo, BackupManifest, CollectionFingerprint, CompactAllStats, LogCallback, OxiDb, RestoreInfo,
+};
 pub use error::{Error, Result};
-pub use sql::{execute_sql, SqlResult};
+pub use metrics::Metrics;
+pub use schema::{CollectionSchema, ValidationLevel};
+pub use sql::{execute_sql, execute_sql_with_params, SqlResult};
+pub use storage::SyncMode;
+pub use transaction::RetryPolicy;
 pub use tx_log::TransactionId;
 pub use vector::DistanceMetric;
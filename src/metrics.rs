@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Most distinct command names tracked; later ones are counted as `other` so
+/// a client sending made-up commands can't grow the map without bound.
+const MAX_COMMANDS: usize = 128;
+
+/// Request counters, rendered in Prometheus text exposition format by
+/// [`crate::OxiDb::metrics_text`]. Recording a command already seen is a
+/// read lock and two relaxed atomic adds.
+#[derive(Default)]
+pub struct Metrics {
+    commands: RwLock<HashMap<String, CommandCounters>>,
+}
+
+#[derive(Default)]
+struct CommandCounters {
+    calls: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl CommandCounters {
+    fn record(&self, failed: bool) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        if failed {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one handled command, and one error if it failed.
+    pub fn record_command(&self, cmd: &str, failed: bool) {
+        if let Some(counters) = self.commands.read().unwrap().get(cmd) {
+            counters.record(failed);
+            return;
+        }
+        let mut commands = self.commands.write().unwrap();
+        let key = if commands.len() >= MAX_COMMANDS && !commands.contains_key(cmd) { "other" } else { cmd };
+        commands.entry(key.to_string()).or_default().record(failed);
+    }
+
+    /// Number of times `cmd` has been handled.
+    pub fn command_count(&self, cmd: &str) -> u64 {
+        self.commands.read().unwrap().get(cmd).map_or(0, |c| c.calls.load(Ordering::Relaxed))
+    }
+
+    /// Number of times `cmd` has failed.
+    pub fn error_count(&self, cmd: &str) -> u64 {
+        self.commands.read().unwrap().get(cmd).map_or(0, |c| c.errors.load(Ordering::Relaxed))
+    }
+
+    /// Render the counters plus the given gauges, commands sorted by name.
+    pub(crate) fn render(&self, active_transactions: usize, collections: usize) -> String {
+        let commands = self.commands.read().unwrap();
+        let mut names: Vec<&String> = commands.keys().collect();
+        names.sort();
+
+        let mut out = String::new();
+        out.push_str("# HELP oxidb_commands_total Commands handled, by command.\n");
+        out.push_str("# TYPE oxidb_commands_total counter\n");
+        for name in &names {
+            let calls = commands[*name].calls.load(Ordering::Relaxed);
+            let _ = writeln!(out, "oxidb_commands_total{{command=\"{}\"}} {calls}", escape_label(name));
+        }
+        out.push_str("# HELP oxidb_command_errors_total Commands that returned an error, by command.\n");
+        out.push_str("# TYPE oxidb_command_errors_total counter\n");
+        for name in &names {
+            let errors = commands[*name].errors.load(Ordering::Relaxed);
+            let _ = writeln!(out, "oxidb_command_errors_total{{command=\"{}\"}} {errors}", escape_label(name));
+        }
+        out.push_str("# HELP oxidb_active_transactions Transactions currently open.\n");
+        out.push_str("# TYPE oxidb_active_transactions gauge\n");
+        let _ = writeln!(out, "oxidb_active_transactions {active_transactions}");
+        out.push_str("# HELP oxidb_collections Collections in the database.\n");
+        out.push_str("# TYPE oxidb_collections gauge\n");
+        let _ = writeln!(out, "oxidb_collections {collections}");
+        out
+    }
+}
+
+/// Escape a label value as the exposition format requires.
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_and_renders_commands() {
+        let metrics = Metrics::new();
+        metrics.record_command("find", false);
+        metrics.record_command("find", true);
+        metrics.record_command("insert", false);
+        assert_eq!(metrics.command_count("find"), 2);
+        assert_eq!(metrics.error_count("find"), 1);
+        assert_eq!(metrics.command_count("delete"), 0);
+
+        let text = metrics.render(1, 3);
+        assert!(text.contains("# TYPE oxidb_commands_total counter\n"));
+        assert!(text.contains("oxidb_commands_total{command=\"find\"} 2\n"));
+        assert!(text.contains("oxidb_commands_total{command=\"insert\"} 1\n"));
+        assert!(text.contains("oxidb_command_errors_total{command=\"find\"} 1\n"));
+        assert!(text.contains("oxidb_command_errors_total{command=\"insert\"} 0\n"));
+        assert!(text.contains("oxidb_active_transactions 1\n"));
+        assert!(text.contains("oxidb_collections 3\n"));
+        let find = text.find("command=\"find\"").unwrap();
+        assert!(find < text.find("command=\"insert\"").unwrap());
+    }
+
+    #[test]
+    fn caps_distinct_commands_and_escapes_labels() {
+        let metrics = Metrics::new();
+        for i in 0..MAX_COMMANDS + 5 {
+            metrics.record_command(&format!("cmd{i}"), true);
+        }
+        assert_eq!(metrics.commands.read().unwrap().len(), MAX_COMMANDS + 1);
+        assert_eq!(metrics.command_count("other"), 5);
+
+        let metrics = Metrics::new();
+        metrics.record_command("say \"hi\"\n", false);
+        assert!(metrics.render(0, 0).contains("command=\"say \\\"hi\\\"\\n\""));
+    }
+}
@@ -23,10 +23,36 @@ pub struct FindOptions {
     pub sort: Option<Vec<(String, SortOrder)>>,
     pub skip: Option<u64>,
     pub limit: Option<u64>,
+    /// Index to use instead of the planner's choice.
+    pub hint: Option<IndexHint>,
+    /// Leave out documents whose `_expireAt` has passed but which the TTL
+    /// sweeper hasn't removed yet.
+    pub respect_ttl: bool,
+}
+
+/// Options for `Collection::find_and_modify`.
+#[derive(Debug, Clone, Default)]
+pub struct FindAndModifyOptions {
+    /// Order that decides which document is modified when several match.
+    pub sort: Option<Vec<(String, SortOrder)>>,
+    /// Return the document as it is after the update instead of before.
+    pub return_new: bool,
+    /// Delete the matched document instead of updating it.
+    pub remove: bool,
+}
+
+/// A `hint` find option: run the query through one named index (a field
+/// name, or a composite index name such as `"a_b"`), or scan the collection.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IndexHint {
+    Index(String),
+    /// `{"$natural": 1}`: ignore every index.
+    Natural,
 }
 
 /// Parse find options from the JSON request object.
-/// Expects optional fields: `sort` (object: field→1/-1), `skip` (u64), `limit` (u64).
+/// Expects optional fields: `sort` (object: field→1/-1), `skip` (u64), `limit` (u64),
+/// `hint` (index name or `{"$natural": 1}`).
 pub fn parse_find_options(request: &JsonValue) -> Result<FindOptions> {
     let mut opts = FindOptions::default();
 
@@ -63,9 +89,80 @@ pub fn parse_find_options(request: &JsonValue) -> Result<FindOptions> {
         }
     }
 
+    if let Some(respect_ttl) = request.get("respect_ttl").and_then(|v| v.as_bool()) {
+        opts.respect_ttl = respect_ttl;
+    }
+
+    if let Some(hint_val) = request.get("hint") {
+        opts.hint = Some(match hint_val {
+            JsonValue::String(name) => IndexHint::Index(name.clone()),
+            JsonValue::Object(obj) if obj.len() == 1 && obj.contains_key("$natural") => IndexHint::Natural,
+            _ => {
+                return Err(Error::InvalidQuery(
+                    "hint must be an index name or {\"$natural\": 1}".into(),
+                ))
+            }
+        });
+    }
+
     Ok(opts)
 }
 
+/// Parse find_and_modify options from the JSON request object: `sort` as
+/// for `find`, and the booleans `new` and `remove`.
+pub fn parse_find_and_modify_options(request: &JsonValue) -> Result<FindAndModifyOptions> {
+    Ok(FindAndModifyOptions {
+        sort: parse_find_options(request)?.sort,
+        return_new: request.get("new").and_then(JsonValue::as_bool).unwrap_or(false),
+        remove: request.get("remove").and_then(JsonValue::as_bool).unwrap_or(false),
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Query plan: what `explain` reports
+// ---------------------------------------------------------------------------
+
+/// Decisions recorded while executing a find, reported by `explain`.
+#[derive(Debug, Clone, Default)]
+pub struct QueryPlan {
+    /// "collection scan", "index", "index sort" or "geo".
+    pub stage: &'static str,
+    /// Indexes consulted, as (name, kind) where kind is "field", "composite" or "geo".
+    pub indexes: Vec<(String, &'static str)>,
+    /// Candidate documents the chosen strategy expected to visit, when known up front.
+    pub estimated_candidates: Option<usize>,
+    /// Documents actually read and tested.
+    pub docs_examined: usize,
+    /// Whether candidates were re-checked against the full query.
+    pub post_filter: bool,
+    /// Whether results were sorted in memory after matching.
+    pub in_memory_sort: bool,
+    /// For a single-field index sort: "forward" when the sort follows the
+    /// index's declared direction, "backward" when it walks it in reverse.
+    pub direction: Option<&'static str>,
+    pub n_returned: usize,
+}
+
+impl QueryPlan {
+    pub fn to_json(&self) -> JsonValue {
+        let indexes: Vec<JsonValue> = self
+            .indexes
+            .iter()
+            .map(|(name, kind)| serde_json::json!({"name": name, "type": kind}))
+            .collect();
+        serde_json::json!({
+            "stage": self.stage,
+            "indexes": indexes,
+            "estimated_candidates": self.estimated_candidates,
+            "docs_examined": self.docs_examined,
+            "post_filter": self.post_filter,
+            "in_memory_sort": self.in_memory_sort,
+            "direction": self.direction,
+            "n_returned": self.n_returned,
+        })
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Query AST
 // ---------------------------------------------------------------------------
@@ -81,6 +178,22 @@ pub enum QueryOp {
     In(Vec<IndexValue>),
     Exists(bool),
     Regex(regex::Regex),
+    /// `$size`: the field is an array whose length satisfies every condition.
+    Size(Vec<QueryOp>),
+    /// `$type`: the field's JSON type is one of these names.
+    Type(Vec<&'static str>),
+    /// `$mod`: the field is a number with this remainder, as (divisor, remainder).
+    Mod(i64, i64),
+}
+
+impl QueryOp {
+    /// Operators no field index can answer; they're checked document by document.
+    fn needs_scan(&self) -> bool {
+        matches!(
+            self,
+            QueryOp::Exists(_) | QueryOp::Regex(_) | QueryOp::Size(_) | QueryOp::Type(_) | QueryOp::Mod(..)
+        )
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -88,9 +201,32 @@ pub enum Query {
     Field { field: String, op: QueryOp },
     And(Vec<Query>),
     Or(Vec<Query>),
+    /// `$expr`: compare values computed from the document itself.
+    Expr(ExprCondition),
     All, // match everything
 }
 
+/// Comparison inside `$expr`, e.g. `{"$gt": ["$spent", "$budget"]}`.
+#[derive(Debug, Clone)]
+pub struct ExprCondition {
+    /// One of `$eq`, `$ne`, `$gt`, `$gte`, `$lt`, `$lte`.
+    pub op: &'static str,
+    pub lhs: ExprOperand,
+    pub rhs: ExprOperand,
+}
+
+/// A value inside `$expr`: a `"$field"` reference, a literal, or arithmetic
+/// over other operands.
+#[derive(Debug, Clone)]
+pub enum ExprOperand {
+    Field(String),
+    Literal(IndexValue),
+    Add(Vec<ExprOperand>),
+    Subtract(Box<ExprOperand>, Box<ExprOperand>),
+    Multiply(Vec<ExprOperand>),
+    Divide(Box<ExprOperand>, Box<ExprOperand>),
+}
+
 // ---------------------------------------------------------------------------
 // Parsing: JSON → Query AST
 // ---------------------------------------------------------------------------
@@ -122,6 +258,7 @@ pub fn parse_query(query: &JsonValue) -> Result<Query> {
                 let subs: Result<Vec<Query>> = arr.iter().map(parse_query).collect();
                 conditions.push(Query::Or(subs?));
             }
+            "$expr" => conditions.push(Query::Expr(parse_expr_condition(value)?)),
             field => {
                 if value.is_object() {
                     let ops = value.as_object().unwrap();
@@ -164,6 +301,137 @@ pub fn parse_query(query: &JsonValue) -> Result<Query> {
     }
 }
 
+fn parse_size(op_val: &JsonValue) -> Result<QueryOp> {
+    let invalid = || Error::InvalidQuery("$size must be a non-negative integer or an object of comparisons".into());
+    if op_val.is_u64() {
+        return Ok(QueryOp::Size(vec![QueryOp::Eq(IndexValue::from_json(op_val))]));
+    }
+    let ops = op_val.as_object().filter(|ops| !ops.is_empty()).ok_or_else(invalid)?;
+    let conditions = ops
+        .iter()
+        .map(|(op_key, val)| match op_key.as_str() {
+            "$eq" | "$ne" | "$gt" | "$gte" | "$lt" | "$lte" if val.is_number() => parse_op(op_key, val, ops),
+            _ => Err(invalid()),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(QueryOp::Size(conditions))
+}
+
+/// Type names accepted by `$type`, as reported by `json_type_name`.
+const JSON_TYPE_NAMES: [&str; 6] = ["string", "number", "bool", "array", "object", "null"];
+
+fn parse_type(op_val: &JsonValue) -> Result<QueryOp> {
+    let names = match op_val {
+        JsonValue::Array(names) if !names.is_empty() => names.iter().collect(),
+        JsonValue::String(_) => vec![op_val],
+        _ => return Err(Error::InvalidQuery("$type must be a type name or an array of them".into())),
+    };
+    let types = names
+        .into_iter()
+        .map(|name| {
+            let name = name.as_str().unwrap_or_default();
+            JSON_TYPE_NAMES.into_iter().find(|t| *t == name).ok_or_else(|| {
+                Error::InvalidQuery(format!(
+                    "unknown $type: {name:?} (expected one of {})",
+                    JSON_TYPE_NAMES.join(", ")
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(QueryOp::Type(types))
+}
+
+/// Whether `val` is a number leaving `remainder` when divided by `divisor`.
+/// Fractions are truncated first, and the remainder takes the sign of the
+/// dividend, so `-5 % 4` is `-1`.
+fn matches_mod(divisor: i64, remainder: i64, val: Option<&JsonValue>) -> bool {
+    let n = match val {
+        Some(JsonValue::Number(n)) => n
+            .as_i64()
+            .or_else(|| n.as_f64().filter(|f| f.is_finite()).map(|f| f.trunc() as i64)),
+        _ => None,
+    };
+    n.is_some_and(|n| n.wrapping_rem(divisor) == remainder)
+}
+
+fn json_type_name(val: &JsonValue) -> &'static str {
+    match val {
+        JsonValue::String(_) => "string",
+        JsonValue::Number(_) => "number",
+        JsonValue::Bool(_) => "bool",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+        JsonValue::Null => "null",
+    }
+}
+
+/// Whether `val` is an array whose length satisfies every `$size` condition.
+fn matches_size(conditions: &[QueryOp], val: Option<&JsonValue>) -> bool {
+    let Some(arr) = val.and_then(JsonValue::as_array) else {
+        return false;
+    };
+    let len = IndexValue::Integer(arr.len() as i64);
+    conditions.iter().all(|op| match op {
+        QueryOp::Eq(v) => len == *v,
+        QueryOp::Ne(v) => len != *v,
+        QueryOp::Gt(v) => len > *v,
+        QueryOp::Gte(v) => len >= *v,
+        QueryOp::Lt(v) => len < *v,
+        QueryOp::Lte(v) => len <= *v,
+        _ => false,
+    })
+}
+
+fn parse_expr_condition(value: &JsonValue) -> Result<ExprCondition> {
+    let Some((op_key, args)) = value.as_object().filter(|m| m.len() == 1).and_then(|m| m.iter().next()) else {
+        return Err(Error::InvalidQuery("$expr must be an object with a single comparison operator".into()));
+    };
+    let op = match op_key.as_str() {
+        "$eq" => "$eq",
+        "$ne" => "$ne",
+        "$gt" => "$gt",
+        "$gte" => "$gte",
+        "$lt" => "$lt",
+        "$lte" => "$lte",
+        other => return Err(Error::InvalidQuery(format!("unsupported $expr operator: {other}"))),
+    };
+    match args.as_array().map(Vec::as_slice) {
+        Some([lhs, rhs]) => Ok(ExprCondition {
+            op,
+            lhs: parse_expr_operand(lhs)?,
+            rhs: parse_expr_operand(rhs)?,
+        }),
+        _ => Err(Error::InvalidQuery(format!("$expr {op} requires an array of 2 arguments"))),
+    }
+}
+
+fn parse_expr_operand(value: &JsonValue) -> Result<ExprOperand> {
+    match value {
+        JsonValue::String(s) if s.starts_with('$') => Ok(ExprOperand::Field(s[1..].to_string())),
+        JsonValue::Object(map) if map.len() == 1 => {
+            let (key, arg) = map.iter().next().unwrap();
+            let args = arg
+                .as_array()
+                .ok_or_else(|| Error::InvalidQuery(format!("{key} requires an array")))?;
+            let operands = args.iter().map(parse_expr_operand).collect::<Result<Vec<_>>>()?;
+            let pair = |operands: Vec<ExprOperand>| -> Result<(Box<ExprOperand>, Box<ExprOperand>)> {
+                let [lhs, rhs]: [ExprOperand; 2] = operands
+                    .try_into()
+                    .map_err(|_| Error::InvalidQuery(format!("{key} requires exactly 2 arguments")))?;
+                Ok((Box::new(lhs), Box::new(rhs)))
+            };
+            match key.as_str() {
+                "$add" => Ok(ExprOperand::Add(operands)),
+                "$multiply" => Ok(ExprOperand::Multiply(operands)),
+                "$subtract" => pair(operands).map(|(a, b)| ExprOperand::Subtract(a, b)),
+                "$divide" => pair(operands).map(|(a, b)| ExprOperand::Divide(a, b)),
+                other => Err(Error::InvalidQuery(format!("unsupported $expr operator: {other}"))),
+            }
+        }
+        _ => Ok(ExprOperand::Literal(IndexValue::from_json(value))),
+    }
+}
+
 fn parse_op(
     op_key: &str,
     op_val: &JsonValue,
@@ -176,6 +444,9 @@ fn parse_op(
         "$gte" => Ok(QueryOp::Gte(IndexValue::from_json(op_val))),
         "$lt" => Ok(QueryOp::Lt(IndexValue::from_json(op_val))),
         "$lte" => Ok(QueryOp::Lte(IndexValue::from_json(op_val))),
+        "$near" | "$geoWithin" => Err(Error::InvalidQuery(format!(
+            "{op_key} is only supported on a top-level field of a find query"
+        ))),
         "$in" => {
             let arr = op_val
                 .as_array()
@@ -206,6 +477,16 @@ fn parse_op(
             })?;
             Ok(QueryOp::Regex(re))
         }
+        "$size" => parse_size(op_val),
+        "$type" => parse_type(op_val),
+        "$mod" => match op_val.as_array().map(Vec::as_slice) {
+            Some([divisor, remainder]) => match (divisor.as_i64(), remainder.as_i64()) {
+                (Some(0), Some(_)) => Err(Error::InvalidQuery("$mod divisor must not be zero".into())),
+                (Some(d), Some(r)) => Ok(QueryOp::Mod(d, r)),
+                _ => Err(Error::InvalidQuery("$mod divisor and remainder must be integers".into())),
+            },
+            _ => Err(Error::InvalidQuery("$mod must be an array of [divisor, remainder]".into())),
+        },
         _ => Err(Error::InvalidQuery(format!("unknown operator: {}", op_key))),
     }
 }
@@ -221,13 +502,42 @@ pub fn execute_indexed(
     composite_indexes: &[CompositeIndex],
 ) -> Option<BTreeSet<DocumentId>> {
     match query {
-        Query::All => None, // None = full scan needed
+        Query::All | Query::Expr(_) => None, // None = full scan needed
         Query::Field { field, op } => execute_field_op(field, op, field_indexes, composite_indexes),
         Query::And(subs) => {
             // Try merged range on same field (e.g. {age: {$gte: 25, $lte: 35}})
             if let Some((idx, start, end)) = try_merge_range_and(subs, field_indexes) {
                 return Some(idx.find_range(start, end));
             }
+            // Equality prefix + range on the next field of a composite index
+            // (e.g. {formId: "1", createdAt: {$gte: ..., $lt: ...}} on (formId, createdAt)).
+            if let Some((comp_idx, prefix, start, end)) =
+                try_composite_prefix_range(subs, composite_indexes)
+            {
+                let range_field = &comp_idx.fields[prefix.len()];
+                let mut result = comp_idx.find_prefix_range(&prefix, start, end);
+                for sub in subs {
+                    let covered = match sub {
+                        Query::Field { field, op: QueryOp::Eq(v) } => comp_idx.fields
+                            [..prefix.len()]
+                            .iter()
+                            .zip(&prefix)
+                            .any(|(f, pv)| f == field && pv == v),
+                        Query::Field {
+                            field,
+                            op: QueryOp::Gt(_) | QueryOp::Gte(_) | QueryOp::Lt(_) | QueryOp::Lte(_),
+                        } => field == range_field,
+                        _ => false,
+                    };
+                    if covered {
+                        continue;
+                    }
+                    if let Some(ids) = execute_indexed(sub, field_indexes, composite_indexes) {
+                        result = &result & &ids;
+                    }
+                }
+                return Some(result);
+            }
             let mut result: Option<BTreeSet<DocumentId>> = None;
             for sub in subs {
                 if let Some(ids) = execute_indexed(sub, field_indexes, composite_indexes) {
@@ -259,6 +569,96 @@ pub fn execute_indexed(
     }
 }
 
+/// List the indexes `execute_indexed` would consult for `query`, as
+/// (name, kind) pairs. Mirrors its decision order.
+pub fn indexes_used(
+    query: &Query,
+    field_indexes: &std::collections::HashMap<String, FieldIndex>,
+    composite_indexes: &[CompositeIndex],
+) -> Vec<(String, &'static str)> {
+    let mut used = Vec::new();
+    match query {
+        Query::All | Query::Expr(_) => {}
+        Query::Field { field, op } => {
+            if field_indexes.contains_key(field) && !op.needs_scan() {
+                used.push((field.clone(), "field"));
+            }
+        }
+        Query::And(subs) => {
+            if let Some((idx, _, _)) = try_merge_range_and(subs, field_indexes) {
+                used.push((idx.field.clone(), "field"));
+                return used;
+            }
+            let mut covered: &[String] = &[];
+            if let Some((comp_idx, prefix, _, _)) =
+                try_composite_prefix_range(subs, composite_indexes)
+            {
+                used.push((comp_idx.name(), "composite"));
+                covered = &comp_idx.fields[..=prefix.len()];
+            }
+            for sub in subs {
+                if matches!(sub, Query::Field { field, .. } if covered.contains(field)) {
+                    continue;
+                }
+                for entry in indexes_used(sub, field_indexes, composite_indexes) {
+                    if !used.contains(&entry) {
+                        used.push(entry);
+                    }
+                }
+            }
+        }
+        Query::Or(subs) => {
+            for sub in subs {
+                let sub_used = indexes_used(sub, field_indexes, composite_indexes);
+                if sub_used.is_empty() {
+                    return Vec::new();
+                }
+                for entry in sub_used {
+                    if !used.contains(&entry) {
+                        used.push(entry);
+                    }
+                }
+            }
+        }
+    }
+    used
+}
+
+/// Whether a document whose field is null satisfies `op`. Sparse indexes
+/// leave such documents out, so they can't answer these conditions.
+fn matches_null(op: &QueryOp) -> bool {
+    let null = IndexValue::Null;
+    match op {
+        QueryOp::Eq(v) => null == *v,
+        QueryOp::Ne(v) => null != *v,
+        QueryOp::Gt(v) => null > *v,
+        QueryOp::Gte(v) => null >= *v,
+        QueryOp::Lt(v) => null < *v,
+        QueryOp::Lte(v) => null <= *v,
+        QueryOp::In(vals) => vals.contains(&null),
+        QueryOp::Exists(_) | QueryOp::Regex(_) => true,
+        QueryOp::Size(_) => false,
+        QueryOp::Type(types) => types.contains(&"null"),
+        QueryOp::Mod(..) => false,
+    }
+}
+
+/// Whether a null field satisfies every condition of an AND.
+fn all_match_null(subs: &[Query]) -> bool {
+    subs.iter().all(|sub| matches!(sub, Query::Field { op, .. } if matches_null(op)))
+}
+
+/// Whether `query` can only match documents where `field` is present and
+/// not null, so a sparse index on it misses none of the matches.
+pub fn excludes_null(query: &Query, field: &str) -> bool {
+    match query {
+        Query::Field { field: f, op } => f == field && !matches_null(op),
+        Query::And(subs) => subs.iter().any(|sub| excludes_null(sub, field)),
+        Query::Or(subs) => !subs.is_empty() && subs.iter().all(|sub| excludes_null(sub, field)),
+        Query::All | Query::Expr(_) => false,
+    }
+}
+
 fn execute_field_op(
     field: &str,
     op: &QueryOp,
@@ -266,8 +666,12 @@ fn execute_field_op(
     _composite_indexes: &[CompositeIndex],
 ) -> Option<BTreeSet<DocumentId>> {
     let idx = field_indexes.get(field)?;
+    if idx.sparse && matches_null(op) {
+        return None;
+    }
 
     Some(match op {
+        QueryOp::Eq(v) if !idx.may_contain(v) => BTreeSet::new(),
         QueryOp::Eq(v) => idx.find_eq(v),
         QueryOp::Ne(v) => idx.find_ne(v),
         QueryOp::Gt(v) => idx.find_range(Bound::Excluded(v), Bound::Unbounded),
@@ -275,7 +679,11 @@ fn execute_field_op(
         QueryOp::Lt(v) => idx.find_range(Bound::Unbounded, Bound::Excluded(v)),
         QueryOp::Lte(v) => idx.find_range(Bound::Unbounded, Bound::Included(v)),
         QueryOp::In(vals) => idx.find_in(vals),
-        QueryOp::Exists(_) | QueryOp::Regex(_) => return None,
+        QueryOp::Exists(_)
+        | QueryOp::Regex(_)
+        | QueryOp::Size(_)
+        | QueryOp::Type(_)
+        | QueryOp::Mod(..) => return None,
     })
 }
 
@@ -323,6 +731,9 @@ fn try_merge_range_and<'a>(
 
     let field = field_name?;
     let idx = field_indexes.get(field)?;
+    if idx.sparse && all_match_null(subs) {
+        return None;
+    }
 
     let start = if let Some(v) = gte_bound {
         Bound::Included(v)
@@ -343,6 +754,92 @@ fn try_merge_range_and<'a>(
     Some((idx, start, end))
 }
 
+/// Composite index, equality prefix values, and range bounds on the next field.
+type PrefixRange<'a> = (
+    &'a CompositeIndex,
+    Vec<IndexValue>,
+    Bound<&'a IndexValue>,
+    Bound<&'a IndexValue>,
+);
+
+/// Find a composite index whose leading fields are all constrained by `$eq`
+/// conditions in `subs` and whose next field carries range conditions.
+/// Returns the index, the equality prefix and the merged range bounds.
+/// Prefers the longest equality prefix.
+fn try_composite_prefix_range<'a>(
+    subs: &'a [Query],
+    composite_indexes: &'a [CompositeIndex],
+) -> Option<PrefixRange<'a>> {
+    let mut best: Option<PrefixRange<'a>> = None;
+
+    for comp_idx in composite_indexes {
+        let fields = &comp_idx.fields;
+        for k in (1..fields.len()).rev() {
+            if best.as_ref().is_some_and(|(_, p, _, _)| p.len() >= k) {
+                break;
+            }
+            let prefix: Option<Vec<IndexValue>> = fields[..k]
+                .iter()
+                .map(|f| {
+                    subs.iter().find_map(|sub| match sub {
+                        Query::Field { field, op: QueryOp::Eq(v) } if field == f => Some(v.clone()),
+                        _ => None,
+                    })
+                })
+                .collect();
+            let Some(prefix) = prefix else {
+                continue;
+            };
+            let (start, end) = range_bounds_on(subs, &fields[k]);
+            if matches!((start, end), (Bound::Unbounded, Bound::Unbounded)) {
+                continue;
+            }
+            best = Some((comp_idx, prefix, start, end));
+            break;
+        }
+    }
+
+    best
+}
+
+/// Merge the `$gt`/`$gte`/`$lt`/`$lte` conditions on `field` found among
+/// top-level AND conditions into a single pair of bounds.
+fn range_bounds_on<'a>(
+    subs: &'a [Query],
+    field: &str,
+) -> (Bound<&'a IndexValue>, Bound<&'a IndexValue>) {
+    let mut start = Bound::Unbounded;
+    let mut end = Bound::Unbounded;
+    for sub in subs {
+        if let Query::Field { field: f, op } = sub {
+            if f != field {
+                continue;
+            }
+            match op {
+                QueryOp::Gte(v) => start = Bound::Included(v),
+                QueryOp::Gt(v) => start = Bound::Excluded(v),
+                QueryOp::Lt(v) => end = Bound::Excluded(v),
+                QueryOp::Lte(v) => end = Bound::Included(v),
+                _ => {}
+            }
+        }
+    }
+    (start, end)
+}
+
+/// Range bounds on `field` from a query's top-level conditions. Returns
+/// unbounded on both sides when the query has no range conditions on it.
+pub fn extract_range_bounds<'a>(
+    query: &'a Query,
+    field: &str,
+) -> (Bound<&'a IndexValue>, Bound<&'a IndexValue>) {
+    match query {
+        Query::Field { .. } => range_bounds_on(std::slice::from_ref(query), field),
+        Query::And(subs) => range_bounds_on(subs, field),
+        _ => (Bound::Unbounded, Bound::Unbounded),
+    }
+}
+
 /// Execute a query lazily against indexes, calling `callback` for each matching
 /// DocumentId. The callback returns `true` to continue or `false` to stop.
 /// Returns `Some(true)` if the query was fully handled by indexes,
@@ -353,10 +850,14 @@ pub fn execute_indexed_lazy(
     callback: &mut dyn FnMut(DocumentId) -> bool,
 ) -> Option<bool> {
     match query {
-        Query::All => None,
+        Query::All | Query::Expr(_) => None,
         Query::Field { field, op } => {
             let idx = field_indexes.get(field.as_str())?;
+            if idx.sparse && matches_null(op) {
+                return None;
+            }
             Some(match op {
+                QueryOp::Eq(v) if !idx.may_contain(v) => true,
                 QueryOp::Eq(v) => {
                     let mut cont = true;
                     idx.for_each_eq(v, |id| {
@@ -413,7 +914,11 @@ pub fn execute_indexed_lazy(
                     });
                     cont
                 }
-                QueryOp::Exists(_) | QueryOp::Regex(_) => return None,
+                QueryOp::Exists(_)
+                | QueryOp::Regex(_)
+                | QueryOp::Size(_)
+                | QueryOp::Type(_)
+                | QueryOp::Mod(..) => return None,
             })
         }
         Query::And(subs) => {
@@ -479,6 +984,7 @@ pub fn execute_indexed_lazy(
 pub fn matches_doc(query: &Query, doc: &Document) -> bool {
     match query {
         Query::All => true,
+        Query::Expr(cond) => cond.matches(&doc.data),
         Query::Field { field, op } => {
             let field_val = doc.get_field(field);
             match op {
@@ -488,6 +994,9 @@ pub fn matches_doc(query: &Query, doc: &Document) -> bool {
                         .and_then(|v| v.as_str())
                         .is_some_and(|s| re.is_match(s))
                 }
+                QueryOp::Size(conditions) => matches_size(conditions, field_val),
+                QueryOp::Type(types) => field_val.is_some_and(|v| types.contains(&json_type_name(v))),
+                QueryOp::Mod(divisor, remainder) => matches_mod(*divisor, *remainder, field_val),
                 _ => {
                     let Some(val) = field_val else {
                         return false;
@@ -501,7 +1010,11 @@ pub fn matches_doc(query: &Query, doc: &Document) -> bool {
                         QueryOp::Lt(v) => iv < *v,
                         QueryOp::Lte(v) => iv <= *v,
                         QueryOp::In(vals) => vals.contains(&iv),
-                        QueryOp::Exists(_) | QueryOp::Regex(_) => unreachable!(),
+                        QueryOp::Exists(_)
+                        | QueryOp::Regex(_)
+                        | QueryOp::Size(_)
+                        | QueryOp::Type(_)
+                        | QueryOp::Mod(..) => unreachable!(),
                     }
                 }
             }
@@ -520,6 +1033,70 @@ fn resolve_field_ref<'a>(data: &'a JsonValue, path: &str) -> Option<&'a JsonValu
     Some(current)
 }
 
+impl ExprCondition {
+    /// Evaluate the comparison against one document. A missing field, or
+    /// arithmetic on something that isn't a number, never matches.
+    pub fn matches(&self, data: &JsonValue) -> bool {
+        let (Some(lhs), Some(rhs)) = (self.lhs.eval(data), self.rhs.eval(data)) else {
+            return false;
+        };
+        match self.op {
+            "$eq" => lhs == rhs,
+            "$ne" => lhs != rhs,
+            "$gt" => lhs > rhs,
+            "$gte" => lhs >= rhs,
+            "$lt" => lhs < rhs,
+            "$lte" => lhs <= rhs,
+            _ => unreachable!("operator checked by parse_expr_condition"),
+        }
+    }
+}
+
+impl ExprOperand {
+    fn eval(&self, data: &JsonValue) -> Option<IndexValue> {
+        match self {
+            ExprOperand::Field(path) => resolve_field_ref(data, path).map(IndexValue::from_json),
+            ExprOperand::Literal(v) => Some(v.clone()),
+            ExprOperand::Add(ops) => ops.iter().try_fold(IndexValue::Integer(0), |acc, op| {
+                arith(&acc, &op.eval(data)?, i64::checked_add, |a, b| a + b)
+            }),
+            ExprOperand::Multiply(ops) => ops.iter().try_fold(IndexValue::Integer(1), |acc, op| {
+                arith(&acc, &op.eval(data)?, i64::checked_mul, |a, b| a * b)
+            }),
+            ExprOperand::Subtract(a, b) => arith(&a.eval(data)?, &b.eval(data)?, i64::checked_sub, |a, b| a - b),
+            ExprOperand::Divide(a, b) => {
+                let divisor = as_f64(&b.eval(data)?).filter(|d| *d != 0.0)?;
+                Some(IndexValue::Float(as_f64(&a.eval(data)?)? / divisor))
+            }
+        }
+    }
+}
+
+/// Integer arithmetic while both sides are integers and it doesn't
+/// overflow, floating point otherwise.
+fn arith(
+    a: &IndexValue,
+    b: &IndexValue,
+    int_op: fn(i64, i64) -> Option<i64>,
+    float_op: fn(f64, f64) -> f64,
+) -> Option<IndexValue> {
+    if let Some(n) = match (a, b) {
+        (IndexValue::Integer(x), IndexValue::Integer(y)) => int_op(*x, *y),
+        _ => None,
+    } {
+        return Some(IndexValue::Integer(n));
+    }
+    Some(IndexValue::Float(float_op(as_f64(a)?, as_f64(b)?)))
+}
+
+fn as_f64(v: &IndexValue) -> Option<f64> {
+    match v {
+        IndexValue::Integer(i) => Some(*i as f64),
+        IndexValue::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
 /// Returns true if every condition in the query is backed by a field index,
 /// meaning `execute_indexed` returns the exact set of matching IDs and no
 /// post-filtering with `matches_value` is needed.
@@ -529,12 +1106,14 @@ pub fn is_fully_indexed(
 ) -> bool {
     match query {
         Query::All => true,
+        Query::Expr(_) => false,
         Query::Field { field, op } => {
-            // $exists and $regex can't be resolved by index
-            if matches!(op, QueryOp::Exists(_) | QueryOp::Regex(_)) {
+            if op.needs_scan() {
                 return false;
             }
-            field_indexes.contains_key(field.as_str())
+            field_indexes
+                .get(field.as_str())
+                .is_some_and(|idx| !(idx.sparse && matches_null(op)))
         }
         Query::And(subs) => subs.iter().all(|s| is_fully_indexed(s, field_indexes)),
         Query::Or(subs) => subs.iter().all(|s| is_fully_indexed(s, field_indexes)),
@@ -549,8 +1128,12 @@ pub fn count_indexed(
 ) -> Option<usize> {
     match query {
         Query::All => None, // caller should use primary_index.len()
+        Query::Expr(_) => None,
         Query::Field { field, op } => {
             let idx = field_indexes.get(field.as_str())?;
+            if idx.sparse && matches_null(op) {
+                return None;
+            }
             Some(match op {
                 QueryOp::Eq(v) => idx.count_eq(v),
                 QueryOp::Ne(_v) => return None, // expensive, fall through
@@ -559,7 +1142,11 @@ pub fn count_indexed(
                 QueryOp::Lt(v) => idx.count_range(Bound::Unbounded, Bound::Excluded(v)),
                 QueryOp::Lte(v) => idx.count_range(Bound::Unbounded, Bound::Included(v)),
                 QueryOp::In(vals) => idx.count_in(vals),
-                QueryOp::Exists(_) | QueryOp::Regex(_) => return None,
+                QueryOp::Exists(_)
+                | QueryOp::Regex(_)
+                | QueryOp::Size(_)
+                | QueryOp::Type(_)
+                | QueryOp::Mod(..) => return None,
             })
         }
         Query::And(subs) => {
@@ -608,6 +1195,9 @@ fn count_single_field_and(
 
     let field = field_name?;
     let idx = field_indexes.get(field)?;
+    if idx.sparse && all_match_null(subs) {
+        return None;
+    }
 
     // If there's an eq value, the range constraints must also be satisfied
     if let Some(eq_val) = eq_value {
@@ -633,6 +1223,24 @@ fn count_single_field_and(
     Some(idx.count_range(start, end))
 }
 
+/// The part of `query` that constrains `field`: the query itself if it is a
+/// condition on `field`, or the matching conditions of a top-level `$and`.
+pub fn conditions_on_field(query: &Query, field: &str) -> Option<Query> {
+    let on_field = |q: &Query| matches!(q, Query::Field { field: f, .. } if f == field);
+    match query {
+        Query::Field { .. } if on_field(query) => Some(query.clone()),
+        Query::And(subs) => {
+            let mut conds: Vec<Query> = subs.iter().filter(|q| on_field(q)).cloned().collect();
+            match conds.len() {
+                0 => None,
+                1 => conds.pop(),
+                _ => Some(Query::And(conds)),
+            }
+        }
+        _ => None,
+    }
+}
+
 /// Extract equality conditions from a query as a field→value map.
 /// Returns None if the query contains no equality conditions.
 /// Only extracts top-level $eq conditions (not nested in $or).
@@ -683,6 +1291,7 @@ pub fn is_eq_only_on(query: &Query, fields: &[String]) -> bool {
 pub fn matches_value(query: &Query, data: &JsonValue) -> bool {
     match query {
         Query::All => true,
+        Query::Expr(cond) => cond.matches(data),
         Query::Field { field, op } => {
             let field_val = resolve_field_ref(data, field);
             match op {
@@ -692,6 +1301,9 @@ pub fn matches_value(query: &Query, data: &JsonValue) -> bool {
                         .and_then(|v| v.as_str())
                         .is_some_and(|s| re.is_match(s))
                 }
+                QueryOp::Size(conditions) => matches_size(conditions, field_val),
+                QueryOp::Type(types) => field_val.is_some_and(|v| types.contains(&json_type_name(v))),
+                QueryOp::Mod(divisor, remainder) => matches_mod(*divisor, *remainder, field_val),
                 _ => {
                     let Some(val) = field_val else {
                         return false;
@@ -705,7 +1317,11 @@ pub fn matches_value(query: &Query, data: &JsonValue) -> bool {
                         QueryOp::Lt(v) => iv < *v,
                         QueryOp::Lte(v) => iv <= *v,
                         QueryOp::In(vals) => vals.contains(&iv),
-                        QueryOp::Exists(_) | QueryOp::Regex(_) => unreachable!(),
+                        QueryOp::Exists(_)
+                        | QueryOp::Regex(_)
+                        | QueryOp::Size(_)
+                        | QueryOp::Type(_)
+                        | QueryOp::Mod(..) => unreachable!(),
                     }
                 }
             }
@@ -721,10 +1337,11 @@ pub fn matches_value(query: &Query, data: &JsonValue) -> bool {
 
 /// Check if raw JSONB bytes match the query WITHOUT full deserialization.
 /// Extracts only the fields referenced by the query using JSONB path lookup.
-/// Returns `None` for legacy JSON text (bytes starting with '{' or '[').
+/// Returns `None` for legacy JSON text (bytes starting with '{' or '[') and
+/// compressed records.
 pub fn matches_raw_jsonb(query: &Query, bytes: &[u8]) -> Option<bool> {
-    // Only works with JSONB binary, not legacy JSON text
-    if bytes.is_empty() || bytes[0] == b'{' || bytes[0] == b'[' {
+    // Only works with uncompressed JSONB binary, not legacy JSON text
+    if bytes.is_empty() || bytes[0] == b'{' || bytes[0] == b'[' || crate::codec::is_compressed(bytes) {
         return None;
     }
 
@@ -735,7 +1352,13 @@ pub fn matches_raw_jsonb(query: &Query, bytes: &[u8]) -> Option<bool> {
 fn matches_raw_inner(query: &Query, raw: &jsonb::RawJsonb) -> Option<bool> {
     match query {
         Query::All => Some(true),
+        // Needs the whole document — fall back to full decode
+        Query::Expr(_) => None,
         Query::Field { field, op } => {
+            // Array lengths, value types and remainders need the decoded value
+            if matches!(op, QueryOp::Size(_) | QueryOp::Type(_) | QueryOp::Mod(..)) {
+                return None;
+            }
             // For $regex, we need the raw string — fall back to full decode
             if matches!(op, QueryOp::Regex(_)) {
                 return extract_raw_string_value(raw, field).map(|opt_s| {
@@ -760,7 +1383,11 @@ fn matches_raw_inner(query: &Query, raw: &jsonb::RawJsonb) -> Option<bool> {
                         QueryOp::Lt(v) => iv < *v,
                         QueryOp::Lte(v) => iv <= *v,
                         QueryOp::In(vals) => vals.contains(&iv),
-                        QueryOp::Exists(_) | QueryOp::Regex(_) => unreachable!(),
+                        QueryOp::Exists(_)
+                        | QueryOp::Regex(_)
+                        | QueryOp::Size(_)
+                        | QueryOp::Type(_)
+                        | QueryOp::Mod(..) => unreachable!(),
                     })
                 }
             }
@@ -1069,6 +1696,43 @@ mod tests {
         assert_eq!(result, Some(BTreeSet::from([1, 3])));
     }
 
+    #[test]
+    fn execute_indexed_composite_prefix_range() {
+        let mut comp = CompositeIndex::new(vec!["formId".into(), "createdAt".into()]);
+        let docs = [
+            (1, json!({"formId": "1", "createdAt": "2023-12-31"})),
+            (2, json!({"formId": "1", "createdAt": "2024-01-01"})),
+            (3, json!({"formId": "1", "createdAt": "2024-06-30"})),
+            (4, json!({"formId": "1", "createdAt": "2024-07-01"})),
+            (5, json!({"formId": "2", "createdAt": "2024-03-01"})),
+        ];
+        for (id, data) in docs {
+            comp.insert_value(id, &data);
+        }
+        let field_indexes = std::collections::HashMap::new();
+        let composites = [comp];
+
+        let q = parse_query(&json!({
+            "formId": "1",
+            "createdAt": {"$gte": "2024-01-01", "$lt": "2024-07-01"}
+        }))
+        .unwrap();
+        let ids = execute_indexed(&q, &field_indexes, &composites).unwrap();
+        assert_eq!(ids, BTreeSet::from([2, 3]));
+
+        let q = parse_query(&json!({
+            "formId": "1",
+            "createdAt": {"$gt": "2024-01-01", "$lte": "2024-07-01"}
+        }))
+        .unwrap();
+        let ids = execute_indexed(&q, &field_indexes, &composites).unwrap();
+        assert_eq!(ids, BTreeSet::from([3, 4]));
+
+        // Equality alone on the prefix is not a range scan
+        let q = parse_query(&json!({"formId": "1"})).unwrap();
+        assert!(execute_indexed(&q, &field_indexes, &composites).is_none());
+    }
+
     #[test]
     fn execute_indexed_returns_none_for_unindexed() {
         let field_indexes = std::collections::HashMap::new();
@@ -1116,6 +1780,18 @@ mod tests {
         assert!(opts.sort.is_some());
         assert_eq!(opts.skip, Some(5));
         assert_eq!(opts.limit, Some(10));
+        assert!(!opts.respect_ttl);
+        assert!(parse_find_options(&json!({"respect_ttl": true})).unwrap().respect_ttl);
+    }
+
+    #[test]
+    fn parse_find_options_hint() {
+        let opts = parse_find_options(&json!({"hint": "email"})).unwrap();
+        assert_eq!(opts.hint, Some(IndexHint::Index("email".into())));
+        let opts = parse_find_options(&json!({"hint": {"$natural": 1}})).unwrap();
+        assert_eq!(opts.hint, Some(IndexHint::Natural));
+        assert!(parse_find_options(&json!({"hint": 1})).is_err());
+        assert!(parse_find_options(&json!({"hint": {"email": 1}})).is_err());
     }
 
     #[test]
@@ -1131,4 +1807,187 @@ mod tests {
         let doc = Document::new(1, json!({"other": "field"})).unwrap();
         assert!(!matches_doc(&q, &doc));
     }
+
+    #[test]
+    fn expr_compares_two_fields() {
+        let q = parse_query(&json!({"$expr": {"$gt": ["$spent", "$budget"]}})).unwrap();
+        assert!(matches_value(&q, &json!({"spent": 120, "budget": 100})));
+        assert!(!matches_value(&q, &json!({"spent": 80, "budget": 100})));
+        assert!(matches_value(&q, &json!({"spent": 100.5, "budget": 100})));
+
+        let q = parse_query(&json!({"$expr": {"$eq": ["$billing.city", "$shipping.city"]}})).unwrap();
+        let doc = Document::new(1, json!({"billing": {"city": "Oslo"}, "shipping": {"city": "Oslo"}})).unwrap();
+        assert!(matches_doc(&q, &doc));
+    }
+
+    #[test]
+    fn expr_compares_field_against_constant_and_arithmetic() {
+        let q = parse_query(&json!({"$expr": {"$lte": ["$qty", 5]}})).unwrap();
+        assert!(matches_value(&q, &json!({"qty": 5})));
+        assert!(!matches_value(&q, &json!({"qty": 6})));
+
+        // Over budget by more than 10%
+        let q = parse_query(&json!({
+            "$expr": {"$gt": [{"$subtract": ["$spent", "$budget"]}, {"$multiply": ["$budget", 0.1]}]}
+        }))
+        .unwrap();
+        assert!(matches_value(&q, &json!({"spent": 115, "budget": 100})));
+        assert!(!matches_value(&q, &json!({"spent": 105, "budget": 100})));
+
+        let q = parse_query(&json!({"$expr": {"$eq": [{"$add": ["$a", "$b", 1]}, {"$divide": ["$c", 2]}]}})).unwrap();
+        assert!(matches_value(&q, &json!({"a": 1, "b": 2, "c": 8})));
+        // Division by zero never matches
+        let q = parse_query(&json!({"$expr": {"$ne": [{"$divide": ["$a", "$b"]}, 1]}})).unwrap();
+        assert!(!matches_value(&q, &json!({"a": 1, "b": 0})));
+    }
+
+    #[test]
+    fn expr_missing_field_no_match() {
+        let q = parse_query(&json!({"$expr": {"$ne": ["$spent", "$budget"]}})).unwrap();
+        assert!(!matches_value(&q, &json!({"spent": 10})));
+        assert!(!matches_value(&q, &json!({})));
+        let q = parse_query(&json!({"$expr": {"$lt": [{"$add": ["$a", "$missing"]}, 100]}})).unwrap();
+        assert!(!matches_value(&q, &json!({"a": 1})));
+        // Arithmetic on a string is treated like a missing value
+        assert!(!matches_value(&q, &json!({"a": 1, "missing": "x"})));
+    }
+
+    #[test]
+    fn expr_combines_with_other_conditions_and_rejects_bad_input() {
+        let q = parse_query(&json!({"status": "open", "$expr": {"$gt": ["$spent", "$budget"]}})).unwrap();
+        assert!(matches!(&q, Query::And(subs) if subs.len() == 2));
+        assert!(matches_value(&q, &json!({"status": "open", "spent": 2, "budget": 1})));
+        assert!(!matches_value(&q, &json!({"status": "closed", "spent": 2, "budget": 1})));
+        assert!(!is_fully_indexed(&q, &std::collections::HashMap::new()));
+
+        assert!(parse_query(&json!({"$expr": "$spent"})).is_err());
+        assert!(parse_query(&json!({"$expr": {"$in": ["$a", "$b"]}})).is_err());
+        assert!(parse_query(&json!({"$expr": {"$gt": ["$a"]}})).is_err());
+        assert!(parse_query(&json!({"$expr": {"$gt": [{"$subtract": ["$a"]}, 1]}})).is_err());
+        assert!(parse_query(&json!({"$expr": {"$gt": [{"$pow": ["$a", 2]}, 1]}})).is_err());
+    }
+
+    #[test]
+    fn size_matches_exact_array_length() {
+        let q = parse_query(&json!({"tags": {"$size": 3}})).unwrap();
+        assert!(matches_value(&q, &json!({"tags": ["a", "b", "c"]})));
+        assert!(!matches_value(&q, &json!({"tags": ["a", "b"]})));
+        let doc = Document::new(1, json!({"tags": [1, 2, 3]})).unwrap();
+        assert!(matches_doc(&q, &doc));
+
+        let empty = parse_query(&json!({"tags": {"$size": 0}})).unwrap();
+        assert!(matches_value(&empty, &json!({"tags": []})));
+        assert!(!matches_value(&empty, &json!({"tags": ["a"]})));
+        assert!(!matches_value(&empty, &json!({})));
+    }
+
+    #[test]
+    fn size_matches_length_range() {
+        let q = parse_query(&json!({"tags": {"$size": {"$gte": 2, "$lt": 4}}})).unwrap();
+        assert!(!matches_value(&q, &json!({"tags": ["a"]})));
+        assert!(matches_value(&q, &json!({"tags": ["a", "b"]})));
+        assert!(matches_value(&q, &json!({"tags": ["a", "b", "c"]})));
+        assert!(!matches_value(&q, &json!({"tags": ["a", "b", "c", "d"]})));
+    }
+
+    #[test]
+    fn size_never_matches_non_arrays() {
+        let q = parse_query(&json!({"tags": {"$size": 1}})).unwrap();
+        assert!(!matches_value(&q, &json!({"tags": "a"})));
+        assert!(!matches_value(&q, &json!({"tags": {"a": 1}})));
+        assert!(!matches_value(&q, &json!({"tags": null})));
+        assert!(!matches_value(&q, &json!({"other": ["a"]})));
+        assert!(!is_fully_indexed(&q, &std::collections::HashMap::new()));
+
+        assert!(parse_query(&json!({"tags": {"$size": -1}})).is_err());
+        assert!(parse_query(&json!({"tags": {"$size": "3"}})).is_err());
+        assert!(parse_query(&json!({"tags": {"$size": {}}})).is_err());
+        assert!(parse_query(&json!({"tags": {"$size": {"$in": [1, 2]}}})).is_err());
+    }
+
+    #[test]
+    fn type_matches_each_json_type() {
+        let cases = [
+            ("string", json!("42")),
+            ("number", json!(42)),
+            ("number", json!(4.2)),
+            ("bool", json!(false)),
+            ("array", json!([1, 2])),
+            ("object", json!({"years": 42})),
+            ("null", json!(null)),
+        ];
+        for (type_name, value) in &cases {
+            let q = parse_query(&json!({"age": {"$type": type_name}})).unwrap();
+            for (other_name, other) in &cases {
+                let doc = json!({"age": other});
+                assert_eq!(matches_value(&q, &doc), type_name == other_name, "$type {type_name} vs {other}");
+            }
+            let doc = Document::new(1, json!({"age": value})).unwrap();
+            assert!(matches_doc(&q, &doc));
+        }
+        // Date-like strings are still strings
+        let q = parse_query(&json!({"created": {"$type": "string"}})).unwrap();
+        assert!(matches_value(&q, &json!({"created": "2024-06-15"})));
+    }
+
+    #[test]
+    fn type_accepts_array_of_types() {
+        let q = parse_query(&json!({"age": {"$type": ["string", "null"]}})).unwrap();
+        assert!(matches_value(&q, &json!({"age": "forty"})));
+        assert!(matches_value(&q, &json!({"age": null})));
+        assert!(!matches_value(&q, &json!({"age": 40})));
+    }
+
+    #[test]
+    fn type_missing_field_no_match() {
+        let q = parse_query(&json!({"age": {"$type": "null"}})).unwrap();
+        assert!(!matches_value(&q, &json!({"name": "Alice"})));
+        assert!(matches_value(&q, &json!({"profile": {"age": null}, "age": null})));
+        let nested = parse_query(&json!({"profile.age": {"$type": "number"}})).unwrap();
+        assert!(matches_value(&nested, &json!({"profile": {"age": 30}})));
+        assert!(!matches_value(&nested, &json!({"profile": {}})));
+
+        assert!(parse_query(&json!({"age": {"$type": "integer"}})).is_err());
+        assert!(parse_query(&json!({"age": {"$type": 2}})).is_err());
+        assert!(parse_query(&json!({"age": {"$type": []}})).is_err());
+    }
+
+    #[test]
+    fn mod_matches_remainder() {
+        let q = parse_query(&json!({"userId": {"$mod": [4, 0]}})).unwrap();
+        assert!(matches_value(&q, &json!({"userId": 8})));
+        assert!(matches_value(&q, &json!({"userId": 0})));
+        assert!(!matches_value(&q, &json!({"userId": 9})));
+        let q = parse_query(&json!({"userId": {"$mod": [4, 1]}})).unwrap();
+        assert!(matches_value(&q, &json!({"userId": 9})));
+        assert!(!matches_value(&q, &json!({"userId": 8})));
+        // Fractions are truncated
+        assert!(matches_value(&q, &json!({"userId": 5.75})));
+    }
+
+    #[test]
+    fn mod_negative_numbers_keep_the_dividend_sign() {
+        let q = parse_query(&json!({"n": {"$mod": [4, -1]}})).unwrap();
+        assert!(matches_value(&q, &json!({"n": -5})));
+        assert!(!matches_value(&q, &json!({"n": 3})));
+        let q = parse_query(&json!({"n": {"$mod": [-4, 1]}})).unwrap();
+        assert!(matches_value(&q, &json!({"n": 5})));
+        let q = parse_query(&json!({"n": {"$mod": [-1, 0]}})).unwrap();
+        assert!(matches_value(&q, &json!({"n": i64::MIN})));
+    }
+
+    #[test]
+    fn mod_non_numeric_field_no_match() {
+        let q = parse_query(&json!({"userId": {"$mod": [2, 0]}})).unwrap();
+        assert!(!matches_value(&q, &json!({"userId": "4"})));
+        assert!(!matches_value(&q, &json!({"userId": null})));
+        assert!(!matches_value(&q, &json!({"userId": [4]})));
+        assert!(!matches_value(&q, &json!({})));
+
+        assert!(parse_query(&json!({"userId": {"$mod": 4}})).is_err());
+        assert!(parse_query(&json!({"userId": {"$mod": [4]}})).is_err());
+        assert!(parse_query(&json!({"userId": {"$mod": [4, 0, 1]}})).is_err());
+        assert!(parse_query(&json!({"userId": {"$mod": [0, 0]}})).is_err());
+        assert!(parse_query(&json!({"userId": {"$mod": ["4", 0]}})).is_err());
+    }
 }
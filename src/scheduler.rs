@@ -1,6 +1,10 @@
-use std::sync::{mpsc, Arc};
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
 use std::time::Duration;
 
+use chrono::{Datelike, NaiveDate, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
 use serde_json::{json, Value};
 
 use crate::engine::OxiDb;
@@ -137,6 +141,69 @@ pub fn cron_matches(expr: &CronExpr, minute: u8, hour: u8, dom: u8, month: u8, d
         && expr.dows.contains(&dow)
 }
 
+// ---------------------------------------------------------------------------
+// Timezones
+// ---------------------------------------------------------------------------
+
+/// Parse an IANA timezone name such as `"America/New_York"`.
+pub fn parse_timezone(name: &str) -> Result<Tz> {
+    name.parse::<Tz>()
+        .map_err(|_| Error::ScheduleError(format!("unknown timezone: {name}")))
+}
+
+/// How far ahead `next_cron_run` searches. Long enough for any expression
+/// that can match at all, e.g. Feb 29 falling on a given weekday.
+const MAX_CRON_SEARCH_DAYS: i64 = 366 * 30;
+
+/// The first instant (epoch seconds, minute-aligned) after `after_epoch` at
+/// which `expr` fires, with fields interpreted as wall-clock time in `tz`.
+///
+/// DST transitions fire each matching wall-clock time exactly once: a time
+/// skipped by a spring-forward gap fires when the gap ends, and a time
+/// repeated by a fall-back fires on its first occurrence only.
+pub fn next_cron_run(expr: &CronExpr, tz: Tz, after_epoch: i64) -> Option<i64> {
+    let start = tz.timestamp_opt(after_epoch, 0).single()?.date_naive();
+    // The previous day too: after a fall-back, times earlier on the clock
+    // than `after` may still be in the future
+    let first_day = start.pred_opt()?;
+    for offset in 0..MAX_CRON_SEARCH_DAYS {
+        let day = first_day + chrono::Days::new(offset as u64);
+        if !day_matches(expr, day) {
+            continue;
+        }
+        for &hour in &expr.hours {
+            for &minute in &expr.minutes {
+                let local = day.and_hms_opt(hour as u32, minute as u32, 0)?;
+                let run = resolve_local(tz, local)?;
+                if run > after_epoch {
+                    return Some(run);
+                }
+            }
+        }
+    }
+    None
+}
+
+fn day_matches(expr: &CronExpr, day: NaiveDate) -> bool {
+    expr.doms.contains(&(day.day() as u8))
+        && expr.months.contains(&(day.month() as u8))
+        && expr.dows.contains(&(day.weekday().num_days_from_sunday() as u8))
+}
+
+/// Map a wall-clock time in `tz` to epoch seconds: the earlier instant if it
+/// occurs twice, or the end of the gap if it doesn't occur at all.
+fn resolve_local(tz: Tz, local: NaiveDateTime) -> Option<i64> {
+    let mut candidate = local;
+    // DST gaps are at most a few hours; search a day to be safe
+    for _ in 0..=24 * 60 {
+        if let Some(dt) = tz.from_local_datetime(&candidate).earliest() {
+            return Some(dt.timestamp());
+        }
+        candidate += chrono::Duration::minutes(1);
+    }
+    None
+}
+
 // ---------------------------------------------------------------------------
 // Interval parser
 // ---------------------------------------------------------------------------
@@ -180,6 +247,9 @@ pub fn parse_interval(s: &str) -> Result<Duration> {
 // ---------------------------------------------------------------------------
 
 /// Determine if a schedule is due to run at the given timestamp (epoch seconds).
+///
+/// `now_parts` are the UTC calendar fields of `now_epoch`; cron schedules with
+/// a `"timezone"` ignore them and use `next_cron_run` in that zone instead.
 pub fn is_schedule_due(schedule: &Value, now_epoch: i64, now_parts: (u8, u8, u8, u8, u8)) -> bool {
     let enabled = schedule
         .get("enabled")
@@ -200,9 +270,24 @@ pub fn is_schedule_due(schedule: &Value, now_epoch: i64, now_parts: (u8, u8, u8,
             Ok(e) => e,
             Err(_) => return false,
         };
-        let (minute, hour, dom, month, dow) = now_parts;
-        if !cron_matches(&expr, minute, hour, dom, month, dow) {
-            return false;
+        match schedule.get("timezone").and_then(|v| v.as_str()) {
+            Some(tz_name) => {
+                let tz = match parse_timezone(tz_name) {
+                    Ok(tz) => tz,
+                    Err(_) => return false,
+                };
+                // Due if the next run from the start of this minute is now
+                let minute_start = now_epoch - now_epoch.rem_euclid(60);
+                if next_cron_run(&expr, tz, minute_start - 1) != Some(minute_start) {
+                    return false;
+                }
+            }
+            None => {
+                let (minute, hour, dom, month, dow) = now_parts;
+                if !cron_matches(&expr, minute, hour, dom, month, dow) {
+                    return false;
+                }
+            }
         }
         // Don't re-run within the same minute
         let same_minute = (now_epoch - last_run_epoch).unsigned_abs() < 60;
@@ -222,15 +307,89 @@ pub fn is_schedule_due(schedule: &Value, now_epoch: i64, now_parts: (u8, u8, u8,
     false
 }
 
+// ---------------------------------------------------------------------------
+// Overlap tracking
+// ---------------------------------------------------------------------------
+
+/// What to do with a schedule that has come due.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RunDecision {
+    /// Not running: start a run now.
+    Start,
+    /// Still running with `catch_up` set: run again once it finishes.
+    Queued,
+    /// Still running: skip this run.
+    Skipped,
+}
+
+/// In-memory record of which schedules are running, so that a schedule
+/// never runs concurrently with itself.
+#[derive(Clone, Default)]
+pub struct RunTracker {
+    /// Running schedule name → runs queued behind the current one.
+    running: Arc<Mutex<HashMap<String, u32>>>,
+}
+
+impl RunTracker {
+    /// Decide whether a due schedule may start, marking it running if so.
+    pub fn begin(&self, name: &str, catch_up: bool) -> RunDecision {
+        let mut running = self.running.lock().unwrap();
+        match running.get_mut(name) {
+            None => {
+                running.insert(name.to_string(), 0);
+                RunDecision::Start
+            }
+            Some(queued) if catch_up => {
+                *queued += 1;
+                RunDecision::Queued
+            }
+            Some(_) => RunDecision::Skipped,
+        }
+    }
+
+    /// Record that a run finished. Returns true if a queued run should
+    /// start now, in which case the schedule stays marked running.
+    pub fn finish(&self, name: &str) -> bool {
+        let mut running = self.running.lock().unwrap();
+        match running.get_mut(name) {
+            Some(queued) if *queued > 0 => {
+                *queued -= 1;
+                true
+            }
+            _ => {
+                running.remove(name);
+                false
+            }
+        }
+    }
+
+    pub fn is_running(&self, name: &str) -> bool {
+        self.running.lock().unwrap().contains_key(name)
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Scheduler worker loop
 // ---------------------------------------------------------------------------
 
+/// Collection holding each schedule's recent runs.
+pub const RUNS_COLLECTION: &str = "_schedule_runs";
+
+/// Runs kept per schedule unless it sets `"history_limit"`.
+pub const DEFAULT_HISTORY_LIMIT: usize = 20;
+
+/// Runs a schedule's procedure: `(db, procedure, params)`.
+pub type RunFn = Arc<dyn Fn(&OxiDb, &str, Value) -> Result<Value> + Send + Sync>;
+
 /// The scheduler background thread body.
 ///
-/// Wakes every second, checks enabled schedules, and runs due procedures.
-/// Exits when the shutdown channel is closed (sender dropped).
+/// Wakes every second, checks enabled schedules, and starts due procedures
+/// on their own threads. Exits when the shutdown channel is closed (sender
+/// dropped).
 pub fn scheduler_loop(db: Arc<OxiDb>, rx: mpsc::Receiver<()>) {
+    let run: RunFn = Arc::new(|db: &OxiDb, procedure: &str, params: Value| {
+        db.call_procedure(procedure, params)
+    });
     loop {
         // Sleep 1 second, checking for shutdown
         match rx.recv_timeout(Duration::from_secs(1)) {
@@ -239,68 +398,198 @@ pub fn scheduler_loop(db: Arc<OxiDb>, rx: mpsc::Receiver<()>) {
             Err(mpsc::RecvTimeoutError::Timeout) => {}          // normal tick
         }
 
-        // Get current time components
-        let now_epoch = epoch_now();
-        let now_parts = epoch_to_parts(now_epoch);
+        run_due_schedules(&db, db.schedule_runs(), epoch_now(), &run);
+    }
+}
 
-        // Load enabled schedules from _schedules collection
-        let schedules = match db.find("_schedules", &json!({"enabled": true})) {
-            Ok(s) => s,
-            Err(_) => continue, // collection may not exist yet
-        };
+/// Start every schedule that is due at `now_epoch`, each on its own thread,
+/// and return the handles of the threads started.
+///
+/// A schedule that is due while its previous run is still going is skipped
+/// and recorded as `"skipped_overlap"`, or, if it has `"catch_up": true`,
+/// run again once the current run finishes.
+pub fn run_due_schedules(
+    db: &Arc<OxiDb>,
+    tracker: &RunTracker,
+    now_epoch: i64,
+    run: &RunFn,
+) -> Vec<JoinHandle<()>> {
+    let now_parts = epoch_to_parts(now_epoch);
+
+    // Load enabled schedules from _schedules collection
+    let schedules = match db.find_unlimited("_schedules", &json!({"enabled": true})) {
+        Ok(s) => s,
+        Err(_) => return Vec::new(), // collection may not exist yet
+    };
 
-        for sched in &schedules {
-            if !is_schedule_due(sched, now_epoch, now_parts) {
-                continue;
+    let mut handles = Vec::new();
+    for sched in &schedules {
+        if !is_schedule_due(sched, now_epoch, now_parts) {
+            continue;
+        }
+
+        let job = match ScheduleJob::from_doc(sched) {
+            Some(job) => job,
+            None => continue,
+        };
+        let name = job.name.clone();
+
+        // Mark this due time as handled whether or not it runs now
+        let last_run_iso = epoch_to_iso(now_epoch);
+        let decision = tracker.begin(&name, job.catch_up);
+        let update = match decision {
+            RunDecision::Start | RunDecision::Queued => json!({
+                "$set": {"last_run_epoch": now_epoch},
+            }),
+            RunDecision::Skipped => {
+                eprintln!("[scheduler] skipping schedule '{name}': previous run still in progress");
+                json!({
+                    "$set": {
+                        "last_run_epoch": now_epoch,
+                        "last_status": "skipped_overlap",
+                        "last_skipped": last_run_iso,
+                    },
+                    "$inc": {"skip_count": 1},
+                })
             }
+        };
+        let _ = db.update("_schedules", &json!({"name": name}), &update);
+        if decision != RunDecision::Start {
+            continue;
+        }
 
-            let name = match sched.get("name").and_then(|v| v.as_str()) {
-                Some(n) => n.to_string(),
-                None => continue,
-            };
-            let procedure = match sched.get("procedure").and_then(|v| v.as_str()) {
-                Some(p) => p.to_string(),
-                None => continue,
-            };
-            let params = sched.get("params").cloned().unwrap_or(json!({}));
-
-            // Execute the procedure
-            let (status, error) = match db.call_procedure(&procedure, params) {
-                Ok(_) => ("ok".to_string(), Value::Null),
-                Err(e) => {
-                    let msg = e.to_string();
-                    eprintln!("[scheduler] error running schedule '{name}': {msg}");
-                    ("error".to_string(), Value::String(msg))
-                }
-            };
+        let db = Arc::clone(db);
+        let tracker = tracker.clone();
+        let run = Arc::clone(run);
+        handles.push(std::thread::spawn(move || {
+            let _ = job.run_started(&db, &tracker, &run);
+        }));
+    }
+    handles
+}
+
+/// Run a schedule immediately on the calling thread, recording the outcome
+/// as a scheduled run would, and return the procedure's result. Fails if the
+/// schedule is already running.
+pub fn run_schedule_now(
+    db: &OxiDb,
+    tracker: &RunTracker,
+    sched: &Value,
+    run: &RunFn,
+) -> Result<Value> {
+    let job = ScheduleJob::from_doc(sched).ok_or_else(|| {
+        Error::ScheduleError("schedule is missing 'name' or 'procedure'".into())
+    })?;
+    // Never queue: the caller is waiting for this run
+    if tracker.begin(&job.name, false) != RunDecision::Start {
+        return Err(Error::ScheduleError(format!(
+            "schedule '{}' is already running",
+            job.name
+        )));
+    }
+    let _ = db.update(
+        "_schedules",
+        &json!({"name": job.name}),
+        &json!({"$set": {"last_run_epoch": epoch_now()}}),
+    );
+    job.run_started(db, tracker, run)
+}
 
-            // Build ISO 8601 timestamp for last_run
-            let last_run_iso = epoch_to_iso(now_epoch);
+/// A schedule document's run settings.
+struct ScheduleJob {
+    name: String,
+    procedure: String,
+    params: Value,
+    catch_up: bool,
+    history_limit: usize,
+}
 
-            // Update the schedule record
-            let run_count = sched
-                .get("run_count")
+impl ScheduleJob {
+    fn from_doc(sched: &Value) -> Option<Self> {
+        Some(Self {
+            name: sched.get("name")?.as_str()?.to_string(),
+            procedure: sched.get("procedure")?.as_str()?.to_string(),
+            params: sched.get("params").cloned().unwrap_or(json!({})),
+            catch_up: sched.get("catch_up").and_then(|v| v.as_bool()).unwrap_or(false),
+            history_limit: sched
+                .get("history_limit")
                 .and_then(|v| v.as_u64())
-                .unwrap_or(0)
-                + 1;
+                .map_or(DEFAULT_HISTORY_LIMIT, |n| n as usize),
+        })
+    }
 
-            let _ = db.update(
-                "_schedules",
-                &json!({"name": name}),
-                &json!({
-                    "$set": {
-                        "last_run": last_run_iso,
-                        "last_run_epoch": now_epoch,
-                        "last_status": status,
-                        "last_error": error,
-                        "run_count": run_count,
-                    }
-                }),
-            );
+    /// Run a schedule that `tracker` has marked running, then any runs
+    /// queued behind it, and release it. Returns the first run's result.
+    fn run_started(&self, db: &OxiDb, tracker: &RunTracker, run: &RunFn) -> Result<Value> {
+        let result = self.run_once(db, run);
+        while tracker.finish(&self.name) {
+            let _ = self.run_once(db, run);
+        }
+        result
+    }
+
+    /// Run the procedure once and record the outcome, both on the schedule
+    /// and in its run history.
+    fn run_once(&self, db: &OxiDb, run: &RunFn) -> Result<Value> {
+        let name = self.name.as_str();
+        let started = epoch_now();
+        let timer = std::time::Instant::now();
+        let result = run(db, &self.procedure, self.params.clone());
+        let (status, error) = match &result {
+            Ok(_) => ("ok".to_string(), Value::Null),
+            Err(e) => {
+                let msg = e.to_string();
+                eprintln!("[scheduler] error running schedule '{name}': {msg}");
+                ("error".to_string(), Value::String(msg))
+            }
+        };
+
+        let _ = db.update(
+            "_schedules",
+            &json!({"name": name}),
+            &json!({
+                "$set": {
+                    "last_run": epoch_to_iso(started),
+                    "last_status": status,
+                    "last_error": error,
+                },
+                "$inc": {"run_count": 1},
+            }),
+        );
+
+        let entry = json!({
+            "schedule": name,
+            "timestamp": epoch_to_iso(started),
+            "epoch": started,
+            "status": status,
+            "duration_ms": timer.elapsed().as_millis() as u64,
+            "error": error,
+        });
+        if let Err(e) = record_run(db, name, entry, self.history_limit) {
+            eprintln!("[scheduler] failed to record run of schedule '{name}': {e}");
         }
+        result
     }
 }
 
+/// Append a run to the schedule's history, dropping the oldest runs beyond
+/// `limit`.
+fn record_run(db: &OxiDb, name: &str, entry: Value, limit: usize) -> Result<()> {
+    db.insert(RUNS_COLLECTION, entry)?;
+    let mut ids: Vec<u64> = db
+        .find(RUNS_COLLECTION, &json!({"schedule": name}))?
+        .iter()
+        .filter_map(|run| run.get("_id").and_then(|v| v.as_u64()))
+        .collect();
+    if ids.len() > limit {
+        ids.sort_unstable();
+        for id in &ids[..ids.len() - limit] {
+            db.delete(RUNS_COLLECTION, &json!({"_id": id}))?;
+        }
+    }
+    Ok(())
+}
+
 // ---------------------------------------------------------------------------
 // Time helpers (no chrono dependency — uses std SystemTime)
 // ---------------------------------------------------------------------------
@@ -372,6 +661,7 @@ fn epoch_to_iso(epoch: i64) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     // -- Cron parser tests --
 
@@ -463,6 +753,65 @@ mod tests {
         assert!(!cron_matches(&expr, 0, 9, 1, 1, 6)); // Saturday
     }
 
+    // -- Timezone tests --
+
+    fn utc(s: &str) -> i64 {
+        chrono::DateTime::parse_from_rfc3339(s).unwrap().timestamp()
+    }
+
+    #[test]
+    fn next_run_in_timezone() {
+        let expr = parse_cron("0 9 * * *").unwrap();
+        let tz = parse_timezone("America/New_York").unwrap();
+        // 9am EDT is 13:00 UTC
+        assert_eq!(
+            next_cron_run(&expr, tz, utc("2026-07-01T00:00:00Z")),
+            Some(utc("2026-07-01T13:00:00Z"))
+        );
+        // 9am EST is 14:00 UTC
+        assert_eq!(
+            next_cron_run(&expr, tz, utc("2026-12-01T15:00:00Z")),
+            Some(utc("2026-12-02T14:00:00Z"))
+        );
+        assert!(parse_timezone("Mars/Olympus_Mons").is_err());
+    }
+
+    #[test]
+    fn next_run_across_spring_forward() {
+        // 2026-03-08: New York clocks jump from 02:00 EST to 03:00 EDT, so
+        // 02:30 doesn't exist that day and runs when the gap ends
+        let expr = parse_cron("30 2 * * *").unwrap();
+        let tz = parse_timezone("America/New_York").unwrap();
+        let run = next_cron_run(&expr, tz, utc("2026-03-07T12:00:00Z")).unwrap();
+        assert_eq!(run, utc("2026-03-08T07:00:00Z")); // 03:00 EDT
+        let next = next_cron_run(&expr, tz, run).unwrap();
+        assert_eq!(next, utc("2026-03-09T06:30:00Z")); // 02:30 EDT
+    }
+
+    #[test]
+    fn next_run_across_fall_back() {
+        // 2026-11-01: New York clocks fall back from 02:00 EDT to 01:00 EST,
+        // so 01:30 happens twice; the job runs only the first time
+        let expr = parse_cron("30 1 * * *").unwrap();
+        let tz = parse_timezone("America/New_York").unwrap();
+        let run = next_cron_run(&expr, tz, utc("2026-10-31T12:00:00Z")).unwrap();
+        assert_eq!(run, utc("2026-11-01T05:30:00Z")); // 01:30 EDT
+        let next = next_cron_run(&expr, tz, run).unwrap();
+        assert_eq!(next, utc("2026-11-02T06:30:00Z")); // 01:30 EST, next day
+
+        let sched = json!({
+            "name": "test",
+            "procedure": "proc",
+            "cron": "30 1 * * *",
+            "timezone": "America/New_York",
+            "enabled": true,
+            "last_run_epoch": 0,
+        });
+        assert!(is_schedule_due(&sched, utc("2026-11-01T05:30:10Z"), (0, 0, 1, 1, 0)));
+        // The repeated 01:30 (EST) is not a run time
+        assert!(!is_schedule_due(&sched, utc("2026-11-01T06:30:10Z"), (0, 0, 1, 1, 0)));
+    }
+
     // -- Interval parser tests --
 
     #[test]
@@ -548,6 +897,176 @@ mod tests {
         assert!(!is_schedule_due(&sched, 1000000, (0, 0, 1, 1, 0)));
     }
 
+    // -- Overlap tests --
+
+    struct SlowRun {
+        run: RunFn,
+        runs: Arc<AtomicUsize>,
+        max_active: Arc<AtomicUsize>,
+    }
+
+    /// A procedure runner that takes `ms` and tracks how many runs overlap.
+    fn slow_run(ms: u64) -> SlowRun {
+        let active = Arc::new(AtomicUsize::new(0));
+        let runs = Arc::new(AtomicUsize::new(0));
+        let max_active = Arc::new(AtomicUsize::new(0));
+        let (r, m) = (Arc::clone(&runs), Arc::clone(&max_active));
+        let run: RunFn = Arc::new(move |_db: &OxiDb, _proc: &str, _params: Value| {
+            let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+            m.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(ms));
+            active.fetch_sub(1, Ordering::SeqCst);
+            r.fetch_add(1, Ordering::SeqCst);
+            Ok(Value::Null)
+        });
+        SlowRun { run, runs, max_active }
+    }
+
+    fn open_with_schedule(dir: &tempfile::TempDir, extra: Value) -> Arc<OxiDb> {
+        let db = Arc::new(OxiDb::open(dir.path()).unwrap());
+        let mut sched = json!({
+            "name": "slow",
+            "procedure": "proc",
+            "every": "1s",
+            "enabled": true,
+            "last_run_epoch": 0,
+        });
+        sched.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        db.insert("_schedules", sched).unwrap();
+        db
+    }
+
+    #[test]
+    fn overlapping_run_is_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_with_schedule(&dir, json!({}));
+        let slow = slow_run(300);
+        let tracker = RunTracker::default();
+
+        let started = run_due_schedules(&db, &tracker, 1000, &slow.run);
+        assert_eq!(started.len(), 1);
+        // Due again while the first run is still going
+        assert!(run_due_schedules(&db, &tracker, 1001, &slow.run).is_empty());
+        let sched = db.find_one("_schedules", &json!({"name": "slow"})).unwrap().unwrap();
+        assert_eq!(sched["last_status"], "skipped_overlap");
+        assert_eq!(sched["skip_count"], 1);
+
+        for h in started {
+            h.join().unwrap();
+        }
+        assert_eq!(slow.runs.load(Ordering::SeqCst), 1);
+        assert_eq!(slow.max_active.load(Ordering::SeqCst), 1);
+        assert!(!tracker.is_running("slow"));
+        let sched = db.find_one("_schedules", &json!({"name": "slow"})).unwrap().unwrap();
+        assert_eq!(sched["last_status"], "ok");
+        assert_eq!(sched["run_count"], 1);
+        assert_eq!(sched["last_skipped"], epoch_to_iso(1001));
+
+        // Once finished it runs normally again
+        let started = run_due_schedules(&db, &tracker, 1002, &slow.run);
+        assert_eq!(started.len(), 1);
+        for h in started {
+            h.join().unwrap();
+        }
+        assert_eq!(slow.runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn catch_up_queues_missed_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_with_schedule(&dir, json!({"catch_up": true}));
+        let slow = slow_run(200);
+        let tracker = RunTracker::default();
+
+        let started = run_due_schedules(&db, &tracker, 1000, &slow.run);
+        assert!(run_due_schedules(&db, &tracker, 1001, &slow.run).is_empty());
+        assert!(run_due_schedules(&db, &tracker, 1002, &slow.run).is_empty());
+        for h in started {
+            h.join().unwrap();
+        }
+
+        // Both missed runs ran afterwards, one at a time
+        assert_eq!(slow.runs.load(Ordering::SeqCst), 3);
+        assert_eq!(slow.max_active.load(Ordering::SeqCst), 1);
+        let sched = db.find_one("_schedules", &json!({"name": "slow"})).unwrap().unwrap();
+        assert_eq!(sched["run_count"], 3);
+        assert!(sched.get("skip_count").is_none());
+    }
+
+    #[test]
+    fn run_history_records_each_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_with_schedule(&dir, json!({"history_limit": 3}));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let c = Arc::clone(&calls);
+        // Every second run fails
+        let run: RunFn = Arc::new(move |_db: &OxiDb, _proc: &str, _params: Value| {
+            if c.fetch_add(1, Ordering::SeqCst) % 2 == 1 {
+                Err(Error::ScheduleError("boom".into()))
+            } else {
+                Ok(Value::Null)
+            }
+        });
+        let tracker = RunTracker::default();
+        for now in [1000, 1001, 1002] {
+            for h in run_due_schedules(&db, &tracker, now, &run) {
+                h.join().unwrap();
+            }
+        }
+
+        let history = db.schedule_history("slow", None).unwrap();
+        let statuses: Vec<&str> = history.iter().map(|r| r["status"].as_str().unwrap()).collect();
+        // Most recent first
+        assert_eq!(statuses, vec!["ok", "error", "ok"]);
+        assert_eq!(history[1]["error"], "schedule error: boom");
+        assert!(history[0]["error"].is_null());
+        assert!(history.iter().all(|r| r["duration_ms"].is_u64() && r["timestamp"].is_string()));
+        assert_eq!(db.schedule_history("slow", Some(1)).unwrap().len(), 1);
+
+        // Trimmed to history_limit
+        for h in run_due_schedules(&db, &tracker, 1003, &run) {
+            h.join().unwrap();
+        }
+        let history = db.schedule_history("slow", None).unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0]["status"], "error");
+        assert_eq!(history[2]["status"], "error");
+        assert!(db.schedule_history("missing", None).is_err());
+    }
+
+    #[test]
+    fn run_now_returns_result_and_respects_overlap() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_with_schedule(&dir, json!({"params": {"n": "7"}}));
+        let tracker = RunTracker::default();
+        // Echo the stored params back as the procedure result
+        let echo: RunFn = Arc::new(|_db: &OxiDb, procedure: &str, params: Value| {
+            Ok(json!({"procedure": procedure, "params": params}))
+        });
+        let sched = db.find_one("_schedules", &json!({"name": "slow"})).unwrap().unwrap();
+
+        let result = run_schedule_now(&db, &tracker, &sched, &echo).unwrap();
+        assert_eq!(result, json!({"procedure": "proc", "params": {"n": "7"}}));
+        assert!(!tracker.is_running("slow"));
+        let after = db.find_one("_schedules", &json!({"name": "slow"})).unwrap().unwrap();
+        assert_eq!(after["run_count"], 1);
+        assert_eq!(after["last_status"], "ok");
+        assert!(after["last_run_epoch"].as_i64().unwrap() > 0);
+        assert_eq!(db.schedule_history("slow", None).unwrap().len(), 1);
+
+        // Refused while a scheduled run is in progress
+        let slow = slow_run(300);
+        let started = run_due_schedules(&db, &tracker, 1000, &slow.run);
+        assert_eq!(started.len(), 1);
+        let err = run_schedule_now(&db, &tracker, &sched, &echo).unwrap_err();
+        assert!(err.to_string().contains("already running"));
+        for h in started {
+            h.join().unwrap();
+        }
+        let after = db.find_one("_schedules", &json!({"name": "slow"})).unwrap().unwrap();
+        assert_eq!(after["run_count"], 2);
+    }
+
     // -- Time helper tests --
 
     #[test]
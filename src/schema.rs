@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::error::{Error, Result};
+
+/// Keywords that only describe a schema and never affect validation.
+const ANNOTATIONS: &[&str] = &["$schema", "$id", "$comment", "title", "description", "default", "examples"];
+
+/// Fields the database adds to every document, allowed even when
+/// `additionalProperties` is false at the top level.
+const SYSTEM_FIELDS: &[&str] = &["_id", "_version"];
+
+/// What happens to a document that fails validation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationLevel {
+    /// Reject the write with `Error::ValidationFailed`.
+    #[default]
+    Strict,
+    /// Log a warning and write the document anyway.
+    Warn,
+}
+
+impl ValidationLevel {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "strict" => Ok(Self::Strict),
+            "warn" => Ok(Self::Warn),
+            _ => Err(Error::InvalidSchema(format!("unknown validation level '{s}' (expected strict or warn)"))),
+        }
+    }
+}
+
+/// A JSON Schema that documents written to a collection must satisfy.
+///
+/// Supports the keywords `type`, `enum`, `const`, `required`, `properties`,
+/// `additionalProperties`, `minimum`, `maximum`, `exclusiveMinimum`,
+/// `exclusiveMaximum`, `minLength`, `maxLength`, `pattern`, `items`,
+/// `minItems`, `maxItems` and `uniqueItems`, plus annotations such as
+/// `title` and `description`. Any other keyword is rejected rather than
+/// silently ignored.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CollectionSchema {
+    pub schema: Value,
+    #[serde(default)]
+    pub level: ValidationLevel,
+    /// Convert values to the type the schema asks for when possible
+    /// (`"42"` to `42`, `"true"` to `true`, `7` to `"7"`) before validating.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub coerce: bool,
+    /// Compiled `pattern` regexes, keyed by pattern.
+    #[serde(skip)]
+    patterns: HashMap<String, Regex>,
+}
+
+impl CollectionSchema {
+    /// Check `schema` and compile its patterns.
+    pub fn new(schema: Value, level: ValidationLevel, coerce: bool) -> Result<Self> {
+        let mut patterns = HashMap::new();
+        check_schema(&schema, "", &mut patterns)?;
+        Ok(Self { schema, level, coerce, patterns })
+    }
+
+    /// Validate `doc`, coercing its values first if enabled. Returns a
+    /// description of each violation; empty if the document is valid.
+    pub fn validate(&self, doc: &mut Value) -> Vec<String> {
+        let mut errors = Vec::new();
+        self.validate_value(&self.schema, doc, "", &mut errors);
+        errors
+    }
+
+    fn validate_value(&self, schema: &Value, value: &mut Value, path: &str, errors: &mut Vec<String>) {
+        let Some(schema) = schema.as_object() else {
+            // `true` accepts anything, `false` nothing
+            if schema == &Value::Bool(false) {
+                errors.push(format!("{}: not allowed", display_path(path)));
+            }
+            return;
+        };
+
+        if let Some(types) = schema.get("type") {
+            let types: Vec<&str> = match types {
+                Value::String(t) => vec![t.as_str()],
+                Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !types.iter().any(|t| has_type(value, t)) {
+                if self.coerce {
+                    coerce(value, &types);
+                }
+                if !types.iter().any(|t| has_type(value, t)) {
+                    errors.push(format!(
+                        "{}: expected {}, found {}",
+                        display_path(path),
+                        types.join(" or "),
+                        type_name(value)
+                    ));
+                    return;
+                }
+            }
+        }
+
+        match schema.get("enum") {
+            Some(Value::Array(allowed)) if !allowed.contains(value) => {
+                errors.push(format!("{}: must be one of {}", display_path(path), Value::Array(allowed.clone())));
+            }
+            _ => {}
+        }
+        if let Some(expected) = schema.get("const").filter(|&expected| expected != &*value) {
+            errors.push(format!("{}: must be {expected}", display_path(path)));
+        }
+
+        match value {
+            Value::Number(n) => check_number(schema, n.as_f64().unwrap_or(f64::NAN), path, errors),
+            Value::String(s) => self.check_string(schema, s, path, errors),
+            Value::Array(items) => self.check_array(schema, items, path, errors),
+            Value::Object(obj) => self.check_object(schema, obj, path, errors),
+            _ => {}
+        }
+    }
+
+    fn check_string(&self, schema: &Map<String, Value>, s: &str, path: &str, errors: &mut Vec<String>) {
+        let len = s.chars().count() as u64;
+        if let Some(min) = schema.get("minLength").and_then(Value::as_u64).filter(|&min| len < min) {
+            errors.push(format!("{}: must be at least {min} characters", display_path(path)));
+        }
+        if let Some(max) = schema.get("maxLength").and_then(Value::as_u64).filter(|&max| len > max) {
+            errors.push(format!("{}: must be at most {max} characters", display_path(path)));
+        }
+        let pattern = schema.get("pattern").and_then(Value::as_str);
+        if let Some(pattern) = pattern.filter(|p| self.patterns.get(*p).is_some_and(|re| !re.is_match(s))) {
+            errors.push(format!("{}: must match pattern '{pattern}'", display_path(path)));
+        }
+    }
+
+    fn check_array(&self, schema: &Map<String, Value>, items: &mut [Value], path: &str, errors: &mut Vec<String>) {
+        let len = items.len() as u64;
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64).filter(|&min| len < min) {
+            errors.push(format!("{}: must have at least {min} items", display_path(path)));
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64).filter(|&max| len > max) {
+            errors.push(format!("{}: must have at most {max} items", display_path(path)));
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (i, item) in items.iter_mut().enumerate() {
+                self.validate_value(item_schema, item, &format!("{path}[{i}]"), errors);
+            }
+        }
+        if schema.get("uniqueItems") == Some(&Value::Bool(true)) {
+            let duplicate = items.iter().enumerate().any(|(i, item)| items[..i].contains(item));
+            if duplicate {
+                errors.push(format!("{}: items must be unique", display_path(path)));
+            }
+        }
+    }
+
+    fn check_object(&self, schema: &Map<String, Value>, obj: &mut Map<String, Value>, path: &str, errors: &mut Vec<String>) {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for field in required.iter().filter_map(Value::as_str) {
+                if !obj.contains_key(field) {
+                    errors.push(format!("{}: missing required field '{field}'", display_path(path)));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let additional = schema.get("additionalProperties");
+        for (key, value) in obj.iter_mut() {
+            let field_path = if path.is_empty() { key.clone() } else { format!("{path}.{key}") };
+            match (properties.and_then(|p| p.get(key)), additional) {
+                (Some(prop_schema), _) => self.validate_value(prop_schema, value, &field_path, errors),
+                (None, _) if path.is_empty() && SYSTEM_FIELDS.contains(&key.as_str()) => {}
+                (None, Some(extra)) => self.validate_value(extra, value, &field_path, errors),
+                (None, None) => {}
+            }
+        }
+    }
+}
+
+fn check_number(schema: &Map<String, Value>, n: f64, path: &str, errors: &mut Vec<String>) {
+    let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+    if let Some(min) = bound("minimum").filter(|&min| n < min) {
+        errors.push(format!("{}: must be >= {min}", display_path(path)));
+    }
+    if let Some(max) = bound("maximum").filter(|&max| n > max) {
+        errors.push(format!("{}: must be <= {max}", display_path(path)));
+    }
+    if let Some(min) = bound("exclusiveMinimum").filter(|&min| n <= min) {
+        errors.push(format!("{}: must be > {min}", display_path(path)));
+    }
+    if let Some(max) = bound("exclusiveMaximum").filter(|&max| n >= max) {
+        errors.push(format!("{}: must be < {max}", display_path(path)));
+    }
+}
+
+/// Reject a schema that uses unsupported keywords or malformed values, and
+/// compile its patterns into `patterns`.
+fn check_schema(schema: &Value, path: &str, patterns: &mut HashMap<String, Regex>) -> Result<()> {
+    let obj = match schema {
+        Value::Object(obj) => obj,
+        Value::Bool(_) => return Ok(()),
+        _ => return Err(invalid(path, "a schema must be an object or a boolean")),
+    };
+    for (keyword, value) in obj {
+        let at = if path.is_empty() { keyword.clone() } else { format!("{path}.{keyword}") };
+        match keyword.as_str() {
+            "type" => {
+                let types: Vec<&Value> = match value {
+                    Value::Array(ts) => ts.iter().collect(),
+                    t => vec![t],
+                };
+                for t in types {
+                    if !t.as_str().is_some_and(|t| TYPES.contains(&t)) {
+                        return Err(invalid(&at, &format!("unknown type {t} (expected one of {})", TYPES.join(", "))));
+                    }
+                }
+            }
+            "enum" if !value.is_array() => return Err(invalid(&at, "must be an array")),
+            "required" if !value.as_array().is_some_and(|r| r.iter().all(Value::is_string)) => {
+                return Err(invalid(&at, "must be an array of field names"));
+            }
+            "properties" => {
+                let props = value.as_object().ok_or_else(|| invalid(&at, "must be an object"))?;
+                for (name, prop) in props {
+                    check_schema(prop, &format!("{at}.{name}"), patterns)?;
+                }
+            }
+            "additionalProperties" | "items" => check_schema(value, &at, patterns)?,
+            "minimum" | "maximum" | "exclusiveMinimum" | "exclusiveMaximum" if !value.is_number() => {
+                return Err(invalid(&at, "must be a number"));
+            }
+            "minLength" | "maxLength" | "minItems" | "maxItems" if value.as_u64().is_none() => {
+                return Err(invalid(&at, "must be a non-negative integer"));
+            }
+            "uniqueItems" if !value.is_boolean() => return Err(invalid(&at, "must be a boolean")),
+            "pattern" => {
+                let pattern = value.as_str().ok_or_else(|| invalid(&at, "must be a string"))?;
+                let re = Regex::new(pattern).map_err(|e| invalid(&at, &e.to_string()))?;
+                patterns.insert(pattern.to_string(), re);
+            }
+            "enum" | "const" | "required" | "minimum" | "maximum" | "exclusiveMinimum" | "exclusiveMaximum"
+            | "minLength" | "maxLength" | "minItems" | "maxItems" | "uniqueItems" => {}
+            k if ANNOTATIONS.contains(&k) => {}
+            _ => return Err(invalid(&at, "unsupported keyword")),
+        }
+    }
+    Ok(())
+}
+
+const TYPES: &[&str] = &["null", "boolean", "integer", "number", "string", "array", "object"];
+
+fn invalid(path: &str, msg: &str) -> Error {
+    Error::InvalidSchema(format!("{}: {msg}", display_path(path)))
+}
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() { "document" } else { path }
+}
+
+fn has_type(value: &Value, t: &str) -> bool {
+    match t {
+        "integer" => match value {
+            Value::Number(n) => n.is_i64() || n.is_u64() || n.as_f64().is_some_and(|f| f.fract() == 0.0),
+            _ => false,
+        },
+        _ => type_name(value) == t,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Convert `value` to the first of `types` it can be read as, leaving it
+/// unchanged if none fits.
+fn coerce(value: &mut Value, types: &[&str]) {
+    for t in types {
+        let converted = match (*t, &*value) {
+            ("integer", Value::String(s)) => s.trim().parse::<i64>().ok().map(Value::from),
+            ("integer", Value::Number(n)) => {
+                n.as_f64().filter(|f| f.fract() == 0.0 && f.abs() < i64::MAX as f64).map(|f| Value::from(f as i64))
+            }
+            ("number", Value::String(s)) => {
+                let s = s.trim();
+                s.parse::<i64>().ok().map(Value::from).or_else(|| {
+                    s.parse::<f64>().ok().filter(|f| f.is_finite()).map(Value::from)
+                })
+            }
+            ("boolean", Value::String(s)) => match s.as_str() {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            ("string", Value::Number(n)) => Some(Value::String(n.to_string())),
+            ("string", Value::Bool(b)) => Some(Value::String(b.to_string())),
+            _ => None,
+        };
+        if let Some(converted) = converted {
+            *value = converted;
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn user_schema(coerce: bool) -> CollectionSchema {
+        let schema = json!({
+            "type": "object",
+            "required": ["name", "age"],
+            "properties": {
+                "name": {"type": "string", "minLength": 2},
+                "age": {"type": "integer", "minimum": 0},
+                "email": {"type": "string", "pattern": "^[^@]+@[^@]+$"},
+                "tags": {"type": "array", "items": {"type": "string"}, "uniqueItems": true},
+                "active": {"type": "boolean"},
+                "role": {"enum": ["admin", "user"]}
+            },
+            "additionalProperties": false
+        });
+        CollectionSchema::new(schema, ValidationLevel::Strict, coerce).unwrap()
+    }
+
+    #[test]
+    fn valid_document_passes() {
+        let mut doc = json!({"_id": 1, "_version": 1, "name": "Ann", "age": 30, "email": "ann@example.com", "tags": ["a", "b"]});
+        assert!(user_schema(false).validate(&mut doc).is_empty());
+    }
+
+    #[test]
+    fn reports_each_violation_with_its_path() {
+        let mut doc = json!({"name": "", "email": "nope", "tags": ["a", 2, "a"], "role": "root", "extra": 1});
+        let mut errors = user_schema(false).validate(&mut doc);
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                "document: missing required field 'age'".to_string(),
+                "email: must match pattern '^[^@]+@[^@]+$'".to_string(),
+                "extra: not allowed".to_string(),
+                "name: must be at least 2 characters".to_string(),
+                "role: must be one of [\"admin\",\"user\"]".to_string(),
+                "tags: items must be unique".to_string(),
+                "tags[1]: expected string, found number".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn coercion_converts_values_before_validating() {
+        let mut doc = json!({"name": 42, "age": "31", "active": "true"});
+        assert!(user_schema(true).validate(&mut doc).is_empty());
+        assert_eq!(doc, json!({"name": "42", "age": 31, "active": true}));
+
+        let mut doc = json!({"name": "Ann", "age": "old"});
+        assert_eq!(user_schema(true).validate(&mut doc), vec!["age: expected integer, found string".to_string()]);
+    }
+
+    #[test]
+    fn rejects_malformed_schemas() {
+        let bad = [
+            json!("string"),
+            json!({"type": "text"}),
+            json!({"required": "name"}),
+            json!({"properties": {"age": {"minimum": "0"}}}),
+            json!({"properties": {"code": {"pattern": "("}}}),
+            json!({"oneOf": []}),
+        ];
+        for schema in bad {
+            let err = CollectionSchema::new(schema.clone(), ValidationLevel::Strict, false).unwrap_err();
+            assert_eq!(err.code(), "invalid_schema", "{schema}");
+        }
+        assert!(matches!(ValidationLevel::parse("loose"), Err(Error::InvalidSchema(_))));
+    }
+}
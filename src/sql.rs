@@ -1,12 +1,16 @@
+use std::cell::{Cell, RefCell};
+use std::ops::ControlFlow;
+
 use serde_json::{json, Map, Value};
 use sqlparser::ast::{
     self, AssignmentTarget, BinaryOperator, CreateIndex, Expr, FromTable, FunctionArg,
     FunctionArgExpr, GroupByExpr, JoinConstraint, JoinOperator, LimitClause, ObjectName,
     ObjectType, OrderByExpr, OrderByKind, Query, SelectItem, SetExpr, Statement, TableFactor,
-    TableObject, TableWithJoins,
+    TableObject, TableWithJoins, visit_expressions_mut,
 };
 use sqlparser::dialect::GenericDialect;
 use sqlparser::parser::Parser;
+use sqlparser::tokenizer::Span;
 
 use crate::engine::OxiDb;
 use crate::error::{Error, Result};
@@ -24,9 +28,18 @@ pub enum SqlResult {
 
 /// Parse and execute a SQL statement against the OxiDB engine.
 pub fn execute_sql(db: &OxiDb, sql: &str) -> Result<SqlResult> {
+    execute_sql_with_params(db, sql, &Value::Null)
+}
+
+/// Parse and execute a SQL statement, binding `params` to its placeholders:
+/// an array binds `?` placeholders in order, an object binds `:name`
+/// placeholders by name. Values are bound into the parsed statement, never
+/// spliced into the SQL text, so they can't change its meaning.
+pub fn execute_sql_with_params(db: &OxiDb, sql: &str, params: &Value) -> Result<SqlResult> {
     let dialect = GenericDialect {};
-    let statements = Parser::parse_sql(&dialect, sql)
+    let mut statements = Parser::parse_sql(&dialect, sql)
         .map_err(|e| Error::InvalidQuery(format!("SQL parse error: {e}")))?;
+    bind_params(&mut statements, params)?;
 
     if statements.is_empty() {
         return Err(Error::InvalidQuery("empty SQL statement".into()));
@@ -41,6 +54,118 @@ pub fn execute_sql(db: &OxiDb, sql: &str) -> Result<SqlResult> {
     execute_statement(db, stmt)
 }
 
+// ---------------------------------------------------------------------------
+// Parameter binding
+// ---------------------------------------------------------------------------
+
+/// Replace the placeholders in `statements` with the literal values in
+/// `params` (an array, an object, or null for none).
+fn bind_params(statements: &mut [Statement], params: &Value) -> Result<()> {
+    // Visiting order isn't source order, so number `?` placeholders by position
+    let mut positional = Vec::new();
+    let mut named = Vec::new();
+    for stmt in statements.iter_mut() {
+        let _ = visit_expressions_mut(stmt, |expr| {
+            if let Expr::Value(ast::ValueWithSpan { value: ast::Value::Placeholder(p), span }) = expr {
+                if p == "?" {
+                    positional.push((span.start.line, span.start.column));
+                } else {
+                    named.push(p.clone());
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+    }
+    positional.sort();
+    if !positional.is_empty() && !named.is_empty() {
+        return Err(Error::InvalidQuery(
+            "cannot mix '?' and named parameters in one statement".into(),
+        ));
+    }
+
+    let bound = |p: &str, span: Span| -> Result<ast::Value> {
+        let value = if p == "?" {
+            let start = (span.start.line, span.start.column);
+            let i = positional.iter().position(|pos| *pos == start).unwrap_or_default();
+            params.get(i)
+        } else {
+            let name = p.strip_prefix(':').ok_or_else(|| {
+                Error::InvalidQuery(format!("unsupported placeholder: {p} (use ? or :name)"))
+            })?;
+            params.get(name)
+        };
+        let value = value
+            .ok_or_else(|| Error::InvalidQuery(format!("missing SQL parameter: {p}")))?;
+        json_to_sql_value(value)
+    };
+
+    // Every parameter must be used, and every placeholder bound
+    match params {
+        Value::Null => {}
+        Value::Array(values) => {
+            if !named.is_empty() || values.len() != positional.len() {
+                return Err(Error::InvalidQuery(format!(
+                    "expected {} SQL parameters, got {}",
+                    positional.len() + named.len(),
+                    values.len()
+                )));
+            }
+        }
+        Value::Object(values) => {
+            if !positional.is_empty() {
+                return Err(Error::InvalidQuery(
+                    "'?' placeholders need params as an array".into(),
+                ));
+            }
+            if let Some(unused) = values.keys().find(|k| !named.contains(&format!(":{k}"))) {
+                return Err(Error::InvalidQuery(format!(
+                    "SQL parameter :{unused} doesn't match any placeholder"
+                )));
+            }
+        }
+        _ => {
+            return Err(Error::InvalidQuery(
+                "SQL params must be an array or an object".into(),
+            ))
+        }
+    }
+
+    for stmt in statements.iter_mut() {
+        let flow = visit_expressions_mut(stmt, |expr| {
+            let (p, span) = match expr {
+                Expr::Value(ast::ValueWithSpan { value: ast::Value::Placeholder(p), span }) => {
+                    (p.clone(), *span)
+                }
+                _ => return ControlFlow::Continue(()),
+            };
+            match bound(&p, span) {
+                Ok(value) => {
+                    *expr = Expr::Value(ast::ValueWithSpan { value, span });
+                    ControlFlow::Continue(())
+                }
+                Err(e) => ControlFlow::Break(e),
+            }
+        });
+        if let ControlFlow::Break(e) = flow {
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// A bound parameter as a SQL literal.
+fn json_to_sql_value(value: &Value) -> Result<ast::Value> {
+    match value {
+        Value::Null => Ok(ast::Value::Null),
+        Value::Bool(b) => Ok(ast::Value::Boolean(*b)),
+        Value::Number(n) => Ok(ast::Value::Number(n.to_string(), false)),
+        Value::String(s) => Ok(ast::Value::SingleQuotedString(s.clone())),
+        _ => Err(Error::InvalidQuery(format!(
+            "SQL parameters must be strings, numbers, booleans or null, got: {value}"
+        ))),
+    }
+}
+
 fn execute_statement(db: &OxiDb, stmt: Statement) -> Result<SqlResult> {
     match stmt {
         Statement::Query(query) => execute_query(db, *query),
@@ -193,6 +318,10 @@ fn execute_simple_select(
 // Aggregate SELECT (GROUP BY / aggregate functions)
 // ---------------------------------------------------------------------------
 
+/// Execute a grouped SELECT as `$group` followed by a `$project` that names
+/// each output column after its alias. HAVING and ORDER BY then run against
+/// those columns; aggregates they use that aren't selected are computed as
+/// hidden columns and dropped at the end.
 fn execute_aggregate_select(
     db: &OxiDb,
     select: &ast::Select,
@@ -209,7 +338,6 @@ fn execute_aggregate_select(
         pipeline.push(json!({"$match": where_json}));
     }
 
-    // $group
     let group_by_fields: Vec<String> = match &select.group_by {
         GroupByExpr::Expressions(exprs, _) => exprs
             .iter()
@@ -217,80 +345,64 @@ fn execute_aggregate_select(
             .collect::<Result<Vec<_>>>()?,
         GroupByExpr::All(_) => Vec::new(),
     };
+    let mut output = GroupOutput::new(group_by_fields);
 
-    let group_id = if group_by_fields.is_empty() {
-        Value::Null
-    } else if group_by_fields.len() == 1 {
-        Value::String(format!("${}", group_by_fields[0]))
-    } else {
-        let mut obj = Map::new();
-        for f in &group_by_fields {
-            obj.insert(f.clone(), Value::String(format!("${f}")));
-        }
-        Value::Object(obj)
-    };
-
-    let mut group_obj = Map::new();
-    group_obj.insert("_id".to_string(), group_id);
-
-    // Map aggregate functions from SELECT
+    // Every selected column is a GROUP BY column or an aggregate
     for item in &select.projection {
         let (expr, alias) = match item {
             SelectItem::UnnamedExpr(e) => (e, None),
             SelectItem::ExprWithAlias { expr, alias } => (expr, Some(alias.value.clone())),
-            _ => continue,
+            _ => {
+                return Err(Error::InvalidQuery(
+                    "SELECT * is not supported with GROUP BY or aggregate functions".into(),
+                ))
+            }
         };
-
         match expr {
-            Expr::Function(f) => {
-                let func_name = f.name.to_string().to_uppercase();
-                let output_name = alias.unwrap_or_else(|| func_name.to_lowercase());
-
-                let acc = match func_name.as_str() {
-                    "COUNT" => json!({"$sum": 1}),
-                    "SUM" => {
-                        let field = extract_function_field_arg(f)?;
-                        json!({"$sum": format!("${field}")})
-                    }
-                    "AVG" => {
-                        let field = extract_function_field_arg(f)?;
-                        json!({"$avg": format!("${field}")})
-                    }
-                    "MIN" => {
-                        let field = extract_function_field_arg(f)?;
-                        json!({"$min": format!("${field}")})
-                    }
-                    "MAX" => {
-                        let field = extract_function_field_arg(f)?;
-                        json!({"$max": format!("${field}")})
-                    }
-                    _ => continue,
-                };
-                group_obj.insert(output_name, acc);
+            Expr::Function(f) if is_aggregate_expr(expr) => {
+                output.add_aggregate(f, &expr.to_string(), alias)?;
             }
             Expr::Identifier(_) | Expr::CompoundIdentifier(_) => {
-                // GROUP BY column referenced in SELECT — skip, it's in _id
+                let field = expr_to_field_name(expr)?;
+                if !output.add_group_column(&field, alias) {
+                    return Err(Error::InvalidQuery(format!(
+                        "column '{field}' must appear in GROUP BY or be used in an aggregate function"
+                    )));
+                }
+            }
+            _ => {
+                return Err(Error::InvalidQuery(format!(
+                    "unsupported expression in grouped SELECT: {expr}"
+                )))
             }
-            _ => {}
         }
     }
 
-    pipeline.push(json!({"$group": group_obj}));
+    // HAVING and ORDER BY may name output columns, GROUP BY columns or
+    // aggregates
+    let output = RefCell::new(output);
+    let having = match &select.having {
+        Some(having) => Some(translate_expr_with(having, &|e| output.borrow_mut().column(e))?),
+        None => None,
+    };
+    let mut sort_obj = Map::new();
+    for ob in order_by {
+        let field = output.borrow_mut().column(&ob.expr)?;
+        let dir = if ob.options.asc.unwrap_or(true) { 1 } else { -1 };
+        sort_obj.insert(field, json!(dir));
+    }
+    let output = output.into_inner();
+
+    pipeline.push(json!({"$group": output.group}));
+    pipeline.push(json!({"$project": output.project}));
 
     // HAVING → second $match
-    if let Some(having) = &select.having {
-        let having_json = translate_expr(having)?;
+    if let Some(having_json) = having {
         pipeline.push(json!({"$match": having_json}));
     }
 
     // ORDER BY → $sort
-    if !order_by.is_empty() {
-        let mut sort_obj = Map::new();
-        for ob in order_by {
-            let field = expr_to_field_name(&ob.expr)?;
-            let dir = if ob.options.asc.unwrap_or(true) { 1 } else { -1 };
-            sort_obj.insert(field, json!(dir));
-        }
+    if !sort_obj.is_empty() {
         pipeline.push(json!({"$sort": sort_obj}));
     }
 
@@ -304,47 +416,139 @@ fn execute_aggregate_select(
         pipeline.push(json!({"$limit": expr_to_u64(lim)?}));
     }
 
+    if !output.hidden.is_empty() {
+        let drop: Map<String, Value> = output.hidden.iter().map(|h| (h.clone(), json!(0))).collect();
+        pipeline.push(json!({"$project": drop}));
+    }
+
     let pipeline_json = Value::Array(pipeline);
     let results = db.aggregate(&table, &pipeline_json)?;
+    Ok(SqlResult::Select(results))
+}
 
-    // Flatten _id field back into the result for user-friendly output
-    let flattened: Vec<Value> = results
-        .into_iter()
-        .map(|mut doc| {
-            if let Some(id) = doc.get("_id").cloned()
-                && let Some(obj) = doc.as_object_mut()
-            {
-                obj.remove("_id");
-                match id {
-                    Value::Object(id_map) => {
-                        for (k, v) in id_map {
-                            obj.insert(k, v);
-                        }
-                    }
-                    Value::String(s) => {
-                        if !group_by_fields.is_empty() {
-                            obj.insert(group_by_fields[0].clone(), Value::String(s));
-                        }
-                    }
-                    Value::Null => {}
-                    other => {
-                        if !group_by_fields.is_empty() {
-                            obj.insert(group_by_fields[0].clone(), other);
-                        }
-                    }
-                }
+/// The `$group` and `$project` stages behind a grouped SELECT's output
+/// columns.
+struct GroupOutput {
+    group_by: Vec<String>,
+    /// The `$group` stage: `_id` and one accumulator per aggregate.
+    group: Map<String, Value>,
+    /// The `$project` stage: output column → group key path or accumulator.
+    project: Map<String, Value>,
+    /// Columns only needed by HAVING or ORDER BY.
+    hidden: Vec<String>,
+}
+
+impl GroupOutput {
+    fn new(group_by: Vec<String>) -> Self {
+        // A single key groups by its value; several by an object of them
+        let group_id = match group_by.as_slice() {
+            [] => Value::Null,
+            [field] => Value::String(format!("${field}")),
+            fields => Value::Object(
+                fields
+                    .iter()
+                    .enumerate()
+                    .map(|(i, f)| (format!("k{i}"), Value::String(format!("${f}"))))
+                    .collect(),
+            ),
+        };
+        let mut group = Map::new();
+        group.insert("_id".to_string(), group_id);
+        let mut project = Map::new();
+        project.insert("_id".to_string(), json!(0));
+        Self { group_by, group, project, hidden: Vec::new() }
+    }
+
+    /// Path of a GROUP BY column's value after `$group`.
+    fn group_key_path(&self, field: &str) -> Option<String> {
+        let i = self.group_by.iter().position(|f| f == field)?;
+        Some(if self.group_by.len() == 1 { "$_id".to_string() } else { format!("$_id.k{i}") })
+    }
+
+    /// Output a GROUP BY column, named `alias` or after itself. Returns false
+    /// if `field` isn't a GROUP BY column.
+    fn add_group_column(&mut self, field: &str, alias: Option<String>) -> bool {
+        match self.group_key_path(field) {
+            Some(path) => {
+                self.project.insert(alias.unwrap_or_else(|| field.to_string()), json!(path));
+                true
             }
-            doc
-        })
-        .collect();
+            None => false,
+        }
+    }
 
-    Ok(SqlResult::Select(flattened))
+    /// Output an aggregate, named `alias`, else after the function (`count`,
+    /// `avg`, ...), else by its SQL text if that name is taken. Returns the
+    /// column name.
+    fn add_aggregate(&mut self, f: &ast::Function, text: &str, alias: Option<String>) -> Result<String> {
+        let acc = aggregate_accumulator(f)?;
+        let name = alias.unwrap_or_else(|| {
+            let short = f.name.to_string().to_lowercase();
+            if self.project.contains_key(&short) { text.to_string() } else { short }
+        });
+        self.group.insert(name.clone(), acc);
+        self.project.insert(name.clone(), json!(1));
+        Ok(name)
+    }
+
+    /// Output column a HAVING or ORDER BY expression refers to, adding a
+    /// hidden column for a GROUP BY column or aggregate that isn't selected.
+    fn column(&mut self, expr: &Expr) -> Result<String> {
+        if let Expr::Function(f) = expr {
+            if !is_aggregate_expr(expr) {
+                return Err(Error::InvalidQuery(format!("unsupported function: {expr}")));
+            }
+            let acc = aggregate_accumulator(f)?;
+            if let Some((name, _)) = self.group.iter().find(|(k, v)| *k != "_id" && **v == acc) {
+                return Ok(name.clone());
+            }
+            let name = format!("__agg{}", self.hidden.len());
+            self.group.insert(name.clone(), acc);
+            self.project.insert(name.clone(), json!(1));
+            self.hidden.push(name.clone());
+            return Ok(name);
+        }
+        let field = expr_to_field_name(expr)?;
+        if self.project.contains_key(&field) && field != "_id" {
+            return Ok(field);
+        }
+        // A GROUP BY column selected under an alias, or not selected at all
+        let path = self.group_key_path(&field).ok_or_else(|| {
+            Error::InvalidQuery(format!(
+                "column '{field}' must appear in GROUP BY or be used in an aggregate function"
+            ))
+        })?;
+        if let Some((name, _)) = self.project.iter().find(|(_, v)| v.as_str() == Some(path.as_str())) {
+            return Ok(name.clone());
+        }
+        let name = format!("__key{}", self.hidden.len());
+        self.project.insert(name.clone(), json!(path));
+        self.hidden.push(name.clone());
+        Ok(name)
+    }
+}
+
+/// The `$group` accumulator for an aggregate function call.
+fn aggregate_accumulator(f: &ast::Function) -> Result<Value> {
+    let op = match f.name.to_string().to_uppercase().as_str() {
+        "COUNT" => return Ok(json!({"$sum": 1})),
+        "SUM" => "$sum",
+        "AVG" => "$avg",
+        "MIN" => "$min",
+        "MAX" => "$max",
+        other => return Err(Error::InvalidQuery(format!("unsupported aggregate function: {other}"))),
+    };
+    let field = extract_function_field_arg(f)?;
+    Ok(json!({op: format!("${field}")}))
 }
 
 // ---------------------------------------------------------------------------
 // JOIN SELECT
 // ---------------------------------------------------------------------------
 
+/// Execute a SELECT with INNER/LEFT JOINs as an aggregation pipeline: each
+/// join becomes a `$lookup` on the joined table's ON column (which uses its
+/// field index, if any) followed by an `$unwind`.
 fn execute_join_select(
     db: &OxiDb,
     select: &ast::Select,
@@ -357,26 +561,37 @@ fn execute_join_select(
         .first()
         .ok_or_else(|| Error::InvalidQuery("missing FROM clause".into()))?;
 
-    let left_table = extract_table_factor_name(&from.relation)?;
+    let mut scope = JoinScope::default();
+    let left_table = scope.add_table(&from.relation)?;
+    let mut joins = Vec::with_capacity(from.joins.len());
+    for join in &from.joins {
+        let (constraint, is_left) = extract_join_operator_constraint(&join.join_operator)?;
+        let right_table = scope.add_table(&join.relation)?;
+        joins.push((right_table, constraint, is_left));
+    }
+
     let mut pipeline: Vec<Value> = Vec::new();
 
-    // WHERE → $match (before join for efficiency)
+    // WHERE → $match, before the joins unless it filters on a joined table
+    let mut where_after_joins = None;
     if let Some(expr) = &select.selection {
-        let where_json = translate_expr(expr)?;
-        pipeline.push(json!({"$match": where_json}));
+        let uses_joined = Cell::new(false);
+        let where_json = translate_expr_with(expr, &|e| {
+            let (table, path) = scope.column(e)?;
+            uses_joined.set(uses_joined.get() || table.is_some_and(|t| t > 0));
+            Ok(scope.field_path(table, &path))
+        })?;
+        if uses_joined.get() {
+            where_after_joins = Some(json!({"$match": where_json}));
+        } else {
+            pipeline.push(json!({"$match": where_json}));
+        }
     }
 
-    for join in &from.joins {
-        let right_table = extract_table_factor_name(&join.relation)?;
-        let constraint = extract_join_operator_constraint(&join.join_operator)?;
-        let (local_field, foreign_field) = extract_join_condition(&constraint)?;
-
-        let is_left = matches!(
-            &join.join_operator,
-            JoinOperator::LeftOuter(_) | JoinOperator::LeftSemi(_) | JoinOperator::LeftAnti(_)
-        );
-
-        let as_field = format!("_{right_table}");
+    for (i, (right_table, constraint, is_left)) in joins.iter().enumerate() {
+        let table = i + 1;
+        let (local_field, foreign_field) = extract_join_condition(constraint, &scope, table)?;
+        let as_field = scope.prefixes[table].clone().unwrap_or_default();
 
         pipeline.push(json!({
             "$lookup": {
@@ -394,12 +609,13 @@ fn execute_join_select(
             }
         }));
     }
+    pipeline.extend(where_after_joins);
 
     // ORDER BY → $sort
     if !order_by.is_empty() {
         let mut sort_obj = Map::new();
         for ob in order_by {
-            let field = expr_to_field_name(&ob.expr)?;
+            let field = scope.field(&ob.expr)?;
             let dir = if ob.options.asc.unwrap_or(true) { 1 } else { -1 };
             sort_obj.insert(field, json!(dir));
         }
@@ -427,38 +643,142 @@ fn execute_join_select(
         return Ok(SqlResult::Select(docs));
     }
 
-    let projected = apply_projection(&docs, &select.projection)?;
+    let projected = apply_join_projection(&docs, &select.projection, &scope)?;
     Ok(SqlResult::Select(projected))
 }
 
-fn extract_join_operator_constraint(op: &JoinOperator) -> Result<JoinConstraint> {
-    match op {
-        JoinOperator::Inner(c)
-        | JoinOperator::LeftOuter(c)
-        | JoinOperator::RightOuter(c)
-        | JoinOperator::FullOuter(c, ..)
-        | JoinOperator::LeftSemi(c)
-        | JoinOperator::RightSemi(c)
-        | JoinOperator::LeftAnti(c)
-        | JoinOperator::RightAnti(c) => Ok(c.clone()),
-        _ => Err(Error::InvalidQuery("unsupported JOIN type".into())),
-    }
+/// The tables of a JOIN query and where their columns live in the joined
+/// documents. Table 0 is the FROM table, whose columns stay at the top
+/// level; each joined table's row is nested under `_<alias>` (or
+/// `_<table>` without an alias).
+#[derive(Default)]
+struct JoinScope {
+    /// Table name or alias → table number.
+    qualifiers: Vec<(String, usize)>,
+    /// Field holding each table's row; `None` for the FROM table.
+    prefixes: Vec<Option<String>>,
 }
 
-fn extract_join_condition(constraint: &JoinConstraint) -> Result<(String, String)> {
-    match constraint {
-        JoinConstraint::On(expr) => match expr {
-            Expr::BinaryOp { left, op: BinaryOperator::Eq, right } => {
-                let left_field = expr_to_field_name(left)?;
-                let right_field = expr_to_field_name(right)?;
-                Ok((left_field, right_field))
+impl JoinScope {
+    /// Register the next table, returning its collection name.
+    fn add_table(&mut self, factor: &TableFactor) -> Result<String> {
+        let name = extract_table_factor_name(factor)?;
+        let alias = match factor {
+            TableFactor::Table { alias: Some(alias), .. } => Some(alias.name.value.clone()),
+            _ => None,
+        };
+        let table = self.prefixes.len();
+        let qualifier = alias.clone().unwrap_or_else(|| name.clone());
+        if self.qualifiers.iter().any(|(q, _)| *q == qualifier) {
+            return Err(Error::InvalidQuery(format!(
+                "table '{qualifier}' appears more than once in FROM; give it an alias"
+            )));
+        }
+        self.prefixes.push((table > 0).then(|| format!("_{qualifier}")));
+        self.qualifiers.push((qualifier, table));
+        if alias.is_some() {
+            self.qualifiers.push((name.clone(), table));
+        }
+        Ok(name)
+    }
+
+    /// Table number of a table name or alias.
+    fn table(&self, qualifier: &str) -> Result<usize> {
+        self.qualifiers
+            .iter()
+            .find(|(q, _)| q == qualifier)
+            .map(|(_, t)| *t)
+            .ok_or_else(|| Error::InvalidQuery(format!("unknown table: {qualifier}")))
+    }
+
+    /// Split a column reference into the table it names, if qualified, and
+    /// the field path within that table's rows.
+    fn column(&self, expr: &Expr) -> Result<(Option<usize>, String)> {
+        if let Expr::CompoundIdentifier(parts) = expr {
+            let table = self.table(&parts[0].value).ok();
+            if let Some(table) = table.filter(|_| parts.len() > 1) {
+                let path = parts[1..]
+                    .iter()
+                    .map(|p| p.value.as_str())
+                    .collect::<Vec<_>>()
+                    .join(".");
+                return Ok((Some(table), path));
             }
-            _ => Err(Error::InvalidQuery(
+        }
+        Ok((None, expr_to_field_name(expr)?))
+    }
+
+    /// Path of a table's field in the joined documents. Unqualified columns
+    /// belong to the FROM table.
+    fn field_path(&self, table: Option<usize>, path: &str) -> String {
+        match table.and_then(|t| self.prefixes[t].as_deref()) {
+            Some(prefix) => format!("{prefix}.{path}"),
+            None => path.to_string(),
+        }
+    }
+
+    /// Path of a column reference in the joined documents.
+    fn field(&self, expr: &Expr) -> Result<String> {
+        let (table, path) = self.column(expr)?;
+        Ok(self.field_path(table, &path))
+    }
+}
+
+/// Returns the join constraint and whether the join is a LEFT join.
+fn extract_join_operator_constraint(op: &JoinOperator) -> Result<(&JoinConstraint, bool)> {
+    let kind = match op {
+        JoinOperator::Join(c) | JoinOperator::Inner(c) => return Ok((c, false)),
+        JoinOperator::Left(c) | JoinOperator::LeftOuter(c) => return Ok((c, true)),
+        JoinOperator::Right(_) | JoinOperator::RightOuter(_) => "RIGHT",
+        JoinOperator::FullOuter(..) => "FULL OUTER",
+        JoinOperator::CrossJoin(_) => "CROSS",
+        JoinOperator::Semi(_) | JoinOperator::LeftSemi(_) | JoinOperator::RightSemi(_) => "SEMI",
+        JoinOperator::Anti(_) | JoinOperator::LeftAnti(_) | JoinOperator::RightAnti(_) => "ANTI",
+        _ => "this",
+    };
+    Err(Error::InvalidQuery(format!(
+        "unsupported JOIN type: {kind} JOIN (only INNER and LEFT joins are supported)"
+    )))
+}
+
+/// Resolve `ON a = b` for the join of table number `table` into the
+/// `$lookup` local field (in the documents joined so far) and foreign field
+/// (in the joined table). One side must be a column of the joined table.
+fn extract_join_condition(
+    constraint: &JoinConstraint,
+    scope: &JoinScope,
+    table: usize,
+) -> Result<(String, String)> {
+    let (left, right) = match constraint {
+        JoinConstraint::On(Expr::BinaryOp { left, op: BinaryOperator::Eq, right }) => {
+            (left, right)
+        }
+        JoinConstraint::On(_) => {
+            return Err(Error::InvalidQuery(
                 "JOIN ON must be a simple equality condition".into(),
-            )),
-        },
+            ))
+        }
+        _ => {
+            return Err(Error::InvalidQuery(
+                "only JOIN ... ON is supported".into(),
+            ))
+        }
+    };
+    let (left_table, left_path) = scope.column(left)?;
+    let (right_table, right_path) = scope.column(right)?;
+    let earlier = |t: Option<usize>| t.is_none_or(|t| t < table);
+    match (left_table, right_table) {
+        // Unqualified on both sides: local = foreign
+        (None, None) => Ok((left_path, right_path)),
+        (l, Some(r)) if r == table && earlier(l) => {
+            Ok((scope.field_path(l, &left_path), right_path))
+        }
+        (Some(l), r) if l == table && earlier(r) => {
+            Ok((scope.field_path(r, &right_path), left_path))
+        }
         _ => Err(Error::InvalidQuery(
-            "only JOIN ... ON is supported".into(),
+            "JOIN ON must compare a column of the joined table with a column of an earlier table"
+                .into(),
         )),
     }
 }
@@ -639,22 +959,27 @@ fn execute_show_tables(db: &OxiDb) -> Result<SqlResult> {
 // ---------------------------------------------------------------------------
 
 fn translate_expr(expr: &Expr) -> Result<Value> {
+    translate_expr_with(expr, &expr_to_field_name)
+}
+
+/// Translate a WHERE expression, naming document fields with `field`.
+fn translate_expr_with(expr: &Expr, field: &dyn Fn(&Expr) -> Result<String>) -> Result<Value> {
     match expr {
-        Expr::BinaryOp { left, op, right } => translate_binary_op(left, op, right),
-        Expr::Nested(inner) => translate_expr(inner),
+        Expr::BinaryOp { left, op, right } => translate_binary_op(left, op, right, field),
+        Expr::Nested(inner) => translate_expr_with(inner, field),
         Expr::IsNull(expr) => {
-            let field = expr_to_field_name(expr)?;
+            let field = field(expr)?;
             Ok(json!({field: {"$exists": false}}))
         }
         Expr::IsNotNull(expr) => {
-            let field = expr_to_field_name(expr)?;
+            let field = field(expr)?;
             Ok(json!({field: {"$exists": true}}))
         }
         Expr::InList { expr, list, negated } => {
             if *negated {
                 return Err(Error::InvalidQuery("NOT IN is not supported".into()));
             }
-            let field = expr_to_field_name(expr)?;
+            let field = field(expr)?;
             let values: Vec<Value> = list
                 .iter()
                 .map(translate_expr_to_value)
@@ -665,7 +990,7 @@ fn translate_expr(expr: &Expr) -> Result<Value> {
             if *negated {
                 return Err(Error::InvalidQuery("NOT BETWEEN is not supported".into()));
             }
-            let field = expr_to_field_name(expr)?;
+            let field = field(expr)?;
             let low_val = translate_expr_to_value(low)?;
             let high_val = translate_expr_to_value(high)?;
             Ok(json!({field: {"$gte": low_val, "$lte": high_val}}))
@@ -674,7 +999,7 @@ fn translate_expr(expr: &Expr) -> Result<Value> {
             if *negated {
                 return Err(Error::InvalidQuery("NOT LIKE is not supported".into()));
             }
-            let field = expr_to_field_name(expr)?;
+            let field = field(expr)?;
             let pattern_str = match pattern.as_ref() {
                 Expr::Value(v) => value_with_span_to_string(v)?,
                 _ => {
@@ -692,45 +1017,50 @@ fn translate_expr(expr: &Expr) -> Result<Value> {
     }
 }
 
-fn translate_binary_op(left: &Expr, op: &BinaryOperator, right: &Expr) -> Result<Value> {
+fn translate_binary_op(
+    left: &Expr,
+    op: &BinaryOperator,
+    right: &Expr,
+    field: &dyn Fn(&Expr) -> Result<String>,
+) -> Result<Value> {
     match op {
         BinaryOperator::And => {
-            let lhs = translate_expr(left)?;
-            let rhs = translate_expr(right)?;
+            let lhs = translate_expr_with(left, field)?;
+            let rhs = translate_expr_with(right, field)?;
             Ok(merge_and(lhs, rhs))
         }
         BinaryOperator::Or => {
-            let lhs = translate_expr(left)?;
-            let rhs = translate_expr(right)?;
+            let lhs = translate_expr_with(left, field)?;
+            let rhs = translate_expr_with(right, field)?;
             Ok(json!({"$or": [lhs, rhs]}))
         }
         BinaryOperator::Eq => {
-            let field = expr_to_field_name(left)?;
+            let field = field(left)?;
             let val = translate_expr_to_value(right)?;
             Ok(json!({field: val}))
         }
         BinaryOperator::NotEq => {
-            let field = expr_to_field_name(left)?;
+            let field = field(left)?;
             let val = translate_expr_to_value(right)?;
             Ok(json!({field: {"$ne": val}}))
         }
         BinaryOperator::Gt => {
-            let field = expr_to_field_name(left)?;
+            let field = field(left)?;
             let val = translate_expr_to_value(right)?;
             Ok(json!({field: {"$gt": val}}))
         }
         BinaryOperator::GtEq => {
-            let field = expr_to_field_name(left)?;
+            let field = field(left)?;
             let val = translate_expr_to_value(right)?;
             Ok(json!({field: {"$gte": val}}))
         }
         BinaryOperator::Lt => {
-            let field = expr_to_field_name(left)?;
+            let field = field(left)?;
             let val = translate_expr_to_value(right)?;
             Ok(json!({field: {"$lt": val}}))
         }
         BinaryOperator::LtEq => {
-            let field = expr_to_field_name(left)?;
+            let field = field(left)?;
             let val = translate_expr_to_value(right)?;
             Ok(json!({field: {"$lte": val}}))
         }
@@ -984,6 +1314,54 @@ fn apply_projection(docs: &[Value], items: &[SelectItem]) -> Result<Vec<Value>>
     Ok(result)
 }
 
+/// Project joined documents. Columns missing from a row (such as those of
+/// an unmatched LEFT JOIN table) come back as NULL.
+fn apply_join_projection(
+    docs: &[Value],
+    items: &[SelectItem],
+    scope: &JoinScope,
+) -> Result<Vec<Value>> {
+    let mut result = Vec::with_capacity(docs.len());
+    for doc in docs {
+        let mut out = Map::new();
+        if let Some(id) = doc.get("_id") {
+            out.insert("_id".to_string(), id.clone());
+        }
+        for item in items {
+            match item {
+                SelectItem::UnnamedExpr(expr) => {
+                    let (table, path) = scope.column(expr)?;
+                    let val = crate::pipeline::resolve_field(doc, &scope.field_path(table, &path));
+                    out.insert(path, val);
+                }
+                SelectItem::ExprWithAlias { expr, alias } => {
+                    let val = crate::pipeline::resolve_field(doc, &scope.field(expr)?);
+                    out.insert(alias.value.clone(), val);
+                }
+                SelectItem::QualifiedWildcard(
+                    ast::SelectItemQualifiedWildcardKind::ObjectName(name),
+                    _,
+                ) => {
+                    let table = scope.table(&object_name_to_string(name))?;
+                    let row = match &scope.prefixes[table] {
+                        Some(prefix) => doc.get(prefix).and_then(|v| v.as_object()),
+                        None => doc.as_object(),
+                    };
+                    // Keep earlier columns (and the FROM row's _id) on name clashes
+                    for (k, v) in row.into_iter().flatten() {
+                        if !scope.prefixes.contains(&Some(k.clone())) {
+                            out.entry(k.clone()).or_insert_with(|| v.clone());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        result.push(Value::Object(out));
+    }
+    Ok(result)
+}
+
 // ---------------------------------------------------------------------------
 // Tests
 // ---------------------------------------------------------------------------
@@ -1213,6 +1591,255 @@ mod tests {
         }
     }
 
+    fn select_with(db: &OxiDb, sql: &str, params: Value) -> Vec<Value> {
+        match execute_sql_with_params(db, sql, &params).unwrap() {
+            SqlResult::Select(docs) => docs,
+            _ => panic!("expected Select"),
+        }
+    }
+
+    #[test]
+    fn positional_params_bind_values() {
+        let db = temp_db();
+        seed_users(&db);
+        let docs = select_with(&db, "SELECT name FROM users WHERE city = ? AND age >= ?", json!(["NYC", 31]));
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0]["name"], "Charlie");
+
+        db.insert("flags", json!({"name": "a", "on": true})).unwrap();
+        db.insert("flags", json!({"name": "b", "on": false})).unwrap();
+        let docs = select_with(&db, "SELECT name FROM flags WHERE on = ?", json!([false]));
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0]["name"], "b");
+
+        // Bound in source order, including LIMIT
+        let docs = select_with(
+            &db,
+            "SELECT name FROM users WHERE city = ? ORDER BY name LIMIT ?",
+            json!(["NYC", 1]),
+        );
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0]["name"], "Alice");
+    }
+
+    #[test]
+    fn named_params_bind_values() {
+        let db = temp_db();
+        seed_users(&db);
+        let result = execute_sql_with_params(
+            &db,
+            "UPDATE users SET city = :city WHERE name = :name",
+            &json!({"name": "Bob", "city": "SF"}),
+        )
+        .unwrap();
+        assert!(matches!(result, SqlResult::Update(1)));
+        let docs = select_with(&db, "SELECT name FROM users WHERE city = :c", json!({"c": "SF"}));
+        assert_eq!(docs[0]["name"], "Bob");
+    }
+
+    #[test]
+    fn params_are_not_interpreted_as_sql() {
+        let db = temp_db();
+        seed_users(&db);
+        for hostile in ["x' OR '1'='1", "Alice'; DROP TABLE users; --", "Alice' --"] {
+            let docs = select_with(&db, "SELECT * FROM users WHERE name = ?", json!([hostile]));
+            assert!(docs.is_empty(), "{hostile}");
+        }
+        execute_sql_with_params(
+            &db,
+            "INSERT INTO users (name, city) VALUES (?, ?)",
+            &json!(["O'Brien", "'); DELETE FROM users; --"]),
+        )
+        .unwrap();
+        let docs = select_with(&db, "SELECT city FROM users WHERE name = ?", json!(["O'Brien"]));
+        assert_eq!(docs[0]["city"], "'); DELETE FROM users; --");
+        assert_eq!(db.find("users", &json!({})).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn param_count_mismatch_returns_error() {
+        let db = temp_db();
+        seed_users(&db);
+        let sql = "SELECT * FROM users WHERE city = ? AND name = ?";
+        for params in [json!(["NYC"]), json!(["NYC", "Alice", "extra"])] {
+            let err = execute_sql_with_params(&db, sql, &params).unwrap_err().to_string();
+            assert!(err.contains("expected 2 SQL parameters"), "{err}");
+        }
+        assert!(execute_sql(&db, sql).is_err());
+        let err = execute_sql_with_params(&db, "SELECT * FROM users WHERE city = :c", &json!({}))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("missing SQL parameter: :c"), "{err}");
+        let err = execute_sql_with_params(&db, "SELECT * FROM users", &json!({"c": 1}))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(":c doesn't match"), "{err}");
+    }
+
+    fn seed_staff(db: &OxiDb) {
+        for (name, dept, age) in [
+            ("Ann", "eng", 30),
+            ("Ben", "eng", 40),
+            ("Cat", "eng", 50),
+            ("Dan", "ops", 20),
+            ("Eve", "ops", 30),
+            ("Fay", "hr", 45),
+        ] {
+            db.insert("staff", json!({"name": name, "dept": dept, "age": age})).unwrap();
+        }
+    }
+
+    #[test]
+    fn group_by_with_multiple_accumulators() {
+        let db = temp_db();
+        seed_staff(&db);
+        let docs = select_rows(
+            &db,
+            "SELECT dept, COUNT(*), AVG(age), MIN(age) AS youngest, MAX(age) AS oldest, \
+             SUM(age) AS total FROM staff GROUP BY dept ORDER BY dept",
+        );
+        assert_eq!(docs.len(), 3);
+        assert_eq!(
+            docs[0],
+            json!({"dept": "eng", "count": 3, "avg": 40, "youngest": 30, "oldest": 50, "total": 120})
+        );
+        assert_eq!(docs[1]["dept"], "hr");
+        assert_eq!(docs[2]["count"], 2);
+        assert_eq!(docs[2]["avg"], 25);
+
+        // Group columns are keyed by their alias
+        let docs = select_rows(
+            &db,
+            "SELECT dept AS department, COUNT(*) AS n FROM staff GROUP BY dept ORDER BY n DESC",
+        );
+        assert_eq!(docs[0], json!({"department": "eng", "n": 3}));
+    }
+
+    #[test]
+    fn having_filters_groups() {
+        let db = temp_db();
+        seed_staff(&db);
+        let docs = select_rows(
+            &db,
+            "SELECT dept, COUNT(*) AS n FROM staff GROUP BY dept HAVING COUNT(*) > 1 ORDER BY dept",
+        );
+        let depts: Vec<&str> = docs.iter().map(|d| d["dept"].as_str().unwrap()).collect();
+        assert_eq!(depts, vec!["eng", "ops"]);
+
+        // By alias, and by an aggregate that isn't selected
+        let docs = select_rows(
+            &db,
+            "SELECT dept FROM staff GROUP BY dept HAVING MAX(age) >= 45 ORDER BY dept",
+        );
+        assert_eq!(docs, vec![json!({"dept": "eng"}), json!({"dept": "hr"})]);
+        let docs = select_rows(&db, "SELECT dept, AVG(age) AS a FROM staff GROUP BY dept HAVING a < 30");
+        assert_eq!(docs, vec![json!({"dept": "ops", "a": 25})]);
+    }
+
+    #[test]
+    fn ungrouped_column_returns_error() {
+        let db = temp_db();
+        seed_staff(&db);
+        let err = execute_sql(&db, "SELECT dept, name, COUNT(*) FROM staff GROUP BY dept")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("'name' must appear in GROUP BY"), "{err}");
+        assert!(execute_sql(&db, "SELECT name, COUNT(*) FROM staff").is_err());
+    }
+
+    fn seed_orders(db: &OxiDb) {
+        let alice = db.insert("users", json!({"name": "Alice"})).unwrap();
+        let bob = db.insert("users", json!({"name": "Bob"})).unwrap();
+        db.insert("orders", json!({"id": "o1", "userId": alice})).unwrap();
+        db.insert("orders", json!({"id": "o2", "userId": bob})).unwrap();
+        db.insert("orders", json!({"id": "o3", "userId": alice})).unwrap();
+        // No matching user
+        db.insert("orders", json!({"id": "o4", "userId": 999})).unwrap();
+    }
+
+    fn select_rows(db: &OxiDb, sql: &str) -> Vec<Value> {
+        match execute_sql(db, sql).unwrap() {
+            SqlResult::Select(docs) => docs,
+            _ => panic!("expected Select"),
+        }
+    }
+
+    #[test]
+    fn inner_join_returns_matched_pairs() {
+        let db = temp_db();
+        seed_orders(&db);
+        let docs = select_rows(
+            &db,
+            "SELECT o.id, u.name FROM orders o JOIN users u ON o.userId = u._id ORDER BY o.id",
+        );
+        let pairs: Vec<(&str, &str)> = docs
+            .iter()
+            .map(|d| (d["id"].as_str().unwrap(), d["name"].as_str().unwrap()))
+            .collect();
+        assert_eq!(pairs, vec![("o1", "Alice"), ("o2", "Bob"), ("o3", "Alice")]);
+
+        // ON sides in either order, INNER keyword, alias on a joined column
+        let docs = select_rows(
+            &db,
+            "SELECT o.id, u.name AS customer FROM orders AS o INNER JOIN users AS u \
+             ON u._id = o.userId WHERE u.name = 'Bob'",
+        );
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0]["id"], "o2");
+        assert_eq!(docs[0]["customer"], "Bob");
+
+        // Qualified wildcard: every column of one table
+        let docs = select_rows(
+            &db,
+            "SELECT o.*, u.name AS customer FROM orders o JOIN users u ON o.userId = u._id \
+             WHERE o.id = 'o1'",
+        );
+        assert_eq!(docs[0]["id"], "o1");
+        assert_eq!(docs[0]["customer"], "Alice");
+        assert!(docs[0].get("_u").is_none());
+    }
+
+    #[test]
+    fn left_join_keeps_unmatched_rows_with_nulls() {
+        let db = temp_db();
+        seed_orders(&db);
+        let docs = select_rows(
+            &db,
+            "SELECT o.id, u.name FROM orders o LEFT JOIN users u ON o.userId = u._id ORDER BY o.id",
+        );
+        assert_eq!(docs.len(), 4);
+        assert_eq!(docs[3]["id"], "o4");
+        assert!(docs[3]["name"].is_null());
+        assert_eq!(docs[0]["name"], "Alice");
+
+        // Filtering on the left table happens before the join
+        let docs = select_rows(
+            &db,
+            "SELECT o.id, u.name FROM orders o LEFT OUTER JOIN users u ON o.userId = u._id \
+             WHERE o.id = 'o4'",
+        );
+        assert_eq!(docs.len(), 1);
+        assert!(docs[0]["name"].is_null());
+    }
+
+    #[test]
+    fn unsupported_join_type_returns_error() {
+        let db = temp_db();
+        seed_orders(&db);
+        for sql in [
+            "SELECT * FROM orders o RIGHT JOIN users u ON o.userId = u._id",
+            "SELECT * FROM orders o FULL OUTER JOIN users u ON o.userId = u._id",
+        ] {
+            let err = execute_sql(&db, sql).unwrap_err().to_string();
+            assert!(err.contains("unsupported JOIN type"), "{err}");
+        }
+        // ON must reference the joined table
+        let err = execute_sql(&db, "SELECT * FROM orders o JOIN users u ON o.userId = o.id")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("joined table"), "{err}");
+    }
+
     #[test]
     fn insert_single_row() {
         let db = temp_db();
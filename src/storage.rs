@@ -4,16 +4,130 @@ use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 use crate::crypto::EncryptionKey;
-use crate::error::Result;
+use crate::error::{Error, Result};
 
 const RECORD_ACTIVE: u8 = 0;
 const RECORD_DELETED: u8 = 1;
+/// Status bit of records that carry a CRC32 after their length. Records
+/// written before checksums were added don't have it and are read unchecked.
+const RECORD_CHECKSUMMED: u8 = 2;
+
+/// When writes are fsynced, set for the whole engine (`OxiDb::set_sync_mode`,
+/// `OXIDB_SYNC`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyncMode {
+    /// Every write fsyncs on its own before returning.
+    Full,
+    /// Like `Full`, but concurrent single-document inserts into a collection
+    /// share fsyncs (see `wal::GroupCommit`). Same guarantee, higher
+    /// throughput under concurrency.
+    #[default]
+    Group,
+    /// Never fsync collection data or WAL files; the OS writes them back in
+    /// its own time. A crash of the process loses nothing, but a power loss
+    /// or kernel crash may lose recent writes. `flush` still fsyncs, and so
+    /// do the transaction commit log and commit history (see `tx_log`).
+    None,
+}
+
+impl SyncMode {
+    /// Parse `full`, `group` or `none`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "full" => Some(Self::Full),
+            "group" => Some(Self::Group),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+}
 
 /// Location of a document in the data file.
 #[derive(Debug, Clone, Copy)]
 pub struct DocLocation {
     pub offset: u64,
     pub length: u32,
+    /// Whether the record has a CRC32 (see `RECORD_CHECKSUMMED`).
+    pub checksummed: bool,
+}
+
+impl DocLocation {
+    /// Bytes the record takes in the data file, header included.
+    pub fn record_len(&self) -> u64 {
+        self.header_len() + self.length as u64
+    }
+
+    fn header_len(&self) -> u64 {
+        if self.checksummed {
+            9
+        } else {
+            5
+        }
+    }
+}
+
+/// A record's header as read from the data file.
+struct RecordHeader {
+    status: u8,
+    length: u32,
+    crc: Option<u32>,
+}
+
+impl RecordHeader {
+    fn read_from(reader: &mut impl Read) -> Result<Self> {
+        let mut header = [0u8; 5];
+        reader.read_exact(&mut header)?;
+        let status = header[0];
+        let length = u32::from_le_bytes([header[1], header[2], header[3], header[4]]);
+        let crc = if status & RECORD_CHECKSUMMED != 0 {
+            let mut crc = [0u8; 4];
+            reader.read_exact(&mut crc)?;
+            Some(u32::from_le_bytes(crc))
+        } else {
+            None
+        };
+        Ok(Self { status, length, crc })
+    }
+
+    fn is_active(&self) -> bool {
+        self.status & RECORD_DELETED == 0
+    }
+
+    fn location(&self, offset: u64) -> DocLocation {
+        DocLocation { offset, length: self.length, checksummed: self.crc.is_some() }
+    }
+}
+
+/// CRC32 of a record's length and payload. The status byte is left out
+/// because deletes flip it in place.
+fn record_crc(length: u32, payload: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&length.to_le_bytes());
+    hasher.update(payload);
+    hasher.finalize()
+}
+
+/// Write an active, checksummed record.
+fn write_record(file: &mut File, payload: &[u8]) -> Result<u32> {
+    let length = payload.len() as u32;
+    let mut header = [0u8; 9];
+    header[0] = RECORD_ACTIVE | RECORD_CHECKSUMMED;
+    header[1..5].copy_from_slice(&length.to_le_bytes());
+    header[5..].copy_from_slice(&record_crc(length, payload).to_le_bytes());
+    file.write_all(&header)?;
+    file.write_all(payload)?;
+    Ok(length)
+}
+
+/// A record found by `Storage::salvage`.
+#[derive(Debug)]
+pub enum Salvaged {
+    /// An active record whose checksum (if it has one) matches, with its
+    /// payload as stored (still encrypted if the collection is).
+    Intact { offset: u64, payload: Vec<u8> },
+    /// Bytes that can't be trusted: a record failing its checksum, or
+    /// everything from a truncated or unparseable record to the end of file.
+    Damaged { offset: u64, bytes: Vec<u8> },
 }
 
 struct StorageInner {
@@ -23,14 +137,22 @@ struct StorageInner {
 
 /// Append-only file storage for documents.
 ///
-/// Record format: [status: u8][length: u32 LE][payload]
-/// - status 0 = active, 1 = deleted (soft delete)
-/// - payload is either raw json_bytes or encrypted bytes
+/// Record format: [status: u8][length: u32 LE][crc: u32 LE][payload]
+/// - status bit 0: 0 = active, 1 = deleted (soft delete)
+/// - status bit 1: set when the record has `crc`, the CRC32 of the length
+///   and payload; records written by older versions have neither
+/// - payload is the encoded document (see `codec`: JSONB, legacy JSON text,
+///   or deflate-compressed JSONB), encrypted if encryption is enabled
+///
+/// A checksum mismatch is reported as `Error::Corruption`.
 ///
 /// Thread-safe: all file operations are serialized via an internal Mutex.
 pub struct Storage {
     _path: PathBuf,
     inner: Mutex<StorageInner>,
+    /// Second handle on the data file, for fsyncs that don't hold `inner`.
+    sync_file: Arc<File>,
+    sync_mode: SyncMode,
     encryption: Option<Arc<EncryptionKey>>,
 }
 
@@ -52,6 +174,7 @@ impl Storage {
             .open(path)?;
 
         let current_offset = file.metadata()?.len();
+        let sync_file = Arc::new(file.try_clone()?);
 
         Ok(Self {
             _path: path.to_path_buf(),
@@ -59,6 +182,8 @@ impl Storage {
                 file,
                 current_offset,
             }),
+            sync_file,
+            sync_mode: SyncMode::default(),
             encryption,
         })
     }
@@ -79,41 +204,44 @@ impl Storage {
         }
     }
 
+    /// Compare a record's payload with its stored CRC, if it has one.
+    fn verify(&self, loc: DocLocation, crc: Option<u32>, payload: &[u8]) -> Result<()> {
+        match crc {
+            Some(crc) if crc != record_crc(loc.length, payload) => Err(Error::Corruption {
+                location: format!("{} offset {}", self._path.display(), loc.offset),
+            }),
+            _ => Ok(()),
+        }
+    }
+
     /// Append a document to the data file, returns its location.
     pub fn append(&self, doc_bytes: &[u8]) -> Result<DocLocation> {
-        let payload = self.maybe_encrypt(doc_bytes)?;
-        let mut inner = self.inner.lock().unwrap();
-        let offset = inner.current_offset;
-        let length = payload.len() as u32;
-
-        inner.file.seek(SeekFrom::End(0))?;
-        inner.file.write_all(&[RECORD_ACTIVE])?;
-        inner.file.write_all(&length.to_le_bytes())?;
-        inner.file.write_all(&payload)?;
-        inner.file.sync_data()?;
-
-        inner.current_offset += 1 + 4 + length as u64;
-
-        Ok(DocLocation { offset, length })
+        let loc = self.append_no_sync(doc_bytes)?;
+        self.sync()?;
+        Ok(loc)
     }
 
     /// Read a document's bytes from the data file.
     pub fn read(&self, loc: DocLocation) -> Result<Vec<u8>> {
         let mut inner = self.inner.lock().unwrap();
         inner.file.seek(SeekFrom::Start(loc.offset + 5))?;
-        let mut buf = vec![0u8; loc.length as usize];
+        let mut buf = vec![0u8; (loc.record_len() - 5) as usize];
         inner.file.read_exact(&mut buf)?;
         drop(inner);
-        self.maybe_decrypt(&buf)
+        let (crc, payload) = if loc.checksummed {
+            let (crc, payload) = buf.split_at(4);
+            (Some(u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]])), payload)
+        } else {
+            (None, &buf[..])
+        };
+        self.verify(loc, crc, payload)?;
+        self.maybe_decrypt(payload)
     }
 
     /// Soft-delete a record by flipping its status byte.
     pub fn mark_deleted(&self, loc: DocLocation) -> Result<()> {
-        let mut inner = self.inner.lock().unwrap();
-        inner.file.seek(SeekFrom::Start(loc.offset))?;
-        inner.file.write_all(&[RECORD_DELETED])?;
-        inner.file.sync_data()?;
-        Ok(())
+        self.mark_deleted_no_sync(loc)?;
+        self.sync()
     }
 
     /// Append a document without fsync (caller must call `sync()` after batch).
@@ -121,16 +249,13 @@ impl Storage {
         let payload = self.maybe_encrypt(doc_bytes)?;
         let mut inner = self.inner.lock().unwrap();
         let offset = inner.current_offset;
-        let length = payload.len() as u32;
 
         inner.file.seek(SeekFrom::End(0))?;
-        inner.file.write_all(&[RECORD_ACTIVE])?;
-        inner.file.write_all(&length.to_le_bytes())?;
-        inner.file.write_all(&payload)?;
-
-        inner.current_offset += 1 + 4 + length as u64;
+        let length = write_record(&mut inner.file, &payload)?;
 
-        Ok(DocLocation { offset, length })
+        let loc = DocLocation { offset, length, checksummed: true };
+        inner.current_offset += loc.record_len();
+        Ok(loc)
     }
 
     /// Append multiple documents without fsync, acquiring the mutex only once.
@@ -148,14 +273,11 @@ impl Storage {
         let mut locations = Vec::with_capacity(payloads.len());
         for payload in &payloads {
             let offset = inner.current_offset;
-            let length = payload.len() as u32;
+            let length = write_record(&mut inner.file, payload)?;
 
-            inner.file.write_all(&[RECORD_ACTIVE])?;
-            inner.file.write_all(&length.to_le_bytes())?;
-            inner.file.write_all(payload)?;
-
-            inner.current_offset += 1 + 4 + length as u64;
-            locations.push(DocLocation { offset, length });
+            let loc = DocLocation { offset, length, checksummed: true };
+            inner.current_offset += loc.record_len();
+            locations.push(loc);
         }
 
         Ok(locations)
@@ -163,19 +285,45 @@ impl Storage {
 
     /// Soft-delete without fsync (caller must call `sync()` after batch).
     pub fn mark_deleted_no_sync(&self, loc: DocLocation) -> Result<()> {
+        let status = match loc.checksummed {
+            true => RECORD_DELETED | RECORD_CHECKSUMMED,
+            false => RECORD_DELETED,
+        };
         let mut inner = self.inner.lock().unwrap();
         inner.file.seek(SeekFrom::Start(loc.offset))?;
-        inner.file.write_all(&[RECORD_DELETED])?;
+        inner.file.write_all(&[status])?;
         Ok(())
     }
 
-    /// Flush and fsync the data file.
+    /// Flush and fsync the data file, unless the sync mode is `None`.
     pub fn sync(&self) -> Result<()> {
+        match self.sync_mode {
+            SyncMode::None => Ok(()),
+            _ => self.force_sync(),
+        }
+    }
+
+    /// fsync the data file whatever the sync mode.
+    pub fn force_sync(&self) -> Result<()> {
         let inner = self.inner.lock().unwrap();
         inner.file.sync_data()?;
         Ok(())
     }
 
+    pub fn sync_mode(&self) -> SyncMode {
+        self.sync_mode
+    }
+
+    pub fn set_sync_mode(&mut self, mode: SyncMode) {
+        self.sync_mode = mode;
+    }
+
+    /// Handle for fsyncing the data file from outside, e.g. by a
+    /// `GroupCommit` after the writer released the collection.
+    pub fn sync_handle(&self) -> &Arc<File> {
+        &self.sync_file
+    }
+
     /// Returns the total file size in bytes.
     pub fn file_size(&self) -> u64 {
         let inner = self.inner.lock().unwrap();
@@ -191,77 +339,134 @@ impl Storage {
     /// The DocLocation contains the correct on-disk payload length (which may
     /// differ from plaintext length when encryption is enabled).
     pub fn iter_active(&self) -> Result<Vec<(DocLocation, Vec<u8>)>> {
+        let mut results = Vec::new();
+        self.for_each_active(|loc, bytes| {
+            results.push((loc, bytes));
+            Ok(())
+        })?;
+        Ok(results)
+    }
+
+    /// Stream active records one at a time via callback, avoiding the large
+    /// Vec allocation of `iter_active`. Stops at the first record that fails
+    /// its checksum.
+    pub fn for_each_active<F>(&self, f: F) -> Result<()>
+    where
+        F: FnMut(DocLocation, Vec<u8>) -> Result<()>,
+    {
+        self.scan_active(f, None)
+    }
+
+    /// Like `for_each_active`, but a record that fails its checksum is
+    /// handed to `on_corrupt` and skipped. A corrupted length can't be
+    /// skipped reliably, so the records after one may fail too.
+    pub fn for_each_active_skipping_corrupt<F, C>(&self, f: F, mut on_corrupt: C) -> Result<()>
+    where
+        F: FnMut(DocLocation, Vec<u8>) -> Result<()>,
+        C: FnMut(DocLocation, Error),
+    {
+        self.scan_active(f, Some(&mut on_corrupt))
+    }
+
+    fn scan_active<F>(&self, mut f: F, mut on_corrupt: Option<&mut dyn FnMut(DocLocation, Error)>) -> Result<()>
+    where
+        F: FnMut(DocLocation, Vec<u8>) -> Result<()>,
+    {
         let mut inner = self.inner.lock().unwrap();
         inner.file.seek(SeekFrom::Start(0))?;
         let file_len = inner.file.metadata()?.len();
-        let mut results = Vec::new();
         let mut pos = 0u64;
 
         while pos < file_len {
-            let mut header = [0u8; 5];
-            inner.file.read_exact(&mut header)?;
-
-            let status = header[0];
-            let length = u32::from_le_bytes([header[1], header[2], header[3], header[4]]);
+            let header = RecordHeader::read_from(&mut inner.file)?;
+            let loc = header.location(pos);
 
-            if status == RECORD_ACTIVE {
-                let mut data = vec![0u8; length as usize];
+            if header.is_active() {
+                let mut data = vec![0u8; header.length as usize];
                 inner.file.read_exact(&mut data)?;
-                let plaintext = match &self.encryption {
-                    Some(key) => key.decrypt(&data)?,
-                    None => data,
-                };
-                results.push((DocLocation { offset: pos, length }, plaintext));
+                match (self.verify(loc, header.crc, &data), on_corrupt.as_mut()) {
+                    (Ok(()), _) => {
+                        let plaintext = match &self.encryption {
+                            Some(key) => key.decrypt(&data)?,
+                            None => data,
+                        };
+                        // Drop inner lock before callback (callback may need to read storage)
+                        drop(inner);
+                        f(loc, plaintext)?;
+                        inner = self.inner.lock().unwrap();
+                        // Re-seek to continue after this record
+                        inner.file.seek(SeekFrom::Start(pos + loc.record_len()))?;
+                    }
+                    (Err(e), Some(on_corrupt)) => on_corrupt(loc, e),
+                    (Err(e), None) => return Err(e),
+                }
             } else {
-                inner.file.seek(SeekFrom::Current(length as i64))?;
+                inner.file.seek(SeekFrom::Current(header.length as i64))?;
             }
 
-            pos += 5 + length as u64;
+            pos += loc.record_len();
         }
 
-        Ok(results)
+        Ok(())
     }
 
-    /// Stream active records one at a time via callback, avoiding the large
-    /// Vec allocation of `iter_active`.
-    pub fn for_each_active<F>(&self, mut f: F) -> Result<()>
+    /// Walk a possibly damaged data file without stopping at bad records,
+    /// for repair. Deleted records are skipped. After a record failing its
+    /// checksum the scan continues at the length it claims, so a damaged
+    /// length makes what follows come out damaged too.
+    pub fn salvage<F>(path: &Path, mut f: F) -> Result<()>
     where
-        F: FnMut(DocLocation, Vec<u8>) -> Result<()>,
+        F: FnMut(Salvaged) -> Result<()>,
     {
-        let mut inner = self.inner.lock().unwrap();
-        inner.file.seek(SeekFrom::Start(0))?;
-        let file_len = inner.file.metadata()?.len();
+        use std::io::BufReader;
+
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::with_capacity(256 * 1024, file);
         let mut pos = 0u64;
 
         while pos < file_len {
             let mut header = [0u8; 5];
-            inner.file.read_exact(&mut header)?;
-
+            let complete = file_len - pos >= 5 && reader.read_exact(&mut header).is_ok();
             let status = header[0];
-            let length = u32::from_le_bytes([header[1], header[2], header[3], header[4]]);
-
-            if status == RECORD_ACTIVE {
-                let mut data = vec![0u8; length as usize];
-                inner.file.read_exact(&mut data)?;
-                let plaintext = match &self.encryption {
-                    Some(key) => key.decrypt(&data)?,
-                    None => data,
-                };
-                // Drop inner lock before callback (callback may need to read storage)
-                drop(inner);
-                f(DocLocation { offset: pos, length }, plaintext)?;
-                inner = self.inner.lock().unwrap();
-                // Re-seek to continue after this record
-                inner.file.seek(SeekFrom::Start(pos + 5 + length as u64))?;
-            } else {
-                inner.file.seek(SeekFrom::Current(length as i64))?;
+            let loc = DocLocation {
+                offset: pos,
+                length: u32::from_le_bytes([header[1], header[2], header[3], header[4]]),
+                checksummed: status & RECORD_CHECKSUMMED != 0,
+            };
+            let known_status = status & !(RECORD_DELETED | RECORD_CHECKSUMMED) == 0;
+            if !complete || !known_status || pos + loc.record_len() > file_len {
+                // Nothing after this point can be framed reliably
+                reader.seek(SeekFrom::Start(pos))?;
+                let mut bytes = Vec::new();
+                reader.read_to_end(&mut bytes)?;
+                return f(Salvaged::Damaged { offset: pos, bytes });
             }
 
-            pos += 5 + length as u64;
+            let mut rest = vec![0u8; (loc.record_len() - 5) as usize];
+            reader.read_exact(&mut rest)?;
+            pos += loc.record_len();
+            if status & RECORD_DELETED != 0 {
+                continue;
+            }
+            let (crc, payload) = match loc.checksummed {
+                true => (Some(u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]])), &rest[4..]),
+                false => (None, &rest[..]),
+            };
+            let record = match crc {
+                Some(crc) if crc != record_crc(loc.length, payload) => {
+                    let mut bytes = header.to_vec();
+                    bytes.extend_from_slice(&rest);
+                    Salvaged::Damaged { offset: loc.offset, bytes }
+                }
+                _ => Salvaged::Intact { offset: loc.offset, payload: payload.to_vec() },
+            };
+            f(record)?;
         }
 
         Ok(())
     }
+
     /// Sequential scan using a separate read-only file handle.
     /// Does NOT hold the main mutex — other reads/writes can proceed concurrently.
     /// Uses BufReader for efficient sequential I/O (OS read-ahead).
@@ -281,15 +486,13 @@ impl Storage {
         let mut decrypt_buf: Vec<u8>;
 
         while pos < file_len {
-            let mut header = [0u8; 5];
-            reader.read_exact(&mut header)?;
-            let status = header[0];
-            let length =
-                u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+            let header = RecordHeader::read_from(&mut reader)?;
+            let loc = header.location(pos);
 
-            if status == RECORD_ACTIVE {
-                buf.resize(length, 0);
+            if header.is_active() {
+                buf.resize(header.length as usize, 0);
                 reader.read_exact(&mut buf)?;
+                self.verify(loc, header.crc, &buf)?;
                 let bytes: &[u8] = match &self.encryption {
                     Some(key) => {
                         decrypt_buf = key.decrypt(&buf)?;
@@ -301,10 +504,49 @@ impl Storage {
                     break;
                 }
             } else {
-                reader.seek(SeekFrom::Current(length as i64))?;
+                reader.seek(SeekFrom::Current(header.length as i64))?;
             }
 
-            pos += 5 + length as u64;
+            pos += loc.record_len();
+        }
+
+        Ok(())
+    }
+
+    /// Read the given records through a separate read-only file handle, in
+    /// slice order. Like `scan_readonly_while` this doesn't hold the main
+    /// mutex, so several threads can scan disjoint parts of the file at once.
+    /// Locations sorted by offset give mostly sequential I/O.
+    pub fn scan_locations_readonly<F>(&self, locations: &[DocLocation], mut f: F) -> Result<()>
+    where
+        F: FnMut(DocLocation, &[u8]) -> Result<bool>,
+    {
+        use std::io::BufReader;
+
+        let file = File::open(&self._path)?;
+        let mut reader = BufReader::with_capacity(256 * 1024, file);
+        let mut pos = 0u64;
+        let mut buf = Vec::with_capacity(4096);
+        let mut decrypt_buf: Vec<u8>;
+
+        for &loc in locations {
+            // seek_relative keeps the buffer when the next record is close by
+            reader.seek_relative(loc.offset as i64 - pos as i64)?;
+            let header = RecordHeader::read_from(&mut reader)?;
+            buf.resize(loc.length as usize, 0);
+            reader.read_exact(&mut buf)?;
+            pos = loc.offset + loc.record_len();
+            self.verify(loc, header.crc, &buf)?;
+            let bytes: &[u8] = match &self.encryption {
+                Some(key) => {
+                    decrypt_buf = key.decrypt(&buf)?;
+                    &decrypt_buf
+                }
+                None => &buf,
+            };
+            if !f(loc, bytes)? {
+                break;
+            }
         }
 
         Ok(())
@@ -377,8 +619,8 @@ mod tests {
 
         let data = b"test";
         storage.append(data).unwrap();
-        // header (1 status + 4 length) + payload
-        assert_eq!(storage.file_size(), 5 + data.len() as u64);
+        // header (1 status + 4 length + 4 crc) + payload
+        assert_eq!(storage.file_size(), 9 + data.len() as u64);
     }
 
     #[test]
@@ -504,4 +746,124 @@ mod tests {
         let loc = storage.append(&data).unwrap();
         assert_eq!(storage.read(loc).unwrap(), data);
     }
+
+    #[test]
+    fn scan_locations_reads_selected_records() {
+        let dir = TempDir::new().unwrap();
+        let storage = test_storage(&dir);
+        let locs: Vec<DocLocation> = (0..10)
+            .map(|i| storage.append(format!("rec{i}").as_bytes()).unwrap())
+            .collect();
+
+        let picked = [locs[1], locs[4], locs[9]];
+        let mut seen = Vec::new();
+        storage
+            .scan_locations_readonly(&picked, |loc, bytes| {
+                seen.push((loc, bytes.to_vec()));
+                Ok(true)
+            })
+            .unwrap();
+        assert_eq!(seen.len(), 3);
+        assert_eq!(seen[0].1, b"rec1");
+        assert_eq!(seen[1].1, b"rec4");
+        assert_eq!(seen[2].0.offset, locs[9].offset);
+        assert_eq!(seen[2].1, b"rec9");
+
+        // Stops early when the callback returns false
+        let mut n = 0;
+        storage
+            .scan_locations_readonly(&locs, |_, _| {
+                n += 1;
+                Ok(n < 2)
+            })
+            .unwrap();
+        assert_eq!(n, 2);
+    }
+
+    /// Flip one payload byte of the record at `loc` on disk.
+    fn corrupt_payload(path: &Path, loc: DocLocation) {
+        let mut raw = std::fs::read(path).unwrap();
+        raw[(loc.offset + loc.record_len() - 1) as usize] ^= 0x01;
+        std::fs::write(path, raw).unwrap();
+    }
+
+    #[test]
+    fn flipped_byte_is_reported_as_corruption() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("test.dat");
+        let storage = Storage::open(&path).unwrap();
+        storage.append(b"first").unwrap();
+        let loc = storage.append(b"second").unwrap();
+        storage.append(b"third").unwrap();
+        corrupt_payload(&path, loc);
+
+        let err = storage.read(loc).unwrap_err();
+        assert!(matches!(&err, Error::Corruption { location } if location.ends_with(&format!("offset {}", loc.offset))));
+        assert!(matches!(storage.iter_active(), Err(Error::Corruption { .. })));
+        assert!(matches!(storage.for_each_active(|_, _| Ok(())), Err(Error::Corruption { .. })));
+        assert!(matches!(storage.scan_readonly_while(|_| Ok(true)), Err(Error::Corruption { .. })));
+        assert!(matches!(
+            storage.scan_locations_readonly(&[loc], |_, _| Ok(true)),
+            Err(Error::Corruption { .. })
+        ));
+
+        // Skipping reports the bad record and carries on
+        let mut seen = Vec::new();
+        let mut skipped = Vec::new();
+        storage
+            .for_each_active_skipping_corrupt(
+                |_, bytes| {
+                    seen.push(bytes);
+                    Ok(())
+                },
+                |loc, _| skipped.push(loc.offset),
+            )
+            .unwrap();
+        assert_eq!(seen, vec![b"first".to_vec(), b"third".to_vec()]);
+        assert_eq!(skipped, vec![loc.offset]);
+    }
+
+    #[test]
+    fn corrupt_encrypted_record_fails_checksum_first() {
+        let dir = TempDir::new().unwrap();
+        let key_path = dir.path().join("test.key");
+        std::fs::write(&key_path, [0x42u8; 32]).unwrap();
+        let enc_key = EncryptionKey::load_from_file(&key_path).unwrap();
+        let path = dir.path().join("encrypted.dat");
+        let storage = Storage::open_with_encryption(&path, Some(enc_key)).unwrap();
+
+        let loc = storage.append(b"secret").unwrap();
+        corrupt_payload(&path, loc);
+        assert!(matches!(storage.read(loc), Err(Error::Corruption { .. })));
+    }
+
+    #[test]
+    fn legacy_records_without_checksum_still_read() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("legacy.dat");
+        // Two records in the original format: [status][length][payload]
+        let mut raw = Vec::new();
+        for (status, payload) in [(RECORD_ACTIVE, &b"old"[..]), (RECORD_DELETED, &b"gone"[..])] {
+            raw.push(status);
+            raw.extend((payload.len() as u32).to_le_bytes());
+            raw.extend(payload);
+        }
+        std::fs::write(&path, raw).unwrap();
+
+        let storage = Storage::open(&path).unwrap();
+        let new_loc = storage.append(b"new").unwrap();
+        let active = storage.iter_active().unwrap();
+        assert_eq!(active.len(), 2);
+        let (old_loc, old) = &active[0];
+        assert_eq!((old.as_slice(), old_loc.checksummed, old_loc.record_len()), (&b"old"[..], false, 8));
+        assert_eq!(storage.read(*old_loc).unwrap(), b"old");
+        assert_eq!(active[1].1, b"new");
+        assert!(active[1].0.checksummed);
+
+        // Deleting keeps each record's format
+        storage.mark_deleted(*old_loc).unwrap();
+        storage.mark_deleted(new_loc).unwrap();
+        assert!(storage.iter_active().unwrap().is_empty());
+        assert_eq!(storage.read(new_loc).unwrap(), b"new");
+    }
 }
@@ -1,4 +1,5 @@
 use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
 
 use serde_json::Value;
 
@@ -19,6 +20,15 @@ pub enum WriteOp {
     Delete { collection: String, query: Value },
 }
 
+/// A named point inside a transaction that buffered work can be rolled back to.
+/// Writes are buffered until commit, so a savepoint only needs the buffer lengths.
+pub struct Savepoint {
+    pub name: String,
+    pub read_set_len: usize,
+    pub write_ops_len: usize,
+    pub collections_involved: BTreeSet<String>,
+}
+
 /// An active transaction holding its read set, write set, and involved collections.
 pub struct Transaction {
     pub id: TransactionId,
@@ -26,6 +36,14 @@ pub struct Transaction {
     pub write_ops: Vec<WriteOp>,
     /// BTreeSet for sorted lock acquisition (deadlock-free ordering).
     pub collections_involved: BTreeSet<String>,
+    /// Savepoints in creation order.
+    pub savepoints: Vec<Savepoint>,
+    /// Read-only transactions reject buffered writes and commit without
+    /// locking, validating, or touching the WAL.
+    pub read_only: bool,
+    pub started_at: Instant,
+    /// Maximum lifetime; operations after this fail with `TransactionTimedOut`.
+    pub timeout: Option<Duration>,
 }
 
 impl Transaction {
@@ -35,6 +53,86 @@ impl Transaction {
             read_set: Vec::new(),
             write_ops: Vec::new(),
             collections_involved: BTreeSet::new(),
+            savepoints: Vec::new(),
+            read_only: false,
+            started_at: Instant::now(),
+            timeout: None,
+        }
+    }
+
+    pub fn new_read_only(id: TransactionId) -> Self {
+        Self {
+            read_only: true,
+            ..Self::new(id)
         }
     }
+
+    /// True once the transaction has outlived its timeout.
+    pub fn is_expired(&self) -> bool {
+        self.timeout
+            .is_some_and(|t| self.started_at.elapsed() > t)
+    }
+
+    /// Record a savepoint at the current end of the read and write buffers.
+    /// Reusing a name creates a new savepoint; rollbacks target the most recent one.
+    pub fn savepoint(&mut self, name: &str) {
+        self.savepoints.push(Savepoint {
+            name: name.to_string(),
+            read_set_len: self.read_set.len(),
+            write_ops_len: self.write_ops.len(),
+            collections_involved: self.collections_involved.clone(),
+        });
+    }
+
+    /// Discard everything buffered after the named savepoint. The savepoint
+    /// itself is kept so it can be rolled back to again; later savepoints are
+    /// dropped. Returns false if no savepoint has that name.
+    pub fn rollback_to(&mut self, name: &str) -> bool {
+        let Some(pos) = self.savepoints.iter().rposition(|sp| sp.name == name) else {
+            return false;
+        };
+        self.savepoints.truncate(pos + 1);
+        let sp = &self.savepoints[pos];
+        self.read_set.truncate(sp.read_set_len);
+        self.write_ops.truncate(sp.write_ops_len);
+        self.collections_involved = sp.collections_involved.clone();
+        true
+    }
+}
+
+/// Retry settings for `OxiDb::run_transaction`.
+///
+/// The delay before retry `n` (1-based) is `initial_backoff * 2^(n-1)`,
+/// capped at `max_backoff`, with random jitter down to half that value so
+/// that competing writers spread out.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one. Zero is treated as one.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Backoff to wait after the given failed attempt (1-based).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+        let delay = self
+            .initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff);
+        let half = delay / 2;
+        let jitter_nanos = rand::random_range(0..=half.as_nanos() as u64);
+        half + Duration::from_nanos(jitter_nanos)
+    }
 }
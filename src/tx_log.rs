@@ -12,6 +12,9 @@ pub type TransactionId = u64;
 ///
 /// Format: sequence of `[tx_id: u64 LE]` entries, append-only.
 /// A tx_id present in this log means the transaction is committed.
+///
+/// Every change is fsynced whatever the engine's `SyncMode`: the log decides
+/// which transactions recovery keeps, so it must not lag behind them.
 pub struct TxCommitLog {
     inner: Mutex<File>,
     #[allow(dead_code)]
@@ -105,6 +108,72 @@ impl TxCommitLog {
     }
 }
 
+/// Commit history kept while WAL retention is enabled, for point-in-time
+/// recovery. Unlike `TxCommitLog` it is never cleared.
+///
+/// Format: sequence of `[tx_id: u64 LE][committed_at_ms: i64 LE]` entries,
+/// append-only, in commit order.
+pub struct TxHistory {
+    inner: Mutex<File>,
+}
+
+impl TxHistory {
+    pub const FILE_NAME: &'static str = "_tx_history";
+
+    /// Open or create the history file at `<data_dir>/_tx_history`.
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(data_dir.join(Self::FILE_NAME))?;
+        Ok(Self { inner: Mutex::new(file) })
+    }
+
+    /// Whether `data_dir` has a commit history (i.e. retention is enabled).
+    pub fn exists(data_dir: &Path) -> bool {
+        data_dir.join(Self::FILE_NAME).exists()
+    }
+
+    /// Record that `tx_id` committed at `committed_at_ms`, and fsync.
+    pub fn record(&self, tx_id: TransactionId, committed_at_ms: i64) -> Result<()> {
+        let mut entry = [0u8; 16];
+        entry[..8].copy_from_slice(&tx_id.to_le_bytes());
+        entry[8..].copy_from_slice(&committed_at_ms.to_le_bytes());
+        let mut file = self.inner.lock().unwrap();
+        file.write_all(&entry)?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Read the `(tx_id, committed_at_ms)` history of `data_dir`, in
+    /// commit order. Empty if there is none.
+    pub fn read(data_dir: &Path) -> Result<Vec<(TransactionId, i64)>> {
+        let path = data_dir.join(Self::FILE_NAME);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let bytes = fs::read(path)?;
+        Ok(bytes
+            .chunks_exact(16)
+            .map(|entry| {
+                (
+                    u64::from_le_bytes(entry[..8].try_into().unwrap()),
+                    i64::from_le_bytes(entry[8..].try_into().unwrap()),
+                )
+            })
+            .collect())
+    }
+
+    /// Delete the history file of `data_dir`, if any.
+    pub fn remove(data_dir: &Path) -> Result<()> {
+        let path = data_dir.join(Self::FILE_NAME);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -206,4 +275,23 @@ mod tests {
         let committed = log.read_committed().unwrap();
         assert!(committed.contains(&5));
     }
+
+    #[test]
+    fn tx_history_keeps_commit_order() {
+        let dir = TempDir::new().unwrap();
+        assert!(!TxHistory::exists(dir.path()));
+        {
+            let history = TxHistory::open(dir.path()).unwrap();
+            history.record(3, 100).unwrap();
+            history.record(1, 200).unwrap();
+        }
+        assert!(TxHistory::exists(dir.path()));
+
+        let history = TxHistory::open(dir.path()).unwrap();
+        history.record(2, 300).unwrap();
+        assert_eq!(TxHistory::read(dir.path()).unwrap(), vec![(3, 100), (1, 200), (2, 300)]);
+
+        TxHistory::remove(dir.path()).unwrap();
+        assert!(TxHistory::read(dir.path()).unwrap().is_empty());
+    }
 }
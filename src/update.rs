@@ -2,6 +2,7 @@ use serde_json::{Map, Value};
 
 use crate::error::{Error, Result};
 use crate::pipeline::{resolve_field, set_field};
+use crate::query::{self, Query};
 use crate::value::IndexValue;
 
 /// Apply all update operators in `update` to `doc`.
@@ -9,36 +10,217 @@ use crate::value::IndexValue;
 /// `update` must be an object whose keys are operator names (`$set`, `$inc`, etc.)
 /// and whose values are objects mapping field paths to operand values.
 /// Multiple operators in a single update are applied sequentially.
+///
+/// A path segment `$[]` applies the operator to every element of the array
+/// at that point, and `$[<id>]` to the elements matching the filter for
+/// `<id>` in the update's `arrayFilters` list, e.g.
+/// `{"$set": {"items.$[i].done": true}, "arrayFilters": [{"i.id": 42}]}`.
 pub fn apply_update(doc: &mut Value, update: &Value) -> Result<()> {
     let obj = update
         .as_object()
         .ok_or_else(|| Error::InvalidQuery("update must be an object".into()))?;
+    let mut filters = ArrayFilters::parse(obj.get("arrayFilters"))?;
 
     for (op, fields) in obj {
+        if op == "arrayFilters" {
+            continue;
+        }
         let fields = fields.as_object().ok_or_else(|| {
             Error::InvalidQuery(format!("{op} value must be an object"))
         })?;
-        match op.as_str() {
-            "$set" => apply_set(doc, fields)?,
-            "$unset" => apply_unset(doc, fields)?,
-            "$inc" => apply_inc(doc, fields)?,
-            "$mul" => apply_mul(doc, fields)?,
-            "$min" => apply_min(doc, fields)?,
-            "$max" => apply_max(doc, fields)?,
-            "$rename" => apply_rename(doc, fields)?,
-            "$currentDate" => apply_current_date(doc, fields)?,
-            "$push" => apply_push(doc, fields)?,
-            "$pull" => apply_pull(doc, fields)?,
-            "$addToSet" => apply_add_to_set(doc, fields)?,
-            "$pop" => apply_pop(doc, fields)?,
-            _ => {
+        if !fields.keys().any(|path| is_positional(path)) {
+            apply_operator(doc, op, fields)?;
+            continue;
+        }
+        let (positional, plain): (Vec<_>, Vec<_>) =
+            fields.iter().partition(|(path, _)| is_positional(path));
+        let plain: Map<String, Value> = plain.into_iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        apply_operator(doc, op, &plain)?;
+        for (path, value) in positional {
+            apply_positional(doc, op, path, value, &mut filters)?;
+        }
+    }
+    filters.check_all_used()
+}
+
+fn apply_operator(doc: &mut Value, op: &str, fields: &Map<String, Value>) -> Result<()> {
+    match op {
+        "$set" => apply_set(doc, fields),
+        "$unset" => apply_unset(doc, fields),
+        "$inc" => apply_inc(doc, fields),
+        "$mul" => apply_mul(doc, fields),
+        "$min" => apply_min(doc, fields),
+        "$max" => apply_max(doc, fields),
+        "$rename" => apply_rename(doc, fields),
+        "$currentDate" => apply_current_date(doc, fields),
+        "$push" => apply_push(doc, fields),
+        "$pull" => apply_pull(doc, fields),
+        "$addToSet" => apply_add_to_set(doc, fields),
+        "$pop" => apply_pop(doc, fields),
+        _ => Err(Error::InvalidQuery(format!("unknown update operator: {op}"))),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Array element paths ($[] and $[<id>])
+// ---------------------------------------------------------------------------
+
+/// Key the current array element is stored under while an operator is
+/// applied to it as a one-field document.
+const ELEMENT_KEY: &str = "$";
+
+/// The identifier of a `$[...]` path segment (empty for `$[]`), or `None`
+/// for an ordinary segment.
+fn positional_selector(segment: &str) -> Option<&str> {
+    segment.strip_prefix("$[")?.strip_suffix(']')
+}
+
+fn is_positional(path: &str) -> bool {
+    path.split('.').any(|segment| positional_selector(segment).is_some())
+}
+
+/// Apply `op` to the elements selected by the first `$[...]` segment of
+/// `path`. Each element is wrapped as `{"$": element}` and the rest of the
+/// path applied to it, so every operator works on elements unchanged, and
+/// further `$[...]` segments recurse.
+fn apply_positional(
+    doc: &mut Value,
+    op: &str,
+    path: &str,
+    value: &Value,
+    filters: &mut ArrayFilters,
+) -> Result<()> {
+    if op == "$rename" {
+        return Err(Error::InvalidQuery(format!(
+            "$rename does not support array element paths ('{path}')"
+        )));
+    }
+    let segments: Vec<&str> = path.split('.').collect();
+    let pos = segments
+        .iter()
+        .position(|s| positional_selector(s).is_some())
+        .expect("caller checked the path is positional");
+    let selector = positional_selector(segments[pos]).unwrap();
+    let filter = if selector.is_empty() {
+        None
+    } else {
+        Some(filters.index_of(selector)?)
+    };
+    let array_path = segments[..pos].join(".");
+    let rest = &segments[pos + 1..];
+    let element_path = std::iter::once(ELEMENT_KEY)
+        .chain(rest.iter().copied())
+        .collect::<Vec<_>>()
+        .join(".");
+
+    let mut target = Some(&mut *doc);
+    for part in &segments[..pos] {
+        target = target.and_then(|v| v.as_object_mut()).and_then(|m| m.get_mut(*part));
+    }
+    let Some(Value::Array(elements)) = target else {
+        return Err(Error::InvalidQuery(format!(
+            "{op} path '{path}' requires '{array_path}' to be an array"
+        )));
+    };
+
+    for element in elements.iter_mut() {
+        if filter.is_some_and(|i| !filters.matches(i, element)) {
+            continue;
+        }
+        let mut wrapper = Value::Object(Map::from_iter([(ELEMENT_KEY.to_string(), std::mem::take(element))]));
+        let result = if is_positional(&element_path) {
+            apply_positional(&mut wrapper, op, &element_path, value, filters)
+        } else {
+            apply_operator(&mut wrapper, op, &Map::from_iter([(element_path.clone(), value.clone())]))
+        };
+        *element = wrapper
+            .as_object_mut()
+            .and_then(|m| m.remove(ELEMENT_KEY))
+            .unwrap_or(Value::Null);
+        result?;
+    }
+    Ok(())
+}
+
+/// The parsed `arrayFilters` of an update. Each filter is a query whose
+/// keys all start with the same identifier (`{"i.qty": {"$gt": 5}}`, or
+/// `{"i": 3}` for an array of scalars); an element matches when the filter
+/// matches `{<identifier>: element}`.
+struct ArrayFilters {
+    filters: Vec<ArrayFilter>,
+}
+
+struct ArrayFilter {
+    identifier: String,
+    query: Query,
+    used: bool,
+}
+
+impl ArrayFilters {
+    fn parse(value: Option<&Value>) -> Result<Self> {
+        let Some(value) = value else {
+            return Ok(Self { filters: Vec::new() });
+        };
+        let list = value
+            .as_array()
+            .ok_or_else(|| Error::InvalidQuery("arrayFilters must be an array".into()))?;
+        let mut filters: Vec<ArrayFilter> = Vec::with_capacity(list.len());
+        for filter in list {
+            let keys = filter.as_object().map(|m| m.keys()).into_iter().flatten();
+            let mut identifiers = keys.map(|k| k.split('.').next().unwrap_or_default());
+            let identifier = identifiers.next().unwrap_or_default();
+            if !is_identifier(identifier) || identifiers.any(|i| i != identifier) {
                 return Err(Error::InvalidQuery(format!(
-                    "unknown update operator: {op}"
-                )))
+                    "each array filter must be an object whose keys start with one identifier: {filter}"
+                )));
+            }
+            if filters.iter().any(|f| f.identifier == identifier) {
+                return Err(Error::InvalidQuery(format!(
+                    "duplicate array filter for identifier '{identifier}'"
+                )));
             }
+            filters.push(ArrayFilter {
+                identifier: identifier.to_string(),
+                query: query::parse_query(filter)?,
+                used: false,
+            });
+        }
+        Ok(Self { filters })
+    }
+
+    /// Find the filter for `identifier` and mark it used.
+    fn index_of(&mut self, identifier: &str) -> Result<usize> {
+        let i = self
+            .filters
+            .iter()
+            .position(|f| f.identifier == identifier)
+            .ok_or_else(|| {
+                Error::InvalidQuery(format!("no array filter found for identifier '{identifier}'"))
+            })?;
+        self.filters[i].used = true;
+        Ok(i)
+    }
+
+    fn matches(&self, index: usize, element: &Value) -> bool {
+        let filter = &self.filters[index];
+        let doc = Value::Object(Map::from_iter([(filter.identifier.clone(), element.clone())]));
+        query::matches_value(&filter.query, &doc)
+    }
+
+    fn check_all_used(&self) -> Result<()> {
+        match self.filters.iter().find(|f| !f.used) {
+            Some(f) => Err(Error::InvalidQuery(format!(
+                "array filter for identifier '{}' is not used in the update",
+                f.identifier
+            ))),
+            None => Ok(()),
         }
     }
-    Ok(())
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
 
 // ---------------------------------------------------------------------------
@@ -60,47 +242,77 @@ fn apply_unset(doc: &mut Value, fields: &Map<String, Value>) -> Result<()> {
 }
 
 fn apply_inc(doc: &mut Value, fields: &Map<String, Value>) -> Result<()> {
-    for (path, inc_val) in fields {
-        let inc = inc_val.as_f64().ok_or_else(|| {
-            Error::InvalidQuery(format!("$inc value for '{path}' must be numeric"))
-        })?;
-        let current = resolve_field(doc, path);
-        let new_val = match &current {
-            Value::Null => inc,
-            v => {
-                let cur = v.as_f64().ok_or_else(|| {
-                    Error::InvalidQuery(format!(
-                        "$inc cannot be applied to non-numeric field '{path}'"
-                    ))
-                })?;
-                cur + inc
-            }
+    apply_arithmetic(doc, fields, "$inc")
+}
+
+fn apply_mul(doc: &mut Value, fields: &Map<String, Value>) -> Result<()> {
+    apply_arithmetic(doc, fields, "$mul")
+}
+
+/// Shared body of `$inc` and `$mul`. A missing field, and any missing
+/// parents on its dotted path, are created: `$inc` starts from zero and
+/// `$mul` yields zero of the operand's type. An existing value that isn't a
+/// number, or a parent that isn't an object, is an error rather than being
+/// overwritten. Two integers give an integer (overflow is an error);
+/// anything involving a float gives a float.
+fn apply_arithmetic(doc: &mut Value, fields: &Map<String, Value>, op: &str) -> Result<()> {
+    for (path, operand) in fields {
+        let Value::Number(operand) = operand else {
+            return Err(Error::InvalidQuery(format!("{op} value for '{path}' must be numeric")));
+        };
+        let new_val = match numeric_field(doc, path, op)? {
+            Some(current) => combine_numbers(current, operand, op, path)?,
+            None if op == "$inc" => Value::Number(operand.clone()),
+            None if operand.is_f64() => Value::from(0.0),
+            None => Value::from(0),
         };
-        set_field(doc, path, number_to_value(new_val));
+        set_field(doc, path, new_val);
     }
     Ok(())
 }
 
-fn apply_mul(doc: &mut Value, fields: &Map<String, Value>) -> Result<()> {
-    for (path, mul_val) in fields {
-        let mul = mul_val.as_f64().ok_or_else(|| {
-            Error::InvalidQuery(format!("$mul value for '{path}' must be numeric"))
-        })?;
-        let current = resolve_field(doc, path);
-        let new_val = match &current {
-            Value::Null => 0.0,
-            v => {
-                let cur = v.as_f64().ok_or_else(|| {
-                    Error::InvalidQuery(format!(
-                        "$mul cannot be applied to non-numeric field '{path}'"
-                    ))
-                })?;
-                cur * mul
-            }
+/// The number at `path`, or `None` if the field (or one of its parents) is missing.
+fn numeric_field<'a>(doc: &'a Value, path: &str, op: &str) -> Result<Option<&'a serde_json::Number>> {
+    let mut current = doc;
+    let mut walked = 0usize;
+    for part in path.split('.') {
+        let Value::Object(map) = current else {
+            return Err(Error::InvalidQuery(format!(
+                "{op} cannot create field '{path}': '{}' is not an object",
+                &path[..walked.saturating_sub(1)]
+            )));
         };
-        set_field(doc, path, number_to_value(new_val));
+        match map.get(part) {
+            Some(v) => current = v,
+            None => return Ok(None),
+        }
+        walked += part.len() + 1;
     }
-    Ok(())
+    match current {
+        Value::Number(n) => Ok(Some(n)),
+        _ => Err(Error::InvalidQuery(format!(
+            "{op} cannot be applied to non-numeric field '{path}'"
+        ))),
+    }
+}
+
+fn combine_numbers(
+    current: &serde_json::Number,
+    operand: &serde_json::Number,
+    op: &str,
+    path: &str,
+) -> Result<Value> {
+    if let (Some(a), Some(b)) = (current.as_i64(), operand.as_i64()) {
+        let result = if op == "$inc" { a.checked_add(b) } else { a.checked_mul(b) };
+        return result.map(Value::from).ok_or_else(|| {
+            Error::InvalidQuery(format!("{op} on field '{path}' overflows a 64-bit integer"))
+        });
+    }
+    let (a, b) = (current.as_f64().unwrap_or(0.0), operand.as_f64().unwrap_or(0.0));
+    let result = if op == "$inc" { a + b } else { a * b };
+    serde_json::Number::from_f64(result).map(Value::Number).ok_or_else(|| {
+        Error::InvalidQuery(format!("{op} on field '{path}' is not a finite number"))
+    })
 }
 
 fn apply_min(doc: &mut Value, fields: &Map<String, Value>) -> Result<()> {
@@ -151,10 +363,27 @@ fn apply_rename(doc: &mut Value, fields: &Map<String, Value>) -> Result<()> {
     Ok(())
 }
 
+/// `$currentDate`: `true` or `{"$type": "date"}` stores an RFC 3339 string,
+/// `{"$type": "timestamp"}` stores epoch milliseconds. Every field in one
+/// update gets the same instant.
 fn apply_current_date(doc: &mut Value, fields: &Map<String, Value>) -> Result<()> {
-    let now = chrono::Utc::now().to_rfc3339();
-    for (path, _) in fields {
-        set_field(doc, path, Value::String(now.clone()));
+    let now = chrono::Utc::now();
+    for (path, spec) in fields {
+        let type_name = match spec {
+            Value::Bool(true) => "date",
+            Value::Object(obj) => obj.get("$type").and_then(|t| t.as_str()).unwrap_or_default(),
+            _ => "",
+        };
+        let value = match type_name {
+            "date" => Value::String(now.to_rfc3339()),
+            "timestamp" => Value::from(now.timestamp_millis()),
+            _ => {
+                return Err(Error::InvalidQuery(format!(
+                    "$currentDate value for '{path}' must be true or {{\"$type\": \"date\" | \"timestamp\"}}"
+                )))
+            }
+        };
+        set_field(doc, path, value);
     }
     Ok(())
 }
@@ -297,16 +526,6 @@ fn remove_field(doc: &mut Value, path: &str) {
     }
 }
 
-fn number_to_value(n: f64) -> Value {
-    if n.fract() == 0.0 && n >= i64::MIN as f64 && n <= i64::MAX as f64 {
-        Value::Number((n as i64).into())
-    } else {
-        serde_json::Number::from_f64(n)
-            .map(Value::Number)
-            .unwrap_or(Value::Null)
-    }
-}
-
 // ===========================================================================
 // Tests
 // ===========================================================================
@@ -383,7 +602,40 @@ mod tests {
     fn inc_float() {
         let mut doc = json!({"val": 1.5});
         apply_update(&mut doc, &json!({"$inc": {"val": 0.5}})).unwrap();
-        assert_eq!(doc["val"], 2);
+        assert_eq!(doc["val"], 2.0);
+        assert!(doc["val"].is_f64());
+    }
+
+    #[test]
+    fn inc_preserves_integer_and_float_types() {
+        let mut doc = json!({"a": 5, "b": 5, "c": 2.5});
+        apply_update(&mut doc, &json!({"$inc": {"a": 2, "b": 0.5, "c": 0.5}})).unwrap();
+        assert!(doc["a"].is_i64());
+        assert_eq!(doc["a"], 7);
+        assert_eq!(doc["b"], 5.5);
+        assert!(doc["c"].is_f64());
+        assert_eq!(doc["c"], 3.0);
+
+        let mut doc = json!({"n": i64::MAX});
+        assert!(apply_update(&mut doc, &json!({"$inc": {"n": 1}})).is_err());
+        assert_eq!(doc["n"], i64::MAX);
+    }
+
+    #[test]
+    fn inc_nested_creates_intermediate_objects() {
+        let mut doc = json!({"name": "Alice"});
+        apply_update(&mut doc, &json!({"$inc": {"stats.views": 1}})).unwrap();
+        assert_eq!(doc["stats"], json!({"views": 1}));
+        apply_update(&mut doc, &json!({"$inc": {"stats.views": 2, "stats.daily.mon": 1}})).unwrap();
+        assert_eq!(doc["stats"], json!({"views": 3, "daily": {"mon": 1}}));
+    }
+
+    #[test]
+    fn inc_missing_field_keeps_operand_type() {
+        let mut doc = json!({});
+        apply_update(&mut doc, &json!({"$inc": {"i": 3, "f": 1.5}})).unwrap();
+        assert!(doc["i"].is_i64());
+        assert_eq!(doc["f"], 1.5);
     }
 
     #[test]
@@ -398,6 +650,26 @@ mod tests {
         let mut doc = json!({"name": "Alice"});
         let result = apply_update(&mut doc, &json!({"$inc": {"name": 1}}));
         assert!(result.is_err());
+        assert_eq!(doc["name"], "Alice");
+
+        // An explicit null is not a number either
+        let mut doc = json!({"n": null});
+        assert!(apply_update(&mut doc, &json!({"$inc": {"n": 1}})).is_err());
+        assert!(doc["n"].is_null());
+    }
+
+    #[test]
+    fn inc_error_on_non_object_parent() {
+        let mut doc = json!({"stats": 5});
+        let err = apply_update(&mut doc, &json!({"$inc": {"stats.views": 1}})).unwrap_err();
+        assert!(err.to_string().contains("'stats' is not an object"), "{err}");
+        assert_eq!(doc["stats"], 5);
+    }
+
+    #[test]
+    fn inc_error_on_non_numeric_operand() {
+        let mut doc = json!({"n": 1});
+        assert!(apply_update(&mut doc, &json!({"$inc": {"n": "1"}})).is_err());
     }
 
     // -----------------------------------------------------------------------
@@ -416,6 +688,23 @@ mod tests {
         let mut doc = json!({"name": "Alice"});
         apply_update(&mut doc, &json!({"$mul": {"count": 5}})).unwrap();
         assert_eq!(doc["count"], 0);
+        assert!(doc["count"].is_i64());
+        apply_update(&mut doc, &json!({"$mul": {"ratio": 0.5}})).unwrap();
+        assert!(doc["ratio"].is_f64());
+    }
+
+    #[test]
+    fn mul_nested_and_type_preservation() {
+        let mut doc = json!({"item": {"price": 10, "weight": 1.5}});
+        apply_update(&mut doc, &json!({"$mul": {"item.price": 3, "item.weight": 2, "item.tax.rate": 2}})).unwrap();
+        assert!(doc["item"]["price"].is_i64());
+        assert_eq!(doc["item"]["price"], 30);
+        assert!(doc["item"]["weight"].is_f64());
+        assert_eq!(doc["item"]["weight"], 3.0);
+        assert_eq!(doc["item"]["tax"], json!({"rate": 0}));
+
+        let mut doc = json!({"n": i64::MAX});
+        assert!(apply_update(&mut doc, &json!({"$mul": {"n": 2}})).is_err());
     }
 
     #[test]
@@ -423,6 +712,10 @@ mod tests {
         let mut doc = json!({"name": "Alice"});
         let result = apply_update(&mut doc, &json!({"$mul": {"name": 2}}));
         assert!(result.is_err());
+        assert_eq!(doc["name"], "Alice");
+
+        let mut doc = json!({"item": [1, 2]});
+        assert!(apply_update(&mut doc, &json!({"$mul": {"item.price": 2}})).is_err());
     }
 
     // -----------------------------------------------------------------------
@@ -450,6 +743,14 @@ mod tests {
         assert_eq!(doc["score"], 50);
     }
 
+    #[test]
+    fn min_compares_mixed_numbers_and_dates() {
+        let mut doc = json!({"qty": 7, "seen": "2024-05-01T00:00:00Z"});
+        apply_update(&mut doc, &json!({"$min": {"qty": 6.5, "seen": "2024-06-01T00:00:00Z"}})).unwrap();
+        assert_eq!(doc["qty"], 6.5);
+        assert_eq!(doc["seen"], "2024-05-01T00:00:00Z");
+    }
+
     // -----------------------------------------------------------------------
     // $max
     // -----------------------------------------------------------------------
@@ -468,6 +769,13 @@ mod tests {
         assert_eq!(doc["score"], 100);
     }
 
+    #[test]
+    fn max_sets_missing_nested_field() {
+        let mut doc = json!({"name": "Alice"});
+        apply_update(&mut doc, &json!({"$max": {"stats.best": 12}})).unwrap();
+        assert_eq!(doc["stats"], json!({"best": 12}));
+    }
+
     // -----------------------------------------------------------------------
     // $rename
     // -----------------------------------------------------------------------
@@ -488,6 +796,106 @@ mod tests {
         assert_eq!(doc["user"]["name"], "Alice");
     }
 
+    // -----------------------------------------------------------------------
+    // Array element paths
+    // -----------------------------------------------------------------------
+
+    fn order() -> Value {
+        json!({"items": [
+            {"id": 41, "qty": 1, "done": false},
+            {"id": 42, "qty": 5, "done": false},
+            {"id": 43, "qty": 9, "done": false},
+        ]})
+    }
+
+    #[test]
+    fn array_filter_updates_matching_element() {
+        let mut doc = order();
+        apply_update(
+            &mut doc,
+            &json!({"$set": {"items.$[i].done": true}, "arrayFilters": [{"i.id": 42}]}),
+        )
+        .unwrap();
+        let done: Vec<bool> = doc["items"].as_array().unwrap().iter().map(|i| i["done"].as_bool().unwrap()).collect();
+        assert_eq!(done, [false, true, false]);
+        assert_eq!(doc["items"][1]["qty"], 5);
+    }
+
+    #[test]
+    fn array_filter_with_operators_and_scalars() {
+        let mut doc = order();
+        apply_update(
+            &mut doc,
+            &json!({"$inc": {"items.$[big].qty": 10}, "arrayFilters": [{"big.qty": {"$gte": 5}}]}),
+        )
+        .unwrap();
+        assert_eq!(doc["items"][0]["qty"], 1);
+        assert_eq!(doc["items"][1]["qty"], 15);
+        assert_eq!(doc["items"][2]["qty"], 19);
+
+        let mut doc = json!({"scores": [40, 90, 55]});
+        apply_update(&mut doc, &json!({"$set": {"scores.$[s]": 60}, "arrayFilters": [{"s": {"$lt": 60}}]})).unwrap();
+        assert_eq!(doc["scores"], json!([60, 90, 60]));
+    }
+
+    #[test]
+    fn all_positional_updates_every_element() {
+        let mut doc = order();
+        apply_update(&mut doc, &json!({"$set": {"items.$[].done": true}, "$unset": {"items.$[].qty": ""}})).unwrap();
+        for item in doc["items"].as_array().unwrap() {
+            assert_eq!(item["done"], true);
+            assert!(item.get("qty").is_none());
+        }
+    }
+
+    #[test]
+    fn nested_positional_paths() {
+        let mut doc = json!({"orders": [
+            {"lines": [{"sku": "a", "n": 1}, {"sku": "b", "n": 1}]},
+            {"lines": [{"sku": "b", "n": 2}]},
+        ]});
+        apply_update(
+            &mut doc,
+            &json!({"$inc": {"orders.$[].lines.$[l].n": 1}, "arrayFilters": [{"l.sku": "b"}]}),
+        )
+        .unwrap();
+        assert_eq!(doc["orders"][0]["lines"][0]["n"], 1);
+        assert_eq!(doc["orders"][0]["lines"][1]["n"], 2);
+        assert_eq!(doc["orders"][1]["lines"][0]["n"], 3);
+    }
+
+    #[test]
+    fn array_filter_no_match_leaves_array_unchanged() {
+        let mut doc = order();
+        apply_update(
+            &mut doc,
+            &json!({"$set": {"items.$[i].done": true}, "arrayFilters": [{"i.id": 99}]}),
+        )
+        .unwrap();
+        assert_eq!(doc, order());
+    }
+
+    #[test]
+    fn array_filter_errors() {
+        let mut doc = order();
+        // Identifier without a filter
+        let err = apply_update(&mut doc, &json!({"$set": {"items.$[x].done": true}})).unwrap_err();
+        assert!(err.to_string().contains("identifier 'x'"), "{err}");
+        // Filter never used
+        let err = apply_update(&mut doc, &json!({"$set": {"items.$[].done": true}, "arrayFilters": [{"i.id": 1}]}))
+            .unwrap_err();
+        assert!(err.to_string().contains("not used"), "{err}");
+        // Mixed identifiers in one filter
+        assert!(apply_update(
+            &mut doc,
+            &json!({"$set": {"items.$[i].done": true}, "arrayFilters": [{"i.id": 1, "j.id": 2}]})
+        )
+        .is_err());
+        // Not an array
+        assert!(apply_update(&mut doc, &json!({"$set": {"missing.$[].done": true}})).is_err());
+        assert!(apply_update(&mut doc, &json!({"$rename": {"items.$[].qty": "count"}})).is_err());
+    }
+
     // -----------------------------------------------------------------------
     // $currentDate
     // -----------------------------------------------------------------------
@@ -501,6 +909,29 @@ mod tests {
         assert!(chrono::DateTime::parse_from_rfc3339(val).is_ok());
     }
 
+    #[test]
+    fn current_date_type_spec() {
+        let before = chrono::Utc::now().timestamp_millis();
+        let mut doc = json!({});
+        apply_update(
+            &mut doc,
+            &json!({"$currentDate": {"a": {"$type": "date"}, "b": {"$type": "timestamp"}}}),
+        )
+        .unwrap();
+        let a = chrono::DateTime::parse_from_rfc3339(doc["a"].as_str().unwrap()).unwrap();
+        let b = doc["b"].as_i64().unwrap();
+        assert!(b >= before);
+        assert_eq!(a.timestamp_millis(), b);
+    }
+
+    #[test]
+    fn current_date_rejects_bad_spec() {
+        let mut doc = json!({});
+        assert!(apply_update(&mut doc, &json!({"$currentDate": {"a": false}})).is_err());
+        assert!(apply_update(&mut doc, &json!({"$currentDate": {"a": {"$type": "week"}}})).is_err());
+        assert!(doc.get("a").is_none());
+    }
+
     // -----------------------------------------------------------------------
     // $push
     // -----------------------------------------------------------------------
@@ -39,6 +39,13 @@ impl DistanceMetric {
         }
     }
 
+    /// Parse a metric name as accepted by `create_vector_index`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        Self::from_str(s).ok_or_else(|| {
+            format!("unknown metric '{s}'; expected one of cosine, euclidean, dot_product")
+        })
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             DistanceMetric::Cosine => "cosine",
@@ -73,6 +80,75 @@ pub struct VectorSearchResult {
     pub similarity: f32,
 }
 
+/// Options for `vector_search`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VectorSearchOptions {
+    /// HNSW search beam width; `None` uses the default of 50.
+    pub ef_search: Option<usize>,
+    /// Compare the query against every vector instead of walking the graph.
+    pub exact: bool,
+    /// Leave out results less similar than this.
+    pub min_similarity: Option<f32>,
+}
+
+/// Default HNSW `M`.
+pub const DEFAULT_M: usize = 16;
+/// Default HNSW `ef_construction`.
+pub const DEFAULT_EF_CONSTRUCTION: usize = 200;
+/// Accepted range for `M`.
+pub const M_RANGE: std::ops::RangeInclusive<usize> = 2..=128;
+/// Largest accepted `ef_construction`; the smallest is `M`.
+pub const MAX_EF_CONSTRUCTION: usize = 4096;
+
+/// HNSW build parameters of a vector index. Larger values give a better
+/// connected graph, and so better recall, at the cost of slower inserts
+/// and more memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HnswParams {
+    /// Max connections per node per layer (twice as many at layer 0).
+    pub m: usize,
+    /// Search width while inserting a node.
+    pub ef_construction: usize,
+}
+
+impl Default for HnswParams {
+    fn default() -> Self {
+        Self { m: DEFAULT_M, ef_construction: DEFAULT_EF_CONSTRUCTION }
+    }
+}
+
+impl HnswParams {
+    /// Parameters from optional user input, with defaults for what's left out.
+    pub fn new(m: Option<usize>, ef_construction: Option<usize>) -> Result<Self, String> {
+        let params = Self {
+            m: m.unwrap_or(DEFAULT_M),
+            ef_construction: ef_construction.unwrap_or(DEFAULT_EF_CONSTRUCTION),
+        };
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Check `m` is within `M_RANGE` and `ef_construction` between `m` and
+    /// `MAX_EF_CONSTRUCTION`.
+    pub fn validate(&self) -> Result<(), String> {
+        if !M_RANGE.contains(&self.m) {
+            return Err(format!(
+                "m must be between {} and {}, got {}",
+                M_RANGE.start(),
+                M_RANGE.end(),
+                self.m
+            ));
+        }
+        if !(self.m..=MAX_EF_CONSTRUCTION).contains(&self.ef_construction) {
+            return Err(format!(
+                "ef_construction must be between m ({}) and {}, got {}",
+                self.m, MAX_EF_CONSTRUCTION, self.ef_construction
+            ));
+        }
+        Ok(())
+    }
+}
+
 /// HNSW configuration parameters.
 #[derive(Debug, Clone)]
 struct HnswConfig {
@@ -82,13 +158,13 @@ struct HnswConfig {
     ml: f64,               // Level multiplier: 1/ln(M)
 }
 
-impl Default for HnswConfig {
-    fn default() -> Self {
+impl From<HnswParams> for HnswConfig {
+    fn from(params: HnswParams) -> Self {
         Self {
-            m: 16,
-            m_max0: 32,
-            ef_construction: 200,
-            ml: 1.0 / (16.0_f64).ln(),
+            m: params.m,
+            m_max0: params.m * 2,
+            ef_construction: params.ef_construction,
+            ml: 1.0 / (params.m as f64).ln(),
         }
     }
 }
@@ -374,15 +450,26 @@ impl HnswGraph {
         let results = self.search_layer(query, &[nearest], ef_search.max(k), 0, vectors, metric);
 
         results.into_iter()
-            .filter(|(_, idx)| {
-                // Only return nodes that are still live (have an entry in doc_to_node)
-                *idx < self.nodes.len() && self.doc_to_node.contains_key(&self.nodes[*idx].doc_id)
-            })
+            .filter(|(_, idx)| self.is_live(*idx))
             .take(k)
             .map(|(dist, idx)| (dist, self.nodes[idx].doc_id))
             .collect()
     }
 
+    /// Whether a node is still in the index. A removed node stays in `nodes`
+    /// as a tombstone; if its document was re-inserted, `doc_to_node` points
+    /// at the new node instead.
+    fn is_live(&self, node_idx: usize) -> bool {
+        self.nodes
+            .get(node_idx)
+            .is_some_and(|node| self.doc_to_node.get(&node.doc_id) == Some(&node_idx))
+    }
+
+    /// Removed nodes still taking up a slot in the graph.
+    fn tombstones(&self) -> usize {
+        self.nodes.len() - self.doc_to_node.len()
+    }
+
     /// Check if the graph should be rebuilt (>20% deleted).
     fn needs_rebuild(&self) -> bool {
         let total = self.nodes.len();
@@ -452,6 +539,16 @@ fn compute_distance(a: &[f32], b: &[f32], metric: DistanceMetric) -> f32 {
     }
 }
 
+/// Scale a vector to unit length. A zero vector has no direction and is left
+/// as is; its dot product with anything is 0, the cosine distance 1 that
+/// `compute_distance` gives it too.
+fn normalize(vec: &mut [f32]) {
+    let norm = vec.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vec.iter_mut().for_each(|v| *v /= norm);
+    }
+}
+
 /// Extract a vector (array of numbers) from a JSON value at a field path.
 fn extract_vector(data: &Value, field: &str) -> Option<Vec<f32>> {
     let mut current = data;
@@ -475,11 +572,12 @@ pub struct VectorIndex {
     hnsw: Option<HnswGraph>,
     flat_threshold: usize,
     hnsw_config: HnswConfig,
+    normalized: bool,
 }
 
 impl VectorIndex {
     /// Create a new empty vector index.
-    pub fn new(field: String, dimension: usize, metric: DistanceMetric) -> Self {
+    pub fn new(field: String, dimension: usize, metric: DistanceMetric, params: HnswParams) -> Self {
         Self {
             field,
             dimension,
@@ -487,7 +585,41 @@ impl VectorIndex {
             vectors: HashMap::new(),
             hnsw: None,
             flat_threshold: 1000,
-            hnsw_config: HnswConfig::default(),
+            hnsw_config: params.into(),
+            normalized: false,
+        }
+    }
+
+    /// Store vectors scaled to unit length, and scale queries the same way,
+    /// so cosine distance comes down to a dot product. Only meaningful for
+    /// the cosine metric; `Collection::create_vector_index` enforces that.
+    pub fn with_normalization(mut self, normalized: bool) -> Self {
+        self.normalized = normalized;
+        self
+    }
+
+    /// Whether stored vectors are unit-normalized.
+    pub fn is_normalized(&self) -> bool {
+        self.normalized
+    }
+
+    /// Metric the stored vectors are compared with. Normalized cosine
+    /// vectors use the dot product, whose distance `-dot` is the cosine
+    /// distance less 1; `reported_distance` adds it back.
+    fn compare_metric(&self) -> DistanceMetric {
+        if self.normalized { DistanceMetric::DotProduct } else { self.metric }
+    }
+
+    /// Convert a distance under `compare_metric` to one under `metric`.
+    fn reported_distance(&self, dist: f32) -> f32 {
+        if self.normalized { 1.0 + dist } else { dist }
+    }
+
+    /// The HNSW build parameters the index was created with.
+    pub fn params(&self) -> HnswParams {
+        HnswParams {
+            m: self.hnsw_config.m,
+            ef_construction: self.hnsw_config.ef_construction,
         }
     }
 
@@ -513,7 +645,7 @@ impl VectorIndex {
 
     /// Insert a document's vector into the index.
     pub fn insert(&mut self, doc_id: DocumentId, data: &Value) -> Result<(), String> {
-        let vec = match extract_vector(data, &self.field) {
+        let mut vec = match extract_vector(data, &self.field) {
             Some(v) => v,
             None => return Ok(()), // Field not present or not an array — skip
         };
@@ -526,14 +658,18 @@ impl VectorIndex {
             ));
         }
 
+        if self.normalized {
+            normalize(&mut vec);
+        }
         self.vectors.insert(doc_id, vec);
 
         // Build/update HNSW if above threshold
         if self.vectors.len() >= self.flat_threshold {
+            let metric = self.compare_metric();
             if self.hnsw.is_none() {
                 self.rebuild_hnsw();
             } else if let Some(ref mut hnsw) = self.hnsw {
-                hnsw.insert(doc_id, &self.vectors, self.metric);
+                hnsw.insert(doc_id, &self.vectors, metric);
             }
         }
 
@@ -556,7 +692,24 @@ impl VectorIndex {
         }
     }
 
-    /// Clear the entire index (used during compaction).
+    /// Removed entries the HNSW graph still holds as tombstones. They're
+    /// never returned by a search, but they take memory and, in numbers,
+    /// leave the graph poorly connected until it's rebuilt.
+    pub fn tombstones(&self) -> usize {
+        self.hnsw.as_ref().map_or(0, HnswGraph::tombstones)
+    }
+
+    /// Rebuild the HNSW graph from the live vectors, dropping tombstones.
+    /// Returns the number of tombstones removed.
+    pub fn compact(&mut self) -> usize {
+        let tombstones = self.tombstones();
+        if tombstones > 0 {
+            self.rebuild_hnsw();
+        }
+        tombstones
+    }
+
+    /// Clear the entire index.
     pub fn clear(&mut self) {
         self.vectors.clear();
         self.hnsw = None;
@@ -564,17 +717,12 @@ impl VectorIndex {
 
     /// Search for the k nearest neighbors to the query vector.
     pub fn search(&self, query: &[f32], k: usize, ef_search: Option<usize>) -> Result<Vec<VectorSearchResult>, String> {
-        if query.len() != self.dimension {
-            return Err(format!(
-                "query vector dimension mismatch: expected {}, got {}",
-                self.dimension,
-                query.len()
-            ));
-        }
+        self.check_query_dimension(query)?;
 
         if self.vectors.is_empty() {
             return Ok(Vec::new());
         }
+        let query = &self.prepare_query(query);
 
         let results = if self.vectors.len() < self.flat_threshold || self.hnsw.is_none() {
             self.flat_search(query, k)
@@ -586,12 +734,54 @@ impl VectorIndex {
         Ok(results)
     }
 
+    /// Exact k nearest neighbors, comparing the query against every vector
+    /// even when an HNSW graph exists. Slower than `search` on large indexes
+    /// but never misses a neighbor, so it doubles as the baseline for recall.
+    pub fn search_exact(&self, query: &[f32], k: usize) -> Result<Vec<VectorSearchResult>, String> {
+        self.check_query_dimension(query)?;
+        Ok(self.flat_search(&self.prepare_query(query), k))
+    }
+
+    /// The query as the stored vectors are kept: normalized if they are.
+    fn prepare_query(&self, query: &[f32]) -> Vec<f32> {
+        let mut query = query.to_vec();
+        if self.normalized {
+            normalize(&mut query);
+        }
+        query
+    }
+
+    /// `search` or `search_exact` as the options say, dropping results
+    /// below `min_similarity`.
+    pub fn search_with(&self, query: &[f32], k: usize, opts: &VectorSearchOptions) -> Result<Vec<VectorSearchResult>, String> {
+        let mut results = if opts.exact {
+            self.search_exact(query, k)?
+        } else {
+            self.search(query, k, opts.ef_search)?
+        };
+        if let Some(min) = opts.min_similarity {
+            results.retain(|r| r.similarity >= min);
+        }
+        Ok(results)
+    }
+
+    fn check_query_dimension(&self, query: &[f32]) -> Result<(), String> {
+        if query.len() != self.dimension {
+            return Err(format!(
+                "query vector dimension mismatch: expected {}, got {}",
+                self.dimension,
+                query.len()
+            ));
+        }
+        Ok(())
+    }
+
     /// Brute-force exact KNN search.
     fn flat_search(&self, query: &[f32], k: usize) -> Vec<VectorSearchResult> {
         let mut heap: BinaryHeap<OrdF32Id> = BinaryHeap::new();
 
         for (&doc_id, vec) in &self.vectors {
-            let dist = compute_distance(query, vec, self.metric);
+            let dist = compute_distance(query, vec, self.compare_metric());
             if heap.len() < k {
                 heap.push(OrdF32Id(dist, doc_id));
             } else if heap.peek().is_some_and(|top| dist < top.0) {
@@ -602,11 +792,7 @@ impl VectorIndex {
 
         let mut results: Vec<VectorSearchResult> = heap.into_sorted_vec()
             .into_iter()
-            .map(|OrdF32Id(dist, doc_id)| VectorSearchResult {
-                doc_id,
-                distance: dist,
-                similarity: self.metric.to_similarity(dist),
-            })
+            .map(|OrdF32Id(dist, doc_id)| self.result(doc_id, dist))
             .collect();
         results.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap_or(std::cmp::Ordering::Equal));
         results
@@ -619,22 +805,27 @@ impl VectorIndex {
             None => return self.flat_search(query, k),
         };
 
-        let results = hnsw.search(query, k, ef_search, &self.vectors, self.metric);
+        let results = hnsw.search(query, k, ef_search, &self.vectors, self.compare_metric());
         results.into_iter()
-            .map(|(dist, doc_id)| VectorSearchResult {
-                doc_id,
-                distance: dist,
-                similarity: self.metric.to_similarity(dist),
-            })
+            .map(|(dist, doc_id)| self.result(doc_id, dist))
             .collect()
     }
 
+    fn result(&self, doc_id: DocumentId, dist: f32) -> VectorSearchResult {
+        let distance = self.reported_distance(dist);
+        VectorSearchResult {
+            doc_id,
+            distance,
+            similarity: self.metric.to_similarity(distance),
+        }
+    }
+
     /// Rebuild the HNSW graph from scratch.
     fn rebuild_hnsw(&mut self) {
         let mut graph = HnswGraph::new(self.hnsw_config.clone());
         let doc_ids: Vec<DocumentId> = self.vectors.keys().copied().collect();
         for doc_id in doc_ids {
-            graph.insert(doc_id, &self.vectors, self.metric);
+            graph.insert(doc_id, &self.vectors, self.compare_metric());
         }
         self.hnsw = Some(graph);
     }
@@ -656,6 +847,13 @@ impl VectorIndex {
         // Metric
         w.write_all(&[self.metric.to_u8()])?;
 
+        // HNSW parameters
+        w.write_all(&(self.hnsw_config.m as u32).to_le_bytes())?;
+        w.write_all(&(self.hnsw_config.ef_construction as u32).to_le_bytes())?;
+
+        // Normalization flag
+        w.write_all(&[self.normalized as u8])?;
+
         // Vector count
         w.write_all(&(self.vectors.len() as u64).to_le_bytes())?;
 
@@ -691,6 +889,25 @@ impl VectorIndex {
         let metric = DistanceMetric::from_u8(metric_buf[0])
             .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid metric"))?;
 
+        // HNSW parameters
+        r.read_exact(&mut len_buf)?;
+        let m = u32::from_le_bytes(len_buf) as usize;
+        r.read_exact(&mut len_buf)?;
+        let ef_construction = u32::from_le_bytes(len_buf) as usize;
+        let params = HnswParams { m, ef_construction };
+        params
+            .validate()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        // Normalization flag
+        let mut flag_buf = [0u8; 1];
+        r.read_exact(&mut flag_buf)?;
+        let normalized = match flag_buf[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid normalization flag")),
+        };
+
         // Vector count
         let mut count_buf = [0u8; 8];
         r.read_exact(&mut count_buf)?;
@@ -712,7 +929,7 @@ impl VectorIndex {
         }
 
         let flat_threshold = 1000;
-        let hnsw_config = HnswConfig::default();
+        let hnsw_config = params.into();
 
         let mut idx = Self {
             field,
@@ -722,6 +939,7 @@ impl VectorIndex {
             hnsw: None,
             flat_threshold,
             hnsw_config,
+            normalized,
         };
 
         // Rebuild HNSW if above threshold
@@ -806,7 +1024,7 @@ mod tests {
 
     #[test]
     fn test_flat_search_exact() {
-        let mut idx = VectorIndex::new("embedding".to_string(), 3, DistanceMetric::Cosine);
+        let mut idx = VectorIndex::new("embedding".to_string(), 3, DistanceMetric::Cosine, HnswParams::default());
 
         for i in 0..10u64 {
             let v = vec![i as f32, 0.0, 0.0];
@@ -826,7 +1044,7 @@ mod tests {
 
     #[test]
     fn test_dimension_mismatch() {
-        let mut idx = VectorIndex::new("vec".to_string(), 3, DistanceMetric::Cosine);
+        let mut idx = VectorIndex::new("vec".to_string(), 3, DistanceMetric::Cosine, HnswParams::default());
         let doc = json!({"vec": [1.0, 2.0]});
         let result = idx.insert(1, &doc);
         assert!(result.is_err());
@@ -834,7 +1052,7 @@ mod tests {
 
     #[test]
     fn test_insert_remove_lifecycle() {
-        let mut idx = VectorIndex::new("vec".to_string(), 2, DistanceMetric::Euclidean);
+        let mut idx = VectorIndex::new("vec".to_string(), 2, DistanceMetric::Euclidean, HnswParams::default());
 
         let doc1 = json!({"vec": [1.0, 0.0]});
         let doc2 = json!({"vec": [0.0, 1.0]});
@@ -852,7 +1070,8 @@ mod tests {
 
     #[test]
     fn test_binary_roundtrip() {
-        let mut idx = VectorIndex::new("embedding".to_string(), 3, DistanceMetric::Euclidean);
+        let params = HnswParams { m: 8, ef_construction: 64 };
+        let mut idx = VectorIndex::new("embedding".to_string(), 3, DistanceMetric::Euclidean, params);
         for i in 0..50u64 {
             let doc = json!({"embedding": [i as f64 * 0.1, i as f64 * 0.2, i as f64 * 0.3]});
             idx.insert(i, &doc).unwrap();
@@ -867,6 +1086,7 @@ mod tests {
         assert_eq!(idx2.field, "embedding");
         assert_eq!(idx2.dimension, 3);
         assert_eq!(idx2.metric, DistanceMetric::Euclidean);
+        assert_eq!(idx2.params(), params);
         assert_eq!(idx2.len(), 50);
 
         // Search should return same results
@@ -882,7 +1102,7 @@ mod tests {
 
     #[test]
     fn test_missing_field_skipped() {
-        let mut idx = VectorIndex::new("embedding".to_string(), 3, DistanceMetric::Cosine);
+        let mut idx = VectorIndex::new("embedding".to_string(), 3, DistanceMetric::Cosine, HnswParams::default());
         let doc = json!({"name": "Alice"});
         assert!(idx.insert(1, &doc).is_ok());
         assert_eq!(idx.len(), 0);
@@ -893,7 +1113,7 @@ mod tests {
         // Build index with enough vectors to trigger HNSW
         let dim = 8;
         let n = 1500; // above flat_threshold of 1000
-        let mut idx = VectorIndex::new("vec".to_string(), dim, DistanceMetric::Euclidean);
+        let mut idx = VectorIndex::new("vec".to_string(), dim, DistanceMetric::Euclidean, HnswParams::default());
 
         for i in 0..n as u64 {
             let v: Vec<f64> = (0..dim).map(|d| i as f64 + d as f64 * 0.01).collect();
@@ -935,5 +1155,214 @@ mod tests {
         assert_eq!(DistanceMetric::from_str("dotproduct"), Some(DistanceMetric::DotProduct));
         assert_eq!(DistanceMetric::from_str("dot_product"), Some(DistanceMetric::DotProduct));
         assert_eq!(DistanceMetric::from_str("invalid"), None);
+
+        assert_eq!(DistanceMetric::parse("dot_product"), Ok(DistanceMetric::DotProduct));
+        let err = DistanceMetric::parse("manhattan").unwrap_err();
+        assert!(err.contains("'manhattan'") && err.contains("cosine, euclidean, dot_product"), "{err}");
+    }
+
+    #[test]
+    fn test_hnsw_params_validation() {
+        assert_eq!(HnswParams::new(None, None).unwrap(), HnswParams::default());
+        assert_eq!(HnswParams::new(Some(32), None).unwrap(), HnswParams { m: 32, ef_construction: 200 });
+        assert_eq!(HnswParams::new(Some(8), Some(8)).unwrap(), HnswParams { m: 8, ef_construction: 8 });
+        assert!(HnswParams::new(Some(1), None).is_err());
+        assert!(HnswParams::new(Some(129), None).is_err());
+        assert!(HnswParams::new(Some(16), Some(15)).is_err());
+        assert!(HnswParams::new(None, Some(MAX_EF_CONSTRUCTION + 1)).is_err());
+    }
+
+    #[test]
+    fn test_hnsw_recall_with_different_ef_construction() {
+        // Deterministic pseudo-random vectors
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % 10_000) as f64 / 10_000.0
+        };
+        let dim = 8;
+        let docs: Vec<Value> = (0..400).map(|_| json!({"vec": (0..dim).map(|_| next()).collect::<Vec<_>>()})).collect();
+        let queries: Vec<Vec<f32>> = (0..20).map(|_| (0..dim).map(|_| next() as f32).collect()).collect();
+
+        for ef_construction in [16, 400] {
+            let params = HnswParams::new(Some(8), Some(ef_construction)).unwrap();
+            let mut idx = VectorIndex::new("vec".to_string(), dim, DistanceMetric::Euclidean, params);
+            idx.flat_threshold = 100;
+            for (i, doc) in docs.iter().enumerate() {
+                idx.insert(i as u64, doc).unwrap();
+            }
+            assert!(idx.hnsw.is_some(), "HNSW should be built above threshold");
+            assert_eq!(idx.hnsw.as_ref().unwrap().config.ef_construction, ef_construction);
+
+            let k = 10;
+            let mut found = 0;
+            for query in &queries {
+                let exact: Vec<u64> = idx.search_exact(query, k).unwrap().iter().map(|r| r.doc_id).collect();
+                let approx = idx.search(query, k, Some(64)).unwrap();
+                assert_eq!(approx[0].doc_id, exact[0], "nearest neighbor (ef_construction={ef_construction})");
+                found += approx.iter().filter(|r| exact.contains(&r.doc_id)).count();
+            }
+            let recall = found as f64 / (k * queries.len()) as f64;
+            assert!(recall >= 0.9, "recall {recall} with ef_construction={ef_construction}");
+        }
+    }
+
+    #[test]
+    fn test_exact_search_returns_true_nearest() {
+        let mut idx = VectorIndex::new("vec".to_string(), 2, DistanceMetric::Euclidean, HnswParams::default());
+        idx.flat_threshold = 10;
+        // A 10x10 grid; HNSW is built over it
+        for i in 0..100u64 {
+            idx.insert(i, &json!({"vec": [(i % 10) as f64, (i / 10) as f64]})).unwrap();
+        }
+        assert!(idx.hnsw.is_some());
+
+        let results = idx.search_exact(&[3.2, 4.3], 5).unwrap();
+        let ids: Vec<u64> = results.iter().map(|r| r.doc_id).collect();
+        // (3,4), (3,5), (4,4), (4,5), (2,4)
+        assert_eq!(ids, [43, 53, 44, 54, 42]);
+        assert!((results[0].distance - (0.2f32 * 0.2 + 0.3 * 0.3).sqrt()).abs() < 1e-5);
+        for w in results.windows(2) {
+            assert!(w[0].distance <= w[1].distance);
+        }
+
+        assert_eq!(idx.search_exact(&[0.0, 0.0], 500).unwrap().len(), 100);
+        assert!(idx.search_exact(&[0.0], 1).is_err());
+    }
+
+    #[test]
+    fn test_approximate_recall_against_exact() {
+        let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % 10_000) as f32 / 10_000.0 - 0.5
+        };
+        let dim = 16;
+        let mut idx = VectorIndex::new("vec".to_string(), dim, DistanceMetric::Cosine, HnswParams::default());
+        for i in 0..1200u64 {
+            let v: Vec<f32> = (0..dim).map(|_| next()).collect();
+            idx.insert(i, &json!({"vec": v})).unwrap();
+        }
+        assert!(idx.hnsw.is_some(), "HNSW should be built above threshold");
+
+        let k = 10;
+        let queries: Vec<Vec<f32>> = (0..25).map(|_| (0..dim).map(|_| next()).collect()).collect();
+        let mut found = 0;
+        for query in &queries {
+            let exact: Vec<u64> = idx.search_exact(query, k).unwrap().iter().map(|r| r.doc_id).collect();
+            found += idx.search(query, k, None).unwrap().iter().filter(|r| exact.contains(&r.doc_id)).count();
+        }
+        let recall = found as f64 / (k * queries.len()) as f64;
+        assert!(recall >= 0.8, "recall {recall}");
+    }
+
+    #[test]
+    fn test_tombstones_compact_away() {
+        let mut seed = 0xd1b5_4a32_d192_ed03u64;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % 10_000) as f32 / 10_000.0
+        };
+        let dim = 8;
+        let params = HnswParams { m: 8, ef_construction: 64 };
+        let mut idx = VectorIndex::new("vec".to_string(), dim, DistanceMetric::Euclidean, params);
+        for i in 0..1500u64 {
+            let v: Vec<f32> = (0..dim).map(|_| next()).collect();
+            idx.insert(i, &json!({"vec": v})).unwrap();
+        }
+        // Under the 20% that triggers an automatic rebuild
+        let removed: Vec<u64> = (0..1500).step_by(6).collect();
+        for &id in &removed {
+            idx.remove(id);
+        }
+        assert_eq!(idx.tombstones(), removed.len());
+        assert_eq!(idx.len(), 1500 - removed.len());
+
+        let queries: Vec<Vec<f32>> = (0..25).map(|_| (0..dim).map(|_| next()).collect()).collect();
+        for query in &queries {
+            let results = idx.search(query, 20, None).unwrap();
+            assert!(results.iter().all(|r| !removed.contains(&r.doc_id)), "search returned a removed id");
+        }
+
+        assert_eq!(idx.compact(), removed.len());
+        assert_eq!(idx.tombstones(), 0);
+        assert_eq!(idx.hnsw.as_ref().unwrap().nodes.len(), idx.len());
+        assert_eq!(idx.compact(), 0);
+
+        let k = 10;
+        let mut found = 0;
+        for query in &queries {
+            let exact: Vec<u64> = idx.search_exact(query, k).unwrap().iter().map(|r| r.doc_id).collect();
+            let approx = idx.search(query, k, None).unwrap();
+            assert!(approx.iter().all(|r| !removed.contains(&r.doc_id)));
+            found += approx.iter().filter(|r| exact.contains(&r.doc_id)).count();
+        }
+        let recall = found as f64 / (k * queries.len()) as f64;
+        assert!(recall >= 0.9, "recall {recall}");
+    }
+
+    #[test]
+    fn test_reinserted_doc_is_returned_once() {
+        let mut idx = VectorIndex::new("vec".to_string(), 2, DistanceMetric::Euclidean, HnswParams::default());
+        idx.flat_threshold = 10;
+        for i in 0..50u64 {
+            idx.insert(i, &json!({"vec": [i as f64, 0.0]})).unwrap();
+        }
+        // An update: the old node becomes a tombstone for the same doc id
+        idx.remove(7);
+        idx.insert(7, &json!({"vec": [7.0, 0.5]})).unwrap();
+        assert_eq!(idx.tombstones(), 1);
+
+        let results = idx.search(&[7.0, 0.4], 5, None).unwrap();
+        assert_eq!(results[0].doc_id, 7);
+        assert_eq!(results.iter().filter(|r| r.doc_id == 7).count(), 1);
+        assert_eq!(idx.compact(), 1);
+        assert_eq!(idx.search(&[7.0, 0.4], 1, None).unwrap()[0].doc_id, 7);
+    }
+
+    #[test]
+    fn test_normalized_cosine_matches_unnormalized() {
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % 10_000) as f32 / 1_000.0 - 5.0
+        };
+        let dim = 8;
+        let mut plain = VectorIndex::new("vec".to_string(), dim, DistanceMetric::Cosine, HnswParams::default());
+        let mut normalized = VectorIndex::new("vec".to_string(), dim, DistanceMetric::Cosine, HnswParams::default())
+            .with_normalization(true);
+        for i in 0..300u64 {
+            let v: Vec<f32> = (0..dim).map(|_| next()).collect();
+            plain.insert(i, &json!({"vec": v})).unwrap();
+            normalized.insert(i, &json!({"vec": v})).unwrap();
+        }
+        assert!(normalized.vectors.values().all(|v| (v.iter().map(|x| x * x).sum::<f32>() - 1.0).abs() < 1e-4));
+
+        for _ in 0..10 {
+            let query: Vec<f32> = (0..dim).map(|_| next()).collect();
+            let a = plain.search(&query, 10, None).unwrap();
+            let b = normalized.search(&query, 10, None).unwrap();
+            assert_eq!(
+                a.iter().map(|r| r.doc_id).collect::<Vec<_>>(),
+                b.iter().map(|r| r.doc_id).collect::<Vec<_>>()
+            );
+            for (a, b) in a.iter().zip(&b) {
+                assert!((a.distance - b.distance).abs() < 1e-4);
+                assert!((a.similarity - b.similarity).abs() < 1e-4);
+            }
+        }
+
+        let mut buf = Vec::new();
+        normalized.write_to(&mut buf).unwrap();
+        let restored = VectorIndex::read_from(&mut io::Cursor::new(&buf)).unwrap();
+        assert!(restored.is_normalized());
     }
 }
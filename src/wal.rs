@@ -2,22 +2,33 @@ use std::collections::{HashMap, HashSet};
 use std::fs::{self, File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
 
 use crc32fast::Hasher;
 
 use crate::crypto::EncryptionKey;
+use crate::doc_cache::DocCache;
 use crate::document::DocumentId;
 use crate::engine::LogCallback;
 use crate::error::Result;
 use crate::index::{CompositeIndex, FieldIndex};
-use crate::storage::{DocLocation, Storage};
+use crate::storage::{DocLocation, Storage, SyncMode};
 
 const OP_INSERT: u8 = 1;
 const OP_UPDATE: u8 = 2;
 const OP_DELETE: u8 = 3;
 
+/// Suffix of the file that retains checkpointed WAL entries for
+/// point-in-time recovery: `<collection>.wal.history`.
+pub const HISTORY_SUFFIX: &str = ".wal.history";
+
+/// Default batching window of `GroupCommit`.
+pub const DEFAULT_GROUP_COMMIT_WINDOW: Duration = Duration::from_micros(500);
+
 /// A WAL entry representing a pending mutation.
+#[derive(Clone)]
 pub enum WalEntry {
     Insert { doc_id: DocumentId, doc_bytes: Vec<u8>, tx_id: u64 },
     Update { doc_id: DocumentId, doc_bytes: Vec<u8>, tx_id: u64 },
@@ -49,6 +60,132 @@ impl WalEntry {
     }
 }
 
+/// Group commit for single-document writes: each writer appends without
+/// fsync while holding the collection lock, registers the write, then waits
+/// here after releasing the lock. One waiter at a time leads: it fsyncs the
+/// data file once for every write registered so far, and the others return
+/// when a sync covering their write completes. When other writers are in
+/// flight the leader first waits `window` so that they can join its sync.
+pub struct GroupCommit {
+    state: Mutex<GroupState>,
+    done: Condvar,
+    window_us: AtomicU64,
+}
+
+struct GroupState {
+    /// Writes registered so far; a write's ticket is its position.
+    written: u64,
+    /// Writes known to be on disk.
+    synced: u64,
+    /// Data file the latest write went to.
+    file: Option<Arc<File>>,
+    leading: bool,
+    waiting: usize,
+    /// Writes up to this one were covered by an fsync that failed.
+    failed: Option<(u64, String)>,
+    syncs: u64,
+}
+
+/// A registered write, to pass to `GroupCommit::wait`.
+#[derive(Debug, Clone, Copy)]
+pub struct CommitTicket(u64);
+
+impl GroupCommit {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            state: Mutex::new(GroupState {
+                written: 0,
+                synced: 0,
+                file: None,
+                leading: false,
+                waiting: 0,
+                failed: None,
+                syncs: 0,
+            }),
+            done: Condvar::new(),
+            window_us: AtomicU64::new(window.as_micros() as u64),
+        }
+    }
+
+    /// Change the batching window; zero syncs right away, so only writes
+    /// that arrive during an fsync share the next one.
+    pub fn set_window(&self, window: Duration) {
+        self.window_us.store(window.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Record a write just appended to `file` without fsync. Writes must
+    /// register in the order they were appended.
+    pub fn register(&self, file: &Arc<File>) -> CommitTicket {
+        let mut state = self.state.lock().unwrap();
+        state.written += 1;
+        state.file = Some(Arc::clone(file));
+        CommitTicket(state.written)
+    }
+
+    /// Block until the write behind `ticket` is on disk, leading the fsync
+    /// if no other waiter is. Fails if the fsync covering it failed.
+    pub fn wait(&self, ticket: CommitTicket) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.synced >= ticket.0 {
+                return Ok(());
+            }
+            if let Some((_, msg)) = state.failed.as_ref().filter(|(upto, _)| ticket.0 <= *upto) {
+                return Err(std::io::Error::other(msg.clone()).into());
+            }
+            if !state.leading {
+                break;
+            }
+            state.waiting += 1;
+            state = self.done.wait(state).unwrap();
+            state.waiting -= 1;
+        }
+
+        state.leading = true;
+        let window = Duration::from_micros(self.window_us.load(Ordering::Relaxed));
+        if !window.is_zero() && (state.waiting > 0 || state.written > ticket.0) {
+            drop(state);
+            std::thread::sleep(window);
+            state = self.state.lock().unwrap();
+        }
+        let target = state.written;
+        let file = state.file.clone();
+        drop(state);
+
+        let result = match file {
+            Some(file) => file.sync_data(),
+            None => Ok(()),
+        };
+
+        let mut state = self.state.lock().unwrap();
+        state.leading = false;
+        state.syncs += 1;
+        match &result {
+            Ok(()) => state.synced = state.synced.max(target),
+            Err(e) => state.failed = Some((target, e.to_string())),
+        }
+        drop(state);
+        self.done.notify_all();
+        result.map_err(Into::into)
+    }
+
+    /// Number of fsyncs issued so far.
+    pub fn syncs(&self) -> u64 {
+        self.state.lock().unwrap().syncs
+    }
+}
+
+/// The in-memory state `Wal::recover` brings up to date as it replays: the
+/// primary index and document cache, plus the version, field and composite
+/// indexes, so that a cached index load stays consistent.
+pub struct RecoveryTargets<'a> {
+    pub primary_index: &'a mut HashMap<DocumentId, DocLocation>,
+    pub doc_cache: &'a mut DocCache,
+    pub version_index: &'a mut HashMap<DocumentId, u64>,
+    pub field_indexes: &'a mut HashMap<String, FieldIndex>,
+    pub composite_indexes: &'a mut [CompositeIndex],
+}
+
 /// Write-ahead log for crash-safe mutations.
 ///
 /// Thread-safe: all file operations are serialized via an internal Mutex.
@@ -56,6 +193,9 @@ pub struct Wal {
     inner: Mutex<File>,
     path: PathBuf,
     encryption: Option<Arc<EncryptionKey>>,
+    /// Where checkpointed entries are retained, if WAL retention is on.
+    history: Option<Mutex<File>>,
+    sync_mode: SyncMode,
 }
 
 impl Wal {
@@ -80,9 +220,53 @@ impl Wal {
             inner: Mutex::new(file),
             path: path.to_path_buf(),
             encryption,
+            history: None,
+            sync_mode: SyncMode::default(),
         })
     }
 
+    /// Retain entries in the history file at `path` when the WAL is
+    /// checkpointed instead of discarding them. `None` stops retaining.
+    ///
+    /// History format: sequence of
+    /// `[checkpointed_at_ms: i64 LE][crc32: u32 LE][len: u32 LE][payload]`,
+    /// where the payload is the WAL entry payload as written to the WAL.
+    pub fn set_history(&mut self, path: Option<&Path>) -> Result<()> {
+        self.history = match path {
+            Some(path) => Some(Mutex::new(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            )),
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// With `SyncMode::None`, the methods that normally fsync don't.
+    pub fn set_sync_mode(&mut self, mode: SyncMode) {
+        self.sync_mode = mode;
+    }
+
+    fn sync(&self, file: &File) -> Result<()> {
+        if self.sync_mode != SyncMode::None {
+            file.sync_data()?;
+        }
+        Ok(())
+    }
+
+    pub fn has_history(&self) -> bool {
+        self.history.is_some()
+    }
+
+    /// Append entries directly to the history file, fsynced. Used for the
+    /// base snapshot taken when retention is enabled.
+    pub fn append_history(&self, entries: &[WalEntry]) -> Result<()> {
+        let payloads = entries
+            .iter()
+            .map(|entry| self.serialize_entry(entry))
+            .collect::<Result<Vec<_>>>()?;
+        self.write_history(&payloads, true)
+    }
+
     /// Serialize and append a WAL entry, then fsync.
     pub fn log(&self, entry: &WalEntry) -> Result<()> {
         let payload = self.serialize_entry(entry)?;
@@ -93,7 +277,7 @@ impl Wal {
         file.write_all(&crc.to_le_bytes())?;
         file.write_all(&(payload.len() as u32).to_le_bytes())?;
         file.write_all(&payload)?;
-        file.sync_data()?;
+        self.sync(&file)?;
 
         Ok(())
     }
@@ -123,7 +307,7 @@ impl Wal {
             file.write_all(&(payload.len() as u32).to_le_bytes())?;
             file.write_all(&payload)?;
         }
-        file.sync_data()?;
+        self.sync(&file)?;
         Ok(())
     }
 
@@ -143,35 +327,65 @@ impl Wal {
 
     /// Truncate the WAL to 0 (checkpoint), then fsync.
     pub fn checkpoint(&self) -> Result<()> {
-        let file = self.inner.lock().unwrap();
+        let mut file = self.inner.lock().unwrap();
+        self.retain(&mut file, true)?;
         file.set_len(0)?;
-        file.sync_data()?;
+        self.sync(&file)?;
         Ok(())
     }
 
     /// Truncate the WAL to 0 without fsync.
     pub fn checkpoint_no_sync(&self) -> Result<()> {
-        let file = self.inner.lock().unwrap();
+        let mut file = self.inner.lock().unwrap();
+        self.retain(&mut file, false)?;
         file.set_len(0)?;
         Ok(())
     }
 
-    /// Read all valid entries from the WAL and replay them idempotently.
-    /// When field_indexes and composite_indexes are provided, WAL replay also
-    /// updates those indexes so that a cached index load remains consistent.
+    /// Copy the entries about to be checkpointed into the history file.
+    fn retain(&self, file: &mut File, sync: bool) -> Result<()> {
+        if self.history.is_none() {
+            return Ok(());
+        }
+        let payloads = Self::read_payloads(file)?;
+        self.write_history(&payloads, sync)
+    }
+
+    fn write_history(&self, payloads: &[Vec<u8>], sync: bool) -> Result<()> {
+        let Some(history) = &self.history else {
+            return Ok(());
+        };
+        if payloads.is_empty() {
+            return Ok(());
+        }
+        let at_ms = chrono::Utc::now().timestamp_millis();
+        let mut buf = Vec::new();
+        for payload in payloads {
+            buf.extend_from_slice(&at_ms.to_le_bytes());
+            buf.extend_from_slice(&Self::compute_crc(payload).to_le_bytes());
+            buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            buf.extend_from_slice(payload);
+        }
+        let mut file = history.lock().unwrap();
+        file.write_all(&buf)?;
+        if sync {
+            self.sync(&file)?;
+        }
+        Ok(())
+    }
+
+    /// Read all valid entries from the WAL and replay them idempotently into
+    /// `targets`.
     pub fn recover(
         &self,
         storage: &Storage,
-        primary_index: &mut HashMap<DocumentId, DocLocation>,
-        doc_cache: &mut HashMap<DocumentId, Arc<serde_json::Value>>,
+        targets: RecoveryTargets<'_>,
         next_id: &mut DocumentId,
         committed_tx_ids: &HashSet<u64>,
-        version_index: &mut HashMap<DocumentId, u64>,
-        field_indexes: &mut HashMap<String, FieldIndex>,
-        composite_indexes: &mut Vec<CompositeIndex>,
         verbose: bool,
         log_callback: &Option<LogCallback>,
     ) -> Result<()> {
+        let RecoveryTargets { primary_index, doc_cache, version_index, field_indexes, composite_indexes } = targets;
         let vlog = |msg: &str| {
             eprintln!("{msg}");
             if let Some(cb) = log_callback {
@@ -272,7 +486,7 @@ impl Wal {
                         storage.mark_deleted(loc)?;
                         primary_index.remove(&doc_id);
                     }
-                    doc_cache.remove(&doc_id);
+                    doc_cache.remove(doc_id);
                     version_index.remove(&doc_id);
                     deletes += 1;
                 }
@@ -357,9 +571,23 @@ impl Wal {
 
     fn read_entries(&self) -> Result<Vec<WalEntry>> {
         let mut file = self.inner.lock().unwrap();
+        let mut entries = Vec::new();
+        for payload in Self::read_payloads(&mut file)? {
+            // Parse payload
+            match self.parse_payload(&payload) {
+                Some(entry) => entries.push(entry),
+                None => break, // Malformed payload
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Read the payloads of all intact entries, stopping at the first
+    /// truncated or corrupt one.
+    fn read_payloads(file: &mut File) -> Result<Vec<Vec<u8>>> {
         file.seek(SeekFrom::Start(0))?;
         let file_len = file.metadata()?.len();
-        let mut entries = Vec::new();
+        let mut payloads = Vec::new();
         let mut pos = 0u64;
 
         while pos + 8 <= file_len {
@@ -388,17 +616,11 @@ impl Wal {
                 break; // Corrupt entry, stop replay
             }
 
-            // Parse payload
-            if let Some(entry) = self.parse_payload(&payload) {
-                entries.push(entry);
-            } else {
-                break; // Malformed payload
-            }
-
+            payloads.push(payload);
             pos += 8 + payload_len as u64;
         }
 
-        Ok(entries)
+        Ok(payloads)
     }
 
     /// Payload format: [op_type: u8][tx_id: u64 LE][doc_id: u64 LE][encrypted_doc_bytes...]
@@ -428,6 +650,56 @@ impl Wal {
     }
 }
 
+/// Copy the retained entries of a history file that `keep(tx_id,
+/// checkpointed_at_ms)` accepts into a new WAL at `wal_path`, in order.
+///
+/// Entries are written as non-transactional (tx_id 0) so that recovery of
+/// the new WAL applies them unconditionally. Document bytes are copied
+/// as-is, so no encryption key is needed. Returns the number of entries
+/// written.
+pub fn write_history_as_wal(
+    history_path: &Path,
+    wal_path: &Path,
+    keep: impl Fn(u64, i64) -> bool,
+) -> Result<usize> {
+    let history = fs::read(history_path)?;
+    let mut out = Vec::new();
+    let mut written = 0;
+    let mut pos = 0usize;
+
+    while pos + 16 <= history.len() {
+        let at_ms = i64::from_le_bytes(history[pos..pos + 8].try_into().unwrap());
+        let stored_crc = u32::from_le_bytes(history[pos + 8..pos + 12].try_into().unwrap());
+        let payload_len =
+            u32::from_le_bytes(history[pos + 12..pos + 16].try_into().unwrap()) as usize;
+        let start = pos + 16;
+        if start + payload_len > history.len() {
+            break; // Truncated entry, stop
+        }
+        let payload = &history[start..start + payload_len];
+        if Wal::compute_crc(payload) != stored_crc || payload.len() < 17 {
+            break; // Corrupt entry, stop
+        }
+        pos = start + payload_len;
+
+        let tx_id = u64::from_le_bytes(payload[1..9].try_into().unwrap());
+        if !keep(tx_id, at_ms) {
+            continue;
+        }
+        let mut payload = payload.to_vec();
+        payload[1..9].copy_from_slice(&0u64.to_le_bytes());
+        out.extend_from_slice(&Wal::compute_crc(&payload).to_le_bytes());
+        out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        out.extend_from_slice(&payload);
+        written += 1;
+    }
+
+    let mut file = File::create(wal_path)?;
+    file.write_all(&out)?;
+    file.sync_data()?;
+    Ok(written)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -437,6 +709,16 @@ mod tests {
         Wal::open(&dir.path().join("test.wal")).unwrap()
     }
 
+    fn targets<'a>(
+        primary_index: &'a mut HashMap<DocumentId, DocLocation>,
+        doc_cache: &'a mut DocCache,
+        version_index: &'a mut HashMap<DocumentId, u64>,
+        field_indexes: &'a mut HashMap<String, FieldIndex>,
+        composite_indexes: &'a mut [CompositeIndex],
+    ) -> RecoveryTargets<'a> {
+        RecoveryTargets { primary_index, doc_cache, version_index, field_indexes, composite_indexes }
+    }
+
     #[test]
     fn log_and_read_insert() {
         let dir = TempDir::new().unwrap();
@@ -524,6 +806,42 @@ mod tests {
         assert!(wal.read_entries().unwrap().is_empty());
     }
 
+    #[test]
+    fn checkpoint_retains_entries_in_history() {
+        let dir = TempDir::new().unwrap();
+        let history_path = dir.path().join(format!("test{HISTORY_SUFFIX}"));
+        let mut wal = test_wal(&dir);
+        wal.set_history(Some(&history_path)).unwrap();
+
+        wal.log(&WalEntry::insert(1, b"a".to_vec())).unwrap();
+        wal.checkpoint().unwrap();
+        wal.log(&WalEntry::Update { doc_id: 1, doc_bytes: b"b".to_vec(), tx_id: 7 }).unwrap();
+        wal.log(&WalEntry::Delete { doc_id: 1, tx_id: 8 }).unwrap();
+        wal.checkpoint_no_sync().unwrap();
+        assert!(wal.read_entries().unwrap().is_empty());
+
+        // Keep everything except tx 8; kept entries lose their tx_id
+        let out_path = dir.path().join("replay.wal");
+        let written =
+            write_history_as_wal(&history_path, &out_path, |tx_id, _| tx_id != 8).unwrap();
+        assert_eq!(written, 2);
+        let replay = Wal::open(&out_path).unwrap();
+        let entries = replay.read_entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|e| e.tx_id() == 0));
+        match &entries[1] {
+            WalEntry::Update { doc_bytes, .. } => assert_eq!(doc_bytes, b"b"),
+            _ => panic!("expected Update"),
+        }
+
+        // Without history, checkpoints discard entries as before
+        wal.set_history(None).unwrap();
+        wal.log(&WalEntry::insert(2, b"c".to_vec())).unwrap();
+        wal.checkpoint().unwrap();
+        let written = write_history_as_wal(&history_path, &out_path, |_, _| true).unwrap();
+        assert_eq!(written, 3);
+    }
+
     #[test]
     fn crc_corruption_stops_replay() {
         let dir = TempDir::new().unwrap();
@@ -585,8 +903,8 @@ mod tests {
 
         let mut fi = HashMap::new();
         let mut ci = Vec::new();
-        let mut dc = HashMap::new();
-        wal.recover(&storage, &mut primary_index, &mut dc, &mut next_id, &committed, &mut version_index, &mut fi, &mut ci, false, &None)
+        let mut dc = DocCache::full();
+        wal.recover(&storage, targets(&mut primary_index, &mut dc, &mut version_index, &mut fi, &mut ci), &mut next_id, &committed, false, &None)
             .unwrap();
 
         assert_eq!(primary_index.len(), 1);
@@ -614,9 +932,9 @@ mod tests {
         let mut version_index = HashMap::new();
         let mut fi = HashMap::new();
         let mut ci = Vec::new();
-        let mut dc = HashMap::new();
+        let mut dc = DocCache::full();
 
-        wal.recover(&storage, &mut primary_index, &mut dc, &mut next_id, &committed, &mut version_index, &mut fi, &mut ci, false, &None)
+        wal.recover(&storage, targets(&mut primary_index, &mut dc, &mut version_index, &mut fi, &mut ci), &mut next_id, &committed, false, &None)
             .unwrap();
 
         assert!(primary_index.is_empty()); // Should be skipped
@@ -642,9 +960,9 @@ mod tests {
         let mut version_index = HashMap::new();
         let mut fi = HashMap::new();
         let mut ci = Vec::new();
-        let mut dc = HashMap::new();
+        let mut dc = DocCache::full();
 
-        wal.recover(&storage, &mut primary_index, &mut dc, &mut next_id, &committed, &mut version_index, &mut fi, &mut ci, false, &None)
+        wal.recover(&storage, targets(&mut primary_index, &mut dc, &mut version_index, &mut fi, &mut ci), &mut next_id, &committed, false, &None)
             .unwrap();
 
         assert_eq!(primary_index.len(), 1);
@@ -666,13 +984,13 @@ mod tests {
         let mut version_index = HashMap::new();
         let mut fi = HashMap::new();
         let mut ci = Vec::new();
-        let mut dc = HashMap::new();
+        let mut dc = DocCache::full();
 
         // Now log a delete in WAL
         let wal = Wal::open(&wal_path).unwrap();
         wal.log(&WalEntry::delete(0)).unwrap();
 
-        wal.recover(&storage, &mut primary_index, &mut dc, &mut next_id, &committed, &mut version_index, &mut fi, &mut ci, false, &None)
+        wal.recover(&storage, targets(&mut primary_index, &mut dc, &mut version_index, &mut fi, &mut ci), &mut next_id, &committed, false, &None)
             .unwrap();
 
         assert!(primary_index.is_empty());
@@ -729,6 +1047,24 @@ mod tests {
         assert_eq!(entries.len(), 3);
     }
 
+    #[test]
+    fn group_commit_covers_registered_writes() {
+        let dir = TempDir::new().unwrap();
+        let file = Arc::new(File::create(dir.path().join("data")).unwrap());
+        let group = GroupCommit::new(Duration::ZERO);
+
+        // One sync covers every write registered before it started
+        let first = group.register(&file);
+        let second = group.register(&file);
+        group.wait(second).unwrap();
+        group.wait(first).unwrap();
+        assert_eq!(group.syncs(), 1);
+
+        let third = group.register(&file);
+        group.wait(third).unwrap();
+        assert_eq!(group.syncs(), 2);
+    }
+
     #[test]
     fn empty_wal_reads_nothing() {
         let dir = TempDir::new().unwrap();